@@ -0,0 +1,284 @@
+//! Provider and model benchmarking on the staged changes.
+//!
+//! The prompts are built once from the staged context and sent unchanged to
+//! every target, one after another so latencies do not compete for bandwidth.
+//! Token usage comes from the provider's response when it reports one.
+
+pub mod pricing;
+
+use anyhow::{Result, anyhow};
+use clap::Args;
+use cloy::commands::commit::types::{GeneratedMessage, format_commit_message};
+use cloy::commands::common::service::create_commit_service;
+use cloy::common::CommonParams;
+use cloy::config::Config;
+use cloy::error::{ErrorCode, coded};
+use cloy::llm::engine::{self, TokenUsage};
+use cloy::llm::provider::ProviderKind;
+use serde::Serialize;
+use std::fmt::Write;
+use std::time::Instant;
+
+#[derive(Args, Clone, Debug)]
+pub struct BenchParams {
+    #[arg(
+        long = "target",
+        value_name = "PROVIDER[:MODEL]",
+        value_parser = parse_target,
+        help = "Provider and model to benchmark; repeatable (default: every provider with an API key)"
+    )]
+    pub targets: Vec<Target>,
+
+    #[arg(long, help = "Print the results as JSON")]
+    pub json: bool,
+}
+
+/// A provider and, optionally, a model other than the configured one
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Target {
+    pub provider: ProviderKind,
+    pub model: Option<String>,
+}
+
+fn parse_target(value: &str) -> Result<Target, String> {
+    let (provider, model) = match value.split_once(':') {
+        Some((provider, model)) => (provider, Some(model.trim().to_string())),
+        None => (value, None),
+    };
+    let provider = ProviderKind::from_name(provider.trim()).ok_or_else(|| {
+        let known: Vec<&str> = ProviderKind::all().iter().map(|p| p.as_str()).collect();
+        format!(
+            "Unknown provider '{provider}'. Known providers: {}",
+            known.join(", ")
+        )
+    })?;
+    Ok(Target {
+        provider,
+        model: model.filter(|m| !m.is_empty()),
+    })
+}
+
+/// Outcome of one target
+#[derive(Debug, Clone, Serialize)]
+pub struct BenchResult {
+    pub provider: String,
+    pub model: String,
+    pub latency_ms: u128,
+    pub prompt_tokens: Option<u32>,
+    pub completion_tokens: Option<u32>,
+    pub cost_usd: Option<f64>,
+    pub message: Option<String>,
+    pub error: Option<String>,
+}
+
+impl BenchResult {
+    fn label(&self) -> String {
+        format!("{}/{}", self.provider, self.model)
+    }
+}
+
+/// Targets from the command line, or every provider that has an API key
+fn resolve_targets(config: &Config, targets: &[Target]) -> Vec<Target> {
+    if !targets.is_empty() {
+        return targets.to_vec();
+    }
+    ProviderKind::all()
+        .iter()
+        .filter(|provider| {
+            config
+                .get_provider_config(provider.as_str())
+                .is_some_and(|p| !p.api_key.is_empty())
+        })
+        .map(|provider| Target {
+            provider: *provider,
+            model: None,
+        })
+        .collect()
+}
+
+/// Handles the bench command.
+pub async fn handle_bench(
+    common: CommonParams,
+    repository_url: Option<String>,
+    params: &BenchParams,
+) -> Result<()> {
+    let mut config = Config::load()?;
+    common.apply_to_config(&mut config)?;
+
+    let targets = resolve_targets(&config, &params.targets);
+    if targets.is_empty() {
+        return Err(anyhow!(
+            "No provider has an API key configured; pass --target or set gitai.<provider>-apikey"
+        ));
+    }
+
+    let service = create_commit_service(&common, repository_url, &config)?;
+    let git_info = service.get_git_info().await?;
+    if git_info.staged_files.is_empty() {
+        return Err(coded(
+            ErrorCode::NoStagedChanges,
+            "No staged changes to benchmark with",
+        ));
+    }
+    let instructions = common
+        .instructions
+        .clone()
+        .unwrap_or_else(|| config.instructions.clone());
+    let (system_prompt, user_prompt) = service.commit_message_prompts(&instructions).await?;
+
+    let mut results = Vec::with_capacity(targets.len());
+    for target in &targets {
+        eprintln!("Running {}...", target.provider);
+        results.push(run_target(&config, target, &system_prompt, &user_prompt).await);
+    }
+
+    if params.json {
+        println!("{}", serde_json::to_string_pretty(&results)?);
+    } else {
+        println!("{}", format_results(&results));
+    }
+    Ok(())
+}
+
+async fn run_target(
+    config: &Config,
+    target: &Target,
+    system_prompt: &str,
+    user_prompt: &str,
+) -> BenchResult {
+    let provider = target.provider.as_str();
+    let mut config = config.clone();
+    let provider_config = config.providers.entry(provider.to_string()).or_default();
+    if let Some(model) = &target.model {
+        provider_config.model_name.clone_from(model);
+    }
+    if provider_config.model_name.is_empty() {
+        provider_config.model_name = target.provider.default_model().to_string();
+    }
+    let model = provider_config.model_name.clone();
+
+    let started = Instant::now();
+    let outcome = engine::get_message_with_usage::<GeneratedMessage>(
+        &config,
+        provider,
+        system_prompt,
+        user_prompt,
+    )
+    .await;
+    let latency_ms = started.elapsed().as_millis();
+
+    let (message, usage, error) = match outcome {
+        Ok((message, usage)) => (Some(format_commit_message(&message)), usage, None),
+        Err(e) => (None, None, Some(e.to_string())),
+    };
+    let cost_usd = usage
+        .zip(pricing::prices(&config, provider, &model))
+        .map(|(usage, prices): (TokenUsage, _)| pricing::estimate_cost(usage, prices));
+
+    BenchResult {
+        provider: provider.to_string(),
+        model,
+        latency_ms,
+        prompt_tokens: usage.map(|u| u.prompt_tokens),
+        completion_tokens: usage.map(|u| u.completion_tokens),
+        cost_usd,
+        message,
+        error,
+    }
+}
+
+/// Renders a summary table followed by each target's message
+pub fn format_results(results: &[BenchResult]) -> String {
+    let width = results
+        .iter()
+        .map(|r| r.label().len())
+        .max()
+        .unwrap_or(0)
+        .max("Target".len());
+    let optional = |value: Option<String>| value.unwrap_or_else(|| "n/a".to_string());
+
+    let mut out = format!(
+        "{:<width$}  {:>9}  {:>15}  {:>9}\n",
+        "Target", "Latency", "Tokens in/out", "Cost"
+    );
+    for result in results {
+        let tokens = result
+            .prompt_tokens
+            .zip(result.completion_tokens)
+            .map(|(input, output)| format!("{input}/{output}"));
+        let _ = writeln!(
+            out,
+            "{:<width$}  {:>8}ms  {:>15}  {:>9}",
+            result.label(),
+            result.latency_ms,
+            optional(tokens),
+            optional(result.cost_usd.map(|c| format!("${c:.5}")))
+        );
+    }
+
+    for result in results {
+        let _ = write!(out, "\n== {} ==\n", result.label());
+        match (&result.message, &result.error) {
+            (Some(message), _) => out.push_str(message),
+            (None, Some(error)) => {
+                let _ = write!(out, "Failed: {error}");
+            }
+            (None, None) => {}
+        }
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_target() {
+        assert_eq!(
+            parse_target("openrouter:anthropic/claude-3.5-sonnet"),
+            Ok(Target {
+                provider: ProviderKind::OpenRouter,
+                model: Some("anthropic/claude-3.5-sonnet".to_string()),
+            })
+        );
+        assert_eq!(
+            parse_target("google"),
+            Ok(Target {
+                provider: ProviderKind::Google,
+                model: None,
+            })
+        );
+        assert!(parse_target("nope:model").is_err());
+    }
+
+    #[test]
+    fn test_format_results() {
+        let results = vec![
+            BenchResult {
+                provider: "google".to_string(),
+                model: "gemini-2.0-flash".to_string(),
+                latency_ms: 1234,
+                prompt_tokens: Some(5000),
+                completion_tokens: Some(200),
+                cost_usd: Some(0.00058),
+                message: Some("fix: handle empty repos".to_string()),
+                error: None,
+            },
+            BenchResult {
+                provider: "openrouter".to_string(),
+                model: "x/y".to_string(),
+                latency_ms: 10,
+                prompt_tokens: None,
+                completion_tokens: None,
+                cost_usd: None,
+                message: None,
+                error: Some("Provider error".to_string()),
+            },
+        ];
+        let table = format_results(&results);
+        assert!(table.contains("google/gemini-2.0-flash      1234ms         5000/200   $0.00058"));
+        assert!(table.contains("== openrouter/x/y ==\nFailed: Provider error"));
+    }
+}
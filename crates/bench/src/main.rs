@@ -0,0 +1,44 @@
+use bench::{BenchParams, handle_bench};
+use clap::Parser;
+use cloy::app::defaults;
+use cloy::common::CommonParams;
+use cloy::{error, init_app};
+
+#[derive(Parser)]
+#[command(
+    name = "git-bench",
+    version,
+    author,
+    about = "Compare providers and models on the staged changes",
+    styles = cloy::app::args::get_styles(),
+)]
+struct BenchCli {
+    #[command(flatten)]
+    common: CommonParams,
+
+    #[command(flatten)]
+    params: BenchParams,
+}
+
+#[tokio::main]
+async fn main() {
+    init_app();
+
+    let BenchCli { mut common, params } = defaults::parse::<BenchCli>("bench");
+    let repository_url = std::mem::take(&mut common.repository_url);
+
+    if let Err(e) = handle_bench(common, repository_url, &params).await {
+        std::process::exit(error::report(&e));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn verify_cli() {
+        BenchCli::command().debug_assert();
+    }
+}
@@ -0,0 +1,92 @@
+//! Cost estimates from token usage
+//!
+//! Built-in prices are list prices in USD per million tokens at the time of
+//! writing and only cover common models. Set
+//! `gitai.<provider>-additionalinput-price` and `...output-price` to price
+//! other models or correct outdated entries.
+
+use cloy::config::Config;
+use cloy::llm::engine::TokenUsage;
+
+/// Additional param overriding the input price (USD per million tokens)
+pub const INPUT_PRICE_PARAM: &str = "input-price";
+/// Additional param overriding the output price (USD per million tokens)
+pub const OUTPUT_PRICE_PARAM: &str = "output-price";
+
+/// Model name fragment, input price, output price
+const PRICES: &[(&str, f64, f64)] = &[
+    ("gemini-2.5-pro", 1.25, 10.0),
+    ("gemini-2.5-flash-lite", 0.10, 0.40),
+    ("gemini-2.5-flash", 0.30, 2.50),
+    ("gemini-2.0-flash-lite", 0.075, 0.30),
+    ("gemini-2.0-flash", 0.10, 0.40),
+    ("gpt-4o-mini", 0.15, 0.60),
+    ("gpt-4o", 2.50, 10.0),
+    ("claude-3-5-haiku", 0.80, 4.0),
+    ("claude-3-5-sonnet", 3.0, 15.0),
+];
+
+/// Input and output price per million tokens for a model, if known
+pub fn prices(config: &Config, provider: &str, model: &str) -> Option<(f64, f64)> {
+    let params = config
+        .get_provider_config(provider)
+        .map(|p| &p.additional_params);
+    let param = |key: &str| {
+        params
+            .and_then(|p| p.get(key))
+            .and_then(|v| v.trim().parse::<f64>().ok())
+    };
+    if let (Some(input), Some(output)) = (param(INPUT_PRICE_PARAM), param(OUTPUT_PRICE_PARAM)) {
+        return Some((input, output));
+    }
+
+    // The first matching fragment wins, so longer names are listed first
+    PRICES
+        .iter()
+        .find(|(fragment, _, _)| model.contains(fragment))
+        .map(|(_, input, output)| (*input, *output))
+}
+
+/// Estimated cost in USD of a generation
+pub fn estimate_cost(usage: TokenUsage, (input, output): (f64, f64)) -> f64 {
+    (f64::from(usage.prompt_tokens) * input + f64::from(usage.completion_tokens) * output)
+        / 1_000_000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prices_and_cost() {
+        let mut config = Config::default();
+        assert_eq!(
+            prices(&config, "openrouter", "google/gemini-2.0-flash-001"),
+            Some((0.10, 0.40))
+        );
+        assert_eq!(
+            prices(&config, "google", "gemini-2.0-flash-lite"),
+            Some((0.075, 0.30))
+        );
+        assert_eq!(prices(&config, "google", "unknown-model"), None);
+
+        let google = config
+            .providers
+            .get_mut("google")
+            .expect("Google is always configured");
+        google
+            .additional_params
+            .insert(INPUT_PRICE_PARAM.to_string(), "1".to_string());
+        google
+            .additional_params
+            .insert(OUTPUT_PRICE_PARAM.to_string(), "2".to_string());
+        assert_eq!(prices(&config, "google", "unknown-model"), Some((1.0, 2.0)));
+
+        let usage = TokenUsage {
+            prompt_tokens: 1_000_000,
+            completion_tokens: 500_000,
+            cached_tokens: 0,
+        };
+        assert!((estimate_cost(usage, (1.0, 2.0)) - 2.0).abs() < f64::EPSILON);
+    }
+}
@@ -1,13 +1,15 @@
 use anyhow::{Context, Result};
 use chrono;
 use cloy::commands::changelog::change_analyzer::AnalyzedChange;
-use cloy::commands::changelog::common::generate_changes_content;
+use cloy::commands::changelog::common::{
+    ChangePrompts, generate_changes_content, generate_package_changes_content,
+};
 use cloy::commands::changelog::models::{
     BreakingChange, ChangeEntry, ChangeMetrics, ChangelogResponse, ChangelogType,
 };
 use cloy::common::DetailLevel;
 use cloy::config::Config;
-use cloy::git::GitRepo;
+use cloy::git::{GitRepo, Package};
 use colored::Colorize;
 use log::debug;
 use prompts::changelog as changelog_prompts;
@@ -34,14 +36,44 @@ impl ChangelogGenerator {
             to,
             config,
             detail_level,
-            system_prompt_adapter,
-            user_prompt_adapter,
+            ChangePrompts {
+                name: "changelog",
+                system: system_prompt_adapter,
+                user: user_prompt_adapter,
+            },
         )
         .await?;
 
         Ok(format_changelog_response(&changelog))
     }
 
+    /// The changelog of the commits touching `package`, or `None` when there are none
+    pub async fn generate_for_package(
+        git_repo: Arc<GitRepo>,
+        from: &str,
+        to: &str,
+        config: &Config,
+        detail_level: DetailLevel,
+        package: &Package,
+    ) -> Result<Option<String>> {
+        let changelog: Option<ChangelogResponse> = generate_package_changes_content(
+            git_repo,
+            from,
+            to,
+            config,
+            detail_level,
+            ChangePrompts {
+                name: "changelog",
+                system: system_prompt_adapter,
+                user: user_prompt_adapter,
+            },
+            Some(package),
+        )
+        .await?;
+
+        Ok(changelog.as_ref().map(format_changelog_response))
+    }
+
     pub fn update_changelog_file(
         changelog_content: &str,
         changelog_path: &str,
@@ -118,9 +150,13 @@ fn user_prompt_adapter(
         }
         writeln!(changes_buf, "Impact score: {:.2}", change.impact_score).ok();
 
-        match detail_level {
-            DetailLevel::Minimal => {}
-            DetailLevel::Standard | DetailLevel::Detailed => {
+        match (detail_level, &change.summary) {
+            (DetailLevel::Minimal, _) => {}
+            // Commits of long ranges are summarized to keep the prompt small
+            (_, Some(summary)) => {
+                writeln!(changes_buf, "Summary: {summary}").ok();
+            }
+            (DetailLevel::Standard | DetailLevel::Detailed, None) => {
                 changes_buf.push_str("File changes:\n");
                 for file_change in &change.file_changes {
                     writeln!(
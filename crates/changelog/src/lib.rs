@@ -2,12 +2,13 @@ pub mod change_log;
 
 use crate::change_log::ChangelogGenerator;
 use anyhow::{Context, Result, anyhow};
-use cloy::common::CommonParams;
+use cloy::common::{CommonParams, DetailLevel};
 use cloy::config::Config;
 use cloy::git::GitRepo;
 use cloy::output;
 use colored::Colorize;
 use std::env;
+use std::path::Path;
 use std::sync::Arc;
 
 pub struct ChangelogCommandConfig {
@@ -18,6 +19,8 @@ pub struct ChangelogCommandConfig {
     pub save: bool,
     pub changelog_path: Option<String>,
     pub version_name: Option<String>,
+    /// One changelog per workspace member, in the member's directory
+    pub per_package: bool,
 }
 
 pub async fn handle_changelog_command(
@@ -32,6 +35,7 @@ pub async fn handle_changelog_command(
         save,
         changelog_path,
         version_name,
+        per_package,
     } = config;
 
     let mut config = Config::load()?;
@@ -39,16 +43,7 @@ pub async fn handle_changelog_command(
 
     let mut spinner = output::create_tui_spinner("Generating changelog...");
 
-    if let Err(e) = config.check_environment() {
-        output::print_error(&format!("Error: {e}"));
-        output::print_info("\nPlease ensure the following:");
-        output::print_info("1. Git is installed and accessible from the command line.");
-        output::print_info(
-            "2. You are running this command from within a Git repository or provide a repository URL with --repo.",
-        );
-        output::print_info("3. You have set up your configuration using 'git config'.");
-        return Err(e);
-    }
+    config.check_environment()?;
 
     let repo_url = repository_url.or(common.repository_url);
 
@@ -68,41 +63,27 @@ pub async fn handle_changelog_command(
         changelog_path
     };
 
-    let from_ref = if let Some(f) = from {
-        Some(f)
-    } else if save {
-        output::print_info("Detecting latest tag...");
-        match git_repo.get_latest_tag() {
-            Ok(Some(tag)) => {
-                output::print_success(&format!("Found latest tag: {tag}"));
-                Some(tag)
-            }
-            Ok(None) => {
-                output::print_info("No tags found, using first commit...");
-                match git_repo.get_first_commit() {
-                    Ok(commit) => Some(commit),
-                    Err(e) => {
-                        output::print_error(&format!("Failed to get first commit: {e}"));
-                        return Err(anyhow!("Cannot determine starting point for changelog"));
-                    }
-                }
-            }
-            Err(e) => {
-                output::print_error(&format!("Failed to get latest tag: {e}"));
-                return Err(anyhow!("Failed to detect latest tag: {e}"));
-            }
-        }
-    } else {
-        None
-    };
-
-    let from_ref = from_ref
-        .ok_or_else(|| anyhow!("Starting reference (--from) is required when not using --save"))?;
+    let from_ref = starting_ref(&git_repo, from, save)?;
 
     let to = to.unwrap_or_else(|| "HEAD".to_string());
 
     let detail_level = common.detail_level;
 
+    if per_package {
+        spinner.tick();
+        return write_package_changelogs(
+            &git_repo,
+            &from_ref,
+            &to,
+            &config,
+            detail_level,
+            should_update_file
+                .then(|| changelog_file_path.unwrap_or_else(|| "CHANGELOG.md".to_string())),
+            version_name,
+        )
+        .await;
+    }
+
     let changelog =
         ChangelogGenerator::generate(git_repo, &from_ref, &to, &config, detail_level).await?;
 
@@ -138,3 +119,100 @@ pub async fn handle_changelog_command(
 
     Ok(())
 }
+
+/// Generates a changelog for each workspace member with commits in the range,
+/// printing it and, with `file_name`, writing it to that file in the member's directory
+async fn write_package_changelogs(
+    git_repo: &Arc<GitRepo>,
+    from: &str,
+    to: &str,
+    config: &Config,
+    detail_level: DetailLevel,
+    file_name: Option<String>,
+    version_name: Option<String>,
+) -> Result<()> {
+    let packages = git_repo.workspace_packages(to)?;
+    if packages.is_empty() {
+        return Err(anyhow!(
+            "No Cargo, npm or pnpm workspace members found at {to}"
+        ));
+    }
+
+    for package in &packages {
+        let mut spinner =
+            output::create_tui_spinner(&format!("Generating changelog for {}...", package.name));
+        let changelog = ChangelogGenerator::generate_for_package(
+            Arc::clone(git_repo),
+            from,
+            to,
+            config,
+            detail_level,
+            package,
+        )
+        .await?;
+        spinner.tick();
+        let Some(changelog) = changelog else {
+            output::print_info(&format!(
+                "No changes in {} ({})",
+                package.name, package.path
+            ));
+            continue;
+        };
+
+        output::print_info(&format!("\n{} ({})", package.name.bold(), package.path));
+        output::print_bordered_content(&changelog);
+
+        if let Some(file_name) = &file_name {
+            let path = Path::new(&package.path).join(file_name);
+            let path = path.to_string_lossy();
+            match ChangelogGenerator::update_changelog_file(
+                &changelog,
+                &path,
+                git_repo,
+                to,
+                version_name.clone(),
+            ) {
+                Ok(()) => output::print_success(&format!(
+                    "✨ Changelog successfully updated at {}",
+                    path.bright_green()
+                )),
+                Err(e) => output::print_error(&format!("Failed to update changelog file: {e}")),
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// `from`, or with `--save` the latest tag, or the first commit when there is none
+fn starting_ref(git_repo: &GitRepo, from: Option<String>, save: bool) -> Result<String> {
+    let from_ref = if let Some(f) = from {
+        Some(f)
+    } else if save {
+        output::print_info("Detecting latest tag...");
+        match git_repo.get_latest_tag() {
+            Ok(Some(tag)) => {
+                output::print_success(&format!("Found latest tag: {tag}"));
+                Some(tag)
+            }
+            Ok(None) => {
+                output::print_info("No tags found, using first commit...");
+                match git_repo.get_first_commit() {
+                    Ok(commit) => Some(commit),
+                    Err(e) => {
+                        output::print_error(&format!("Failed to get first commit: {e}"));
+                        return Err(anyhow!("Cannot determine starting point for changelog"));
+                    }
+                }
+            }
+            Err(e) => {
+                output::print_error(&format!("Failed to get latest tag: {e}"));
+                return Err(anyhow!("Failed to detect latest tag: {e}"));
+            }
+        }
+    } else {
+        None
+    };
+
+    from_ref.ok_or_else(|| anyhow!("Starting reference (--from) is required when not using --save"))
+}
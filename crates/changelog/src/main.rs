@@ -1,11 +1,11 @@
 use anyhow::Result;
 use changelog::{ChangelogCommandConfig, handle_changelog_command};
 use clap::{Args, Parser, crate_authors, crate_version};
+use cloy::app::defaults;
 use cloy::{
     app::args::{get_dynamic_help, get_styles},
     common::CommonParams,
-    init_app,
-    output::print_error,
+    error, init_app,
 };
 
 #[derive(Args, Clone, Debug)]
@@ -27,6 +27,12 @@ struct ChangelogParams {
 
     #[arg(long, help = "Explicit version name to use in the changelog")]
     version_name: Option<String>,
+
+    #[arg(
+        long,
+        help = "Write a changelog for each Cargo, npm or pnpm workspace member, from the commits touching it"
+    )]
+    per_package: bool,
 }
 
 #[derive(Parser)]
@@ -50,7 +56,7 @@ struct CliArgs {
 async fn main() -> Result<()> {
     init_app();
 
-    let cli_args = CliArgs::parse();
+    let cli_args = defaults::parse::<CliArgs>("changelog");
     let CliArgs { mut common, params } = cli_args;
     let repository_url = std::mem::take(&mut common.repository_url);
 
@@ -64,12 +70,12 @@ async fn main() -> Result<()> {
             save: params.save,
             changelog_path: params.file,
             version_name: params.version_name,
+            per_package: params.per_package,
         },
     )
     .await
     {
-        print_error(&format!("Error: {e}"));
-        std::process::exit(1);
+        std::process::exit(error::report(&e));
     }
 
     Ok(())
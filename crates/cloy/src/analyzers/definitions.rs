@@ -0,0 +1,205 @@
+//! Traits and interfaces a changed file implements, and where they are defined.
+//!
+//! A change inside `impl Storage for DiskStore` is hard to describe without
+//! the `Storage` trait, which lives in a file the diff does not touch. The
+//! implemented names are read off the diff, including the hunk headers git
+//! fills with the enclosing `impl` line, and the defining files are then
+//! found by scanning unchanged files of the same language.
+
+use crate::llm::context::StagedFile;
+use regex::Regex;
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::LazyLock;
+
+/// `impl Trait for Type`, `impl<T> path::Trait<T> for Type<T>`
+static RUST_IMPL: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\bimpl(?:<.*?>)?\s+!?([A-Za-z_][\w:]*)(?:<.*?>)?\s+for\s").expect("Valid regex")
+});
+
+/// `class Foo extends Bar implements Baz, Qux<T> {`
+static IMPLEMENTS: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\bimplements\s+([^{]+)").expect("Valid regex"));
+
+static RUST_TRAIT: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^\s*(?:pub(?:\([^)]*\))?\s+)?(?:unsafe\s+)?(?:auto\s+)?trait\s+([A-Za-z_]\w*)")
+        .expect("Valid regex")
+});
+
+static TS_INTERFACE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^\s*(?:export\s+)?(?:default\s+)?(?:declare\s+)?interface\s+([A-Za-z_$][\w$]*)")
+        .expect("Valid regex")
+});
+
+static JAVA_INTERFACE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"^\s*(?:(?:public|protected|private|abstract|static|sealed|non-sealed)\s+)*interface\s+(\w+)",
+    )
+    .expect("Valid regex")
+});
+
+/// Languages whose implementations and definitions are read
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum Language {
+    Rust,
+    TypeScript,
+    Java,
+}
+
+impl Language {
+    /// The language of `path`, by extension
+    pub(crate) fn of(path: &str) -> Option<Self> {
+        match Path::new(path).extension()?.to_str()? {
+            "rs" => Some(Self::Rust),
+            "ts" | "tsx" | "mts" | "cts" => Some(Self::TypeScript),
+            "java" => Some(Self::Java),
+            _ => None,
+        }
+    }
+}
+
+/// Names of the traits or interfaces `file` implements where it changes
+pub(crate) fn implemented_traits(file: &StagedFile) -> Vec<String> {
+    let Some(language) = Language::of(&file.path) else {
+        return Vec::new();
+    };
+
+    let mut names = Vec::new();
+    for line in file.diff.lines() {
+        let text = if let Some(header) = line.strip_prefix("@@") {
+            // `@@ -1,4 +1,5 @@ impl Trait for Type {`
+            header.split_once("@@").map_or("", |(_, context)| context)
+        } else if line.starts_with("+++") || line.starts_with("---") {
+            continue;
+        } else if let Some(text) = line.strip_prefix(['+', ' ']) {
+            text
+        } else {
+            continue;
+        };
+
+        let found: Vec<&str> = match language {
+            Language::Rust => RUST_IMPL
+                .captures(text)
+                .and_then(|captures| captures.get(1))
+                .map(|name| name.as_str())
+                .into_iter()
+                .collect(),
+            Language::TypeScript | Language::Java => IMPLEMENTS
+                .captures(text)
+                .and_then(|captures| captures.get(1))
+                .map(|list| split_type_list(list.as_str()))
+                .unwrap_or_default(),
+        };
+        for name in found {
+            // `fmt::Display` is looked up as `Display`
+            let name = name.rsplit(['.', ':']).next().unwrap_or(name);
+            if !name.is_empty() && !names.iter().any(|n| n == name) {
+                names.push(name.to_string());
+            }
+        }
+    }
+    names
+}
+
+/// The names in `Foo, Bar<T, U>`, without type arguments
+fn split_type_list(list: &str) -> Vec<&str> {
+    let mut names = Vec::new();
+    let mut depth = 0usize;
+    let mut start = Some(0);
+    for (i, c) in list.char_indices() {
+        match c {
+            '<' => {
+                if depth == 0
+                    && let Some(from) = start.take()
+                {
+                    names.push(list[from..i].trim());
+                }
+                depth += 1;
+            }
+            '>' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                if let Some(from) = start {
+                    names.push(list[from..i].trim());
+                }
+                start = Some(i + 1);
+            }
+            _ => {}
+        }
+    }
+    if let Some(from) = start {
+        names.push(list[from..].trim());
+    }
+    names.retain(|name| !name.is_empty() && !name.contains(char::is_whitespace));
+    names
+}
+
+/// Traits or interfaces among `names` that `text` defines, with the line each starts on
+pub(crate) fn defined_traits(
+    language: Language,
+    text: &str,
+    names: &HashSet<String>,
+) -> Vec<(String, usize)> {
+    let regex: &Regex = match language {
+        Language::Rust => &RUST_TRAIT,
+        Language::TypeScript => &TS_INTERFACE,
+        Language::Java => &JAVA_INTERFACE,
+    };
+    text.lines()
+        .enumerate()
+        .filter_map(|(number, line)| {
+            let name = regex.captures(line)?.get(1)?.as_str();
+            names.contains(name).then(|| (name.to_string(), number))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::context::ChangeType;
+
+    fn staged(path: &str, diff: &str) -> StagedFile {
+        StagedFile {
+            path: path.to_string(),
+            change_type: ChangeType::Modified,
+            diff: diff.to_string(),
+            content: None,
+            content_excluded: false,
+        }
+    }
+
+    #[test]
+    fn test_implemented_traits() {
+        let diff = "\
+--- a/src/disk.rs
++++ b/src/disk.rs
+@@ -10,6 +10,7 @@ impl Storage for DiskStore {
+     fn read(&self) -> Vec<u8> {
++        self.sync();
+ }
++impl<T: Into<String>> fmt::Display for Wrapper<T> {
+";
+        assert_eq!(
+            implemented_traits(&staged("src/disk.rs", diff)),
+            ["Storage", "Display"]
+        );
+
+        let diff = "+export class Store extends Base<T> implements Cache<K, V>, Closeable {\n";
+        assert_eq!(
+            implemented_traits(&staged("src/store.ts", diff)),
+            ["Cache", "Closeable"]
+        );
+        assert!(implemented_traits(&staged("README.md", diff)).is_empty());
+    }
+
+    #[test]
+    fn test_defined_traits() {
+        let names: HashSet<String> = ["Storage".to_string()].into();
+        let text = "use std::io;\n\npub(crate) trait Storage: Send {\n    fn read(&self);\n}\n";
+        assert_eq!(
+            defined_traits(Language::Rust, text, &names),
+            [("Storage".to_string(), 2)]
+        );
+        assert!(defined_traits(Language::Rust, "trait Other {}", &names).is_empty());
+    }
+}
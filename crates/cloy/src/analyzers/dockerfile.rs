@@ -0,0 +1,149 @@
+use super::{FileAnalyzer, changed_lines, push_unique};
+use crate::llm::context::StagedFile;
+
+/// Characters of a `RUN` command kept in a note
+const MAX_COMMAND_CHARS: usize = 60;
+
+/// Base images, exposed ports and `RUN` layers a Dockerfile changes
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DockerfileAnalyzer;
+
+impl DockerfileAnalyzer {
+    /// Files it reads
+    pub const PATTERNS: &'static [&'static str] = &[
+        "Dockerfile",
+        "Dockerfile.*",
+        "*.dockerfile",
+        "Containerfile",
+    ];
+}
+
+/// The arguments of `instruction` on `line`, which Docker matches without case
+fn arguments<'a>(line: &'a str, instruction: &str) -> Option<&'a str> {
+    let line = line.trim_start();
+    let (word, rest) = line.split_once(char::is_whitespace)?;
+    word.eq_ignore_ascii_case(instruction)
+        .then(|| rest.trim())
+        .filter(|rest| !rest.is_empty())
+}
+
+/// The image of a `FROM`, without `--platform` and the stage name
+fn image(arguments: &str) -> Option<String> {
+    arguments
+        .split_whitespace()
+        .find(|word| !word.starts_with("--"))
+        .map(ToString::to_string)
+}
+
+/// A `RUN` command cut to [`MAX_COMMAND_CHARS`], without its line continuation
+fn command(arguments: &str) -> String {
+    let command = arguments.trim_end_matches('\\').trim_end();
+    if command.chars().count() > MAX_COMMAND_CHARS {
+        let cut: String = command.chars().take(MAX_COMMAND_CHARS).collect();
+        format!("{}...", cut.trim_end())
+    } else {
+        command.to_string()
+    }
+}
+
+/// Items on added lines that no removed line has, and the other way round
+fn delta(added: &[String], removed: &[String]) -> (Vec<String>, Vec<String>) {
+    let new = added.iter().filter(|item| !removed.contains(item));
+    let gone = removed.iter().filter(|item| !added.contains(item));
+    (new.cloned().collect(), gone.cloned().collect())
+}
+
+impl FileAnalyzer for DockerfileAnalyzer {
+    fn name(&self) -> &'static str {
+        "dockerfile"
+    }
+
+    fn analyze(&self, file: &StagedFile) -> Vec<String> {
+        let mut images = (Vec::new(), Vec::new());
+        let mut ports = (Vec::new(), Vec::new());
+        let mut commands = (Vec::new(), Vec::new());
+        for (origin, line) in changed_lines(&file.diff) {
+            let side = |pair: &mut (Vec<String>, Vec<String>), item: String| {
+                if origin == '+' {
+                    push_unique(&mut pair.0, item);
+                } else {
+                    push_unique(&mut pair.1, item);
+                }
+            };
+            if let Some(image) = arguments(line, "FROM").and_then(image) {
+                side(&mut images, image);
+            } else if let Some(exposed) = arguments(line, "EXPOSE") {
+                for port in exposed.split_whitespace() {
+                    side(&mut ports, port.to_string());
+                }
+            } else if let Some(run) = arguments(line, "RUN") {
+                side(&mut commands, command(run));
+            }
+        }
+
+        let mut notes = Vec::new();
+        let (new, gone) = delta(&images.0, &images.1);
+        // Base images replaced one for one read as upgrades, stage by stage
+        let replaced = new.len().min(gone.len());
+        for (from, to) in gone.iter().zip(&new) {
+            notes.push(format!("base image {from} → {to}"));
+        }
+        notes.extend(
+            new[replaced..]
+                .iter()
+                .map(|image| format!("adds a stage from {image}")),
+        );
+        notes.extend(
+            gone[replaced..]
+                .iter()
+                .map(|image| format!("drops the stage from {image}")),
+        );
+
+        let (new, gone) = delta(&ports.0, &ports.1);
+        notes.extend(new.iter().map(|port| format!("exposes port {port}")));
+        notes.extend(
+            gone.iter()
+                .map(|port| format!("stops exposing port {port}")),
+        );
+
+        let (new, gone) = delta(&commands.0, &commands.1);
+        notes.extend(new.iter().map(|run| format!("new RUN layer: {run}")));
+        notes.extend(gone.iter().map(|run| format!("removes RUN layer: {run}")));
+        notes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::context::ChangeType;
+
+    #[test]
+    fn test_dockerfile_notes() {
+        let diff = "\
+-FROM --platform=linux/amd64 node:18 AS build
++FROM --platform=linux/amd64 node:20 AS build
+ RUN npm ci
++run apt-get update && apt-get install -y curl \\
++from nginx:alpine
+-EXPOSE 80
++EXPOSE 80 8080
+";
+        let file = StagedFile {
+            path: "web/Dockerfile".to_string(),
+            change_type: ChangeType::Modified,
+            diff: diff.to_string(),
+            content: None,
+            content_excluded: false,
+        };
+        assert_eq!(
+            DockerfileAnalyzer.analyze(&file),
+            [
+                "base image node:18 → node:20",
+                "adds a stage from nginx:alpine",
+                "exposes port 8080",
+                "new RUN layer: apt-get update && apt-get install -y curl",
+            ]
+        );
+    }
+}
@@ -0,0 +1,385 @@
+use super::{ContractChange, FileAnalyzer, lines_before_hunk, push_unique};
+use crate::llm::api_changes::{ApiChange, ApiChangeKind};
+use crate::llm::context::StagedFile;
+use regex::Regex;
+use std::sync::LazyLock;
+
+/// `type User {`, `input NewUser {`, `enum Role {`, `union Result = ...`
+static TYPE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^\s*(?:extend\s+)?(type|interface|input|enum|union|scalar)\s+(\w+)")
+        .expect("Valid regex")
+});
+
+/// `name(arg: Int = 1): Type`, on one line
+static FIELD: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^\s*(\w+)\s*(?:\(([^)]*)\))?\s*:\s*([\w\[\]!]+)(\s*=)?").expect("Valid regex")
+});
+
+static ENUM_VALUE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^\s*([A-Za-z_]\w*)\s*(?:@.*)?$").expect("Valid regex"));
+
+/// Types, fields and enum values a GraphQL schema adds or removes, and the types it narrows
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GraphQlAnalyzer;
+
+impl GraphQlAnalyzer {
+    pub const NAME: &'static str = "graphql";
+
+    /// Files it reads
+    pub const PATTERNS: &'static [&'static str] = &["*.graphql", "*.graphqls", "*.gql"];
+}
+
+/// A field or an argument, as declared
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Field {
+    name: String,
+    ty: String,
+    has_default: bool,
+}
+
+/// A field's declaration line
+#[derive(Debug, Clone)]
+struct Declaration {
+    /// `Type.field`
+    name: String,
+    /// Whether the type is an input type, which clients send rather than read
+    input: bool,
+    field: Field,
+    args: Vec<Field>,
+    signature: String,
+}
+
+fn parse_field(text: &str) -> Option<Field> {
+    let (name, rest) = text.split_once(':')?;
+    let (ty, default) = match rest.split_once('=') {
+        Some((ty, _)) => (ty, true),
+        None => (rest, false),
+    };
+    let ty = ty.split_whitespace().next()?;
+    Some(Field {
+        name: name.trim().to_string(),
+        ty: ty.to_string(),
+        has_default: default,
+    })
+}
+
+/// Whether clients written against `old` can break on `new`; inputs may only
+/// drop a `!`, outputs may only add one
+fn narrows(input: bool, old: &str, new: &str) -> bool {
+    let (wider, narrower) = if input { (new, old) } else { (old, new) };
+    old != new && narrower.strip_suffix('!') != Some(wider)
+}
+
+/// Whether a field's new declaration breaks clients of the old one
+fn breaks(old: &Declaration, new: &Declaration) -> bool {
+    if narrows(old.input, &old.field.ty, &new.field.ty) {
+        return true;
+    }
+    let removed_or_narrowed = old.args.iter().any(|arg| {
+        new.args
+            .iter()
+            .find(|new| new.name == arg.name)
+            .is_none_or(|new| narrows(true, &arg.ty, &new.ty))
+    });
+    removed_or_narrowed
+        || new.args.iter().any(|arg| {
+            arg.ty.ends_with('!')
+                && !arg.has_default
+                && !old.args.iter().any(|old| old.name == arg.name)
+        })
+}
+
+/// Moves `current` to the line `text`; the kind and name of the type a field
+/// on the line would belong to
+fn enter(text: &str, current: &mut Option<(String, String)>) -> Option<(String, String)> {
+    let trimmed = text.trim();
+    if let Some(captures) = TYPE.captures(text) {
+        let opens = trimmed.ends_with('{');
+        *current = opens.then(|| (captures[1].to_string(), captures[2].to_string()));
+        return None;
+    }
+    if trimmed.starts_with('}') {
+        *current = None;
+    }
+    current.clone()
+}
+
+/// Declarations on the added and on the removed lines of a diff
+#[derive(Debug, Default)]
+struct Sides {
+    types: (Vec<String>, Vec<String>),
+    fields: (Vec<Declaration>, Vec<Declaration>),
+    values: (Vec<String>, Vec<String>),
+}
+
+/// The types, fields and enum values on the changed lines of a schema's diff
+fn declarations(file: &StagedFile) -> Sides {
+    let Sides {
+        mut types,
+        mut fields,
+        mut values,
+    } = Sides::default();
+    // Kind and name of the type the diff is in
+    let mut current: Option<(String, String)> = None;
+
+    for line in file.diff.lines() {
+        if line.starts_with("+++") || line.starts_with("---") {
+            continue;
+        }
+        if let Some(before) = lines_before_hunk(file, line) {
+            current = None;
+            for text in before {
+                enter(text, &mut current);
+            }
+            continue;
+        }
+        let (origin, text) = line.split_at(line.chars().next().map_or(0, char::len_utf8));
+        let side = match origin {
+            "+" => 0,
+            "-" => 1,
+            _ => {
+                enter(text, &mut current);
+                continue;
+            }
+        };
+        let pick = |pair: &mut (Vec<String>, Vec<String>), item: String| {
+            let list = if side == 0 { &mut pair.0 } else { &mut pair.1 };
+            push_unique(list, item);
+        };
+        if let Some(captures) = TYPE.captures(text) {
+            pick(&mut types, format!("{} {}", &captures[1], &captures[2]));
+        }
+        let trimmed = text.trim();
+        let Some((kind, name)) = enter(text, &mut current) else {
+            continue;
+        };
+        if trimmed.starts_with(['"', '#', '}']) {
+            continue;
+        }
+        if kind == "enum" {
+            if let Some(captures) = ENUM_VALUE.captures(trimmed) {
+                pick(&mut values, format!("{name}.{}", &captures[1]));
+            }
+        } else if let Some(captures) = FIELD.captures(trimmed) {
+            let declaration = Declaration {
+                name: format!("{name}.{}", &captures[1]),
+                input: kind == "input",
+                field: Field {
+                    name: captures[1].to_string(),
+                    ty: captures[3].to_string(),
+                    has_default: captures.get(4).is_some(),
+                },
+                args: captures.get(2).map_or_else(Vec::new, |args| {
+                    args.as_str().split(',').filter_map(parse_field).collect()
+                }),
+                signature: trimmed.to_string(),
+            };
+            if side == 0 {
+                fields.0.push(declaration);
+            } else {
+                fields.1.push(declaration);
+            }
+        }
+    }
+    Sides {
+        types,
+        fields,
+        values,
+    }
+}
+
+/// What declaring `new` in place of `old`, or of nothing, does to clients
+fn field_change(
+    path: &str,
+    old: Option<&Declaration>,
+    new: &Declaration,
+) -> Option<ContractChange> {
+    let narrowed = |before: String| {
+        ContractChange::Breaking(ApiChange {
+            path: path.to_string(),
+            kind: ApiChangeKind::Narrowed,
+            item: format!("field {}", new.name),
+            before,
+            after: Some(new.signature.clone()),
+        })
+    };
+    match old {
+        Some(old) if breaks(old, new) => Some(narrowed(old.signature.clone())),
+        Some(old) if old.signature != new.signature => Some(ContractChange::Compatible(format!(
+            "changes field {}: {} → {}",
+            new.name, old.signature, new.signature
+        ))),
+        Some(_) => None,
+        // A required input field must now be sent by every client
+        None if new.input && new.field.ty.ends_with('!') && !new.field.has_default => {
+            Some(narrowed("absent".to_string()))
+        }
+        None => Some(ContractChange::Compatible(format!(
+            "adds field {}",
+            new.name
+        ))),
+    }
+}
+
+/// What a GraphQL schema's diff changes in the API, and what of it breaks clients
+pub(crate) fn contract_changes(file: &StagedFile) -> Vec<ContractChange> {
+    let Sides {
+        types,
+        fields,
+        values,
+    } = declarations(file);
+    let breaking = |kind, item: String, before: String, after: Option<String>| ApiChange {
+        path: file.path.clone(),
+        kind,
+        item,
+        before,
+        after,
+    };
+    let mut changes = Vec::new();
+    let (added_types, removed_types) = types;
+    let type_name = |item: &String| item.split_once(' ').map(|(_, name)| name.to_string());
+    let new_types: Vec<String> = added_types
+        .iter()
+        .filter(|item| !removed_types.contains(item))
+        .filter_map(type_name)
+        .collect();
+    let gone_types: Vec<String> = removed_types
+        .iter()
+        .filter(|item| !added_types.contains(item))
+        .filter_map(type_name)
+        .collect();
+    let in_types = |item: &str, names: &[String]| {
+        item.split_once('.')
+            .is_some_and(|(owner, _)| names.iter().any(|name| name == owner))
+    };
+
+    for item in added_types
+        .iter()
+        .filter(|item| !removed_types.contains(item))
+    {
+        changes.push(ContractChange::Compatible(format!("adds {item}")));
+    }
+    let (added_fields, removed_fields) = fields;
+    for new in &added_fields {
+        let old = removed_fields.iter().find(|old| old.name == new.name);
+        if old.is_some() || !in_types(&new.name, &new_types) {
+            changes.extend(field_change(&file.path, old, new));
+        }
+    }
+    let (added_values, removed_values) = values;
+    for value in added_values
+        .iter()
+        .filter(|value| !removed_values.contains(value) && !in_types(value, &new_types))
+    {
+        changes.push(ContractChange::Compatible(format!(
+            "adds enum value {value}"
+        )));
+    }
+
+    for item in removed_types
+        .iter()
+        .filter(|item| !added_types.contains(item))
+    {
+        changes.push(ContractChange::Breaking(breaking(
+            ApiChangeKind::Removed,
+            item.clone(),
+            item.clone(),
+            None,
+        )));
+    }
+    for old in removed_fields.iter().filter(|old| {
+        !added_fields.iter().any(|new| new.name == old.name) && !in_types(&old.name, &gone_types)
+    }) {
+        changes.push(ContractChange::Breaking(breaking(
+            ApiChangeKind::Removed,
+            format!("field {}", old.name),
+            old.signature.clone(),
+            None,
+        )));
+    }
+    for value in removed_values
+        .iter()
+        .filter(|value| !added_values.contains(value) && !in_types(value, &gone_types))
+    {
+        changes.push(ContractChange::Breaking(breaking(
+            ApiChangeKind::Removed,
+            format!("enum value {value}"),
+            value.clone(),
+            None,
+        )));
+    }
+    changes
+}
+
+impl FileAnalyzer for GraphQlAnalyzer {
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn analyze(&self, file: &StagedFile) -> Vec<String> {
+        contract_changes(file)
+            .iter()
+            .map(ContractChange::note)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::context::ChangeType;
+
+    #[test]
+    fn test_graphql_contract_changes() {
+        let diff = "\
+@@ -1,20 +1,22 @@
+ type User {
+   id: ID!
+-  name: String!
++  name: String
+-  email: String
++  email: String!
+-  legacyId: Int
+ }
++type Team {
++  id: ID!
++}
+ type Query {
+-  users(first: Int): [User!]!
++  users(first: Int, after: String!): [User!]!
+ }
+ input NewUser {
++  email: String!
++  nickname: String
+ }
+ enum Role {
+   MEMBER
+-  ADMIN
++  GUEST
+ }
+-scalar Date
+";
+        let file = StagedFile {
+            path: "schema/schema.graphql".to_string(),
+            change_type: ChangeType::Modified,
+            diff: diff.to_string(),
+            content: None,
+            content_excluded: false,
+        };
+        assert_eq!(
+            GraphQlAnalyzer.analyze(&file),
+            [
+                "adds type Team",
+                "narrows field User.name: name: String! → name: String (breaking)",
+                "changes field User.email: email: String → email: String!",
+                "narrows field Query.users: users(first: Int): [User!]! → users(first: Int, after: String!): [User!]! (breaking)",
+                "narrows field NewUser.email: absent → email: String! (breaking)",
+                "adds field NewUser.nickname",
+                "adds enum value Role.GUEST",
+                "removes scalar Date (breaking)",
+                "removes field User.legacyId (breaking)",
+                "removes enum value Role.ADMIN (breaking)",
+            ]
+        );
+    }
+}
@@ -0,0 +1,495 @@
+use super::FileAnalyzer;
+use crate::llm::context::StagedFile;
+use regex::Regex;
+use serde::Serialize;
+use std::fmt;
+use std::path::Path;
+use std::sync::LazyLock;
+
+/// Keys of manifests that take versions but are not dependencies
+const NOT_DEPENDENCIES: &[&str] = &[
+    "version",
+    "edition",
+    "rust-version",
+    "name",
+    "description",
+    "license",
+    "python",
+    "requires-python",
+    "node",
+    "npm",
+    "php",
+    "go",
+    "toolchain",
+];
+
+/// `[section]` and `[[section]]` headers of TOML manifests
+static TOML_HEADER: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^\s*\[\[?([^\]]+)\]\]?").expect("Valid regex"));
+
+/// `name = "1.2"`, `name = { version = "1.2" }` and `name.workspace = true` in TOML
+static TOML_ENTRY: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"^\s*"?([A-Za-z0-9_.@/-]+?)"?(\.workspace)?\s*=\s*(.*)$"#).expect("Valid regex")
+});
+
+/// `key = [` opening an array of requirement strings in TOML
+static TOML_ARRAY: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"^\s*"?([A-Za-z0-9_.-]+)"?\s*=\s*\["#).expect("Valid regex"));
+
+/// `version = "1.2"` inside an inline table
+static TOML_VERSION: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"version\s*=\s*"([^"]+)""#).expect("Valid regex"));
+
+/// `"name>=1.2"` in an array of PEP 508 requirements
+static REQUIREMENT_STRING: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"^\s*"([A-Za-z0-9_.-]+)(?:\[[^\]]*\])?\s*([^";]*)"#).expect("Valid regex")
+});
+
+/// `name>=1.2` on a line of `requirements.txt`
+static REQUIREMENT_LINE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^([A-Za-z0-9_.-]+)(?:\[[^\]]*\])?\s*([^;#]*)").expect("Valid regex")
+});
+
+/// `"key": {` opening an object in JSON
+static JSON_OBJECT: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"^\s*"([^"]+)"\s*:\s*\{"#).expect("Valid regex"));
+
+/// `"name": "^1.2"` in JSON
+static JSON_ENTRY: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"^\s*"([^"]+)"\s*:\s*"([^"]*)""#).expect("Valid regex"));
+
+/// `module/path v1.2.3` in `go.mod`
+static GO_ENTRY: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^\s*(?:require\s+)?([^\s()]+\.[^\s()]+/[^\s()]+)\s+(v\S+)").expect("Valid regex")
+});
+
+/// What a dependency is needed for
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DependencyKind {
+    Normal,
+    Dev,
+    Build,
+}
+
+/// A dependency a manifest adds, removes or moves to another version
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct DependencyChange {
+    pub name: String,
+    pub kind: DependencyKind,
+    /// Version before the change; `None` for an added dependency
+    pub from: Option<String>,
+    /// Version after the change; `None` for a removed dependency
+    pub to: Option<String>,
+}
+
+impl fmt::Display for DependencyChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let version = |version: Option<&str>| {
+            version
+                .filter(|version| !version.is_empty())
+                .map(|version| format!(" {version}"))
+                .unwrap_or_default()
+        };
+        match (self.from.as_deref(), self.to.as_deref()) {
+            (Some(from), Some(to)) => write!(f, "bumps {} {from} → {to}", self.name)?,
+            (None, to) => write!(f, "adds {}{}", self.name, version(to))?,
+            (from, None) => write!(f, "removes {}{}", self.name, version(from))?,
+        }
+        match self.kind {
+            DependencyKind::Normal => Ok(()),
+            DependencyKind::Dev => f.write_str(" (dev)"),
+            DependencyKind::Build => f.write_str(" (build)"),
+        }
+    }
+}
+
+/// Where in a manifest a diff line is
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Scope {
+    /// Not seen yet, as at the start of a hunk
+    Unknown,
+    /// A section without dependencies
+    Other,
+    /// A table of `name = version` entries
+    Table(DependencyKind),
+    /// A table for one dependency, as `[dependencies.serde]`
+    Single(DependencyKind, String),
+    /// An array of requirement strings; `outer` is the scope it is in
+    Array(DependencyKind, Box<Scope>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Toml,
+    Json,
+    Requirements,
+    GoMod,
+}
+
+/// The kind of dependencies a TOML section or array key holds, if any
+fn toml_kind(key: &str) -> Option<DependencyKind> {
+    let key = key.trim().trim_matches(['"', '\'']);
+    if key.contains("dev-dependencies")
+        || key.contains("group.dev")
+        || key == "dev"
+        || key == "test"
+    {
+        Some(DependencyKind::Dev)
+    } else if key.contains("build-dependencies") || key == "build-system" || key == "requires" {
+        Some(DependencyKind::Build)
+    } else if key.ends_with("dependencies") || key == "optional-dependencies" {
+        Some(DependencyKind::Normal)
+    } else {
+        None
+    }
+}
+
+/// The scope a TOML header opens
+fn toml_scope(header: &str) -> Scope {
+    // `[dependencies.serde]` and `[target.'cfg(unix)'.dependencies.libc]`
+    for marker in ["dev-dependencies.", "build-dependencies.", "dependencies."] {
+        if let Some(at) = header.rfind(marker)
+            && let Some(kind) = toml_kind(&header[..at + marker.len() - 1])
+        {
+            return Scope::Single(kind, header[at + marker.len()..].to_string());
+        }
+    }
+    match toml_kind(header) {
+        Some(kind) => Scope::Table(kind),
+        // `[project]` and `[build-system]` hold arrays of requirements
+        None if header == "project" || header == "build-system" => Scope::Unknown,
+        None => Scope::Other,
+    }
+}
+
+/// The version in the value of a TOML dependency entry
+fn toml_version(value: &str, workspace: bool) -> Option<String> {
+    let value = value.trim();
+    if workspace || value.contains("workspace = true") {
+        return Some("(workspace)".to_string());
+    }
+    if let Some(quoted) = value.strip_prefix('"') {
+        return quoted.split('"').next().map(ToString::to_string);
+    }
+    TOML_VERSION
+        .captures(value)
+        .map(|captures| captures[1].to_string())
+}
+
+fn dependency_name(name: &str) -> bool {
+    !NOT_DEPENDENCIES.contains(&name)
+}
+
+/// Reads one line of a manifest, moving the scope and returning the dependency it names
+fn read_line(
+    format: Format,
+    scope: &mut Scope,
+    line: &str,
+) -> Option<(String, DependencyKind, String)> {
+    let trimmed = line.trim();
+    match format {
+        Format::Requirements => {
+            if trimmed.starts_with(['#', '-']) {
+                return None;
+            }
+            let captures = REQUIREMENT_LINE.captures(trimmed)?;
+            Some((
+                captures[1].to_string(),
+                DependencyKind::Normal,
+                captures[2].trim().to_string(),
+            ))
+        }
+        Format::GoMod => {
+            if trimmed.starts_with("require (") {
+                *scope = Scope::Table(DependencyKind::Normal);
+                return None;
+            }
+            if trimmed == ")" {
+                *scope = Scope::Other;
+                return None;
+            }
+            let captures = GO_ENTRY.captures(line)?;
+            Some((
+                captures[1].to_string(),
+                DependencyKind::Normal,
+                captures[2].to_string(),
+            ))
+        }
+        Format::Json => {
+            if let Some(captures) = JSON_OBJECT.captures(line) {
+                *scope = match &captures[1] {
+                    "dependencies" | "peerDependencies" | "optionalDependencies" | "require" => {
+                        Scope::Table(DependencyKind::Normal)
+                    }
+                    "devDependencies" | "require-dev" => Scope::Table(DependencyKind::Dev),
+                    _ => Scope::Other,
+                };
+                return None;
+            }
+            if trimmed.starts_with('}') {
+                *scope = Scope::Unknown;
+                return None;
+            }
+            let kind = match scope {
+                Scope::Table(kind) => *kind,
+                Scope::Unknown => DependencyKind::Normal,
+                _ => return None,
+            };
+            let captures = JSON_ENTRY.captures(line)?;
+            let name = captures[1].to_string();
+            dependency_name(&name).then(|| (name, kind, captures[2].to_string()))
+        }
+        Format::Toml => read_toml_line(scope, line),
+    }
+}
+
+fn read_toml_line(scope: &mut Scope, line: &str) -> Option<(String, DependencyKind, String)> {
+    let trimmed = line.trim();
+    if let Some(captures) = TOML_HEADER.captures(line) {
+        *scope = toml_scope(captures[1].trim());
+        return None;
+    }
+    if let Scope::Array(kind, outer) = scope {
+        if trimmed.starts_with(']') {
+            *scope = (**outer).clone();
+            return None;
+        }
+        let kind = *kind;
+        let captures = REQUIREMENT_STRING.captures(line)?;
+        return Some((
+            captures[1].to_string(),
+            kind,
+            captures[2].trim().to_string(),
+        ));
+    }
+    if let Some(captures) = TOML_ARRAY.captures(line)
+        && let Some(kind) = toml_kind(&captures[1])
+        && !matches!(scope, Scope::Other)
+    {
+        // One-line arrays are not read
+        if !line.contains(']') {
+            *scope = Scope::Array(kind, Box::new(scope.clone()));
+        }
+        return None;
+    }
+    match scope {
+        Scope::Single(kind, name) => {
+            let version = TOML_VERSION.captures(line)?;
+            Some((name.clone(), *kind, version[1].to_string()))
+        }
+        Scope::Table(kind) => {
+            let captures = TOML_ENTRY.captures(line)?;
+            let version = toml_version(&captures[3], captures.get(2).is_some()).unwrap_or_default();
+            Some((captures[1].to_string(), *kind, version))
+        }
+        Scope::Unknown => {
+            let captures = TOML_ENTRY.captures(line)?;
+            let name = captures[1].to_string();
+            let version = toml_version(&captures[3], captures.get(2).is_some())?;
+            (dependency_name(&name) && version.starts_with(|c: char| c.is_ascii_digit()))
+                .then_some((name, DependencyKind::Normal, version))
+        }
+        _ => None,
+    }
+}
+
+/// Dependencies a manifest adds, removes or bumps, read from its diff
+pub fn dependency_changes(file: &StagedFile) -> Vec<DependencyChange> {
+    let name = Path::new(&file.path)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or_default();
+    let format = match name {
+        "package.json" | "composer.json" => Format::Json,
+        "requirements.txt" => Format::Requirements,
+        "go.mod" => Format::GoMod,
+        _ => Format::Toml,
+    };
+
+    let mut changes: Vec<DependencyChange> = Vec::new();
+    let mut scope = Scope::Unknown;
+    for line in file.diff.lines() {
+        if line.starts_with("+++") || line.starts_with("---") {
+            continue;
+        }
+        if line.trim_start().starts_with("@@") {
+            scope = Scope::Unknown;
+            continue;
+        }
+        let (origin, text) = line.split_at(line.chars().next().map_or(0, char::len_utf8));
+        let Some((name, kind, version)) = read_line(format, &mut scope, text) else {
+            continue;
+        };
+        let side = match origin {
+            "+" => true,
+            "-" => false,
+            _ => continue,
+        };
+        let index = changes
+            .iter()
+            .position(|change| change.name == name && change.kind == kind)
+            .unwrap_or_else(|| {
+                changes.push(DependencyChange {
+                    name,
+                    kind,
+                    from: None,
+                    to: None,
+                });
+                changes.len() - 1
+            });
+        let slot = if side {
+            &mut changes[index].to
+        } else {
+            &mut changes[index].from
+        };
+        slot.get_or_insert(version);
+    }
+    // Lines that moved or only changed formatting
+    changes.retain(|change| change.from != change.to);
+    changes
+}
+
+/// Dependencies a package manifest adds, removes or bumps
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ManifestAnalyzer;
+
+impl ManifestAnalyzer {
+    /// Name its notes carry, which prompts list as dependencies
+    pub const NAME: &'static str = "manifest";
+
+    /// Manifests it reads
+    pub const PATTERNS: &'static [&'static str] = &[
+        "Cargo.toml",
+        "package.json",
+        "pyproject.toml",
+        "requirements.txt",
+        "go.mod",
+        "composer.json",
+    ];
+}
+
+impl FileAnalyzer for ManifestAnalyzer {
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn analyze(&self, file: &StagedFile) -> Vec<String> {
+        dependency_changes(file)
+            .iter()
+            .map(ToString::to_string)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::context::ChangeType;
+
+    fn notes(path: &str, diff: &str) -> Vec<String> {
+        ManifestAnalyzer.analyze(&StagedFile {
+            path: path.to_string(),
+            change_type: ChangeType::Modified,
+            diff: diff.to_string(),
+            content: None,
+            content_excluded: false,
+        })
+    }
+
+    #[test]
+    fn test_cargo_manifest_notes() {
+        let diff = "\
+@@ -1,12 +1,13 @@
+-version = \"0.1.0\"
++version = \"0.2.0\"
+ edition = \"2021\"
+ [dependencies]
+-serde = \"1.0.1\"
++serde = \"1.0.2\"
++tokio = { version = \"1.38\", features = [\"full\"] }
+-log = \"0.4\"
+ anyhow.workspace = true
++regex.workspace = true
+ [dev-dependencies]
++insta = \"1.34\"
+ [build-dependencies.cc]
+-version = \"1.0.83\"
++version = \"1.0.90\"
+";
+        assert_eq!(
+            notes("crates/cli/Cargo.toml", diff),
+            [
+                "bumps serde 1.0.1 → 1.0.2",
+                "adds tokio 1.38",
+                "removes log 0.4",
+                "adds regex (workspace)",
+                "adds insta 1.34 (dev)",
+                "bumps cc 1.0.83 → 1.0.90 (build)",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_other_manifest_notes() {
+        let package = "\
+@@ -2,9 +2,9 @@
+-  \"version\": \"1.0.0\",
++  \"version\": \"1.1.0\",
+   \"scripts\": {
+-    \"test\": \"jest 1\"
++    \"test\": \"vitest 2\"
+   },
+   \"dependencies\": {
+-    \"react\": \"^18.2.0\"
++    \"react\": \"^18.3.1\"
+   },
+   \"devDependencies\": {
++    \"vitest\": \"^2.0.0\"
+";
+        assert_eq!(
+            notes("web/package.json", package),
+            ["bumps react ^18.2.0 → ^18.3.1", "adds vitest ^2.0.0 (dev)"]
+        );
+
+        let pyproject = "\
+ [project]
+ dependencies = [
+-  \"requests>=2.31\",
++  \"requests>=2.32\",
++  \"httpx[http2]\",
+ ]
+ [project.optional-dependencies]
+ dev = [
++  \"pytest>=8\",
+ ]
+";
+        assert_eq!(
+            notes("pyproject.toml", pyproject),
+            [
+                "bumps requests >=2.31 → >=2.32",
+                "adds httpx",
+                "adds pytest >=8 (dev)",
+            ]
+        );
+
+        let go_mod = "\
+ require (
+-\tgithub.com/gin-gonic/gin v1.9.1
++\tgithub.com/gin-gonic/gin v1.10.0
+ )
+-go 1.21
++go 1.22
+";
+        assert_eq!(
+            notes("go.mod", go_mod),
+            ["bumps github.com/gin-gonic/gin v1.9.1 → v1.10.0"]
+        );
+        assert_eq!(
+            notes(
+                "requirements.txt",
+                "-flask==2.3.0\n+flask==3.0.0\n+# pinned\n"
+            ),
+            ["bumps flask ==2.3.0 → ==3.0.0"]
+        );
+    }
+}
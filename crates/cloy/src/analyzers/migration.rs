@@ -0,0 +1,346 @@
+use super::{FileAnalyzer, push_unique};
+use crate::llm::context::StagedFile;
+use regex::Regex;
+use std::path::Path;
+use std::sync::LazyLock;
+
+/// A table, index or column name, possibly schema-qualified and quoted
+const NAME: &str = r#"([\w."`\[\]]+)"#;
+
+/// Markers that start the down section of SQL migrations (golang-migrate, goose, dbmate)
+const DOWN_MARKERS: &[&str] = &["-- +migrate down", "-- +goose down", "-- migrate:down"];
+
+static CREATE_TABLE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(&format!(
+        r"(?i)^CREATE\s+(?:TEMP(?:ORARY)?\s+)?TABLE\s+(?:IF\s+NOT\s+EXISTS\s+)?{NAME}"
+    ))
+    .expect("Valid regex")
+});
+
+static DROP_TABLE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(&format!(r"(?i)^DROP\s+TABLE\s+(?:IF\s+EXISTS\s+)?{NAME}")).expect("Valid regex")
+});
+
+static CREATE_INDEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(&format!(
+        r"(?i)^CREATE\s+(UNIQUE\s+)?INDEX\s+(?:CONCURRENTLY\s+)?(?:IF\s+NOT\s+EXISTS\s+)?{NAME}\s+ON\s+(?:ONLY\s+)?{NAME}"
+    ))
+    .expect("Valid regex")
+});
+
+static DROP_INDEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(&format!(
+        r"(?i)^DROP\s+INDEX\s+(?:CONCURRENTLY\s+)?(?:IF\s+EXISTS\s+)?{NAME}"
+    ))
+    .expect("Valid regex")
+});
+
+static ALTER_TABLE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(&format!(
+        r"(?i)^ALTER\s+TABLE\s+(?:IF\s+EXISTS\s+)?(?:ONLY\s+)?{NAME}\s+(.*)$"
+    ))
+    .expect("Valid regex")
+});
+
+/// One action of an `ALTER TABLE`
+static ALTER_ACTION: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r#"(?i)^(ADD|DROP|ALTER|MODIFY|RENAME)\s+(?:(COLUMN|CONSTRAINT|INDEX|KEY|PRIMARY\s+KEY|FOREIGN\s+KEY|TO)\s+)?(?:IF\s+(?:NOT\s+)?EXISTS\s+)?([\w"`\[\]]+)?"#,
+    )
+    .expect("Valid regex")
+});
+
+/// Schema calls of Rails migrations
+static RAILS_CALL: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"^\s*(create_table|drop_table|rename_table|add_column|remove_column|change_column|rename_column|add_index|remove_index|add_reference|remove_reference)\s*\(?\s*:(\w+)(?:\s*,\s*:(\w+))?",
+    )
+    .expect("Valid regex")
+});
+
+/// Schema changes a database migration makes, and whether it can be rolled back
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MigrationAnalyzer;
+
+impl MigrationAnalyzer {
+    /// Migration directories it reads, by the conventions of common migration tools
+    pub const PATTERNS: &'static [&'static str] = &[
+        "**/migrations/**/*.sql",
+        "**/migrate/**/*.sql",
+        "**/db/migrate/*.rb",
+    ];
+}
+
+fn unquote(name: &str) -> String {
+    name.replace(['"', '`', '[', ']'], "")
+}
+
+/// What a SQL statement does to the schema, and whether it loses data
+fn sql_operations(statement: &str) -> Vec<(String, bool)> {
+    if let Some(captures) = CREATE_TABLE.captures(statement) {
+        return vec![(format!("creates table {}", unquote(&captures[1])), false)];
+    }
+    if let Some(captures) = DROP_TABLE.captures(statement) {
+        return vec![(format!("drops table {}", unquote(&captures[1])), true)];
+    }
+    if let Some(captures) = CREATE_INDEX.captures(statement) {
+        let unique = if captures.get(1).is_some() {
+            "unique "
+        } else {
+            ""
+        };
+        let (index, table) = (unquote(&captures[2]), unquote(&captures[3]));
+        return vec![(format!("creates {unique}index {index} on {table}"), false)];
+    }
+    if let Some(captures) = DROP_INDEX.captures(statement) {
+        return vec![(format!("drops index {}", unquote(&captures[1])), false)];
+    }
+    let Some(captures) = ALTER_TABLE.captures(statement) else {
+        return Vec::new();
+    };
+    let table = unquote(&captures[1]);
+    captures[2]
+        .split(',')
+        .filter_map(|action| ALTER_ACTION.captures(action.trim()))
+        .map(|action| {
+            let verb = action[1].to_uppercase();
+            let kind = action
+                .get(2)
+                .map(|kind| kind.as_str().to_lowercase())
+                .unwrap_or_default();
+            let name = action.get(3).map(|name| unquote(name.as_str()));
+            let target = match (kind.as_str(), &name) {
+                ("constraint" | "index" | "key", Some(name)) => format!("{kind} {name} on {table}"),
+                (kind, _) if kind.contains("key") => format!("{kind} on {table}"),
+                (_, Some(name)) => format!("column {table}.{name}"),
+                (_, None) => format!("table {table}"),
+            };
+            match verb.as_str() {
+                "ADD" => (format!("adds {target}"), false),
+                "DROP" => (
+                    format!("drops {target}"),
+                    kind.is_empty() || kind == "column",
+                ),
+                "RENAME" if kind == "to" => (
+                    format!("renames table {table} to {}", name.unwrap_or_default()),
+                    false,
+                ),
+                "RENAME" => (format!("renames {target}"), false),
+                _ => (format!("alters {target}"), false),
+            }
+        })
+        .collect()
+}
+
+/// What a Rails schema call does, and whether it loses data
+fn rails_operation(line: &str) -> Option<(String, bool)> {
+    let captures = RAILS_CALL.captures(line)?;
+    let table = &captures[2];
+    let column = captures.get(3).map_or("", |column| column.as_str());
+    Some(match &captures[1] {
+        "create_table" => (format!("creates table {table}"), false),
+        "drop_table" => (format!("drops table {table}"), true),
+        "rename_table" => (format!("renames table {table} to {column}"), false),
+        "add_column" => (format!("adds column {table}.{column}"), false),
+        "remove_column" => (format!("drops column {table}.{column}"), true),
+        "change_column" => (format!("alters column {table}.{column}"), false),
+        "rename_column" => (format!("renames column {table}.{column}"), false),
+        "add_index" => (format!("creates index on {table}"), false),
+        "remove_index" => (format!("drops index on {table}"), false),
+        "add_reference" => (format!("adds reference {table}.{column}"), false),
+        _ => (format!("drops reference {table}.{column}"), true),
+    })
+}
+
+impl FileAnalyzer for MigrationAnalyzer {
+    fn name(&self) -> &'static str {
+        "migration"
+    }
+
+    fn analyze(&self, file: &StagedFile) -> Vec<String> {
+        let rails = Path::new(&file.path)
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("rb"));
+        let mut notes = Vec::new();
+        let mut destructive = false;
+        // Whether lines are in the down section, and whether the file has one
+        let mut in_down = false;
+        let mut has_down = false;
+        let mut statement = String::new();
+        let mut record = |operation: (String, bool), notes: &mut Vec<String>| {
+            destructive |= operation.1;
+            push_unique(notes, operation.0);
+        };
+
+        // Context lines place the added ones in the up or down section
+        let lines = file
+            .diff
+            .lines()
+            .filter(|line| !line.starts_with("+++"))
+            .filter_map(|line| {
+                let origin = line.chars().next()?;
+                matches!(origin, '+' | ' ').then(|| (origin, &line[1..]))
+            });
+        for (origin, line) in lines {
+            let trimmed = line.trim();
+            if trimmed.is_empty() {
+                continue;
+            }
+            let lower = trimmed.to_lowercase();
+            if DOWN_MARKERS.iter().any(|marker| lower.starts_with(marker))
+                || lower.starts_with("def down")
+            {
+                in_down = true;
+                has_down = true;
+            } else if lower.starts_with("def change") {
+                // Rails rolls `change` back by inverting each call
+                has_down = true;
+            } else if lower.starts_with("def up") {
+                in_down = false;
+            }
+            if in_down || origin != '+' {
+                continue;
+            }
+            if rails {
+                if let Some(operation) = rails_operation(trimmed) {
+                    record(operation, &mut notes);
+                }
+                continue;
+            }
+            if trimmed.starts_with("--") {
+                continue;
+            }
+            // Statements may span lines; they end at `;`
+            statement.push(' ');
+            statement.push_str(trimmed);
+            while let Some(end) = statement.find(';') {
+                for operation in sql_operations(statement[..end].trim()) {
+                    record(operation, &mut notes);
+                }
+                statement.drain(..=end);
+            }
+        }
+        for operation in sql_operations(statement.trim()) {
+            record(operation, &mut notes);
+        }
+        if notes.is_empty() {
+            return notes;
+        }
+
+        let name = file.path.rsplit('/').next().unwrap_or(&file.path);
+        // A down section outside the hunks is still in the file
+        let has_down = has_down
+            || file.content.as_deref().is_some_and(|content| {
+                content.lines().any(|line| {
+                    let lower = line.trim().to_lowercase();
+                    DOWN_MARKERS.iter().any(|marker| lower.starts_with(marker))
+                        || lower.starts_with("def down")
+                        || lower.starts_with("def change")
+                })
+            });
+        let reversible = has_down && !file.diff.contains("IrreversibleMigration");
+        notes.push(if name.contains(".down.") || name.contains("_down.") {
+            "down migration: rolls back its up migration".to_string()
+        } else if reversible {
+            "reversible: has a down migration".to_string()
+        } else if name.contains(".up.") || name.contains("_up.") {
+            "reversible only with its matching down migration".to_string()
+        } else {
+            "irreversible: no down migration".to_string()
+        });
+        if destructive {
+            notes.push("destructive: drops data that a rollback does not restore".to_string());
+        }
+        notes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::context::ChangeType;
+
+    fn added(path: &str, text: &str) -> StagedFile {
+        StagedFile {
+            path: path.to_string(),
+            change_type: ChangeType::Added,
+            diff: text.lines().flat_map(|line| ["+", line, "\n"]).collect(),
+            content: None,
+            content_excluded: false,
+        }
+    }
+
+    #[test]
+    fn test_sql_migration_notes() {
+        let sql = "\
+-- +goose Up
+CREATE TABLE IF NOT EXISTS \"users\" (
+  id bigint PRIMARY KEY
+);
+ALTER TABLE users ADD COLUMN email text NOT NULL, DROP COLUMN legacy_name;
+CREATE UNIQUE INDEX users_email_idx ON users (email);
+-- +goose Down
+DROP TABLE users;
+";
+        assert_eq!(
+            MigrationAnalyzer.analyze(&added("db/migrations/0004_users.sql", sql)),
+            [
+                "creates table users",
+                "adds column users.email",
+                "drops column users.legacy_name",
+                "creates unique index users_email_idx on users",
+                "reversible: has a down migration",
+                "destructive: drops data that a rollback does not restore",
+            ]
+        );
+        assert_eq!(
+            MigrationAnalyzer.analyze(&added(
+                "migrations/2_orders.up.sql",
+                "DROP INDEX orders_idx;"
+            )),
+            [
+                "drops index orders_idx",
+                "reversible only with its matching down migration"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rails_migration_notes() {
+        let rb = "\
+class AddEmailToUsers < ActiveRecord::Migration[7.1]
+  def change
+    add_column :users, :email, :string
+    add_index :users, :email
+  end
+end
+";
+        assert_eq!(
+            MigrationAnalyzer.analyze(&added("db/migrate/20240101_add_email.rb", rb)),
+            [
+                "adds column users.email",
+                "creates index on users",
+                "reversible: has a down migration",
+            ]
+        );
+
+        // Editing the up section of a migration whose down section is context
+        let edited = StagedFile {
+            change_type: ChangeType::Modified,
+            diff: "@@ -1,5 +1,6 @@\n class AddEmailToUsers < ActiveRecord::Migration[7.1]\n   def up\n+    add_column :users, :email, :string\n   end\n   def down\n+    remove_column :users, :email\n"
+                .to_string(),
+            ..added("db/migrate/20240101_add_email.rb", "")
+        };
+        assert_eq!(
+            MigrationAnalyzer.analyze(&edited),
+            [
+                "adds column users.email",
+                "reversible: has a down migration"
+            ]
+        );
+
+        let registry = super::super::AnalyzerRegistry::with_builtins();
+        assert!(registry.analyzer_for("app/models/user.rb").is_none());
+        assert!(registry.analyzer_for("schema.sql").is_none());
+    }
+}
@@ -0,0 +1,321 @@
+//! Format-specific notes on changed files.
+//!
+//! A diff of a manifest or a schema says more to a reader who knows the
+//! format: which dependency moved, which column was dropped. An analyzer
+//! reads one kind of file and turns its diff into short notes for the prompt.
+//! Analyzers are picked by gitignore-style patterns, and the last registered
+//! pattern matching a path wins, as in CODEOWNERS, so tools embedding gitai
+//! can add their own formats with [`register_analyzer`] or replace a built-in
+//! one for a pattern.
+
+mod definitions;
+mod dockerfile;
+mod graphql;
+mod manifest;
+mod migration;
+mod openapi;
+mod terraform;
+
+pub(crate) use definitions::{Language, defined_traits, implemented_traits};
+pub use dockerfile::DockerfileAnalyzer;
+pub use graphql::GraphQlAnalyzer;
+pub use manifest::{DependencyChange, DependencyKind, ManifestAnalyzer, dependency_changes};
+pub use migration::MigrationAnalyzer;
+pub use openapi::OpenApiAnalyzer;
+pub use terraform::TerraformAnalyzer;
+
+use crate::git::pattern_regex;
+use crate::llm::api_changes::{ApiChange, ApiChangeKind};
+use crate::llm::context::{CommitContext, StagedFile};
+use anyhow::{Result, anyhow};
+use log::debug;
+use parking_lot::RwLock;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::{Arc, LazyLock};
+
+/// Notes kept per file; the rest are counted
+pub const MAX_NOTES_PER_FILE: usize = 10;
+
+/// Reads the changes to one kind of file
+pub trait FileAnalyzer: Send + Sync {
+    /// Short name shown with the notes, such as `manifest`
+    fn name(&self) -> &'static str;
+
+    /// Notes on what the file's diff changes; empty when nothing stands out
+    fn analyze(&self, file: &StagedFile) -> Vec<String>;
+}
+
+/// Notes an analyzer made on a changed file
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct FileAnalysis {
+    pub path: String,
+    pub analyzer: String,
+    pub notes: Vec<String>,
+}
+
+impl fmt::Display for FileAnalysis {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({}):", self.path, self.analyzer)?;
+        for note in self.notes.iter().take(MAX_NOTES_PER_FILE) {
+            write!(f, "\n  - {note}")?;
+        }
+        if self.notes.len() > MAX_NOTES_PER_FILE {
+            write!(
+                f,
+                "\n  - and {} more",
+                self.notes.len() - MAX_NOTES_PER_FILE
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Analyzers by path pattern
+#[derive(Default, Clone)]
+pub struct AnalyzerRegistry {
+    entries: Vec<(String, Regex, Arc<dyn FileAnalyzer>)>,
+}
+
+impl fmt::Debug for AnalyzerRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map()
+            .entries(
+                self.entries
+                    .iter()
+                    .map(|(pattern, _, analyzer)| (pattern, analyzer.name())),
+            )
+            .finish()
+    }
+}
+
+impl AnalyzerRegistry {
+    /// A registry without analyzers
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A registry with gitai's own analyzers
+    pub fn with_builtins() -> Self {
+        let mut registry = Self::new();
+        registry.builtin(ManifestAnalyzer::PATTERNS, ManifestAnalyzer);
+        registry.builtin(TerraformAnalyzer::PATTERNS, TerraformAnalyzer);
+        registry.builtin(DockerfileAnalyzer::PATTERNS, DockerfileAnalyzer);
+        registry.builtin(MigrationAnalyzer::PATTERNS, MigrationAnalyzer);
+        registry.builtin(OpenApiAnalyzer::PATTERNS, OpenApiAnalyzer);
+        registry.builtin(GraphQlAnalyzer::PATTERNS, GraphQlAnalyzer);
+        registry
+    }
+
+    fn builtin(&mut self, patterns: &[&str], analyzer: impl FileAnalyzer + 'static) {
+        let analyzer: Arc<dyn FileAnalyzer> = Arc::new(analyzer);
+        for pattern in patterns {
+            self.entries.push((
+                (*pattern).to_string(),
+                pattern_regex(pattern).expect("Valid built-in pattern"),
+                Arc::clone(&analyzer),
+            ));
+        }
+    }
+
+    /// Analyzes paths matching the gitignore-style `pattern` with `analyzer`,
+    /// ahead of every analyzer registered before
+    pub fn register(&mut self, pattern: &str, analyzer: Arc<dyn FileAnalyzer>) -> Result<()> {
+        let regex =
+            pattern_regex(pattern).ok_or_else(|| anyhow!("Invalid analyzer pattern: {pattern}"))?;
+        self.entries.push((pattern.to_string(), regex, analyzer));
+        Ok(())
+    }
+
+    /// The analyzer for `path`: the last registered one whose pattern matches
+    pub fn analyzer_for(&self, path: &str) -> Option<&Arc<dyn FileAnalyzer>> {
+        self.entries
+            .iter()
+            .rev()
+            .find(|(_, regex, _)| regex.is_match(path))
+            .map(|(_, _, analyzer)| analyzer)
+    }
+
+    /// The notes of each file an analyzer has something to say about
+    pub fn analyze(&self, files: &[StagedFile]) -> Vec<FileAnalysis> {
+        files
+            .iter()
+            .filter_map(|file| {
+                let analyzer = self.analyzer_for(&file.path)?;
+                let notes = analyzer.analyze(file);
+                (!notes.is_empty()).then(|| FileAnalysis {
+                    path: file.path.clone(),
+                    analyzer: analyzer.name().to_string(),
+                    notes,
+                })
+            })
+            .collect()
+    }
+}
+
+/// A change to the API contract a schema file describes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum ContractChange {
+    /// A change clients keep working through, such as a new endpoint
+    Compatible(String),
+    /// A change that breaks clients
+    Breaking(ApiChange),
+}
+
+impl ContractChange {
+    fn note(&self) -> String {
+        match self {
+            Self::Compatible(note) => note.clone(),
+            Self::Breaking(change) => match (&change.kind, &change.after) {
+                (ApiChangeKind::Removed, _) | (_, None) => {
+                    format!("removes {} (breaking)", change.item)
+                }
+                (_, Some(after)) => format!(
+                    "narrows {}: {} → {after} (breaking)",
+                    change.item, change.before
+                ),
+            },
+        }
+    }
+}
+
+/// The added (`+`) and removed (`-`) lines of a diff, without their marker
+pub(crate) fn changed_lines(diff: &str) -> impl Iterator<Item = (char, &str)> {
+    diff.lines()
+        .filter(|line| !line.starts_with("+++") && !line.starts_with("---"))
+        .filter_map(|line| {
+            let origin = line.chars().next()?;
+            matches!(origin, '+' | '-').then(|| (origin, &line[1..]))
+        })
+}
+
+/// For a hunk header such as `@@ -3,4 +5,6 @@`, the lines of the new file
+/// before the hunk, so parsers tracking nesting know where the hunk sits;
+/// empty when the file's content is not known, and `None` for other lines
+pub(crate) fn lines_before_hunk<'a>(file: &'a StagedFile, line: &str) -> Option<Vec<&'a str>> {
+    let header = line.trim_start().strip_prefix("@@ ")?;
+    let start: usize = header
+        .split_whitespace()
+        .find_map(|range| range.strip_prefix('+'))
+        .and_then(|range| range.split(',').next())
+        .and_then(|start| start.parse().ok())
+        .unwrap_or(1);
+    Some(file.content.as_deref().map_or_else(Vec::new, |content| {
+        content.lines().take(start.saturating_sub(1)).collect()
+    }))
+}
+
+/// Adds `item` to `list` unless it is there
+pub(crate) fn push_unique(list: &mut Vec<String>, item: String) {
+    if !list.contains(&item) {
+        list.push(item);
+    }
+}
+
+/// The registry prompts are built with
+static REGISTRY: LazyLock<RwLock<AnalyzerRegistry>> =
+    LazyLock::new(|| RwLock::new(AnalyzerRegistry::with_builtins()));
+
+/// Registers `analyzer` for `pattern` in the registry prompts are built with
+pub fn register_analyzer(pattern: &str, analyzer: Arc<dyn FileAnalyzer>) -> Result<()> {
+    REGISTRY.write().register(pattern, analyzer)
+}
+
+/// Changes to an API contract in `file` that break clients, when it is a
+/// schema read by a built-in analyzer
+pub fn contract_breaks(file: &StagedFile) -> Vec<ApiChange> {
+    let changes = match REGISTRY.read().analyzer_for(&file.path).map(|a| a.name()) {
+        Some(OpenApiAnalyzer::NAME) => openapi::contract_changes(file),
+        Some(GraphQlAnalyzer::NAME) => graphql::contract_changes(file),
+        _ => return Vec::new(),
+    };
+    changes
+        .into_iter()
+        .filter_map(|change| match change {
+            ContractChange::Breaking(change) => Some(change),
+            ContractChange::Compatible(_) => None,
+        })
+        .collect()
+}
+
+/// Analyzes the context's staged files for the prompt
+pub fn attach_analyses(context: &mut CommitContext) {
+    context.analyses = REGISTRY.read().analyze(&context.staged_files);
+    debug!("Analyzed {} changed files", context.analyses.len());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::context::ChangeType;
+
+    struct Proto;
+
+    impl FileAnalyzer for Proto {
+        fn name(&self) -> &'static str {
+            "proto"
+        }
+
+        fn analyze(&self, file: &StagedFile) -> Vec<String> {
+            file.diff
+                .lines()
+                .filter_map(|line| line.strip_prefix("+message "))
+                .map(|name| format!("adds message {}", name.trim_end_matches(" {")))
+                .collect()
+        }
+    }
+
+    fn file(path: &str, diff: &str) -> StagedFile {
+        StagedFile {
+            path: path.to_string(),
+            change_type: ChangeType::Modified,
+            diff: diff.to_string(),
+            content: None,
+            content_excluded: false,
+        }
+    }
+
+    #[test]
+    fn test_registered_analyzers_win() {
+        let mut registry = AnalyzerRegistry::with_builtins();
+        assert!(registry.analyzer_for("api/user.proto").is_none());
+        assert_eq!(
+            registry
+                .analyzer_for("crates/cli/Cargo.toml")
+                .map(|analyzer| analyzer.name()),
+            Some("manifest")
+        );
+
+        registry
+            .register("*.proto", Arc::new(Proto))
+            .expect("Valid pattern");
+        registry
+            .register("Cargo.toml", Arc::new(Proto))
+            .expect("Valid pattern");
+        assert!(registry.register("/", Arc::new(Proto)).is_err());
+        assert_eq!(
+            registry
+                .analyzer_for("Cargo.toml")
+                .map(|analyzer| analyzer.name()),
+            Some("proto")
+        );
+
+        let analyses = registry.analyze(&[
+            file("api/user.proto", "+message User {\n+  string id = 1;\n"),
+            file("src/lib.rs", "+fn main() {}\n"),
+        ]);
+        assert_eq!(
+            analyses,
+            [FileAnalysis {
+                path: "api/user.proto".to_string(),
+                analyzer: "proto".to_string(),
+                notes: vec!["adds message User".to_string()],
+            }]
+        );
+        assert_eq!(
+            analyses[0].to_string(),
+            "api/user.proto (proto):\n  - adds message User"
+        );
+    }
+}
@@ -0,0 +1,325 @@
+use super::{ContractChange, FileAnalyzer, lines_before_hunk};
+use crate::llm::api_changes::{ApiChange, ApiChangeKind};
+use crate::llm::context::StagedFile;
+use std::collections::HashMap;
+
+/// Operation keys of a path item
+const METHODS: &[&str] = &[
+    "get", "put", "post", "delete", "patch", "head", "options", "trace",
+];
+
+/// Endpoints and schemas an API description adds or removes, and the types it narrows
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OpenApiAnalyzer;
+
+impl OpenApiAnalyzer {
+    pub const NAME: &'static str = "openapi";
+
+    /// Files it reads; JSON documents are left to the diff
+    pub const PATTERNS: &'static [&'static str] = &[
+        "openapi.yaml",
+        "openapi.yml",
+        "*.openapi.yaml",
+        "*.openapi.yml",
+        "swagger.yaml",
+        "swagger.yml",
+    ];
+}
+
+/// A YAML mapping key, or a list item, with the column it starts at
+#[derive(Debug, Clone)]
+struct Entry {
+    indent: usize,
+    /// The key, or `-` for a list item
+    key: String,
+    /// The scalar after the key, or a list item's `name:` or scalar
+    value: Option<String>,
+}
+
+fn unquote(text: &str) -> String {
+    text.trim().trim_matches(['"', '\'']).to_string()
+}
+
+/// The key and value of `key: value`, if the text is a mapping entry
+fn key_value(text: &str) -> Option<(String, Option<String>)> {
+    let (key, value) = match text.split_once(": ") {
+        Some((key, value)) => (key, Some(value)),
+        None => (text.strip_suffix(':')?, None),
+    };
+    let value = value
+        .map(|value| value.split(" #").next().unwrap_or(value))
+        .map(unquote)
+        .filter(|value| !value.is_empty());
+    Some((unquote(key), value))
+}
+
+/// The nesting of keys down to the line last read, as far as the diff shows it
+#[derive(Debug, Default)]
+struct Path {
+    entries: Vec<Entry>,
+}
+
+impl Path {
+    /// Moves to the line `text`; false when it is not a key or list item
+    fn enter(&mut self, text: &str) -> bool {
+        let content = text.trim_start();
+        if content.is_empty() || content.starts_with('#') {
+            return false;
+        }
+        let indent = text.len() - content.len();
+        let (item, rest) = match content.strip_prefix("- ") {
+            Some(rest) => (true, rest.trim_start()),
+            None if content == "-" => (true, ""),
+            None => (false, content),
+        };
+        let pair = key_value(rest);
+        if !item && pair.is_none() {
+            return false;
+        }
+        self.entries.retain(|entry| entry.indent < indent);
+        if item {
+            let value = match &pair {
+                Some((key, value)) if key == "name" => value.clone(),
+                Some(_) => None,
+                None => Some(unquote(rest)).filter(|value| !value.is_empty()),
+            };
+            self.entries.push(Entry {
+                indent,
+                key: "-".to_string(),
+                value,
+            });
+        }
+        if let Some((key, value)) = pair {
+            let indent = indent + content.len() - rest.len();
+            self.entries.push(Entry { indent, key, value });
+        }
+        true
+    }
+
+    fn key(&self, depth: usize) -> Option<&str> {
+        self.entries.get(depth).map(|entry| entry.key.as_str())
+    }
+
+    /// The endpoint or schema the line is in, and whether the line names it
+    fn scope(&self) -> Option<(String, bool)> {
+        let named = self.entries.len() == 3;
+        match (self.key(0)?, self.key(1)?, self.key(2)?) {
+            ("paths", path, method) if path.starts_with('/') && METHODS.contains(&method) => {
+                Some((format!("endpoint {} {path}", method.to_uppercase()), named))
+            }
+            ("components", "schemas", schema) => Some((format!("schema {schema}"), named)),
+            _ => None,
+        }
+    }
+
+    /// The parameter or property the line is in, within its scope
+    fn detail(&self) -> Option<String> {
+        self.entries
+            .windows(2)
+            .rev()
+            .find_map(|pair| match (pair[0].key.as_str(), &pair[1]) {
+                ("parameters", Entry { key, value, .. }) if key == "-" => {
+                    Some(format!("parameter {}", value.as_deref().unwrap_or("?")))
+                }
+                ("properties", Entry { key, .. }) => Some(format!("field {key}")),
+                _ => None,
+            })
+    }
+
+    /// What the line is about, such as `field email of schema User`
+    fn label(&self) -> Option<String> {
+        let (scope, _) = self.scope()?;
+        Some(match self.detail() {
+            Some(detail) => format!("{detail} of {scope}"),
+            None => scope,
+        })
+    }
+}
+
+/// What an API description's diff changes in the API, and what of it breaks clients
+pub(crate) fn contract_changes(file: &StagedFile) -> Vec<ContractChange> {
+    let mut added = Vec::new();
+    let mut removed = Vec::new();
+    let mut narrowed = Vec::new();
+    // Types removed by label, to pair with the type added in their place
+    let mut old_types: HashMap<String, String> = HashMap::new();
+    let mut path = Path::default();
+    let breaking = |kind, item: String, before: String, after: Option<String>| ApiChange {
+        path: file.path.clone(),
+        kind,
+        item,
+        before,
+        after,
+    };
+
+    for line in file.diff.lines() {
+        if line.starts_with("+++") || line.starts_with("---") {
+            continue;
+        }
+        if let Some(before) = lines_before_hunk(file, line) {
+            path = Path::default();
+            for text in before {
+                path.enter(text);
+            }
+            continue;
+        }
+        let (origin, text) = line.split_at(line.chars().next().map_or(0, char::len_utf8));
+        if !path.enter(text) || !matches!(origin, "+" | "-") {
+            continue;
+        }
+        let Some((scope, named)) = path.scope() else {
+            continue;
+        };
+        let Some(entry) = path.entries.last() else {
+            continue;
+        };
+        let parent = path
+            .entries
+            .iter()
+            .rev()
+            .nth(1)
+            .map(|entry| entry.key.as_str());
+        match (origin, entry.key.as_str(), entry.value.as_deref()) {
+            ("+", _, _) if named => added.push(scope),
+            ("-", _, _) if named => removed.push(scope),
+            ("-", "type", Some(old)) => {
+                old_types.insert(path.label().unwrap_or(scope), old.to_string());
+            }
+            ("+", "type", Some(new)) => {
+                let label = path.label().unwrap_or(scope);
+                if let Some(old) = old_types.remove(&label)
+                    && old != new
+                {
+                    narrowed.push(breaking(
+                        ApiChangeKind::Narrowed,
+                        label,
+                        old,
+                        Some(new.to_string()),
+                    ));
+                }
+            }
+            // Parameters of a new endpoint were never optional
+            ("+", "required", Some("true"))
+                if path.detail().is_some() && !added.contains(&scope) =>
+            {
+                if let Some(label) = path.label() {
+                    narrowed.push(breaking(
+                        ApiChangeKind::Narrowed,
+                        label,
+                        "optional".to_string(),
+                        Some("required".to_string()),
+                    ));
+                }
+            }
+            ("-", "-", Some(value)) if parent == Some("enum") => {
+                let enum_path = Path {
+                    entries: path.entries[..path.entries.len() - 2].to_vec(),
+                };
+                let label = enum_path.label().unwrap_or(scope);
+                removed.push(format!("enum value {value} of {label}"));
+            }
+            _ => {}
+        }
+    }
+
+    // An endpoint or schema removed and added again was moved
+    let moved: Vec<String> = added
+        .iter()
+        .filter(|item| removed.contains(item))
+        .cloned()
+        .collect();
+    added.retain(|item| !moved.contains(item));
+    removed.retain(|item| !moved.contains(item));
+
+    let changes = added
+        .into_iter()
+        .map(|item| ContractChange::Compatible(format!("adds {item}")));
+    let changes = changes.chain(removed.into_iter().map(|item| {
+        ContractChange::Breaking(breaking(ApiChangeKind::Removed, item.clone(), item, None))
+    }));
+    changes
+        .chain(narrowed.into_iter().map(ContractChange::Breaking))
+        .collect()
+}
+
+impl FileAnalyzer for OpenApiAnalyzer {
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn analyze(&self, file: &StagedFile) -> Vec<String> {
+        contract_changes(file)
+            .iter()
+            .map(ContractChange::note)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::context::ChangeType;
+
+    #[test]
+    fn test_openapi_contract_changes() {
+        let content = "\
+openapi: 3.0.3
+paths:
+  /users:
+    get:
+      parameters:
+        - name: limit
+          in: query
+          required: true
+          schema:
+            type: string
+    post:
+      summary: Create a user
+components:
+  schemas:
+    User:
+      properties:
+        role:
+          type: string
+          enum:
+            - member
+";
+        let diff = "\
+--- a/openapi.yaml
++++ b/openapi.yaml
+@@ -6,8 +6,9 @@ paths:
+         - name: limit
+           in: query
++          required: true
+           schema:
+-            type: integer
++            type: string
++    post:
++      summary: Create a user
+-  /users/{id}:
+-    delete:
+-      summary: Delete a user
+@@ -20,3 +19,2 @@ components:
+           enum:
+             - member
+-            - admin
+";
+        let file = StagedFile {
+            path: "api/openapi.yaml".to_string(),
+            change_type: ChangeType::Modified,
+            diff: diff.to_string(),
+            content: Some(content.to_string()),
+            content_excluded: false,
+        };
+        assert_eq!(
+            OpenApiAnalyzer.analyze(&file),
+            [
+                "adds endpoint POST /users",
+                "removes endpoint DELETE /users/{id} (breaking)",
+                "removes enum value admin of field role of schema User (breaking)",
+                "narrows parameter limit of endpoint GET /users: optional → required (breaking)",
+                "narrows parameter limit of endpoint GET /users: integer → string (breaking)",
+            ]
+        );
+    }
+}
@@ -0,0 +1,140 @@
+use super::{FileAnalyzer, push_unique};
+use crate::llm::context::StagedFile;
+use regex::Regex;
+use std::sync::LazyLock;
+
+/// `resource "type" "name" {`, `data "type" "name" {` and `module "name" {`
+static BLOCK: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"^(resource|data|module)\s+"([^"]+)"(?:\s+"([^"]+)")?"#).expect("Valid regex")
+});
+
+/// Resources, data sources and modules a Terraform file adds, changes or destroys
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TerraformAnalyzer;
+
+impl TerraformAnalyzer {
+    /// Files it reads
+    pub const PATTERNS: &'static [&'static str] = &["*.tf", "*.hcl"];
+}
+
+/// The address Terraform prints for the block a line opens, such as `aws_s3_bucket.logs`
+fn block_address(line: &str) -> Option<String> {
+    let captures = BLOCK.captures(line)?;
+    Some(match (&captures[1], captures.get(3)) {
+        ("resource", Some(name)) => format!("{}.{}", &captures[2], name.as_str()),
+        ("data", Some(name)) => format!("data.{}.{}", &captures[2], name.as_str()),
+        (_, _) => format!("module.{}", &captures[2]),
+    })
+}
+
+impl FileAnalyzer for TerraformAnalyzer {
+    fn name(&self) -> &'static str {
+        "terraform"
+    }
+
+    fn analyze(&self, file: &StagedFile) -> Vec<String> {
+        let mut added = Vec::new();
+        let mut destroyed = Vec::new();
+        let mut changed = Vec::new();
+        // The block the diff is in, from its header line or the hunk header
+        let mut current = None;
+        for line in file.diff.lines() {
+            if line.starts_with("+++") || line.starts_with("---") {
+                continue;
+            }
+            let hunk = line.trim_start().strip_prefix("@@");
+            if let Some(hunk) = hunk {
+                current = hunk
+                    .split_once("@@")
+                    .and_then(|(_, rest)| block_address(rest.trim()));
+                continue;
+            }
+            let (origin, text) = line.split_at(line.chars().next().map_or(0, char::len_utf8));
+            if let Some(address) = block_address(text) {
+                match origin {
+                    "+" => push_unique(&mut added, address.clone()),
+                    "-" => push_unique(&mut destroyed, address.clone()),
+                    _ => {}
+                }
+                current = Some(address);
+            } else if text.starts_with('}') {
+                current = None;
+            } else if matches!(origin, "+" | "-")
+                && !text.trim().is_empty()
+                && let Some(address) = &current
+            {
+                push_unique(&mut changed, address.clone());
+            }
+        }
+
+        // A block removed and added again was rewritten in place
+        let rewritten: Vec<String> = added
+            .iter()
+            .filter(|address| destroyed.contains(address))
+            .cloned()
+            .collect();
+        added.retain(|address| !rewritten.contains(address));
+        destroyed.retain(|address| !rewritten.contains(address));
+        for address in rewritten {
+            push_unique(&mut changed, address);
+        }
+        changed.retain(|address| !added.contains(address) && !destroyed.contains(address));
+
+        let notes = added.into_iter().map(|address| format!("adds {address}"));
+        let notes = notes.chain(
+            changed
+                .into_iter()
+                .map(|address| format!("changes {address}")),
+        );
+        notes
+            .chain(
+                destroyed
+                    .into_iter()
+                    .map(|address| format!("destroys {address}")),
+            )
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::context::ChangeType;
+
+    #[test]
+    fn test_terraform_notes() {
+        let diff = "\
+@@ -1,9 +1,12 @@
+ resource \"aws_s3_bucket\" \"logs\" {
+-  bucket = \"old-logs\"
++  bucket = \"app-logs\"
+ }
++
++module \"vpc\" {
++  source = \"./vpc\"
++}
+-data \"aws_ami\" \"ubuntu\" {
+-  most_recent = true
+-}
+@@ -20,3 +23,3 @@ resource \"aws_instance\" \"web\" {
+-  instance_type = \"t3.micro\"
++  instance_type = \"t3.small\"
+";
+        let file = StagedFile {
+            path: "infra/main.tf".to_string(),
+            change_type: ChangeType::Modified,
+            diff: diff.to_string(),
+            content: None,
+            content_excluded: false,
+        };
+        assert_eq!(
+            TerraformAnalyzer.analyze(&file),
+            [
+                "adds module.vpc",
+                "changes aws_s3_bucket.logs",
+                "changes aws_instance.web",
+                "destroys data.aws_ami.ubuntu",
+            ]
+        );
+    }
+}
@@ -1,7 +1,14 @@
+use crate::commands::commit::batch::BatchFormat;
+use crate::commands::commit::experiment::EXPERIMENT_PROMPTS;
+use crate::config::Config;
+use anyhow::Result;
 use clap::Args;
 use clap::builder::{Styles, styling::AnsiColor};
 use colored::Colorize;
+use std::path::PathBuf;
 
+// Each bool is an independent command-line switch
+#[allow(clippy::struct_excessive_bools)]
 #[derive(Args, Clone, Debug)]
 pub struct MessageParams {
     #[arg(short, long, help = "Print the generated message to stdout and exit")]
@@ -27,6 +34,186 @@ pub struct MessageParams {
         value_parser = parse_context_ratio
     )]
     pub context_ratio: Option<f32>,
+
+    #[arg(
+        long,
+        help = "Generate only a one-line subject from the changed files, without diffs, using the provider's fast-model when set",
+        conflicts_with_all = ["complete", "undo", "drafts", "delete_draft", "fixup", "stdin", "batch", "experiment"]
+    )]
+    pub subject_only: bool,
+
+    #[arg(
+        long,
+        help = "Use numbered prompts instead of the full-screen TUI (screen readers, dumb terminals)",
+        conflicts_with = "print"
+    )]
+    pub plain: bool,
+
+    #[arg(
+        long,
+        help = "Undo the last commit made by gitai, keeping its changes staged",
+        conflicts_with_all = ["print", "complete"]
+    )]
+    pub undo: bool,
+
+    #[arg(
+        long,
+        help = "List the message drafts saved when a session was cancelled",
+        conflicts_with_all = ["print", "complete", "undo"]
+    )]
+    pub drafts: bool,
+
+    #[arg(
+        long,
+        value_name = "ID",
+        help = "Delete the draft with this id from --drafts, or `all`",
+        conflicts_with_all = ["print", "complete", "undo", "drafts"]
+    )]
+    pub delete_draft: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "COMMIT",
+        help = "Write `fixup! <subject of COMMIT>` for `git rebase --autosquash` instead of generating a message",
+        conflicts_with_all = ["complete", "undo", "drafts", "delete_draft"]
+    )]
+    pub fixup: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "FILE|URL",
+        help = "Log of the failed CI run this change fixes; its error excerpts go into the prompt",
+        conflicts_with_all = ["complete", "undo", "drafts", "delete_draft", "fixup"]
+    )]
+    pub ci_log: Option<String>,
+
+    #[arg(
+        long = "context-file",
+        value_name = "PATH",
+        help = "File whose text goes into the prompt as extra context, such as a design doc or ticket export; repeatable",
+        conflicts_with_all = ["complete", "undo", "drafts", "delete_draft"]
+    )]
+    pub context_files: Vec<PathBuf>,
+
+    #[arg(
+        long,
+        help = "Read a unified diff from stdin instead of the staged changes and print its message; works outside a repository",
+        conflicts_with_all = ["complete", "plain", "undo", "drafts", "delete_draft", "fixup", "ci"]
+    )]
+    pub stdin: bool,
+
+    #[arg(
+        long,
+        help = "Non-interactive mode for pipelines: message on stdout, exit 2 without staged changes, 3 on provider failure",
+        conflicts_with_all = ["plain", "undo", "complete", "drafts", "delete_draft"]
+    )]
+    pub ci: bool,
+
+    #[arg(
+        long,
+        value_name = "SECS",
+        help = "Give up after this many seconds in --ci mode (default: 120)",
+        requires = "ci"
+    )]
+    pub timeout: Option<u64>,
+
+    #[arg(
+        long,
+        help = "Regenerate the message of every commit in --from..--to and print a SHA to message mapping",
+        requires = "from",
+        conflicts_with_all = ["print", "complete", "plain", "undo", "ci"]
+    )]
+    pub batch: bool,
+
+    #[arg(
+        long,
+        value_name = "REV",
+        help = "Exclusive start of the --batch range",
+        requires = "batch"
+    )]
+    pub from: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "REV",
+        help = "Inclusive end of the --batch range (default: HEAD)",
+        requires = "batch"
+    )]
+    pub to: Option<String>,
+
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = BatchFormat::Json,
+        help = "Output format for --batch"
+    )]
+    pub batch_format: BatchFormat,
+
+    #[arg(
+        long,
+        num_args = 2,
+        value_names = ["A", "B"],
+        help = "Compare two prompt template files (or `default`) on recent commits and print a report",
+        conflicts_with_all = ["print", "complete", "plain", "undo", "ci", "batch"]
+    )]
+    pub experiment: Option<Vec<String>>,
+
+    #[arg(
+        long,
+        default_value = "commit-user",
+        value_parser = clap::builder::PossibleValuesParser::new(EXPERIMENT_PROMPTS),
+        help = "Prompt the --experiment variants replace"
+    )]
+    pub experiment_prompt: String,
+
+    #[arg(
+        long,
+        default_value_t = 10,
+        help = "Number of recent commits --experiment scores"
+    )]
+    pub samples: usize,
+
+    #[command(flatten)]
+    pub trailers: TrailerArgs,
+}
+
+/// Trailers for the commit, added to those from git config
+#[derive(Args, Clone, Debug, Default)]
+pub struct TrailerArgs {
+    #[arg(
+        long,
+        value_name = "NAME <EMAIL>|PRESET",
+        help = "Add a Co-authored-by trailer, or one per member of a gitai-pair preset (repeatable)"
+    )]
+    pub co_author: Vec<String>,
+
+    #[arg(
+        long,
+        value_name = "KEY: VALUE",
+        help = "Add a trailer such as `Reviewed-by: Name <email>` (repeatable)"
+    )]
+    pub trailer: Vec<String>,
+
+    #[arg(
+        short,
+        long,
+        help = "Add a Signed-off-by trailer for the configured user"
+    )]
+    pub signoff: bool,
+}
+
+impl TrailerArgs {
+    /// Adds these trailers to the ones `config` loaded
+    pub fn apply_to_config(&self, config: &mut Config) -> Result<()> {
+        for co_author in &self.co_author {
+            config.trailers.add_co_author(co_author)?;
+        }
+        for trailer in &self.trailer {
+            config.trailers.add_trailer(trailer)?;
+        }
+        config.trailers.signoff |= self.signoff;
+        Ok(())
+    }
 }
 
 pub fn get_styles() -> Styles {
@@ -0,0 +1,180 @@
+//! Per-command default flags from git config.
+//!
+//! A `[gitai-defaults "<command>"]` section sets flags a command gets as if
+//! they were typed, such as `print = true` under `"message"`. Flags given on
+//! the command line win, and a default that would clash with them, by
+//! conflicting with a given flag or repeating it, is left out.
+
+use crate::config::{ConfigSelection, load_command_defaults, select_config};
+use crate::{output, trace};
+use clap::error::ErrorKind;
+use clap::parser::ValueSource;
+use clap::{ArgMatches, Command, Parser};
+use log::debug;
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+/// The command-line tokens a default stands for; a false switch adds nothing
+fn default_tokens(arg: &clap::Arg, value: &str) -> Option<Vec<OsString>> {
+    let long = arg.get_long()?;
+    if arg.get_action().takes_values() {
+        return Some(vec![format!("--{long}={value}").into()]);
+    }
+    match crate::config::parse_bool(value) {
+        Some(true) => Some(vec![format!("--{long}").into()]),
+        Some(false) => Some(Vec::new()),
+        None => None,
+    }
+}
+
+/// `args` with the defaults for `command` added after the program name
+pub fn args_with_defaults(
+    command: &Command,
+    name: &str,
+    args: Vec<OsString>,
+    defaults: &BTreeMap<String, String>,
+) -> Vec<OsString> {
+    // Leave errors, --help and --version to the real parse
+    let Ok(given) = command.clone().try_get_matches_from(&args) else {
+        return args;
+    };
+
+    let mut args = args;
+    for (flag, value) in defaults {
+        let flag = flag.replace('_', "-");
+        let Some(arg) = command
+            .get_arguments()
+            .find(|arg| arg.get_long() == Some(flag.as_str()))
+        else {
+            output::print_warning(&format!(
+                "Ignoring gitai-defaults.{name}.{flag}: there is no --{flag} option"
+            ));
+            continue;
+        };
+        let Some(tokens) = default_tokens(arg, value) else {
+            output::print_warning(&format!(
+                "Ignoring gitai-defaults.{name}.{flag}: `{value}` is not a boolean"
+            ));
+            continue;
+        };
+
+        if given.value_source(arg.get_id().as_str()) == Some(ValueSource::CommandLine) {
+            continue;
+        }
+
+        let mut candidate = args.clone();
+        candidate.splice(1..1, tokens);
+        match command.clone().try_get_matches_from(&candidate) {
+            Ok(_) => args = candidate,
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    ErrorKind::ArgumentConflict | ErrorKind::MissingRequiredArgument
+                ) =>
+            {
+                debug!("Skipping default --{flag} for {name}: {}", e.kind());
+            }
+            Err(e) => output::print_warning(&format!(
+                "Ignoring gitai-defaults.{name}.{flag}: {}",
+                e.kind()
+            )),
+        }
+    }
+    args
+}
+
+/// The `--config` file and `--profile` given on the command line
+fn selection(matches: &ArgMatches) -> ConfigSelection {
+    ConfigSelection {
+        file: matches
+            .try_get_one::<PathBuf>("config_file")
+            .ok()
+            .flatten()
+            .cloned(),
+        profile: matches
+            .try_get_one::<String>("profile")
+            .ok()
+            .flatten()
+            .cloned(),
+    }
+}
+
+/// Parses the process's arguments with the user's defaults for `name` merged in.
+///
+/// The `--config` file and `--profile` are chosen first, so the defaults and
+/// every later config load come from them, and `--trace` starts recording.
+pub fn parse<P: Parser>(name: &str) -> P {
+    let command = P::command();
+    let args: Vec<OsString> = std::env::args_os().collect();
+    if let Ok(matches) = command.clone().try_get_matches_from(&args) {
+        select_config(selection(&matches));
+        if let Ok(Some(path)) = matches.try_get_one::<PathBuf>("trace")
+            && let Err(e) = trace::enable_chrome_trace(path)
+        {
+            output::print_warning(&format!("Not tracing: {e:#}"));
+        }
+    }
+    let args = args_with_defaults(&command, name, args, &load_command_defaults(name));
+    P::parse_from(args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[derive(Parser, Debug)]
+    struct Cli {
+        #[arg(short, long, conflicts_with = "plain")]
+        print: bool,
+        #[arg(long)]
+        plain: bool,
+        #[arg(long)]
+        timeout: Option<u64>,
+        #[arg(long)]
+        prefix: Option<String>,
+    }
+
+    fn merged(args: &[&str], defaults: &[(&str, &str)]) -> Vec<String> {
+        let defaults = defaults
+            .iter()
+            .map(|(flag, value)| ((*flag).to_string(), (*value).to_string()))
+            .collect();
+        let args = args.iter().map(OsString::from).collect();
+        args_with_defaults(&Cli::command(), "test", args, &defaults)
+            .into_iter()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn test_defaults_fill_in_missing_flags() {
+        assert_eq!(
+            merged(&["cmd"], &[("print", "true"), ("timeout", "30")]),
+            ["cmd", "--timeout=30", "--print"]
+        );
+        assert_eq!(merged(&["cmd"], &[("print", "no")]), ["cmd"]);
+        // Ignored with a warning
+        assert_eq!(
+            merged(&["cmd"], &[("nope", "1"), ("print", "maybe")]),
+            ["cmd"]
+        );
+    }
+
+    #[test]
+    fn test_command_line_wins() {
+        assert_eq!(
+            merged(&["cmd", "--timeout", "5"], &[("timeout", "30")]),
+            ["cmd", "--timeout", "5"]
+        );
+        assert_eq!(
+            merged(&["cmd", "--plain"], &[("print", "true")]),
+            ["cmd", "--plain"]
+        );
+        assert_eq!(
+            merged(&["cmd", "--bogus"], &[("print", "true")]),
+            ["cmd", "--bogus"]
+        );
+    }
+}
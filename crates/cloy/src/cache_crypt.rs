@@ -0,0 +1,142 @@
+//! Encryption at rest for gitai's caches of commit messages.
+//!
+//! With `gitai.encrypt-cache` on, the style profiles kept per author and the
+//! drafts kept per repository are written sealed with AES-256-GCM. The key is
+//! generated on first use and stored in the OS keychain: the macOS Keychain,
+//! the Windows Credential Manager or the Linux kernel keyring. Files written
+//! before the setting was turned on stay readable and are sealed the next
+//! time they are written. A key the keychain lost, as the Linux keyring does
+//! at logout, makes sealed caches unreadable, which only costs what they held.
+
+use crate::config::parse_bool;
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use anyhow::{Context, Result, anyhow};
+use git2::Repository;
+use std::fs;
+use std::path::Path;
+
+/// Start of a sealed file, followed by the nonce and the ciphertext
+const MAGIC: &[u8] = b"gitai-sealed-v1\n";
+
+/// Bytes of the AES-GCM nonce
+const NONCE_LEN: usize = 12;
+
+/// Keychain service and account the key is stored under
+const KEYCHAIN_SERVICE: &str = "gitai";
+const KEYCHAIN_ACCOUNT: &str = "cache-key";
+
+/// Whether `gitai.encrypt-cache` is on for `repo`, or globally without one
+pub fn enabled(repo: Option<&Repository>) -> bool {
+    let config = match repo {
+        Some(repo) => repo.config(),
+        None => git2::Config::open_default(),
+    };
+    config
+        .and_then(|config| config.get_string("gitai.encrypt-cache"))
+        .ok()
+        .and_then(|value| parse_bool(&value))
+        .unwrap_or(false)
+}
+
+/// Whether `data` was written by [`seal`]
+pub fn is_sealed(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+/// `plaintext` encrypted with `key` under a fresh nonce
+pub fn seal(key: &[u8; 32], plaintext: &[u8]) -> Result<Vec<u8>> {
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key));
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, plaintext)
+        .map_err(|_| anyhow!("Failed to encrypt the cache"))?;
+    let mut sealed = Vec::with_capacity(MAGIC.len() + NONCE_LEN + ciphertext.len());
+    sealed.extend_from_slice(MAGIC);
+    sealed.extend_from_slice(&nonce);
+    sealed.extend_from_slice(&ciphertext);
+    Ok(sealed)
+}
+
+/// The plaintext of sealed `data`; fails for another key or a damaged file
+pub fn open(key: &[u8; 32], data: &[u8]) -> Result<Vec<u8>> {
+    let rest = data
+        .strip_prefix(MAGIC)
+        .ok_or_else(|| anyhow!("Not a sealed cache file"))?;
+    if rest.len() < NONCE_LEN {
+        return Err(anyhow!("Sealed cache file is truncated"));
+    }
+    let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key));
+    cipher
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| anyhow!("Failed to decrypt the cache; was the keychain entry replaced?"))
+}
+
+fn keychain_entry() -> Result<keyring::Entry> {
+    keyring::Entry::new(KEYCHAIN_SERVICE, KEYCHAIN_ACCOUNT).context("No OS keychain available")
+}
+
+/// The key in the keychain, generated and stored when `create` is set and there is none
+fn key(create: bool) -> Result<[u8; 32]> {
+    let entry = keychain_entry()?;
+    match entry.get_secret() {
+        Ok(secret) => secret
+            .try_into()
+            .map_err(|_| anyhow!("The cache key in the keychain is not 32 bytes")),
+        Err(keyring::Error::NoEntry) if create => {
+            let key: [u8; 32] = Aes256Gcm::generate_key(&mut OsRng).into();
+            entry
+                .set_secret(&key)
+                .context("Failed to store the cache key in the keychain")?;
+            Ok(key)
+        }
+        Err(e) => Err(anyhow!(e).context("Failed to read the cache key from the keychain")),
+    }
+}
+
+/// Reads a cache file, decrypting it if it was sealed
+pub fn read(path: &Path) -> Result<Vec<u8>> {
+    let data = fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    if is_sealed(&data) {
+        open(&key(false)?, &data)
+    } else {
+        Ok(data)
+    }
+}
+
+/// Writes a cache file, sealed when `encrypt` is set
+pub fn write(path: &Path, contents: &[u8], encrypt: bool) -> Result<()> {
+    let data = if encrypt {
+        seal(&key(true)?, contents)?
+    } else {
+        contents.to_vec()
+    };
+    fs::write(path, data).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seal_round_trip() {
+        let key = [7_u8; 32];
+        let sealed = seal(&key, b"feat: add parser").expect("Failed to seal");
+        assert!(is_sealed(&sealed));
+        assert!(!sealed.windows(6).any(|window| window == b"parser"));
+        assert_eq!(
+            open(&key, &sealed).expect("Failed to open"),
+            b"feat: add parser"
+        );
+
+        // Fresh nonce every time
+        assert_ne!(
+            seal(&key, b"feat: add parser").expect("Failed to seal"),
+            sealed
+        );
+        assert!(open(&[8_u8; 32], &sealed).is_err());
+        assert!(open(&key, &sealed[..MAGIC.len() + 4]).is_err());
+        assert!(!is_sealed(b"[]"));
+    }
+}
@@ -0,0 +1,296 @@
+//! A small API for tools that embed gitai, such as editors and bots.
+//!
+//! [`Client`] wires the configuration, the repository and the commit service
+//! together, so generating a message takes a few lines:
+//!
+//! ```no_run
+//! # async fn run() -> anyhow::Result<()> {
+//! let client = cloy::Client::builder()
+//!     .repository("/path/to/repo")
+//!     .provider("openrouter")
+//!     .build()?;
+//! let message = client.generate_commit_message().await?;
+//! client.commit(&message)?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Settings the builder does not set come from git config, as they do for the
+//! binaries.
+
+use crate::commands::commit::CommitService;
+use crate::commands::commit::types::{GeneratedMessage, format_commit_message};
+use crate::common::DetailLevel;
+use crate::config::{Config, ProviderConfig};
+use crate::error::{ErrorCode, coded};
+use crate::git::{CommitResult, GitRepo};
+use crate::llm::provider::ProviderKind;
+use anyhow::{Result, anyhow};
+use std::path::PathBuf;
+
+/// Builds a [`Client`]
+#[derive(Debug)]
+pub struct ClientBuilder {
+    repository: Option<PathBuf>,
+    remote: Option<String>,
+    config: Option<Config>,
+    provider: Option<String>,
+    model: Option<String>,
+    api_key: Option<String>,
+    instructions: Option<String>,
+    language: Option<String>,
+    detail_level: DetailLevel,
+}
+
+impl Default for ClientBuilder {
+    fn default() -> Self {
+        Self {
+            repository: None,
+            remote: None,
+            config: None,
+            provider: None,
+            model: None,
+            api_key: None,
+            instructions: None,
+            language: None,
+            detail_level: DetailLevel::Standard,
+        }
+    }
+}
+
+impl ClientBuilder {
+    /// Works in the repository at `path` instead of the current directory
+    #[must_use]
+    pub fn repository(mut self, path: impl Into<PathBuf>) -> Self {
+        self.repository = Some(path.into());
+        self
+    }
+
+    /// Clones `url` into a temporary directory and works there
+    #[must_use]
+    pub fn remote(mut self, url: impl Into<String>) -> Self {
+        self.remote = Some(url.into());
+        self
+    }
+
+    /// Uses `config` instead of loading it from git config
+    #[must_use]
+    pub fn config(mut self, config: Config) -> Self {
+        self.config = Some(config);
+        self
+    }
+
+    /// Generates with `provider`, such as `openrouter` or `google`
+    #[must_use]
+    pub fn provider(mut self, provider: impl Into<String>) -> Self {
+        self.provider = Some(provider.into());
+        self
+    }
+
+    #[must_use]
+    pub fn model(mut self, model: impl Into<String>) -> Self {
+        self.model = Some(model.into());
+        self
+    }
+
+    /// API key of the provider, in place of the configured one
+    #[must_use]
+    pub fn api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = Some(api_key.into());
+        self
+    }
+
+    /// Instructions added to every prompt, in place of `gitai.instructions`
+    #[must_use]
+    pub fn instructions(mut self, instructions: impl Into<String>) -> Self {
+        self.instructions = Some(instructions.into());
+        self
+    }
+
+    /// Language generated text is written in, such as `ja`
+    #[must_use]
+    pub fn language(mut self, language: impl Into<String>) -> Self {
+        self.language = Some(language.into());
+        self
+    }
+
+    #[must_use]
+    pub fn detail_level(mut self, detail_level: DetailLevel) -> Self {
+        self.detail_level = detail_level;
+        self
+    }
+
+    /// The client, after checking the provider settings
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the configuration cannot be loaded, the provider is
+    /// unknown, its settings are invalid, or a remote cannot be cloned.
+    pub fn build(self) -> Result<Client> {
+        let mut config = match self.config {
+            Some(config) => config,
+            None => Config::load()?,
+        };
+        if let Some(provider) = self.provider {
+            let kind = ProviderKind::from_name(&provider)
+                .ok_or_else(|| anyhow!("Unknown provider: {provider}"))?;
+            config.provider = Some(kind.as_str().to_string());
+        }
+        let provider = config.active_provider().as_str();
+        let provider_config = config
+            .providers
+            .entry(provider.to_string())
+            .or_insert_with(|| ProviderConfig::default_for(provider));
+        if let Some(model) = self.model {
+            provider_config.model_name = model;
+        }
+        if let Some(api_key) = self.api_key {
+            provider_config.api_key = api_key;
+        }
+        if let Some(instructions) = self.instructions {
+            config.instructions = instructions;
+        }
+        if self.language.is_some() {
+            config.language = self.language;
+        }
+        config.check_provider_settings()?;
+
+        let git_repo = match (self.remote, self.repository) {
+            (Some(url), _) => GitRepo::clone_remote_repository(&url)?,
+            (None, Some(path)) => GitRepo::new(&path)?,
+            (None, None) => GitRepo::new_from_url(None)?,
+        };
+        let instructions = config.instructions.clone();
+        let repo_path = git_repo.repo_path().clone();
+        let service =
+            CommitService::new(config, &repo_path, provider, self.detail_level, git_repo)?;
+        Ok(Client {
+            service,
+            instructions,
+        })
+    }
+}
+
+/// Generates commit messages and pull request descriptions for one repository
+pub struct Client {
+    service: CommitService,
+    instructions: String,
+}
+
+impl Client {
+    pub fn builder() -> ClientBuilder {
+        ClientBuilder::default()
+    }
+
+    /// A client for the repository in the current directory, configured from git config
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the configuration cannot be loaded or is invalid.
+    pub fn new() -> Result<Self> {
+        Self::builder().build()
+    }
+
+    /// A message for the staged changes
+    ///
+    /// # Errors
+    ///
+    /// Returns an error when nothing is staged or the provider fails.
+    pub async fn generate_commit_message(&self) -> Result<GeneratedMessage> {
+        let context = self.service.get_git_info().await?;
+        if context.staged_files.is_empty() {
+            return Err(coded(ErrorCode::NoStagedChanges, "No staged changes"));
+        }
+        self.service
+            .generate_message_with_context(&self.instructions, context)
+            .await
+    }
+
+    /// A pull request title and description for the commits reachable from
+    /// `to` but not from `from`, such as `main` and `HEAD`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error when a reference cannot be resolved or the provider fails.
+    pub async fn generate_pr(&self, from: &str, to: &str) -> Result<GeneratedMessage> {
+        self.service
+            .draft_pull_request_for_range(&self.instructions, from, to)
+            .await
+    }
+
+    /// An explanation of what `commit` does
+    ///
+    /// # Errors
+    ///
+    /// Returns an error when the commit cannot be found or the provider fails.
+    pub async fn explain_commit(&self, commit: &str) -> Result<GeneratedMessage> {
+        self.service
+            .explain_commit(&self.instructions, commit)
+            .await
+    }
+
+    /// Commits the staged changes with `message`, with the configured trailers
+    /// and hooks
+    ///
+    /// # Errors
+    ///
+    /// Returns an error when a hook or plugin rejects the commit or it fails.
+    pub fn commit(&self, message: &GeneratedMessage) -> Result<CommitResult> {
+        self.service
+            .perform_commit(&format_commit_message(message), false, None)
+    }
+
+    /// The service behind the client, for everything else gitai can do
+    pub fn service(&self) -> &CommitService {
+        &self.service
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    #[tokio::test]
+    async fn test_client_commits_without_a_provider_call() {
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let repo = git2::Repository::init(dir.path()).expect("Failed to init repo");
+        let mut git_config = repo.config().expect("Failed to open config");
+        git_config
+            .set_str("user.name", "Test")
+            .expect("Failed to set name");
+        git_config
+            .set_str("user.email", "test@example.com")
+            .expect("Failed to set email");
+
+        let client = Client::builder()
+            .repository(dir.path())
+            .config(Config::default())
+            .provider("OpenRouter")
+            .api_key("test-key")
+            .build()
+            .expect("Failed to build client");
+        let config = client.service().config();
+        assert_eq!(config.provider.as_deref(), Some("openrouter"));
+        assert_eq!(
+            config
+                .get_provider_config("openrouter")
+                .map(|provider| provider.api_key.as_str()),
+            Some("test-key")
+        );
+        assert!(client.generate_commit_message().await.is_err());
+
+        std::fs::write(dir.path().join("README.md"), "# Demo\n").expect("Failed to write");
+        let mut index = repo.index().expect("Failed to open index");
+        index
+            .add_path(Path::new("README.md"))
+            .expect("Failed to stage");
+        index.write().expect("Failed to write index");
+        let message = GeneratedMessage {
+            title: "docs: add readme".to_string(),
+            message: String::new(),
+        };
+        let result = client.commit(&message).expect("Failed to commit");
+        assert_eq!(result.files_changed, 1);
+    }
+}
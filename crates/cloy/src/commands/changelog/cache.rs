@@ -0,0 +1,151 @@
+//! Analyses of commits already seen by `git-changelog` and `git-notes`.
+//!
+//! A commit's analysis, and the one-line summary long ranges get, never
+//! change once computed, so both are kept in `.git/gitai/changelog.json`
+//! keyed by commit hash. Regenerating a changelog over a range that grew by a
+//! few commits only diffs, and only summarizes, the new ones.
+//! With `gitai.encrypt-cache` on, the file is sealed; see [`crate::cache_crypt`].
+
+use super::change_analyzer::AnalyzedChange;
+use crate::cache_crypt;
+use anyhow::Result;
+use chrono::Utc;
+use git2::Repository;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+/// Bumped when the analysis changes, so older entries are recomputed
+const CACHE_VERSION: u32 = 1;
+
+/// Commits kept per repository; the longest unused are dropped first
+const MAX_ENTRIES: usize = 10_000;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct Entry {
+    change: AnalyzedChange,
+    /// When a run last read or wrote the entry
+    used_at: i64,
+}
+
+/// Cached commit analyses of one repository
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct ChangeCache {
+    version: u32,
+    entries: HashMap<String, Entry>,
+}
+
+fn cache_path(repo: &Repository) -> PathBuf {
+    repo.path().join("gitai").join("changelog.json")
+}
+
+impl ChangeCache {
+    /// The repository's cache, or an empty one when it is missing, unreadable
+    /// or from another version
+    pub fn load(repo: &Repository) -> Self {
+        cache_crypt::read(&cache_path(repo))
+            .ok()
+            .and_then(|raw| serde_json::from_slice::<Self>(&raw).ok())
+            .filter(|cache| cache.version == CACHE_VERSION)
+            .unwrap_or_default()
+    }
+
+    /// The cached analysis of `hash`
+    pub fn get(&mut self, hash: &str) -> Option<AnalyzedChange> {
+        let entry = self.entries.get_mut(hash)?;
+        entry.used_at = Utc::now().timestamp();
+        Some(entry.change.clone())
+    }
+
+    pub fn insert(&mut self, change: &AnalyzedChange) {
+        self.entries.insert(
+            change.commit_hash.clone(),
+            Entry {
+                change: change.clone(),
+                used_at: Utc::now().timestamp(),
+            },
+        );
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Writes the cache, keeping the `MAX_ENTRIES` most recently used
+    pub fn save(mut self, repo: &Repository) -> Result<()> {
+        if self.entries.len() > MAX_ENTRIES {
+            let mut used: Vec<i64> = self.entries.values().map(|entry| entry.used_at).collect();
+            used.sort_unstable_by(|a, b| b.cmp(a));
+            let cutoff = used[MAX_ENTRIES - 1];
+            self.entries.retain(|_, entry| entry.used_at >= cutoff);
+        }
+        self.version = CACHE_VERSION;
+        let path = cache_path(repo);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        cache_crypt::write(
+            &path,
+            &serde_json::to_vec(&self)?,
+            cache_crypt::enabled(Some(repo)),
+        )
+    }
+}
+
+/// Adds `changes` to the repository's cache, replacing what it had for them
+pub fn store(repo: &Repository, changes: &[AnalyzedChange]) -> Result<()> {
+    let mut cache = ChangeCache::load(repo);
+    for change in changes {
+        cache.insert(change);
+    }
+    cache.save(repo)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::changelog::models::{ChangeMetrics, ChangelogType};
+
+    #[test]
+    fn test_changes_round_trip() {
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let repo = Repository::init(dir.path()).expect("Failed to init repo");
+        let change = AnalyzedChange {
+            commit_hash: "4d7a2146".to_string(),
+            commit_message: "fix: parse empty ranges".to_string(),
+            file_changes: Vec::new(),
+            metrics: ChangeMetrics {
+                total_commits: 1,
+                files_changed: 1,
+                insertions: 3,
+                deletions: 1,
+                total_lines_changed: 4,
+            },
+            impact_score: 0.14,
+            change_type: ChangelogType::Fixed,
+            is_breaking_change: false,
+            associated_issues: vec!["#12".to_string()],
+            pull_request: None,
+            summary: None,
+        };
+        store(&repo, std::slice::from_ref(&change)).expect("Failed to store");
+
+        let summarized = AnalyzedChange {
+            summary: Some("Fixes parsing of empty ranges".to_string()),
+            ..change
+        };
+        store(&repo, std::slice::from_ref(&summarized)).expect("Failed to store");
+
+        let mut cache = ChangeCache::load(&repo);
+        assert_eq!(cache.len(), 1);
+        let cached = cache.get("4d7a2146").expect("Cached change");
+        assert_eq!(cached.summary, summarized.summary);
+        assert_eq!(cached.change_type, ChangelogType::Fixed);
+        assert!(cache.get("9c1e03b8").is_none());
+    }
+}
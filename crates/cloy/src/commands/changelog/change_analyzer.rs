@@ -1,3 +1,4 @@
+use super::cache::ChangeCache;
 use super::engine::DefaultAnalysisEngine;
 use super::models::{ChangeMetrics, ChangelogType};
 use crate::git::GitRepo;
@@ -5,11 +6,12 @@ use crate::llm::context::{ChangeType, RecentCommit};
 
 use anyhow::Result;
 use git2::Oid;
+use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use tokio::sync::mpsc;
 
 /// Represents the analyzed changes for a single commit
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AnalyzedChange {
     pub commit_hash: String,
     pub commit_message: String,
@@ -20,10 +22,13 @@ pub struct AnalyzedChange {
     pub is_breaking_change: bool,
     pub associated_issues: Vec<String>,
     pub pull_request: Option<String>,
+    /// One-line summary, written for commits of long ranges in place of the file list
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
 }
 
 /// Represents changes to a single file
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FileChange {
     pub old_path: String,
     pub new_path: String,
@@ -43,6 +48,7 @@ impl ChangeAnalyzer {
     }
 
     /// Analyze commits between two Git references, streaming results via channel
+    #[tracing::instrument(skip_all)]
     pub async fn analyze_commits(
         &self,
         from: &str,
@@ -56,11 +62,27 @@ impl ChangeAnalyzer {
         let engine = DefaultAnalysisEngine;
 
         let _ = tokio::task::spawn_blocking(move || {
-            git_repo.get_commits_between_stream(&from, &to, |commit| {
-                let analyzed = Self::analyze_commit_inner(&git_repo, &engine, commit)?;
+            let repo = git_repo.open_repo()?;
+            let mut cache = ChangeCache::load(&repo);
+            let (mut analyzed_count, mut cached_count) = (0, 0);
+            let streamed = git_repo.get_commits_between_stream(&from, &to, |commit| {
+                let analyzed = if let Some(cached) = cache.get(&commit.hash) {
+                    cached_count += 1;
+                    cached
+                } else {
+                    let analyzed = Self::analyze_commit_inner(&git_repo, &engine, commit)?;
+                    cache.insert(&analyzed);
+                    analyzed_count += 1;
+                    analyzed
+                };
                 let _ = tx.blocking_send(Ok(analyzed));
                 Ok(())
-            })
+            });
+            log::debug!("Analyzed {analyzed_count} commits, reused {cached_count} from the cache");
+            if let Err(e) = cache.save(&repo) {
+                log::debug!("Failed to save the changelog cache: {e}");
+            }
+            streamed
         })
         .await?;
         Ok(())
@@ -102,10 +124,12 @@ impl ChangeAnalyzer {
             is_breaking_change,
             associated_issues,
             pull_request,
+            summary: None,
         })
     }
 
     /// Analyze changes between two git refs and return the analyzed changes with total metrics
+    #[tracing::instrument(skip_all)]
     pub async fn analyze_changes(
         &self,
         from: &str,
@@ -1,31 +1,157 @@
+use super::cache;
 use super::models::ChangeMetrics;
 use super::readme_reader::get_readme_summary;
 use crate::commands::changelog::change_analyzer::{AnalyzedChange, ChangeAnalyzer};
-use crate::common::DetailLevel;
+use crate::commands::commit::prompt_helpers::apply_template;
+use crate::common::{DetailLevel, get_combined_instructions};
 use crate::config::Config;
-use crate::git::GitRepo;
+use crate::git::{GitRepo, Package};
 use crate::llm::engine;
-use crate::llm::provider::ProviderKind;
 
 use anyhow::{Context, Result};
+use futures::{StreamExt, stream};
 use schemars::JsonSchema;
-use serde::Serialize;
 use serde::de::DeserializeOwned;
-use std::fmt::Debug;
+use serde::{Deserialize, Serialize};
+use std::fmt::{Debug, Write};
 use std::sync::Arc;
 
+/// Ranges with more commits than this describe each commit by a one-line
+/// summary instead of its file list
+const LONG_RANGE_COMMITS: usize = 50;
+
+/// Commits summarized in one request
+const SUMMARY_BATCH: usize = 25;
+
+/// Summary requests sent at once
+const MAX_CONCURRENT_BATCHES: usize = 4;
+
+const SUMMARY_SYSTEM_PROMPT: &str = "You summarize commits for someone writing a changelog. \
+    For each commit, write one sentence on what it changes for users of the project, \
+    based on its message and the files it touches. Return every commit hash you are given.";
+
+/// One-line summaries of a batch of commits
+#[derive(Deserialize, JsonSchema, Debug)]
+struct CommitSummaries {
+    summaries: Vec<CommitSummary>,
+}
+
+#[derive(Deserialize, JsonSchema, Debug)]
+struct CommitSummary {
+    commit_hash: String,
+    summary: String,
+}
+
+fn summary_prompt(changes: &[&AnalyzedChange]) -> String {
+    let mut prompt = String::new();
+    for change in changes {
+        writeln!(prompt, "Commit: {}", change.commit_hash).ok();
+        writeln!(prompt, "Message: {}", change.commit_message.trim()).ok();
+        for file in &change.file_changes {
+            writeln!(prompt, "  - {} ({})", file.new_path, file.change_type).ok();
+        }
+        prompt.push('\n');
+    }
+    prompt
+}
+
+/// Summarizes the commits of a long range that have no summary yet and
+/// caches the summaries. A failed batch leaves its commits with their file lists.
+async fn summarize_commits(
+    git_repo: &GitRepo,
+    config: &Config,
+    provider_name: &str,
+    changes: &mut [AnalyzedChange],
+) {
+    if changes.len() <= LONG_RANGE_COMMITS {
+        return;
+    }
+    let missing: Vec<&AnalyzedChange> = changes.iter().filter(|c| c.summary.is_none()).collect();
+    // Collected first so the stream holds futures, not a borrowing closure
+    let requests: Vec<_> = missing
+        .chunks(SUMMARY_BATCH)
+        .map(|batch| async move {
+            engine::get_message::<CommitSummaries>(
+                config,
+                provider_name,
+                SUMMARY_SYSTEM_PROMPT,
+                &summary_prompt(batch),
+            )
+            .await
+        })
+        .collect();
+    let batches: Vec<_> = stream::iter(requests)
+        .buffer_unordered(MAX_CONCURRENT_BATCHES)
+        .collect()
+        .await;
+
+    let mut summaries = Vec::new();
+    for batch in batches {
+        match batch {
+            Ok(batch) => summaries.extend(batch.summaries),
+            Err(e) => log::debug!("Failed to summarize a batch of commits: {e}"),
+        }
+    }
+    if summaries.is_empty() {
+        return;
+    }
+    let mut summarized = Vec::new();
+    for change in changes.iter_mut() {
+        if let Some(found) = summaries
+            .iter()
+            .find(|s| s.commit_hash == change.commit_hash)
+        {
+            change.summary = Some(found.summary.trim().to_string());
+            summarized.push(change.clone());
+        }
+    }
+    let stored = git_repo
+        .open_repo()
+        .map_err(anyhow::Error::from)
+        .and_then(|repo| cache::store(&repo, &summarized));
+    if let Err(e) = stored {
+        log::debug!("Failed to cache commit summaries: {e}");
+    }
+}
+
 type UserPromptFn =
     fn(&[AnalyzedChange], &ChangeMetrics, DetailLevel, &str, &str, Option<&str>) -> String;
 
+/// Built-in prompts for a change summary
+pub struct ChangePrompts {
+    /// Prefix of the `<name>-system` and `<name>-user` templates that replace them
+    pub name: &'static str,
+    pub system: fn(&Config) -> String,
+    pub user: UserPromptFn,
+}
+
 pub async fn generate_changes_content<T>(
     git_repo: Arc<GitRepo>,
     from: &str,
     to: &str,
     config: &Config,
     detail_level: DetailLevel,
-    create_system_prompt: fn(&Config) -> String,
-    create_user_prompt: UserPromptFn,
+    prompts: ChangePrompts,
 ) -> Result<T>
+where
+    T: DeserializeOwned + Serialize + Debug + JsonSchema,
+{
+    generate_package_changes_content(git_repo, from, to, config, detail_level, prompts, None)
+        .await?
+        .context("No commits to describe")
+}
+
+/// Like [`generate_changes_content`], over the commits touching `package`
+/// only; `None` when none of the range's commits touch it
+pub async fn generate_package_changes_content<T>(
+    git_repo: Arc<GitRepo>,
+    from: &str,
+    to: &str,
+    config: &Config,
+    detail_level: DetailLevel,
+    prompts: ChangePrompts,
+    package: Option<&Package>,
+) -> Result<Option<T>>
 where
     T: DeserializeOwned + Serialize + Debug + JsonSchema,
 {
@@ -33,17 +159,31 @@ where
     let analyzer = ChangeAnalyzer::new(git_repo.clone())?;
 
     // Get analyzed changes
-    let (analyzed_changes, total_metrics) = analyzer.analyze_changes(from, to).await?;
+    let (mut analyzed_changes, mut total_metrics) = analyzer.analyze_changes(from, to).await?;
+    if let Some(package) = package {
+        analyzed_changes.retain(|change| {
+            change
+                .file_changes
+                .iter()
+                .any(|file| package.contains(&file.new_path) || package.contains(&file.old_path))
+        });
+        if analyzed_changes.is_empty() {
+            return Ok(None);
+        }
+        total_metrics = analyzer.calculate_total_metrics(&analyzed_changes);
+    }
+
+    let provider_name = config.active_provider().as_str();
+    summarize_commits(&git_repo, config, provider_name, &mut analyzed_changes).await;
 
     // Get README summary for context
-    let provider_name = ProviderKind::Google.as_str();
     let readme_summary = get_readme_summary(git_repo, to, config, provider_name)
         .await
         .context("Failed to get README summary")?;
 
     // Create prompts for the LLM
-    let system_prompt = create_system_prompt(config);
-    let user_prompt = create_user_prompt(
+    let system_prompt = (prompts.system)(config);
+    let user_prompt = (prompts.user)(
         &analyzed_changes,
         &total_metrics,
         detail_level,
@@ -51,9 +191,40 @@ where
         to,
         readme_summary.as_deref(),
     );
+    let user_prompt = match package {
+        Some(package) => format!(
+            "Package: {} ({}). Only the commits touching this package are listed; \
+             describe the changes to it.\n\n{user_prompt}",
+            package.name, package.path
+        ),
+        None => user_prompt,
+    };
+
+    let vars = serde_json::json!({
+        "from": from,
+        "to": to,
+        "instructions": get_combined_instructions(config),
+        "schema": serde_json::to_string_pretty(&schemars::schema_for!(T))?,
+        "commits": analyzed_changes,
+        "metrics": total_metrics,
+        "readme_summary": readme_summary,
+        "detail_level": detail_level.as_str(),
+        "package": package.map(|package| serde_json::json!({
+            "name": package.name,
+            "path": package.path,
+        })),
+    });
+    let system_prompt = apply_template(
+        config,
+        &format!("{}-system", prompts.name),
+        vars.clone(),
+        system_prompt,
+    )?;
+    let user_prompt = apply_template(config, &format!("{}-user", prompts.name), vars, user_prompt)?;
 
     // Generate content using LLM
     engine::get_message::<T>(config, provider_name, &system_prompt, &user_prompt)
         .await
         .context("Failed to generate content")
+        .map(Some)
 }
@@ -1,3 +1,4 @@
+pub mod cache;
 #[allow(clippy::uninlined_format_args)]
 pub mod change_analyzer;
 #[allow(clippy::uninlined_format_args)]
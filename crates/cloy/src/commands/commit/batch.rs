@@ -0,0 +1,73 @@
+//! Output formats for regenerating the messages of a commit range
+
+use anyhow::Result;
+use clap::ValueEnum;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+/// How `--batch` prints the commit to message mapping
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, ValueEnum)]
+pub enum BatchFormat {
+    /// JSON object keyed by commit SHA
+    #[default]
+    Json,
+    /// Body for `git filter-repo --commit-callback`
+    FilterRepo,
+}
+
+/// A regenerated message for one commit
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BatchEntry {
+    pub commit: String,
+    pub message: String,
+}
+
+/// Renders the regenerated messages in the requested format
+pub fn format_batch(entries: &[BatchEntry], format: BatchFormat) -> Result<String> {
+    match format {
+        BatchFormat::Json => {
+            let mapping: BTreeMap<&str, &str> = entries
+                .iter()
+                .map(|entry| (entry.commit.as_str(), entry.message.as_str()))
+                .collect();
+            Ok(serde_json::to_string_pretty(&mapping)?)
+        }
+        BatchFormat::FilterRepo => {
+            // JSON string escapes are valid Python string literals
+            let mut script = String::from("messages = {\n");
+            for entry in entries {
+                writeln!(
+                    script,
+                    "    b\"{}\": {}.encode(),",
+                    entry.commit,
+                    serde_json::to_string(&format!("{}\n", entry.message.trim_end()))?
+                )?;
+            }
+            script.push_str("}\n");
+            script.push_str("if commit.original_id in messages:\n");
+            script.push_str("    commit.message = messages[commit.original_id]\n");
+            Ok(script)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_batch() {
+        let entries = vec![BatchEntry {
+            commit: "abc123".to_string(),
+            message: "fix: handle \"quotes\"\n\nBody".to_string(),
+        }];
+
+        let json = format_batch(&entries, BatchFormat::Json).expect("JSON output");
+        let parsed: BTreeMap<String, String> = serde_json::from_str(&json).expect("Valid JSON");
+        assert_eq!(parsed["abc123"], "fix: handle \"quotes\"\n\nBody");
+
+        let script = format_batch(&entries, BatchFormat::FilterRepo).expect("Script output");
+        assert!(script.contains(r#"b"abc123": "fix: handle \"quotes\"\n\nBody\n".encode(),"#));
+        assert!(script.ends_with("commit.message = messages[commit.original_id]\n"));
+    }
+}
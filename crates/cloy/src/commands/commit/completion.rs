@@ -2,6 +2,7 @@
 #![allow(clippy::as_conversions)]
 
 use super::git_service_core::GitServiceCore;
+use super::gitmoji::{GitmojiMode, apply_gitmoji};
 use super::prompt_helpers;
 use super::types::GeneratedMessage;
 use crate::common::get_combined_instructions;
@@ -94,7 +95,10 @@ impl CompletionService {
         // Create system prompt for completion
         let schema = schemars::schema_for!(GeneratedMessage);
         let schema_str = serde_json::to_string_pretty(&schema)?;
-        let instructions = get_combined_instructions(&config_clone);
+        let instructions = prompt_helpers::with_project_conventions(
+            get_combined_instructions(&config_clone),
+            &config_clone,
+        );
         let system_prompt =
             commit_prompts::create_completion_system_prompt(&instructions, &schema_str);
 
@@ -109,13 +113,17 @@ impl CompletionService {
             &prompt_helpers::format_enhanced_author_history(&context.author_history, &context),
         );
 
-        let generated_message = engine::get_message::<GeneratedMessage>(
+        let mut generated_message = engine::get_message::<GeneratedMessage>(
             &config_clone,
             self.core.provider_name(),
             &system_prompt,
             &final_user_prompt,
         )
         .await?;
+        apply_gitmoji(
+            GitmojiMode::from_config(&config_clone),
+            &mut generated_message,
+        );
 
         Ok(generated_message)
     }
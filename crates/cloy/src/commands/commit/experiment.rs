@@ -0,0 +1,145 @@
+//! A/B comparison of prompt variants against historical commits
+//!
+//! Each variant regenerates the messages of existing commits; the results are
+//! scored by word overlap with the message the author actually wrote and by
+//! whether they pass the project's conventions lint.
+
+use crate::config::Config;
+use crate::conventions::ProjectConventions;
+use anyhow::Result;
+use std::collections::HashSet;
+use std::fmt::Write;
+use std::path::PathBuf;
+
+/// Variant name that stands for the built-in prompt
+pub const DEFAULT_VARIANT: &str = "default";
+
+/// Prompts an experiment can vary; both feed commit message generation
+pub const EXPERIMENT_PROMPTS: &[&str] = &["commit-user", "commit-system"];
+
+/// Results collected for one prompt variant
+#[derive(Debug, Clone, Default)]
+pub struct VariantReport {
+    pub name: String,
+    similarities: Vec<f32>,
+    lint_passes: usize,
+    failures: usize,
+}
+
+impl VariantReport {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            ..Self::default()
+        }
+    }
+
+    /// Scores a generated message against the original, or counts a failed generation
+    pub fn record(
+        &mut self,
+        generated: Result<String>,
+        original: &str,
+        conventions: &ProjectConventions,
+    ) {
+        match generated {
+            Ok(message) => {
+                self.similarities.push(similarity(&message, original));
+                if conventions.lint(&message).is_empty() {
+                    self.lint_passes += 1;
+                }
+            }
+            Err(e) => {
+                log::debug!("Variant {} failed: {e}", self.name);
+                self.failures += 1;
+            }
+        }
+    }
+
+    /// Mean similarity of the generated messages, 0 when none succeeded
+    #[allow(clippy::cast_precision_loss, clippy::as_conversions)]
+    pub fn mean_similarity(&self) -> f32 {
+        if self.similarities.is_empty() {
+            return 0.0;
+        }
+        self.similarities.iter().sum::<f32>() / self.similarities.len() as f32
+    }
+
+    /// Share of generated messages that pass the lint
+    #[allow(clippy::cast_precision_loss, clippy::as_conversions)]
+    pub fn lint_pass_rate(&self) -> f32 {
+        if self.similarities.is_empty() {
+            return 0.0;
+        }
+        self.lint_passes as f32 / self.similarities.len() as f32
+    }
+}
+
+/// Dice coefficient of the lowercase word sets of two messages, from 0 to 1
+#[allow(clippy::cast_precision_loss, clippy::as_conversions)]
+pub fn similarity(a: &str, b: &str) -> f32 {
+    let words = |text: &str| -> HashSet<String> {
+        text.split(|c: char| !c.is_alphanumeric())
+            .filter(|w| !w.is_empty())
+            .map(str::to_lowercase)
+            .collect()
+    };
+    let (a, b) = (words(a), words(b));
+    if a.is_empty() && b.is_empty() {
+        return 1.0;
+    }
+    let shared = a.intersection(&b).count();
+    (2 * shared) as f32 / (a.len() + b.len()) as f32
+}
+
+/// Configuration that generates with a variant: a template file, or the built-in prompt
+pub fn config_for_variant(config: &Config, prompt: &str, variant: &str) -> Config {
+    let mut config = config.clone();
+    if variant == DEFAULT_VARIANT {
+        config.templates.remove(prompt);
+    } else {
+        config
+            .templates
+            .insert(prompt.to_string(), PathBuf::from(variant));
+    }
+    config
+}
+
+/// Renders the comparison as a Markdown table
+pub fn format_report(prompt: &str, commits: usize, variants: &[VariantReport]) -> String {
+    let mut report = format!("Prompt `{prompt}` over {commits} commits\n\n");
+    report.push_str("| Variant | Similarity | Lint pass rate | Failures |\n");
+    report.push_str("| --- | --- | --- | --- |\n");
+    for variant in variants {
+        let _ = writeln!(
+            report,
+            "| {} | {:.3} | {:.0}% | {} |",
+            variant.name,
+            variant.mean_similarity(),
+            variant.lint_pass_rate() * 100.0,
+            variant.failures
+        );
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_similarity_and_report() {
+        assert!((similarity("Fix the parser", "fix: the parser") - 1.0).abs() < f32::EPSILON);
+        assert!(similarity("add cache", "remove logging").abs() < f32::EPSILON);
+
+        let conventions = ProjectConventions::default();
+        let mut report = VariantReport::new("a.hbs");
+        report.record(Ok("fix parser".to_string()), "fix parser", &conventions);
+        report.record(Ok("Fix parser.".to_string()), "fix parser", &conventions);
+        report.record(Err(anyhow::anyhow!("timeout")), "fix parser", &conventions);
+
+        assert!((report.mean_similarity() - 1.0).abs() < f32::EPSILON);
+        assert!((report.lint_pass_rate() - 0.5).abs() < f32::EPSILON);
+        let table = format_report("commit-user", 3, &[report]);
+        assert!(table.contains("| a.hbs | 1.000 | 50% | 1 |"));
+    }
+}
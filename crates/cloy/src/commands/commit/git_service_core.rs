@@ -1,6 +1,7 @@
 use crate::config::Config;
 use crate::git::{CommitResult, GitRepo};
 use crate::llm::context::CommitContext;
+use crate::plugins::{HookPoint, PreCommit};
 
 use anyhow::Result;
 use log::debug;
@@ -56,6 +57,11 @@ impl GitServiceCore {
         Ok(context)
     }
 
+    /// Drop the cached context after the index or HEAD changed
+    pub async fn invalidate_git_info(&self) {
+        *self.cached_context.write().await = None;
+    }
+
     /// Get Git information including unstaged changes
     pub async fn get_git_info_with_unstaged(
         &self,
@@ -92,6 +98,10 @@ impl GitServiceCore {
 
     /// Performs a commit with the given message.
     ///
+    /// The configured trailers are added to the message, signing off as the
+    /// repository's `user.name` and `user.email`, and the result goes through
+    /// the `pre-commit` plugins, which can change or reject it.
+    ///
     /// # Arguments
     ///
     /// * `message` - The commit message.
@@ -112,6 +122,20 @@ impl GitServiceCore {
             return Err(anyhow::anyhow!("Cannot commit to a remote repository"));
         }
 
+        let message = self
+            .config
+            .trailers
+            .apply(message, self.signer().as_deref());
+        let message = &self
+            .config
+            .plugins
+            .run(
+                HookPoint::PreCommit,
+                PreCommit { message, amend },
+                self.repo.repo_path(),
+            )?
+            .message;
+
         debug!(
             "Performing commit with message: {message}, amend: {amend}, commit_ref: {commit_ref:?}"
         );
@@ -150,6 +174,13 @@ impl GitServiceCore {
         }
     }
 
+    /// `Name <email>` of the repository's configured user, for `Signed-off-by`
+    pub(super) fn signer(&self) -> Option<String> {
+        let repo = self.repo.open_repo().ok()?;
+        let signature = repo.signature().ok()?;
+        Some(format!("{} <{}>", signature.name()?, signature.email()?))
+    }
+
     /// Get a reference to the config
     #[inline]
     pub fn config(&self) -> &Config {
@@ -0,0 +1,249 @@
+//! Gitmoji post-processing for generated commit titles.
+//!
+//! Models use emoji inconsistently: some titles get one, some get a
+//! `:shortcode:`, some get none. Instead of relying on the prompt, titles are
+//! normalized after generation so the configured mode always holds.
+
+use super::types::GeneratedMessage;
+use crate::config::Config;
+use crate::llm::style::parse_conventional_prefix;
+
+/// Conventional commit type, emoji and gitmoji shortcode
+pub const GITMOJI_TABLE: &[(&str, &str, &str)] = &[
+    ("feat", "✨", ":sparkles:"),
+    ("fix", "🐛", ":bug:"),
+    ("docs", "📝", ":memo:"),
+    ("style", "🎨", ":art:"),
+    ("refactor", "♻️", ":recycle:"),
+    ("perf", "⚡️", ":zap:"),
+    ("test", "✅", ":white_check_mark:"),
+    ("build", "📦️", ":package:"),
+    ("ci", "👷", ":construction_worker:"),
+    ("chore", "🔧", ":wrench:"),
+    ("revert", "⏪️", ":rewind:"),
+    ("security", "🔒️", ":lock:"),
+    ("deps", "⬆️", ":arrow_up:"),
+    ("release", "🔖", ":bookmark:"),
+    ("remove", "🔥", ":fire:"),
+    ("wip", "🚧", ":construction:"),
+];
+
+/// Emoji and shortcode used for breaking changes (`type!:`)
+const BREAKING_EMOJI: &str = "💥";
+const BREAKING_SHORTCODE: &str = ":boom:";
+
+/// Leading verbs mapped to a type when the title is not conventional
+const VERB_TYPES: &[(&str, &str)] = &[
+    ("add", "feat"),
+    ("implement", "feat"),
+    ("introduce", "feat"),
+    ("support", "feat"),
+    ("fix", "fix"),
+    ("correct", "fix"),
+    ("resolve", "fix"),
+    ("document", "docs"),
+    ("refactor", "refactor"),
+    ("simplify", "refactor"),
+    ("rename", "refactor"),
+    ("extract", "refactor"),
+    ("optimize", "perf"),
+    ("speed", "perf"),
+    ("test", "test"),
+    ("revert", "revert"),
+    ("remove", "remove"),
+    ("delete", "remove"),
+    ("drop", "remove"),
+    ("bump", "deps"),
+    ("upgrade", "deps"),
+    ("update", "chore"),
+];
+
+/// How emoji in generated titles are handled
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GitmojiMode {
+    /// Prefix every title with the gitmoji for its type
+    Insert,
+    /// Remove a leading gitmoji or shortcode from the table
+    Strip,
+    /// Leave titles as generated
+    Keep,
+}
+
+impl GitmojiMode {
+    /// Resolves the mode from `gitai.gitmoji`, falling back to project conventions.
+    pub fn from_config(config: &Config) -> Self {
+        match config.gitmoji {
+            Some(true) => Self::Insert,
+            Some(false) => Self::Strip,
+            None if config.conventions.as_ref().is_some_and(|c| c.gitmoji) => Self::Insert,
+            None => Self::Keep,
+        }
+    }
+}
+
+/// Returns the emoji for a conventional commit type.
+pub fn emoji_for_type(kind: &str) -> Option<&'static str> {
+    GITMOJI_TABLE
+        .iter()
+        .find(|(t, _, _)| t.eq_ignore_ascii_case(kind))
+        .map(|(_, emoji, _)| *emoji)
+}
+
+/// Applies the gitmoji mode to a generated message title.
+pub fn apply_gitmoji(mode: GitmojiMode, message: &mut GeneratedMessage) {
+    message.title = match mode {
+        GitmojiMode::Insert => insert_gitmoji(&message.title),
+        GitmojiMode::Strip => strip_gitmoji(&message.title).to_string(),
+        GitmojiMode::Keep => return,
+    };
+}
+
+/// Replaces any leading emoji with the one matching the title's type.
+fn insert_gitmoji(title: &str) -> String {
+    let bare = strip_gitmoji(title);
+    let emoji = if is_breaking(bare) {
+        Some(BREAKING_EMOJI)
+    } else {
+        title_type(bare).and_then(emoji_for_type)
+    };
+
+    match emoji {
+        Some(emoji) => format!("{emoji} {bare}"),
+        // Unknown type: keep whatever the model chose rather than guessing
+        None => title.to_string(),
+    }
+}
+
+/// Removes a leading gitmoji or shortcode from the table, leaving any other
+/// text (including emoji gitai does not assign) in place
+fn strip_gitmoji(title: &str) -> &str {
+    let trimmed = title.trim_start();
+    let known = GITMOJI_TABLE
+        .iter()
+        .flat_map(|(_, emoji, code)| [*emoji, *code])
+        .chain([BREAKING_EMOJI, BREAKING_SHORTCODE]);
+    for prefix in known {
+        // Models drop or add the variation selector freely
+        let base = prefix.trim_end_matches('\u{FE0F}');
+        if let Some(rest) = trimmed.strip_prefix(base) {
+            return rest.trim_start_matches('\u{FE0F}').trim_start();
+        }
+    }
+    trimmed
+}
+
+fn is_breaking(title: &str) -> bool {
+    parse_conventional_prefix(title).is_some()
+        && title
+            .split_once(':')
+            .is_some_and(|(head, _)| head.ends_with('!'))
+}
+
+/// Infers the commit type from a conventional prefix or the leading verb
+fn title_type(title: &str) -> Option<&str> {
+    if parse_conventional_prefix(title).is_some() {
+        let head = title.split_once(':').map_or(title, |(head, _)| head);
+        return Some(head.split(['(', '!']).next().unwrap_or(head));
+    }
+
+    let forms = verb_forms(&title.split_whitespace().next()?.to_lowercase());
+    VERB_TYPES
+        .iter()
+        .find(|(v, _)| forms.iter().any(|form| form == v))
+        .map(|(_, t)| *t)
+}
+
+/// The word and the base forms it could be inflected from ("fixes" -> "fix",
+/// "dropped" -> "drop", "updating" -> "update")
+fn verb_forms(word: &str) -> Vec<String> {
+    const SUFFIXES: &[(&str, &str)] = &[
+        ("ies", "y"),
+        ("ied", "y"),
+        ("es", ""),
+        ("s", ""),
+        ("ed", ""),
+        ("d", ""),
+        ("ing", ""),
+        ("ing", "e"),
+    ];
+
+    let mut forms = vec![word.to_string()];
+    for (suffix, replacement) in SUFFIXES {
+        let Some(stem) = word.strip_suffix(suffix) else {
+            continue;
+        };
+        let stem = format!("{stem}{replacement}");
+        // A doubled final consonant is undone: "dropp" -> "drop"
+        let bytes = stem.as_bytes();
+        if stem.is_ascii() && bytes.len() >= 2 && bytes[bytes.len() - 1] == bytes[bytes.len() - 2] {
+            forms.push(stem[..stem.len() - 1].to_string());
+        }
+        forms.push(stem);
+    }
+    forms
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply(mode: GitmojiMode, title: &str) -> String {
+        let mut message = GeneratedMessage {
+            title: title.to_string(),
+            message: String::new(),
+        };
+        apply_gitmoji(mode, &mut message);
+        message.title
+    }
+
+    #[test]
+    fn test_insert_gitmoji() {
+        assert_eq!(
+            apply(GitmojiMode::Insert, "feat(tui): add history"),
+            "✨ feat(tui): add history"
+        );
+        assert_eq!(
+            apply(GitmojiMode::Insert, ":bug: fix: crash on start"),
+            "🐛 fix: crash on start"
+        );
+        assert_eq!(
+            apply(GitmojiMode::Insert, "feat!: drop v1 api"),
+            "💥 feat!: drop v1 api"
+        );
+        assert_eq!(
+            apply(GitmojiMode::Insert, "Remove dead code"),
+            "🔥 Remove dead code"
+        );
+        assert_eq!(
+            apply(GitmojiMode::Insert, "Fixes crash on start"),
+            "🐛 Fixes crash on start"
+        );
+        assert_eq!(
+            apply(GitmojiMode::Insert, "Dropped the v1 api"),
+            "🔥 Dropped the v1 api"
+        );
+        assert_eq!(
+            apply(GitmojiMode::Insert, "Simplified the parser"),
+            "♻️ Simplified the parser"
+        );
+        assert_eq!(
+            apply(GitmojiMode::Insert, "Updating docs"),
+            "🔧 Updating docs"
+        );
+        assert_eq!(apply(GitmojiMode::Insert, "Misc tweaks"), "Misc tweaks");
+        // Verbs match whole words, not prefixes
+        assert_eq!(apply(GitmojiMode::Insert, "Extras for x"), "Extras for x");
+    }
+
+    #[test]
+    fn test_strip_gitmoji() {
+        assert_eq!(apply(GitmojiMode::Strip, "✨ feat: add x"), "feat: add x");
+        assert_eq!(apply(GitmojiMode::Strip, "♻️ refactor: y"), "refactor: y");
+        assert_eq!(apply(GitmojiMode::Strip, ":zap: speed up"), "speed up");
+        assert_eq!(apply(GitmojiMode::Strip, "♻ refactor: y"), "refactor: y");
+        assert_eq!(apply(GitmojiMode::Keep, "✨ feat: add x"), "✨ feat: add x");
+        // Only known gitmoji are removed
+        assert_eq!(apply(GitmojiMode::Strip, ":TODO: note"), ":TODO: note");
+        assert_eq!(apply(GitmojiMode::Strip, "→ fix: y"), "→ fix: y");
+    }
+}
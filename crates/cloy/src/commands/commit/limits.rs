@@ -0,0 +1,179 @@
+//! Length limits of generated commit messages.
+//!
+//! Some models ignore the detail level, so the limits are stated in the
+//! system prompt and then enforced on the message that comes back.
+
+use super::types::GeneratedMessage;
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+
+/// Limits from `gitai.max-bullets`, `gitai.max-subject-length` and
+/// `gitai.max-length`; `None` leaves a dimension unlimited
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct MessageLimits {
+    /// Top-level bullet points in the body
+    pub max_bullets: Option<usize>,
+    /// Characters in the subject line
+    pub max_subject: Option<usize>,
+    /// Characters in the subject, blank line and body together
+    pub max_length: Option<usize>,
+}
+
+impl MessageLimits {
+    /// The limits as rules for the system prompt, or an empty string
+    pub fn instructions(&self) -> String {
+        let mut rules = String::new();
+        if let Some(max) = self.max_subject {
+            writeln!(
+                rules,
+                "- The subject line must not exceed {max} characters."
+            )
+            .ok();
+        }
+        if let Some(max) = self.max_bullets {
+            writeln!(
+                rules,
+                "- The body must not have more than {max} bullet points."
+            )
+            .ok();
+        }
+        if let Some(max) = self.max_length {
+            writeln!(
+                rules,
+                "- The whole message, subject and body, must not exceed {max} characters."
+            )
+            .ok();
+        }
+        if rules.is_empty() {
+            return rules;
+        }
+        format!("Length limits:\n{rules}\n")
+    }
+
+    /// Cuts `message` down to the limits the model did not keep to
+    pub fn enforce(&self, message: &mut GeneratedMessage) {
+        if let Some(max) = self.max_subject {
+            message.title = truncate_words(&message.title, max);
+        }
+        if let Some(max) = self.max_bullets {
+            message.message = keep_bullets(&message.message, max);
+        }
+        if let Some(max) = self.max_length {
+            message.title = truncate_words(&message.title, max);
+            let subject = message.title.chars().count() + 2;
+            message.message = keep_lines(&message.message, max.saturating_sub(subject));
+        }
+    }
+}
+
+/// `text` cut to `max` characters at a word boundary where there is one
+fn truncate_words(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        return text.to_string();
+    }
+    let cut: String = text.chars().take(max + 1).collect();
+    let end = cut.rfind(char::is_whitespace).unwrap_or_else(|| {
+        cut.char_indices()
+            .nth(max)
+            .map_or(cut.len(), |(index, _)| index)
+    });
+    cut[..end]
+        .trim_end_matches(|c: char| c.is_whitespace() || matches!(c, ',' | ';' | ':' | '-'))
+        .to_string()
+}
+
+fn is_bullet(line: &str) -> bool {
+    ["- ", "* ", "+ "]
+        .iter()
+        .any(|marker| line.starts_with(marker))
+}
+
+/// `body` without the top-level bullets after the first `max`, and the
+/// indented lines that continue them
+fn keep_bullets(body: &str, max: usize) -> String {
+    let mut bullets = 0;
+    let mut dropping = false;
+    let mut kept = Vec::new();
+    for line in body.lines() {
+        if is_bullet(line) {
+            bullets += 1;
+            dropping = bullets > max;
+        } else if !line.starts_with(char::is_whitespace) {
+            dropping = false;
+        }
+        if !dropping {
+            kept.push(line);
+        }
+    }
+    if bullets <= max {
+        return body.to_string();
+    }
+    kept.join("\n").trim_end().to_string()
+}
+
+/// The leading lines of `body` that fit in `max` characters
+fn keep_lines(body: &str, max: usize) -> String {
+    if body.chars().count() <= max {
+        return body.to_string();
+    }
+    let mut kept = String::new();
+    for line in body.lines() {
+        let separator = usize::from(!kept.is_empty());
+        if kept.chars().count() + separator + line.chars().count() > max {
+            break;
+        }
+        if separator == 1 {
+            kept.push('\n');
+        }
+        kept.push_str(line);
+    }
+    kept.trim_end().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(title: &str, body: &str) -> GeneratedMessage {
+        GeneratedMessage {
+            title: title.to_string(),
+            message: body.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_enforce_limits() {
+        let limits = MessageLimits {
+            max_bullets: Some(2),
+            max_subject: Some(30),
+            max_length: None,
+        };
+        let mut generated = message(
+            "feat(parser): support nested arrays in the config",
+            "Parses arrays of tables.\n\n- adds a lexer state\n  for nested brackets\n- reports the line of errors\n- caches parsed files\n  by path\n\nCloses #12",
+        );
+        limits.enforce(&mut generated);
+        assert_eq!(generated.title, "feat(parser): support nested");
+        assert_eq!(
+            generated.message,
+            "Parses arrays of tables.\n\n- adds a lexer state\n  for nested brackets\n- reports the line of errors\n\nCloses #12"
+        );
+
+        let limits = MessageLimits {
+            max_length: Some(40),
+            ..MessageLimits::default()
+        };
+        let mut generated = message(
+            "fix: handle empty input",
+            "Returns early.\nLogs a warning for it.",
+        );
+        limits.enforce(&mut generated);
+        assert_eq!(generated.title, "fix: handle empty input");
+        assert_eq!(generated.message, "Returns early.");
+
+        assert_eq!(truncate_words("refactor:everything", 8), "refactor");
+        assert!(MessageLimits::default().instructions().is_empty());
+        assert!(limits.instructions().contains("40 characters"));
+    }
+}
@@ -1,7 +1,14 @@
+pub mod batch;
 #[allow(clippy::uninlined_format_args)]
 pub mod completion;
+pub mod experiment;
 mod git_service_core;
+pub mod gitmoji;
+pub mod limits;
+pub mod post_process;
 pub mod prompt_helpers;
+pub mod special;
+pub mod spelling;
 pub mod types;
 
 #[allow(clippy::uninlined_format_args)]
@@ -17,9 +24,15 @@ use crate::git::CommitResult;
 use std::fmt::Write;
 
 pub fn format_commit_result(result: &CommitResult, message: &str) -> String {
+    let root = if result.root_commit {
+        " (root-commit)"
+    } else {
+        ""
+    };
     let mut output = format!(
-        "[{} {}] {}\n",
+        "[{}{} {}] {}\n",
         result.branch,
+        root,
         result.commit_hash,
         message.lines().next().unwrap_or("")
     );
@@ -0,0 +1,381 @@
+//! Post-processing of generated messages.
+//!
+//! The built-in steps listed in `gitai.post-process` run first, in the order
+//! listed. Then come the post-processors registered by tools embedding gitai
+//! and by WASM plugins, in the order they were registered, before the
+//! `message-generated` plugin commands see the message.
+
+use super::types::GeneratedMessage;
+use crate::config::Config;
+use crate::llm::context::is_imperative_verb;
+use crate::llm::engine;
+use crate::llm::style::{first_description_word, parse_conventional_prefix, strip_leading_emoji};
+use crate::trailers::{TrailerSettings, parse_list};
+use anyhow::Result;
+use log::debug;
+use parking_lot::RwLock;
+use regex::{Regex, RegexBuilder};
+use std::sync::{Arc, LazyLock};
+
+const IMPERATIVE_SYSTEM_PROMPT: &str = "You rewrite commit subjects in the imperative mood, as \
+    in `fix: handle empty input` rather than `fix: handled empty input`. Keep any type, scope \
+    and emoji prefix and the meaning unchanged. Reply with the subject only.";
+
+/// A built-in post-processing step
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PostProcessStep {
+    /// Drops trailing whitespace and extra blank lines
+    TrimWhitespace,
+    /// Starts the subject's description with a capital letter
+    CapitalizeSubject,
+    /// Puts the subject's first verb in the imperative mood
+    Imperative,
+    /// Removes the words in `gitai.forbidden-words`
+    ForbiddenWords,
+    /// Shows the configured trailers in the message before committing
+    Trailers,
+}
+
+impl PostProcessStep {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "trim-whitespace" => Some(Self::TrimWhitespace),
+            "capitalize-subject" => Some(Self::CapitalizeSubject),
+            "imperative" => Some(Self::Imperative),
+            "forbidden-words" => Some(Self::ForbiddenWords),
+            "trailers" => Some(Self::Trailers),
+            _ => None,
+        }
+    }
+}
+
+/// The steps of `gitai.post-process` and the words they remove
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PostProcessSettings {
+    pub steps: Vec<PostProcessStep>,
+    pub forbidden_words: Vec<String>,
+}
+
+impl Default for PostProcessSettings {
+    fn default() -> Self {
+        Self {
+            steps: vec![PostProcessStep::TrimWhitespace],
+            forbidden_words: Vec::new(),
+        }
+    }
+}
+
+impl PostProcessSettings {
+    /// Settings from `gitai.post-process` and `gitai.forbidden-words`, both
+    /// comma-separated; an empty `gitai.post-process` turns every step off
+    pub fn from_values(steps: Option<&str>, forbidden_words: Option<&str>) -> Self {
+        let steps = steps.map_or_else(
+            || Self::default().steps,
+            |steps| {
+                parse_list(steps)
+                    .iter()
+                    .filter_map(|name| {
+                        let step = PostProcessStep::from_name(name);
+                        if step.is_none() {
+                            debug!("Ignoring unknown post-processing step {name:?}");
+                        }
+                        step
+                    })
+                    .collect()
+            },
+        );
+        Self {
+            steps,
+            forbidden_words: forbidden_words.map(parse_list).unwrap_or_default(),
+        }
+    }
+}
+
+/// Runs the configured built-in steps on `message`.
+///
+/// `signer` signs off the trailers, and `provider_name` rewrites subjects the
+/// imperative step cannot fix by itself.
+pub async fn apply_steps(
+    config: &Config,
+    provider_name: &str,
+    signer: Option<&str>,
+    mut message: GeneratedMessage,
+) -> GeneratedMessage {
+    for step in &config.post_process.steps {
+        match step {
+            PostProcessStep::TrimWhitespace => trim_whitespace(&mut message),
+            PostProcessStep::CapitalizeSubject => {
+                message.title = capitalize_subject(&message.title);
+            }
+            PostProcessStep::Imperative => {
+                message.title = imperative_subject(config, provider_name, &message.title).await;
+            }
+            PostProcessStep::ForbiddenWords => {
+                remove_words(&mut message, &config.post_process.forbidden_words);
+            }
+            PostProcessStep::Trailers => append_trailers(&config.trailers, signer, &mut message),
+        }
+    }
+    message
+}
+
+fn trim_whitespace(message: &mut GeneratedMessage) {
+    message.title = message.title.trim().to_string();
+    let mut body = String::new();
+    let mut blank = false;
+    for line in message.message.lines().map(str::trim_end) {
+        if line.is_empty() {
+            blank = !body.is_empty();
+            continue;
+        }
+        if blank {
+            body.push('\n');
+            blank = false;
+        }
+        if !body.is_empty() {
+            body.push('\n');
+        }
+        body.push_str(line);
+    }
+    message.message = body;
+}
+
+/// Byte offset of the subject's description, past any emoji and conventional prefix
+fn description_start(title: &str) -> usize {
+    let stripped = strip_leading_emoji(title);
+    let start = title.len() - stripped.len();
+    if parse_conventional_prefix(stripped).is_none() {
+        return start;
+    }
+    stripped.find(':').map_or(start, |colon| {
+        let rest = &stripped[colon + 1..];
+        start + colon + 1 + (rest.len() - rest.trim_start().len())
+    })
+}
+
+fn capitalize_subject(title: &str) -> String {
+    let (head, description) = title.split_at(description_start(title));
+    let mut chars = description.chars();
+    chars.next().map_or_else(
+        || title.to_string(),
+        |first| format!("{head}{}{}", first.to_uppercase(), chars.as_str()),
+    )
+}
+
+/// The imperative form of a past tense, gerund or third person `word` gitai
+/// knows, such as `add` for `Added`
+fn imperative_form(word: &str) -> Option<String> {
+    let lower = word.to_lowercase();
+    let stems = [
+        ("ied", "y"),
+        ("ies", "y"),
+        ("ed", ""),
+        ("ed", "e"),
+        ("ing", ""),
+        ("ing", "e"),
+        ("es", ""),
+        ("s", ""),
+    ];
+    let verb = stems.iter().find_map(|(suffix, ending)| {
+        let stem = lower.strip_suffix(suffix)?;
+        let verb = format!("{stem}{ending}");
+        is_imperative_verb(&verb).then_some(verb)
+    })?;
+    let mut chars = verb.chars();
+    let first = chars.next()?;
+    Some(if word.starts_with(char::is_uppercase) {
+        format!("{}{}", first.to_uppercase(), chars.as_str())
+    } else {
+        verb
+    })
+}
+
+/// Whether `word` reads like a past tense, gerund or third person verb
+fn looks_non_imperative(word: &str) -> bool {
+    let lower = word.to_lowercase();
+    lower.ends_with("ed")
+        || lower.ends_with("ing")
+        || (lower.ends_with('s') && !lower.ends_with("ss"))
+}
+
+/// `title` with its first verb in the imperative mood, asking the model when
+/// the verb is not one gitai knows
+async fn imperative_subject(config: &Config, provider_name: &str, title: &str) -> String {
+    let Some(word) = first_description_word(title) else {
+        return title.to_string();
+    };
+    if is_imperative_verb(word) || !looks_non_imperative(word) {
+        return title.to_string();
+    }
+    let start = description_start(title);
+    if let Some(verb) = imperative_form(word) {
+        return format!("{}{verb}{}", &title[..start], &title[start + word.len()..]);
+    }
+    match engine::get_message::<String>(config, provider_name, IMPERATIVE_SYSTEM_PROMPT, title)
+        .await
+    {
+        Ok(rewritten) => rewritten
+            .lines()
+            .map(|line| line.trim().trim_matches('`'))
+            .find(|line| !line.is_empty())
+            .map_or_else(|| title.to_string(), ToString::to_string),
+        Err(e) => {
+            debug!("Keeping the subject; the imperative rewrite failed: {e:#}");
+            title.to_string()
+        }
+    }
+}
+
+static EXTRA_SPACES: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"[ \t]{2,}| +([,.;:!?])").expect("Valid regex"));
+
+fn remove_words(message: &mut GeneratedMessage, words: &[String]) {
+    let strip = |text: &str| {
+        let mut text = text.to_string();
+        for word in words {
+            let pattern = format!(r"\b{}\b", regex::escape(word));
+            if let Ok(regex) = RegexBuilder::new(&pattern).case_insensitive(true).build() {
+                text = regex.replace_all(&text, "").into_owned();
+            }
+        }
+        let lines: Vec<String> = text
+            .lines()
+            .map(|line| {
+                EXTRA_SPACES
+                    .replace_all(line, |caps: &regex::Captures| {
+                        caps.get(1)
+                            .map_or_else(|| " ".to_string(), |p| p.as_str().to_string())
+                    })
+                    .trim_end()
+                    .to_string()
+            })
+            .collect();
+        lines.join("\n")
+    };
+    message.title = strip(&message.title).trim().to_string();
+    message.message = strip(&message.message);
+}
+
+fn append_trailers(
+    trailers: &TrailerSettings,
+    signer: Option<&str>,
+    message: &mut GeneratedMessage,
+) {
+    let lines = trailers.lines(signer);
+    if lines.is_empty() {
+        return;
+    }
+    message.message = if message.message.trim().is_empty() {
+        lines.join("\n")
+    } else {
+        crate::trailers::append_trailers(&message.message, &lines)
+    };
+}
+
+/// Rewrites or rejects a generated message
+pub trait MessagePostProcessor: Send + Sync {
+    /// Short name shown in logs and errors
+    fn name(&self) -> &str;
+
+    /// The message to use instead of `message`; an error rejects it
+    fn process(&self, message: GeneratedMessage) -> Result<GeneratedMessage>;
+}
+
+static PROCESSORS: LazyLock<RwLock<Vec<Arc<dyn MessagePostProcessor>>>> =
+    LazyLock::new(|| RwLock::new(Vec::new()));
+
+/// Runs `processor` on every message generated after this call
+pub fn register_post_processor(processor: Arc<dyn MessagePostProcessor>) {
+    PROCESSORS.write().push(processor);
+}
+
+/// Passes `message` through the registered post-processors
+pub fn post_process(mut message: GeneratedMessage) -> Result<GeneratedMessage> {
+    let processors = PROCESSORS.read().clone();
+    for processor in processors {
+        log::debug!("Post-processing the message with {}", processor.name());
+        message = processor.process(message)?;
+    }
+    Ok(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(title: &str, body: &str) -> GeneratedMessage {
+        GeneratedMessage {
+            title: title.to_string(),
+            message: body.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_local_steps() {
+        let mut generated = message(
+            "  fix: handle input  ",
+            "\nFirst line.  \n\n\n\nSecond line.\t\n\n",
+        );
+        trim_whitespace(&mut generated);
+        assert_eq!(
+            generated,
+            message("fix: handle input", "First line.\n\nSecond line.")
+        );
+
+        assert_eq!(
+            capitalize_subject("fix(api): handle input"),
+            "fix(api): Handle input"
+        );
+        assert_eq!(capitalize_subject("✨ feat: add bye"), "✨ feat: Add bye");
+        assert_eq!(capitalize_subject("update readme"), "Update readme");
+
+        assert_eq!(imperative_form("Added").as_deref(), Some("Add"));
+        assert_eq!(imperative_form("removing").as_deref(), Some("remove"));
+        assert_eq!(imperative_form("fixes").as_deref(), Some("fix"));
+        assert_eq!(imperative_form("simplified").as_deref(), Some("simplify"));
+        assert_eq!(imperative_form("frobnicated"), None);
+        assert!(!looks_non_imperative("process"));
+
+        let mut generated = message(
+            "feat: simply add a very basic cache",
+            "This is simply a cache , really.",
+        );
+        remove_words(&mut generated, &["simply".to_string(), "very".to_string()]);
+        assert_eq!(
+            generated,
+            message("feat: add a basic cache", "This is a cache, really.")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_steps_run_in_configured_order() {
+        let mut config = Config {
+            post_process: PostProcessSettings::from_values(
+                Some("imperative, capitalize-subject, trailers, bogus"),
+                None,
+            ),
+            ..Config::default()
+        };
+        config
+            .trailers
+            .add_trailer("Reviewed-by: Ann <ann@example.com>")
+            .expect("Valid trailer");
+        let generated = apply_steps(
+            &config,
+            "google",
+            None,
+            message("fix(api): added retries", "Retries twice."),
+        )
+        .await;
+        assert_eq!(generated.title, "fix(api): Add retries");
+        assert_eq!(
+            generated.message,
+            "Retries twice.\n\nReviewed-by: Ann <ann@example.com>"
+        );
+        assert!(
+            PostProcessSettings::from_values(Some(""), None)
+                .steps
+                .is_empty()
+        );
+    }
+}
@@ -1,8 +1,15 @@
-use crate::llm::context::{ChangeType, CommitContext, RecentCommit, StagedFile};
+use crate::analyzers::ManifestAnalyzer;
+use crate::config::Config;
+use crate::git::ProjectMetadata;
+use crate::llm::context::{ChangeType, CommitContext, RecentCommit, RelatedFile, StagedFile};
+use anyhow::{Context, Result};
+use serde_json::{Value, json};
 
 const MAX_DIFF_LENGTH: usize = 2000;
 const MAX_FILE_CONTENT_LENGTH: usize = 5000;
 const MAX_FILES_FOR_DETAILED_CHANGES: usize = 30;
+const MAX_RELATED_CONTEXT_LENGTH: usize = 4000;
+const MAX_STYLE_EXAMPLES: usize = 3;
 
 pub fn format_recent_commits(commits: &[RecentCommit]) -> String {
     commits
@@ -26,6 +33,30 @@ pub fn format_staged_files(files: &[StagedFile]) -> String {
         .join("\n")
 }
 
+/// One line per file with its change type and added and removed line counts
+pub fn format_change_summary(files: &[StagedFile]) -> String {
+    files
+        .iter()
+        .map(|file| {
+            let (added, removed) =
+                file.diff
+                    .lines()
+                    .fold((0, 0), |(added, removed), line| match line.as_bytes() {
+                        [b'+', b'+', b'+', ..] | [b'-', b'-', b'-', ..] => (added, removed),
+                        [b'+', ..] => (added + 1, removed),
+                        [b'-', ..] => (added, removed + 1),
+                        _ => (added, removed),
+                    });
+            format!(
+                "{} - {} (+{added} -{removed})",
+                file.path,
+                format_change_type(&file.change_type)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
 pub fn format_detailed_changes(files: &[StagedFile]) -> String {
     let mut all_sections = Vec::new();
 
@@ -119,6 +150,35 @@ pub fn format_detailed_changes(files: &[StagedFile]) -> String {
     all_sections.join("\n\n====================\n\n")
 }
 
+/// Formats unchanged related files, dropping whole files once the budget is spent.
+///
+/// Related files are the lowest-priority context: they only frame the diff, so
+/// they get a small fixed budget instead of competing with the hunks.
+pub fn format_related_files(files: &[RelatedFile]) -> String {
+    if files.is_empty() {
+        return "No related files.".to_string();
+    }
+
+    let mut sections = Vec::new();
+    let mut used = 0;
+    for file in files {
+        let section = format!(
+            "File: {} ({})\n```\n{}\n```",
+            file.path, file.reason, file.excerpt
+        );
+        if used + section.len() > MAX_RELATED_CONTEXT_LENGTH {
+            break;
+        }
+        used += section.len();
+        sections.push(section);
+    }
+
+    if sections.is_empty() {
+        return "No related files.".to_string();
+    }
+    sections.join("\n\n")
+}
+
 fn format_change_type(change_type: &ChangeType) -> String {
     match change_type {
         ChangeType::Added => "Added".to_string(),
@@ -130,6 +190,22 @@ fn format_change_type(change_type: &ChangeType) -> String {
 }
 
 pub fn format_enhanced_author_history(history: &[String], context: &CommitContext) -> String {
+    // A profile already condenses the author's habits; a few subjects are
+    // enough to show the tone without paying for the whole history.
+    if let Some(profile) = &context.style_profile {
+        let examples = history
+            .iter()
+            .take(MAX_STYLE_EXAMPLES)
+            .map(|msg| format!("- {}", msg.lines().next().unwrap_or("")))
+            .collect::<Vec<_>>()
+            .join("\n");
+        return if examples.is_empty() {
+            profile.summary()
+        } else {
+            format!("{}\n\nRecent subjects:\n{examples}", profile.summary())
+        };
+    }
+
     if history.is_empty() {
         "No previous commits found for this author.".to_string()
     } else {
@@ -173,3 +249,307 @@ fn truncate_smartly(text: &str, max_len: usize) -> String {
 
     result
 }
+
+/// Appends the project's commit conventions and the configured length
+/// limits to the combined instructions.
+pub fn with_project_conventions(mut instructions: String, config: &Config) -> String {
+    if let Some(conventions) = &config.conventions {
+        let rules = conventions.as_instructions();
+        if !rules.is_empty() {
+            instructions.push_str(&rules);
+            instructions.push_str("\n\n");
+        }
+    }
+    instructions.push_str(&config.limits.instructions());
+    instructions
+}
+
+/// The ticket the branch refers to, or an empty string
+pub fn format_issue(context: &CommitContext) -> String {
+    let Some(issue) = &context.issue else {
+        return String::new();
+    };
+    let url = if issue.url.is_empty() {
+        String::new()
+    } else {
+        format!(" <{}>", issue.url)
+    };
+    let summary = if issue.summary.is_empty() {
+        String::new()
+    } else {
+        format!("\n\n{}", issue.summary)
+    };
+    format!("{} {}{url}{summary}", issue.reference, issue.title)
+}
+
+/// Adds the linked issue to a built-in user prompt, ahead of its data section.
+///
+/// The issue stays the same for every message on a branch, so it goes with the
+/// stable sections that providers can cache.
+pub fn with_issue(prompt: String, context: &CommitContext) -> String {
+    let issue = format_issue(context);
+    if issue.is_empty() {
+        return prompt;
+    }
+    let section = format!(
+        "#### LINKED ISSUE (the requirement this work implements)\n{issue}\n\n\
+         Reference the issue where it explains the change; do not restate it.\n\n"
+    );
+    insert_before_data(prompt, &section)
+}
+
+/// Puts `section` ahead of the data section of a built-in user prompt
+fn insert_before_data(mut prompt: String, section: &str) -> String {
+    let at = prompt.find("#### DATA CONTEXT").unwrap_or(prompt.len());
+    prompt.insert_str(at, section);
+    prompt
+}
+
+/// The project's languages, frameworks and purpose, or an empty string
+pub fn format_project(context: &CommitContext) -> String {
+    context
+        .project
+        .as_ref()
+        .map(ProjectMetadata::overview)
+        .unwrap_or_default()
+}
+
+/// Adds the project overview to a built-in user prompt, ahead of its data section.
+///
+/// Like the issue, it rarely changes, so it goes with the cacheable sections.
+pub fn with_project(prompt: String, context: &CommitContext) -> String {
+    let overview = format_project(context);
+    if overview.is_empty() {
+        return prompt;
+    }
+    let section = format!(
+        "#### PROJECT OVERVIEW (from the build files and README)\n{overview}\n\n\
+         Use it to name things the way the project does; do not describe the project.\n\n"
+    );
+    insert_before_data(prompt, &section)
+}
+
+/// The CODEOWNERS owners of each changed file, one file per line
+pub fn format_owners(context: &CommitContext) -> String {
+    context
+        .owners
+        .iter()
+        .map(|file| format!("{}: {}", file.path, file.owners.join(" ")))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Adds the owners of the changed files to a built-in user prompt, ahead of its data section
+pub fn with_owners(prompt: String, context: &CommitContext) -> String {
+    let owners = format_owners(context);
+    if owners.is_empty() {
+        return prompt;
+    }
+    let section = format!(
+        "#### CODE OWNERS (teams owning the changed files, from CODEOWNERS)\n{owners}\n\n\
+         Use them to name the areas touched; do not list owners in a commit message.\n\n"
+    );
+    insert_before_data(prompt, &section)
+}
+
+/// The possibly breaking API changes found in the diff, one per line
+pub fn format_api_changes(context: &CommitContext) -> String {
+    context
+        .api_changes
+        .iter()
+        .map(|change| format!("- {change}"))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Adds the breaking API changes to a built-in user prompt, ahead of its data section
+pub fn with_api_changes(prompt: String, context: &CommitContext) -> String {
+    let changes = format_api_changes(context);
+    if changes.is_empty() {
+        return prompt;
+    }
+    let section = format!(
+        "#### POSSIBLY BREAKING API CHANGES (public items, endpoints and schema types removed or narrowed, found in the diff)\n{changes}\n\n\
+         These were matched line by line and may include items that only moved or are not \
+         visible to other crates or clients. Check them against the diff; for those that do \
+         break callers, end the message with a `BREAKING CHANGE:` footer that says what \
+         callers must change, and list them under breaking changes in a PR description.\n\n"
+    );
+    insert_before_data(prompt, &section)
+}
+
+/// The changed source files whose tests did not change, one per line
+pub fn format_test_gaps(context: &CommitContext) -> String {
+    context
+        .test_gaps
+        .iter()
+        .map(|gap| format!("- {gap}"))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Adds the testing gaps to a built-in user prompt, ahead of its data section
+pub fn with_test_gaps(prompt: String, context: &CommitContext) -> String {
+    let gaps = format_test_gaps(context);
+    if gaps.is_empty() {
+        return prompt;
+    }
+    let section = format!(
+        "#### TESTING GAPS (source files changed without changes to their tests)\n{gaps}\n\n\
+         In a PR description, point these out in the testing notes; leave them out of commit messages.\n\n"
+    );
+    insert_before_data(prompt, &section)
+}
+
+/// Adds the guessed kind of change to a built-in user prompt, ahead of its data section
+pub fn with_change_kind(prompt: String, context: &CommitContext) -> String {
+    let Some(kind) = context.change_kind else {
+        return prompt;
+    };
+    let section = format!(
+        "#### CHANGE KIND (guessed from the changed paths and line counts)\n{kind}\n\n\
+         Treat it as a hint for the type and wording; the diff decides.\n\n"
+    );
+    insert_before_data(prompt, &section)
+}
+
+/// The dependencies the changed manifests add, remove or bump, one per line
+pub fn format_dependencies(context: &CommitContext) -> String {
+    context
+        .analyses
+        .iter()
+        .filter(|analysis| analysis.analyzer == ManifestAnalyzer::NAME)
+        .flat_map(|analysis| {
+            analysis
+                .notes
+                .iter()
+                .map(move |note| format!("- {}: {note}", analysis.path))
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Adds the dependency changes to a built-in user prompt, ahead of its data section
+pub fn with_dependencies(prompt: String, context: &CommitContext) -> String {
+    let dependencies = format_dependencies(context);
+    if dependencies.is_empty() {
+        return prompt;
+    }
+    let section = format!(
+        "#### DEPENDENCIES (added, removed and bumped, read from the changed manifests)\n{dependencies}\n\n\
+         Name notable ones in a commit message; list them all under dependencies in a PR description.\n\n"
+    );
+    insert_before_data(prompt, &section)
+}
+
+/// The analyzers' notes on changed files other than manifests, one file per entry
+pub fn format_analyses(context: &CommitContext) -> String {
+    context
+        .analyses
+        .iter()
+        .filter(|analysis| analysis.analyzer != ManifestAnalyzer::NAME)
+        .map(|analysis| format!("- {analysis}"))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Adds the format-specific notes on changed files to a built-in user prompt, ahead of its data section
+pub fn with_analyses(prompt: String, context: &CommitContext) -> String {
+    let analyses = format_analyses(context);
+    if analyses.is_empty() {
+        return prompt;
+    }
+    let section = format!(
+        "#### FORMAT-SPECIFIC CHANGES (read from the changed files by format)\n{analyses}\n\n\
+         Prefer these to the raw diff for what the files change. Call out schema changes from \
+         migrations by name, and say when one drops data or cannot be rolled back.\n\n"
+    );
+    insert_before_data(prompt, &section)
+}
+
+/// Adds the failed CI run's error excerpts to a built-in user prompt, ahead of its data section
+pub fn with_ci_failure(prompt: String, context: &CommitContext) -> String {
+    let Some(excerpt) = &context.ci_failure else {
+        return prompt;
+    };
+    let section = format!(
+        "#### CI FAILURE (error excerpts from the failed CI run this change fixes)\n```\n{excerpt}\n```\n\n\
+         Say what was failing and why, and how the change fixes it; do not paste the log.\n\n"
+    );
+    insert_before_data(prompt, &section)
+}
+
+/// The `--context-file` files, each under its path, or an empty string
+pub fn format_context_files(context: &CommitContext) -> String {
+    context
+        .context_files
+        .iter()
+        .map(|file| format!("##### {}\n```\n{}\n```", file.path, file.content.trim_end()))
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// Adds the `--context-file` files to a built-in user prompt, ahead of its data section
+pub fn with_context_files(prompt: String, context: &CommitContext) -> String {
+    let files = format_context_files(context);
+    if files.is_empty() {
+        return prompt;
+    }
+    let section = format!(
+        "#### ADDITIONAL CONTEXT (files the author supplied, such as design docs or tickets)\n{files}\n\n\
+         Use these for the requirements and intent behind the change when the diff bears them out; \
+         do not describe anything the diff does not change.\n\n"
+    );
+    insert_before_data(prompt, &section)
+}
+
+/// Adds every section derived from the context to a built-in user prompt
+pub fn with_context_sections(prompt: String, context: &CommitContext) -> String {
+    let prompt = with_owners(with_issue(with_project(prompt, context), context), context);
+    let prompt = with_test_gaps(with_api_changes(prompt, context), context);
+    let prompt = with_analyses(with_change_kind(prompt, context), context);
+    let prompt = with_dependencies(prompt, context);
+    with_context_files(with_ci_failure(prompt, context), context)
+}
+
+/// Variables every commit-context template can use.
+pub fn context_template_vars(context: &CommitContext) -> Value {
+    json!({
+        "branch": context.branch,
+        "staged_files": format_staged_files(&context.staged_files),
+        "diffs": format_detailed_changes(&context.staged_files),
+        "recent_commits": format_recent_commits(&context.recent_commits),
+        "author_history": format_enhanced_author_history(&context.author_history, context),
+        "related_files": format_related_files(&context.related_files),
+        "issue": format_issue(context),
+        "project": format_project(context),
+        "change_kind": context.change_kind.map(|kind| kind.to_string()).unwrap_or_default(),
+        "analysis": format_analyses(context),
+        "dependencies": format_dependencies(context),
+        "owners": format_owners(context),
+        "api_changes": format_api_changes(context),
+        "test_gaps": format_test_gaps(context),
+        "ci_failure": context.ci_failure.as_deref().unwrap_or_default(),
+        "context_files": format_context_files(context),
+        "context": context,
+    })
+}
+
+/// Renders the user's template for `name` in place of `default`, if one is configured.
+///
+/// `vars` must be a JSON object; `default` is added to it so templates can wrap
+/// the built-in prompt.
+pub fn apply_template(config: &Config, name: &str, vars: Value, default: String) -> Result<String> {
+    let Some(path) = config.templates.get(name) else {
+        return Ok(default);
+    };
+    let template = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {name} template {}", path.display()))?;
+
+    let mut vars = vars;
+    if let Some(object) = vars.as_object_mut() {
+        object.insert("default".to_string(), Value::String(default));
+    }
+    prompts::templates::render(&template, &vars)
+        .with_context(|| format!("Invalid {name} template {}", path.display()))
+}
@@ -1,11 +1,26 @@
 use super::git_service_core::GitServiceCore;
-use super::strategy::{CommitMessageStrategy, CommitPromptStrategy, CompletionStrategy};
+use super::gitmoji::{GitmojiMode, apply_gitmoji};
+use super::post_process::{apply_steps, post_process};
+use super::special;
+use super::strategy::{
+    CommitMessageStrategy, CommitPromptStrategy, CompletionStrategy, ExplainStrategy,
+    PullRequestDraftStrategy, RefineStrategy, SubjectStrategy,
+};
 use super::types::GeneratedMessage;
+use crate::analyzers;
+use crate::commit_cache::{self, Draft};
 use crate::common::DetailLevel;
 use crate::config::Config;
-use crate::git::{CommitResult, GitRepo};
-use crate::llm::context::CommitContext;
-use crate::llm::engine;
+use crate::git::{self, CommitResult, GitRepo, UndoResult};
+use crate::issues;
+use crate::llm::api_changes;
+use crate::llm::context::{CommitContext, RecentCommit, StagedFile, estimate_tokens};
+use crate::llm::{
+    binary, classify, embeddings, engine, model_info, noise, notebook, summarize, test_impact,
+};
+use crate::plugins::{self, HookPoint};
+use crate::progress::{self, Event};
+use crate::usage::{self, Outcome};
 
 use anyhow::Result;
 use log::debug;
@@ -14,6 +29,25 @@ use serde::de::DeserializeOwned;
 use std::path::Path;
 use tokio::sync::mpsc;
 
+/// Tokens of fixed template text around the context in a user prompt
+const PROMPT_OVERHEAD_TOKENS: usize = 1_000;
+
+/// Additional param naming a low-latency model for `--subject-only`
+/// (`gitai.<provider>-additionalfast-model`)
+pub const FAST_MODEL_PARAM: &str = "fast-model";
+
+/// A commit message request built up to the model call, so it can be answered later
+#[derive(Debug, Clone)]
+pub enum PreparedMessage {
+    /// A fixed-form message that needs no model call
+    Fixed(GeneratedMessage),
+    /// Prompts ready to send
+    Prompts {
+        system_prompt: String,
+        user_prompt: String,
+    },
+}
+
 /// Service for handling Git commit operations with AI assistance
 pub struct CommitService {
     core: GitServiceCore,
@@ -29,6 +63,7 @@ impl CommitService {
         detail_level: DetailLevel,
         git_repo: GitRepo,
     ) -> Result<Self> {
+        plugins::register_wasm_plugins(&config.plugins.wasm);
         Ok(Self {
             core: GitServiceCore::new(config, provider_name, git_repo),
             detail_level,
@@ -53,6 +88,12 @@ impl CommitService {
         self.core.get_git_info().await
     }
 
+    /// Re-read Git information, bypassing the cache
+    pub async fn refresh_git_info(&self) -> Result<CommitContext> {
+        self.core.invalidate_git_info().await;
+        self.core.get_git_info().await
+    }
+
     /// Get Git information including unstaged changes
     #[inline]
     pub async fn get_git_info_with_unstaged(
@@ -72,6 +113,198 @@ impl CommitService {
         Ok(context)
     }
 
+    /// Get the most recent commits reachable from HEAD
+    pub fn get_recent_commits(&self, count: usize) -> Result<Vec<RecentCommit>> {
+        self.core.repo().get_recent_commits(count)
+    }
+
+    /// Commits reachable from `to` but not from `from`, newest first
+    pub fn get_commits_between(&self, from: &str, to: &str) -> Result<Vec<RecentCommit>> {
+        self.core
+            .repo()
+            .get_commits_between_with_callback(from, to, |commit| Ok(commit.clone()))
+    }
+
+    /// Generate a replacement message for an existing commit
+    pub async fn reword_commit(
+        &self,
+        instructions: &str,
+        commit_id: &str,
+    ) -> Result<GeneratedMessage> {
+        let context = self.get_git_info_for_commit(commit_id)?;
+        self.generate_message_with_context(instructions, context)
+            .await
+    }
+
+    /// Regenerate the message of a past commit from only what preceded it
+    ///
+    /// Unlike [`Self::reword_commit`], the commit's own message is kept out of
+    /// the prompt, which makes the result comparable to the original.
+    pub async fn replay_commit(
+        &self,
+        instructions: &str,
+        commit_id: &str,
+    ) -> Result<GeneratedMessage> {
+        let context = self.core.repo().get_git_info_for_replay(commit_id)?;
+        self.generate_message_with_context(instructions, context)
+            .await
+    }
+
+    /// Explain what an existing commit does
+    pub async fn explain_commit(
+        &self,
+        instructions: &str,
+        commit_id: &str,
+    ) -> Result<GeneratedMessage> {
+        let context = self.get_git_info_for_commit(commit_id)?;
+        self.generate(ExplainStrategy, instructions, Some(context))
+            .await
+    }
+
+    /// Draft a pull request title and description from an existing commit
+    pub async fn draft_pull_request(
+        &self,
+        instructions: &str,
+        commit_id: &str,
+    ) -> Result<GeneratedMessage> {
+        let context = self.get_git_info_for_commit(commit_id)?;
+        self.generate(PullRequestDraftStrategy, instructions, Some(context))
+            .await
+    }
+
+    /// Draft a pull request title and description for the commits reachable
+    /// from `to` but not from `from`
+    pub async fn draft_pull_request_for_range(
+        &self,
+        instructions: &str,
+        from: &str,
+        to: &str,
+    ) -> Result<GeneratedMessage> {
+        let context =
+            self.core
+                .repo()
+                .get_git_info_for_commit_range(self.core.config(), from, to)?;
+        self.generate(PullRequestDraftStrategy, instructions, Some(context))
+            .await
+    }
+
+    /// Rewrite the message of HEAD without changing its content
+    pub fn reword_head(&self, message: &str) -> Result<CommitResult> {
+        if self.is_remote_repository() {
+            return Err(anyhow::anyhow!(
+                "Cannot reword commits in a remote repository"
+            ));
+        }
+        self.core.repo().reword_head(message)
+    }
+
+    /// Working tree changes that are not staged yet
+    pub fn get_unstaged_files(&self) -> Result<Vec<StagedFile>> {
+        self.core.repo().get_unstaged_files(self.core.config().diff)
+    }
+
+    /// Stage a file from the working tree
+    pub fn stage_file(&self, path: &str) -> Result<()> {
+        self.core.repo().stage_file(path)
+    }
+
+    /// Stage only the selected hunks of a file
+    pub fn stage_hunks(&self, path: &str, hunks: &[usize]) -> Result<()> {
+        self.core.repo().stage_hunks(path, hunks)
+    }
+
+    /// Unstage a file, keeping its working tree changes
+    pub fn unstage_file(&self, path: &str) -> Result<()> {
+        self.core.repo().unstage_file(path)
+    }
+
+    /// The revert or fixup message `fixup` or a revert in progress calls for, if any
+    pub fn special_message(&self, fixup: Option<&str>) -> Result<Option<GeneratedMessage>> {
+        let repo = self.core.repo().open_repo()?;
+        special::special_message(&repo, fixup)
+    }
+
+    /// Undo the last commit made by gitai, keeping its changes staged
+    pub fn undo_last_commit(&self) -> Result<UndoResult> {
+        self.core.repo().undo_last_commit()
+    }
+
+    /// Builds the system and user prompts a strategy sends for the given context
+    #[tracing::instrument(skip_all)]
+    async fn build_prompts<S>(
+        &self,
+        strategy: &S,
+        config: &Config,
+        context: Option<CommitContext>,
+    ) -> Result<(String, String)>
+    where
+        S: CommitPromptStrategy,
+    {
+        let mut context = if let Some(ctx) = context {
+            ctx
+        } else {
+            self.core.get_git_info().await?
+        };
+        if let Ok(repo) = self.core.repo().open_repo() {
+            notebook::normalize_notebooks(&repo, config, &mut context);
+            binary::describe_binaries(&repo, &mut context);
+        }
+        api_changes::attach_api_changes(&mut context);
+        classify::attach_change_kind(&mut context);
+        analyzers::attach_analyses(&mut context);
+        context.ci_failure.clone_from(&config.ci_failure);
+        context.context_files.clone_from(&config.context_files);
+        if let Ok(repo) = self.core.repo().open_repo() {
+            test_impact::attach_test_gaps(&repo, &mut context);
+        }
+        noise::filter_noise(config, &mut context);
+        issues::attach_issue(self.core.repo(), &mut context).await;
+        embeddings::rank_context(config, self.core.provider_name(), &mut context).await;
+        let mut summaries = self
+            .core
+            .repo()
+            .open_repo()
+            .map(|repo| summarize::SummaryCache::load(&repo))
+            .unwrap_or_default();
+        summarize::summarize_large_diffs(
+            config,
+            self.core.provider_name(),
+            &mut summaries,
+            &mut context,
+        )
+        .await;
+        if let Err(e) = summaries.save() {
+            debug!("Failed to cache diff summaries: {e}");
+        }
+        let mut context = config.plugins.run(
+            HookPoint::ContextCollected,
+            context,
+            self.core.repo().repo_path(),
+        )?;
+        progress::emit(&Event::ContextCollected {
+            files: context.staged_files.len(),
+            recent_commits: context.recent_commits.len(),
+        });
+
+        // Everything before reads line diffs
+        git::render_word_diffs(&mut context.staged_files, config.diff);
+
+        let system_prompt = tracing::info_span!("system_prompt")
+            .in_scope(|| strategy.create_system_prompt(config))?;
+        let budget = model_info::input_token_budget(config, self.core.provider_name())
+            .await
+            .saturating_sub(estimate_tokens(&system_prompt) + PROMPT_OVERHEAD_TOKENS);
+        if context.fit_to_budget(budget) {
+            debug!("Trimmed the context to fit {budget} prompt tokens");
+        }
+        let user_prompt = tracing::info_span!("user_prompt")
+            .in_scope(|| strategy.create_user_prompt(config, &context))?;
+        progress::emit(&Event::PromptBuilt {
+            prompt_tokens: estimate_tokens(&system_prompt) + estimate_tokens(&user_prompt),
+        });
+        Ok((system_prompt, user_prompt))
+    }
+
     /// Generic method to generate AI content using a specific strategy
     async fn generate<T, S>(
         &self,
@@ -86,14 +319,9 @@ impl CommitService {
         let mut config_clone = self.core.config_clone();
         config_clone.instructions = instructions.to_string();
 
-        let context = if let Some(ctx) = context {
-            ctx
-        } else {
-            self.core.get_git_info().await?
-        };
-
-        let system_prompt = strategy.create_system_prompt(&config_clone)?;
-        let user_prompt = strategy.create_user_prompt(&context)?;
+        let (system_prompt, user_prompt) = self
+            .build_prompts(&strategy, &config_clone, context)
+            .await?;
 
         engine::get_message::<T>(
             &config_clone,
@@ -104,20 +332,163 @@ impl CommitService {
         .await
     }
 
+    /// Generates a commit message with a strategy and finishes it for the repository
+    async fn generate_commit_message<S>(
+        &self,
+        strategy: S,
+        instructions: &str,
+        context: Option<CommitContext>,
+    ) -> Result<GeneratedMessage>
+    where
+        S: CommitPromptStrategy,
+    {
+        let message = self.generate(strategy, instructions, context).await?;
+        self.finish_message(message).await
+    }
+
+    /// System and user prompts for a commit message from the staged changes
+    pub async fn commit_message_prompts(&self, instructions: &str) -> Result<(String, String)> {
+        let mut config_clone = self.core.config_clone();
+        config_clone.instructions = instructions.to_string();
+        let strategy = CommitMessageStrategy::new(self.detail_level);
+        self.build_prompts(&strategy, &config_clone, None).await
+    }
+
     /// Generate a commit message using AI
     pub async fn generate_message(&self, instructions: &str) -> Result<GeneratedMessage> {
-        let strategy = CommitMessageStrategy::new(self.detail_level);
-        self.generate(strategy, instructions, None).await
+        let context = self.core.get_git_info().await?;
+        self.generate_message_with_context(instructions, context)
+            .await
     }
 
-    /// Generate a commit message using AI with custom context
+    /// Generate a commit message using AI with custom context.
+    ///
+    /// A change that only bumps one dependency gets its fixed-form message
+    /// without calling the model.
     pub async fn generate_message_with_context(
         &self,
         instructions: &str,
         context: CommitContext,
     ) -> Result<GeneratedMessage> {
+        let prepared = self.prepare_message(instructions, context).await?;
+        self.generate_prepared(&prepared).await
+    }
+
+    /// Builds everything a commit message for `context` needs before the model call
+    pub async fn prepare_message(
+        &self,
+        instructions: &str,
+        context: CommitContext,
+    ) -> Result<PreparedMessage> {
+        if let Some(title) = classify::trivial_subject(self.core.config(), &context) {
+            debug!("Single dependency bump; not calling the model");
+            return Ok(PreparedMessage::Fixed(GeneratedMessage {
+                title,
+                message: String::new(),
+            }));
+        }
+        let mut config_clone = self.core.config_clone();
+        config_clone.instructions = instructions.to_string();
         let strategy = CommitMessageStrategy::new(self.detail_level);
-        self.generate(strategy, instructions, Some(context)).await
+        let (system_prompt, user_prompt) = self
+            .build_prompts(&strategy, &config_clone, Some(context))
+            .await?;
+        Ok(PreparedMessage::Prompts {
+            system_prompt,
+            user_prompt,
+        })
+    }
+
+    /// Generates the message of a request built by [`Self::prepare_message`]
+    pub async fn generate_prepared(&self, prepared: &PreparedMessage) -> Result<GeneratedMessage> {
+        match prepared {
+            PreparedMessage::Fixed(message) => self.finish_message(message.clone()).await,
+            PreparedMessage::Prompts {
+                system_prompt,
+                user_prompt,
+            } => {
+                let message = engine::get_message::<GeneratedMessage>(
+                    self.core.config(),
+                    self.core.provider_name(),
+                    system_prompt,
+                    user_prompt,
+                )
+                .await?;
+                self.finish_message(message).await
+            }
+        }
+    }
+
+    /// Generate only a one-line subject, for users who write the body themselves.
+    ///
+    /// The prompt lists the changed files with their line counts but no diffs,
+    /// and skips the enrichment full messages get. The provider's `fast-model`
+    /// replaces its model for the request when configured.
+    pub async fn generate_subject(&self, instructions: &str) -> Result<GeneratedMessage> {
+        let context = self.core.get_git_info().await?;
+        if let Some(title) = classify::trivial_subject(self.core.config(), &context) {
+            let message = GeneratedMessage {
+                title,
+                message: String::new(),
+            };
+            return self.finish_message(message).await;
+        }
+
+        let provider_name = self.core.provider_name();
+        let mut config = self.core.config_clone();
+        config.instructions = instructions.to_string();
+        if let Some(provider_config) = config.providers.get_mut(provider_name)
+            && let Some(model) = provider_config
+                .additional_params
+                .get(FAST_MODEL_PARAM)
+                .filter(|model| !model.trim().is_empty())
+        {
+            debug!("Generating the subject with {model}");
+            provider_config.model_name = model.trim().to_string();
+        }
+
+        let strategy = SubjectStrategy;
+        let system_prompt = strategy.create_system_prompt(&config)?;
+        let user_prompt = strategy.create_user_prompt(&config, &context)?;
+        let response =
+            engine::get_message::<String>(&config, provider_name, &system_prompt, &user_prompt)
+                .await?;
+        let title = response
+            .lines()
+            .map(|line| line.trim().trim_matches(['`', '"']).trim())
+            .find(|line| !line.is_empty())
+            .ok_or_else(|| anyhow::anyhow!("The model returned an empty subject"))?
+            .to_string();
+        let message = GeneratedMessage {
+            title,
+            message: String::new(),
+        };
+        self.finish_message(message).await
+    }
+
+    /// Regenerate a message from a previous draft and the user's feedback
+    pub async fn refine_message(
+        &self,
+        previous: &GeneratedMessage,
+        feedback: &str,
+    ) -> Result<GeneratedMessage> {
+        let instructions = self.core.config().get_effective_instructions();
+        self.refine_message_with_context(&instructions, previous, feedback, None)
+            .await
+    }
+
+    /// Regenerate a message from a previous draft and feedback with custom context
+    pub async fn refine_message_with_context(
+        &self,
+        instructions: &str,
+        previous: &GeneratedMessage,
+        feedback: &str,
+        context: Option<CommitContext>,
+    ) -> Result<GeneratedMessage> {
+        let strategy =
+            RefineStrategy::new(self.detail_level, previous.clone(), feedback.to_string());
+        self.generate_commit_message(strategy, instructions, context)
+            .await
     }
 
     /// Generate a completion for a partially typed message
@@ -128,7 +499,37 @@ impl CommitService {
         instructions: &str,
     ) -> Result<GeneratedMessage> {
         let strategy = CompletionStrategy::new(prefix.to_string(), context_ratio);
-        self.generate(strategy, instructions, None).await
+        self.generate_commit_message(strategy, instructions, None)
+            .await
+    }
+
+    /// Applies title post-processing configured for the repository, passes the
+    /// message through the `gitai.post-process` steps, the registered
+    /// post-processors and the `message-generated` plugins, and cuts the result
+    /// to the length limits
+    async fn finish_message(&self, mut message: GeneratedMessage) -> Result<GeneratedMessage> {
+        let config = self.core.config();
+        apply_gitmoji(GitmojiMode::from_config(config), &mut message);
+        let signer = self.core.signer();
+        let message = apply_steps(
+            config,
+            self.core.provider_name(),
+            signer.as_deref(),
+            message,
+        )
+        .await;
+        let message = post_process(message)?;
+        let mut message = self.core.config().plugins.run(
+            HookPoint::MessageGenerated,
+            message,
+            self.core.repo().repo_path(),
+        )?;
+        // Last, so nothing above can push the message past the limits again
+        config.limits.enforce(&mut message);
+        progress::emit(&Event::MessageReady {
+            title: &message.title,
+        });
+        Ok(message)
     }
 
     /// Performs a commit with the given message.
@@ -142,6 +543,71 @@ impl CommitService {
         self.core.perform_commit(message, amend, commit_ref)
     }
 
+    /// Saves uncommitted messages as a draft of the staged changes
+    pub fn save_draft(&self, messages: &[GeneratedMessage], current: usize) {
+        if self.core.is_remote_repository() {
+            return;
+        }
+        let saved = self
+            .core
+            .repo
+            .open_repo()
+            .map_err(anyhow::Error::from)
+            .and_then(|repo| commit_cache::save(&repo, messages, current));
+        if let Err(e) = saved {
+            debug!("Failed to save the draft: {e:#}");
+        }
+    }
+
+    /// The draft saved for the staged changes, if any
+    pub fn find_draft(&self) -> Option<Draft> {
+        if self.core.is_remote_repository() {
+            return None;
+        }
+        commit_cache::find(&self.core.repo.open_repo().ok()?)
+    }
+
+    /// Drops the draft of the staged changes after they were committed
+    pub fn discard_draft(&self) {
+        if self.core.is_remote_repository() {
+            return;
+        }
+        let discarded = self
+            .core
+            .repo
+            .open_repo()
+            .map_err(anyhow::Error::from)
+            .and_then(|repo| commit_cache::discard(&repo));
+        if let Err(e) = discarded {
+            debug!("Failed to discard the draft: {e:#}");
+        }
+    }
+
+    /// The configuration the service generates with
+    pub fn config(&self) -> &Config {
+        self.core.config()
+    }
+
+    /// Column the editor wraps message bodies at
+    pub fn wrap_column(&self) -> usize {
+        self.core.config().wrap_column()
+    }
+
+    /// Whether the editor flags misspellings
+    pub fn spell_check(&self) -> bool {
+        self.core.config().spell_check()
+    }
+
+    /// Counts how an interactive session ended, when usage stats are on
+    pub fn record_usage(&self, outcome: Outcome, regenerations: usize) {
+        usage::record(
+            self.core.config(),
+            &self.core.provider_name,
+            outcome,
+            regenerations,
+        );
+    }
+
     /// Create a channel for message generation
     pub fn create_message_channel(
         &self,
@@ -0,0 +1,124 @@
+//! Revert and fixup messages, written from the original commit.
+//!
+//! These messages have a fixed form that tools depend on: `git rebase
+//! --autosquash` matches `fixup!` subjects against the commit to squash into,
+//! and `Revert "…"` with its `This reverts commit` line is what `git revert`
+//! writes. Asking a model for them only risks creative rewording, so they are
+//! built directly and the model is never called.
+
+use super::types::GeneratedMessage;
+use anyhow::{Context, Result};
+use git2::{Commit, Repository, RepositoryState};
+
+fn subject(commit: &Commit) -> String {
+    commit.summary().unwrap_or_default().to_string()
+}
+
+/// `fixup! <subject>` for squashing into `commit` with `git rebase --autosquash`
+pub fn fixup_message(commit: &Commit) -> GeneratedMessage {
+    GeneratedMessage {
+        title: format!("fixup! {}", subject(commit)),
+        message: String::new(),
+    }
+}
+
+/// The message `git revert` writes for `commit`; reverting a revert reapplies the original
+pub fn revert_message(commit: &Commit) -> GeneratedMessage {
+    let subject = subject(commit);
+    let title = match subject
+        .strip_prefix("Revert \"")
+        .and_then(|rest| rest.strip_suffix('"'))
+    {
+        Some(original) => format!("Reapply \"{original}\""),
+        None => format!("Revert \"{subject}\""),
+    };
+    GeneratedMessage {
+        title,
+        message: format!("This reverts commit {}.", commit.id()),
+    }
+}
+
+/// The commit a `git revert` in progress is reverting
+pub fn reverting(repo: &Repository) -> Option<Commit<'_>> {
+    if !matches!(
+        repo.state(),
+        RepositoryState::Revert | RepositoryState::RevertSequence
+    ) {
+        return None;
+    }
+    repo.revparse_single("REVERT_HEAD")
+        .ok()?
+        .peel_to_commit()
+        .ok()
+}
+
+/// The fixed-form message for `--fixup <commit>` or a revert in progress, if either applies
+pub fn special_message(repo: &Repository, fixup: Option<&str>) -> Result<Option<GeneratedMessage>> {
+    if let Some(target) = fixup {
+        let commit = repo
+            .revparse_single(target)
+            .and_then(|object| object.peel_to_commit())
+            .with_context(|| format!("Cannot find commit {target} to fix up"))?;
+        return Ok(Some(fixup_message(&commit)));
+    }
+    Ok(reverting(repo).map(|commit| revert_message(&commit)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use git2::{Oid, Signature};
+    use std::fs;
+
+    fn commit(repo: &Repository, subject: &str) -> Oid {
+        let signature = Signature::now("Ada", "ada@example.com").expect("Valid signature");
+        let tree = {
+            let mut index = repo.index().expect("Failed to open index");
+            repo.find_tree(index.write_tree().expect("Failed to write tree"))
+                .expect("Failed to find tree")
+        };
+        let parent = repo.head().ok().and_then(|head| head.peel_to_commit().ok());
+        let parents: Vec<&Commit> = parent.iter().collect();
+        repo.commit(
+            Some("HEAD"),
+            &signature,
+            &signature,
+            subject,
+            &tree,
+            &parents,
+        )
+        .expect("Failed to commit")
+    }
+
+    #[test]
+    fn test_fixup_and_revert_messages() {
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let repo = Repository::init(dir.path()).expect("Failed to init repo");
+        let original = commit(&repo, "Add the parser\n\nWith tests.");
+
+        let fixup = special_message(&repo, Some("HEAD"))
+            .expect("Failed to build message")
+            .expect("Fixup message");
+        assert_eq!(fixup.title, "fixup! Add the parser");
+        assert!(fixup.message.is_empty());
+        assert!(special_message(&repo, Some("nope")).is_err());
+
+        // Nothing applies without --fixup or a revert in progress
+        assert_eq!(special_message(&repo, None).expect("No error"), None);
+
+        fs::write(repo.path().join("REVERT_HEAD"), format!("{original}\n"))
+            .expect("Failed to write REVERT_HEAD");
+        let revert = special_message(&repo, None)
+            .expect("Failed to build message")
+            .expect("Revert message");
+        assert_eq!(revert.title, "Revert \"Add the parser\"");
+        assert_eq!(revert.message, format!("This reverts commit {original}."));
+
+        let reverted = commit(&repo, &format!("{}\n\n{}", revert.title, revert.message));
+        let reverted = repo.find_commit(reverted).expect("Revert commit");
+        assert_eq!(
+            revert_message(&reverted).title,
+            "Reapply \"Add the parser\""
+        );
+    }
+}
@@ -0,0 +1,392 @@
+//! Typo checking of commit messages.
+//!
+//! Models occasionally misspell a word, and reviewers notice. Rather than a
+//! full dictionary, which would flag every identifier and product name, the
+//! check looks words up in a bundled list of common misspellings, so every
+//! flag comes with its correction. Code is skipped: anything in backticks,
+//! paths, URLs, `snake_case`, `camelCase` and all-caps tokens.
+
+use super::types::GeneratedMessage;
+use std::iter;
+use std::ops::Range;
+
+/// Common misspellings and their corrections, sorted by misspelling
+const MISSPELLINGS: &[(&str, &str)] = &[
+    ("accesible", "accessible"),
+    ("accomodate", "accommodate"),
+    ("accross", "across"),
+    ("acheive", "achieve"),
+    ("acknowlege", "acknowledge"),
+    ("aditional", "additional"),
+    ("adress", "address"),
+    ("agressive", "aggressive"),
+    ("algoritm", "algorithm"),
+    ("alot", "a lot"),
+    ("alreay", "already"),
+    ("alredy", "already"),
+    ("ammount", "amount"),
+    ("anonymus", "anonymous"),
+    ("aparent", "apparent"),
+    ("apropriate", "appropriate"),
+    ("arguement", "argument"),
+    ("assertation", "assertion"),
+    ("asynchronus", "asynchronous"),
+    ("atleast", "at least"),
+    ("attemp", "attempt"),
+    ("authenticaton", "authentication"),
+    ("availabe", "available"),
+    ("availible", "available"),
+    ("avaliable", "available"),
+    ("backwords", "backwards"),
+    ("becasue", "because"),
+    ("becuase", "because"),
+    ("begining", "beginning"),
+    ("beleive", "believe"),
+    ("benifit", "benefit"),
+    ("bettter", "better"),
+    ("boundry", "boundary"),
+    ("buffere", "buffer"),
+    ("calender", "calendar"),
+    ("catched", "caught"),
+    ("cheking", "checking"),
+    ("choosen", "chosen"),
+    ("commited", "committed"),
+    ("commiting", "committing"),
+    ("comparision", "comparison"),
+    ("compatability", "compatibility"),
+    ("compatable", "compatible"),
+    ("completly", "completely"),
+    ("concurent", "concurrent"),
+    ("condtion", "condition"),
+    ("configuraton", "configuration"),
+    ("connnection", "connection"),
+    ("consistant", "consistent"),
+    ("containg", "containing"),
+    ("continous", "continuous"),
+    ("convertion", "conversion"),
+    ("correclty", "correctly"),
+    ("corresponing", "corresponding"),
+    ("curent", "current"),
+    ("currenly", "currently"),
+    ("definately", "definitely"),
+    ("defintion", "definition"),
+    ("depedency", "dependency"),
+    ("dependancy", "dependency"),
+    ("dependant", "dependent"),
+    ("deprected", "deprecated"),
+    ("descripton", "description"),
+    ("destory", "destroy"),
+    ("diffrent", "different"),
+    ("directroy", "directory"),
+    ("disabeld", "disabled"),
+    ("doesnt", "doesn't"),
+    ("dont", "don't"),
+    ("dupicate", "duplicate"),
+    ("efficent", "efficient"),
+    ("embeded", "embedded"),
+    ("enviroment", "environment"),
+    ("environemnt", "environment"),
+    ("equivalant", "equivalent"),
+    ("exection", "execution"),
+    ("exising", "existing"),
+    ("existance", "existence"),
+    ("explicitely", "explicitly"),
+    ("extention", "extension"),
+    ("failiure", "failure"),
+    ("familar", "familiar"),
+    ("fucntion", "function"),
+    ("fuction", "function"),
+    ("funcion", "function"),
+    ("gaurantee", "guarantee"),
+    ("genereate", "generate"),
+    ("happend", "happened"),
+    ("heigth", "height"),
+    ("helpfull", "helpful"),
+    ("hierachy", "hierarchy"),
+    ("identifer", "identifier"),
+    ("immediatly", "immediately"),
+    ("implemention", "implementation"),
+    ("implmentation", "implementation"),
+    ("incldue", "include"),
+    ("incomming", "incoming"),
+    ("incorect", "incorrect"),
+    ("independant", "independent"),
+    ("informaton", "information"),
+    ("initalize", "initialize"),
+    ("initialse", "initialise"),
+    ("instaed", "instead"),
+    ("intial", "initial"),
+    ("intialize", "initialize"),
+    ("isnt", "isn't"),
+    ("langauge", "language"),
+    ("lenght", "length"),
+    ("libary", "library"),
+    ("maintainance", "maintenance"),
+    ("managment", "management"),
+    ("mesage", "message"),
+    ("messsage", "message"),
+    ("minimun", "minimum"),
+    ("mispelled", "misspelled"),
+    ("modifed", "modified"),
+    ("neccessary", "necessary"),
+    ("necesary", "necessary"),
+    ("noticable", "noticeable"),
+    ("occured", "occurred"),
+    ("occurence", "occurrence"),
+    ("occuring", "occurring"),
+    ("ommit", "omit"),
+    ("optionnal", "optional"),
+    ("orignal", "original"),
+    ("overriden", "overridden"),
+    ("paramater", "parameter"),
+    ("paramter", "parameter"),
+    ("parrallel", "parallel"),
+    ("perfomance", "performance"),
+    ("permision", "permission"),
+    ("persistant", "persistent"),
+    ("posible", "possible"),
+    ("preceeding", "preceding"),
+    ("preferrable", "preferable"),
+    ("prevous", "previous"),
+    ("privilige", "privilege"),
+    ("proccess", "process"),
+    ("properites", "properties"),
+    ("propogate", "propagate"),
+    ("recieve", "receive"),
+    ("recieved", "received"),
+    ("recursivly", "recursively"),
+    ("redundent", "redundant"),
+    ("refrence", "reference"),
+    ("relevent", "relevant"),
+    ("remaing", "remaining"),
+    ("repositry", "repository"),
+    ("repostiory", "repository"),
+    ("requried", "required"),
+    ("resouce", "resource"),
+    ("respone", "response"),
+    ("retreive", "retrieve"),
+    ("seperate", "separate"),
+    ("seperator", "separator"),
+    ("succesful", "successful"),
+    ("successfull", "successful"),
+    ("sucess", "success"),
+    ("suport", "support"),
+    ("supress", "suppress"),
+    ("synchronus", "synchronous"),
+    ("teh", "the"),
+    ("threshhold", "threshold"),
+    ("tommorow", "tomorrow"),
+    ("transfered", "transferred"),
+    ("truely", "truly"),
+    ("unecessary", "unnecessary"),
+    ("unkown", "unknown"),
+    ("unneccessary", "unnecessary"),
+    ("untill", "until"),
+    ("usally", "usually"),
+    ("usefull", "useful"),
+    ("validaton", "validation"),
+    ("verfiy", "verify"),
+    ("whitespce", "whitespace"),
+    ("wich", "which"),
+    ("wierd", "weird"),
+    ("writting", "writing"),
+];
+
+/// A misspelled word in a piece of text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Typo {
+    /// Line of the text the word is on, counting from 0
+    pub line: usize,
+    /// Byte range of the word within its line
+    pub range: Range<usize>,
+    pub word: String,
+    pub suggestion: String,
+}
+
+/// The correction for a word, in the word's case, if it is a known misspelling
+fn correction(word: &str) -> Option<String> {
+    let lower = word.to_lowercase();
+    let index = MISSPELLINGS
+        .binary_search_by(|(misspelling, _)| misspelling.cmp(&lower.as_str()))
+        .ok()?;
+    let suggestion = MISSPELLINGS[index].1;
+    let mut chars = word.chars();
+    Some(match chars.next() {
+        Some(first) if first.is_uppercase() => {
+            let mut rest = suggestion.chars();
+            rest.next()
+                .map(|c| c.to_uppercase().chain(rest).collect())
+                .unwrap_or_default()
+        }
+        _ => suggestion.to_string(),
+    })
+}
+
+/// Whether a whitespace-separated token is code rather than prose
+fn is_code_token(token: &str) -> bool {
+    token.contains(['/', '\\', '_', '=', '(', '<', '{', '['])
+        || token.contains("::")
+        || token.starts_with(['#', '@', '$', '-', '.'])
+        || token
+            .trim_end_matches(['.', ',', ';', ':', '!', '?'])
+            .contains('.')
+}
+
+/// Whether a word looks like an identifier or an acronym
+fn is_identifier(word: &str) -> bool {
+    word.chars().any(|c| c.is_ascii_digit()) || word.chars().skip(1).any(char::is_uppercase)
+}
+
+/// The known misspellings in `text`, skipping code
+pub fn check(text: &str) -> Vec<Typo> {
+    let mut typos = Vec::new();
+    for (line_index, line) in text.split('\n').enumerate() {
+        let mut in_code = false;
+        let mut offset = 0;
+        for segment in line.split('`') {
+            if !in_code {
+                check_prose(segment, line_index, offset, &mut typos);
+            }
+            in_code = !in_code;
+            offset += segment.len() + 1;
+        }
+    }
+    typos
+}
+
+fn check_prose(prose: &str, line: usize, offset: usize, typos: &mut Vec<Typo>) {
+    let mut token_start = 0;
+    for (index, c) in prose.char_indices().chain(iter::once((prose.len(), ' '))) {
+        if !c.is_whitespace() {
+            continue;
+        }
+        let token = &prose[token_start..index];
+        let token_offset = offset + token_start;
+        token_start = index + c.len_utf8();
+        if token.is_empty() || is_code_token(token) {
+            continue;
+        }
+        let mut word_start = None;
+        for (i, c) in token.char_indices().chain(iter::once((token.len(), ' '))) {
+            let in_word = c.is_alphanumeric() || c == '\'';
+            match word_start {
+                None if in_word => word_start = Some(i),
+                Some(start) if !in_word => {
+                    word_start = None;
+                    let raw = &token[start..i];
+                    let quote = raw.len() - raw.trim_start_matches('\'').len();
+                    let word = raw.trim_matches('\'');
+                    if word.is_empty() || is_identifier(word) {
+                        continue;
+                    }
+                    if let Some(suggestion) = correction(word) {
+                        let begin = token_offset + start + quote;
+                        typos.push(Typo {
+                            line,
+                            range: begin..begin + word.len(),
+                            word: word.to_string(),
+                            suggestion,
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// `text` with every typo replaced by its correction
+pub fn fix(text: &str) -> String {
+    let typos = check(text);
+    if typos.is_empty() {
+        return text.to_string();
+    }
+    text.split('\n')
+        .enumerate()
+        .map(|(index, line)| {
+            let mut line = line.to_string();
+            for typo in typos.iter().rev().filter(|typo| typo.line == index) {
+                line.replace_range(typo.range.clone(), &typo.suggestion);
+            }
+            line
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// The typos of a message, by part
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MessageTypos {
+    pub title: Vec<Typo>,
+    pub body: Vec<Typo>,
+}
+
+impl MessageTypos {
+    pub fn len(&self) -> usize {
+        self.title.len() + self.body.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Title typos first, then body typos
+    pub fn iter(&self) -> impl Iterator<Item = &Typo> {
+        self.title.iter().chain(&self.body)
+    }
+}
+
+/// The typos of a message's title and body
+pub fn check_message(message: &GeneratedMessage) -> MessageTypos {
+    MessageTypos {
+        title: check(&message.title),
+        body: check(&message.message),
+    }
+}
+
+/// The message with the typos of its title and body corrected
+pub fn fix_message(message: &GeneratedMessage) -> GeneratedMessage {
+    GeneratedMessage {
+        title: fix(&message.title),
+        message: fix(&message.message),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_misspellings_are_sorted() {
+        assert!(MISSPELLINGS.windows(2).all(|pair| pair[0].0 < pair[1].0));
+    }
+
+    #[test]
+    fn test_typos_in_prose_are_found_and_fixed() {
+        let text = "Seperate the parser, recieve input\n\n—Teh cache is now `seperate`.";
+        let typos = check(text);
+        let words: Vec<_> = typos.iter().map(|t| (t.line, t.word.as_str())).collect();
+        assert_eq!(words, [(0, "Seperate"), (0, "recieve"), (2, "Teh")]);
+        assert_eq!(&text[typos[1].range.clone()], "recieve");
+        assert_eq!(typos[2].suggestion, "The");
+        assert_eq!(
+            fix(text),
+            "Separate the parser, receive input\n\n—The cache is now `seperate`."
+        );
+    }
+
+    #[test]
+    fn test_code_tokens_are_skipped() {
+        for text in [
+            "Rename `recieve` to receive",
+            "Update src/recieve.rs",
+            "Call recieve_all",
+            "Add recieveMessage handler",
+            "Fix RECIEVE constant",
+            "See https://example.com/recieve",
+            "Use Parser::recieve",
+        ] {
+            assert_eq!(check(text), [], "{text}");
+        }
+    }
+}
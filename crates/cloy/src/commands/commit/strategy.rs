@@ -5,6 +5,8 @@ use crate::config::Config;
 use crate::llm::context::CommitContext;
 use anyhow::Result;
 use prompts::commit as commit_prompts;
+use prompts::pr as pr_prompts;
+use serde_json::json;
 
 /// Trait for defining how to generate prompts for commit-related operations
 pub trait CommitPromptStrategy: Send + Sync {
@@ -12,7 +14,21 @@ pub trait CommitPromptStrategy: Send + Sync {
     fn create_system_prompt(&self, config: &Config) -> Result<String>;
 
     /// Create the user prompt for the operation
-    fn create_user_prompt(&self, context: &CommitContext) -> Result<String>;
+    fn create_user_prompt(&self, config: &Config, context: &CommitContext) -> Result<String>;
+}
+
+/// Variables for system prompt templates
+fn system_template_vars(instructions: &str, schema: &str) -> serde_json::Value {
+    json!({ "instructions": instructions, "schema": schema })
+}
+
+/// Adds strategy-specific variables to the shared commit-context ones
+fn user_template_vars(context: &CommitContext, extra: serde_json::Value) -> serde_json::Value {
+    let mut vars = prompt_helpers::context_template_vars(context);
+    if let (Some(vars), serde_json::Value::Object(extra)) = (vars.as_object_mut(), extra) {
+        vars.extend(extra);
+    }
+    vars
 }
 
 /// Strategy for generating standard commit messages
@@ -30,14 +46,17 @@ impl CommitPromptStrategy for CommitMessageStrategy {
     fn create_system_prompt(&self, config: &Config) -> Result<String> {
         let schema = schemars::schema_for!(GeneratedMessage);
         let schema_str = serde_json::to_string_pretty(&schema)?;
-        let instructions = get_combined_instructions(config);
-        Ok(commit_prompts::create_system_prompt(
-            &instructions,
-            &schema_str,
-        ))
+        let instructions =
+            prompt_helpers::with_project_conventions(get_combined_instructions(config), config);
+        prompt_helpers::apply_template(
+            config,
+            "commit-system",
+            system_template_vars(&instructions, &schema_str),
+            commit_prompts::create_system_prompt(&instructions, &schema_str),
+        )
     }
 
-    fn create_user_prompt(&self, context: &CommitContext) -> Result<String> {
+    fn create_user_prompt(&self, config: &Config, context: &CommitContext) -> Result<String> {
         let detail_instruction = match self.detail_level {
             DetailLevel::Minimal => {
                 "EXIGENCY: Keep it technical and concise. A subsystem subject and a single paragraph of technical reasoning."
@@ -50,17 +69,165 @@ impl CommitPromptStrategy for CommitMessageStrategy {
             }
         };
 
-        Ok(commit_prompts::create_user_prompt(
-            &context.branch,
-            &prompt_helpers::format_staged_files(&context.staged_files),
-            &prompt_helpers::format_detailed_changes(&context.staged_files),
-            &prompt_helpers::format_recent_commits(&context.recent_commits),
-            &prompt_helpers::format_enhanced_author_history(&context.author_history, context),
-            detail_instruction,
+        let default = prompt_helpers::with_context_sections(
+            commit_prompts::create_user_prompt(
+                &context.branch,
+                &prompt_helpers::format_staged_files(&context.staged_files),
+                &prompt_helpers::format_detailed_changes(&context.staged_files),
+                &prompt_helpers::format_recent_commits(&context.recent_commits),
+                &prompt_helpers::format_enhanced_author_history(&context.author_history, context),
+                &prompt_helpers::format_related_files(&context.related_files),
+                detail_instruction,
+            ),
+            context,
+        );
+        prompt_helpers::apply_template(
+            config,
+            "commit-user",
+            user_template_vars(context, json!({ "detail_instruction": detail_instruction })),
+            default,
+        )
+    }
+}
+
+/// Strategy for revising a previous message according to user feedback
+pub struct RefineStrategy {
+    pub base: CommitMessageStrategy,
+    pub previous: GeneratedMessage,
+    pub feedback: String,
+}
+
+impl RefineStrategy {
+    pub fn new(detail_level: DetailLevel, previous: GeneratedMessage, feedback: String) -> Self {
+        Self {
+            base: CommitMessageStrategy::new(detail_level),
+            previous,
+            feedback,
+        }
+    }
+}
+
+impl CommitPromptStrategy for RefineStrategy {
+    fn create_system_prompt(&self, config: &Config) -> Result<String> {
+        self.base.create_system_prompt(config)
+    }
+
+    fn create_user_prompt(&self, config: &Config, context: &CommitContext) -> Result<String> {
+        Ok(commit_prompts::create_refine_user_prompt(
+            &self.base.create_user_prompt(config, context)?,
+            &self.previous.title,
+            &self.previous.message,
+            &self.feedback,
         ))
     }
 }
 
+/// Strategy for explaining an existing commit
+pub struct ExplainStrategy;
+
+impl CommitPromptStrategy for ExplainStrategy {
+    fn create_system_prompt(&self, config: &Config) -> Result<String> {
+        let schema = schemars::schema_for!(GeneratedMessage);
+        let schema_str = serde_json::to_string_pretty(&schema)?;
+        let instructions = get_combined_instructions(config);
+        prompt_helpers::apply_template(
+            config,
+            "explain-system",
+            system_template_vars(&instructions, &schema_str),
+            commit_prompts::create_explain_system_prompt(&instructions, &schema_str),
+        )
+    }
+
+    fn create_user_prompt(&self, config: &Config, context: &CommitContext) -> Result<String> {
+        let default = commit_prompts::create_explain_user_prompt(
+            &prompt_helpers::format_recent_commits(&context.recent_commits),
+            &prompt_helpers::format_detailed_changes(&context.staged_files),
+        );
+        prompt_helpers::apply_template(
+            config,
+            "explain-user",
+            user_template_vars(context, json!({})),
+            default,
+        )
+    }
+}
+
+/// Strategy for drafting a pull request description from a single commit
+///
+/// The draft reuses the PR prompts but answers with a `GeneratedMessage`, whose
+/// title and body map directly onto a PR title and description.
+pub struct PullRequestDraftStrategy;
+
+impl CommitPromptStrategy for PullRequestDraftStrategy {
+    fn create_system_prompt(&self, config: &Config) -> Result<String> {
+        let schema = schemars::schema_for!(GeneratedMessage);
+        let schema_str = serde_json::to_string_pretty(&schema)?;
+        let instructions = get_combined_instructions(config);
+        prompt_helpers::apply_template(
+            config,
+            "pr-system",
+            system_template_vars(&instructions, &schema_str),
+            pr_prompts::create_pr_system_prompt(&instructions, &schema_str),
+        )
+    }
+
+    fn create_user_prompt(&self, config: &Config, context: &CommitContext) -> Result<String> {
+        let commits = prompt_helpers::format_recent_commits(&context.recent_commits);
+        let default = prompt_helpers::with_context_sections(
+            pr_prompts::create_pr_user_prompt(
+                &context.branch,
+                &commits,
+                &prompt_helpers::format_detailed_changes(&context.staged_files),
+                "",
+            ),
+            context,
+        );
+        prompt_helpers::apply_template(
+            config,
+            "pr-user",
+            user_template_vars(context, json!({ "commits": commits })),
+            default,
+        )
+    }
+}
+
+/// Strategy for `--subject-only`: a one-line subject from the file list and
+/// line counts, answered as plain text
+pub struct SubjectStrategy;
+
+impl CommitPromptStrategy for SubjectStrategy {
+    fn create_system_prompt(&self, config: &Config) -> Result<String> {
+        let instructions = get_combined_instructions(config);
+        prompt_helpers::apply_template(
+            config,
+            "subject-system",
+            system_template_vars(&instructions, ""),
+            commit_prompts::create_subject_system_prompt(instructions.trim()),
+        )
+    }
+
+    fn create_user_prompt(&self, config: &Config, context: &CommitContext) -> Result<String> {
+        let change_summary = prompt_helpers::format_change_summary(&context.staged_files);
+        let recent_subjects = context
+            .recent_commits
+            .iter()
+            .filter_map(|commit| commit.message.lines().next())
+            .collect::<Vec<_>>()
+            .join("\n");
+        let default = commit_prompts::create_subject_user_prompt(
+            &context.branch,
+            &change_summary,
+            &recent_subjects,
+        );
+        prompt_helpers::apply_template(
+            config,
+            "subject-user",
+            user_template_vars(context, json!({ "change_summary": change_summary })),
+            default,
+        )
+    }
+}
+
 /// Strategy for completing partially typed commit messages
 pub struct CompletionStrategy {
     pub prefix: String,
@@ -80,15 +247,18 @@ impl CommitPromptStrategy for CompletionStrategy {
     fn create_system_prompt(&self, config: &Config) -> Result<String> {
         let schema = schemars::schema_for!(GeneratedMessage);
         let schema_str = serde_json::to_string_pretty(&schema)?;
-        let instructions = get_combined_instructions(config);
-        Ok(commit_prompts::create_completion_system_prompt(
-            &instructions,
-            &schema_str,
-        ))
+        let instructions =
+            prompt_helpers::with_project_conventions(get_combined_instructions(config), config);
+        prompt_helpers::apply_template(
+            config,
+            "completion-system",
+            system_template_vars(&instructions, &schema_str),
+            commit_prompts::create_completion_system_prompt(&instructions, &schema_str),
+        )
     }
 
-    fn create_user_prompt(&self, context: &CommitContext) -> Result<String> {
-        Ok(commit_prompts::create_completion_user_prompt(
+    fn create_user_prompt(&self, config: &Config, context: &CommitContext) -> Result<String> {
+        let default = commit_prompts::create_completion_user_prompt(
             &self.prefix,
             self.context_ratio,
             &context.branch,
@@ -96,6 +266,15 @@ impl CommitPromptStrategy for CompletionStrategy {
             &prompt_helpers::format_detailed_changes(&context.staged_files),
             &prompt_helpers::format_recent_commits(&context.recent_commits),
             &prompt_helpers::format_enhanced_author_history(&context.author_history, context),
-        ))
+        );
+        prompt_helpers::apply_template(
+            config,
+            "completion-user",
+            user_template_vars(
+                context,
+                json!({ "prefix": self.prefix, "context_ratio": self.context_ratio }),
+            ),
+            default,
+        )
     }
 }
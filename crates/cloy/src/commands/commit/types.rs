@@ -3,7 +3,7 @@ use serde::{Deserialize, Serialize};
 use textwrap;
 
 /// Model for commit message generation results
-#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq, Eq)]
 pub struct GeneratedMessage {
     /// Commit message title/subject line
     pub title: String,
@@ -3,7 +3,6 @@ use crate::commands::commit::service::CommitService;
 use crate::common::CommonParams;
 use crate::config::Config;
 use crate::git::GitRepo;
-use crate::llm::provider::ProviderKind;
 
 use anyhow::{Context, Result};
 use std::sync::Arc;
@@ -18,7 +17,7 @@ pub fn create_commit_service(
     let git_repo = GitRepo::new_from_url(repo_url).context("Failed to create GitRepo")?;
 
     let repo_path = git_repo.repo_path().clone();
-    let provider_name = ProviderKind::Google.as_str();
+    let provider_name = config.active_provider().as_str();
 
     let detail_level = common.detail_level;
 
@@ -50,7 +49,7 @@ pub fn create_completion_service(
     let git_repo = GitRepo::new_from_url(repo_url).context("Failed to create GitRepo")?;
 
     let repo_path = git_repo.repo_path().clone();
-    let provider_name = ProviderKind::Google.as_str();
+    let provider_name = config.active_provider().as_str();
 
     let service = Arc::new(
         CompletionService::new(config.clone(), &repo_path, provider_name, git_repo)
@@ -63,3 +62,25 @@ pub fn create_completion_service(
 
     Ok(service)
 }
+
+/// A commit service for changes given as a patch, usable outside a repository
+pub fn create_patch_service(common: &CommonParams, config: &Config) -> Result<Arc<CommitService>> {
+    let current_dir = std::env::current_dir()?;
+    let git_repo = GitRepo::new(&current_dir)?;
+    let provider_name = config.active_provider().as_str();
+
+    config
+        .check_provider_settings()
+        .context("Environment check failed")?;
+
+    Ok(Arc::new(
+        CommitService::new(
+            config.clone(),
+            &current_dir,
+            provider_name,
+            common.detail_level,
+            git_repo,
+        )
+        .context("Failed to create CommitService")?,
+    ))
+}
@@ -1,3 +1,5 @@
+use crate::output::is_plain_mode;
+use crate::progress;
 use crate::tui::spinner::SpinnerState;
 
 use anyhow::Result;
@@ -18,6 +20,18 @@ pub async fn run_with_spinner<F, T>(
 where
     F: AsyncFnOnce() -> Result<T, anyhow::Error>,
 {
+    // Tools reading progress events draw their own
+    if progress::is_json() {
+        return operation().await;
+    }
+
+    // Redrawn frames are noise for screen readers; announce the step once
+    if is_plain_mode() {
+        let (_, message, _, _) = spinner.tick();
+        println!("{message}");
+        return operation().await;
+    }
+
     let (tx, mut rx) = tokio::sync::mpsc::channel(1);
 
     let spinner_handle = tokio::spawn(async move {
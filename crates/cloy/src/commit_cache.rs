@@ -0,0 +1,274 @@
+//! Drafts of commit messages that were generated but not committed.
+//!
+//! Cancelling the TUI saves its messages to `.git/gitai/drafts.json`, keyed
+//! by the tree the index would commit. When `git-message` later finds the
+//! same staged changes, it offers the draft instead of generating again.
+//! Committing drops the draft, and `git-message --drafts` lists the rest.
+//! With `gitai.encrypt-cache` on, the file is sealed; see [`crate::cache_crypt`].
+
+use crate::cache_crypt;
+use crate::commands::commit::types::GeneratedMessage;
+use anyhow::{Context, Result, anyhow};
+use chrono::Utc;
+use git2::{Oid, Repository};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+/// Number of drafts kept per repository; the oldest are dropped first
+const MAX_DRAFTS: usize = 20;
+
+/// Messages for one set of staged changes
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Draft {
+    /// Tree the index pointed at when the draft was saved
+    pub tree: String,
+    /// Branch checked out at the time, for listing
+    pub branch: Option<String>,
+    /// Email of the user the messages were generated for, for purging
+    #[serde(default)]
+    pub author: Option<String>,
+    pub messages: Vec<GeneratedMessage>,
+    /// Message that was showing when the session ended
+    pub current: usize,
+    pub saved_at: i64,
+}
+
+impl Draft {
+    /// The message that was showing, or the first
+    pub fn current_message(&self) -> Option<&GeneratedMessage> {
+        self.messages
+            .get(self.current)
+            .or_else(|| self.messages.first())
+    }
+
+    /// Abbreviated tree hash, used to name the draft on the command line
+    pub fn id(&self) -> &str {
+        &self.tree[..self.tree.len().min(7)]
+    }
+}
+
+fn drafts_path(repo: &Repository) -> PathBuf {
+    repo.path().join("gitai").join("drafts.json")
+}
+
+fn read_drafts(repo: &Repository) -> Vec<Draft> {
+    cache_crypt::read(&drafts_path(repo))
+        .ok()
+        .and_then(|raw| serde_json::from_slice(&raw).ok())
+        .unwrap_or_default()
+}
+
+fn write_drafts(repo: &Repository, drafts: &[Draft]) -> Result<()> {
+    let path = drafts_path(repo);
+    if drafts.is_empty() {
+        return match fs::remove_file(&path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                Err(e).with_context(|| format!("Failed to remove {}", path.display()))
+            }
+            _ => Ok(()),
+        };
+    }
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    cache_crypt::write(
+        &path,
+        &serde_json::to_vec_pretty(drafts)?,
+        cache_crypt::enabled(Some(repo)),
+    )
+}
+
+/// The tree the index would commit, which identifies the staged changes
+pub fn staged_tree(repo: &Repository) -> Result<Oid> {
+    Ok(repo.index()?.write_tree()?)
+}
+
+/// Saves the messages for the staged changes, replacing an older draft of the same changes
+pub fn save(repo: &Repository, messages: &[GeneratedMessage], current: usize) -> Result<()> {
+    let messages: Vec<GeneratedMessage> = messages
+        .iter()
+        .filter(|m| !m.title.trim().is_empty())
+        .cloned()
+        .collect();
+    if messages.is_empty() {
+        return Ok(());
+    }
+    let tree = staged_tree(repo)?.to_string();
+    let branch = repo
+        .head()
+        .ok()
+        .and_then(|head| head.shorthand().map(ToString::to_string));
+    let author = repo
+        .config()
+        .and_then(|config| config.get_string("user.email"))
+        .ok();
+
+    let mut drafts = read_drafts(repo);
+    drafts.retain(|draft| draft.tree != tree);
+    drafts.push(Draft {
+        tree,
+        branch,
+        author,
+        current: current.min(messages.len() - 1),
+        messages,
+        saved_at: Utc::now().timestamp(),
+    });
+    let excess = drafts.len().saturating_sub(MAX_DRAFTS);
+    drafts.drain(..excess);
+    write_drafts(repo, &drafts)
+}
+
+/// The draft saved for the currently staged changes
+pub fn find(repo: &Repository) -> Option<Draft> {
+    let tree = staged_tree(repo).ok()?.to_string();
+    read_drafts(repo)
+        .into_iter()
+        .find(|draft| draft.tree == tree)
+}
+
+/// All drafts of the repository, newest first
+pub fn list(repo: &Repository) -> Vec<Draft> {
+    let mut drafts = read_drafts(repo);
+    drafts.reverse();
+    drafts
+}
+
+/// Drops the draft for the currently staged changes, if there is one
+pub fn discard(repo: &Repository) -> Result<()> {
+    let tree = staged_tree(repo)?.to_string();
+    let mut drafts = read_drafts(repo);
+    let before = drafts.len();
+    drafts.retain(|draft| draft.tree != tree);
+    if drafts.len() == before {
+        return Ok(());
+    }
+    write_drafts(repo, &drafts)
+}
+
+/// Deletes the draft whose tree hash starts with `id`, or every draft for `all`.
+///
+/// Returns the number of drafts deleted.
+pub fn delete(repo: &Repository, id: &str) -> Result<usize> {
+    let mut drafts = read_drafts(repo);
+    let before = drafts.len();
+    if id == "all" {
+        drafts.clear();
+    } else {
+        let matching = drafts.iter().filter(|d| d.tree.starts_with(id)).count();
+        match matching {
+            0 => return Err(anyhow!("No draft {id}; list them with --drafts")),
+            1 => drafts.retain(|draft| !draft.tree.starts_with(id)),
+            _ => {
+                return Err(anyhow!(
+                    "{id} names {matching} drafts; give more of the hash"
+                ));
+            }
+        }
+    }
+    write_drafts(repo, &drafts)?;
+    Ok(before - drafts.len())
+}
+
+/// Deletes the drafts generated for `email`; returns how many were deleted
+pub fn purge_author(repo: &Repository, email: &str) -> Result<usize> {
+    let mut drafts = read_drafts(repo);
+    let before = drafts.len();
+    drafts.retain(|draft| {
+        !draft
+            .author
+            .as_deref()
+            .is_some_and(|author| author.eq_ignore_ascii_case(email))
+    });
+    if drafts.len() == before {
+        return Ok(0);
+    }
+    write_drafts(repo, &drafts)?;
+    Ok(before - drafts.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn stage(repo: &Repository, name: &str) {
+        let workdir = repo.workdir().expect("Repository should have a workdir");
+        fs::write(workdir.join(name), name).expect("Failed to write file");
+        let mut index = repo.index().expect("Failed to open index");
+        index.add_path(Path::new(name)).expect("Failed to add file");
+        index.write().expect("Failed to write index");
+    }
+
+    fn message(title: &str) -> GeneratedMessage {
+        GeneratedMessage {
+            title: title.to_string(),
+            message: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_drafts_follow_the_staged_changes() {
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let repo = Repository::init(dir.path()).expect("Failed to init repo");
+
+        stage(&repo, "a.txt");
+        save(&repo, &[message("Add a"), message("Add file a")], 1).expect("Failed to save");
+        let draft = find(&repo).expect("Draft for the staged changes");
+        assert_eq!(draft.current_message(), Some(&message("Add file a")));
+
+        // Other staged changes have no draft until saved
+        stage(&repo, "b.txt");
+        assert!(find(&repo).is_none());
+        save(&repo, &[message("Add b")], 0).expect("Failed to save");
+        assert_eq!(list(&repo).len(), 2);
+        assert_eq!(list(&repo)[0].messages, vec![message("Add b")]);
+
+        discard(&repo).expect("Failed to discard");
+        assert!(find(&repo).is_none());
+        assert_eq!(list(&repo).len(), 1);
+
+        assert!(delete(&repo, "zzz").is_err());
+        let id = list(&repo)[0].id().to_string();
+        assert_eq!(delete(&repo, &id).expect("Failed to delete"), 1);
+        assert!(!drafts_path(&repo).exists());
+    }
+
+    #[test]
+    fn test_empty_messages_are_not_saved() {
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let repo = Repository::init(dir.path()).expect("Failed to init repo");
+        stage(&repo, "a.txt");
+        save(&repo, &[message("  ")], 0).expect("Failed to save");
+        assert!(list(&repo).is_empty());
+    }
+
+    #[test]
+    fn test_purge_author() {
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let repo = Repository::init(dir.path()).expect("Failed to init repo");
+        let mut config = repo.config().expect("Failed to open config");
+        config
+            .set_str("user.email", "ada@example.com")
+            .expect("Failed to set email");
+        stage(&repo, "a.txt");
+        save(&repo, &[message("Add a")], 0).expect("Failed to save");
+        config
+            .set_str("user.email", "grace@example.com")
+            .expect("Failed to set email");
+        stage(&repo, "b.txt");
+        save(&repo, &[message("Add b")], 0).expect("Failed to save");
+
+        assert_eq!(
+            purge_author(&repo, "Ada@Example.com").expect("Failed to purge"),
+            1
+        );
+        assert_eq!(
+            purge_author(&repo, "ada@example.com").expect("Failed to purge"),
+            0
+        );
+        let drafts = list(&repo);
+        assert_eq!(drafts.len(), 1);
+        assert_eq!(drafts[0].author.as_deref(), Some("grace@example.com"));
+    }
+}
@@ -1,9 +1,12 @@
 use crate::config::Config;
-use crate::llm::provider::ProviderKind;
+use crate::output;
+use crate::progress::{self, ProgressFormat};
+use crate::tui::theme::{BUILTIN_PALETTES, Theme};
 use anyhow::Result;
 use clap::{Args, ValueEnum};
 use std::env;
 use std::fmt::Write;
+use std::path::PathBuf;
 use std::str::FromStr;
 
 #[derive(Clone, Copy, PartialEq, Eq, Debug, ValueEnum)]
@@ -92,6 +95,14 @@ pub struct CommonParams {
     #[arg(short, long, help = "Custom instructions for this operation")]
     pub instructions: Option<String>,
 
+    /// Language to write generated text in
+    #[arg(
+        long,
+        value_name = "CODE",
+        help = "Write messages, PR descriptions and changelogs in this language (e.g. ja, de, pt-BR)"
+    )]
+    pub language: Option<String>,
+
     /// Set the detail level
     #[arg(
         long,
@@ -108,13 +119,115 @@ pub struct CommonParams {
     )]
     pub repository_url: Option<String>,
 
-    /// Theme mode (dark, light, system)
+    /// Theme: a mode, a built-in palette or a theme defined in config
     #[arg(
         long = "theme",
-        help = "Theme mode (dark, light, system)",
-        default_value = "system"
+        help = "Theme: dark, light, system, solarized, gruvbox, catppuccin or a [gitai-theme] from git config",
+        default_value = "system",
+        value_parser = parse_theme_name
+    )]
+    pub theme: String,
+
+    /// Disable colored output (also honors `NO_COLOR`)
+    #[arg(
+        long = "no-color",
+        help = "Disable colored output (also honors NO_COLOR)"
+    )]
+    pub no_color: bool,
+
+    /// Git config file whose settings win over the repository's and the user's
+    #[arg(
+        long = "config",
+        value_name = "PATH",
+        help = "Read settings from this git config file ahead of the repository and global ones (also GITAI_CONFIG)"
+    )]
+    pub config_file: Option<PathBuf>,
+
+    /// Profile whose settings win over the plain ones
+    #[arg(
+        long,
+        value_name = "NAME",
+        help = "Use the settings of the [gitai-profile \"NAME\"] section from git config (also GITAI_PROFILE)"
+    )]
+    pub profile: Option<String>,
+
+    /// File to write a Chrome trace of the run's spans to
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "Write a Chrome trace of where the run spends its time to this file (open in Perfetto or chrome://tracing)"
+    )]
+    pub trace: Option<PathBuf>,
+
+    /// How progress is shown
+    #[arg(
+        long,
+        value_enum,
+        value_name = "FORMAT",
+        default_value_t,
+        help = "Show progress as spinners (human) or as NDJSON events on stderr for tools wrapping gitai (json)"
+    )]
+    pub progress: ProgressFormat,
+
+    /// Unchanged lines around each change in the diffs sent to the model
+    #[arg(
+        short = 'U',
+        long = "unified",
+        value_name = "N",
+        help = "Show N lines of context around changes in the diffs sent to the model (also gitai.diff-context)"
+    )]
+    pub unified: Option<u32>,
+
+    /// Show changed lines word by word
+    #[arg(
+        long = "word-diff",
+        help = "Send changed lines word by word, as [-old-]{+new+}, for prose-heavy changes (also gitai.word-diff)"
+    )]
+    pub word_diff: bool,
+
+    /// Pin the temperature and seed for reproducible output
+    #[arg(
+        long,
+        help = "Generate with temperature 0 and a fixed seed, where the provider takes one, for reproducible output in CI and evaluations"
+    )]
+    pub deterministic: bool,
+
+    /// Top-level bullet points allowed in a commit message body
+    #[arg(
+        long,
+        value_name = "N",
+        help = "Keep at most N bullet points in commit message bodies (also gitai.max-bullets)"
+    )]
+    pub max_bullets: Option<usize>,
+
+    /// Characters allowed in a commit subject
+    #[arg(
+        long,
+        value_name = "N",
+        help = "Cut commit subjects to N characters (also gitai.max-subject-length)"
+    )]
+    pub max_subject_length: Option<usize>,
+
+    /// Characters allowed in a whole commit message
+    #[arg(
+        long,
+        value_name = "N",
+        help = "Cut commit messages to N characters in total (also gitai.max-length)"
     )]
-    pub theme: ThemeMode,
+    pub max_length: Option<usize>,
+}
+
+/// Accepts modes, built-in palettes and themes defined in git config
+fn parse_theme_name(value: &str) -> Result<String, String> {
+    let user_themes = Config::load().map(|c| c.themes).unwrap_or_default();
+    if Theme::exists(value, &user_themes) {
+        Ok(value.to_string())
+    } else {
+        Err(format!(
+            "unknown theme '{value}' (expected dark, light, system, {} or a [gitai-theme] from git config)",
+            BUILTIN_PALETTES.join(", ")
+        ))
+    }
 }
 
 impl Default for CommonParams {
@@ -122,9 +235,21 @@ impl Default for CommonParams {
         Self {
             model: None,
             instructions: None,
+            language: None,
             detail_level: DetailLevel::Standard,
             repository_url: None,
-            theme: ThemeMode::Dark,
+            theme: "dark".to_string(),
+            no_color: false,
+            config_file: None,
+            profile: None,
+            trace: None,
+            progress: ProgressFormat::Human,
+            unified: None,
+            word_diff: false,
+            deterministic: false,
+            max_bullets: None,
+            max_subject_length: None,
+            max_length: None,
         }
     }
 }
@@ -133,8 +258,14 @@ impl CommonParams {
     pub fn apply_to_config(&self, config: &mut Config) -> Result<bool> {
         let mut changes_made = false;
 
+        // Output settings are process-wide; every command applies them here
+        if self.no_color || output::no_color_requested() {
+            output::set_color_enabled(false);
+        }
+        progress::set_format(self.progress);
+
         if let Some(model) = &self.model {
-            let provider_name = ProviderKind::Google.as_str();
+            let provider_name = config.active_provider().as_str();
             if let Some(provider_config) = config.providers.get_mut(provider_name)
                 && provider_config.model_name != *model
             {
@@ -147,6 +278,31 @@ impl CommonParams {
             config.set_temp_instructions(Some(instructions.clone()));
         }
 
+        if let Some(language) = &self.language {
+            config.language = Some(language.clone());
+        }
+
+        if let Some(lines) = self.unified {
+            config.diff.context_lines = lines;
+        }
+        if self.word_diff {
+            config.diff.word_diff = true;
+        }
+        if self.max_bullets.is_some() {
+            config.limits.max_bullets = self.max_bullets;
+        }
+        if self.max_subject_length.is_some() {
+            config.limits.max_subject = self.max_subject_length;
+        }
+        if self.max_length.is_some() {
+            config.limits.max_length = self.max_length;
+        }
+        if self.deterministic {
+            for provider_config in config.providers.values_mut() {
+                provider_config.make_deterministic();
+            }
+        }
+
         Ok(changes_made)
     }
 }
@@ -163,5 +319,12 @@ pub fn get_combined_instructions(config: &Config) -> String {
         .ok();
     }
 
+    if let Some(language) = &config.language {
+        let language = prompts::language::language_instructions(language);
+        if !language.is_empty() {
+            write!(&mut prompt, "{language}\n\n").ok();
+        }
+    }
+
     prompt
 }
@@ -1,20 +1,244 @@
+use crate::commands::commit::limits::MessageLimits;
+use crate::commands::commit::post_process::PostProcessSettings;
+use crate::config_migration;
+use crate::conventions::ProjectConventions;
+use crate::error::{ErrorCode, coded};
 use crate::git::GitRepo;
+use crate::http::{self, HttpSettings};
+use crate::issues::{IssueSettings, IssueTracker};
+use crate::llm::context::ContextFile;
+use crate::llm::endpoint::validate_endpoint;
 use crate::llm::engine::{get_available_provider_names, get_default_model_for_provider};
 use crate::llm::provider::ProviderKind;
+use crate::plugins::{self, HookPoint, PluginSettings, WasmPluginSpec};
+use crate::trailers::{self, TrailerSettings};
 
 use anyhow::{Result, anyhow};
-use git2::Config as GitConfig;
+use git2::{Config as GitConfig, ConfigLevel};
 use log::debug;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
 
-/// Get a configuration value with layered priority: env var > local git config > global git config
+/// Instructions the Conventional Commits preset of `gitai init` stores in `gitai.instructions`
+pub const CONVENTIONAL_INSTRUCTIONS: &str = "Follow the Conventional Commits format \
+    `type(scope): subject`, with types such as feat, fix, docs, refactor, test and chore.";
+
+/// Column message bodies are wrapped at when `gitai.wrap-column` is unset
+pub const DEFAULT_WRAP_COLUMN: usize = 72;
+
+/// Additional param holding the sampling temperature
+pub const TEMPERATURE_PARAM: &str = "temperature";
+/// Additional param holding the nucleus sampling cutoff
+pub const TOP_P_PARAM: &str = "top_p";
+/// Additional param holding the sampling seed, for providers that take one
+pub const SEED_PARAM: &str = "seed";
+/// Seed `--deterministic` uses when none is configured
+pub const DETERMINISTIC_SEED: u64 = 42;
+
+/// How much history goes into the context of a generation
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(default)]
+pub struct ContextDepth {
+    /// Recent commits of the branch, used when no commit touched the changed files
+    pub recent_commits: usize,
+    /// Recent commits that touched the changed files
+    pub file_commits: usize,
+    /// The author's own latest messages, as examples of their style
+    pub author_history: usize,
+    /// Most commits searched for ones that touched the changed files
+    pub history_walk: usize,
+}
+
+impl Default for ContextDepth {
+    fn default() -> Self {
+        Self {
+            recent_commits: 10,
+            file_commits: 10,
+            author_history: 10,
+            history_walk: 5_000,
+        }
+    }
+}
+
+/// How the diffs in prompts are generated
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(default)]
+pub struct DiffSettings {
+    /// Unchanged lines shown around each change, as with `git diff -U`
+    pub context_lines: u32,
+    /// Whether changed lines are shown word by word, as `[-old-]{+new+}`
+    pub word_diff: bool,
+    /// Bytes above which a file's diff and content are left out, or 0 for no limit
+    pub max_file_size: u64,
+    /// Bytes above which a new file's content is sampled, or 0 to never sample
+    pub sample_size: u64,
+}
+
+impl Default for DiffSettings {
+    fn default() -> Self {
+        Self {
+            context_lines: 3,
+            word_diff: false,
+            max_file_size: 1024 * 1024,
+            sample_size: 16 * 1024,
+        }
+    }
+}
+
+/// The config file and profile chosen with `--config` and `--profile`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConfigSelection {
+    /// Git config file read ahead of the repository's and the user's
+    pub file: Option<PathBuf>,
+    /// Name of the `[gitai-profile "<name>"]` section whose settings win
+    pub profile: Option<String>,
+}
+
+static SELECTION: RwLock<ConfigSelection> = RwLock::new(ConfigSelection {
+    file: None,
+    profile: None,
+});
+
+/// Chooses the config file and profile every later load uses.
+///
+/// Whatever is left unset falls back to `GITAI_CONFIG` and `GITAI_PROFILE`.
+pub fn select_config(selection: ConfigSelection) {
+    if let Ok(mut current) = SELECTION.write() {
+        *current = selection;
+    }
+}
+
+/// The chosen value, or else a non-empty environment variable
+fn selected(
+    value: impl FnOnce(&ConfigSelection) -> Option<String>,
+    env_var: &str,
+) -> Option<String> {
+    SELECTION
+        .read()
+        .ok()
+        .and_then(|selection| value(&selection))
+        .or_else(|| std::env::var(env_var).ok())
+        .filter(|value| !value.trim().is_empty())
+}
+
+fn selected_file() -> Option<PathBuf> {
+    selected(
+        |selection| {
+            selection
+                .file
+                .as_ref()
+                .map(|path| path.to_string_lossy().into_owned())
+        },
+        "GITAI_CONFIG",
+    )
+    .map(PathBuf::from)
+}
+
+fn active_profile() -> Option<String> {
+    selected(|selection| selection.profile.clone(), "GITAI_PROFILE")
+}
+
+/// Section the settings of `profile` are written to, `gitai` without one
+fn settings_section(profile: Option<&str>) -> String {
+    profile.map_or_else(
+        || "gitai".to_string(),
+        |name| format!("gitai-profile.{name}"),
+    )
+}
+
+/// Puts the `--config` file on top of a config, so its values win
+fn with_selected_file(mut config: GitConfig) -> GitConfig {
+    if let Some(path) = selected_file()
+        && let Err(e) = config.add_file(&path, ConfigLevel::App, true)
+    {
+        debug!("Failed to read config file {}: {e}", path.display());
+    }
+    config
+}
+
+/// The configs of `repo` and the user, each topped by the `--config` file
+fn selected_configs(repo: Option<&git2::Repository>) -> (Option<GitConfig>, Option<GitConfig>) {
+    let global_config = GitConfig::open_default().ok().map(with_selected_file);
+    let local_config = repo
+        .and_then(|repo| repo.config().ok())
+        .map(with_selected_file);
+    (local_config, global_config)
+}
+
+/// The current repository's and the user's git configs, each topped by the `--config` file
+fn open_configs() -> (Option<GitConfig>, Option<GitConfig>) {
+    selected_configs(git2::Repository::discover(".").ok().as_ref())
+}
+
+/// Whether any config has settings for `profile`
+fn profile_defined(
+    profile: &str,
+    local_config: Option<&GitConfig>,
+    global_config: Option<&GitConfig>,
+) -> bool {
+    let pattern = format!(r"^gitai-profile\.{}\.", regex::escape(profile));
+    [local_config, global_config]
+        .into_iter()
+        .flatten()
+        .any(|config| {
+            config
+                .entries(Some(&pattern))
+                .is_ok_and(|mut entries| entries.next().is_some())
+        })
+}
+
+/// The value `profile` sets for `gitai.<key>`, as `gitai-profile.<profile>.<key>`
+fn profile_value(
+    profile: &str,
+    key: &str,
+    local_config: Option<&GitConfig>,
+    global_config: Option<&GitConfig>,
+) -> Option<String> {
+    let profile_key = format!("gitai-profile.{profile}.{}", key.strip_prefix("gitai.")?);
+    [local_config, global_config]
+        .into_iter()
+        .flatten()
+        .find_map(|config| config.get_string(&profile_key).ok())
+}
+
+/// Fails when the chosen config file is missing or the chosen profile has no settings
+fn check_selection(
+    local_config: Option<&GitConfig>,
+    global_config: Option<&GitConfig>,
+) -> Result<()> {
+    if let Some(path) = selected_file()
+        && !path.is_file()
+    {
+        return Err(anyhow!("Config file {} does not exist", path.display()));
+    }
+    if let Some(profile) = active_profile()
+        && !profile_defined(&profile, local_config, global_config)
+    {
+        return Err(anyhow!(
+            "Profile '{profile}' is not defined; add settings with `git config --global gitai-profile.{profile}.<key> <value>`"
+        ));
+    }
+    Ok(())
+}
+
+/// Get a configuration value with layered priority:
+/// profile > env var > local git config > global git config
+///
+/// The active profile's `gitai-profile.<name>.<key>` stands in for `gitai.<key>`.
 fn get_layered_value(
     key: &str,
     env_var: Option<&str>,
     local_config: Option<&GitConfig>,
     global_config: Option<&GitConfig>,
 ) -> Option<String> {
+    if let Some(profile) = active_profile()
+        && let Some(val) = profile_value(&profile, key, local_config, global_config)
+    {
+        return Some(val);
+    }
+
     // First, check environment variable
     if let Some(env) = env_var
         && let Ok(val) = std::env::var(env)
@@ -61,12 +285,539 @@ fn load_additional_params(
     }
 }
 
+/// Load user-defined TUI themes from `[gitai-theme "<name>"]` sections
+fn load_themes(config: &GitConfig, themes: &mut HashMap<String, HashMap<String, String>>) {
+    if let Ok(mut entries) = config.entries(Some(r"^gitai-theme\.")) {
+        while let Some(Ok(entry)) = entries.next() {
+            if let Some(name) = entry.name()
+                && let Some(value) = entry.value()
+                && let Some((theme, slot)) = name
+                    .strip_prefix("gitai-theme.")
+                    .and_then(|rest| rest.rsplit_once('.'))
+            {
+                themes
+                    .entry(theme.to_string())
+                    .or_default()
+                    .insert(slot.to_string(), value.to_string());
+            }
+        }
+    }
+}
+
+/// Load the flags set in a `[gitai-defaults "<command>"]` section
+fn load_defaults(config: &GitConfig, command: &str, defaults: &mut BTreeMap<String, String>) {
+    let prefix = format!("gitai-defaults.{command}.");
+    if let Ok(mut entries) = config.entries(Some(&format!("^{}", regex::escape(&prefix)))) {
+        while let Some(Ok(entry)) = entries.next() {
+            if let Some(name) = entry.name()
+                && let Some(value) = entry.value()
+                && let Some(flag) = name.strip_prefix(&prefix)
+            {
+                defaults.insert(flag.to_string(), value.to_string());
+            }
+        }
+    }
+}
+
+/// Default flags for `command` from `[gitai-defaults "<command>"]`, by long flag name.
+///
+/// Repository settings override global ones flag by flag.
+pub fn load_command_defaults(command: &str) -> BTreeMap<String, String> {
+    let (local_config, global_config) = open_configs();
+    let mut defaults = BTreeMap::new();
+    for config in [global_config.as_ref(), local_config.as_ref()]
+        .into_iter()
+        .flatten()
+    {
+        load_defaults(config, command, &mut defaults);
+    }
+    defaults
+}
+
+/// Load `gitai.wrap-column` and `gitai.spell-check` for the TUI editor
+fn load_editor_settings(
+    local_config: Option<&GitConfig>,
+    global_config: Option<&GitConfig>,
+) -> (Option<usize>, Option<bool>) {
+    let wrap_column = get_layered_value(
+        "gitai.wrap-column",
+        Some("GITAI_WRAP_COLUMN"),
+        local_config,
+        global_config,
+    )
+    .and_then(|value| value.trim().parse().ok());
+    let spell_check = get_layered_value(
+        "gitai.spell-check",
+        Some("GITAI_SPELL_CHECK"),
+        local_config,
+        global_config,
+    )
+    .and_then(|value| parse_bool(&value));
+    (wrap_column, spell_check)
+}
+
+/// Load the `gitai.template-<name>` prompt template paths
+fn load_templates(
+    local_config: Option<&GitConfig>,
+    global_config: Option<&GitConfig>,
+    workdir: Option<&Path>,
+) -> HashMap<String, PathBuf> {
+    prompts::templates::TEMPLATE_NAMES
+        .iter()
+        .filter_map(|name| {
+            let value = get_layered_value(
+                &format!("gitai.template-{name}"),
+                None,
+                local_config,
+                global_config,
+            )?;
+            Some(((*name).to_string(), resolve_template_path(&value, workdir)))
+        })
+        .collect()
+}
+
+/// Load `gitai.config-version`, which is 0 when unset
+fn load_config_version(local_config: Option<&GitConfig>, global_config: Option<&GitConfig>) -> u32 {
+    get_layered_value(
+        config_migration::VERSION_KEY,
+        None,
+        local_config,
+        global_config,
+    )
+    .and_then(|value| value.trim().parse().ok())
+    .unwrap_or(0)
+}
+
+/// Load `gitai.context-recent-commits`, `-file-commits`, `-author-history` and `-history-walk`
+fn load_context_depth(
+    local_config: Option<&GitConfig>,
+    global_config: Option<&GitConfig>,
+) -> ContextDepth {
+    let depth = |key: &str, default: usize| {
+        get_layered_value(
+            &format!("gitai.context-{key}"),
+            None,
+            local_config,
+            global_config,
+        )
+        .and_then(|value| value.trim().parse().ok())
+        .unwrap_or(default)
+    };
+    let defaults = ContextDepth::default();
+    ContextDepth {
+        recent_commits: depth("recent-commits", defaults.recent_commits),
+        file_commits: depth("file-commits", defaults.file_commits),
+        author_history: depth("author-history", defaults.author_history),
+        history_walk: depth("history-walk", defaults.history_walk),
+    }
+}
+
+/// Load `gitai.post-process` and `gitai.forbidden-words`
+fn load_post_process_settings(
+    local_config: Option<&GitConfig>,
+    global_config: Option<&GitConfig>,
+) -> PostProcessSettings {
+    let value = |key: &str| get_layered_value(key, None, local_config, global_config);
+    PostProcessSettings::from_values(
+        value("gitai.post-process").as_deref(),
+        value("gitai.forbidden-words").as_deref(),
+    )
+}
+
+/// Load `gitai.notebook-outputs`
+fn load_notebook_outputs(
+    local_config: Option<&GitConfig>,
+    global_config: Option<&GitConfig>,
+) -> Option<bool> {
+    get_layered_value("gitai.notebook-outputs", None, local_config, global_config)
+        .and_then(|value| parse_bool(&value))
+}
+
+/// Load `gitai.summarize-diffs`
+fn load_summarize_diffs(
+    local_config: Option<&GitConfig>,
+    global_config: Option<&GitConfig>,
+) -> Option<bool> {
+    get_layered_value("gitai.summarize-diffs", None, local_config, global_config)
+        .and_then(|value| parse_bool(&value))
+}
+
+/// Load `gitai.max-bullets`, `gitai.max-subject-length` and `gitai.max-length`
+fn load_message_limits(
+    local_config: Option<&GitConfig>,
+    global_config: Option<&GitConfig>,
+) -> MessageLimits {
+    let limit = |key: &str| {
+        get_layered_value(key, None, local_config, global_config)
+            .and_then(|value| value.trim().parse().ok())
+    };
+    MessageLimits {
+        max_bullets: limit("gitai.max-bullets"),
+        max_subject: limit("gitai.max-subject-length"),
+        max_length: limit("gitai.max-length"),
+    }
+}
+
+/// Load `gitai.diff-context`, `gitai.word-diff`, `gitai.max-file-size` and `gitai.sample-size`
+fn load_diff_settings(
+    local_config: Option<&GitConfig>,
+    global_config: Option<&GitConfig>,
+) -> DiffSettings {
+    let value = |key: &str| get_layered_value(key, None, local_config, global_config);
+    let defaults = DiffSettings::default();
+    DiffSettings {
+        context_lines: value("gitai.diff-context")
+            .and_then(|value| value.trim().parse().ok())
+            .unwrap_or(defaults.context_lines),
+        word_diff: value("gitai.word-diff")
+            .and_then(|value| parse_bool(&value))
+            .unwrap_or(defaults.word_diff),
+        max_file_size: value("gitai.max-file-size")
+            .and_then(|value| value.trim().parse().ok())
+            .unwrap_or(defaults.max_file_size),
+        sample_size: value("gitai.sample-size")
+            .and_then(|value| value.trim().parse().ok())
+            .unwrap_or(defaults.sample_size),
+    }
+}
+
+/// Load `gitai.noise-filter` and the comma-separated `gitai.noise-patterns`
+fn load_noise_settings(
+    local_config: Option<&GitConfig>,
+    global_config: Option<&GitConfig>,
+) -> (Option<bool>, Vec<String>) {
+    let noise_filter = get_layered_value("gitai.noise-filter", None, local_config, global_config)
+        .and_then(|value| parse_bool(&value));
+    let noise_patterns =
+        get_layered_value("gitai.noise-patterns", None, local_config, global_config)
+            .map(|value| {
+                value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|p| !p.is_empty())
+                    .map(ToString::to_string)
+                    .collect()
+            })
+            .unwrap_or_default();
+    (noise_filter, noise_patterns)
+}
+
+/// Load the trailers added to created commits, with `[gitai-pair]` presets merged per name
+fn load_trailer_settings(
+    local_config: Option<&GitConfig>,
+    global_config: Option<&GitConfig>,
+) -> TrailerSettings {
+    let list = |key: &str| {
+        get_layered_value(key, None, local_config, global_config)
+            .map(|value| trailers::parse_list(&value))
+            .unwrap_or_default()
+    };
+    let mut pairs = HashMap::new();
+    for config in [global_config, local_config].into_iter().flatten() {
+        if let Ok(mut entries) = config.entries(Some(r"^gitai-pair\.")) {
+            while let Some(Ok(entry)) = entries.next() {
+                if let Some(name) = entry.name()
+                    && let Some(value) = entry.value()
+                    && let Some(preset) = name.strip_prefix("gitai-pair.")
+                {
+                    pairs.insert(preset.to_lowercase(), trailers::parse_list(value));
+                }
+            }
+        }
+    }
+    TrailerSettings {
+        co_authors: list("gitai.co-authors"),
+        trailers: list("gitai.trailers"),
+        signoff: get_layered_value("gitai.signoff", None, local_config, global_config)
+            .and_then(|value| parse_bool(&value))
+            .unwrap_or(false),
+        pairs,
+    }
+}
+
+/// Load the plugin commands of each hook point, where every value of a
+/// `gitai.hook-<point>` key is a command, global ones first, and the WASM plugins
+fn load_plugin_settings(
+    local_config: Option<&GitConfig>,
+    global_config: Option<&GitConfig>,
+) -> PluginSettings {
+    // A repository's config already holds the global and system levels
+    let config = local_config.or(global_config);
+    let commands = |point: HookPoint| {
+        let key = format!("gitai.hook-{}", point.name());
+        let mut commands = Vec::new();
+        if let Some(config) = config
+            && let Ok(mut entries) = config.multivar(&key, None)
+        {
+            while let Some(Ok(entry)) = entries.next() {
+                if let Some(command) = entry.value().map(str::trim)
+                    && !command.is_empty()
+                {
+                    commands.push(command.to_string());
+                }
+            }
+        }
+        commands
+    };
+    PluginSettings {
+        context_collected: commands(HookPoint::ContextCollected),
+        message_generated: commands(HookPoint::MessageGenerated),
+        pre_commit: commands(HookPoint::PreCommit),
+        wasm: load_wasm_plugins(config),
+    }
+}
+
+/// Load the WASM plugins declared in `[gitai-plugin "<name>"]` sections, in
+/// the order they are first declared
+fn load_wasm_plugins(config: Option<&GitConfig>) -> Vec<WasmPluginSpec> {
+    let Some(config) = config else {
+        return Vec::new();
+    };
+    let mut specs: Vec<WasmPluginSpec> = Vec::new();
+    if let Ok(mut entries) = config.entries(Some(r"^gitai-plugin\.")) {
+        while let Some(Ok(entry)) = entries.next() {
+            let (Some(name), Some(value)) = (entry.name(), entry.value()) else {
+                continue;
+            };
+            let Some((plugin, key)) = name
+                .strip_prefix("gitai-plugin.")
+                .and_then(|rest| rest.rsplit_once('.'))
+            else {
+                continue;
+            };
+            let index = specs
+                .iter()
+                .position(|spec| spec.name == plugin)
+                .unwrap_or_else(|| {
+                    specs.push(WasmPluginSpec {
+                        name: plugin.to_string(),
+                        ..WasmPluginSpec::default()
+                    });
+                    specs.len() - 1
+                });
+            let spec = &mut specs[index];
+            match key {
+                "path" => spec.path = PathBuf::from(value.trim()),
+                "analyze" => spec.analyze = trailers::parse_list(value),
+                "post-process" => spec.post_process = parse_bool(value).unwrap_or(false),
+                _ => debug!("Unknown WASM plugin setting {name}"),
+            }
+        }
+    }
+    let dir = plugins::plugin_dir().unwrap_or_default();
+    for spec in specs
+        .iter_mut()
+        .filter(|spec| spec.path.as_os_str().is_empty())
+    {
+        spec.path = dir.join(format!("{}.wasm", spec.name));
+    }
+    specs
+}
+
+/// Load the proxy and TLS settings of gitai's HTTP clients.
+///
+/// They are read from the user's git configs and the environment only, never
+/// from a project's `.gitai`, so a repository cannot reroute requests
+/// that carry API keys.
+pub(crate) fn load_http_settings() -> HttpSettings {
+    let (local_config, global_config) = open_configs();
+    let value = |key: &str, env: &str| {
+        get_layered_value(
+            key,
+            Some(env),
+            local_config.as_ref(),
+            global_config.as_ref(),
+        )
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+    };
+    HttpSettings {
+        proxy: value("gitai.proxy", "GITAI_PROXY"),
+        no_proxy: value("gitai.no-proxy", "GITAI_NO_PROXY"),
+        ca_bundle: value("gitai.ca-bundle", "GITAI_CA_BUNDLE").map(PathBuf::from),
+        tls_backend: value("gitai.tls-backend", "GITAI_TLS_BACKEND"),
+    }
+}
+
+/// Issue tracker settings; tokens come from git config or the environment only
+pub(crate) fn load_issue_settings() -> IssueSettings {
+    let (local_config, global_config) = open_configs();
+    let value = |key: &str, env: &str| {
+        get_layered_value(
+            key,
+            Some(env),
+            local_config.as_ref(),
+            global_config.as_ref(),
+        )
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+    };
+    IssueSettings {
+        tracker: value("gitai.issue-tracker", "GITAI_ISSUE_TRACKER")
+            .and_then(|name| IssueTracker::from_name(&name)),
+        jira_url: value("gitai.jira-url", "GITAI_JIRA_URL"),
+        jira_user: value("gitai.jira-user", "GITAI_JIRA_USER"),
+        jira_token: value("gitai.jira-token", "GITAI_JIRA_TOKEN"),
+        jira_projects: value("gitai.jira-projects", "GITAI_JIRA_PROJECTS")
+            .map(|projects| {
+                projects
+                    .split(',')
+                    .map(|project| project.trim().to_string())
+                    .filter(|project| !project.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default(),
+        github_token: value("gitai.github-token", "GITHUB_TOKEN"),
+        github_api: value("gitai.github-api", "GITAI_GITHUB_API"),
+    }
+}
+
+/// Load the API key, model and additional parameters of every provider
+fn load_providers(
+    local_config: Option<&GitConfig>,
+    global_config: Option<&GitConfig>,
+) -> HashMap<String, ProviderConfig> {
+    let mut providers = HashMap::new();
+    for provider in get_available_provider_names() {
+        let api_key = get_layered_value(
+            &format!("gitai.{provider}-apikey"),
+            get_api_key_env_var(&provider),
+            local_config,
+            global_config,
+        )
+        .unwrap_or_default();
+
+        let default_model = get_default_model_for_provider(&provider).to_string();
+        let model = get_layered_value(
+            &format!("gitai.{provider}-model"),
+            None, // no env for model yet
+            local_config,
+            global_config,
+        )
+        .unwrap_or(default_model);
+
+        let mut additional_params = HashMap::new();
+        // Load from global first, then local to allow local to override
+        if let Some(config) = global_config {
+            load_additional_params(config, &provider, &mut additional_params);
+        }
+        if let Some(config) = local_config {
+            load_additional_params(config, &provider, &mut additional_params);
+        }
+
+        providers.insert(
+            #[allow(clippy::implicit_clone)]
+            provider.to_owned(),
+            ProviderConfig {
+                api_key,
+                model_name: model,
+                additional_params,
+            },
+        );
+    }
+    providers
+}
+
+/// Resolve a template path: `~/` is the home directory, relative paths start at the repository root
+fn resolve_template_path(value: &str, workdir: Option<&Path>) -> PathBuf {
+    if let Some(rest) = value.strip_prefix("~/")
+        && let Some(home) = dirs::home_dir()
+    {
+        return home.join(rest);
+    }
+    let path = PathBuf::from(value);
+    match workdir {
+        Some(root) if path.is_relative() => root.join(path),
+        _ => path,
+    }
+}
+
+/// Parse a git-style boolean (`true`/`yes`/`on`/`1` and their negations)
+pub(crate) fn parse_bool(value: &str) -> Option<bool> {
+    match value.trim().to_lowercase().as_str() {
+        "true" | "yes" | "on" | "1" => Some(true),
+        "false" | "no" | "off" | "0" => Some(false),
+        _ => None,
+    }
+}
+
+/// Write `gitai.<provider>-apikey` to a git config file only the owner can read
+///
+/// libgit2 would write the file readable by everyone before it could be
+/// restricted, so the file is rendered here, created with mode 0600 next to
+/// the old one and moved over it. The other keys it held are kept.
+fn write_credential(path: &Path, provider: &str, api_key: &str) -> Result<()> {
+    use std::fmt::Write as _;
+    use std::io::Write as _;
+
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let mut keys = BTreeMap::new();
+    if path.exists() {
+        let existing = GitConfig::open(path)?;
+        let mut entries = existing.entries(Some(r"^gitai\..*-apikey$"))?;
+        while let Some(Ok(entry)) = entries.next() {
+            if let Some(name) = entry.name().and_then(|name| name.strip_prefix("gitai."))
+                && let Some(value) = entry.value()
+            {
+                keys.insert(name.to_string(), value.to_string());
+            }
+        }
+    }
+    keys.insert(
+        format!("{provider}-apikey").to_lowercase(),
+        api_key.to_string(),
+    );
+
+    let mut content = String::from("[gitai]\n");
+    for (name, value) in &keys {
+        let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+        let _ = writeln!(content, "\t{name} = \"{value}\"");
+    }
+
+    let staged = path.with_extension("new");
+    // A leftover file would keep its own mode
+    let _ = std::fs::remove_file(&staged);
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(&staged)?;
+    file.write_all(content.as_bytes())?;
+    file.sync_all()?;
+    std::fs::rename(&staged, path)?;
+    Ok(())
+}
+
+/// Add `include.path` for a file unless the config already includes it
+fn include_config_file(config: &mut GitConfig, path: &Path) -> Result<()> {
+    let path = path.to_string_lossy();
+    let mut included = false;
+    if let Ok(mut entries) = config.multivar("include.path", None) {
+        while let Some(Ok(entry)) = entries.next() {
+            included |= entry.value() == Some(path.as_ref());
+        }
+    }
+    if !included {
+        // A pattern matching no existing value appends a new entry
+        config.set_multivar("include.path", "^$", &path)?;
+    }
+    Ok(())
+}
+
 /// Get the environment variable name for a provider's API key
-fn get_api_key_env_var(provider: &str) -> Option<&'static str> {
+pub fn get_api_key_env_var(provider: &str) -> Option<&'static str> {
     match ProviderKind::from_name(provider) {
         Some(ProviderKind::Google) => Some("GOOGLE_API_KEY"),
         Some(ProviderKind::OpenRouter) => Some("OPENROUTER_API_KEY"),
-        _ => None,
+        Some(ProviderKind::AzureOpenAI) => Some("AZURE_OPENAI_API_KEY"),
+        Some(ProviderKind::OpenAICompatible) => Some("OPENAI_API_KEY"),
+        Some(ProviderKind::Bedrock) => Some("AWS_BEARER_TOKEN_BEDROCK"),
+        None => None,
     }
 }
 
@@ -80,13 +831,82 @@ pub struct ConfigUpdate {
 /// Configuration structure
 #[derive(Deserialize, Serialize, Clone, Debug)]
 pub struct Config {
+    /// Provider used for generation; Google when unset
+    #[serde(default)]
+    pub provider: Option<String>,
+    /// Provider raced against the active one; the first valid response is used
+    #[serde(default)]
+    pub race: Option<String>,
     /// Provider-specific configurations
     pub providers: HashMap<String, ProviderConfig>,
+    /// Layout version of the settings, see [`crate::config_migration`]; 0 when unset
+    #[serde(default)]
+    pub config_version: u32,
     /// Instructions for commit messages
     #[serde(default)]
     pub instructions: String,
     #[serde(skip)]
     pub temp_instructions: Option<String>,
+    /// Gitmoji mode for commit titles: insert when true, strip when false
+    #[serde(default)]
+    pub gitmoji: Option<bool>,
+    /// Whether commit outcomes are counted locally for `gitai usage`; off unless set
+    #[serde(default)]
+    pub usage_stats: Option<bool>,
+    /// Column the TUI editor hard-wraps message bodies at; 0 turns wrapping off
+    #[serde(default)]
+    pub wrap_column: Option<usize>,
+    /// Language generated text is written in, as a code such as `ja` or a name
+    #[serde(default)]
+    pub language: Option<String>,
+    /// Whether the TUI flags common misspellings in messages; on unless set to false
+    #[serde(default)]
+    pub spell_check: Option<bool>,
+    /// User-defined TUI themes: theme name to color slot overrides
+    #[serde(default)]
+    pub themes: HashMap<String, HashMap<String, String>>,
+    /// Prompt template files by prompt name (`commit-user`, `pr-system`, ...)
+    #[serde(default)]
+    pub templates: HashMap<String, PathBuf>,
+    /// Whether generated, vendored and whitespace-only changes are filtered from prompts
+    #[serde(default)]
+    pub noise_filter: Option<bool>,
+    /// Pathspecs treated as noise in addition to the built-in ones
+    #[serde(default)]
+    pub noise_patterns: Vec<String>,
+    /// Whether notebook summaries show the text outputs of changed cells
+    #[serde(default)]
+    pub notebook_outputs: Option<bool>,
+    /// Whether oversized diffs are summarized before the prompt; on unless set to false
+    #[serde(default)]
+    pub summarize_diffs: Option<bool>,
+    /// Commit conventions from the repository's `.gitai`
+    #[serde(skip)]
+    pub conventions: Option<ProjectConventions>,
+    /// Error excerpts of a failed CI run given with `--ci-log`, for this run only
+    #[serde(skip)]
+    pub ci_failure: Option<String>,
+    /// Files given with `--context-file`, for this run only
+    #[serde(skip)]
+    pub context_files: Vec<ContextFile>,
+    /// Trailers added to the commits gitai creates
+    #[serde(skip)]
+    pub trailers: TrailerSettings,
+    /// Built-in steps run on every generated message
+    #[serde(skip)]
+    pub post_process: PostProcessSettings,
+    /// External commands run at hook points, from git config only
+    #[serde(skip)]
+    pub plugins: PluginSettings,
+    /// How many commits of history the context holds
+    #[serde(default)]
+    pub context: ContextDepth,
+    /// Context lines and word-diff mode of the diffs in prompts
+    #[serde(default)]
+    pub diff: DiffSettings,
+    /// Bullet and character limits of generated commit messages
+    #[serde(default)]
+    pub limits: MessageLimits,
     /// Flag indicating if this config is local
     #[serde(skip)]
     pub is_local: bool,
@@ -106,17 +926,38 @@ pub struct ProviderConfig {
 }
 
 impl Config {
-    /// Load the configuration with layered priority: env > local git > global git
+    /// Load the configuration with layered priority: profile > env > `--config` file > local git > global git
     ///
     /// # Errors
     ///
-    /// Returns an error if the git configuration cannot be accessed.
+    /// Returns an error if the git configuration cannot be accessed, the
+    /// `--config` file does not exist or the profile has no settings.
     pub fn load() -> Result<Self> {
+        Self::load_from(Path::new("."))
+    }
+
+    /// Load the configuration of the repository containing `dir`, as [`Self::load`] does
+    /// for the current directory
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the git configuration cannot be accessed, the
+    /// `--config` file does not exist or the profile has no settings.
+    pub fn load_from(dir: &Path) -> Result<Self> {
         // Open git configs
-        let global_config = GitConfig::open_default().ok();
-        let local_config = git2::Repository::discover(".")
-            .ok()
-            .and_then(|repo| repo.config().ok());
+        let repo = git2::Repository::discover(dir).ok();
+        let (local_config, global_config) = selected_configs(repo.as_ref());
+        check_selection(local_config.as_ref(), global_config.as_ref())?;
+        let conventions = repo
+            .as_ref()
+            .and_then(git2::Repository::workdir)
+            .and_then(|root| match ProjectConventions::load(root) {
+                Ok(conventions) => conventions,
+                Err(e) => {
+                    debug!("Ignoring unreadable project conventions: {e}");
+                    None
+                }
+            });
 
         let instructions = get_layered_value(
             "gitai.instructions",
@@ -126,49 +967,89 @@ impl Config {
         )
         .unwrap_or_default();
 
-        let mut providers = HashMap::new();
-        for provider in get_available_provider_names() {
-            let api_key = get_layered_value(
-                &format!("gitai.{provider}-apikey"),
-                get_api_key_env_var(&provider),
-                local_config.as_ref(),
-                global_config.as_ref(),
-            )
-            .unwrap_or_default();
+        let provider = get_layered_value(
+            "gitai.provider",
+            Some("GITAI_PROVIDER"),
+            local_config.as_ref(),
+            global_config.as_ref(),
+        );
+        let race = get_layered_value(
+            "gitai.race",
+            Some("GITAI_RACE"),
+            local_config.as_ref(),
+            global_config.as_ref(),
+        );
 
-            let default_model = get_default_model_for_provider(&provider).to_string();
-            let model = get_layered_value(
-                &format!("gitai.{provider}-model"),
-                None, // no env for model yet
-                local_config.as_ref(),
-                global_config.as_ref(),
-            )
-            .unwrap_or(default_model);
-
-            let mut additional_params = HashMap::new();
-            // Load from global first, then local to allow local to override
-            if let Some(ref config) = global_config {
-                load_additional_params(config, &provider, &mut additional_params);
-            }
-            if let Some(ref config) = local_config {
-                load_additional_params(config, &provider, &mut additional_params);
-            }
+        let gitmoji = get_layered_value(
+            "gitai.gitmoji",
+            Some("GITAI_GITMOJI"),
+            local_config.as_ref(),
+            global_config.as_ref(),
+        )
+        .and_then(|value| parse_bool(&value));
+        let usage_stats = get_layered_value(
+            "gitai.usage-stats",
+            Some("GITAI_USAGE_STATS"),
+            local_config.as_ref(),
+            global_config.as_ref(),
+        )
+        .and_then(|value| parse_bool(&value));
+        let language = get_layered_value(
+            "gitai.language",
+            Some("GITAI_LANGUAGE"),
+            local_config.as_ref(),
+            global_config.as_ref(),
+        )
+        .filter(|language| !language.trim().is_empty());
+        let (wrap_column, spell_check) =
+            load_editor_settings(local_config.as_ref(), global_config.as_ref());
 
-            providers.insert(
-                #[allow(clippy::implicit_clone)]
-                provider.to_owned(),
-                ProviderConfig {
-                    api_key,
-                    model_name: model,
-                    additional_params,
-                },
-            );
+        // Global first, then local, so repository themes override per slot
+        let mut themes = HashMap::new();
+        for config in [global_config.as_ref(), local_config.as_ref()]
+            .into_iter()
+            .flatten()
+        {
+            load_themes(config, &mut themes);
         }
 
+        let (noise_filter, noise_patterns) =
+            load_noise_settings(local_config.as_ref(), global_config.as_ref());
+
+        let workdir = repo.as_ref().and_then(git2::Repository::workdir);
+        let templates = load_templates(local_config.as_ref(), global_config.as_ref(), workdir);
+
+        let providers = load_providers(local_config.as_ref(), global_config.as_ref());
+
+        let config_version = load_config_version(local_config.as_ref(), global_config.as_ref());
+
         let config = Self {
+            provider,
+            race,
             providers,
+            config_version,
             instructions,
             temp_instructions: None,
+            gitmoji,
+            usage_stats,
+            language,
+            wrap_column,
+            spell_check,
+            themes,
+            templates,
+            noise_filter,
+            noise_patterns,
+            notebook_outputs: load_notebook_outputs(local_config.as_ref(), global_config.as_ref()),
+            summarize_diffs: load_summarize_diffs(local_config.as_ref(), global_config.as_ref()),
+            conventions,
+            ci_failure: None,
+            context_files: Vec::new(),
+            trailers: load_trailer_settings(local_config.as_ref(), global_config.as_ref()),
+            post_process: load_post_process_settings(local_config.as_ref(), global_config.as_ref()),
+            plugins: load_plugin_settings(local_config.as_ref(), global_config.as_ref()),
+            context: load_context_depth(local_config.as_ref(), global_config.as_ref()),
+            diff: load_diff_settings(local_config.as_ref(), global_config.as_ref()),
+            limits: load_message_limits(local_config.as_ref(), global_config.as_ref()),
             is_local: false,
         };
 
@@ -198,11 +1079,32 @@ impl Config {
                 .extend(proj_provider_config.additional_params);
         }
 
+        if project_config.provider.is_some() {
+            self.provider = project_config.provider;
+        }
+        if project_config.race.is_some() {
+            self.race = project_config.race;
+        }
         // Always override instructions field if set in project config
         self.instructions.clone_from(&project_config.instructions);
+        if project_config.gitmoji.is_some() {
+            self.gitmoji = project_config.gitmoji;
+        }
+        if project_config.noise_filter.is_some() {
+            self.noise_filter = project_config.noise_filter;
+        }
+        self.noise_patterns.extend(project_config.noise_patterns);
+        if project_config.notebook_outputs.is_some() {
+            self.notebook_outputs = project_config.notebook_outputs;
+        }
+        if project_config.summarize_diffs.is_some() {
+            self.summarize_diffs = project_config.summarize_diffs;
+        }
     }
 
-    /// Save the configuration to git config
+    /// Save the configuration to the `--config` file, or else the global git config
+    ///
+    /// With `--profile`, the settings go into that profile's section.
     ///
     /// # Errors
     ///
@@ -213,17 +1115,50 @@ impl Config {
             return Ok(());
         }
 
-        let mut config = GitConfig::open_default()?;
-        self.save_to_config(&mut config, "gitai")?;
-        debug!("Configuration saved to global git config: {self:?}");
+        let section = settings_section(active_profile().as_deref());
+        if let Some(path) = selected_file() {
+            self.save_to_config(&mut GitConfig::open(&path)?, &section)?;
+            debug!("Configuration saved to {section} in {}", path.display());
+        } else {
+            self.save_to_config(&mut GitConfig::open_default()?, &section)?;
+            debug!("Configuration saved to {section} in global git config: {self:?}");
+        }
         Ok(())
     }
 
     /// Save the configuration to a git config
     fn save_to_config(&self, config: &mut GitConfig, prefix: &str) -> Result<()> {
-        // Set default provider
+        if let Some(provider) = &self.provider {
+            config.set_str(&format!("{prefix}.provider"), provider)?;
+        }
+        if let Some(race) = &self.race {
+            config.set_str(&format!("{prefix}.race"), race)?;
+        }
+        // Written in the current layout, so nothing is left to migrate
+        config.set_str(
+            &format!("{prefix}.config-version"),
+            &config_migration::CURRENT_VERSION.to_string(),
+        )?;
         // Set instructions
         config.set_str(&format!("{prefix}.instructions"), &self.instructions)?;
+        if let Some(gitmoji) = self.gitmoji {
+            config.set_bool(&format!("{prefix}.gitmoji"), gitmoji)?;
+        }
+        if let Some(usage_stats) = self.usage_stats {
+            config.set_bool(&format!("{prefix}.usage-stats"), usage_stats)?;
+        }
+        if let Some(language) = &self.language {
+            config.set_str(&format!("{prefix}.language"), language)?;
+        }
+        if let Some(spell_check) = self.spell_check {
+            config.set_bool(&format!("{prefix}.spell-check"), spell_check)?;
+        }
+        if let Some(wrap_column) = self.wrap_column {
+            config.set_i64(
+                &format!("{prefix}.wrap-column"),
+                i64::try_from(wrap_column).unwrap_or(i64::MAX),
+            )?;
+        }
 
         for (provider, provider_config) in &self.providers {
             // Set model
@@ -240,6 +1175,22 @@ impl Config {
         Ok(())
     }
 
+    /// Store an API key outside the repository, readable only by the current user.
+    ///
+    /// The key goes into `<config dir>/gitai/credentials`, a git config file
+    /// the global git config includes, so it is loaded like any other key but
+    /// never lands in a shared or repository config file.
+    pub fn store_api_key(provider: &str, api_key: &str) -> Result<PathBuf> {
+        let path = dirs::config_dir()
+            .ok_or_else(|| anyhow!("No configuration directory on this platform"))?
+            .join("gitai")
+            .join("credentials");
+        write_credential(&path, provider, api_key)?;
+        include_config_file(&mut GitConfig::open_default()?, &path)?;
+        debug!("Stored the {provider} API key in {}", path.display());
+        Ok(path)
+    }
+
     /// Save the configuration as a project-specific configuration
     pub fn save_as_project_config(&self) -> Result<()> {
         let repo = git2::Repository::discover(".")?;
@@ -257,7 +1208,8 @@ impl Config {
 
         // Save to local git config
         let mut config = repo.config()?;
-        project_config.save_to_config(&mut config, "gitai")?;
+        project_config
+            .save_to_config(&mut config, &settings_section(active_profile().as_deref()))?;
         debug!("Project configuration saved to local git config: {project_config:?}");
         Ok(())
     }
@@ -266,11 +1218,27 @@ impl Config {
     pub fn check_environment(&self) -> Result<()> {
         // Check if we're in a git repository
         if !GitRepo::is_inside_work_tree()? {
-            return Err(anyhow!(
-                "Not in a Git repository. Please run this command from within a Git repository."
+            return Err(coded(
+                ErrorCode::NotARepository,
+                "Not in a Git repository. Please run this command from within a Git repository.",
             ));
         }
 
+        self.check_provider_settings()
+    }
+
+    /// Check the provider's endpoint and the HTTP settings, for work outside a repository
+    pub fn check_provider_settings(&self) -> Result<()> {
+        // Endpoint settings are checked before any request is made
+        let provider = self.active_provider();
+        if let Some(provider_config) = self.get_provider_config(provider.as_str()) {
+            validate_endpoint(provider, &provider_config.additional_params)
+                .map_err(|e| coded(ErrorCode::InvalidConfig, e))?;
+        }
+        http::settings()
+            .validate()
+            .map_err(|e| coded(ErrorCode::InvalidConfig, e))?;
+
         Ok(())
     }
 
@@ -288,9 +1256,36 @@ impl Config {
             .to_string()
     }
 
+    /// The provider commands generate with: `gitai.provider`, or Google when unset or unknown
+    #[must_use]
+    pub fn active_provider(&self) -> ProviderKind {
+        match self.provider.as_deref().map(ProviderKind::from_name) {
+            Some(Some(provider)) => provider,
+            Some(None) => {
+                debug!(
+                    "Unknown provider {:?} in gitai.provider, using Google",
+                    self.provider
+                );
+                ProviderKind::Google
+            }
+            None => ProviderKind::Google,
+        }
+    }
+
+    /// The provider raced against `primary` under `gitai.race`, unless unknown or the same
+    #[must_use]
+    pub fn race_provider(&self, primary: &str) -> Option<ProviderKind> {
+        let race = self.race.as_deref()?;
+        let Some(rival) = ProviderKind::from_name(race) else {
+            debug!("Unknown provider {race:?} in gitai.race, not racing");
+            return None;
+        };
+        (ProviderKind::from_name(primary) != Some(rival)).then_some(rival)
+    }
+
     /// Update the configuration with new values
     pub fn update(&mut self, update: ConfigUpdate) -> Result<()> {
-        let provider_name = ProviderKind::Google.as_str().to_string();
+        let provider_name = self.active_provider().as_str().to_string();
 
         if let Some(key) = update.api_key {
             let entry = self.providers.entry(provider_name.clone()).or_default();
@@ -332,6 +1327,22 @@ impl Config {
     }
 
     /// Check if this is a project config
+    /// Column the TUI editor wraps message bodies at
+    #[must_use]
+    pub fn wrap_column(&self) -> usize {
+        self.wrap_column.unwrap_or(DEFAULT_WRAP_COLUMN)
+    }
+
+    /// Whether the TUI flags misspellings, which it only knows in English
+    #[must_use]
+    pub fn spell_check(&self) -> bool {
+        self.spell_check != Some(false)
+            && self
+                .language
+                .as_deref()
+                .is_none_or(prompts::language::is_english)
+    }
+
     #[inline]
     #[must_use]
     pub const fn is_project_config(&self) -> bool {
@@ -347,9 +1358,32 @@ impl Default for Config {
             .collect();
 
         Self {
+            provider: None,
+            race: None,
             providers,
+            config_version: config_migration::CURRENT_VERSION,
             instructions: String::new(),
             temp_instructions: None,
+            gitmoji: None,
+            usage_stats: None,
+            language: None,
+            wrap_column: None,
+            spell_check: None,
+            themes: HashMap::new(),
+            templates: HashMap::new(),
+            noise_filter: None,
+            noise_patterns: Vec::new(),
+            notebook_outputs: None,
+            summarize_diffs: None,
+            conventions: None,
+            ci_failure: None,
+            context_files: Vec::new(),
+            trailers: TrailerSettings::default(),
+            post_process: PostProcessSettings::default(),
+            plugins: PluginSettings::default(),
+            context: ContextDepth::default(),
+            diff: DiffSettings::default(),
+            limits: MessageLimits::default(),
             is_local: false,
         }
     }
@@ -365,4 +1399,204 @@ impl ProviderConfig {
             additional_params: HashMap::new(),
         }
     }
+
+    /// Sampling temperature, from the `temperature` param
+    #[must_use]
+    pub fn temperature(&self) -> Option<f32> {
+        self.param(TEMPERATURE_PARAM)
+    }
+
+    /// Nucleus sampling cutoff, from the `top_p` param
+    #[must_use]
+    pub fn top_p(&self) -> Option<f32> {
+        self.param(TOP_P_PARAM)
+    }
+
+    /// Sampling seed, from the `seed` param
+    #[must_use]
+    pub fn seed(&self) -> Option<u64> {
+        self.param(SEED_PARAM)
+    }
+
+    fn param<T: std::str::FromStr>(&self, key: &str) -> Option<T> {
+        self.additional_params
+            .get(key)
+            .and_then(|value| value.trim().parse().ok())
+    }
+
+    /// Pins the temperature to 0 and the seed, keeping a configured seed, so
+    /// the same prompt gets the same answer as far as the provider allows
+    pub fn make_deterministic(&mut self) {
+        self.additional_params
+            .insert(TEMPERATURE_PARAM.to_string(), "0".to_string());
+        if self.seed().is_none() {
+            self.additional_params
+                .insert(SEED_PARAM.to_string(), DETERMINISTIC_SEED.to_string());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile_values_stand_in_for_plain_keys() {
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let mut config =
+            GitConfig::open(&dir.path().join("config")).expect("Failed to open config");
+        config
+            .set_str("gitai.provider", "google")
+            .expect("Failed to set provider");
+        config
+            .set_str("gitai-profile.work.provider", "anthropic")
+            .expect("Failed to set provider");
+
+        assert_eq!(
+            profile_value("work", "gitai.provider", None, Some(&config)).as_deref(),
+            Some("anthropic")
+        );
+
+        assert_eq!(
+            profile_value("work", "gitai.language", Some(&config), None),
+            None
+        );
+        assert!(profile_defined("work", None, Some(&config)));
+        assert!(!profile_defined("home", Some(&config), None));
+
+        let saved = Config {
+            language: Some("ja".to_string()),
+            ..Config::default()
+        };
+        saved
+            .save_to_config(&mut config, &settings_section(Some("work")))
+            .expect("Failed to save profile");
+        assert_eq!(
+            profile_value("work", "gitai.language", None, Some(&config)).as_deref(),
+            Some("ja")
+        );
+        assert!(config.get_string("gitai.language").is_err());
+    }
+
+    #[test]
+    fn test_context_depth() {
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let mut global =
+            GitConfig::open(&dir.path().join("global")).expect("Failed to open config");
+        global
+            .set_str("gitai.context-author-history", "50")
+            .expect("Failed to set depth");
+        global
+            .set_str("gitai.context-file-commits", "lots")
+            .expect("Failed to set depth");
+        let mut local = GitConfig::open(&dir.path().join("local")).expect("Failed to open config");
+        local
+            .set_str("gitai.context-recent-commits", "0")
+            .expect("Failed to set depth");
+
+        assert_eq!(
+            load_context_depth(Some(&local), Some(&global)),
+            ContextDepth {
+                recent_commits: 0,
+                file_commits: 10,
+                author_history: 50,
+                history_walk: 5_000,
+            }
+        );
+        assert_eq!(load_context_depth(None, None), ContextDepth::default());
+    }
+
+    #[test]
+    fn test_race_provider() {
+        let mut config = Config {
+            race: Some("OpenRouter".to_string()),
+            ..Config::default()
+        };
+        assert_eq!(
+            config.race_provider("google"),
+            Some(ProviderKind::OpenRouter)
+        );
+        assert_eq!(config.race_provider("openrouter"), None);
+
+        config.race = Some("nonexistent".to_string());
+        assert_eq!(config.race_provider("google"), None);
+    }
+
+    #[test]
+    fn test_spell_check_follows_the_language() {
+        let mut config = Config::default();
+        assert!(config.spell_check());
+        config.language = Some("en-US".to_string());
+        assert!(config.spell_check());
+        config.language = Some("ja".to_string());
+        assert!(!config.spell_check());
+        config.language = None;
+        config.spell_check = Some(false);
+        assert!(!config.spell_check());
+    }
+
+    #[test]
+    fn test_stored_api_key_is_included() {
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let credentials = dir.path().join("gitai").join("credentials");
+        let global = dir.path().join("gitconfig");
+        std::fs::write(&global, "[include]\n\tpath = /elsewhere\n").expect("Failed to write");
+
+        write_credential(&credentials, "openrouter", "other").expect("Failed to store key");
+        write_credential(&credentials, "google", "sec\"ret").expect("Failed to store key");
+        write_credential(&credentials, "google", "secret").expect("Failed to store key");
+        let mut config = GitConfig::open(&global).expect("Failed to open config");
+        include_config_file(&mut config, &credentials).expect("Failed to include");
+        include_config_file(&mut config, &credentials).expect("Failed to include twice");
+
+        let config = GitConfig::open(&global).expect("Failed to reopen config");
+        assert_eq!(
+            config
+                .get_string("gitai.google-apikey")
+                .expect("Key not visible through the include"),
+            "secret"
+        );
+        assert_eq!(
+            config
+                .get_string("gitai.openrouter-apikey")
+                .expect("Other key lost"),
+            "other"
+        );
+        let mut paths = Vec::new();
+        let mut entries = config
+            .multivar("include.path", None)
+            .expect("Failed to read includes");
+        while let Some(Ok(entry)) = entries.next() {
+            paths.push(entry.value().unwrap_or_default().to_string());
+        }
+        assert_eq!(paths.len(), 2, "{paths:?}");
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&credentials)
+                .expect("Missing credentials")
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+    }
+
+    #[test]
+    fn test_make_deterministic_keeps_a_configured_seed() {
+        let mut provider = ProviderConfig::default_for("google");
+        provider
+            .additional_params
+            .insert(TEMPERATURE_PARAM.to_string(), "0.9".to_string());
+        provider.make_deterministic();
+        assert_eq!(provider.temperature(), Some(0.0));
+        assert_eq!(provider.seed(), Some(DETERMINISTIC_SEED));
+
+        provider
+            .additional_params
+            .insert(SEED_PARAM.to_string(), " 7 ".to_string());
+        provider.make_deterministic();
+        assert_eq!(provider.seed(), Some(7));
+        assert_eq!(provider.top_p(), None);
+    }
 }
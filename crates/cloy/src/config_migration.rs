@@ -0,0 +1,300 @@
+//! Upgrades of gitai settings written in an older layout.
+//!
+//! Each git config file holding gitai settings can carry
+//! `gitai.config-version`; a file without it is at version 0. When a key is
+//! renamed or its layout changes, a migration is added to [`MIGRATIONS`]. It
+//! reads the file's entries and lists the changes that bring them to its
+//! version. `gitai config migrate` applies the pending migrations of a file in
+//! order and records the new version; `--dry-run` only lists the changes.
+
+use crate::llm::provider::ProviderKind;
+use anyhow::Result;
+use git2::{Config as GitConfig, ConfigLevel, Repository};
+use std::fmt;
+
+/// Key holding the layout version of a config file
+pub const VERSION_KEY: &str = "gitai.config-version";
+
+/// Version of the layout this build reads
+pub const CURRENT_VERSION: u32 = 1;
+
+/// Sections whose keys are gitai settings
+const SECTIONS: &[&str] = &["gitai.", "gitai-profile."];
+
+/// Suffixes of the per-provider keys, after the provider's name
+const PROVIDER_SUFFIXES: &[&str] = &["-apikey", "-model", "-additional"];
+
+/// A change to one key of a config file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    /// Moves a value to a new key
+    Rename { from: String, to: String },
+    /// Removes a key superseded by one already set
+    Remove { key: String, superseded_by: String },
+    /// Sets a key to a value
+    Set { key: String, value: String },
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Rename { from, to } => write!(f, "rename {from} to {to}"),
+            Self::Remove { key, superseded_by } => {
+                write!(f, "remove {key}, as {superseded_by} is set")
+            }
+            Self::Set { key, value } => write!(f, "set {key} = {value}"),
+        }
+    }
+}
+
+/// An upgrade of the layout to one version
+struct Migration {
+    /// Version a file is at once this migration has run
+    version: u32,
+    /// What the migration changes, for the listing
+    description: &'static str,
+    /// Changes that bring the entries of a file to `version`
+    plan: fn(&[(String, String)]) -> Vec<Change>,
+}
+
+/// Every migration, oldest first
+const MIGRATIONS: &[Migration] = &[Migration {
+    version: 1,
+    description: "Provider keys written under an alias, such as openai- or azure-, use the provider's name",
+    plan: canonical_provider_keys,
+}];
+
+/// The migrations pending for one config file and what each would change
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Plan {
+    /// Version the file is at
+    pub from: u32,
+    /// Description and changes of each pending migration, in order
+    pub steps: Vec<(&'static str, Vec<Change>)>,
+}
+
+impl Plan {
+    /// Whether the file is already at the current version
+    pub fn is_current(&self) -> bool {
+        self.from >= CURRENT_VERSION
+    }
+
+    /// Whether any migration changes a setting, beyond recording the version
+    pub fn changes_settings(&self) -> bool {
+        self.steps.iter().any(|(_, changes)| !changes.is_empty())
+    }
+}
+
+/// The global config file and the repository's, where they exist
+pub fn config_files(repo: Option<&Repository>) -> Vec<(&'static str, GitConfig)> {
+    let global =
+        GitConfig::open_default().and_then(|config| config.open_level(ConfigLevel::Global));
+    let local = repo.map(|repo| {
+        repo.config()
+            .and_then(|config| config.open_level(ConfigLevel::Local))
+    });
+    [
+        ("global config", Some(global)),
+        ("repository config", local),
+    ]
+    .into_iter()
+    .filter_map(|(name, config)| Some((name, config?.ok()?)))
+    .collect()
+}
+
+/// The gitai entries of a single config file, as lowercase names and values
+///
+/// Entries of included files are left out, as they are changed in their own file.
+pub fn entries(config: &GitConfig) -> Result<Vec<(String, String)>> {
+    let mut entries = Vec::new();
+    let mut iter = config.entries(None)?;
+    while let Some(entry) = iter.next() {
+        let entry = entry?;
+        if let (Some(name), Some(value)) = (entry.name(), entry.value())
+            && entry.include_depth() == 0
+            && SECTIONS.iter().any(|section| name.starts_with(section))
+        {
+            entries.push((name.to_string(), value.to_string()));
+        }
+    }
+    Ok(entries)
+}
+
+/// Plans the pending migrations for a file with `entries`
+pub fn plan(entries: &[(String, String)]) -> Plan {
+    let from = entries
+        .iter()
+        .find(|(name, _)| name == VERSION_KEY)
+        .and_then(|(_, value)| value.trim().parse().ok())
+        .unwrap_or(0);
+    // Each migration sees the entries as the ones before it left them
+    let mut entries = entries.to_vec();
+    let mut steps = Vec::new();
+    for migration in MIGRATIONS.iter().filter(|m| m.version > from) {
+        let changes = (migration.plan)(&entries);
+        for change in &changes {
+            apply_to_entries(&mut entries, change);
+        }
+        steps.push((migration.description, changes));
+    }
+    Plan { from, steps }
+}
+
+/// Applies `plan` to a config file and records the current version
+pub fn apply(config: &mut GitConfig, plan: &Plan) -> Result<()> {
+    for change in plan.steps.iter().flat_map(|(_, changes)| changes) {
+        match change {
+            Change::Rename { from, to } => {
+                let value = config.get_string(from)?;
+                config.set_str(to, &value)?;
+                config.remove(from)?;
+            }
+            Change::Remove { key, .. } => config.remove(key)?,
+            Change::Set { key, value } => config.set_str(key, value)?,
+        }
+    }
+    config.set_str(VERSION_KEY, &CURRENT_VERSION.to_string())?;
+    Ok(())
+}
+
+fn apply_to_entries(entries: &mut Vec<(String, String)>, change: &Change) {
+    match change {
+        Change::Rename { from, to } => {
+            for (name, _) in entries.iter_mut().filter(|(name, _)| name == from) {
+                name.clone_from(to);
+            }
+        }
+        Change::Remove { key, .. } => entries.retain(|(name, _)| name != key),
+        Change::Set { key, value } => {
+            entries.retain(|(name, _)| name != key);
+            entries.push((key.clone(), value.clone()));
+        }
+    }
+}
+
+/// The canonical name of a per-provider key written under an alias
+///
+/// `gitai.openai-model` becomes `gitai.openai-compatible-model`, and
+/// `gitai-profile.work.azure-apikey` becomes
+/// `gitai-profile.work.azure-openai-apikey`.
+fn canonical_provider_key(name: &str) -> Option<String> {
+    let (section, key) = name.rsplit_once('.')?;
+    PROVIDER_SUFFIXES.iter().find_map(|suffix| {
+        let (provider, rest) = key.split_at(key.find(suffix)?);
+        let canonical = ProviderKind::from_name(provider)?.as_str();
+        (canonical != provider).then(|| format!("{section}.{canonical}{rest}"))
+    })
+}
+
+/// Version 1: the per-provider keys are read under the provider's name only
+fn canonical_provider_keys(entries: &[(String, String)]) -> Vec<Change> {
+    entries
+        .iter()
+        .filter_map(|(name, _)| {
+            let to = canonical_provider_key(name)?;
+            Some(if entries.iter().any(|(other, _)| *other == to) {
+                Change::Remove {
+                    key: name.clone(),
+                    superseded_by: to,
+                }
+            } else {
+                Change::Rename {
+                    from: name.clone(),
+                    to,
+                }
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(name: &str, value: &str) -> (String, String) {
+        (name.to_string(), value.to_string())
+    }
+
+    #[test]
+    fn test_versions_match_the_migrations() {
+        let versions: Vec<u32> = MIGRATIONS.iter().map(|m| m.version).collect();
+        assert_eq!(versions, (1..=CURRENT_VERSION).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_canonical_provider_key() {
+        assert_eq!(
+            canonical_provider_key("gitai.openai-model").as_deref(),
+            Some("gitai.openai-compatible-model")
+        );
+        assert_eq!(
+            canonical_provider_key("gitai-profile.work.azure-additionalbase-url").as_deref(),
+            Some("gitai-profile.work.azure-openai-additionalbase-url")
+        );
+        assert_eq!(
+            canonical_provider_key("gitai.openai-compatible-model"),
+            None
+        );
+        assert_eq!(canonical_provider_key("gitai.google-apikey"), None);
+        assert_eq!(canonical_provider_key("gitai.max-file-size"), None);
+    }
+
+    #[test]
+    fn test_plan_renames_alias_keys() {
+        let entries = [
+            entry("gitai.openai-model", "gpt-4o"),
+            entry("gitai.aws-bedrock-apikey", "secret"),
+            entry("gitai.bedrock-apikey", "kept"),
+            entry("gitai.language", "de"),
+        ];
+        let plan = plan(&entries);
+        assert_eq!(plan.from, 0);
+        assert!(plan.changes_settings());
+        assert_eq!(
+            plan.steps[0].1,
+            [
+                Change::Rename {
+                    from: "gitai.openai-model".to_string(),
+                    to: "gitai.openai-compatible-model".to_string(),
+                },
+                Change::Remove {
+                    key: "gitai.aws-bedrock-apikey".to_string(),
+                    superseded_by: "gitai.bedrock-apikey".to_string(),
+                },
+            ]
+        );
+
+        let current = super::plan(&[entry(VERSION_KEY, "1"), entry("gitai.openai-model", "x")]);
+        assert!(current.is_current());
+        assert!(current.steps.is_empty());
+    }
+
+    #[test]
+    fn test_apply_migrates_a_file() {
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let path = dir.path().join("config");
+        let mut config = GitConfig::open(&path).expect("Failed to open config");
+        config
+            .set_str("gitai.openai-model", "gpt-4o")
+            .expect("Failed to set key");
+        config
+            .set_str("gitai-profile.work.azure-apikey", "secret")
+            .expect("Failed to set key");
+
+        let plan = plan(&entries(&config).expect("Failed to read entries"));
+        apply(&mut config, &plan).expect("Failed to migrate");
+
+        let config = GitConfig::open(&path).expect("Failed to open config");
+        let mut migrated = entries(&config).expect("Failed to read entries");
+        migrated.sort();
+        assert_eq!(
+            migrated,
+            [
+                entry("gitai-profile.work.azure-openai-apikey", "secret"),
+                entry(VERSION_KEY, "1"),
+                entry("gitai.openai-compatible-model", "gpt-4o"),
+            ]
+        );
+        assert!(super::plan(&migrated).is_current());
+    }
+}
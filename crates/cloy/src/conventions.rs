@@ -0,0 +1,437 @@
+//! Repository-wide commit conventions stored in `.gitai`.
+//!
+//! Conventions are inferred from the history of all authors and committed with
+//! the repository, so every contributor's generator follows the same rules.
+//! The file uses git-config syntax and is read back with libgit2's config
+//! parser, the same way `.gitwire` is handled.
+
+use crate::llm::style::{first_description_word, has_emoji, parse_conventional_prefix};
+use anyhow::{Context, Result};
+use git2::Config as GitConfig;
+use log::debug;
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// File name of the project conventions file at the repository root
+pub const CONVENTIONS_FILENAME: &str = ".gitai";
+
+/// Share of subjects a pattern must reach to be treated as a convention
+const CONVENTION_THRESHOLD: f32 = 0.5;
+/// Number of conventional types recorded
+const MAX_TYPES: usize = 8;
+/// Longest subject `lint` accepts
+const MAX_SUBJECT_LENGTH: usize = 72;
+
+/// How the first word of a subject description is cased
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubjectCase {
+    Lower,
+    Upper,
+}
+
+impl SubjectCase {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Lower => "lower",
+            Self::Upper => "upper",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "lower" => Some(Self::Lower),
+            "upper" => Some(Self::Upper),
+            _ => None,
+        }
+    }
+}
+
+/// Commit conventions shared by a repository
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProjectConventions {
+    /// Subjects use `type(scope): description`
+    pub conventional: bool,
+    /// Conventional types in use, most frequent first
+    pub types: Vec<String>,
+    /// Conventional subjects usually carry a scope
+    pub scopes: bool,
+    /// Subjects start with a gitmoji
+    pub gitmoji: bool,
+    /// Issue key prefix such as `ABC` for `ABC-123`
+    pub ticket_prefix: Option<String>,
+    /// Casing of the description's first word
+    pub subject_case: Option<SubjectCase>,
+    /// Number of commits the conventions were inferred from
+    pub sample_size: usize,
+}
+
+impl ProjectConventions {
+    /// Infers conventions from commit messages of all authors.
+    #[allow(clippy::cast_precision_loss, clippy::as_conversions)]
+    pub fn detect(messages: &[String]) -> Self {
+        let subjects: Vec<&str> = messages
+            .iter()
+            .filter_map(|m| m.lines().next())
+            .map(str::trim)
+            .filter(|s| !s.is_empty() && !s.starts_with("Merge "))
+            .collect();
+        if subjects.is_empty() {
+            return Self::default();
+        }
+
+        let mut types: HashMap<String, usize> = HashMap::new();
+        let mut scoped = 0;
+        let mut emoji = 0;
+        let mut lower = 0;
+        let mut upper = 0;
+        let mut tickets: HashMap<String, usize> = HashMap::new();
+
+        for subject in &subjects {
+            if let Some((kind, has_scope)) = parse_conventional_prefix(subject) {
+                *types.entry(kind).or_insert(0) += 1;
+                if has_scope {
+                    scoped += 1;
+                }
+            }
+            if has_emoji(subject) {
+                emoji += 1;
+            }
+            if let Some(first) = first_description_word(subject).and_then(|w| w.chars().next()) {
+                if first.is_lowercase() {
+                    lower += 1;
+                } else if first.is_uppercase() {
+                    upper += 1;
+                }
+            }
+            if let Some(prefix) = ticket_prefix(subject) {
+                *tickets.entry(prefix).or_insert(0) += 1;
+            }
+        }
+
+        let total = subjects.len() as f32;
+        let share = |count: usize| count as f32 / total;
+        let conventional_count: usize = types.values().sum();
+
+        let mut sorted_types: Vec<(String, usize)> = types.into_iter().collect();
+        sorted_types.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+        let ticket_prefix = tickets
+            .into_iter()
+            .max_by(|a, b| a.1.cmp(&b.1).then_with(|| b.0.cmp(&a.0)))
+            .filter(|(_, count)| share(*count) >= CONVENTION_THRESHOLD)
+            .map(|(prefix, _)| prefix);
+
+        let subject_case = if share(lower) >= CONVENTION_THRESHOLD {
+            Some(SubjectCase::Lower)
+        } else if share(upper) >= CONVENTION_THRESHOLD {
+            Some(SubjectCase::Upper)
+        } else {
+            None
+        };
+
+        let conventional = share(conventional_count) >= CONVENTION_THRESHOLD;
+        Self {
+            conventional,
+            types: if conventional {
+                sorted_types
+                    .into_iter()
+                    .take(MAX_TYPES)
+                    .map(|(t, _)| t)
+                    .collect()
+            } else {
+                Vec::new()
+            },
+            scopes: conventional
+                && (scoped as f32 / conventional_count.max(1) as f32) >= CONVENTION_THRESHOLD,
+            gitmoji: share(emoji) >= CONVENTION_THRESHOLD,
+            ticket_prefix,
+            subject_case,
+            sample_size: subjects.len(),
+        }
+    }
+
+    /// Path of the conventions file for a repository root
+    pub fn path(repo_root: &Path) -> PathBuf {
+        repo_root.join(CONVENTIONS_FILENAME)
+    }
+
+    /// Loads conventions from the repository root, if the file exists.
+    pub fn load(repo_root: &Path) -> Result<Option<Self>> {
+        let path = Self::path(repo_root);
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let config =
+            GitConfig::open(&path).with_context(|| format!("Failed to open {}", path.display()))?;
+        let get_str = |key: &str| {
+            config
+                .get_string(&format!("conventions.{key}"))
+                .ok()
+                .filter(|v| !v.is_empty())
+        };
+        let get_bool = |key: &str| {
+            config
+                .get_bool(&format!("conventions.{key}"))
+                .unwrap_or(false)
+        };
+
+        let conventions = Self {
+            conventional: get_bool("conventional"),
+            types: get_str("types")
+                .map(|t| t.split(',').map(|s| s.trim().to_string()).collect())
+                .unwrap_or_default(),
+            scopes: get_bool("scopes"),
+            gitmoji: get_bool("gitmoji"),
+            ticket_prefix: get_str("ticket-prefix"),
+            subject_case: get_str("subject-case").and_then(|c| SubjectCase::from_name(&c)),
+            sample_size: config
+                .get_i64("conventions.sample-size")
+                .ok()
+                .and_then(|n| usize::try_from(n).ok())
+                .unwrap_or(0),
+        };
+        debug!("Loaded project conventions: {conventions:?}");
+        Ok(Some(conventions))
+    }
+
+    /// Writes the conventions file to the repository root.
+    pub fn save(&self, repo_root: &Path) -> Result<PathBuf> {
+        let path = Self::path(repo_root);
+        fs::write(&path, self.to_file_content())
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        Ok(path)
+    }
+
+    /// Renders the file content
+    pub fn to_file_content(&self) -> String {
+        let mut content = String::new();
+        let _ = writeln!(
+            content,
+            "# Commit conventions inferred by `git conventions analyze`.\n\
+             # Edit freely; gitai follows these rules when generating messages.\n"
+        );
+        let _ = writeln!(content, "[conventions]");
+        let _ = writeln!(content, "conventional = {}", self.conventional);
+        if !self.types.is_empty() {
+            let _ = writeln!(content, "types = \"{}\"", self.types.join(","));
+        }
+        let _ = writeln!(content, "scopes = {}", self.scopes);
+        let _ = writeln!(content, "gitmoji = {}", self.gitmoji);
+        if let Some(prefix) = &self.ticket_prefix {
+            let _ = writeln!(content, "ticket-prefix = \"{prefix}\"");
+        }
+        if let Some(case) = self.subject_case {
+            let _ = writeln!(content, "subject-case = \"{}\"", case.as_str());
+        }
+        let _ = writeln!(content, "sample-size = {}", self.sample_size);
+        content
+    }
+
+    /// Renders the conventions as generator instructions.
+    pub fn as_instructions(&self) -> String {
+        let mut rules = Vec::new();
+        if self.conventional {
+            let scope = if self.scopes {
+                "type(scope): description"
+            } else {
+                "type: description"
+            };
+            rules.push(format!(
+                "Format the subject as `{scope}` (Conventional Commits)."
+            ));
+            if !self.types.is_empty() {
+                rules.push(format!(
+                    "Use one of these types: {}.",
+                    self.types.join(", ")
+                ));
+            }
+        }
+        if self.gitmoji {
+            rules.push("Start the subject with the matching gitmoji.".to_string());
+        }
+        if let Some(prefix) = &self.ticket_prefix {
+            rules.push(format!(
+                "Reference the ticket as `{prefix}-<number>` at the start of the subject when the branch or changes name one."
+            ));
+        }
+        match self.subject_case {
+            Some(SubjectCase::Lower) => {
+                rules.push("Start the subject description with a lowercase letter.".to_string());
+            }
+            Some(SubjectCase::Upper) => {
+                rules.push("Start the subject description with an uppercase letter.".to_string());
+            }
+            None => {}
+        }
+
+        if rules.is_empty() {
+            return String::new();
+        }
+        format!(
+            "Project commit conventions (these take precedence over the default subject format):\n{}",
+            rules
+                .iter()
+                .map(|r| format!("- {r}"))
+                .collect::<Vec<_>>()
+                .join("\n")
+        )
+    }
+
+    /// Checks a message against these conventions and basic subject hygiene.
+    ///
+    /// Returns one description per violated rule; an empty list means the
+    /// message passes.
+    pub fn lint(&self, message: &str) -> Vec<String> {
+        let mut lines = message.lines();
+        let subject = lines.next().unwrap_or("").trim();
+        if subject.is_empty() {
+            return vec!["subject is empty".to_string()];
+        }
+
+        let mut problems = Vec::new();
+        let length = subject.chars().count();
+        if length > MAX_SUBJECT_LENGTH {
+            problems.push(format!(
+                "subject is {length} characters (max {MAX_SUBJECT_LENGTH})"
+            ));
+        }
+        if subject.ends_with('.') {
+            problems.push("subject ends with a period".to_string());
+        }
+        if lines.next().is_some_and(|line| !line.trim().is_empty()) {
+            problems.push("no blank line after the subject".to_string());
+        }
+
+        let prefix = parse_conventional_prefix(subject);
+        match &prefix {
+            None if self.conventional => {
+                problems.push("subject is not a Conventional Commit".to_string());
+            }
+            Some((kind, _))
+                if self.conventional && !self.types.is_empty() && !self.types.contains(kind) =>
+            {
+                problems.push(format!("type `{kind}` is not one of the project's types"));
+            }
+            _ => {}
+        }
+        if self.gitmoji && !has_emoji(subject) {
+            problems.push("subject has no gitmoji".to_string());
+        }
+        if let Some(ticket) = &self.ticket_prefix
+            && !subject.contains(&format!("{ticket}-"))
+        {
+            problems.push(format!("subject does not reference a {ticket} ticket"));
+        }
+        let first = first_description_word(subject).and_then(|w| w.chars().next());
+        match (self.subject_case, first) {
+            (Some(SubjectCase::Lower), Some(c)) if c.is_uppercase() => {
+                problems.push("description should start lowercase".to_string());
+            }
+            (Some(SubjectCase::Upper), Some(c)) if c.is_lowercase() => {
+                problems.push("description should start uppercase".to_string());
+            }
+            _ => {}
+        }
+        problems
+    }
+}
+
+/// Extracts an issue key prefix like `ABC` from `ABC-123: ...` or `[ABC-123] ...`
+fn ticket_prefix(subject: &str) -> Option<String> {
+    let word = subject.split_whitespace().next()?;
+    let word = word.trim_start_matches('[').trim_end_matches([']', ':']);
+    let (prefix, number) = word.split_once('-')?;
+    let valid = !prefix.is_empty()
+        && prefix
+            .chars()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
+        && prefix.starts_with(|c: char| c.is_ascii_uppercase())
+        && !number.is_empty()
+        && number.chars().all(|c| c.is_ascii_digit());
+    valid.then(|| prefix.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn messages(subjects: &[&str]) -> Vec<String> {
+        subjects.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn test_detect_conventional_with_scopes() {
+        let conventions = ProjectConventions::detect(&messages(&[
+            "feat(tui): add history browser",
+            "fix(git): handle empty repos",
+            "feat(llm): rank context",
+            "Merge branch 'main'",
+        ]));
+        assert!(conventions.conventional);
+        assert!(conventions.scopes);
+        assert_eq!(conventions.types, vec!["feat", "fix"]);
+        assert_eq!(conventions.subject_case, Some(SubjectCase::Lower));
+        assert_eq!(conventions.sample_size, 3);
+    }
+
+    #[test]
+    fn test_detect_ticket_prefix() {
+        let conventions = ProjectConventions::detect(&messages(&[
+            "ABC-12: Add login",
+            "[ABC-13] Fix logout",
+            "Update docs",
+        ]));
+        assert!(!conventions.conventional);
+        assert_eq!(conventions.ticket_prefix.as_deref(), Some("ABC"));
+        assert_eq!(conventions.subject_case, Some(SubjectCase::Upper));
+    }
+
+    #[test]
+    fn test_lint() {
+        let conventions = ProjectConventions {
+            conventional: true,
+            types: vec!["feat".to_string(), "fix".to_string()],
+            subject_case: Some(SubjectCase::Lower),
+            ..ProjectConventions::default()
+        };
+        assert!(
+            conventions
+                .lint("feat(tui): add history\n\nBody")
+                .is_empty()
+        );
+        assert_eq!(
+            conventions.lint("chore: Bump deps."),
+            vec![
+                "subject ends with a period",
+                "type `chore` is not one of the project's types",
+                "description should start lowercase",
+            ]
+        );
+        assert_eq!(
+            ProjectConventions::default().lint("Add x\nbody"),
+            vec!["no blank line after the subject"]
+        );
+    }
+
+    #[test]
+    fn test_round_trip_through_file() {
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let conventions = ProjectConventions {
+            conventional: true,
+            types: vec!["feat".to_string(), "fix".to_string()],
+            scopes: true,
+            gitmoji: false,
+            ticket_prefix: Some("OPS".to_string()),
+            subject_case: Some(SubjectCase::Lower),
+            sample_size: 42,
+        };
+        conventions.save(dir.path()).expect("Failed to save");
+        let loaded = ProjectConventions::load(dir.path())
+            .expect("Failed to load")
+            .expect("Conventions file missing");
+        assert_eq!(loaded, conventions);
+    }
+}
@@ -0,0 +1,505 @@
+//! Warm-start daemon for commit hooks.
+//!
+//! `gitai daemon` listens on a Unix socket and keeps each repository's
+//! configuration, the HTTP connection pool and the model metadata cache alive
+//! between requests, so `git-message --ci` run from a hook on every commit
+//! skips the cold start. A connection carries one request and one response,
+//! each a line of JSON. Clients generate in-process whenever no daemon answers.
+//!
+//! Once a repository was served, the daemon watches its index and HEAD. When
+//! they settle on a new state, it rebuilds the context and prompts for the
+//! settings of the latest request, so the next request only waits for the
+//! model.
+
+use crate::commands::commit::CommitService;
+use crate::commands::commit::service::PreparedMessage;
+use crate::commands::commit::types::GeneratedMessage;
+use crate::common::{CommonParams, DetailLevel};
+use crate::config::Config;
+use crate::conventions::ProjectConventions;
+use crate::git::GitRepo;
+use anyhow::{Context, Result, anyhow};
+use clap::ValueEnum;
+use git2::{Config as GitConfig, Repository};
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::Mutex;
+
+/// Environment variable overriding the socket path
+pub const SOCKET_ENV: &str = "GITAI_DAEMON_SOCKET";
+
+/// How long a client waits to reach the daemon before generating in-process
+const CONNECT_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// The socket of `gitai daemon`: `GITAI_DAEMON_SOCKET`, or `gitai/daemon.sock`
+/// in the runtime directory, falling back to the cache directory
+pub fn socket_path() -> PathBuf {
+    if let Some(path) = std::env::var_os(SOCKET_ENV).filter(|path| !path.is_empty()) {
+        return PathBuf::from(path);
+    }
+    dirs::runtime_dir()
+        .or_else(dirs::cache_dir)
+        .unwrap_or_else(std::env::temp_dir)
+        .join("gitai")
+        .join("daemon.sock")
+}
+
+/// A message request; the overrides match the `git-message` flags of the same names
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Request {
+    /// Directory inside the repository, usually the client's working directory
+    pub directory: PathBuf,
+    pub instructions: Option<String>,
+    pub model: Option<String>,
+    pub language: Option<String>,
+    /// `minimal`, `standard` or `detailed`
+    pub detail_level: Option<String>,
+    /// Generate only a subject, as `--subject-only` does
+    pub subject_only: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "kebab-case")]
+pub enum Response {
+    Message(GeneratedMessage),
+    NoStagedChanges,
+    Error { error: String },
+}
+
+/// Sends `request` to the daemon listening on `socket`.
+///
+/// `None` means no daemon answered, and the caller should generate the
+/// message itself.
+pub async fn send(socket: &Path, request: &Request) -> Option<Response> {
+    let exchange = async {
+        let stream = tokio::time::timeout(CONNECT_TIMEOUT, UnixStream::connect(socket))
+            .await
+            .context("Timed out connecting")??;
+        let (reader, mut writer) = stream.into_split();
+        let mut line = serde_json::to_string(request)?;
+        line.push('\n');
+        writer.write_all(line.as_bytes()).await?;
+        writer.shutdown().await?;
+
+        let mut response = String::new();
+        BufReader::new(reader).read_line(&mut response).await?;
+        serde_json::from_str::<Response>(&response).context("Invalid response")
+    };
+    match exchange.await {
+        Ok(response) => Some(response),
+        Err(e) => {
+            debug!("No answer from the daemon at {}: {e:#}", socket.display());
+            None
+        }
+    }
+}
+
+/// How often the index of each repository the daemon served is checked
+const WATCH_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Modification time and length of each file in a list; `None` for a missing file
+type Stamp = Vec<Option<(SystemTime, u64)>>;
+
+fn stamp(files: &[PathBuf]) -> Stamp {
+    files
+        .iter()
+        .map(|file| {
+            let metadata = std::fs::metadata(file).ok()?;
+            Some((metadata.modified().ok()?, metadata.len()))
+        })
+        .collect()
+}
+
+/// Files whose changes invalidate the cached configuration of `repo`
+fn config_files(repo: &Repository) -> Vec<PathBuf> {
+    let mut files = vec![repo.path().join("config")];
+    files.extend(GitConfig::find_global().ok());
+    files.extend(GitConfig::find_xdg().ok());
+    if let Some(workdir) = repo.workdir() {
+        files.push(ProjectConventions::path(workdir));
+    }
+    files
+}
+
+/// The parts of a request that shape its prompt
+fn prompt_settings(request: &Request) -> Request {
+    Request {
+        directory: PathBuf::new(),
+        subject_only: false,
+        ..request.clone()
+    }
+}
+
+/// A message request built for the staged changes before it was asked for
+struct Prepared {
+    settings: Request,
+    /// Stamp of the index, HEAD and configuration it was built from
+    stamp: Stamp,
+    service: Arc<CommitService>,
+    /// `None` when nothing was staged
+    message: Option<PreparedMessage>,
+}
+
+/// What the daemon keeps for one repository
+struct RepoState {
+    root: PathBuf,
+    git_dir: PathBuf,
+    config_files: Vec<PathBuf>,
+    config: Mutex<Option<(Stamp, Config)>>,
+    /// Settings of the latest full message request, which the watcher prepares for
+    settings: Mutex<Option<Request>>,
+    prepared: Mutex<Option<Prepared>>,
+}
+
+impl RepoState {
+    /// Stamp of the index, HEAD and the branch HEAD points to, which together
+    /// decide the staged diff, and of the configuration files
+    fn inputs_stamp(&self) -> Stamp {
+        let head = self.git_dir.join("HEAD");
+        let mut files = vec![self.git_dir.join("index"), head.clone()];
+        files.extend(self.config_files.iter().cloned());
+        if let Ok(content) = std::fs::read_to_string(&head)
+            && let Some(reference) = content.trim().strip_prefix("ref: ")
+        {
+            files.push(self.git_dir.join(reference));
+        }
+        stamp(&files)
+    }
+
+    /// The configuration of the repository, reloaded when its files changed
+    async fn config(&self) -> Result<Config> {
+        let current = stamp(&self.config_files);
+        let mut cached = self.config.lock().await;
+        if let Some((stamp, config)) = cached.as_ref()
+            && *stamp == current
+        {
+            return Ok(config.clone());
+        }
+        debug!("Loading the configuration of {}", self.root.display());
+        let config = Config::load_from(&self.root)?;
+        *cached = Some((current, config.clone()));
+        Ok(config)
+    }
+
+    /// A commit service with `settings` applied, and the instructions to generate with
+    async fn service(&self, settings: &Request) -> Result<(Arc<CommitService>, String)> {
+        let mut config = self.config().await?;
+        let detail_level = settings
+            .detail_level
+            .as_deref()
+            .map(|level| DetailLevel::from_str(level, true).map_err(|e| anyhow!(e)))
+            .transpose()?
+            .unwrap_or(DetailLevel::Standard);
+        let common = CommonParams {
+            model: settings.model.clone(),
+            instructions: settings.instructions.clone(),
+            language: settings.language.clone(),
+            detail_level,
+            ..CommonParams::default()
+        };
+        common.apply_to_config(&mut config)?;
+        let instructions = common
+            .instructions
+            .unwrap_or_else(|| config.instructions.clone());
+
+        let provider_name = config.active_provider().as_str();
+        let service = CommitService::new(
+            config,
+            &self.root,
+            provider_name,
+            detail_level,
+            GitRepo::new(&self.root)?,
+        )?;
+        Ok((Arc::new(service), instructions))
+    }
+
+    /// Builds the request for `settings` from the staged changes of `stamp`
+    async fn prepare(&self, settings: Request, stamp: Stamp) -> Result<Prepared> {
+        let (service, instructions) = self.service(&settings).await?;
+        let context = service.get_git_info().await?;
+        let message = if context.staged_files.is_empty() {
+            None
+        } else {
+            Some(service.prepare_message(&instructions, context).await?)
+        };
+        Ok(Prepared {
+            settings,
+            stamp,
+            service,
+            message,
+        })
+    }
+
+    /// Rebuilds the prepared request whenever the index, HEAD or configuration
+    /// settles on a new state
+    async fn watch(self: Arc<Self>) {
+        let mut last = self.inputs_stamp();
+        let mut attempted = None;
+        loop {
+            tokio::time::sleep(WATCH_INTERVAL).await;
+            let current = self.inputs_stamp();
+            // Wait for a tick without writes, as `git add -p` writes the index repeatedly
+            if current != last {
+                last = current;
+                continue;
+            }
+            let Some(settings) = self.settings.lock().await.clone() else {
+                continue;
+            };
+            let ready = self
+                .prepared
+                .lock()
+                .await
+                .as_ref()
+                .is_some_and(|p| p.settings == settings && p.stamp == current);
+            if ready || attempted.as_ref() == Some(&(settings.clone(), current.clone())) {
+                continue;
+            }
+            attempted = Some((settings.clone(), current.clone()));
+
+            debug!("Preparing the message for {}", self.root.display());
+            match self.prepare(settings, current.clone()).await {
+                Ok(prepared) if self.inputs_stamp() == current => {
+                    *self.prepared.lock().await = Some(prepared);
+                }
+                Ok(_) => debug!("The index changed while preparing; retrying"),
+                Err(e) => debug!("Failed to prepare the message: {e:#}"),
+            }
+        }
+    }
+}
+
+/// State kept between requests
+#[derive(Default)]
+struct Daemon {
+    repos: Mutex<HashMap<PathBuf, Arc<RepoState>>>,
+}
+
+impl Daemon {
+    /// The state of the repository containing `directory`, watched from its first request on
+    async fn repo(&self, directory: &Path) -> Result<Arc<RepoState>> {
+        // `Repository` is not `Sync`, so it must not live across an await
+        let (root, git_dir, config_files) = {
+            let repo = Repository::discover(directory)
+                .with_context(|| format!("{} is not in a repository", directory.display()))?;
+            let root = repo
+                .workdir()
+                .ok_or_else(|| anyhow!("Cannot generate messages in a bare repository"))?
+                .to_path_buf();
+            (root, repo.path().to_path_buf(), config_files(&repo))
+        };
+        let mut repos = self.repos.lock().await;
+        if let Some(state) = repos.get(&root) {
+            return Ok(Arc::clone(state));
+        }
+        let state = Arc::new(RepoState {
+            root: root.clone(),
+            git_dir,
+            config_files,
+            config: Mutex::new(None),
+            settings: Mutex::new(None),
+            prepared: Mutex::new(None),
+        });
+        tokio::spawn(Arc::clone(&state).watch());
+        repos.insert(root, Arc::clone(&state));
+        Ok(state)
+    }
+
+    async fn handle(&self, request: Request) -> Response {
+        match self.generate(request).await {
+            Ok(Some(message)) => Response::Message(message),
+            Ok(None) => Response::NoStagedChanges,
+            Err(e) => Response::Error {
+                error: format!("{e:#}"),
+            },
+        }
+    }
+
+    async fn generate(&self, request: Request) -> Result<Option<GeneratedMessage>> {
+        let state = self.repo(&request.directory).await?;
+        let settings = prompt_settings(&request);
+
+        if !request.subject_only {
+            *state.settings.lock().await = Some(settings.clone());
+            let current = state.inputs_stamp();
+            let ready = state
+                .prepared
+                .lock()
+                .await
+                .as_ref()
+                .filter(|p| p.settings == settings && p.stamp == current)
+                .map(|p| (Arc::clone(&p.service), p.message.clone()));
+            if let Some((service, message)) = ready {
+                debug!("Answering from the prepared message");
+                let Some(message) = message else {
+                    return Ok(None);
+                };
+                if let Some(special) = service.special_message(None)? {
+                    return Ok(Some(special));
+                }
+                return service.generate_prepared(&message).await.map(Some);
+            }
+        }
+
+        let (service, instructions) = state.service(&settings).await?;
+        let context = service.get_git_info().await?;
+        if context.staged_files.is_empty() {
+            return Ok(None);
+        }
+        if let Some(message) = service.special_message(None)? {
+            return Ok(Some(message));
+        }
+        let message = if request.subject_only {
+            service.generate_subject(&instructions).await?
+        } else {
+            service
+                .generate_message_with_context(&instructions, context)
+                .await?
+        };
+        Ok(Some(message))
+    }
+}
+
+/// Binds `socket`, replacing a stale one; fails when a daemon already listens there
+async fn bind(socket: &Path) -> Result<UnixListener> {
+    if let Some(dir) = socket.parent() {
+        std::fs::DirBuilder::new()
+            .recursive(true)
+            .mode(0o700)
+            .create(dir)
+            .with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+    if socket.exists() {
+        if UnixStream::connect(socket).await.is_ok() {
+            return Err(anyhow!(
+                "A daemon is already listening on {}",
+                socket.display()
+            ));
+        }
+        std::fs::remove_file(socket)
+            .with_context(|| format!("Failed to remove the stale {}", socket.display()))?;
+    }
+    let listener = UnixListener::bind(socket)
+        .with_context(|| format!("Failed to listen on {}", socket.display()))?;
+    std::fs::set_permissions(socket, std::fs::Permissions::from_mode(0o600))?;
+    Ok(listener)
+}
+
+async fn serve_connection(daemon: &Daemon, stream: UnixStream) -> Result<()> {
+    let (reader, mut writer) = stream.into_split();
+    let mut line = String::new();
+    BufReader::new(reader).read_line(&mut line).await?;
+    let response = match serde_json::from_str::<Request>(&line) {
+        Ok(request) => daemon.handle(request).await,
+        Err(e) => Response::Error {
+            error: format!("Invalid request: {e}"),
+        },
+    };
+    let mut line = serde_json::to_string(&response)?;
+    line.push('\n');
+    writer.write_all(line.as_bytes()).await?;
+    Ok(())
+}
+
+/// Serves requests on `socket` until interrupted, or until no request came
+/// for `idle_timeout`
+///
+/// # Errors
+///
+/// Returns an error if the socket cannot be bound or another daemon owns it.
+pub async fn serve(socket: &Path, idle_timeout: Option<Duration>) -> Result<()> {
+    let listener = bind(socket).await?;
+    let daemon = Arc::new(Daemon::default());
+    let idle = tokio::time::sleep(idle_timeout.unwrap_or(Duration::MAX));
+    tokio::pin!(idle);
+
+    let result = loop {
+        tokio::select! {
+            accepted = listener.accept() => {
+                let (stream, _) = match accepted {
+                    Ok(accepted) => accepted,
+                    Err(e) => break Err(e.into()),
+                };
+                if let Some(timeout) = idle_timeout {
+                    idle.as_mut().reset(tokio::time::Instant::now() + timeout);
+                }
+                let daemon = Arc::clone(&daemon);
+                tokio::spawn(async move {
+                    if let Err(e) = serve_connection(&daemon, stream).await {
+                        debug!("Dropped a daemon connection: {e:#}");
+                    }
+                });
+            }
+            () = &mut idle, if idle_timeout.is_some() => {
+                debug!("No requests for a while; stopping the daemon");
+                break Ok(());
+            }
+            _ = tokio::signal::ctrl_c() => break Ok(()),
+        }
+    };
+    let _ = std::fs::remove_file(socket);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_request_round_trip() {
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let socket = dir.path().join("daemon.sock");
+        assert_eq!(send(&socket, &Request::default()).await, None);
+
+        let server = {
+            let socket = socket.clone();
+            tokio::spawn(async move { serve(&socket, Some(Duration::from_secs(5))).await })
+        };
+        while !socket.exists() {
+            tokio::task::yield_now().await;
+        }
+        assert!(bind(&socket).await.is_err());
+
+        let request = Request {
+            directory: dir.path().to_path_buf(),
+            ..Request::default()
+        };
+        match send(&socket, &request).await {
+            Some(Response::Error { error }) => assert!(error.contains("not in a repository")),
+            other => panic!("Expected an error response, got {other:?}"),
+        }
+        server.abort();
+    }
+
+    #[tokio::test]
+    async fn test_staging_changes_the_stamp() {
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let repo = Repository::init(dir.path()).expect("Failed to init repo");
+        let state = RepoState {
+            root: dir.path().to_path_buf(),
+            git_dir: repo.path().to_path_buf(),
+            config_files: config_files(&repo),
+            config: Mutex::new(None),
+            settings: Mutex::new(None),
+            prepared: Mutex::new(None),
+        };
+        let before = state.inputs_stamp();
+        assert_eq!(before, state.inputs_stamp());
+
+        std::fs::write(dir.path().join("a.txt"), "a").expect("Failed to write file");
+        let mut index = repo.index().expect("Failed to open index");
+        index
+            .add_path(Path::new("a.txt"))
+            .expect("Failed to stage file");
+        index.write().expect("Failed to write index");
+        assert_ne!(before, state.inputs_stamp());
+    }
+}
@@ -0,0 +1,295 @@
+//! Error codes users can look up, with help text and exit codes.
+//!
+//! Errors travel as `anyhow` chains. Where the kind of failure is known, it
+//! is raised with [`coded`]; otherwise [`ErrorCode::of`] recognises git,
+//! HTTP and I/O errors in the chain. The binaries print failures through
+//! [`report`], which adds the code and a hint on what to do, and exit with
+//! the code's [`exit_code`]. Codes are stable: a code is never reused for a
+//! different failure, and removed ones are left out rather than renumbered.
+
+use crate::output;
+use std::fmt;
+
+/// Process exit codes of the gitai binaries
+pub mod exit_code {
+    /// Unexpected failure, such as an I/O error
+    pub const FAILURE: i32 = 1;
+    /// Nothing is staged, so there is nothing to describe
+    pub const NO_STAGED_CHANGES: i32 = 2;
+    /// The provider failed or the timeout expired
+    pub const PROVIDER_FAILURE: i32 = 3;
+    /// A setting is missing or invalid
+    pub const CONFIG: i32 = 4;
+    /// The repository or a remote could not be read
+    pub const GIT: i32 = 5;
+    /// A git-wire entry is invalid or its sync failed
+    pub const WIRE: i32 = 6;
+}
+
+/// A kind of failure, with a stable code such as `GIT-001`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorCode {
+    /// GIT-001: not inside a git repository
+    NotARepository,
+    /// GIT-002: nothing is staged
+    NoStagedChanges,
+    /// GIT-003: a git operation failed
+    Git,
+    /// GIT-004: a remote could not be cloned or fetched
+    Remote,
+    /// CFG-001: the provider needs an API key and has none
+    MissingApiKey,
+    /// CFG-002: the provider or profile is unknown
+    UnknownProvider,
+    /// CFG-003: a setting has an invalid value
+    InvalidConfig,
+    /// LLM-001: the provider could not be reached
+    ProviderUnreachable,
+    /// LLM-002: the provider rejected the request
+    ProviderRejected,
+    /// LLM-003: the provider did not answer in time
+    Timeout,
+    /// LLM-004: the response could not be decoded
+    InvalidResponse,
+    /// LLM-005: the provider failed otherwise
+    ProviderFailed,
+    /// WIRE-001: a `.gitwire` entry is invalid
+    WireConfig,
+    /// WIRE-002: syncing or checking the wired code failed
+    WireSync,
+    /// WIRE-003: the wired code differs from its source
+    WireDiffers,
+    /// IO-001: a file could not be read or written
+    Io,
+    /// GEN-001: anything else
+    Internal,
+}
+
+impl ErrorCode {
+    /// Every code, in the order of the documentation
+    pub const ALL: [Self; 17] = [
+        Self::NotARepository,
+        Self::NoStagedChanges,
+        Self::Git,
+        Self::Remote,
+        Self::MissingApiKey,
+        Self::UnknownProvider,
+        Self::InvalidConfig,
+        Self::ProviderUnreachable,
+        Self::ProviderRejected,
+        Self::Timeout,
+        Self::InvalidResponse,
+        Self::ProviderFailed,
+        Self::WireConfig,
+        Self::WireSync,
+        Self::WireDiffers,
+        Self::Io,
+        Self::Internal,
+    ];
+
+    /// The stable code users look up
+    pub fn code(self) -> &'static str {
+        match self {
+            Self::NotARepository => "GIT-001",
+            Self::NoStagedChanges => "GIT-002",
+            Self::Git => "GIT-003",
+            Self::Remote => "GIT-004",
+            Self::MissingApiKey => "CFG-001",
+            Self::UnknownProvider => "CFG-002",
+            Self::InvalidConfig => "CFG-003",
+            Self::ProviderUnreachable => "LLM-001",
+            Self::ProviderRejected => "LLM-002",
+            Self::Timeout => "LLM-003",
+            Self::InvalidResponse => "LLM-004",
+            Self::ProviderFailed => "LLM-005",
+            Self::WireConfig => "WIRE-001",
+            Self::WireSync => "WIRE-002",
+            Self::WireDiffers => "WIRE-003",
+            Self::Io => "IO-001",
+            Self::Internal => "GEN-001",
+        }
+    }
+
+    /// What to do about the failure
+    pub fn help(self) -> &'static str {
+        match self {
+            Self::NotARepository => "Run from inside a git repository, or pass --repo <url>",
+            Self::NoStagedChanges => "Stage changes with `git add <file>` or `git add .`",
+            Self::Git => {
+                "Check `git status`; another git process may hold the lock, or a rebase or merge may be in progress"
+            }
+            Self::Remote => "Check the URL and that your credentials can read the remote",
+            Self::MissingApiKey => {
+                "Run `gitai init`, or export the provider's API key variable; `git-doctor` names it"
+            }
+            Self::UnknownProvider => {
+                "Check gitai.provider and --profile; `gitai models` lists the providers"
+            }
+            Self::InvalidConfig => "Run `git-doctor` to find the setting and how to fix it",
+            Self::ProviderUnreachable => {
+                "Check the network, proxy and base URL; `git-doctor` tests the connection"
+            }
+            Self::ProviderRejected => {
+                "Check the API key and the model; `gitai models` lists the models the key can use"
+            }
+            Self::Timeout => "Retry, or allow more time with --timeout",
+            Self::InvalidResponse => "Retry; another model may follow the format more closely",
+            Self::ProviderFailed => "Retry; `git-doctor` checks the provider and the model",
+            Self::WireConfig => "Fix the entry in .gitwire, or pass --url and --src",
+            Self::WireSync => "Check that the source paths exist at the wired revision",
+            Self::WireDiffers => "Run `git-wire sync` to bring the wired code up to date",
+            Self::Io => "Check the permissions and free space of the paths involved",
+            Self::Internal => "Rerun with RUST_LOG=debug and report the output",
+        }
+    }
+
+    /// The process exit code for the failure
+    pub fn exit_code(self) -> i32 {
+        match self {
+            Self::NoStagedChanges => exit_code::NO_STAGED_CHANGES,
+            Self::NotARepository | Self::Git | Self::Remote => exit_code::GIT,
+            Self::MissingApiKey | Self::UnknownProvider | Self::InvalidConfig => exit_code::CONFIG,
+            Self::ProviderUnreachable
+            | Self::ProviderRejected
+            | Self::Timeout
+            | Self::InvalidResponse
+            | Self::ProviderFailed => exit_code::PROVIDER_FAILURE,
+            Self::WireConfig | Self::WireSync | Self::WireDiffers => exit_code::WIRE,
+            Self::Io | Self::Internal => exit_code::FAILURE,
+        }
+    }
+
+    /// The code of `err`: the outermost one raised with [`coded`], or the one
+    /// its git, HTTP or I/O cause implies
+    pub fn of(err: &anyhow::Error) -> Option<Self> {
+        if let Some(coded) = err.chain().find_map(|e| e.downcast_ref::<Coded>()) {
+            return Some(coded.code);
+        }
+        err.chain().find_map(|e| {
+            if let Some(e) = e.downcast_ref::<git2::Error>() {
+                Some(Self::of_git(e))
+            } else if let Some(e) = e.downcast_ref::<reqwest::Error>() {
+                Some(if e.is_timeout() {
+                    Self::Timeout
+                } else if e.is_status() {
+                    Self::ProviderRejected
+                } else {
+                    Self::ProviderUnreachable
+                })
+            } else if e.is::<tokio::time::error::Elapsed>() {
+                Some(Self::Timeout)
+            } else if e.is::<std::io::Error>() {
+                Some(Self::Io)
+            } else {
+                None
+            }
+        })
+    }
+
+    fn of_git(e: &git2::Error) -> Self {
+        match (e.class(), e.code()) {
+            (git2::ErrorClass::Repository, git2::ErrorCode::NotFound) => Self::NotARepository,
+            (git2::ErrorClass::Net | git2::ErrorClass::Ssh | git2::ErrorClass::Http, _)
+            | (_, git2::ErrorCode::Auth | git2::ErrorCode::Certificate) => Self::Remote,
+            _ => Self::Git,
+        }
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.code())
+    }
+}
+
+/// An error raised with its code
+#[derive(Debug)]
+struct Coded {
+    code: ErrorCode,
+    message: String,
+}
+
+impl fmt::Display for Coded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for Coded {}
+
+/// An error with `code` and `message`
+pub fn coded(code: ErrorCode, message: impl fmt::Display) -> anyhow::Error {
+    anyhow::Error::new(Coded {
+        code,
+        message: message.to_string(),
+    })
+}
+
+/// Prints `err` with its code and help, and returns the exit code
+///
+/// An error without a recognisable code is reported as `fallback`.
+pub fn report_as(err: &anyhow::Error, fallback: ErrorCode) -> i32 {
+    let code = ErrorCode::of(err).unwrap_or(fallback);
+    output::print_error(&format!("Error [{code}]: {err:#}"));
+    eprintln!("  help: {}", code.help());
+    code.exit_code()
+}
+
+/// Prints `err` with its code and help, and returns the exit code
+pub fn report(err: &anyhow::Error) -> i32 {
+    report_as(err, ErrorCode::Internal)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_codes_are_unique_and_well_formed() {
+        let codes: HashSet<&str> = ErrorCode::ALL.iter().map(|c| c.code()).collect();
+        assert_eq!(codes.len(), ErrorCode::ALL.len());
+        for code in ErrorCode::ALL {
+            let (prefix, number) = code.code().split_once('-').expect("Code has a prefix");
+            assert!(["GIT", "CFG", "LLM", "WIRE", "IO", "GEN"].contains(&prefix));
+            assert_eq!(number.len(), 3);
+            assert!(!code.help().is_empty());
+        }
+    }
+
+    #[test]
+    fn test_code_of_an_error() {
+        let err = coded(ErrorCode::NoStagedChanges, "No staged changes");
+        assert_eq!(err.to_string(), "No staged changes");
+        assert_eq!(ErrorCode::of(&err), Some(ErrorCode::NoStagedChanges));
+        let wrapped = err.context("Failed to generate");
+        assert_eq!(ErrorCode::of(&wrapped), Some(ErrorCode::NoStagedChanges));
+
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let err = git2::Repository::open(dir.path())
+            .map(|_| ())
+            .context("Failed to open")
+            .expect_err("Not a repository");
+        assert_eq!(ErrorCode::of(&err), Some(ErrorCode::NotARepository));
+
+        let err = anyhow::Error::new(std::io::Error::other("disk full"));
+        assert_eq!(ErrorCode::of(&err), Some(ErrorCode::Io));
+        assert_eq!(ErrorCode::of(&anyhow::anyhow!("unknown")), None);
+    }
+
+    #[test]
+    fn test_exit_codes() {
+        assert_eq!(
+            report(&coded(ErrorCode::NoStagedChanges, "none")),
+            exit_code::NO_STAGED_CHANGES
+        );
+        assert_eq!(
+            report_as(&anyhow::anyhow!("failed"), ErrorCode::ProviderFailed),
+            exit_code::PROVIDER_FAILURE
+        );
+        assert_eq!(report(&anyhow::anyhow!("failed")), exit_code::FAILURE);
+        assert_eq!(ErrorCode::MissingApiKey.exit_code(), exit_code::CONFIG);
+        assert_eq!(ErrorCode::Remote.exit_code(), exit_code::GIT);
+    }
+}
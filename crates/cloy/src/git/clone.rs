@@ -0,0 +1,145 @@
+//! Cloning `--repo` URLs with a progress line and Ctrl-C to abort.
+
+use crate::git::remote;
+use crate::llm::binary::human_size;
+use crate::output::is_plain_mode;
+use anyhow::{Result, anyhow};
+use git2::build::RepoBuilder;
+use git2::{FetchOptions, Progress, RemoteCallbacks, Repository};
+use std::io::{self, IsTerminal, Write};
+use std::path::Path;
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+/// Time between redraws of the progress line
+const REDRAW_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Whether a clone is running, so Ctrl-C cancels it rather than the process
+static CLONING: AtomicBool = AtomicBool::new(false);
+/// Whether Ctrl-C was pressed during the running clone
+static CANCELLED: AtomicBool = AtomicBool::new(false);
+static LISTENER: OnceLock<()> = OnceLock::new();
+
+/// Listens for Ctrl-C once per process. Listening replaces the default
+/// handler, so outside a clone it exits as the default handler would.
+fn listen_for_interrupts() {
+    let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+        return;
+    };
+    LISTENER.get_or_init(|| {
+        runtime.spawn(async {
+            while tokio::signal::ctrl_c().await.is_ok() {
+                if CLONING.load(Ordering::SeqCst) {
+                    CANCELLED.store(true, Ordering::SeqCst);
+                } else {
+                    std::process::exit(130);
+                }
+            }
+        });
+    });
+}
+
+/// `Receiving objects:  45% (450/1000), 1.2 MB`, then `Resolving deltas` once
+/// every object is in
+fn progress_line(objects: (usize, usize), bytes: usize, deltas: (usize, usize)) -> Option<String> {
+    let percent = |(done, total): (usize, usize)| (done * 100).checked_div(total).unwrap_or(0);
+    let (received, total) = objects;
+    if total == 0 {
+        return None;
+    }
+    if received < total || deltas.1 == 0 {
+        return Some(format!(
+            "Receiving objects: {:>3}% ({received}/{total}), {}",
+            percent(objects),
+            human_size(bytes)
+        ));
+    }
+    Some(format!(
+        "Resolving deltas: {:>3}% ({}/{})",
+        percent(deltas),
+        deltas.0,
+        deltas.1
+    ))
+}
+
+fn draw(stats: &Progress<'_>) {
+    let Some(line) = progress_line(
+        (stats.received_objects(), stats.total_objects()),
+        stats.received_bytes(),
+        (stats.indexed_deltas(), stats.total_deltas()),
+    ) else {
+        return;
+    };
+    let mut stderr = io::stderr();
+    if let Err(e) = write!(stderr, "\r\x1b[2K{line}").and_then(|()| stderr.flush()) {
+        log::debug!("Clone progress write failed: {e}");
+    }
+}
+
+/// Clones `url` into `path`, showing objects and bytes received on a
+/// terminal. Ctrl-C aborts the transfer; the caller removes `path`.
+///
+/// The URL, proxy and credentials are resolved from git and SSH
+/// configuration, as the git CLI resolves them.
+pub(crate) fn clone(original: &str, path: &Path) -> Result<Repository> {
+    let show = io::stderr().is_terminal() && !is_plain_mode();
+    if !show {
+        eprintln!("Cloning {original}...");
+    }
+    listen_for_interrupts();
+    CANCELLED.store(false, Ordering::SeqCst);
+    CLONING.store(true, Ordering::SeqCst);
+
+    let mut last_draw: Option<Instant> = None;
+    let (url, ssh) = remote::resolve_url(original);
+    if url != original {
+        log::debug!("Cloning {original} as {url}");
+    }
+    let mut callbacks = RemoteCallbacks::new();
+    remote::add_credentials(&mut callbacks, ssh);
+    callbacks.transfer_progress(|stats| {
+        if CANCELLED.load(Ordering::SeqCst) {
+            return false;
+        }
+        let done = stats.received_objects() == stats.total_objects()
+            && stats.indexed_deltas() == stats.total_deltas();
+        if show && (done || last_draw.is_none_or(|at| at.elapsed() >= REDRAW_INTERVAL)) {
+            draw(&stats);
+            last_draw = Some(Instant::now());
+        }
+        true
+    });
+    let mut fetch = FetchOptions::new();
+    fetch.remote_callbacks(callbacks);
+    fetch.proxy_options(remote::proxy_options());
+    let result = RepoBuilder::new().fetch_options(fetch).clone(&url, path);
+
+    CLONING.store(false, Ordering::SeqCst);
+    if show && last_draw.is_some() {
+        eprintln!();
+    }
+    match result {
+        Ok(repo) => Ok(repo),
+        Err(_) if CANCELLED.swap(false, Ordering::SeqCst) => Err(anyhow!("Clone cancelled")),
+        Err(e) => Err(anyhow!("Failed to clone repository: {e}")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_progress_line() {
+        assert_eq!(progress_line((0, 0), 0, (0, 0)), None);
+        assert_eq!(
+            progress_line((450, 1000), 1_258_291, (0, 0)).as_deref(),
+            Some("Receiving objects:  45% (450/1000), 1.2 MB")
+        );
+        assert_eq!(
+            progress_line((1000, 1000), 2_097_152, (30, 120)).as_deref(),
+            Some("Resolving deltas:  25% (30/120)")
+        );
+    }
+}
@@ -0,0 +1,213 @@
+use crate::llm::context::{FileOwners, StagedFile};
+use anyhow::Result;
+use git2::Repository;
+use log::debug;
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// Where GitHub and GitLab look for the file, in order
+const CODEOWNERS_PATHS: [&str; 3] = [".github/CODEOWNERS", "CODEOWNERS", "docs/CODEOWNERS"];
+
+/// Ownership rules of a CODEOWNERS file
+#[derive(Debug, Default)]
+pub struct CodeOwners {
+    rules: Vec<(Regex, Vec<String>)>,
+}
+
+/// A gitignore-style pattern as a regex over repository paths.
+///
+/// A pattern without a slash before its end matches at any depth, and a
+/// pattern matching a directory matches everything in it.
+pub(crate) fn pattern_regex(pattern: &str) -> Option<Regex> {
+    let directory = pattern.ends_with('/');
+    let pattern = pattern.trim_end_matches('/');
+    let anchored = pattern.contains('/');
+    let pattern = pattern.trim_start_matches('/');
+    if pattern.is_empty() {
+        return None;
+    }
+
+    let mut regex = String::from(if anchored { "^" } else { "^(?:.*/)?" });
+    let mut chars = pattern.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                if chars.peek() == Some(&'/') {
+                    chars.next();
+                    regex.push_str("(?:.*/)?");
+                } else {
+                    regex.push_str(".*");
+                }
+            }
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    regex.push_str(if directory { "/.*$" } else { "(?:/.*)?$" });
+    Regex::new(&regex).ok()
+}
+
+impl CodeOwners {
+    pub fn parse(text: &str) -> Self {
+        let rules = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .filter_map(|line| {
+                let mut fields = line.split_whitespace();
+                let regex = pattern_regex(fields.next()?)?;
+                let owners = fields
+                    .take_while(|field| !field.starts_with('#'))
+                    .map(ToString::to_string)
+                    .collect();
+                Some((regex, owners))
+            })
+            .collect();
+        Self { rules }
+    }
+
+    /// The repository's CODEOWNERS, from the worktree or else `HEAD`
+    pub fn load(repo: &Repository) -> Option<Self> {
+        if let Some(workdir) = repo.workdir() {
+            for path in CODEOWNERS_PATHS {
+                if let Ok(text) = fs::read_to_string(workdir.join(path)) {
+                    return Some(Self::parse(&text));
+                }
+            }
+            return None;
+        }
+        let tree = repo.head().ok()?.peel_to_tree().ok()?;
+        CODEOWNERS_PATHS.iter().find_map(|path| {
+            let blob = tree.get_path(Path::new(path)).ok()?.to_object(repo).ok()?;
+            let blob = blob.as_blob()?;
+            Some(Self::parse(&String::from_utf8_lossy(blob.content())))
+        })
+    }
+
+    /// Owners of `path`; the last matching rule wins, and a rule without owners unsets them
+    pub fn owners_of(&self, path: &str) -> &[String] {
+        self.rules
+            .iter()
+            .rev()
+            .find(|(regex, _)| regex.is_match(path))
+            .map_or(&[], |(_, owners)| owners.as_slice())
+    }
+
+    /// Owners of each of `paths` that has any
+    pub fn annotate<'a>(&self, paths: impl IntoIterator<Item = &'a str>) -> Vec<FileOwners> {
+        paths
+            .into_iter()
+            .filter_map(|path| {
+                let owners = self.owners_of(path);
+                (!owners.is_empty()).then(|| FileOwners {
+                    path: path.to_string(),
+                    owners: owners.to_vec(),
+                })
+            })
+            .collect()
+    }
+}
+
+/// Owners of the staged files, per the repository's CODEOWNERS
+pub fn owners_of_files(repo: &Repository, files: &[StagedFile]) -> Vec<FileOwners> {
+    let Some(codeowners) = CodeOwners::load(repo) else {
+        return Vec::new();
+    };
+    let owners = codeowners.annotate(files.iter().map(|f| f.path.as_str()));
+    debug!(
+        "CODEOWNERS covers {} of {} files",
+        owners.len(),
+        files.len()
+    );
+    owners
+}
+
+/// Owners of the files changed between the merge base of `from` and `to`, and `to`
+pub fn owners_for_range(repo: &Repository, from: &str, to: &str) -> Result<Vec<FileOwners>> {
+    let Some(codeowners) = CodeOwners::load(repo) else {
+        return Ok(Vec::new());
+    };
+    let from = repo.revparse_single(from)?.peel_to_commit()?;
+    let to = repo.revparse_single(to)?.peel_to_commit()?;
+    let base = repo.find_commit(repo.merge_base(from.id(), to.id())?)?;
+    let diff = repo.diff_tree_to_tree(Some(&base.tree()?), Some(&to.tree()?), None)?;
+    let paths: Vec<String> = diff
+        .deltas()
+        .filter_map(|delta| delta.new_file().path().or_else(|| delta.old_file().path()))
+        .map(|path| path.to_string_lossy().replace('\\', "/"))
+        .collect();
+    Ok(codeowners.annotate(paths.iter().map(String::as_str)))
+}
+
+/// Owners of the files staged against HEAD
+pub fn owners_for_staged(repo: &Repository) -> Result<Vec<FileOwners>> {
+    let Some(codeowners) = CodeOwners::load(repo) else {
+        return Ok(Vec::new());
+    };
+    let head = repo.head().ok().and_then(|head| head.peel_to_tree().ok());
+    let diff = repo.diff_tree_to_index(head.as_ref(), None, None)?;
+    let paths: Vec<String> = diff
+        .deltas()
+        .filter_map(|delta| delta.new_file().path().or_else(|| delta.old_file().path()))
+        .map(|path| path.to_string_lossy().replace('\\', "/"))
+        .collect();
+    Ok(codeowners.annotate(paths.iter().map(String::as_str)))
+}
+
+/// Changed files grouped by owner
+pub fn areas_by_owner(owners: &[FileOwners]) -> BTreeMap<&str, Vec<&str>> {
+    let mut areas: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for file in owners {
+        for owner in &file.owners {
+            areas.entry(owner).or_default().push(&file.path);
+        }
+    }
+    areas
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CODEOWNERS: &str = "\
+# Default owners
+*       @org/everyone
+
+*.rs    @org/rust
+/docs/  @org/docs docs@example.com
+crates/**/tests/ @org/qa
+/build.rs
+";
+
+    #[test]
+    fn test_last_matching_rule_wins() {
+        let codeowners = CodeOwners::parse(CODEOWNERS);
+        assert_eq!(codeowners.owners_of("README.md"), ["@org/everyone"]);
+        assert_eq!(codeowners.owners_of("src/lib.rs"), ["@org/rust"]);
+        assert_eq!(
+            codeowners.owners_of("docs/guide/intro.md"),
+            ["@org/docs", "docs@example.com"]
+        );
+        // Anchored: a nested docs directory is not /docs/
+        assert_eq!(codeowners.owners_of("src/docs/a.md"), ["@org/everyone"]);
+        assert_eq!(
+            codeowners.owners_of("crates/cloy/tests/git.rs"),
+            ["@org/qa"]
+        );
+        assert!(codeowners.owners_of("build.rs").is_empty());
+    }
+
+    #[test]
+    fn test_areas_group_files_by_owner() {
+        let codeowners = CodeOwners::parse(CODEOWNERS);
+        let owners = codeowners.annotate(["src/a.rs", "src/b.rs", "docs/x.md", "build.rs"]);
+        assert_eq!(owners.len(), 3);
+        let areas = areas_by_owner(&owners);
+        assert_eq!(areas["@org/rust"], ["src/a.rs", "src/b.rs"]);
+        assert_eq!(areas["docs@example.com"], ["docs/x.md"]);
+    }
+}
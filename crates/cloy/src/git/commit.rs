@@ -1,24 +1,12 @@
-use crate::git::utils::is_binary_diff;
-use crate::llm::context::{ChangeType, RecentCommit, StagedFile};
+use crate::config::DiffSettings;
+use crate::git::utils::{detect_renames, is_binary_diff, rename_similarity, unborn_branch_name};
+use crate::git::{sample, word_diff};
+use crate::llm::context::{ChangeType, DiffSource, RecentCommit, StagedFile};
 use anyhow::{Result, anyhow};
 use chrono;
 use git2::{FileMode, Repository};
 use log::debug;
 
-/// Apply rename/copy detection to a diff
-fn detect_renames(diff: &mut git2::Diff<'_>) -> Result<()> {
-    let mut find_options = git2::DiffFindOptions::new();
-    find_options
-        .renames(true) // Detect renames
-        .copies(true) // Detect copies
-        .rename_threshold(50) // 50% similarity threshold
-        .copy_threshold(50) // 50% for copies
-        .rename_limit(200); // Max files to compare
-
-    diff.find_similar(Some(&mut find_options))?;
-    Ok(())
-}
-
 fn extract_delta_info(delta: &git2::DiffDelta) -> Option<(String, ChangeType, git2::Delta)> {
     let to_owned_path =
         |p: Option<&std::path::Path>| p.and_then(|path| path.to_str()).map(String::from);
@@ -45,8 +33,7 @@ fn extract_delta_info(delta: &git2::DiffDelta) -> Option<(String, ChangeType, gi
                 to_owned_path(delta.new_file().path())?,
                 ChangeType::Renamed {
                     from: old_path,
-                    // git2 0.20.x does not expose DiffDelta::similarity();
-                    // the actual similarity is computed by find_similar() internally.
+                    // Filled in from the patch by process_patch
                     similarity: 0,
                 },
                 delta.status(),
@@ -58,7 +45,7 @@ fn extract_delta_info(delta: &git2::DiffDelta) -> Option<(String, ChangeType, gi
                 to_owned_path(delta.new_file().path())?,
                 ChangeType::Copied {
                     from: old_path,
-                    // git2 0.20.x does not expose DiffDelta::similarity()
+                    // Filled in from the patch by process_patch
                     similarity: 0,
                 },
                 delta.status(),
@@ -76,13 +63,10 @@ fn process_patch(
 ) -> (ChangeType, String) {
     let mut final_change_type = change_type.clone();
 
-    if patch.num_hunks() == 0 {
-        match &mut final_change_type {
-            ChangeType::Renamed { similarity, .. } | ChangeType::Copied { similarity, .. } => {
-                *similarity = 100;
-            }
-            _ => {}
-        }
+    if let ChangeType::Renamed { similarity, .. } | ChangeType::Copied { similarity, .. } =
+        &mut final_change_type
+    {
+        *similarity = rename_similarity(patch);
     }
 
     if patch.num_hunks() == 0 && matches!(status, git2::Delta::Renamed | git2::Delta::Copied) {
@@ -186,6 +170,24 @@ fn get_files_from_diff(repo: &Repository, diff: &mut git2::Diff<'_>) -> Result<V
     Ok(files)
 }
 
+/// A blob's text for the prompt, sampled for new files over the sample size
+fn blob_text(
+    repo: &Repository,
+    id: git2::Oid,
+    change_type: &ChangeType,
+    settings: DiffSettings,
+) -> Option<String> {
+    let budget = usize::try_from(settings.sample_size).unwrap_or(usize::MAX);
+    if settings.sample_size > 0
+        && *change_type == ChangeType::Added
+        && repo.odb().ok()?.read_header(id).ok()?.0 > budget
+    {
+        return sample::sample_blob(repo, id, budget).ok().flatten();
+    }
+    let blob = repo.find_blob(id).ok()?;
+    std::str::from_utf8(blob.content()).ok().map(str::to_string)
+}
+
 /// Results from a commit operation
 #[derive(Debug, Clone)]
 pub struct CommitResult {
@@ -195,6 +197,8 @@ pub struct CommitResult {
     pub insertions: usize,
     pub deletions: usize,
     pub new_files: Vec<(String, FileMode)>,
+    /// Whether the commit has no parents
+    pub root_commit: bool,
 }
 
 /// Collects information about a specific commit
@@ -205,6 +209,17 @@ pub struct CommitInfo {
     pub file_paths: Vec<String>,
 }
 
+/// The commit HEAD points to, with a clear error in a repository without commits
+fn head_commit<'r>(repo: &'r Repository, action: &str) -> Result<git2::Commit<'r>> {
+    match repo.head() {
+        Ok(head) => Ok(head.peel_to_commit()?),
+        Err(e) if e.code() == git2::ErrorCode::UnbornBranch => Err(anyhow!(
+            "The repository has no commits yet; there is nothing to {action}"
+        )),
+        Err(e) => Err(e.into()),
+    }
+}
+
 /// Amend a commit with a new message
 ///
 /// # Arguments
@@ -235,7 +250,7 @@ pub fn amend_commit(
     }
 
     // Get the current HEAD commit
-    let head_commit = repo.head()?.peel_to_commit()?;
+    let head_commit = head_commit(repo, "amend")?;
 
     // Get the signature for the new commit
     let signature = repo.signature()?;
@@ -310,6 +325,62 @@ pub fn amend_commit(
         insertions,
         deletions,
         new_files,
+        root_commit: parents.is_empty(),
+    })
+}
+
+/// Rewrites the message of HEAD without touching its tree.
+///
+/// Unlike [`amend_commit`], staged changes are never folded into the commit,
+/// so a reword can be done while other work is staged.
+pub fn reword_head(repo: &Repository, message: &str, is_remote: bool) -> Result<CommitResult> {
+    if is_remote {
+        return Err(anyhow!(
+            "Cannot reword commits in a remote repository in read-only mode"
+        ));
+    }
+
+    let head_commit = head_commit(repo, "reword")?;
+    let tree = head_commit.tree()?;
+    let parents: Vec<git2::Commit> = head_commit.parents().collect();
+    let parent_refs: Vec<&git2::Commit> = parents.iter().collect();
+
+    let commit_oid = repo.commit(
+        None,
+        &head_commit.author(),
+        &repo.signature()?,
+        message,
+        &tree,
+        &parent_refs,
+    )?;
+    repo.head()?
+        .set_target(commit_oid, "reword commit message")?;
+
+    let branch_name = repo.head()?.shorthand().unwrap_or("HEAD").to_string();
+    let hash_str = commit_oid.to_string();
+    let parent_tree = parents.first().map(git2::Commit::tree).transpose()?;
+    let diff = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), None)?;
+    let stats = diff.stats()?;
+
+    let new_files = diff
+        .deltas()
+        .filter(|delta| delta.status() == git2::Delta::Added)
+        .filter_map(|delta| {
+            let file = delta.new_file();
+            file.path()
+                .and_then(|p| p.to_str())
+                .map(|path| (path.to_string(), file.mode()))
+        })
+        .collect();
+
+    Ok(CommitResult {
+        branch: branch_name,
+        commit_hash: hash_str[..hash_str.len().min(7)].to_string(),
+        files_changed: stats.files_changed(),
+        insertions: stats.insertions(),
+        deletions: stats.deletions(),
+        new_files,
+        root_commit: parents.is_empty(),
     })
 }
 
@@ -360,6 +431,7 @@ pub fn commit(repo: &Repository, message: &str, is_remote: bool) -> Result<Commi
     let tree = repo.find_tree(tree_id)?;
 
     // Handle fresh repositories (no HEAD) vs existing repositories
+    let root_commit = head_tree.is_none();
     let (commit_oid, branch_name) = if let Ok(head) = repo.head() {
         // Existing repository with HEAD
         let parent_commit = head.peel_to_commit()?;
@@ -375,7 +447,7 @@ pub fn commit(repo: &Repository, message: &str, is_remote: bool) -> Result<Commi
     } else {
         // Fresh repository - create initial commit with no parents
         let commit_oid = repo.commit(Some("HEAD"), &signature, &signature, message, &tree, &[])?;
-        (commit_oid, "main".to_string())
+        (commit_oid, unborn_branch_name(repo))
     };
 
     let commit = repo.find_commit(commit_oid)?;
@@ -389,6 +461,7 @@ pub fn commit(repo: &Repository, message: &str, is_remote: bool) -> Result<Commi
         insertions,
         deletions,
         new_files,
+        root_commit,
     })
 }
 
@@ -472,17 +545,45 @@ where
     Ok(())
 }
 
+/// The trees [`get_commit_files`] compares: the commit's first parent and the commit
+pub fn commit_diff_source(repo: &Repository, commit_id: &str) -> Result<DiffSource> {
+    let commit = repo.revparse_single(commit_id)?.peel_to_commit()?;
+    Ok(DiffSource::Commits {
+        from: commit.parent_id(0).ok().map(|id| id.to_string()),
+        to: commit.id().to_string(),
+    })
+}
+
+/// The merge base of two branches and the target's commit, which a branch diff compares
+///
+/// The merge base is the point where the target branch diverged from the base branch.
+pub fn branch_diff_commits(
+    repo: &Repository,
+    base_branch: &str,
+    target_branch: &str,
+) -> Result<(git2::Oid, git2::Oid)> {
+    let base_commit = resolve_branch(repo, base_branch)?;
+    let target_commit = repo.revparse_single(target_branch)?.peel_to_commit()?;
+    let merge_base = repo.merge_base(base_commit.id(), target_commit.id())?;
+    Ok((merge_base, target_commit.id()))
+}
+
 /// Retrieves the files changed in a specific commit
 ///
 /// # Arguments
 ///
 /// * `repo` - The git repository
 /// * `commit_id` - The ID of the commit to analyze.
+/// * `settings` - How the diffs are generated
 ///
 /// # Returns
 ///
 /// A Result containing a Vec of `StagedFile` objects for the commit or an error.
-pub fn get_commit_files(repo: &Repository, commit_id: &str) -> Result<Vec<StagedFile>> {
+pub fn get_commit_files(
+    repo: &Repository,
+    commit_id: &str,
+    settings: DiffSettings,
+) -> Result<Vec<StagedFile>> {
     debug!("Getting files for commit: {}", commit_id);
 
     // Parse the commit ID
@@ -498,7 +599,11 @@ pub fn get_commit_files(repo: &Repository, commit_id: &str) -> Result<Vec<Staged
 
     let parent_tree = parent_commit.map(|c| c.tree()).transpose()?;
 
-    let mut diff = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&commit_tree), None)?;
+    let mut diff = repo.diff_tree_to_tree(
+        parent_tree.as_ref(),
+        Some(&commit_tree),
+        Some(&mut word_diff::diff_options(settings)),
+    )?;
     let commit_files = get_files_from_diff(repo, &mut diff)?;
 
     debug!("Found {} files in commit", commit_files.len());
@@ -607,6 +712,7 @@ pub fn get_commit_date(repo: &Repository, commit_ish: &str) -> Result<String> {
 /// * `repo` - The git repository
 /// * `base_branch` - The base branch (e.g., "main")
 /// * `target_branch` - The target branch (e.g., "feature-branch")
+/// * `settings` - How the diffs are generated
 ///
 /// # Returns
 ///
@@ -615,28 +721,25 @@ pub fn get_branch_diff_files(
     repo: &Repository,
     base_branch: &str,
     target_branch: &str,
+    settings: DiffSettings,
 ) -> Result<Vec<StagedFile>> {
     debug!(
         "Getting files changed between branches: {} -> {}",
         base_branch, target_branch
     );
 
-    // Resolve branch references
-    let base_commit = resolve_branch(repo, base_branch)?;
-    let target_commit = repo.revparse_single(target_branch)?.peel_to_commit()?;
-
-    // Find the merge-base (common ancestor) between the branches
-    // This gives us the point where the target branch diverged from the base branch
-    let merge_base_oid = repo.merge_base(base_commit.id(), target_commit.id())?;
-    let merge_base_commit = repo.find_commit(merge_base_oid)?;
-
+    let (merge_base_oid, target_oid) = branch_diff_commits(repo, base_branch, target_branch)?;
     debug!("Using merge-base {} for comparison", merge_base_oid);
 
-    let base_tree = merge_base_commit.tree()?;
-    let target_tree = target_commit.tree()?;
+    let base_tree = repo.find_commit(merge_base_oid)?.tree()?;
+    let target_tree = repo.find_commit(target_oid)?.tree()?;
 
     // Create diff between the merge-base tree and target tree
-    let mut diff = repo.diff_tree_to_tree(Some(&base_tree), Some(&target_tree), None)?;
+    let mut diff = repo.diff_tree_to_tree(
+        Some(&base_tree),
+        Some(&target_tree),
+        Some(&mut word_diff::diff_options(settings)),
+    )?;
     let mut branch_files = get_files_from_diff(repo, &mut diff)?;
 
     // Get file content from target branch if it's a modified or added file
@@ -644,11 +747,8 @@ pub fn get_branch_diff_files(
         if !file.content_excluded
             && matches!(file.change_type, ChangeType::Added | ChangeType::Modified)
             && let Ok(entry) = target_tree.get_path(std::path::Path::new(&file.path))
-            && let Ok(object) = entry.to_object(repo)
-            && let Some(blob) = object.as_blob()
-            && let Ok(content) = std::str::from_utf8(blob.content())
         {
-            file.content = Some(content.to_string());
+            file.content = blob_text(repo, entry.id(), &file.change_type, settings);
         }
     }
 
@@ -697,7 +797,8 @@ pub fn extract_branch_diff_info(
     let recent_commits = recent_commits?;
 
     // Get file paths from the diff for metadata
-    let diff_files = get_branch_diff_files(repo, base_branch, target_branch)?;
+    let diff_files =
+        get_branch_diff_files(repo, base_branch, target_branch, DiffSettings::default())?;
     let file_paths: Vec<String> = diff_files.iter().map(|file| file.path.clone()).collect();
 
     Ok((display_branch, recent_commits, file_paths))
@@ -749,11 +850,17 @@ pub fn get_commits_for_pr(repo: &Repository, from: &str, to: &str) -> Result<Vec
 /// * `repo` - The git repository
 /// * `from` - The starting Git reference (exclusive)
 /// * `to` - The ending Git reference (inclusive)
+/// * `settings` - How the diffs are generated
 ///
 /// # Returns
 ///
 /// A Result containing a Vec of `StagedFile` objects for the commit range or an error.
-pub fn get_commit_range_files(repo: &Repository, from: &str, to: &str) -> Result<Vec<StagedFile>> {
+pub fn get_commit_range_files(
+    repo: &Repository,
+    from: &str,
+    to: &str,
+    settings: DiffSettings,
+) -> Result<Vec<StagedFile>> {
     debug!("Getting files changed in commit range: {} -> {}", from, to);
 
     // Resolve commit references
@@ -764,7 +871,11 @@ pub fn get_commit_range_files(repo: &Repository, from: &str, to: &str) -> Result
     let to_tree = to_commit.tree()?;
 
     // Create diff between the from and to trees
-    let mut diff = repo.diff_tree_to_tree(Some(&from_tree), Some(&to_tree), None)?;
+    let mut diff = repo.diff_tree_to_tree(
+        Some(&from_tree),
+        Some(&to_tree),
+        Some(&mut word_diff::diff_options(settings)),
+    )?;
     let mut range_files = get_files_from_diff(repo, &mut diff)?;
 
     // Get file content from to commit if it's a modified or added file
@@ -772,11 +883,8 @@ pub fn get_commit_range_files(repo: &Repository, from: &str, to: &str) -> Result
         if !file.content_excluded
             && matches!(file.change_type, ChangeType::Added | ChangeType::Modified)
             && let Ok(entry) = to_tree.get_path(std::path::Path::new(&file.path))
-            && let Ok(object) = entry.to_object(repo)
-            && let Some(blob) = object.as_blob()
-            && let Ok(content) = std::str::from_utf8(blob.content())
         {
-            file.content = Some(content.to_string());
+            file.content = blob_text(repo, entry.id(), &file.change_type, settings);
         }
     }
 
@@ -799,7 +907,7 @@ pub fn extract_commit_range_info(
     let recent_commits = recent_commits?;
 
     // Get file paths from the range for metadata
-    let range_files = get_commit_range_files(repo, from, to)?;
+    let range_files = get_commit_range_files(repo, from, to, DiffSettings::default())?;
     let file_paths: Vec<String> = range_files.iter().map(|file| file.path.clone()).collect();
 
     Ok((display_range, recent_commits, file_paths))
@@ -848,3 +956,56 @@ fn resolve_branch<'a>(repo: &'a Repository, branch_name: &str) -> Result<git2::C
         ))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::commit::format_commit_result;
+    use crate::git::get_file_statuses;
+
+    #[test]
+    fn test_initial_commit_in_empty_repository() {
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let repo = Repository::init(dir.path()).expect("Failed to init repo");
+        let mut config = repo.config().expect("Failed to open config");
+        config
+            .set_str("user.name", "Test")
+            .expect("Failed to set name");
+        config
+            .set_str("user.email", "test@example.com")
+            .expect("Failed to set email");
+        repo.set_head("refs/heads/trunk")
+            .expect("Failed to point HEAD");
+
+        let error = reword_head(&repo, "msg", false).expect_err("Nothing to reword");
+        assert!(error.to_string().contains("no commits yet"));
+
+        std::fs::write(dir.path().join("a.txt"), "a\n").expect("Failed to write file");
+        let mut index = repo.index().expect("Failed to open index");
+        index
+            .add_path(std::path::Path::new("a.txt"))
+            .expect("Failed to stage");
+        index.write().expect("Failed to write index");
+
+        let staged =
+            get_file_statuses(&repo, DiffSettings::default()).expect("Failed to read statuses");
+        assert_eq!(staged.len(), 1);
+        assert_eq!(staged[0].change_type, ChangeType::Added);
+
+        let result = commit(&repo, "Initial commit", false).expect("Failed to commit");
+        assert!(result.root_commit);
+        assert_eq!(result.branch, "trunk");
+        assert!(
+            format_commit_result(&result, "Initial commit")
+                .starts_with(&format!("[trunk (root-commit) {}]", result.commit_hash))
+        );
+
+        std::fs::write(dir.path().join("a.txt"), "b\n").expect("Failed to write file");
+        index
+            .add_path(std::path::Path::new("a.txt"))
+            .expect("Failed to stage");
+        index.write().expect("Failed to write index");
+        let result = commit(&repo, "Second", false).expect("Failed to commit");
+        assert!(!result.root_commit);
+    }
+}
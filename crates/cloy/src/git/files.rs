@@ -1,8 +1,14 @@
-use crate::git::utils::is_binary_diff;
+use crate::config::DiffSettings;
+use crate::git::utils::{detect_renames, is_binary_diff, rename_similarity};
+use crate::git::{sample, word_diff};
 use crate::llm::context::{ChangeType, RecentCommit, StagedFile};
 use anyhow::{Context, Result};
-use git2::{DiffOptions, Repository, StatusOptions};
+use git2::{
+    ApplyLocation, ApplyOptions, DiffDelta, DiffOptions, Index, IndexEntryExtendedFlag, Odb,
+    Repository, StatusOptions, StatusShow,
+};
 use log::debug;
+use std::fmt::Write as _;
 use std::fs;
 use std::path::Path;
 
@@ -15,37 +21,120 @@ pub struct RepoFilesInfo {
     pub file_paths: Vec<String>,
 }
 
+/// The change a delta makes, or `None` for unchanged and untracked entries
+fn change_type(delta: &git2::DiffDelta<'_>) -> Result<Option<ChangeType>> {
+    let from = || {
+        delta
+            .old_file()
+            .path()
+            .and_then(|p| p.to_str())
+            .map(ToString::to_string)
+    };
+    Ok(Some(match delta.status() {
+        git2::Delta::Added => ChangeType::Added,
+        git2::Delta::Modified => ChangeType::Modified,
+        git2::Delta::Deleted => ChangeType::Deleted,
+        git2::Delta::Renamed => ChangeType::Renamed {
+            from: from().context("Missing old path for rename")?,
+            // Filled in from the patch
+            similarity: 0,
+        },
+        git2::Delta::Copied => ChangeType::Copied {
+            from: from().context("Missing old path for copy")?,
+            // Filled in from the patch
+            similarity: 0,
+        },
+        _ => return Ok(None),
+    }))
+}
+
+/// Paths whose index entries differ from HEAD
+///
+/// Only the index is compared, so the worktree is never scanned and untracked
+/// directories are never walked.
+fn staged_paths(repo: &Repository) -> Result<Vec<String>> {
+    let mut options = StatusOptions::new();
+    options
+        .show(StatusShow::Index)
+        .include_untracked(false)
+        .include_ignored(false);
+    let statuses = repo.statuses(Some(&mut options))?;
+    Ok(statuses
+        .iter()
+        .filter_map(|entry| entry.path().map(str::to_string))
+        .collect())
+}
+
+/// Size of the larger side of a delta, from the object headers when the diff has none
+fn delta_size(odb: &Odb<'_>, delta: &DiffDelta<'_>) -> u64 {
+    [delta.old_file(), delta.new_file()]
+        .iter()
+        .map(|file| {
+            if file.size() > 0 || file.id().is_zero() {
+                file.size()
+            } else {
+                odb.read_header(file.id())
+                    .map_or(0, |(size, _)| u64::try_from(size).unwrap_or(u64::MAX))
+            }
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+/// Whether a file of `size` bytes is too large for its diff and content to be loaded
+fn is_large(size: u64, max_file_size: u64) -> bool {
+    max_file_size > 0 && size > max_file_size
+}
+
+/// What a file too large to load shows instead of its diff
+fn large_file_placeholder(size: u64) -> String {
+    format!("[Large file changed, {} KB]", size.div_ceil(1024))
+}
+
 /// Retrieves the status of files in the repository.
 ///
+/// The staged paths come from a status of the index alone, and only those
+/// paths are diffed. Ignored paths are checked with libgit2, which reads only
+/// the `.gitignore` files of each path's own directories, so no walk of the
+/// repository is needed. Files over `settings.max_file_size` are listed
+/// without loading their diff or content.
+///
 /// # Arguments
 ///
 /// * `repo` - The git repository
-/// * `gitignore_matcher` - The gitignore matcher for file exclusion
+/// * `settings` - Context lines, word-diff mode and size limit of the diffs
 ///
 /// # Returns
 ///
 /// A Result containing a Vec of `StagedFile` objects or an error.
-pub fn get_file_statuses(repo: &Repository) -> Result<Vec<StagedFile>> {
+pub fn get_file_statuses(repo: &Repository, settings: DiffSettings) -> Result<Vec<StagedFile>> {
     debug!("Getting file statuses");
     let mut staged_files = Vec::new();
 
+    let paths = staged_paths(repo)?;
+    if paths.is_empty() {
+        debug!("Found no staged files");
+        return Ok(staged_files);
+    }
+
     // Peel HEAD tree once
     let head_tree = match repo.head() {
         Ok(head) => Some(head.peel_to_commit()?.tree()?),
         Err(_) => None,
     };
 
-    // Get all staged changes in one diff operation (much faster than file-by-file)
-    let mut diff_options = DiffOptions::new();
+    // Exact paths let the diff skip every unchanged directory
+    let mut diff_options = word_diff::diff_options(settings);
+    diff_options.disable_pathspec_match(true);
+    for path in &paths {
+        diff_options.pathspec(path);
+    }
     let diff = repo.diff_tree_to_index(head_tree.as_ref(), None, Some(&mut diff_options))?;
 
-    // Use libgit2's built-in rename detection
-    let mut find_options = git2::DiffFindOptions::new();
-    find_options.renames(true);
-    find_options.copies(true);
-    find_options.remove_unmodified(true);
-    let mut diff = diff; // Make it mutable to detect renames
-    diff.find_similar(Some(&mut find_options))?;
+    let mut diff = diff;
+    detect_renames(&mut diff)?;
+    let index = repo.index()?;
+    let odb = repo.odb()?;
 
     for (i, delta) in diff.deltas().enumerate() {
         let path = delta
@@ -55,47 +144,41 @@ pub fn get_file_statuses(repo: &Repository) -> Result<Vec<StagedFile>> {
             .and_then(|p| p.to_str())
             .context("Could not get path")?;
 
-        let change_type = match delta.status() {
-            git2::Delta::Added => ChangeType::Added,
-            git2::Delta::Modified => ChangeType::Modified,
-            git2::Delta::Deleted => ChangeType::Deleted,
-            git2::Delta::Renamed => {
-                let from = delta
-                    .old_file()
-                    .path()
-                    .and_then(|p| p.to_str())
-                    .context("Missing old path for rename")?
-                    .to_string();
-                ChangeType::Renamed {
-                    from,
-                    // git2 0.20.x does not expose DiffDelta::similarity()
-                    similarity: 0,
-                }
-            }
-            git2::Delta::Copied => {
-                let from = delta
-                    .old_file()
-                    .path()
-                    .and_then(|p| p.to_str())
-                    .context("Missing old path for copy")?
-                    .to_string();
-                ChangeType::Copied {
-                    from,
-                    // git2 0.20.x does not expose DiffDelta::similarity()
-                    similarity: 0,
-                }
-            }
-            _ => continue,
+        let Some(mut change_type) = change_type(&delta)? else {
+            continue;
         };
 
         let should_exclude = repo.is_path_ignored(path).unwrap_or(false);
+        let size = delta_size(&odb, &delta);
+        let large = is_large(size, settings.max_file_size);
+        // New files over the sample size are streamed and sampled, not diffed
+        let sampled = if !should_exclude
+            && change_type == ChangeType::Added
+            && is_large(size, settings.sample_size)
+        {
+            sample_staged(repo, &index, path, settings.sample_size)?
+        } else {
+            None
+        };
 
         let diff_text = if should_exclude {
             String::from("[Content excluded]")
+        } else if sampled.is_some() {
+            format!(
+                "[New file, {} KB, sampled in its full content]",
+                size.div_ceil(1024)
+            )
+        } else if large {
+            large_file_placeholder(size)
         } else {
             // Create patch for this delta
             let mut file_patch = git2::Patch::from_diff(&diff, i)?
                 .ok_or_else(|| anyhow::anyhow!("Failed to get patch for {}", path))?;
+            if let ChangeType::Renamed { similarity, .. } | ChangeType::Copied { similarity, .. } =
+                &mut change_type
+            {
+                *similarity = rename_similarity(&mut file_patch);
+            }
 
             let buf = file_patch.to_buf()?;
             let text = String::from_utf8_lossy(&buf).to_string();
@@ -106,18 +189,16 @@ pub fn get_file_statuses(repo: &Repository) -> Result<Vec<StagedFile>> {
             }
         };
 
-        let content = if should_exclude
+        let content = if sampled.is_some() {
+            sampled
+        } else if should_exclude
+            || large
             || !matches!(change_type, ChangeType::Modified)
             || is_binary_diff(&diff_text)
         {
             None
         } else {
-            let path_obj = Path::new(path);
-            if path_obj.exists() {
-                Some(fs::read_to_string(path_obj)?)
-            } else {
-                None
-            }
+            staged_content(repo, &index, path)?
         };
 
         staged_files.push(StagedFile {
@@ -135,21 +216,37 @@ pub fn get_file_statuses(repo: &Repository) -> Result<Vec<StagedFile>> {
 
 /// Gets unstaged file changes from the repository
 ///
+/// Their diffs keep git's default context, so their hunks are the ones
+/// [`stage_hunks`] stages. Only the worktree is compared with the index, and
+/// untracked directories are listed without walking into them. Files over
+/// `max_file_size` bytes are listed without loading their diff or content.
+///
 /// # Returns
 ///
 /// A Result containing a Vec of `StagedFile` objects for unstaged changes or an error.
-pub fn get_unstaged_file_statuses(repo: &Repository) -> Result<Vec<StagedFile>> {
+pub fn get_unstaged_file_statuses(
+    repo: &Repository,
+    max_file_size: u64,
+) -> Result<Vec<StagedFile>> {
     debug!("Getting unstaged file statuses");
     let mut unstaged_files = Vec::new();
 
     let mut opts = StatusOptions::new();
-    opts.include_untracked(true);
+    opts.show(StatusShow::Workdir)
+        .include_untracked(true)
+        .recurse_untracked_dirs(false);
     let statuses = repo.statuses(Some(&mut opts))?;
+    let index = repo.index()?;
 
     for entry in statuses.iter() {
         let path = entry.path().context("Could not get path")?;
         let status = entry.status();
 
+        // Paths outside a sparse-checkout cone are absent on purpose, not deleted
+        if status.is_wt_deleted() && is_skip_worktree(&index, path) {
+            continue;
+        }
+
         // Look for changes in the working directory (unstaged)
         if status.is_wt_new() || status.is_wt_modified() || status.is_wt_deleted() {
             let change_type = if status.is_wt_new() {
@@ -161,23 +258,32 @@ pub fn get_unstaged_file_statuses(repo: &Repository) -> Result<Vec<StagedFile>>
             };
 
             let should_exclude = repo.is_path_ignored(path).unwrap_or(false);
+            let size = repo
+                .workdir()
+                .and_then(|workdir| fs::metadata(workdir.join(path)).ok())
+                .map_or(0, |metadata| metadata.len());
+            let large = is_large(size, max_file_size);
             let diff = if should_exclude {
                 String::from("[Content excluded]")
+            } else if large {
+                large_file_placeholder(size)
             } else {
                 get_diff_for_unstaged_file(repo, path)?
             };
 
-            let content =
-                if should_exclude || change_type != ChangeType::Modified || is_binary_diff(&diff) {
-                    None
-                } else {
-                    let path_obj = Path::new(path);
-                    if path_obj.exists() {
-                        Some(fs::read_to_string(path_obj)?)
-                    } else {
-                        None
-                    }
-                };
+            let content = if should_exclude
+                || large
+                || change_type != ChangeType::Modified
+                || is_binary_diff(&diff)
+            {
+                None
+            } else {
+                repo.workdir()
+                    .map(|workdir| workdir.join(path))
+                    .filter(|file| file.exists())
+                    .map(fs::read_to_string)
+                    .transpose()?
+            };
 
             unstaged_files.push(StagedFile {
                 path: path.to_string(),
@@ -193,6 +299,157 @@ pub fn get_unstaged_file_statuses(repo: &Repository) -> Result<Vec<StagedFile>>
     Ok(unstaged_files)
 }
 
+/// A path in a `---` or `+++` line, without timestamp and `a/` or `b/` prefix
+fn patch_path(name: &str) -> Option<&str> {
+    let name = name.split('\t').next().unwrap_or(name).trim_end();
+    if name == "/dev/null" {
+        return None;
+    }
+    Some(
+        name.strip_prefix("a/")
+            .or_else(|| name.strip_prefix("b/"))
+            .unwrap_or(name),
+    )
+}
+
+/// `patch` with the `diff --git` headers libgit2 needs, for plain unified diffs
+/// such as `diff -u`, `hg diff` and `svn diff` print
+fn with_git_headers(patch: &str) -> String {
+    if patch.lines().any(|line| line.starts_with("diff --git ")) {
+        return patch.to_string();
+    }
+    let lines: Vec<&str> = patch.lines().collect();
+    let mut out = String::with_capacity(patch.len());
+    let mut i = 0;
+    while i < lines.len() {
+        if let (Some(old), Some(new)) = (
+            lines[i].strip_prefix("--- "),
+            lines.get(i + 1).and_then(|line| line.strip_prefix("+++ ")),
+        ) && lines.get(i + 2).is_some_and(|line| line.starts_with("@@"))
+        {
+            let (old, new) = (patch_path(old), patch_path(new));
+            if let Some(path) = new.or(old) {
+                let _ = writeln!(out, "diff --git a/{path} b/{path}");
+                match (old, new) {
+                    (None, _) => out.push_str("new file mode 100644\n--- /dev/null\n"),
+                    (_, None) => out.push_str("deleted file mode 100644\n"),
+                    _ => {}
+                }
+                if old.is_some() {
+                    let _ = writeln!(out, "--- a/{path}");
+                }
+                if new.is_some() {
+                    let _ = writeln!(out, "+++ b/{path}");
+                } else {
+                    out.push_str("+++ /dev/null\n");
+                }
+                i += 2;
+                continue;
+            }
+        }
+        out.push_str(lines[i]);
+        out.push('\n');
+        i += 1;
+    }
+    out
+}
+
+/// Reads the changed files of a patch, such as `git diff --cached` prints.
+///
+/// Plain unified diffs from other tools are read too. No repository is needed, so files carry only their diffs and never their
+/// full content.
+pub fn files_from_patch(text: &str) -> Result<Vec<StagedFile>> {
+    let diff = git2::Diff::from_buffer(with_git_headers(text).as_bytes())
+        .context("Not a patch in the format git diff prints")?;
+    let mut files = Vec::new();
+    for (i, delta) in diff.deltas().enumerate() {
+        let Some(mut change_type) = change_type(&delta)? else {
+            continue;
+        };
+        let path = delta
+            .new_file()
+            .path()
+            .or_else(|| delta.old_file().path())
+            .and_then(|p| p.to_str())
+            .context("Could not get path")?;
+        let mut file_patch = git2::Patch::from_diff(&diff, i)?
+            .ok_or_else(|| anyhow::anyhow!("Failed to get patch for {}", path))?;
+        if let ChangeType::Renamed { similarity, .. } | ChangeType::Copied { similarity, .. } =
+            &mut change_type
+        {
+            *similarity = rename_similarity(&mut file_patch);
+        }
+        let text = String::from_utf8_lossy(&file_patch.to_buf()?).to_string();
+        files.push(StagedFile {
+            path: path.to_string(),
+            change_type,
+            diff: if is_binary_diff(&text) {
+                String::from("[Binary file changed]")
+            } else {
+                text
+            },
+            content: None,
+            content_excluded: false,
+        });
+    }
+    debug!("Found {} files in the patch", files.len());
+    Ok(files)
+}
+
+/// Reads the staged content of a modified file.
+///
+/// The worktree copy is used when it is checked out; paths outside a
+/// sparse-checkout cone, or otherwise missing from the worktree, are read
+/// from the index blob instead.
+fn staged_content(repo: &Repository, index: &Index, path: &str) -> Result<Option<String>> {
+    let worktree_file = repo
+        .workdir()
+        .map(|workdir| workdir.join(path))
+        .filter(|file| file.exists());
+    if let Some(file) = worktree_file.filter(|_| !is_skip_worktree(index, path)) {
+        return Ok(Some(fs::read_to_string(file)?));
+    }
+
+    let Some(entry) = index.get_path(Path::new(path), 0) else {
+        return Ok(None);
+    };
+    let blob = repo
+        .find_blob(entry.id)
+        .with_context(|| format!("Failed to read the staged blob of {path}"))?;
+    if blob.is_binary() {
+        return Ok(None);
+    }
+    Ok(Some(String::from_utf8_lossy(blob.content()).into_owned()))
+}
+
+/// A sample of a staged new file, streamed from the worktree or the staged blob
+fn sample_staged(
+    repo: &Repository,
+    index: &Index,
+    path: &str,
+    sample_size: u64,
+) -> Result<Option<String>> {
+    let budget = usize::try_from(sample_size).unwrap_or(usize::MAX);
+    let worktree_file = repo
+        .workdir()
+        .map(|workdir| workdir.join(path))
+        .filter(|file| file.exists());
+    if let Some(file) = worktree_file.filter(|_| !is_skip_worktree(index, path)) {
+        return sample::sample_file(&file, budget);
+    }
+    match index.get_path(Path::new(path), 0) {
+        Some(entry) => sample::sample_blob(repo, entry.id, budget),
+        None => Ok(None),
+    }
+}
+
+/// Whether the index marks `path` as outside the sparse-checkout cone
+fn is_skip_worktree(index: &Index, path: &str) -> bool {
+    index.get_path(Path::new(path), 0).is_some_and(|entry| {
+        entry.flags_extended & IndexEntryExtendedFlag::SKIP_WORKTREE.bits() != 0
+    })
+}
+
 /// Gets the diff for an unstaged file
 ///
 /// # Arguments
@@ -233,3 +490,375 @@ pub fn get_diff_for_unstaged_file(repo: &Repository, path: &str) -> Result<Strin
         Ok(diff_string)
     }
 }
+
+/// Adds the working tree state of a path to the index.
+///
+/// Files deleted from the working tree are removed from the index, matching
+/// `git add -A <path>`.
+pub fn stage_path(repo: &Repository, path: &str) -> Result<()> {
+    debug!("Staging {}", path);
+    let workdir = repo
+        .workdir()
+        .context("Cannot stage files in a bare repository")?;
+    let mut index = repo.index()?;
+    if workdir.join(path).exists() {
+        index.add_path(Path::new(path))?;
+    } else {
+        index.remove_path(Path::new(path))?;
+    }
+    index.write()?;
+    Ok(())
+}
+
+/// Stages only some hunks of a file's unstaged changes, like `git add -p`.
+///
+/// `hunks` are zero-based positions in the index-to-workdir diff of `path`,
+/// in the order they appear in the diff shown to the user.
+pub fn stage_hunks(repo: &Repository, path: &str, hunks: &[usize]) -> Result<()> {
+    debug!("Staging hunks {:?} of {}", hunks, path);
+    let mut diff_options = DiffOptions::new();
+    diff_options.pathspec(path);
+    let diff = repo.diff_index_to_workdir(None, Some(&mut diff_options))?;
+
+    let mut position = 0;
+    let mut apply_options = ApplyOptions::new();
+    apply_options.hunk_callback(|hunk| {
+        if hunk.is_none() {
+            return true;
+        }
+        let keep = hunks.contains(&position);
+        position += 1;
+        keep
+    });
+    repo.apply(&diff, ApplyLocation::Index, Some(&mut apply_options))
+        .with_context(|| format!("Failed to stage hunks of {path}"))
+}
+
+/// Resets the index entry of a path to HEAD, keeping the working tree.
+///
+/// Before the first commit the entry is simply dropped, matching
+/// `git rm --cached <path>`.
+pub fn unstage_path(repo: &Repository, path: &str) -> Result<()> {
+    debug!("Unstaging {}", path);
+    if let Some(head) = repo.head().ok().and_then(|h| h.peel_to_commit().ok()) {
+        repo.reset_default(Some(head.as_object()), [path])?;
+    } else {
+        let mut index = repo.index()?;
+        index.remove_path(Path::new(path))?;
+        index.write()?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plain_unified_diffs_get_git_headers() {
+        // As `hg diff` prints, with a timestamp after the name
+        let patch = "\
+diff -r 3b18e51 src/lib.rs
+--- a/src/lib.rs\tMon Jan 01 00:00:00 2024 +0000
++++ b/src/lib.rs\tMon Jan 01 00:00:01 2024 +0000
+@@ -1 +1 @@
+-old
++new
+--- /dev/null
++++ b/docs/new.md
+@@ -0,0 +1 @@
++Docs
+--- gone.txt
++++ /dev/null
+@@ -1 +0,0 @@
+-bye
+";
+        let files = files_from_patch(patch).expect("Failed to read patch");
+        let summary: Vec<(&str, &ChangeType)> = files
+            .iter()
+            .map(|file| (file.path.as_str(), &file.change_type))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("src/lib.rs", &ChangeType::Modified),
+                ("docs/new.md", &ChangeType::Added),
+                ("gone.txt", &ChangeType::Deleted),
+            ]
+        );
+        assert!(files[0].diff.contains("+new"));
+    }
+
+    #[test]
+    fn test_files_from_patch() {
+        let patch = "\
+diff --git a/src/lib.rs b/src/lib.rs
+index 3b18e51..a1b2c3d 100644
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -1,2 +1,2 @@
+-pub fn parse() {}
++pub fn parse(input: &str) {}
+ pub fn render() {}
+diff --git a/NOTES.md b/NOTES.md
+new file mode 100644
+index 0000000..e69de29
+--- /dev/null
++++ b/NOTES.md
+@@ -0,0 +1 @@
++Notes
+diff --git a/old.rs b/new.rs
+similarity index 90%
+rename from old.rs
+rename to new.rs
+";
+        let files = files_from_patch(patch).expect("Failed to read patch");
+        let summary: Vec<(&str, &ChangeType)> = files
+            .iter()
+            .map(|file| (file.path.as_str(), &file.change_type))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("src/lib.rs", &ChangeType::Modified),
+                ("NOTES.md", &ChangeType::Added),
+                (
+                    "new.rs",
+                    &ChangeType::Renamed {
+                        from: "old.rs".to_string(),
+                        similarity: 90
+                    }
+                ),
+            ]
+        );
+        assert!(files[0].diff.contains("+pub fn parse(input: &str) {}"));
+        assert!(files.iter().all(|file| file.content.is_none()));
+        assert!(files_from_patch("not a patch").is_err());
+    }
+
+    fn status_of(repo: &Repository, path: &str) -> git2::Status {
+        repo.status_file(Path::new(path))
+            .expect("Failed to read status")
+    }
+
+    #[test]
+    fn test_staged_rename_is_detected() {
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let repo = Repository::init(dir.path()).expect("Failed to init repo");
+        let original = (1..=20)
+            .map(|i| format!("line {i}"))
+            .collect::<Vec<_>>()
+            .join("\n")
+            + "\n";
+        fs::write(dir.path().join("old.txt"), &original).expect("Failed to write file");
+        stage_path(&repo, "old.txt").expect("Failed to stage");
+        let mut index = repo.index().expect("Failed to open index");
+        let tree = repo
+            .find_tree(index.write_tree().expect("Failed to write tree"))
+            .expect("Failed to find tree");
+        let sig = git2::Signature::now("Test", "test@example.com").expect("Failed to sign");
+        repo.commit(Some("HEAD"), &sig, &sig, "init", &tree, &[])
+            .expect("Failed to commit");
+
+        // Move the file and edit one line, as `git mv` followed by an edit would
+        fs::remove_file(dir.path().join("old.txt")).expect("Failed to remove file");
+        fs::write(
+            dir.path().join("new.txt"),
+            original.replace("line 20", "last line"),
+        )
+        .expect("Failed to write file");
+        stage_path(&repo, "old.txt").expect("Failed to stage deletion");
+        stage_path(&repo, "new.txt").expect("Failed to stage");
+
+        let files =
+            get_file_statuses(&repo, DiffSettings::default()).expect("Failed to read statuses");
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].path, "new.txt");
+        match &files[0].change_type {
+            ChangeType::Renamed { from, similarity } => {
+                assert_eq!(from, "old.txt");
+                assert!((80..100).contains(similarity), "similarity {similarity}");
+            }
+            other => panic!("Expected a rename, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_sparse_checkout_reads_staged_blob() {
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let repo = Repository::init(dir.path()).expect("Failed to init repo");
+        fs::write(dir.path().join("a.txt"), "one\n").expect("Failed to write file");
+        stage_path(&repo, "a.txt").expect("Failed to stage");
+        let mut index = repo.index().expect("Failed to open index");
+        let tree = repo
+            .find_tree(index.write_tree().expect("Failed to write tree"))
+            .expect("Failed to find tree");
+        let sig = git2::Signature::now("Test", "test@example.com").expect("Failed to sign");
+        repo.commit(Some("HEAD"), &sig, &sig, "init", &tree, &[])
+            .expect("Failed to commit");
+
+        // Stage an edit, then drop the file out of the cone as `git sparse-checkout` would
+        fs::write(dir.path().join("a.txt"), "two\n").expect("Failed to write file");
+        stage_path(&repo, "a.txt").expect("Failed to stage");
+        let mut index = repo.index().expect("Failed to open index");
+        let mut entry = index
+            .get_path(Path::new("a.txt"), 0)
+            .expect("Missing index entry");
+        entry.flags_extended |= IndexEntryExtendedFlag::SKIP_WORKTREE.bits();
+        index.add(&entry).expect("Failed to update entry");
+        index.write().expect("Failed to write index");
+        fs::remove_file(dir.path().join("a.txt")).expect("Failed to remove file");
+
+        let files =
+            get_file_statuses(&repo, DiffSettings::default()).expect("Failed to read statuses");
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].content.as_deref(), Some("two\n"));
+        let unstaged = get_unstaged_file_statuses(&repo, DiffSettings::default().max_file_size)
+            .expect("Failed to read statuses");
+        assert!(unstaged.is_empty(), "{unstaged:?}");
+    }
+
+    #[test]
+    fn test_large_files_are_listed_without_loading() {
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let repo = Repository::init(dir.path()).expect("Failed to init repo");
+        fs::write(dir.path().join("big.txt"), "small\n").expect("Failed to write file");
+        fs::write(dir.path().join("small.txt"), "one\n").expect("Failed to write file");
+        stage_path(&repo, "big.txt").expect("Failed to stage");
+        stage_path(&repo, "small.txt").expect("Failed to stage");
+        let mut index = repo.index().expect("Failed to open index");
+        let tree = repo
+            .find_tree(index.write_tree().expect("Failed to write tree"))
+            .expect("Failed to find tree");
+        let sig = git2::Signature::now("Test", "test@example.com").expect("Failed to sign");
+        repo.commit(Some("HEAD"), &sig, &sig, "init", &tree, &[])
+            .expect("Failed to commit");
+
+        fs::write(dir.path().join("big.txt"), "line\n".repeat(1000)).expect("Failed to write");
+        fs::write(dir.path().join("small.txt"), "two\n").expect("Failed to write file");
+        stage_path(&repo, "big.txt").expect("Failed to stage");
+        stage_path(&repo, "small.txt").expect("Failed to stage");
+        fs::create_dir_all(dir.path().join("untracked/deep")).expect("Failed to create dir");
+        fs::write(dir.path().join("untracked/deep/a.txt"), "a").expect("Failed to write file");
+
+        let settings = DiffSettings {
+            max_file_size: 1024,
+            ..DiffSettings::default()
+        };
+        let files = get_file_statuses(&repo, settings).expect("Failed to read statuses");
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].path, "big.txt");
+        assert_eq!(files[0].diff, "[Large file changed, 5 KB]");
+        assert!(files[0].content.is_none());
+        assert!(files[1].diff.contains("+two"));
+        assert_eq!(files[1].content.as_deref(), Some("two\n"));
+
+        // The whole file is diffed once the limit is lifted
+        let unlimited = DiffSettings {
+            max_file_size: 0,
+            ..DiffSettings::default()
+        };
+        let files = get_file_statuses(&repo, unlimited).expect("Failed to read statuses");
+        assert!(files[0].diff.contains("+line"));
+
+        fs::write(dir.path().join("big.txt"), "edit\n".repeat(1000)).expect("Failed to write");
+        let unstaged = get_unstaged_file_statuses(&repo, 1024).expect("Failed to read statuses");
+        let paths: Vec<&str> = unstaged.iter().map(|file| file.path.as_str()).collect();
+        // Untracked directories are listed, not walked
+        assert_eq!(paths, ["big.txt", "untracked/"]);
+        assert_eq!(unstaged[0].diff, "[Large file changed, 5 KB]");
+        assert!(unstaged[0].content.is_none());
+    }
+
+    #[test]
+    fn test_large_new_files_are_sampled() {
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let repo = Repository::init(dir.path()).expect("Failed to init repo");
+        let text = (0..2000).fold(String::new(), |mut text, i| {
+            let _ = writeln!(text, "const VALUE_{i}: u32 = {i};");
+            text
+        }) + "pub fn last() {}\n";
+        fs::write(dir.path().join("generated.rs"), &text).expect("Failed to write file");
+        stage_path(&repo, "generated.rs").expect("Failed to stage");
+
+        let settings = DiffSettings {
+            sample_size: 2048,
+            ..DiffSettings::default()
+        };
+        let files = get_file_statuses(&repo, settings).expect("Failed to read statuses");
+        assert!(
+            files[0].diff.starts_with("[New file, "),
+            "{}",
+            files[0].diff
+        );
+        let content = files[0].content.as_deref().expect("Sampled content");
+        assert!(content.len() < 2200);
+        assert!(content.starts_with("const VALUE_0: u32 = 0;\n"));
+        assert!(content.contains("lines sampled ...]"));
+        assert!(content.ends_with("pub fn last() {}\n"));
+
+        // Without sampling a new file is only diffed
+        let settings = DiffSettings {
+            sample_size: 0,
+            ..DiffSettings::default()
+        };
+        let files = get_file_statuses(&repo, settings).expect("Failed to read statuses");
+        assert!(files[0].diff.contains("+pub fn last() {}"));
+        assert!(files[0].content.is_none());
+    }
+
+    #[test]
+    fn test_stage_and_unstage_path() {
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let repo = Repository::init(dir.path()).expect("Failed to init repo");
+        fs::write(dir.path().join("a.txt"), "a").expect("Failed to write file");
+
+        // Unborn HEAD: unstaging drops the index entry
+        stage_path(&repo, "a.txt").expect("Failed to stage");
+        assert!(status_of(&repo, "a.txt").is_index_new());
+        unstage_path(&repo, "a.txt").expect("Failed to unstage");
+        assert!(status_of(&repo, "a.txt").is_wt_new());
+
+        stage_path(&repo, "a.txt").expect("Failed to stage");
+        let mut index = repo.index().expect("Failed to open index");
+        let tree = repo
+            .find_tree(index.write_tree().expect("Failed to write tree"))
+            .expect("Failed to find tree");
+        let sig = git2::Signature::now("Test", "test@example.com").expect("Failed to sign");
+        repo.commit(Some("HEAD"), &sig, &sig, "init", &tree, &[])
+            .expect("Failed to commit");
+
+        fs::write(dir.path().join("a.txt"), "b").expect("Failed to write file");
+        stage_path(&repo, "a.txt").expect("Failed to stage");
+        assert!(status_of(&repo, "a.txt").is_index_modified());
+        unstage_path(&repo, "a.txt").expect("Failed to unstage");
+        assert_eq!(status_of(&repo, "a.txt"), git2::Status::WT_MODIFIED);
+
+        // Two distant edits produce two hunks; stage only the second
+        let original = (1..=20)
+            .map(|i| format!("line {i}"))
+            .collect::<Vec<_>>()
+            .join("\n")
+            + "\n";
+        fs::write(dir.path().join("a.txt"), &original).expect("Failed to write file");
+        stage_path(&repo, "a.txt").expect("Failed to stage");
+        let edited = original
+            .replace("line 1\n", "first\n")
+            .replace("line 20\n", "last\n");
+        fs::write(dir.path().join("a.txt"), &edited).expect("Failed to write file");
+        stage_hunks(&repo, "a.txt", &[1]).expect("Failed to stage hunk");
+
+        let index = repo.index().expect("Failed to open index");
+        let entry = index
+            .get_path(Path::new("a.txt"), 0)
+            .expect("File missing from index");
+        let blob = repo.find_blob(entry.id).expect("Failed to find blob");
+        let staged = String::from_utf8_lossy(blob.content());
+        assert!(staged.starts_with("line 1\n"));
+        assert!(staged.ends_with("last\n"));
+
+        fs::remove_file(dir.path().join("a.txt")).expect("Failed to remove file");
+        stage_path(&repo, "a.txt").expect("Failed to stage deletion");
+        assert!(status_of(&repo, "a.txt").is_index_deleted());
+    }
+}
@@ -4,14 +4,48 @@
 //! - Recent commits
 //! - File-specific commit history
 //! - Author commit history
+//!
+//! Authors are compared through the repository's `.mailmap`, so commits made
+//! under an old or alternate address count as the same person's.
 
+use crate::config::ContextDepth;
 use crate::llm::context::RecentCommit;
 
 use anyhow::Result;
-use git2::Repository;
+use git2::{Commit, DiffOptions, Mailmap, Oid, Repository, Signature, Tree};
 use log::debug;
 
-use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// The repository's mailmap, from `.mailmap` and `mailmap.file`, if it can be read
+pub fn load_mailmap(repo: &Repository) -> Option<Mailmap> {
+    repo.mailmap()
+        .map_err(|e| debug!("Not using the mailmap: {e}"))
+        .ok()
+}
+
+/// The canonical, lowercased email of `name <email>`
+pub fn canonical_email(mailmap: Option<&Mailmap>, name: &str, email: &str) -> String {
+    // Signatures need a name; mailmap entries keyed by email alone still match
+    let name = if name.trim().is_empty() { email } else { name };
+    mailmap
+        .zip(Signature::now(name, email).ok())
+        .and_then(|(mailmap, signature)| mailmap.resolve_signature(&signature).ok())
+        .and_then(|signature| signature.email().map(str::to_string))
+        .unwrap_or_else(|| email.to_string())
+        .to_lowercase()
+}
+
+/// The canonical, lowercased email of a commit's author
+pub fn commit_author_email(mailmap: Option<&Mailmap>, commit: &Commit) -> String {
+    let author = match mailmap {
+        Some(mailmap) => commit
+            .author_with_mailmap(mailmap)
+            .unwrap_or_else(|_| commit.author()),
+        None => commit.author(),
+    };
+    author.email().unwrap_or_default().to_lowercase()
+}
 
 /// Retrieves recent commits from the repository.
 ///
@@ -50,17 +84,70 @@ pub fn get_recent_commits(repo: &Repository, count: usize) -> Result<Vec<RecentC
     Ok(commits)
 }
 
+/// Retrieves commits reachable from `start`, newest first, with their canonical author emails.
+///
+/// Used to rebuild the history a past commit was written against, so nothing
+/// after it leaks into a replayed context.
+pub fn get_commits_before(
+    repo: &Repository,
+    start: Oid,
+    count: usize,
+) -> Result<Vec<(RecentCommit, String)>> {
+    let mut revwalk = repo.revwalk()?;
+    revwalk.push(start)?;
+    let mailmap = load_mailmap(repo);
+
+    revwalk
+        .take(count)
+        .map(|oid| {
+            let commit = repo.find_commit(oid?)?;
+            let email = commit_author_email(mailmap.as_ref(), &commit);
+            Ok((
+                RecentCommit {
+                    hash: commit.id().to_string(),
+                    message: commit.message().map(String::from).unwrap_or_default(),
+                    timestamp: commit.time().seconds().to_string(),
+                },
+                email,
+            ))
+        })
+        .collect()
+}
+
+/// How far the search for commits touching the changed files may go
+///
+/// Files that rarely change would otherwise walk the whole history, which
+/// takes seconds on repositories with hundreds of thousands of commits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WalkLimit {
+    /// Most commits looked at
+    pub commits: usize,
+    /// Longest the walk may run
+    pub time: Duration,
+}
+
+impl Default for WalkLimit {
+    fn default() -> Self {
+        Self {
+            commits: ContextDepth::default().history_walk,
+            time: Duration::from_millis(750),
+        }
+    }
+}
+
 /// Retrieves recent commits that touched any of the specified file paths.
 ///
 /// This is more relevant than generic recent commits because it returns only
 /// commits that actually modified the files being changed, similar to
-/// `git log --follow -- <path>` but for multiple files.
+/// `git log --follow -- <path>` but for multiple files. The walk stops once
+/// `max_commits` are found or `limit` is reached, returning what it found.
 ///
 /// # Arguments
 ///
 /// * `repo` - Reference to an open git2 Repository
 /// * `file_paths` - The file paths to filter commits by
 /// * `max_commits` - Maximum number of commits to return
+/// * `limit` - How many commits, and how long, the walk may take
 ///
 /// # Returns
 ///
@@ -69,13 +156,14 @@ pub fn get_commits_for_files(
     repo: &Repository,
     file_paths: &[String],
     max_commits: usize,
+    limit: WalkLimit,
 ) -> Result<Vec<RecentCommit>> {
     debug!(
         "Fetching up to {max_commits} commits for {} files",
         file_paths.len()
     );
 
-    if file_paths.is_empty() {
+    if file_paths.is_empty() || max_commits == 0 {
         debug!("No files specified, returning empty commits");
         return Ok(Vec::new());
     }
@@ -88,68 +176,68 @@ pub fn get_commits_for_files(
         return Ok(Vec::new());
     }
 
-    let mut relevant_commits = Vec::new();
+    // Exact paths let the tree diff skip every other directory
+    let mut options = DiffOptions::new();
+    options.disable_pathspec_match(true);
+    for path in file_paths {
+        options.pathspec(path);
+    }
 
-    // Process commits until we have enough or run out
-    for oid_result in revwalk {
-        if relevant_commits.len() >= max_commits {
-            break;
-        }
+    let started = Instant::now();
+    let mut relevant_commits = Vec::new();
+    let mut scanned = 0;
 
+    for oid_result in revwalk.take(limit.commits) {
         let oid = oid_result?;
         let commit = repo.find_commit(oid)?;
+        scanned += 1;
 
-        if commit_touches_files(repo, &commit, file_paths)? {
+        if commit_touches_files(repo, &commit, &mut options)? {
             relevant_commits.push(RecentCommit {
                 hash: oid.to_string(),
                 message: commit.message().map(String::from).unwrap_or_default(),
                 timestamp: commit.time().seconds().to_string(),
             });
+            if relevant_commits.len() >= max_commits {
+                break;
+            }
+        }
+
+        if started.elapsed() >= limit.time {
+            debug!("Stopped looking for file history after {scanned} commits");
+            break;
         }
     }
 
     debug!(
-        "Found {} commits that touched the specified files",
+        "Found {} commits that touched the specified files in {scanned} commits",
         relevant_commits.len()
     );
     Ok(relevant_commits)
 }
 
-/// Checks if a commit touches any files in the given file set
+/// Checks if a commit touches any of the files in the diff options' pathspec
 fn commit_touches_files(
-    _repo: &Repository,
-    commit: &git2::Commit,
-    file_paths: &[String],
+    repo: &Repository,
+    commit: &Commit,
+    options: &mut DiffOptions,
 ) -> Result<bool> {
-    let commit_tree = commit.tree()?;
     let parent_tree = if commit.parent_count() > 0 {
         Some(commit.parent(0)?.tree()?)
     } else {
         None
     };
-
-    for path_str in file_paths {
-        let path = Path::new(path_str);
-
-        // Get the entry at this path in the current commit
-        let entry_oid = commit_tree.get_path(path).map(|e| e.id()).ok();
-
-        // Get the entry at this path in the parent commit
-        let parent_entry_oid = match &parent_tree {
-            Some(t) => t.get_path(path).map(|e| e.id()).ok(),
-            None => None,
-        };
-
-        // If the OIDs are different, the file has changed (or was added/deleted)
-        if entry_oid != parent_entry_oid {
-            return Ok(true);
-        }
+    // Merges and empty commits that leave the tree as it was touch nothing
+    if parent_tree.as_ref().map(Tree::id) == Some(commit.tree_id()) {
+        return Ok(false);
     }
 
-    Ok(false)
+    let commit_tree = commit.tree()?;
+    let diff = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&commit_tree), Some(options))?;
+    Ok(diff.deltas().len() > 0)
 }
 
-/// Retrieves the author's recent commit messages.
+/// Retrieves the author's recent commit messages, under any of their mailmap identities.
 ///
 /// # Arguments
 ///
@@ -174,15 +262,16 @@ pub fn get_author_commit_history(
         return Ok(Vec::new());
     }
 
+    let mailmap = load_mailmap(repo);
+    let canonical = canonical_email(mailmap.as_ref(), "", author_email);
     let mut commit_messages = Vec::new();
 
     for oid_result in revwalk.take(count) {
         let oid = oid_result?;
         let commit = repo.find_commit(oid)?;
-        let author = commit.author();
 
-        // Filter by author email
-        if author.email() == Some(author_email) {
+        // Filter by the author's canonical email
+        if commit_author_email(mailmap.as_ref(), &commit) == canonical {
             let message = commit.message().map(String::from).unwrap_or_default();
             commit_messages.push(message);
         }
@@ -194,3 +283,222 @@ pub fn get_author_commit_history(
     );
     Ok(commit_messages)
 }
+
+/// The latest of the author's commits among the last `count` reachable from HEAD
+pub fn latest_author_commit(
+    repo: &Repository,
+    author_email: &str,
+    count: usize,
+) -> Result<Option<Oid>> {
+    let mut revwalk = repo.revwalk()?;
+    if revwalk.push_head().is_err() {
+        return Ok(None);
+    }
+
+    let mailmap = load_mailmap(repo);
+    let canonical = canonical_email(mailmap.as_ref(), "", author_email);
+    for oid_result in revwalk.take(count) {
+        let oid = oid_result?;
+        if commit_author_email(mailmap.as_ref(), &repo.find_commit(oid)?) == canonical {
+            return Ok(Some(oid));
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn commit_as(repo: &Repository, email: &str, message: &str) {
+        let signature = Signature::now("Ada", email).expect("Valid signature");
+        let tree = {
+            let mut index = repo.index().expect("Failed to open index");
+            repo.find_tree(index.write_tree().expect("Failed to write tree"))
+                .expect("Failed to find tree")
+        };
+        let parent = repo.head().ok().and_then(|head| head.peel_to_commit().ok());
+        let parents: Vec<&Commit> = parent.iter().collect();
+        repo.commit(
+            Some("HEAD"),
+            &signature,
+            &signature,
+            message,
+            &tree,
+            &parents,
+        )
+        .expect("Failed to commit");
+    }
+
+    fn commit_file(repo: &Repository, path: &str, contents: &str) {
+        let workdir = repo.workdir().expect("Repo has a workdir");
+        fs::write(workdir.join(path), contents).expect("Failed to write file");
+        let mut index = repo.index().expect("Failed to open index");
+        index
+            .add_path(std::path::Path::new(path))
+            .expect("Failed to stage file");
+        index.write().expect("Failed to write index");
+        commit_as(repo, "ada@example.com", &format!("Change {path}"));
+    }
+
+    #[test]
+    fn test_commits_for_files_stop_at_the_limits() {
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let repo = Repository::init(dir.path()).expect("Failed to init repo");
+        commit_file(&repo, "old.rs", "1");
+        for i in 0..5 {
+            commit_file(&repo, "busy.rs", &i.to_string());
+        }
+        commit_as(&repo, "ada@example.com", "Empty");
+        commit_file(&repo, "old.rs", "2");
+
+        let files = ["old.rs".to_string()];
+        let commits = get_commits_for_files(&repo, &files, 10, WalkLimit::default())
+            .expect("Failed to read history");
+        assert_eq!(commits.len(), 2);
+        assert!(commits.iter().all(|c| c.message == "Change old.rs"));
+
+        let first = get_commits_for_files(&repo, &files, 1, WalkLimit::default())
+            .expect("Failed to read history");
+        assert_eq!(first.len(), 1);
+
+        // The first commit is out of reach of a short walk
+        let limit = WalkLimit {
+            commits: 7,
+            ..WalkLimit::default()
+        };
+        let commits =
+            get_commits_for_files(&repo, &files, 10, limit).expect("Failed to read history");
+        assert_eq!(commits.len(), 1);
+
+        let limit = WalkLimit {
+            time: Duration::ZERO,
+            ..WalkLimit::default()
+        };
+        let commits =
+            get_commits_for_files(&repo, &files, 10, limit).expect("Failed to read history");
+        assert!(commits.len() <= 1);
+    }
+
+    #[test]
+    #[ignore = "Builds a 100k-commit history; run with --ignored --nocapture to time the walk"]
+    fn bench_commits_for_files_on_deep_history() {
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let repo = Repository::init(dir.path()).expect("Failed to init repo");
+        let signature = Signature::now("Ada", "ada@example.com").expect("Valid signature");
+        let mut parent: Option<Oid> = None;
+        for i in 0..100_000 {
+            let mut builder = repo.treebuilder(None).expect("Failed to build tree");
+            let blob = repo
+                .blob(i.to_string().as_bytes())
+                .expect("Failed to write blob");
+            builder
+                .insert("busy.rs", blob, 0o100_644)
+                .expect("Failed to add file");
+            if i == 0 {
+                builder
+                    .insert("rare.rs", blob, 0o100_644)
+                    .expect("Failed to add file");
+            } else if let Some(rare) = parent
+                .and_then(|oid| repo.find_commit(oid).ok())
+                .and_then(|c| c.tree().ok())
+                .and_then(|t| t.get_name("rare.rs").map(|e| e.id()))
+            {
+                builder
+                    .insert("rare.rs", rare, 0o100_644)
+                    .expect("Failed to add file");
+            }
+            let tree = repo
+                .find_tree(builder.write().expect("Failed to write tree"))
+                .expect("Failed to find tree");
+            let parents: Vec<Commit> = parent
+                .iter()
+                .map(|oid| repo.find_commit(*oid).expect("Failed to find parent"))
+                .collect();
+            let parents: Vec<&Commit> = parents.iter().collect();
+            parent = Some(
+                repo.commit(
+                    Some("HEAD"),
+                    &signature,
+                    &signature,
+                    &format!("Commit {i}"),
+                    &tree,
+                    &parents,
+                )
+                .expect("Failed to commit"),
+            );
+        }
+
+        for (name, limit) in [
+            ("default limits", WalkLimit::default()),
+            (
+                "whole history",
+                WalkLimit {
+                    commits: usize::MAX,
+                    time: Duration::MAX,
+                },
+            ),
+        ] {
+            for file in ["busy.rs", "rare.rs"] {
+                let started = Instant::now();
+                let commits = get_commits_for_files(&repo, &[file.to_string()], 10, limit)
+                    .expect("Failed to read history");
+                println!(
+                    "{file}, {name}: {} commits in {:?}",
+                    commits.len(),
+                    started.elapsed()
+                );
+            }
+        }
+        let started = Instant::now();
+        get_commits_for_files(&repo, &["rare.rs".to_string()], 10, WalkLimit::default())
+            .expect("Failed to read history");
+        assert!(started.elapsed() < WalkLimit::default().time * 2);
+    }
+
+    #[test]
+    fn test_author_history_spans_mailmap_identities() {
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let repo = Repository::init(dir.path()).expect("Failed to init repo");
+        commit_as(&repo, "ada@old.example", "Old address");
+        commit_as(&repo, "grace@example.com", "Someone else");
+        commit_as(&repo, "Ada@New.example", "New address");
+
+        let history = get_author_commit_history(&repo, "ada@new.example", 10)
+            .expect("Failed to read history");
+        assert_eq!(history, ["New address"]);
+
+        fs::write(
+            dir.path().join(".mailmap"),
+            "Ada <ada@new.example> <ada@old.example>\n",
+        )
+        .expect("Failed to write mailmap");
+        let history = get_author_commit_history(&repo, "ada@new.example", 10)
+            .expect("Failed to read history");
+        assert_eq!(history, ["New address", "Old address"]);
+        // The old address resolves to the same person
+        let history = get_author_commit_history(&repo, "ada@old.example", 10)
+            .expect("Failed to read history");
+        assert_eq!(history.len(), 2);
+    }
+
+    #[test]
+    fn test_latest_author_commit_ignores_other_authors() {
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let repo = Repository::init(dir.path()).expect("Failed to init repo");
+        assert_eq!(
+            latest_author_commit(&repo, "ada@example.com", 10).expect("Failed to walk"),
+            None
+        );
+
+        commit_as(&repo, "ada@example.com", "Mine");
+        let mine = repo.head().ok().and_then(|head| head.target());
+        commit_as(&repo, "grace@example.com", "Someone else");
+
+        assert_eq!(
+            latest_author_commit(&repo, "ada@example.com", 10).expect("Failed to walk"),
+            mine
+        );
+    }
+}
@@ -5,9 +5,22 @@
 use anyhow::{Context as AnyhowContext, Result, anyhow};
 use git2::Repository;
 use log::debug;
-use std::path::Path;
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
 
+/// Marker line identifying the `prepare-commit-msg` hook gitai installs
+const MESSAGE_HOOK_MARKER: &str = "# Installed by gitai";
+
+/// Fills in the message for a plain `git commit`; messages given with `-m`,
+/// templates, merges, squashes and amends are left alone, as is any failure
+const MESSAGE_HOOK: &str = r#"#!/bin/sh
+# Installed by gitai
+[ -n "$2" ] && exit 0
+message=$(git-message --ci) || exit 0
+{ printf '%s\n' "$message"; cat "$1"; } > "$1.gitai" && mv "$1.gitai" "$1"
+"#;
+
 /// Executes a Git hook from the given repository.
 ///
 /// # Arguments
@@ -86,3 +99,60 @@ fn execute_hook_file(hook_path: &Path, repo: &Repository, hook_name: &str) -> Re
         ))
     }
 }
+
+/// Path of the `prepare-commit-msg` hook in a repository
+fn message_hook_path(repo: &Repository) -> PathBuf {
+    repo.path().join("hooks").join("prepare-commit-msg")
+}
+
+/// Whether the `prepare-commit-msg` hook gitai installs is in place
+pub fn message_hook_installed(repo: &Repository) -> bool {
+    fs::read_to_string(message_hook_path(repo))
+        .is_ok_and(|script| script.contains(MESSAGE_HOOK_MARKER))
+}
+
+/// Installs a `prepare-commit-msg` hook that drafts messages with `git-message`.
+///
+/// An existing hook that gitai did not install is never overwritten.
+pub fn install_message_hook(repo: &Repository) -> Result<PathBuf> {
+    let path = message_hook_path(repo);
+    if path.exists() && !message_hook_installed(repo) {
+        return Err(anyhow!(
+            "{} already exists; add `git-message --ci` to it by hand",
+            path.display()
+        ));
+    }
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+    fs::write(&path, MESSAGE_HOOK)
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755))
+            .with_context(|| format!("Failed to make {} executable", path.display()))?;
+    }
+    debug!("Installed prepare-commit-msg hook at {}", path.display());
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_install_message_hook() {
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let repo = Repository::init(dir.path()).expect("Failed to init repo");
+        assert!(!message_hook_installed(&repo));
+
+        let path = install_message_hook(&repo).expect("Failed to install hook");
+        assert!(message_hook_installed(&repo));
+        // Reinstalling over our own hook is fine, a foreign hook is kept
+        install_message_hook(&repo).expect("Failed to reinstall hook");
+        fs::write(&path, "#!/bin/sh\nexit 0\n").expect("Failed to write hook");
+        assert!(install_message_hook(&repo).is_err());
+    }
+}
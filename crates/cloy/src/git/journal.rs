@@ -0,0 +1,187 @@
+use anyhow::{Context, Result, anyhow};
+use git2::{Oid, Repository, ResetType};
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+/// Number of entries kept in the journal
+const MAX_JOURNAL_ENTRIES: usize = 50;
+
+/// A commit created by gitai, as recorded in `.git/gitai/journal.jsonl`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct JournalEntry {
+    /// Commit gitai created
+    pub commit: String,
+    /// HEAD before the commit; `None` for the initial commit
+    pub previous_head: Option<String>,
+    /// `commit`, `amend` or `reword`
+    pub kind: String,
+    pub timestamp: i64,
+}
+
+/// Outcome of undoing a gitai commit
+#[derive(Debug, Clone)]
+pub struct UndoResult {
+    pub undone: String,
+    pub restored: String,
+    pub subject: String,
+}
+
+fn journal_path(repo: &Repository) -> PathBuf {
+    repo.path().join("gitai").join("journal.jsonl")
+}
+
+fn read_entries(repo: &Repository) -> Vec<JournalEntry> {
+    fs::read_to_string(journal_path(repo))
+        .map(|raw| {
+            raw.lines()
+                .filter_map(|line| serde_json::from_str(line).ok())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn write_entries(repo: &Repository, entries: &[JournalEntry]) -> Result<()> {
+    let path = journal_path(repo);
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut content = String::new();
+    for entry in entries {
+        content.push_str(&serde_json::to_string(entry)?);
+        content.push('\n');
+    }
+    fs::write(&path, content).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Returns the current HEAD commit, if any
+pub fn head_oid(repo: &Repository) -> Option<Oid> {
+    repo.head().ok().and_then(|h| h.target())
+}
+
+/// Records that gitai moved HEAD from `previous_head` to the current HEAD.
+///
+/// Journaling never fails the commit it describes; errors are only logged.
+pub fn record(repo: &Repository, previous_head: Option<Oid>, kind: &str) {
+    let Some(head) = head_oid(repo) else {
+        return;
+    };
+
+    let mut entries = read_entries(repo);
+    entries.push(JournalEntry {
+        commit: head.to_string(),
+        previous_head: previous_head.map(|oid| oid.to_string()),
+        kind: kind.to_string(),
+        timestamp: chrono::Utc::now().timestamp(),
+    });
+    let excess = entries.len().saturating_sub(MAX_JOURNAL_ENTRIES);
+    entries.drain(..excess);
+
+    if let Err(e) = write_entries(repo, &entries) {
+        debug!("Failed to record commit in journal: {e}");
+    }
+}
+
+/// Soft-resets the last commit made by gitai, keeping its changes staged.
+///
+/// Refuses to act unless HEAD is exactly the journaled commit, so commits made
+/// outside gitai (or after a rebase) are never discarded.
+pub fn undo_last(repo: &Repository) -> Result<UndoResult> {
+    let mut entries = read_entries(repo);
+    let entry = entries
+        .pop()
+        .ok_or_else(|| anyhow!("No commit made by gitai is recorded in this repository"))?;
+
+    let head = head_oid(repo).ok_or_else(|| anyhow!("Repository has no HEAD commit"))?;
+    if head.to_string() != entry.commit {
+        return Err(anyhow!(
+            "HEAD ({}) is not the last commit made by gitai ({}); refusing to undo",
+            short(&head.to_string()),
+            short(&entry.commit)
+        ));
+    }
+
+    let previous = entry
+        .previous_head
+        .as_deref()
+        .ok_or_else(|| anyhow!("Cannot undo the initial commit of a repository"))?;
+    let target = repo.find_object(Oid::from_str(previous)?, None)?;
+    let subject = repo
+        .find_commit(head)?
+        .summary()
+        .unwrap_or_default()
+        .to_string();
+
+    repo.reset(&target, ResetType::Soft, None)?;
+    write_entries(repo, &entries)?;
+
+    Ok(UndoResult {
+        undone: short(&entry.commit).to_string(),
+        restored: short(previous).to_string(),
+        subject,
+    })
+}
+
+fn short(hash: &str) -> &str {
+    &hash[..hash.len().min(7)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn commit_file(repo: &Repository, name: &str, parent: Option<Oid>) -> Oid {
+        let workdir = repo.workdir().expect("Repository should have a workdir");
+        fs::write(workdir.join(name), name).expect("Failed to write file");
+        let mut index = repo.index().expect("Failed to open index");
+        index.add_path(Path::new(name)).expect("Failed to add file");
+        index.write().expect("Failed to write index");
+        let tree = repo
+            .find_tree(index.write_tree().expect("Failed to write tree"))
+            .expect("Failed to find tree");
+        let sig = git2::Signature::now("Test", "test@example.com").expect("Failed to sign");
+        let parents: Vec<git2::Commit> = parent
+            .map(|p| repo.find_commit(p).expect("Failed to find parent"))
+            .into_iter()
+            .collect();
+        let parent_refs: Vec<&git2::Commit> = parents.iter().collect();
+        repo.commit(Some("HEAD"), &sig, &sig, name, &tree, &parent_refs)
+            .expect("Failed to commit")
+    }
+
+    #[test]
+    fn test_undo_restores_previous_head_and_keeps_changes_staged() {
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let repo = Repository::init(dir.path()).expect("Failed to init repo");
+
+        let first = commit_file(&repo, "a.txt", None);
+        let second = commit_file(&repo, "b.txt", Some(first));
+        record(&repo, Some(first), "commit");
+
+        let result = undo_last(&repo).expect("Undo should succeed");
+        assert_eq!(result.undone, short(&second.to_string()));
+        assert_eq!(head_oid(&repo), Some(first));
+
+        let index = repo.index().expect("Failed to open index");
+        assert!(index.get_path(Path::new("b.txt"), 0).is_some());
+
+        // The entry was consumed, so a second undo has nothing to act on
+        assert!(undo_last(&repo).is_err());
+    }
+
+    #[test]
+    fn test_undo_refuses_when_head_moved() {
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let repo = Repository::init(dir.path()).expect("Failed to init repo");
+
+        let first = commit_file(&repo, "a.txt", None);
+        let second = commit_file(&repo, "b.txt", Some(first));
+        record(&repo, Some(first), "commit");
+        commit_file(&repo, "c.txt", Some(second));
+
+        assert!(undo_last(&repo).is_err());
+        assert_ne!(head_oid(&repo), Some(first));
+    }
+}
@@ -1,20 +1,43 @@
 // Git module providing functionality for Git repository operations
 
+mod clone;
+mod codeowners;
 #[allow(clippy::uninlined_format_args)]
 mod commit;
 #[allow(clippy::uninlined_format_args)]
 mod files;
 mod history;
 mod hooks;
+mod journal;
+mod project;
+mod related;
+mod remote;
 #[allow(clippy::uninlined_format_args)]
 mod repository;
+mod reviewers;
+mod sample;
 mod utils;
+mod vcs;
+mod word_diff;
+mod workspace;
 
 // Re-export primary types for public use
+pub(crate) use codeowners::pattern_regex;
+pub use codeowners::{CodeOwners, areas_by_owner, owners_for_range, owners_for_staged};
 pub use commit::CommitInfo;
 pub use commit::CommitResult;
+pub use history::{canonical_email, load_mailmap};
+pub use hooks::{install_message_hook, message_hook_installed};
+pub use journal::UndoResult;
+pub use project::ProjectMetadata;
+pub(crate) use related::MAX_RELATED_FILES;
+pub use remote::fetch_options;
 pub use repository::GhostRefManager;
 pub use repository::GitRepo;
+pub use reviewers::{ReviewerSuggestion, reviewers_for_range, reviewers_for_staged};
+pub use vcs::{Frontend, WorkingCopy};
+pub use word_diff::render_word_diffs;
+pub use workspace::{Package, workspace_packages};
 
 // Re-export utility functions
 pub use utils::*;
@@ -22,4 +45,4 @@ pub use utils::*;
 // Re-export type aliases to maintain backward compatibility
 pub use crate::llm::context::{RecentCommit, StagedFile};
 pub use files::RepoFilesInfo;
-pub use files::get_file_statuses;
+pub use files::{files_from_patch, get_file_statuses};
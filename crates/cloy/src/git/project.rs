@@ -0,0 +1,417 @@
+//! A short overview of the project, for the prompt.
+//!
+//! A diff says what changed but not what the project is. Build files at the
+//! root name the languages, the build systems, and through their dependencies
+//! the frameworks. The directories holding most files are the main modules,
+//! and the README's first paragraph says what the project is for. Everything
+//! is read from the index, like related files, and the overview is capped at
+//! [`MAX_OVERVIEW_TOKENS`] so it never crowds out the diff.
+//!
+//! The overview is kept in `.git/gitai/brief.json` with the blob ids of the
+//! build files and READMEs it was read from, and only read again once one of
+//! them changes.
+
+use crate::llm::context::estimate_tokens;
+use git2::{Index, Repository};
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Token budget of the whole overview
+pub const MAX_OVERVIEW_TOKENS: usize = 150;
+
+/// Build files at the root and the language each stands for
+const BUILD_FILES: &[(&str, &str)] = &[
+    ("Cargo.toml", "Rust"),
+    ("package.json", "JavaScript"),
+    ("tsconfig.json", "TypeScript"),
+    ("pyproject.toml", "Python"),
+    ("setup.py", "Python"),
+    ("requirements.txt", "Python"),
+    ("go.mod", "Go"),
+    ("pom.xml", "Java"),
+    ("build.gradle", "Java"),
+    ("build.gradle.kts", "Kotlin"),
+    ("Gemfile", "Ruby"),
+    ("composer.json", "PHP"),
+    ("mix.exs", "Elixir"),
+    ("Package.swift", "Swift"),
+    ("pubspec.yaml", "Dart"),
+    ("CMakeLists.txt", "C/C++"),
+];
+
+/// Files at the root that name a build system
+const BUILD_SYSTEMS: &[(&str, &str)] = &[
+    ("Cargo.toml", "Cargo"),
+    ("pnpm-lock.yaml", "pnpm"),
+    ("yarn.lock", "Yarn"),
+    ("package-lock.json", "npm"),
+    ("pyproject.toml", "pyproject"),
+    ("setup.py", "setuptools"),
+    ("go.mod", "Go modules"),
+    ("pom.xml", "Maven"),
+    ("build.gradle", "Gradle"),
+    ("build.gradle.kts", "Gradle"),
+    ("CMakeLists.txt", "CMake"),
+    ("meson.build", "Meson"),
+    ("MODULE.bazel", "Bazel"),
+    ("WORKSPACE", "Bazel"),
+    ("Makefile", "Make"),
+    ("mix.exs", "Mix"),
+    ("Package.swift", "SwiftPM"),
+];
+
+/// Directories whose subdirectories are the modules, as in workspaces
+const MODULE_CONTAINERS: &[&str] = &[
+    "crates", "packages", "apps", "libs", "modules", "services", "cmd", "plugins",
+];
+
+/// Modules listed in the overview
+const MAX_MODULES: usize = 8;
+
+/// Dependency names, as they appear in build files, and the framework they stand for
+const FRAMEWORKS: &[(&str, &str)] = &[
+    ("tokio", "Tokio"),
+    ("axum", "Axum"),
+    ("actix-web", "Actix Web"),
+    ("rocket", "Rocket"),
+    ("bevy", "Bevy"),
+    ("tauri", "Tauri"),
+    ("ratatui", "Ratatui"),
+    ("clap", "clap"),
+    ("react", "React"),
+    ("next", "Next.js"),
+    ("vue", "Vue"),
+    ("svelte", "Svelte"),
+    ("@angular/core", "Angular"),
+    ("express", "Express"),
+    ("@nestjs/core", "NestJS"),
+    ("electron", "Electron"),
+    ("django", "Django"),
+    ("flask", "Flask"),
+    ("fastapi", "FastAPI"),
+    ("torch", "PyTorch"),
+    ("tensorflow", "TensorFlow"),
+    ("gin-gonic/gin", "Gin"),
+    ("labstack/echo", "Echo"),
+    ("gofiber/fiber", "Fiber"),
+    ("springframework", "Spring"),
+    ("rails", "Rails"),
+    ("laravel/framework", "Laravel"),
+    ("phoenix", "Phoenix"),
+    ("flutter", "Flutter"),
+];
+
+/// README names, most common first
+const READMES: &[&str] = &[
+    "README.md",
+    "README",
+    "README.rst",
+    "README.txt",
+    "readme.md",
+];
+
+/// Languages, frameworks, build systems, main modules and purpose of the project
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct ProjectMetadata {
+    pub languages: Vec<String>,
+    pub frameworks: Vec<String>,
+    pub build_systems: Vec<String>,
+    /// Directories holding the most files, largest first
+    pub modules: Vec<String>,
+    /// First paragraph of the README
+    pub summary: Option<String>,
+}
+
+/// The persisted overview and the files it was read from
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct Brief {
+    /// `path:blob id` of each build file and README in the index
+    sources: Vec<String>,
+    metadata: Option<ProjectMetadata>,
+}
+
+fn brief_path(repo: &Repository) -> PathBuf {
+    repo.path().join("gitai").join("brief.json")
+}
+
+/// The build files and READMEs in the index, with their blob ids
+fn brief_sources(index: &Index) -> Vec<String> {
+    BUILD_FILES
+        .iter()
+        .map(|(file, _)| *file)
+        .chain(BUILD_SYSTEMS.iter().map(|(file, _)| *file))
+        .chain(READMES.iter().copied())
+        .filter_map(|file| {
+            let entry = index.get_path(Path::new(file), 0)?;
+            Some(format!("{file}:{}", entry.id))
+        })
+        .collect()
+}
+
+impl ProjectMetadata {
+    /// The overview kept for the repository, read again only when a build file
+    /// or the README changed; `None` when nothing describes the project
+    pub fn brief(repo: &Repository) -> Option<Self> {
+        let sources = brief_sources(&repo.index().ok()?);
+        let path = brief_path(repo);
+        let cached = std::fs::read(&path)
+            .ok()
+            .and_then(|raw| serde_json::from_slice::<Brief>(&raw).ok());
+        if let Some(brief) = cached
+            && brief.sources == sources
+        {
+            return brief.metadata;
+        }
+
+        let metadata = Self::detect(repo);
+        let brief = Brief {
+            sources,
+            metadata: metadata.clone(),
+        };
+        let written = path
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|()| std::fs::write(&path, serde_json::to_vec_pretty(&brief)?));
+        if let Err(e) = written {
+            debug!("Failed to save the project brief: {e}");
+        }
+        metadata
+    }
+
+    /// Reads the overview from the index; `None` when nothing describes the project
+    pub fn detect(repo: &Repository) -> Option<Self> {
+        let index = repo.index().ok()?;
+        let read = |path: &str| {
+            let entry = index.get_path(Path::new(path), 0)?;
+            let blob = repo.find_blob(entry.id).ok()?;
+            (!blob.is_binary()).then(|| String::from_utf8_lossy(blob.content()).into_owned())
+        };
+
+        let mut metadata = Self::default();
+        for (file, language) in BUILD_FILES {
+            let Some(text) = read(file) else {
+                continue;
+            };
+            push_unique(&mut metadata.languages, language);
+            for framework in frameworks_in(&text) {
+                push_unique(&mut metadata.frameworks, framework);
+            }
+        }
+        for (file, build_system) in BUILD_SYSTEMS {
+            if index.get_path(Path::new(file), 0).is_some() {
+                push_unique(&mut metadata.build_systems, build_system);
+            }
+        }
+        metadata.modules = main_modules(
+            index
+                .iter()
+                .map(|entry| String::from_utf8_lossy(&entry.path).into_owned()),
+        );
+        metadata.summary = READMES
+            .iter()
+            .find_map(|name| read(name))
+            .and_then(|readme| first_paragraph(&readme));
+
+        debug!("Project metadata: {metadata:?}");
+        (metadata != Self::default()).then_some(metadata)
+    }
+
+    /// The overview as prompt lines, within [`MAX_OVERVIEW_TOKENS`]
+    pub fn overview(&self) -> String {
+        let mut lines = Vec::new();
+        if !self.languages.is_empty() {
+            lines.push(format!("Languages: {}", self.languages.join(", ")));
+        }
+        if !self.frameworks.is_empty() {
+            lines.push(format!("Frameworks: {}", self.frameworks.join(", ")));
+        }
+        if !self.build_systems.is_empty() {
+            lines.push(format!("Build: {}", self.build_systems.join(", ")));
+        }
+        if !self.modules.is_empty() {
+            lines.push(format!("Main modules: {}", self.modules.join(", ")));
+        }
+        let used: usize = lines.iter().map(|line| estimate_tokens(line) + 1).sum();
+        if let Some(summary) = &self.summary {
+            let budget = MAX_OVERVIEW_TOKENS.saturating_sub(used) * 4;
+            let summary = truncate_words(summary, budget);
+            if !summary.is_empty() {
+                lines.push(summary);
+            }
+        }
+        lines.join("\n")
+    }
+}
+
+fn push_unique(list: &mut Vec<String>, item: &str) {
+    if !list.iter().any(|existing| existing == item) {
+        list.push(item.to_string());
+    }
+}
+
+/// The directories holding the most of `paths`, counting each subdirectory
+/// of a workspace directory such as `crates/` as a module of its own
+fn main_modules(paths: impl Iterator<Item = String>) -> Vec<String> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for path in paths {
+        let mut parts = path.split('/');
+        let (Some(first), Some(second)) = (parts.next(), parts.next()) else {
+            continue;
+        };
+        if first.starts_with('.') {
+            continue;
+        }
+        let module = if MODULE_CONTAINERS.contains(&first) && parts.next().is_some() {
+            format!("{first}/{second}")
+        } else {
+            first.to_string()
+        };
+        *counts.entry(module).or_default() += 1;
+    }
+    let mut modules: Vec<(String, usize)> = counts.into_iter().collect();
+    modules.sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then_with(|| a.cmp(b)));
+    modules
+        .into_iter()
+        .take(MAX_MODULES)
+        .map(|(module, _)| module)
+        .collect()
+}
+
+/// The frameworks whose dependency names appear in a build file
+fn frameworks_in(text: &str) -> impl Iterator<Item = &'static str> {
+    let tokens: Vec<&str> = text
+        .split(|c: char| !(c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '@' | '/')))
+        .filter(|token| !token.is_empty())
+        .collect();
+    FRAMEWORKS
+        .iter()
+        .filter(move |(dependency, _)| {
+            tokens.iter().any(|token| {
+                // Go modules are full paths such as `github.com/gin-gonic/gin`
+                token == dependency
+                    || token
+                        .strip_suffix(dependency)
+                        .is_some_and(|rest| rest.ends_with('/'))
+            })
+        })
+        .map(|(_, name)| *name)
+}
+
+/// The README's first paragraph of prose, skipping headings, badges and markup
+fn first_paragraph(readme: &str) -> Option<String> {
+    let is_prose = |line: &str| {
+        !line.starts_with('#')
+            && !line.starts_with('!')
+            && !line.starts_with("[!")
+            && !line.starts_with('<')
+            && !line.starts_with("..")
+            && !line.chars().all(|c| matches!(c, '=' | '-' | '*' | '_'))
+    };
+    let paragraph: Vec<&str> = readme
+        .lines()
+        .map(str::trim)
+        .skip_while(|line| line.is_empty() || !is_prose(line))
+        .take_while(|line| !line.is_empty() && is_prose(line))
+        .collect();
+    (!paragraph.is_empty()).then(|| paragraph.join(" "))
+}
+
+/// `text` cut at a word boundary to at most `max_bytes`, marked with `...` when cut
+fn truncate_words(text: &str, max_bytes: usize) -> String {
+    if text.len() <= max_bytes {
+        return text.to_string();
+    }
+    let mut end = max_bytes.saturating_sub(3);
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    let cut = text[..end].rfind(' ').unwrap_or(end);
+    let cut = text[..cut].trim_end();
+    if cut.is_empty() {
+        String::new()
+    } else {
+        format!("{cut}...")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frameworks_in_build_files() {
+        let cargo = "[dependencies]\ntokio.workspace = true\naxum = \"0.7\"\nserde = \"1\"\n";
+        assert_eq!(frameworks_in(cargo).collect::<Vec<_>>(), ["Tokio", "Axum"]);
+        let go_mod = "module example.com/api\n\nrequire github.com/gin-gonic/gin v1.9.1\n";
+        assert_eq!(frameworks_in(go_mod).collect::<Vec<_>>(), ["Gin"]);
+        // Names inside longer ones do not count
+        assert_eq!(frameworks_in("\"react-native-svg\": \"1\"").count(), 0);
+    }
+
+    #[test]
+    fn test_main_modules() {
+        let paths = [
+            "README.md",
+            "crates/cloy/src/lib.rs",
+            "crates/cloy/src/config.rs",
+            "crates/pr/src/lib.rs",
+            "docs/guide.md",
+            ".github/workflows/ci.yml",
+            "crates/Cargo.toml",
+        ];
+        assert_eq!(
+            main_modules(paths.iter().map(ToString::to_string)),
+            ["crates/cloy", "crates", "crates/pr", "docs"]
+        );
+    }
+
+    #[test]
+    fn test_brief_is_kept_until_a_source_changes() {
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let repo = Repository::init(dir.path()).expect("Failed to init repo");
+        let stage = |path: &str, content: &str| {
+            std::fs::write(dir.path().join(path), content).expect("Failed to write file");
+            let mut index = repo.index().expect("Failed to open index");
+            index.add_path(Path::new(path)).expect("Failed to stage");
+            index.write().expect("Failed to write index");
+        };
+        stage("Cargo.toml", "[dependencies]\ntokio = \"1\"\n");
+        let brief = ProjectMetadata::brief(&repo).expect("Project should be described");
+        assert_eq!(brief.build_systems, ["Cargo"]);
+        assert!(brief_path(&repo).exists());
+
+        // Unrelated changes keep the saved brief
+        stage("main.rs", "fn main() {}");
+        assert_eq!(ProjectMetadata::brief(&repo), Some(brief));
+
+        stage("Cargo.toml", "[dependencies]\naxum = \"1\"\n");
+        let brief = ProjectMetadata::brief(&repo).expect("Project should be described");
+        assert_eq!(brief.frameworks, ["Axum"]);
+    }
+
+    #[test]
+    fn test_overview() {
+        let readme = "# gitai\n\n[![CI](badge.svg)](ci)\n\nGenerates commit messages\nfrom staged changes.\n\nMore later.\n";
+        let metadata = ProjectMetadata {
+            languages: vec!["Rust".to_string()],
+            frameworks: vec!["Tokio".to_string()],
+            summary: first_paragraph(readme),
+            ..ProjectMetadata::default()
+        };
+        assert_eq!(
+            metadata.overview(),
+            "Languages: Rust\nFrameworks: Tokio\nGenerates commit messages from staged changes."
+        );
+
+        let long = ProjectMetadata {
+            summary: Some("word ".repeat(1_000)),
+            ..ProjectMetadata::default()
+        };
+        let overview = long.overview();
+        assert!(estimate_tokens(&overview) <= MAX_OVERVIEW_TOKENS);
+        assert!(overview.ends_with("word..."));
+    }
+}
@@ -0,0 +1,289 @@
+use crate::analyzers::{self, Language};
+use crate::llm::context::{ChangeType, RelatedFile, StagedFile};
+use git2::{Index, Repository};
+use log::debug;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+/// Upper bound on how many related files are attached to a context
+pub(crate) const MAX_RELATED_FILES: usize = 8;
+/// Number of lines kept from each related file
+const MAX_RELATED_LINES: usize = 40;
+/// Files read at most while looking for trait definitions
+const MAX_DEFINITION_SCAN: usize = 2000;
+/// Files larger than this are not searched for trait definitions
+const MAX_DEFINITION_BYTES: usize = 256 * 1024;
+
+/// Finds unchanged files that give the staged changes their surrounding shape.
+///
+/// The LLM only sees hunks, so a change to `src/git/files.rs` loses the module
+/// declarations in `src/git/mod.rs`, a change to `foo.c` loses the
+/// prototypes in `foo.h`, and a change inside `impl Storage for DiskStore`
+/// loses the `Storage` trait. Those files rarely change together with the
+/// code that depends on them, which is exactly why they are missing from the
+/// diff. Candidates come from path conventions and from the trait and
+/// interface definitions the analyzers find, and are read from the index, so
+/// this never touches the worktree.
+pub fn find_related_files(repo: &Repository, staged_files: &[StagedFile]) -> Vec<RelatedFile> {
+    let Ok(index) = repo.index() else {
+        return Vec::new();
+    };
+
+    let changed: HashSet<&str> = staged_files.iter().map(|f| f.path.as_str()).collect();
+    let implemented: Vec<Vec<String>> = staged_files
+        .iter()
+        .map(|file| {
+            if matches!(file.change_type, ChangeType::Deleted) {
+                Vec::new()
+            } else {
+                analyzers::implemented_traits(file)
+            }
+        })
+        .collect();
+    let definitions = find_definitions(repo, &index, staged_files, &implemented, &changed);
+
+    let mut seen = HashSet::new();
+    let mut related = Vec::new();
+
+    for (file, traits) in staged_files.iter().zip(&implemented) {
+        if matches!(file.change_type, ChangeType::Deleted) {
+            continue;
+        }
+
+        let by_path = related_candidates(&file.path)
+            .into_iter()
+            .map(|(path, reason)| (path, format!("{reason} of {}", file.path), 0));
+        let by_definition = traits.iter().filter_map(|name| {
+            let (path, line) = definitions.get(name)?;
+            Some((
+                path.clone(),
+                format!("definition of {name}, implemented in {}", file.path),
+                *line,
+            ))
+        });
+
+        for (candidate, reason, start) in by_path.chain(by_definition) {
+            if related.len() >= MAX_RELATED_FILES {
+                debug!("Related file limit reached ({MAX_RELATED_FILES})");
+                return related;
+            }
+            if changed.contains(candidate.as_str()) || !seen.insert(candidate.clone()) {
+                continue;
+            }
+
+            let Some(entry) = index.get_path(Path::new(&candidate), 0) else {
+                continue;
+            };
+            let Ok(blob) = repo.find_blob(entry.id) else {
+                continue;
+            };
+            if blob.is_binary() {
+                continue;
+            }
+
+            let text = String::from_utf8_lossy(blob.content());
+            let excerpt = text
+                .lines()
+                .skip(start)
+                .take(MAX_RELATED_LINES)
+                .collect::<Vec<_>>()
+                .join("\n");
+
+            related.push(RelatedFile {
+                path: candidate,
+                reason,
+                excerpt,
+            });
+        }
+    }
+
+    debug!("Found {} related files", related.len());
+    related
+}
+
+/// Where each implemented trait or interface is defined: the unchanged file
+/// and the line its definition starts on.
+///
+/// Indexed files of the implementing file's language are scanned until every
+/// name is found, up to `MAX_DEFINITION_SCAN` files.
+fn find_definitions(
+    repo: &Repository,
+    index: &Index,
+    staged_files: &[StagedFile],
+    implemented: &[Vec<String>],
+    changed: &HashSet<&str>,
+) -> HashMap<String, (String, usize)> {
+    let mut wanted: HashMap<Language, HashSet<String>> = HashMap::new();
+    for (file, names) in staged_files.iter().zip(implemented) {
+        if let Some(language) = Language::of(&file.path)
+            && !names.is_empty()
+        {
+            wanted
+                .entry(language)
+                .or_default()
+                .extend(names.iter().cloned());
+        }
+    }
+    let mut remaining: usize = wanted.values().map(HashSet::len).sum();
+    let mut definitions = HashMap::new();
+    if remaining == 0 {
+        return definitions;
+    }
+
+    let mut scanned = 0;
+    for entry in index.iter() {
+        if remaining == 0 || scanned >= MAX_DEFINITION_SCAN {
+            break;
+        }
+        let path = String::from_utf8_lossy(&entry.path).into_owned();
+        let Some(language) = Language::of(&path) else {
+            continue;
+        };
+        let Some(names) = wanted.get(&language) else {
+            continue;
+        };
+        if changed.contains(path.as_str())
+            || usize::try_from(entry.file_size).unwrap_or(usize::MAX) > MAX_DEFINITION_BYTES
+        {
+            continue;
+        }
+        let Ok(blob) = repo.find_blob(entry.id) else {
+            continue;
+        };
+        scanned += 1;
+
+        let text = String::from_utf8_lossy(blob.content());
+        for (name, line) in analyzers::defined_traits(language, &text, names) {
+            if let Entry::Vacant(slot) = definitions.entry(name) {
+                remaining -= 1;
+                slot.insert((path.clone(), line));
+            }
+        }
+    }
+    debug!("Found {} trait definitions", definitions.len());
+    definitions
+}
+
+/// Derives candidate paths for a changed file, most specific first.
+fn related_candidates(path: &str) -> Vec<(String, &'static str)> {
+    let path = Path::new(path);
+    let Some(ext) = path.extension().and_then(|e| e.to_str()) else {
+        return Vec::new();
+    };
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("");
+    let dir = path.parent().unwrap_or_else(|| Path::new(""));
+    let join = |name: &str| dir.join(name).to_string_lossy().replace('\\', "/");
+
+    let mut candidates = Vec::new();
+    match ext {
+        "rs" => {
+            // A non-root module is declared by `<dir>/mod.rs` or by `<dir>.rs`
+            if !matches!(stem, "mod" | "lib" | "main") {
+                candidates.push((join("mod.rs"), "module parent"));
+                if let Some(name) = dir.file_name().and_then(|n| n.to_str())
+                    && let Some(grandparent) = dir.parent()
+                {
+                    let sibling = grandparent.join(format!("{name}.rs"));
+                    candidates.push((
+                        sibling.to_string_lossy().replace('\\', "/"),
+                        "module parent",
+                    ));
+                }
+            }
+            if dir.file_name().and_then(|n| n.to_str()) == Some("src") && stem != "lib" {
+                candidates.push((join("lib.rs"), "crate root"));
+            }
+        }
+        "c" | "cc" | "cpp" | "cxx" | "m" | "mm" => {
+            for header in ["h", "hpp", "hh"] {
+                candidates.push((join(&format!("{stem}.{header}")), "header"));
+            }
+        }
+        "h" | "hpp" | "hh" => {
+            for source in ["c", "cc", "cpp"] {
+                candidates.push((join(&format!("{stem}.{source}")), "implementation"));
+            }
+        }
+        "py" if stem != "__init__" => {
+            candidates.push((join("__init__.py"), "package init"));
+        }
+        "ts" | "tsx" | "js" | "jsx" if stem != "index" => {
+            for index in ["index.ts", "index.js"] {
+                candidates.push((join(index), "module index"));
+            }
+        }
+        _ => {}
+    }
+
+    candidates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn paths(path: &str) -> Vec<String> {
+        related_candidates(path)
+            .into_iter()
+            .map(|(p, _)| p)
+            .collect()
+    }
+
+    #[test]
+    fn test_rust_module_parents() {
+        let candidates = paths("crates/cloy/src/git/files.rs");
+        assert!(candidates.contains(&"crates/cloy/src/git/mod.rs".to_string()));
+        assert!(candidates.contains(&"crates/cloy/src/git.rs".to_string()));
+        assert!(paths("src/lib.rs").is_empty());
+    }
+
+    #[test]
+    fn test_trait_definition_of_changed_impl() {
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let repo = Repository::init(dir.path()).expect("Failed to init repo");
+        let files = [
+            (
+                "src/storage/api.rs",
+                "use std::io;\n\npub trait Storage {\n    fn read(&self) -> io::Result<Vec<u8>>;\n}\n",
+            ),
+            (
+                "src/disk.rs",
+                "impl Storage for DiskStore {\n    fn read(&self) -> io::Result<Vec<u8>> {\n        self.sync()\n    }\n}\n",
+            ),
+        ];
+        let mut index = repo.index().expect("No index");
+        for (path, content) in files {
+            let full = dir.path().join(path);
+            std::fs::create_dir_all(full.parent().expect("No parent")).expect("Failed to mkdir");
+            std::fs::write(&full, content).expect("Failed to write");
+            index.add_path(Path::new(path)).expect("Failed to stage");
+        }
+        index.write().expect("Failed to write index");
+
+        let staged = StagedFile {
+            path: "src/disk.rs".to_string(),
+            change_type: ChangeType::Modified,
+            diff: "@@ -1,4 +1,4 @@ impl Storage for DiskStore {\n     fn read(&self) -> io::Result<Vec<u8>> {\n-        todo!()\n+        self.sync()\n".to_string(),
+            content: None,
+            content_excluded: false,
+        };
+        let related = find_related_files(&repo, &[staged]);
+        let definition = related
+            .iter()
+            .find(|f| f.path == "src/storage/api.rs")
+            .expect("Trait definition not found");
+        assert_eq!(
+            definition.reason,
+            "definition of Storage, implemented in src/disk.rs"
+        );
+        assert!(definition.excerpt.starts_with("pub trait Storage {"));
+    }
+
+    #[test]
+    fn test_c_header_pairing() {
+        assert_eq!(paths("src/foo.c")[0], "src/foo.h");
+        assert_eq!(paths("include/foo.h")[0], "include/foo.c");
+        assert!(paths("Makefile").is_empty());
+    }
+}
@@ -0,0 +1,315 @@
+//! Remote access the way the git CLI does it: `url.<base>.insteadOf`
+//! rewrites, `http.proxy` and the proxy variables, `~/.ssh/config` host
+//! entries, the SSH agent and default keys, and credential helpers.
+
+use git2::{Config, Cred, CredentialType, FetchOptions, ProxyOptions, RemoteCallbacks};
+use std::path::{Path, PathBuf};
+use url::Url;
+
+/// Keys tried after the agent's, as `ssh` tries them
+const DEFAULT_IDENTITIES: &[&str] = &["id_ed25519", "id_ecdsa", "id_rsa"];
+
+/// What `~/.ssh/config` says about a host
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub(crate) struct SshHost {
+    pub hostname: Option<String>,
+    pub user: Option<String>,
+    pub port: Option<u16>,
+    pub identity_files: Vec<PathBuf>,
+}
+
+/// Whether `pattern` from a `Host` line matches `host`; `*` and `?` are wildcards
+fn host_matches(pattern: &str, host: &str) -> bool {
+    fn matches(pattern: &[u8], host: &[u8]) -> bool {
+        match (pattern.first(), host.first()) {
+            (None, None) => true,
+            (Some(b'*'), _) => {
+                matches(&pattern[1..], host) || (!host.is_empty() && matches(pattern, &host[1..]))
+            }
+            (Some(b'?'), Some(_)) => matches(&pattern[1..], &host[1..]),
+            (Some(p), Some(h)) if p.eq_ignore_ascii_case(h) => matches(&pattern[1..], &host[1..]),
+            _ => false,
+        }
+    }
+    matches(pattern.as_bytes(), host.as_bytes())
+}
+
+/// The settings of `config` for `host`. The first value of each keyword wins,
+/// as in `ssh`; `Match` blocks are skipped.
+pub(crate) fn parse_ssh_config(config: &str, host: &str, home: &Path) -> SshHost {
+    let mut entry = SshHost::default();
+    let mut applies = true;
+    for line in config.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (keyword, value) = line
+            .split_once(|c: char| c.is_whitespace() || c == '=')
+            .map_or((line, ""), |(keyword, value)| {
+                (keyword, value.trim_start_matches([' ', '\t', '=']).trim())
+            });
+        let value = value.trim_matches('"');
+        match keyword.to_ascii_lowercase().as_str() {
+            "host" => {
+                let (negated, patterns): (Vec<&str>, Vec<&str>) =
+                    value.split_whitespace().partition(|p| p.starts_with('!'));
+                applies = patterns.iter().any(|p| host_matches(p, host))
+                    && !negated.iter().any(|p| host_matches(&p[1..], host));
+            }
+            "match" => applies = false,
+            _ if !applies => {}
+            "hostname" if entry.hostname.is_none() => {
+                entry.hostname = Some(value.replace("%h", host));
+            }
+            "user" if entry.user.is_none() => entry.user = Some(value.to_string()),
+            "port" if entry.port.is_none() => entry.port = value.parse().ok(),
+            "identityfile" => {
+                let path = match value.strip_prefix("~/") {
+                    Some(rest) => home.join(rest),
+                    None => PathBuf::from(value),
+                };
+                entry.identity_files.push(path);
+            }
+            _ => {}
+        }
+    }
+    entry
+}
+
+fn ssh_host(host: &str) -> SshHost {
+    let Some(home) = dirs::home_dir() else {
+        return SshHost::default();
+    };
+    std::fs::read_to_string(home.join(".ssh").join("config"))
+        .map(|config| parse_ssh_config(&config, host, &home))
+        .unwrap_or_default()
+}
+
+/// `url` with the longest matching `url.<base>.insteadOf` prefix replaced by `<base>`
+pub(crate) fn rewrite_url(config: &Config, url: &str) -> String {
+    let mut best: Option<(String, String)> = None;
+    if let Ok(mut entries) = config.entries(Some(r"^url\..*\.insteadof$")) {
+        while let Some(Ok(entry)) = entries.next() {
+            let (Some(name), Some(prefix)) = (entry.name(), entry.value()) else {
+                continue;
+            };
+            let base = &name["url.".len()..name.len() - ".insteadof".len()];
+            if url.starts_with(prefix) && best.as_ref().is_none_or(|(p, _)| prefix.len() > p.len())
+            {
+                best = Some((prefix.to_string(), base.to_string()));
+            }
+        }
+    }
+    match best {
+        Some((prefix, base)) => format!("{base}{}", &url[prefix.len()..]),
+        None => url.to_string(),
+    }
+}
+
+/// User, host and path of an scp-like address such as `git@github.com:org/repo`
+pub(crate) fn scp_parts(url: &str) -> Option<(Option<&str>, &str, &str)> {
+    if url.contains("://") {
+        return None;
+    }
+    let (authority, path) = url.split_once(':')?;
+    if authority.is_empty() || authority.contains('/') {
+        return None;
+    }
+    let (user, host) = match authority.split_once('@') {
+        Some((user, host)) => (Some(user), host),
+        None => (None, authority),
+    };
+    Some((user, host, path))
+}
+
+/// `url` with an SSH host alias replaced by its `HostName` and `Port`
+fn resolve_ssh_alias(url: &str, entry: &SshHost) -> String {
+    if entry.hostname.is_none() && entry.port.is_none() {
+        return url.to_string();
+    }
+    if let Some((user, host, path)) = scp_parts(url) {
+        let host = entry.hostname.as_deref().unwrap_or(host);
+        let user = user.map(|user| format!("{user}@")).unwrap_or_default();
+        return match entry.port {
+            Some(port) => format!("ssh://{user}{host}:{port}/{}", path.trim_start_matches('/')),
+            None => format!("{user}{host}:{path}"),
+        };
+    }
+    let Ok(mut parsed) = Url::parse(url) else {
+        return url.to_string();
+    };
+    if let Some(hostname) = &entry.hostname
+        && parsed.set_host(Some(hostname)).is_err()
+    {
+        return url.to_string();
+    }
+    if parsed.port().is_none() && parsed.set_port(entry.port).is_err() {
+        return url.to_string();
+    }
+    parsed.to_string()
+}
+
+/// The SSH host `url` connects to, if it is an SSH URL
+fn ssh_target(url: &str) -> Option<String> {
+    if let Some((_, host, _)) = scp_parts(url) {
+        return Some(host.to_string());
+    }
+    let parsed = Url::parse(url).ok()?;
+    matches!(parsed.scheme(), "ssh" | "git+ssh" | "ssh+git")
+        .then(|| parsed.host_str().map(str::to_string))
+        .flatten()
+}
+
+/// `url` after `insteadOf` rewrites, with SSH host aliases resolved, and
+/// what `~/.ssh/config` says about its host
+pub(crate) fn resolve_url(url: &str) -> (String, SshHost) {
+    let rewritten =
+        Config::open_default().map_or_else(|_| url.to_string(), |config| rewrite_url(&config, url));
+    match ssh_target(&rewritten) {
+        Some(host) => {
+            let entry = ssh_host(&host);
+            (resolve_ssh_alias(&rewritten, &entry), entry)
+        }
+        None => (rewritten, SshHost::default()),
+    }
+}
+
+/// Proxy settings from `http.proxy` and the proxy environment variables
+pub(crate) fn proxy_options() -> ProxyOptions<'static> {
+    let mut proxy = ProxyOptions::new();
+    proxy.auto();
+    proxy
+}
+
+/// Adds a credentials callback that tries, in turn, the SSH agent, the keys
+/// `~/.ssh/config` names for the host, the default keys, and the configured
+/// credential helpers, each once
+pub(crate) fn add_credentials(callbacks: &mut RemoteCallbacks<'_>, ssh: SshHost) {
+    let config = Config::open_default().ok();
+    let home = dirs::home_dir().unwrap_or_default();
+    let mut keys: Vec<PathBuf> = ssh.identity_files.clone();
+    keys.extend(
+        DEFAULT_IDENTITIES
+            .iter()
+            .map(|name| home.join(".ssh").join(name)),
+    );
+    keys.retain(|key| key.is_file());
+    let mut tried_agent = false;
+    let mut next_key = 0;
+    let mut tried_helper = false;
+
+    callbacks.credentials(move |url, username_from_url, allowed| {
+        let user = username_from_url
+            .map(str::to_string)
+            .or_else(|| ssh.user.clone())
+            .unwrap_or_else(|| "git".to_string());
+        if allowed.contains(CredentialType::USERNAME) {
+            return Cred::username(&user);
+        }
+        if allowed.contains(CredentialType::SSH_KEY) {
+            if !tried_agent {
+                tried_agent = true;
+                return Cred::ssh_key_from_agent(&user);
+            }
+            if let Some(key) = keys.get(next_key) {
+                next_key += 1;
+                return Cred::ssh_key(&user, None, key, None);
+            }
+        }
+        if allowed.contains(CredentialType::USER_PASS_PLAINTEXT)
+            && !tried_helper
+            && let Some(config) = &config
+        {
+            tried_helper = true;
+            return Cred::credential_helper(config, url, username_from_url);
+        }
+        if allowed.contains(CredentialType::DEFAULT) {
+            return Cred::default();
+        }
+        Err(git2::Error::from_str(&format!(
+            "no credentials accepted for {url}; check ssh-agent, ~/.ssh/config or credential.helper"
+        )))
+    });
+}
+
+/// `url` as the git CLI resolves it, and fetch options with its credentials
+/// and proxy settings
+pub fn fetch_options(url: &str) -> (String, FetchOptions<'static>) {
+    let (resolved, ssh) = resolve_url(url);
+    if resolved != url {
+        log::debug!("Fetching {url} as {resolved}");
+    }
+    let mut callbacks = RemoteCallbacks::new();
+    add_credentials(&mut callbacks, ssh);
+    let mut fetch = FetchOptions::new();
+    fetch.remote_callbacks(callbacks);
+    fetch.proxy_options(proxy_options());
+    (resolved, fetch)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_remote_resolution() {
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let path = dir.path().join("config");
+        std::fs::write(
+            &path,
+            "[url \"git@github.com:\"]\n\tinsteadOf = gh:\n\
+             [url \"https://mirror.example.com/\"]\n\tinsteadOf = https://\n\tinsteadOf = https://github.com/org/\n",
+        )
+        .expect("Failed to write config");
+        let config = Config::open(&path).expect("Failed to open config");
+        assert_eq!(
+            rewrite_url(&config, "gh:org/repo"),
+            "git@github.com:org/repo"
+        );
+        assert_eq!(
+            rewrite_url(&config, "https://github.com/org/repo"),
+            "https://mirror.example.com/repo"
+        );
+        assert_eq!(rewrite_url(&config, "ssh://host/repo"), "ssh://host/repo");
+
+        let ssh_config = "\
+Host *.internal !bastion.internal
+  User deploy
+Host work
+  HostName git.corp.example.com
+  Port 2222
+  IdentityFile ~/.ssh/work_key
+Host *
+  User fallback
+  IdentityFile ~/.ssh/other
+";
+        let home = Path::new("/home/me");
+        let work = parse_ssh_config(ssh_config, "work", home);
+        assert_eq!(
+            work,
+            SshHost {
+                hostname: Some("git.corp.example.com".to_string()),
+                user: Some("fallback".to_string()),
+                port: Some(2222),
+                identity_files: vec![
+                    PathBuf::from("/home/me/.ssh/work_key"),
+                    PathBuf::from("/home/me/.ssh/other"),
+                ],
+            }
+        );
+        let user = |host| parse_ssh_config(ssh_config, host, home).user;
+        assert_eq!(user("ci.internal").as_deref(), Some("deploy"));
+        assert_eq!(user("bastion.internal").as_deref(), Some("fallback"));
+
+        assert_eq!(
+            resolve_ssh_alias("git@work:org/repo.git", &work),
+            "ssh://git@git.corp.example.com:2222/org/repo.git"
+        );
+        assert_eq!(
+            resolve_ssh_alias("ssh://git@work/org/repo.git", &work),
+            "ssh://git@git.corp.example.com:2222/org/repo.git"
+        );
+        assert_eq!(ssh_target("https://github.com/org/repo"), None);
+    }
+}
@@ -1,11 +1,24 @@
-use crate::config::Config;
-use crate::llm::context::{ChangeType, CommitContext, RecentCommit, StagedFile};
-
+use crate::cache_crypt;
+use crate::config::{Config, ContextDepth, DiffSettings};
+use crate::error::{ErrorCode, coded};
+use crate::llm::context::{
+    ChangeType, CommitContext, DiffSource, FileOwners, RecentCommit, RelatedFile, StagedFile,
+};
+use crate::llm::style::{self, StyleProfile};
+
+use crate::git::clone;
+use crate::git::codeowners;
 use crate::git::commit::{self, CommitResult};
-use crate::git::files::{RepoFilesInfo, get_file_statuses, get_unstaged_file_statuses};
-use crate::git::history;
+use crate::git::files::{self, RepoFilesInfo, get_file_statuses, get_unstaged_file_statuses};
+use crate::git::history::{self, WalkLimit};
 use crate::git::hooks;
-use crate::git::utils::is_inside_work_tree;
+use crate::git::journal::{self, UndoResult};
+use crate::git::project::ProjectMetadata;
+use crate::git::related;
+use crate::git::remote;
+use crate::git::utils::{is_inside_work_tree, unborn_branch_name};
+use crate::git::vcs::WorkingCopy;
+use crate::git::workspace::{self, Package};
 use anyhow::{Context as AnyhowContext, Result, anyhow};
 use git2::{Repository, Tree};
 use std::collections::HashSet;
@@ -17,6 +30,81 @@ use log::debug;
 use tempfile::TempDir;
 use url::Url;
 
+/// Commits before a replayed commit shown as recent history
+const REPLAY_RECENT_COMMITS: usize = 5;
+/// Commits before a replayed commit scanned for the author's history
+const REPLAY_HISTORY_DEPTH: usize = 10;
+
+/// The changed files of a commit context and what they touch
+struct ChangeContext {
+    staged_files: Vec<StagedFile>,
+    recent_commits: Vec<RecentCommit>,
+    related_files: Vec<RelatedFile>,
+    owners: Vec<FileOwners>,
+}
+
+impl ChangeContext {
+    fn gather(
+        repo: &Repository,
+        recent_commits: Vec<RecentCommit>,
+        staged_files: Vec<StagedFile>,
+    ) -> Self {
+        let related_files = related::find_related_files(repo, &staged_files);
+        let owners = codeowners::owners_of_files(repo, &staged_files);
+        Self {
+            staged_files,
+            recent_commits,
+            related_files,
+            owners,
+        }
+    }
+}
+
+/// Who is committing, with their past messages and writing style
+struct AuthorContext {
+    name: String,
+    email: String,
+    history: Vec<String>,
+    style_profile: Option<StyleProfile>,
+}
+
+impl AuthorContext {
+    fn gather(repo: &Repository, depth: ContextDepth) -> Result<Self> {
+        let name = repo.config()?.get_string("user.name").unwrap_or_default();
+        let email = repo.config()?.get_string("user.email").unwrap_or_default();
+
+        if name.is_empty() || email.is_empty() {
+            debug!("Git user config incomplete: name='{name}', email='{email}'");
+        }
+
+        let history = history::get_author_commit_history(repo, &email, depth.author_history)?;
+        // The profile only changes when the author commits, not when others do
+        let last_commit = history::latest_author_commit(repo, &email, style::PROFILE_SAMPLE_SIZE)?
+            .map(|oid| oid.to_string())
+            .unwrap_or_default();
+        // Linked worktrees share their repository's profiles
+        let repository = repo.commondir().to_string_lossy().into_owned();
+        // One profile per person, whichever of their addresses is configured
+        let profile_email =
+            history::canonical_email(history::load_mailmap(repo).as_ref(), &name, &email);
+        let encrypt = cache_crypt::enabled(Some(repo));
+        let style_profile = StyleProfile::load_or_build(
+            &repository,
+            &last_commit,
+            &profile_email,
+            encrypt,
+            |count| history::get_author_commit_history(repo, &email, count),
+        )?;
+
+        Ok(Self {
+            name,
+            email,
+            history,
+            style_profile,
+        })
+    }
+}
+
 /// Represents a Git repository and provides methods for interacting with it.
 pub struct GitRepo {
     repo_path: PathBuf,
@@ -125,6 +213,9 @@ impl GitRepo {
 
     /// Clones a remote repository and creates a `GitRepo` instance for it.
     ///
+    /// Objects and bytes received are shown on a terminal, and Ctrl-C aborts
+    /// the transfer and removes the temporary directory.
+    ///
     /// # Arguments
     ///
     /// * `url` - The URL of the remote repository to clone.
@@ -135,7 +226,11 @@ impl GitRepo {
     pub fn clone_remote_repository(url: &str) -> Result<Self> {
         debug!("Cloning remote repository from URL: {url}");
 
-        Url::parse(url).map_err(|e| anyhow!("Invalid repository URL: {e}"))?;
+        // scp-like addresses such as `git@github.com:org/repo` are not URLs
+        if remote::scp_parts(url).is_none() {
+            Url::parse(url)
+                .map_err(|e| coded(ErrorCode::Remote, format!("Invalid repository URL: {e}")))?;
+        }
 
         // Create a temporary directory for the clone
         let temp_dir = TempDir::new()?;
@@ -146,10 +241,16 @@ impl GitRepo {
             temp_path_buf.display()
         );
 
-        // Clone the repository into the temporary directory
-        let repo = match Repository::clone(url, &temp_path_buf) {
+        // Clone the repository into the temporary directory, removing it on failure
+        let repo = match clone::clone(url, &temp_path_buf) {
             Ok(repo) => repo,
-            Err(e) => return Err(anyhow!("Failed to clone repository: {e}")),
+            Err(e) => {
+                if let Err(close_err) = temp_dir.close() {
+                    debug!("Failed to remove temporary directory: {close_err}");
+                }
+                let code = ErrorCode::of(&e).unwrap_or(ErrorCode::Remote);
+                return Err(coded(code, e));
+            }
         };
 
         debug!(
@@ -167,7 +268,26 @@ impl GitRepo {
 
     /// Open the repository at the stored path
     pub fn open_repo(&self) -> Result<Repository, git2::Error> {
-        Repository::open(&self.repo_path)
+        Self::open_at(&self.repo_path)
+    }
+
+    /// Open the repository at `path` with the index git names in `GIT_INDEX_FILE`.
+    ///
+    /// `git commit -a` and `git commit <paths>` stage into a temporary index
+    /// and point hooks and the editor at it, so that is what will be committed.
+    /// A jj or Sapling working copy without a colocated `.git` opens its Git store.
+    fn open_at(path: &Path) -> Result<Repository, git2::Error> {
+        let repo = match Repository::open(path) {
+            Ok(repo) => repo,
+            Err(e) => match WorkingCopy::discover(path) {
+                Some(copy) => Repository::open(copy.git_dir())?,
+                None => return Err(e),
+            },
+        };
+        if let Some(index_file) = std::env::var_os("GIT_INDEX_FILE") {
+            repo.set_index(&mut git2::Index::open(Path::new(&index_file))?)?;
+        }
+        Ok(repo)
     }
 
     /// Open the repository in the current working directory
@@ -213,9 +333,21 @@ impl GitRepo {
             .next()
             .ok_or_else(|| anyhow!("No remote found"))?;
 
-        // Fetch updates from the remote (all branches)
-        let mut remote = repo.find_remote(remote_name)?;
-        remote.fetch(<&[&str]>::default(), None, None)?;
+        // Fetch updates from the remote (all branches), reaching it the way
+        // the git CLI would
+        let remote = repo.find_remote(remote_name)?;
+        let url = remote
+            .url()
+            .ok_or_else(|| anyhow!("Remote {remote_name} has no URL"))?;
+        let refspecs: Vec<String> = remote
+            .fetch_refspecs()?
+            .iter()
+            .flatten()
+            .map(String::from)
+            .collect();
+        let (url, mut fetch) = remote::fetch_options(url);
+        repo.remote_anonymous(&url)?
+            .fetch(&refspecs, Some(&mut fetch), None)?;
 
         debug!("Successfully updated remote repository");
         Ok(())
@@ -237,9 +369,9 @@ impl GitRepo {
             debug!("Current branch: {branch_name}");
             Ok(branch_name)
         } else {
-            // For fresh repos with no commits, default to "main"
-            debug!("No HEAD found (fresh repository), defaulting to 'main'");
-            Ok("main".to_string())
+            let branch_name = unborn_branch_name(&repo);
+            debug!("No commits yet, HEAD will be on '{branch_name}'");
+            Ok(branch_name)
         }
     }
 
@@ -253,6 +385,10 @@ impl GitRepo {
     ///
     /// A Result indicating success or an error.
     pub fn execute_hook(&self, hook_name: &str) -> Result<()> {
+        // jj and Sapling do not run Git hooks either
+        if self.working_copy().is_some() {
+            return Ok(());
+        }
         let repo = self.open_repo()?;
         hooks::execute_hook(&repo, hook_name, self.is_remote)
     }
@@ -342,9 +478,10 @@ impl GitRepo {
         let recent_commits = self.get_recent_commits(5)?;
 
         // Get staged and unstaged files
-        let mut staged_files = get_file_statuses(&repo)?;
+        let diff = DiffSettings::default();
+        let mut staged_files = get_file_statuses(&repo, diff)?;
         if include_unstaged {
-            let unstaged_files = self.get_unstaged_files()?;
+            let unstaged_files = self.get_unstaged_files(diff)?;
             staged_files.extend(unstaged_files);
             debug!("Combined {} files (staged + unstaged)", staged_files.len());
         }
@@ -370,10 +507,38 @@ impl GitRepo {
         })
     }
 
-    /// Gets unstaged file changes from the repository
-    pub fn get_unstaged_files(&self) -> Result<Vec<StagedFile>> {
+    /// Gets unstaged file changes from the repository; files over
+    /// `diff.max_file_size` are listed without their diff
+    pub fn get_unstaged_files(&self, diff: DiffSettings) -> Result<Vec<StagedFile>> {
+        let repo = self.open_repo()?;
+        get_unstaged_file_statuses(&repo, diff.max_file_size)
+    }
+
+    /// Stages the working tree state of a file
+    pub fn stage_file(&self, path: &str) -> Result<()> {
+        if self.is_remote {
+            return Err(anyhow!("Cannot stage files in a remote repository"));
+        }
         let repo = self.open_repo()?;
-        get_unstaged_file_statuses(&repo)
+        files::stage_path(&repo, path)
+    }
+
+    /// Stages the selected hunks of a file's unstaged changes
+    pub fn stage_hunks(&self, path: &str, hunks: &[usize]) -> Result<()> {
+        if self.is_remote {
+            return Err(anyhow!("Cannot stage files in a remote repository"));
+        }
+        let repo = self.open_repo()?;
+        files::stage_hunks(&repo, path, hunks)
+    }
+
+    /// Unstages a file, keeping its working tree changes
+    pub fn unstage_file(&self, path: &str) -> Result<()> {
+        if self.is_remote {
+            return Err(anyhow!("Cannot unstage files in a remote repository"));
+        }
+        let repo = self.open_repo()?;
+        files::unstage_path(&repo, path)
     }
 
     /// Helper method for creating `CommitContext`
@@ -383,7 +548,7 @@ impl GitRepo {
     /// * `branch` - Branch name
     /// * `recent_commits` - List of recent commits
     /// * `staged_files` - List of staged files
-    /// * `project_metadata` - Project metadata
+    /// * `depth` - How many of the author's messages to include
     ///
     /// # Returns
     ///
@@ -393,6 +558,7 @@ impl GitRepo {
         branch: String,
         recent_commits: Vec<RecentCommit>,
         staged_files: Vec<StagedFile>,
+        depth: ContextDepth,
     ) -> Result<CommitContext> {
         // Get user info
         let repo = self.open_repo()?;
@@ -406,18 +572,22 @@ impl GitRepo {
             );
         }
 
-        // Get author's commit history (last 10 commits)
-        let author_history = self.get_author_commit_history(&user_email, 10)?;
+        let author_history = self.get_author_commit_history(&user_email, depth.author_history)?;
+
+        let owners = codeowners::owners_of_files(&repo, &staged_files);
 
         // Create and return the context
-        Ok(CommitContext::new(
+        let mut context = CommitContext::new(
             branch,
             recent_commits,
             staged_files,
             user_name,
             user_email,
             author_history,
-        ))
+        );
+        context.owners = owners;
+        context.project = ProjectMetadata::brief(&repo);
+        Ok(context)
     }
 
     /// Get Git information including unstaged changes
@@ -429,45 +599,85 @@ impl GitRepo {
     /// # Returns
     ///
     /// A Result containing the `CommitContext` or an error.
-    pub async fn get_git_info(&self, _config: &Config) -> Result<CommitContext> {
-        let repo_path = self.repo_path.clone();
+    #[tracing::instrument(skip_all)]
+    pub async fn get_git_info(&self, config: &Config) -> Result<CommitContext> {
+        self.gather_git_info(config, false).await
+    }
+
+    /// Gathers a commit context, running the independent queries side by side
+    ///
+    /// The changes and their history, the author's history and style, and the
+    /// branch and project brief each run on a blocking worker with a
+    /// `Repository` of their own, since git2 handles can't cross threads.
+    async fn gather_git_info(
+        &self,
+        config: &Config,
+        include_unstaged: bool,
+    ) -> Result<CommitContext> {
+        let depth = config.context;
+        let diff = config.diff;
 
-        task::spawn_blocking(move || {
-            let repo = Repository::open(&repo_path)?;
-            debug!("Getting git info for repo path: {}", repo.path().display());
+        // The whole working copy is the change in jj and Sapling
+        let repo_path = self.repo_path.clone();
+        let working_copy = task::spawn_blocking(move || {
+            WorkingCopy::discover(&repo_path).map(|copy| Self::working_copy_info(&copy, depth))
+        })
+        .await?;
+        if let Some(context) = working_copy {
+            return context;
+        }
 
-            let branch = Self::get_current_branch_sync(&repo);
-            let staged_files = get_file_statuses(&repo)?;
+        let repo_path = self.repo_path.clone();
+        let changes = task::spawn_blocking(move || -> Result<ChangeContext> {
+            let repo = Self::open_at(&repo_path)?;
+            debug!(
+                "Getting git info for repo path: {}, include_unstaged: {}",
+                repo.path().display(),
+                include_unstaged
+            );
 
-            let mut file_paths_set = HashSet::new();
-            for f in &staged_files {
-                file_paths_set.insert(f.path.clone());
-                if let ChangeType::Renamed { from, .. } = &f.change_type {
-                    file_paths_set.insert(from.clone());
-                }
-                if let ChangeType::Copied { from, .. } = &f.change_type {
-                    file_paths_set.insert(from.clone());
-                }
+            let mut staged_files = get_file_statuses(&repo, diff)?;
+            if include_unstaged {
+                let unstaged_files = get_unstaged_file_statuses(&repo, diff.max_file_size)?;
+                staged_files.extend(unstaged_files);
+                debug!("Combined {} files (staged + unstaged)", staged_files.len());
             }
-            let file_paths: Vec<String> = file_paths_set.into_iter().collect();
-
-            let recent_commits = if file_paths.is_empty() {
-                Self::get_recent_commits_sync(&repo, 10)?
-            } else {
-                let file_commits = Self::get_commits_for_files_sync(&repo, &file_paths, 10)?;
-                if file_commits.is_empty() {
-                    Self::get_recent_commits_sync(&repo, 10)?
-                } else {
-                    file_commits
-                }
-            };
+            let recent_commits = Self::history_of_changes_sync(&repo, &staged_files, depth)?;
+            Ok(ChangeContext::gather(&repo, recent_commits, staged_files))
+        });
 
-            let context =
-                Self::create_commit_context_sync(&repo, branch, recent_commits, staged_files)?;
+        let repo_path = self.repo_path.clone();
+        let author = task::spawn_blocking(move || -> Result<AuthorContext> {
+            AuthorContext::gather(&Self::open_at(&repo_path)?, depth)
+        });
 
-            Ok(context)
-        })
-        .await?
+        let repo_path = self.repo_path.clone();
+        let overview = task::spawn_blocking(move || -> Result<_> {
+            let repo = Self::open_at(&repo_path)?;
+            Ok((
+                Self::get_current_branch_sync(&repo),
+                ProjectMetadata::brief(&repo),
+            ))
+        });
+
+        let (changes, author, overview) = tokio::try_join!(changes, author, overview)?;
+        let (branch, project) = overview?;
+        let mut context = Self::assemble_context(branch, changes?, author?, project);
+        context.diff_source = DiffSource::Staged;
+        Ok(context)
+    }
+
+    /// Context for the working copy of a jj or Sapling repository
+    fn working_copy_info(copy: &WorkingCopy, depth: ContextDepth) -> Result<CommitContext> {
+        debug!(
+            "Getting {:?} working copy info for {}",
+            copy.frontend,
+            copy.root.display()
+        );
+        let repo = copy.open_store()?;
+        let staged_files = copy.changes()?;
+        let recent_commits = copy.recent_commits(depth.recent_commits)?;
+        Self::create_commit_context_sync(&repo, copy.branch(), recent_commits, staged_files, depth)
     }
 
     fn get_current_branch_sync(repo: &Repository) -> String {
@@ -476,21 +686,42 @@ impl GitRepo {
             debug!("Current branch: {branch_name}");
             branch_name
         } else {
-            debug!("No HEAD found (fresh repository), defaulting to 'main'");
-            "main".to_string()
+            let branch_name = unborn_branch_name(repo);
+            debug!("No commits yet, HEAD will be on '{branch_name}'");
+            branch_name
         }
     }
 
-    fn get_recent_commits_sync(repo: &Repository, count: usize) -> Result<Vec<RecentCommit>> {
-        history::get_recent_commits(repo, count)
-    }
-
-    fn get_commits_for_files_sync(
+    /// Commits touching the changed files, or the latest ones if none do
+    fn history_of_changes_sync(
         repo: &Repository,
-        file_paths: &[String],
-        max_commits: usize,
+        staged_files: &[StagedFile],
+        depth: ContextDepth,
     ) -> Result<Vec<RecentCommit>> {
-        history::get_commits_for_files(repo, file_paths, max_commits)
+        let mut file_paths_set = HashSet::new();
+        for f in staged_files {
+            file_paths_set.insert(f.path.clone());
+            if let ChangeType::Renamed { from, .. } = &f.change_type {
+                file_paths_set.insert(from.clone());
+            }
+            if let ChangeType::Copied { from, .. } = &f.change_type {
+                file_paths_set.insert(from.clone());
+            }
+        }
+        let file_paths: Vec<String> = file_paths_set.into_iter().collect();
+
+        if !file_paths.is_empty() {
+            let limit = WalkLimit {
+                commits: depth.history_walk,
+                ..WalkLimit::default()
+            };
+            let file_commits =
+                history::get_commits_for_files(repo, &file_paths, depth.file_commits, limit)?;
+            if !file_commits.is_empty() {
+                return Ok(file_commits);
+            }
+        }
+        history::get_recent_commits(repo, depth.recent_commits)
     }
 
     fn create_commit_context_sync(
@@ -498,27 +729,33 @@ impl GitRepo {
         branch: String,
         recent_commits: Vec<RecentCommit>,
         staged_files: Vec<StagedFile>,
+        depth: ContextDepth,
     ) -> Result<CommitContext> {
-        let user_name = repo.config()?.get_string("user.name").unwrap_or_default();
-        let user_email = repo.config()?.get_string("user.email").unwrap_or_default();
-
-        if user_name.is_empty() || user_email.is_empty() {
-            debug!(
-                "Git user config incomplete: name='{}', email='{}'",
-                user_name, user_email
-            );
-        }
-
-        let author_history = history::get_author_commit_history(repo, &user_email, 10)?;
+        let changes = ChangeContext::gather(repo, recent_commits, staged_files);
+        let author = AuthorContext::gather(repo, depth)?;
+        let project = ProjectMetadata::brief(repo);
+        Ok(Self::assemble_context(branch, changes, author, project))
+    }
 
-        Ok(CommitContext::new(
+    fn assemble_context(
+        branch: String,
+        changes: ChangeContext,
+        author: AuthorContext,
+        project: Option<ProjectMetadata>,
+    ) -> CommitContext {
+        let mut context = CommitContext::new(
             branch,
-            recent_commits,
-            staged_files,
-            user_name,
-            user_email,
-            author_history,
-        ))
+            changes.recent_commits,
+            changes.staged_files,
+            author.name,
+            author.email,
+            author.history,
+        );
+        context.related_files = changes.related_files;
+        context.style_profile = author.style_profile;
+        context.owners = changes.owners;
+        context.project = project;
+        context
     }
 
     /// Get Git information including unstaged changes
@@ -531,59 +768,13 @@ impl GitRepo {
     /// # Returns
     ///
     /// A Result containing the `CommitContext` or an error.
+    #[tracing::instrument(skip_all)]
     pub async fn get_git_info_with_unstaged(
         &self,
-        _config: &Config,
+        config: &Config,
         include_unstaged: bool,
     ) -> Result<CommitContext> {
-        let repo_path = self.repo_path.clone();
-
-        task::spawn_blocking(move || {
-            let repo = Repository::open(&repo_path)?;
-            debug!(
-                "Getting git info for repo path: {}, include_unstaged: {}",
-                repo.path().display(),
-                include_unstaged
-            );
-
-            let branch = Self::get_current_branch_sync(&repo);
-            let mut staged_files = get_file_statuses(&repo)?;
-
-            if include_unstaged {
-                let unstaged_files = get_unstaged_file_statuses(&repo)?;
-                staged_files.extend(unstaged_files);
-                debug!("Combined {} files (staged + unstaged)", staged_files.len());
-            }
-
-            let mut file_paths_set = HashSet::new();
-            for f in &staged_files {
-                file_paths_set.insert(f.path.clone());
-                if let ChangeType::Renamed { from, .. } = &f.change_type {
-                    file_paths_set.insert(from.clone());
-                }
-                if let ChangeType::Copied { from, .. } = &f.change_type {
-                    file_paths_set.insert(from.clone());
-                }
-            }
-            let file_paths: Vec<String> = file_paths_set.into_iter().collect();
-
-            let recent_commits = if file_paths.is_empty() {
-                Self::get_recent_commits_sync(&repo, 10)?
-            } else {
-                let file_commits = Self::get_commits_for_files_sync(&repo, &file_paths, 10)?;
-                if file_commits.is_empty() {
-                    Self::get_recent_commits_sync(&repo, 10)?
-                } else {
-                    file_commits
-                }
-            };
-
-            let context =
-                Self::create_commit_context_sync(&repo, branch, recent_commits, staged_files)?;
-
-            Ok(context)
-        })
-        .await?
+        self.gather_git_info(config, include_unstaged).await
     }
 
     /// Get Git information for comparing two branches
@@ -597,9 +788,10 @@ impl GitRepo {
     /// # Returns
     ///
     /// A Result containing the `CommitContext` for the branch comparison or an error.
+    #[tracing::instrument(skip_all)]
     pub fn get_git_info_for_branch_diff(
         &self,
-        _config: &Config,
+        config: &Config,
         base_branch: &str,
         target_branch: &str,
     ) -> Result<CommitContext> {
@@ -611,10 +803,21 @@ impl GitRepo {
             commit::extract_branch_diff_info(&repo, base_branch, target_branch)?;
 
         // Get the actual file changes
-        let branch_files = commit::get_branch_diff_files(&repo, base_branch, target_branch)?;
+        let branch_files =
+            commit::get_branch_diff_files(&repo, base_branch, target_branch, config.diff)?;
 
-        // Create and return the context
-        self.create_commit_context(display_branch, recent_commits, branch_files)
+        let (merge_base, target) = commit::branch_diff_commits(&repo, base_branch, target_branch)?;
+        let mut context = self.create_commit_context(
+            display_branch,
+            recent_commits,
+            branch_files,
+            config.context,
+        )?;
+        context.diff_source = DiffSource::Commits {
+            from: Some(merge_base.to_string()),
+            to: target.to_string(),
+        };
+        Ok(context)
     }
 
     /// Get Git information for a commit range (for PR descriptions)
@@ -628,9 +831,10 @@ impl GitRepo {
     /// # Returns
     ///
     /// A Result containing the `CommitContext` for the commit range or an error.
+    #[tracing::instrument(skip_all)]
     pub fn get_git_info_for_commit_range(
         &self,
-        _config: &Config,
+        config: &Config,
         from: &str,
         to: &str,
     ) -> Result<CommitContext> {
@@ -642,10 +846,20 @@ impl GitRepo {
             commit::extract_commit_range_info(&repo, from, to)?;
 
         // Get the actual file changes
-        let range_files = commit::get_commit_range_files(&repo, from, to)?;
-
-        // Create and return the context
-        self.create_commit_context(display_range, recent_commits, range_files)
+        let range_files = commit::get_commit_range_files(&repo, from, to, config.diff)?;
+
+        let mut context =
+            self.create_commit_context(display_range, recent_commits, range_files, config.context)?;
+        context.diff_source = DiffSource::Commits {
+            from: Some(
+                repo.revparse_single(from)?
+                    .peel_to_commit()?
+                    .id()
+                    .to_string(),
+            ),
+            to: repo.revparse_single(to)?.peel_to_commit()?.id().to_string(),
+        };
+        Ok(context)
     }
 
     /// Get commits for PR description between two references
@@ -657,7 +871,7 @@ impl GitRepo {
     /// Get files changed in a commit range
     pub fn get_commit_range_files(&self, from: &str, to: &str) -> Result<Vec<StagedFile>> {
         let repo = self.open_repo()?;
-        commit::get_commit_range_files(&repo, from, to)
+        commit::get_commit_range_files(&repo, from, to, DiffSettings::default())
     }
 
     /// Retrieves recent commits.
@@ -694,7 +908,7 @@ impl GitRepo {
         max_commits: usize,
     ) -> Result<Vec<RecentCommit>> {
         let repo = self.open_repo()?;
-        history::get_commits_for_files(&repo, file_paths, max_commits)
+        history::get_commits_for_files(&repo, file_paths, max_commits, WalkLimit::default())
     }
 
     /// Retrieves the author's recent commit messages.
@@ -733,8 +947,10 @@ impl GitRepo {
         }
 
         let repo = self.open_repo()?;
+        let previous_head = journal::head_oid(&repo);
         match commit::commit(&repo, message, self.is_remote) {
             Ok(result) => {
+                journal::record(&repo, previous_head, "commit");
                 if let Err(e) = self.execute_hook("post-commit") {
                     debug!("Post-commit hook failed: {e}");
                 }
@@ -757,9 +973,10 @@ impl GitRepo {
     /// # Returns
     ///
     /// A Result containing the `CommitContext` or an error.
+    #[tracing::instrument(skip_all, fields(commit = commit_id))]
     pub fn get_git_info_for_commit(
         &self,
-        _config: &Config,
+        config: &Config,
         commit_id: &str,
     ) -> Result<CommitContext> {
         debug!("Getting git info for commit: {commit_id}");
@@ -772,10 +989,54 @@ impl GitRepo {
         let commit_info = commit::extract_commit_info(&repo, commit_id, &branch)?;
 
         // Get the files from commit after async boundary
-        let commit_files = commit::get_commit_files(&repo, commit_id)?;
+        let commit_files = commit::get_commit_files(&repo, commit_id, config.diff)?;
+
+        let mut context = self.create_commit_context(
+            commit_info.branch,
+            vec![commit_info.commit],
+            commit_files,
+            config.context,
+        )?;
+        context.diff_source = commit::commit_diff_source(&repo, commit_id)?;
+        Ok(context)
+    }
 
-        // Create and return the context
-        self.create_commit_context(commit_info.branch, vec![commit_info.commit], commit_files)
+    /// Get Git information for replaying a past commit as if it were staged on its parent
+    ///
+    /// Recent commits and author history stop at the parent, so neither the
+    /// commit's own message nor later ones reach the prompt.
+    #[tracing::instrument(skip_all, fields(commit = commit_id))]
+    pub fn get_git_info_for_replay(&self, commit_id: &str) -> Result<CommitContext> {
+        let repo = self.open_repo()?;
+        let commit = repo.revparse_single(commit_id)?.peel_to_commit()?;
+        let author_email =
+            history::commit_author_email(history::load_mailmap(&repo).as_ref(), &commit);
+
+        let earlier = match commit.parent_id(0) {
+            Ok(parent) => history::get_commits_before(&repo, parent, REPLAY_HISTORY_DEPTH)?,
+            Err(_) => Vec::new(),
+        };
+        let recent_commits = earlier
+            .iter()
+            .take(REPLAY_RECENT_COMMITS)
+            .map(|(c, _)| c.clone())
+            .collect();
+        let author_history = earlier
+            .iter()
+            .filter(|(_, email)| *email == author_email)
+            .map(|(c, _)| c.message.clone())
+            .collect();
+
+        let files = commit::get_commit_files(&repo, commit_id, DiffSettings::default())?;
+        let mut context = self.create_commit_context(
+            self.get_current_branch()?,
+            recent_commits,
+            files,
+            ContextDepth::default(),
+        )?;
+        context.author_history = author_history;
+        context.diff_source = commit::commit_diff_source(&repo, commit_id)?;
+        Ok(context)
     }
 
     /// Get the commit date for a reference
@@ -807,16 +1068,57 @@ impl GitRepo {
         commit::get_commits_between_stream(&repo, from, to, callback)
     }
 
-    /// Commit changes to the repository
+    /// Cargo, npm and pnpm workspace members declared at `rev`
+    pub fn workspace_packages(&self, rev: &str) -> Result<Vec<Package>> {
+        let repo = self.open_repo()?;
+        workspace::workspace_packages(&repo, rev)
+    }
+
+    /// The jj or Sapling working copy of a local repository
+    fn working_copy(&self) -> Option<WorkingCopy> {
+        if self.is_remote {
+            return None;
+        }
+        WorkingCopy::discover(&self.repo_path)
+    }
+
+    /// Commit changes to the repository; jj and Sapling commit their working copy
     pub fn commit(&self, message: &str) -> Result<CommitResult> {
+        if let Some(copy) = self.working_copy() {
+            return copy.commit(message);
+        }
         let repo = self.open_repo()?;
-        commit::commit(&repo, message, self.is_remote)
+        let previous_head = journal::head_oid(&repo);
+        let result = commit::commit(&repo, message, self.is_remote)?;
+        journal::record(&repo, previous_head, "commit");
+        Ok(result)
     }
 
     /// Amend a commit with a new message
     pub fn amend_commit(&self, message: &str, commit_ref: &str) -> Result<CommitResult> {
         let repo = self.open_repo()?;
-        commit::amend_commit(&repo, message, commit_ref, self.is_remote)
+        let previous_head = journal::head_oid(&repo);
+        let result = commit::amend_commit(&repo, message, commit_ref, self.is_remote)?;
+        journal::record(&repo, previous_head, "amend");
+        Ok(result)
+    }
+
+    /// Rewrite the message of HEAD, keeping its tree
+    pub fn reword_head(&self, message: &str) -> Result<CommitResult> {
+        let repo = self.open_repo()?;
+        let previous_head = journal::head_oid(&repo);
+        let result = commit::reword_head(&repo, message, self.is_remote)?;
+        journal::record(&repo, previous_head, "reword");
+        Ok(result)
+    }
+
+    /// Soft-reset the last commit made by gitai, keeping its changes staged
+    pub fn undo_last_commit(&self) -> Result<UndoResult> {
+        if self.is_remote {
+            return Err(anyhow!("Cannot undo commits in a remote repository"));
+        }
+        let repo = self.open_repo()?;
+        journal::undo_last(&repo)
     }
 
     /// Check if inside a working tree
@@ -827,7 +1129,7 @@ impl GitRepo {
     /// Get the files changed in a specific commit
     pub fn get_commit_files(&self, commit_id: &str) -> Result<Vec<StagedFile>> {
         let repo = self.open_repo()?;
-        commit::get_commit_files(&repo, commit_id)
+        commit::get_commit_files(&repo, commit_id, DiffSettings::default())
     }
 
     /// Get just the file paths for a specific commit
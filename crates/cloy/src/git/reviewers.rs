@@ -0,0 +1,227 @@
+use super::history;
+use anyhow::Result;
+use git2::{BlameOptions, Commit, Delta, Diff, DiffOptions, Oid, Repository};
+use log::debug;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+/// Number of reviewers suggested
+const MAX_REVIEWERS: usize = 5;
+/// Files blamed per suggestion; blame walks history, so large changes are sampled
+const MAX_BLAMED_FILES: usize = 40;
+
+/// Someone who last touched lines the change modifies
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct ReviewerSuggestion {
+    pub name: String,
+    pub email: String,
+    /// Changed lines this person wrote last
+    pub lines: usize,
+    /// Files those lines are in
+    pub files: Vec<String>,
+}
+
+/// Diff options whose hunks hold only the changed lines
+fn without_context() -> DiffOptions {
+    let mut options = DiffOptions::new();
+    options.context_lines(0);
+    options
+}
+
+/// Old-side line ranges of a diff, by file
+fn changed_lines(diff: &Diff) -> Result<BTreeMap<String, Vec<(usize, usize)>>> {
+    let mut ranges: BTreeMap<String, Vec<(usize, usize)>> = BTreeMap::new();
+    diff.foreach(
+        &mut |_, _| true,
+        None,
+        Some(&mut |delta, hunk| {
+            // New files have no history to ask about
+            if matches!(delta.status(), Delta::Added | Delta::Untracked) {
+                return true;
+            }
+            if let Some(path) = delta.old_file().path() {
+                // A pure insertion counts the line it follows
+                let start = usize::try_from(hunk.old_start()).unwrap_or(0).max(1);
+                let count = usize::try_from(hunk.old_lines()).unwrap_or(0).max(1);
+                ranges
+                    .entry(path.to_string_lossy().replace('\\', "/"))
+                    .or_default()
+                    .push((start, count));
+            }
+            true
+        }),
+        None,
+    )?;
+    Ok(ranges)
+}
+
+/// Counts who last touched the old side of `diff`, as of `base`
+fn blame_authors(repo: &Repository, diff: &Diff, base: &Commit) -> Result<Vec<ReviewerSuggestion>> {
+    let mailmap = history::load_mailmap(repo);
+    let own_email = repo
+        .config()
+        .and_then(|config| config.get_string("user.email"))
+        .unwrap_or_default();
+    let own_email = history::canonical_email(mailmap.as_ref(), "", &own_email);
+
+    let mut by_email: HashMap<String, ReviewerSuggestion> = HashMap::new();
+    for (path, ranges) in changed_lines(diff)?.into_iter().take(MAX_BLAMED_FILES) {
+        let mut options = BlameOptions::new();
+        options.newest_commit(base.id());
+        let blame = match repo.blame_file(Path::new(&path), Some(&mut options)) {
+            Ok(blame) => blame,
+            Err(e) => {
+                debug!("Failed to blame {path}: {e}");
+                continue;
+            }
+        };
+        for (start, count) in ranges {
+            for line in start..start + count {
+                let Some(hunk) = blame.get_line(line) else {
+                    continue;
+                };
+                // The same person under several addresses is one reviewer
+                let signature = hunk.final_signature();
+                let signature = match &mailmap {
+                    Some(mailmap) => mailmap.resolve_signature(&signature).unwrap_or(signature),
+                    None => signature,
+                };
+                let Some(email) = signature.email().map(str::to_lowercase) else {
+                    continue;
+                };
+                if email.is_empty() || email == own_email {
+                    continue;
+                }
+                let reviewer =
+                    by_email
+                        .entry(email.clone())
+                        .or_insert_with(|| ReviewerSuggestion {
+                            name: signature.name().unwrap_or(&email).to_string(),
+                            email,
+                            lines: 0,
+                            files: Vec::new(),
+                        });
+                reviewer.lines += 1;
+                if reviewer.files.last() != Some(&path) {
+                    reviewer.files.push(path.clone());
+                }
+            }
+        }
+    }
+
+    let mut reviewers: Vec<ReviewerSuggestion> = by_email.into_values().collect();
+    reviewers.sort_by(|a, b| b.lines.cmp(&a.lines).then_with(|| a.name.cmp(&b.name)));
+    reviewers.truncate(MAX_REVIEWERS);
+    Ok(reviewers)
+}
+
+/// Reviewers for the staged changes: the authors of the lines they modify in `HEAD`
+pub fn reviewers_for_staged(repo: &Repository) -> Result<Vec<ReviewerSuggestion>> {
+    let Ok(head) = repo.head().and_then(|head| head.peel_to_commit()) else {
+        return Ok(Vec::new());
+    };
+    let diff = repo.diff_tree_to_index(Some(&head.tree()?), None, Some(&mut without_context()))?;
+    blame_authors(repo, &diff, &head)
+}
+
+/// Reviewers for `from..to`: the authors of the modified lines as of the merge base
+pub fn reviewers_for_range(
+    repo: &Repository,
+    from: &str,
+    to: &str,
+) -> Result<Vec<ReviewerSuggestion>> {
+    let from = repo.revparse_single(from)?.peel_to_commit()?;
+    let to = repo.revparse_single(to)?.peel_to_commit()?;
+    let base: Oid = repo.merge_base(from.id(), to.id())?;
+    let base = repo.find_commit(base)?;
+    let diff = repo.diff_tree_to_tree(
+        Some(&base.tree()?),
+        Some(&to.tree()?),
+        Some(&mut without_context()),
+    )?;
+    blame_authors(repo, &diff, &base)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use git2::Signature;
+    use std::fs;
+
+    fn commit_file(repo: &Repository, author: &str, content: &str) -> Oid {
+        let workdir = repo.workdir().expect("Repository should have a workdir");
+        fs::write(workdir.join("lib.rs"), content).expect("Failed to write file");
+        let mut index = repo.index().expect("Failed to open index");
+        index
+            .add_path(Path::new("lib.rs"))
+            .expect("Failed to add file");
+        index.write().expect("Failed to write index");
+        let tree = repo
+            .find_tree(index.write_tree().expect("Failed to write tree"))
+            .expect("Failed to find tree");
+        let signature = Signature::now(author, &format!("{}@example.com", author.to_lowercase()))
+            .expect("Valid signature");
+        let parents: Vec<Commit> = repo
+            .head()
+            .ok()
+            .and_then(|head| head.peel_to_commit().ok())
+            .into_iter()
+            .collect();
+        let parents: Vec<&Commit> = parents.iter().collect();
+        repo.commit(
+            Some("HEAD"),
+            &signature,
+            &signature,
+            author,
+            &tree,
+            &parents,
+        )
+        .expect("Failed to commit")
+    }
+
+    #[test]
+    fn test_reviewers_are_the_authors_of_changed_lines() {
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let repo = Repository::init(dir.path()).expect("Failed to init repo");
+        repo.config()
+            .and_then(|mut config| config.set_str("user.email", "me@example.com"))
+            .expect("Failed to set email");
+
+        commit_file(&repo, "Ada", "a\nb\nc\nd\n");
+        let base = commit_file(&repo, "Grace", "a\nB\nC\nd\n");
+        let tip = commit_file(&repo, "Me", "a\nB2\nC2\nd\n");
+
+        let reviewers = reviewers_for_range(&repo, &base.to_string(), &tip.to_string())
+            .expect("Failed to suggest");
+        // Base to tip only touches Grace's lines as of the base
+        assert_eq!(reviewers.len(), 1);
+        assert_eq!(
+            (reviewers[0].name.as_str(), reviewers[0].lines),
+            ("Grace", 2)
+        );
+
+        // The user is never suggested
+        repo.config()
+            .and_then(|mut config| config.set_str("user.email", "grace@example.com"))
+            .expect("Failed to set email");
+        let reviewers = reviewers_for_range(&repo, &base.to_string(), &tip.to_string())
+            .expect("Failed to suggest");
+        assert!(reviewers.is_empty());
+
+        let first = repo
+            .revparse_single("HEAD~2")
+            .expect("First commit")
+            .id()
+            .to_string();
+        let reviewers = reviewers_for_range(&repo, &first, "HEAD").expect("Failed to suggest");
+        assert_eq!(
+            reviewers
+                .iter()
+                .map(|r| (r.name.as_str(), r.lines))
+                .collect::<Vec<_>>(),
+            vec![("Ada", 2)]
+        );
+        assert_eq!(reviewers[0].files, vec!["lib.rs".to_string()]);
+    }
+}
@@ -0,0 +1,182 @@
+//! Samples of large new files, read as a stream.
+//!
+//! A new file's full content goes into the prompt, but a generated client or
+//! a data fixture can run to megabytes. Files over the sample size are read
+//! line by line and only a sample is kept: the head, the tail, and the
+//! definitions in between, so the prompt still sees what the file declares.
+//! Memory stays bounded by the sample size whatever the size of the file.
+
+use anyhow::Result;
+use git2::{Oid, Repository};
+use log::debug;
+use regex::Regex;
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Cursor, Read};
+use std::path::Path;
+use std::sync::LazyLock;
+
+/// Bytes of a line kept; the rest of a longer line is skipped
+const MAX_LINE_BYTES: usize = 400;
+/// Leading bytes checked for the NUL that marks a binary file, as git does
+const BINARY_CHECK_BYTES: usize = 8000;
+
+/// Lines that declare something, in the common languages
+static DEFINITION: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"^\s*(?:(?:pub(?:\([^)]*\))?|export|default|public|private|protected|internal|static|abstract|async|unsafe|final|override)\s+)*(?:fn|def|class|struct|enum|trait|impl|interface|type|func|function|module|mod|object|record|protocol|extension)\b",
+    )
+    .expect("Valid regex")
+});
+
+/// The sample of a text read from `reader`, or `None` for binary content
+///
+/// Text that fits in `budget` bytes comes back whole. Longer text keeps
+/// about two fifths of the budget from its head, a fifth from its tail,
+/// and numbered definition lines from between them with the rest.
+pub fn sample<R: BufRead>(mut reader: R, budget: usize) -> io::Result<Option<String>> {
+    let head_budget = budget * 2 / 5;
+    let tail_budget = budget / 5;
+    let definitions_budget = budget - head_budget - tail_budget;
+
+    let mut head = String::new();
+    let mut tail: VecDeque<(usize, String)> = VecDeque::new();
+    let mut tail_bytes = 0;
+    let mut definitions = Vec::new();
+    let mut definitions_bytes = 0;
+    let mut checked = 0;
+    let mut lines = 0;
+    let mut dropped = false;
+    let mut buf = Vec::new();
+
+    while read_line(&mut reader, &mut buf)? {
+        if checked < BINARY_CHECK_BYTES {
+            if buf.contains(&0) {
+                return Ok(None);
+            }
+            checked += buf.len();
+        }
+        lines += 1;
+        let line = String::from_utf8_lossy(&buf).into_owned();
+
+        if tail.is_empty() && head.len() + line.len() <= head_budget {
+            head.push_str(&line);
+            continue;
+        }
+        tail_bytes += line.len();
+        tail.push_back((lines, line));
+        // The last line stays, however long
+        while tail_bytes > tail_budget && tail.len() > 1 {
+            let Some((number, line)) = tail.pop_front() else {
+                break;
+            };
+            tail_bytes -= line.len();
+            dropped = true;
+            if definitions_bytes + line.len() <= definitions_budget && DEFINITION.is_match(&line) {
+                definitions_bytes += line.len();
+                definitions.push((number, line));
+            }
+        }
+    }
+
+    let mut out = head;
+    if dropped {
+        let shown = out.lines().count() + definitions.len() + tail.len();
+        let _ = writeln!(out, "[... {shown} of {lines} lines sampled ...]");
+        for (number, line) in &definitions {
+            let _ = write!(out, "{number}: {line}");
+        }
+        if !definitions.is_empty() {
+            out.push_str("[...]\n");
+        }
+    }
+    for (_, line) in tail {
+        out.push_str(&line);
+    }
+    Ok(Some(out))
+}
+
+/// Reads one line into `buf`, cut to [`MAX_LINE_BYTES`], and whether there was one
+fn read_line<R: BufRead>(reader: &mut R, buf: &mut Vec<u8>) -> io::Result<bool> {
+    buf.clear();
+    let limit = u64::try_from(MAX_LINE_BYTES).unwrap_or(u64::MAX);
+    if <&mut R as Read>::take(&mut *reader, limit).read_until(b'\n', buf)? == 0 {
+        return Ok(false);
+    }
+    if buf.last() != Some(&b'\n') {
+        // Skip the rest of an overlong line, such as minified code
+        loop {
+            let available = reader.fill_buf()?;
+            if available.is_empty() {
+                break;
+            }
+            if let Some(end) = available.iter().position(|&b| b == b'\n') {
+                reader.consume(end + 1);
+                break;
+            }
+            let len = available.len();
+            reader.consume(len);
+        }
+        buf.push(b'\n');
+    }
+    Ok(true)
+}
+
+/// The sample of a file in the worktree
+pub fn sample_file(path: &Path, budget: usize) -> Result<Option<String>> {
+    Ok(sample(BufReader::new(File::open(path)?), budget)?)
+}
+
+/// The sample of a blob, streamed from the object database when it allows
+///
+/// Loose objects, which `git add` writes, are streamed; packed ones are
+/// loaded whole, as libgit2 can only stream loose objects.
+pub fn sample_blob(repo: &Repository, id: Oid, budget: usize) -> Result<Option<String>> {
+    let odb = repo.odb()?;
+    if let Ok((reader, _, _)) = odb.reader(id) {
+        return Ok(sample(BufReader::new(reader), budget)?);
+    }
+    debug!("Blob {id} can't be streamed, loading it whole");
+    let blob = repo.find_blob(id)?;
+    Ok(sample(Cursor::new(blob.content()), budget)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_small_text_comes_back_whole() {
+        let text = "fn main() {}\n";
+        let sampled = sample(Cursor::new(text), 1024).expect("Failed to sample");
+        assert_eq!(sampled.as_deref(), Some(text));
+        assert_eq!(sample(Cursor::new("a\0b"), 1024).expect("Failed"), None);
+    }
+
+    #[test]
+    fn test_large_text_keeps_head_tail_and_definitions() {
+        let mut text = String::from("//! Generated client\n");
+        for i in 0..5000 {
+            if i % 1000 == 500 {
+                let _ = writeln!(text, "pub fn endpoint_{i}() {{");
+            } else {
+                let _ = writeln!(text, "    let value_{i} = {i};");
+            }
+        }
+        text.push_str("// end\n");
+        text.push_str(&"x".repeat(10_000));
+
+        let sampled = sample(Cursor::new(&text), 3000)
+            .expect("Failed to sample")
+            .expect("Text is not binary");
+        assert!(sampled.len() <= 3100, "{} bytes", sampled.len());
+        assert!(sampled.starts_with("//! Generated client\n"));
+        assert!(sampled.contains("lines sampled ...]"));
+        assert!(sampled.contains("502: pub fn endpoint_500() {"));
+        assert!(sampled.contains("4502: pub fn endpoint_4500() {"));
+        assert!(sampled.contains("// end\n"));
+        // The overlong last line is cut
+        assert!(sampled.ends_with(&format!("{}\n", "x".repeat(MAX_LINE_BYTES))));
+    }
+}
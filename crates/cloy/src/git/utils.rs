@@ -1,5 +1,7 @@
+use crate::llm::context::DiffSource;
 use anyhow::Result;
 use git2::Repository;
+use std::path::Path;
 
 /// Checks if the current directory is inside a Git work tree.
 ///
@@ -10,10 +12,100 @@ use git2::Repository;
 pub fn is_inside_work_tree() -> Result<bool> {
     match Repository::discover(".") {
         Ok(_) => Ok(true),
-        Err(_) => Ok(false),
+        Err(_) => Ok(super::WorkingCopy::discover(&std::env::current_dir()?).is_some()),
     }
 }
 
+/// Branch HEAD will point to once the first commit exists.
+///
+/// In a repository without commits HEAD is a symbolic reference to a branch
+/// that does not exist yet, so `repo.head()` fails; its target is still known.
+pub fn unborn_branch_name(repo: &Repository) -> String {
+    repo.find_reference("HEAD")
+        .ok()
+        .and_then(|head| {
+            head.symbolic_target()
+                .and_then(|target| target.strip_prefix("refs/heads/"))
+                .map(ToString::to_string)
+        })
+        .unwrap_or_else(|| "main".to_string())
+}
+
+/// Minimum similarity, in percent, for a deleted and an added file to pair up
+const RENAME_THRESHOLD: u16 = 50;
+
+/// Most files compared for rename detection, matching git's `diff.renameLimit`
+const RENAME_LIMIT: usize = 1000;
+
+/// Pairs deleted and added files into renames and copies, like `git diff -M -C`
+pub fn detect_renames(diff: &mut git2::Diff<'_>) -> Result<()> {
+    let mut find_options = git2::DiffFindOptions::new();
+    find_options
+        .renames(true)
+        .copies(true)
+        .rename_threshold(RENAME_THRESHOLD)
+        .copy_threshold(RENAME_THRESHOLD)
+        .rename_limit(RENAME_LIMIT);
+    diff.find_similar(Some(&mut find_options))?;
+    Ok(())
+}
+
+/// Similarity of a renamed or copied file in percent.
+///
+/// git2 does not expose the score libgit2 computed, but the patch header
+/// carries it; a patch without that header is estimated from its line counts.
+pub fn rename_similarity(patch: &mut git2::Patch<'_>) -> u32 {
+    if let Ok(buf) = patch.to_buf()
+        && let Some(similarity) = parse_similarity_header(&String::from_utf8_lossy(&buf))
+    {
+        return similarity;
+    }
+    let Ok((context, additions, deletions)) = patch.line_stats() else {
+        return 0;
+    };
+    let largest = (context + additions).max(context + deletions);
+    if largest == 0 {
+        return 100;
+    }
+    u32::try_from(context * 100 / largest).unwrap_or(0)
+}
+
+fn parse_similarity_header(patch: &str) -> Option<u32> {
+    patch
+        .lines()
+        .take_while(|line| !line.starts_with("@@"))
+        .find_map(|line| line.strip_prefix("similarity index "))
+        .and_then(|rest| rest.trim_end_matches('%').parse().ok())
+}
+
+/// The bytes of a file on the new or old side of a diff from `source`.
+///
+/// `None` when the file is not there or the source is not in the repository.
+pub fn blob_content(
+    repo: &Repository,
+    source: &DiffSource,
+    path: &str,
+    new: bool,
+) -> Option<Vec<u8>> {
+    let tree_entry = |rev: &str| {
+        repo.revparse_single(rev)
+            .ok()?
+            .peel_to_tree()
+            .ok()?
+            .get_path(Path::new(path))
+            .ok()
+            .map(|entry| entry.id())
+    };
+    let id = match source {
+        DiffSource::Unknown => return None,
+        DiffSource::Staged if new => repo.index().ok()?.get_path(Path::new(path), 0)?.id,
+        DiffSource::Staged => tree_entry("HEAD")?,
+        DiffSource::Commits { to, .. } if new => tree_entry(to)?,
+        DiffSource::Commits { from, .. } => tree_entry(from.as_deref()?)?,
+    };
+    Some(repo.find_blob(id).ok()?.content().to_vec())
+}
+
 /// Determines if the given diff represents a binary file.
 #[inline]
 pub fn is_binary_diff(diff: &str) -> bool {
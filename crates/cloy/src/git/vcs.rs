@@ -0,0 +1,303 @@
+//! Jujutsu and Sapling working copies.
+//!
+//! jj and Sapling can keep their history in a Git store, but they have no
+//! staging area: the change being described is the working copy itself. Its
+//! changes and recent history are read through the tool's own command line,
+//! and committing goes through it too, so its bookkeeping stays right. The
+//! backing Git store serves everything else gitai reads with libgit2, such as
+//! the author's history and style.
+
+use super::files_from_patch;
+use crate::git::CommitResult;
+use crate::llm::context::{ChangeType, RecentCommit, StagedFile};
+use anyhow::{Context, Result, anyhow};
+use git2::{FileMode, Repository};
+use log::debug;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Fields and records in log output are separated by NUL, which descriptions never hold
+const LOG_SEPARATOR: char = '\0';
+
+/// A Git-compatible frontend with its own working copy model
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Frontend {
+    Jujutsu,
+    Sapling,
+}
+
+impl Frontend {
+    /// The command-line tool
+    fn program(self) -> &'static str {
+        match self {
+            Self::Jujutsu => "jj",
+            Self::Sapling => "sl",
+        }
+    }
+
+    /// The directory marking a working copy's root
+    fn marker(self) -> &'static str {
+        match self {
+            Self::Jujutsu => ".jj",
+            Self::Sapling => ".sl",
+        }
+    }
+
+    /// The Git store inside the marker directory, when none is colocated
+    fn store(self) -> &'static str {
+        match self {
+            Self::Jujutsu => "repo/store/git",
+            Self::Sapling => "store/git",
+        }
+    }
+}
+
+/// A jj or Sapling working copy
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkingCopy {
+    pub frontend: Frontend,
+    pub root: PathBuf,
+}
+
+impl WorkingCopy {
+    /// The jj or Sapling working copy `dir` is in; `None` in a plain Git work tree
+    pub fn discover(dir: &Path) -> Option<Self> {
+        for ancestor in dir.ancestors() {
+            // A colocated jj repository has `.git` too, so markers are checked first
+            for frontend in [Frontend::Jujutsu, Frontend::Sapling] {
+                if ancestor.join(frontend.marker()).is_dir() {
+                    return Some(Self {
+                        frontend,
+                        root: ancestor.to_path_buf(),
+                    });
+                }
+            }
+            if ancestor.join(".git").exists() {
+                return None;
+            }
+        }
+        None
+    }
+
+    /// The Git repository holding the history: colocated, or inside the marker directory
+    pub fn git_dir(&self) -> PathBuf {
+        let colocated = self.root.join(".git");
+        if colocated.exists() {
+            colocated
+        } else {
+            self.root
+                .join(self.frontend.marker())
+                .join(self.frontend.store())
+        }
+    }
+
+    /// Opens the backing Git store
+    pub fn open_store(&self) -> Result<Repository> {
+        let dir = self.git_dir();
+        Repository::open(&dir).with_context(|| {
+            format!(
+                "No Git store at {}; gitai needs a {} repository with the Git backend",
+                dir.display(),
+                self.frontend.program()
+            )
+        })
+    }
+
+    /// Runs the frontend's tool in the root and returns its output
+    fn run(&self, args: &[&str]) -> Result<String> {
+        let program = self.frontend.program();
+        let mut command = Command::new(program);
+        if self.frontend == Frontend::Jujutsu {
+            command.arg("--color=never");
+        }
+        let output = command
+            .args(args)
+            .current_dir(&self.root)
+            .env("HGPLAIN", "1")
+            .output()
+            .with_context(|| format!("Failed to run {program}; is it installed?"))?;
+        if !output.status.success() {
+            return Err(anyhow!(
+                "{program} {} failed: {}",
+                args.first().unwrap_or(&""),
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+
+    /// The changes in the working copy, which is what a commit will record
+    pub fn changes(&self) -> Result<Vec<StagedFile>> {
+        let patch = self.run(&["diff", "--git"])?;
+        if patch.trim().is_empty() {
+            return Ok(Vec::new());
+        }
+        files_from_patch(&patch)
+    }
+
+    /// The latest `count` commits below the working copy, newest first
+    pub fn recent_commits(&self, count: usize) -> Result<Vec<RecentCommit>> {
+        let count = count.to_string();
+        let output = match self.frontend {
+            Frontend::Jujutsu => self.run(&[
+                "log",
+                "--no-graph",
+                "-r",
+                "::@- & ~root()",
+                "-n",
+                &count,
+                "-T",
+                r#"commit_id ++ "\0" ++ committer.timestamp().format("%s") ++ "\0" ++ description ++ "\0""#,
+            ])?,
+            Frontend::Sapling => self.run(&[
+                "log",
+                "-r",
+                "reverse(::.)",
+                "-l",
+                &count,
+                "-T",
+                r"{node}\0{date|hgdate}\0{desc}\0",
+            ])?,
+        };
+        Ok(parse_log(&output))
+    }
+
+    /// The bookmark the working copy builds on, or the tool's name for it
+    pub fn branch(&self) -> String {
+        let output = match self.frontend {
+            Frontend::Jujutsu => self.run(&[
+                "log",
+                "--no-graph",
+                "-r",
+                "heads(::@ & bookmarks())",
+                "-T",
+                r#"bookmarks ++ "\n""#,
+            ]),
+            Frontend::Sapling => self.run(&["log", "-r", ".", "-T", "{activebookmark}"]),
+        };
+        output
+            .ok()
+            .and_then(|output| {
+                let name = output.split_whitespace().next()?;
+                // jj marks diverged and remote-tracking bookmarks
+                let name = name.split('@').next()?.trim_end_matches(['*', '?']);
+                (!name.is_empty()).then(|| name.to_string())
+            })
+            .unwrap_or_else(|| "@".to_string())
+    }
+
+    /// The id of the commit a new commit was just recorded as
+    fn last_commit(&self) -> Result<String> {
+        match self.frontend {
+            Frontend::Jujutsu => self.run(&["log", "--no-graph", "-r", "@-", "-T", "commit_id"]),
+            Frontend::Sapling => self.run(&["log", "-r", ".", "-T", "{node}"]),
+        }
+        .map(|id| id.trim().to_string())
+    }
+
+    /// Commits the working copy's changes with `message`
+    pub fn commit(&self, message: &str) -> Result<CommitResult> {
+        let changes = self.changes()?;
+        if changes.is_empty() {
+            return Err(anyhow!("Nothing to commit"));
+        }
+        let branch = self.branch();
+        self.run(&["commit", "-m", message])?;
+        let commit_hash = self.last_commit()?;
+        debug!("Committed {commit_hash} with {}", self.frontend.program());
+
+        let (insertions, deletions) = line_counts(&changes);
+        Ok(CommitResult {
+            branch,
+            commit_hash: commit_hash.chars().take(7).collect(),
+            files_changed: changes.len(),
+            insertions,
+            deletions,
+            new_files: changes
+                .iter()
+                .filter(|file| file.change_type == ChangeType::Added)
+                .map(|file| (file.path.clone(), FileMode::Blob))
+                .collect(),
+            root_commit: false,
+        })
+    }
+}
+
+/// Commits from log output with NUL-separated id, timestamp and description
+fn parse_log(output: &str) -> Vec<RecentCommit> {
+    let fields: Vec<&str> = output.split(LOG_SEPARATOR).collect();
+    fields
+        .chunks_exact(3)
+        .map(|record| RecentCommit {
+            hash: record[0].trim().to_string(),
+            // Sapling's hgdate is "<seconds> <offset>"
+            timestamp: record[1]
+                .split_whitespace()
+                .next()
+                .unwrap_or_default()
+                .to_string(),
+            message: record[2].trim_end().to_string(),
+        })
+        .filter(|commit| !commit.hash.is_empty())
+        .collect()
+}
+
+/// Added and removed lines across the diffs of `files`
+fn line_counts(files: &[StagedFile]) -> (usize, usize) {
+    let lines = files.iter().flat_map(|file| file.diff.lines());
+    lines.fold((0, 0), |(added, removed), line| {
+        if line.starts_with('+') && !line.starts_with("+++") {
+            (added + 1, removed)
+        } else if line.starts_with('-') && !line.starts_with("---") {
+            (added, removed + 1)
+        } else {
+            (added, removed)
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_discover_prefers_frontend_markers() {
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let nested = dir.path().join("src").join("deep");
+        fs::create_dir_all(&nested).expect("Failed to create dirs");
+        assert_eq!(WorkingCopy::discover(&nested), None);
+
+        // Colocated jj: both markers, jj wins
+        fs::create_dir(dir.path().join(".git")).expect("Failed to create .git");
+        assert_eq!(WorkingCopy::discover(&nested), None);
+        fs::create_dir(dir.path().join(".jj")).expect("Failed to create .jj");
+        let copy = WorkingCopy::discover(&nested).expect("A jj working copy");
+        assert_eq!(copy.frontend, Frontend::Jujutsu);
+        assert_eq!(copy.root, dir.path());
+        assert_eq!(copy.git_dir(), dir.path().join(".git"));
+
+        // A nested Git repository is not part of the outer working copy
+        fs::create_dir(nested.join(".git")).expect("Failed to create nested .git");
+        assert_eq!(WorkingCopy::discover(&nested), None);
+
+        let sapling = tempfile::tempdir().expect("Failed to create temp dir");
+        fs::create_dir(sapling.path().join(".sl")).expect("Failed to create .sl");
+        let copy = WorkingCopy::discover(sapling.path()).expect("A Sapling working copy");
+        assert_eq!(copy.frontend, Frontend::Sapling);
+        assert_eq!(copy.git_dir(), sapling.path().join(".sl/store/git"));
+    }
+
+    #[test]
+    fn test_parse_log() {
+        let output =
+            "abc123\x001700000000\0Add parser\n\nWith tests.\n\0def456\x001690000000 -7200\0Init\0";
+        let commits = parse_log(output);
+        assert_eq!(commits.len(), 2);
+        assert_eq!(commits[0].hash, "abc123");
+        assert_eq!(commits[0].timestamp, "1700000000");
+        assert_eq!(commits[0].message, "Add parser\n\nWith tests.");
+        assert_eq!(commits[1].timestamp, "1690000000");
+        assert!(parse_log("").is_empty());
+    }
+}
@@ -0,0 +1,231 @@
+//! Word-level rendering of line diffs.
+//!
+//! In prose a one-word edit rewrites a whole paragraph line, and the line
+//! diff shows it twice. With `gitai.word-diff` each run of removed lines
+//! followed by added lines is merged into the words that changed, marked the
+//! way `git diff --word-diff=plain` marks them: `[-removed-]{+added+}`. Lines
+//! only removed or only added keep their `-` and `+`.
+//!
+//! Staged files keep their line diffs, which analyzers and classifiers read;
+//! the word-level form is rendered only as the prompt is built.
+
+use crate::config::DiffSettings;
+use crate::llm::context::StagedFile;
+use git2::DiffOptions;
+
+/// Runs whose token table would be larger are left as line diffs
+const MAX_TABLE_CELLS: usize = 1_000_000;
+
+/// Diff options for `settings`
+pub(crate) fn diff_options(settings: DiffSettings) -> DiffOptions {
+    let mut options = DiffOptions::new();
+    options.context_lines(settings.context_lines);
+    options
+}
+
+/// Merges the changed lines of each file's diff word by word in word-diff mode
+pub fn render_word_diffs(files: &mut [StagedFile], settings: DiffSettings) {
+    if !settings.word_diff {
+        return;
+    }
+    for file in files {
+        file.diff = word_diff(&file.diff);
+    }
+}
+
+/// Words, runs of spaces, punctuation marks and newlines
+fn tokens(text: &str) -> Vec<&str> {
+    let mut tokens = Vec::new();
+    let mut start = 0;
+    let kind = |c: char| {
+        if c.is_alphanumeric() || c == '_' {
+            0
+        } else if c == ' ' || c == '\t' {
+            1
+        } else {
+            2
+        }
+    };
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        let next = chars.peek().map(|&(_, next)| next);
+        // Punctuation and newlines stand alone; words and spaces run on
+        if kind(c) == 2 || next.is_none_or(|next| kind(next) != kind(c)) {
+            let end = i + c.len_utf8();
+            tokens.push(&text[start..end]);
+            start = end;
+        }
+    }
+    tokens
+}
+
+/// Wraps each line of `text` in `open` and `close`
+fn mark(out: &mut String, text: &str, open: &str, close: &str) {
+    let lines: Vec<String> = text
+        .split('\n')
+        .map(|line| {
+            if line.is_empty() {
+                String::new()
+            } else {
+                format!("{open}{line}{close}")
+            }
+        })
+        .collect();
+    out.push_str(&lines.join("\n"));
+}
+
+/// `old` and `new` as one text, with the words only in one of them marked
+fn merge_words(old: &str, new: &str) -> Option<String> {
+    let (old, new) = (tokens(old), tokens(new));
+    if old.len().saturating_mul(new.len()) > MAX_TABLE_CELLS {
+        return None;
+    }
+    // Length of the longest common subsequence of each pair of suffixes
+    let width = new.len() + 1;
+    let mut table = vec![0u32; (old.len() + 1) * width];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            table[i * width + j] = if old[i] == new[j] {
+                table[(i + 1) * width + j + 1] + 1
+            } else {
+                table[(i + 1) * width + j].max(table[i * width + j + 1])
+            };
+        }
+    }
+
+    let mut out = String::new();
+    let (mut removed, mut added) = (String::new(), String::new());
+    let flush = |out: &mut String, removed: &mut String, added: &mut String| {
+        mark(out, removed, "[-", "-]");
+        mark(out, added, "{+", "+}");
+        removed.clear();
+        added.clear();
+    };
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            flush(&mut out, &mut removed, &mut added);
+            out.push_str(old[i]);
+            i += 1;
+            j += 1;
+        } else if j == new.len()
+            || (i < old.len() && table[(i + 1) * width + j] >= table[i * width + j + 1])
+        {
+            removed.push_str(old[i]);
+            i += 1;
+        } else {
+            added.push_str(new[j]);
+            j += 1;
+        }
+    }
+    flush(&mut out, &mut removed, &mut added);
+    Some(out)
+}
+
+/// Writes a run of removed and added lines, merged when it has both
+fn flush_run(out: &mut String, removed: &mut Vec<&str>, added: &mut Vec<&str>) {
+    let merged = (!removed.is_empty() && !added.is_empty())
+        .then(|| merge_words(&removed.join("\n"), &added.join("\n")))
+        .flatten();
+    if let Some(merged) = merged {
+        for line in merged.split('\n') {
+            out.push(' ');
+            out.push_str(line);
+            out.push('\n');
+        }
+    } else {
+        for (origin, lines) in [('-', &*removed), ('+', &*added)] {
+            for line in lines {
+                out.push(origin);
+                out.push_str(line);
+                out.push('\n');
+            }
+        }
+    }
+    removed.clear();
+    added.clear();
+}
+
+/// A unified diff with each run of removed then added lines merged word by word
+fn word_diff(patch: &str) -> String {
+    let mut out = String::with_capacity(patch.len());
+    let mut in_hunk = false;
+    let (mut removed, mut added) = (Vec::new(), Vec::new());
+    for line in patch.split_inclusive('\n') {
+        let text = line.strip_suffix('\n').unwrap_or(line);
+        if in_hunk && let Some(rest) = text.strip_prefix('-') {
+            if !added.is_empty() {
+                flush_run(&mut out, &mut removed, &mut added);
+            }
+            removed.push(rest);
+            continue;
+        }
+        if in_hunk && let Some(rest) = text.strip_prefix('+') {
+            added.push(rest);
+            continue;
+        }
+        flush_run(&mut out, &mut removed, &mut added);
+        if text.trim_start().starts_with("@@") {
+            in_hunk = true;
+        } else if text.starts_with("diff --git") {
+            in_hunk = false;
+        }
+        out.push_str(line);
+    }
+    flush_run(&mut out, &mut removed, &mut added);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_word_diff() {
+        let patch = "\
+--- a/docs/intro.md
++++ b/docs/intro.md
+@@ -1,4 +1,4 @@
+ # Intro
+-The quick brown fox jumps over the dog.
++The slow brown fox walks past the dog.
+-Old footer
++New footer, rewritten.
++An added line
+";
+        assert_eq!(
+            word_diff(patch),
+            "\
+--- a/docs/intro.md
++++ b/docs/intro.md
+@@ -1,4 +1,4 @@
+ # Intro
+ The [-quick-]{+slow+} brown fox [-jumps-]{+walks+} [-over-]{+past+} the dog.
+ [-Old-]{+New+} footer{+, rewritten.+}
+ {+An added line+}
+"
+        );
+        let added = "@@ -0,0 +1 @@\n+only added\n";
+        assert_eq!(word_diff(added), added);
+    }
+
+    #[test]
+    fn test_render_word_diffs_only_in_word_diff_mode() {
+        let patch = "@@ -1 +1 @@\n-old text\n+new text\n";
+        let mut files = vec![StagedFile {
+            path: "README.md".to_string(),
+            change_type: crate::llm::context::ChangeType::Modified,
+            diff: patch.to_string(),
+            content: None,
+            content_excluded: false,
+        }];
+        render_word_diffs(&mut files, DiffSettings::default());
+        assert_eq!(files[0].diff, patch);
+        let settings = DiffSettings {
+            word_diff: true,
+            ..DiffSettings::default()
+        };
+        render_word_diffs(&mut files, settings);
+        assert_eq!(files[0].diff, "@@ -1 +1 @@\n [-old-]{+new+} text\n");
+    }
+}
@@ -0,0 +1,336 @@
+//! Packages of a Cargo, npm or pnpm workspace, read from a commit's tree.
+
+use anyhow::Result;
+use git2::{ObjectType, Repository, Tree, TreeWalkMode, TreeWalkResult};
+use regex::Regex;
+use std::sync::LazyLock;
+
+static QUOTED: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#""([^"]*)"|'([^']*)'"#).expect("Valid regex"));
+
+/// A member of the repository's workspace
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Package {
+    pub name: String,
+    /// Directory of the package, relative to the repository root
+    pub path: String,
+    /// `cargo`, `npm` or `pnpm`
+    pub manager: &'static str,
+}
+
+impl Package {
+    /// Whether `file`, relative to the repository root, belongs to the package
+    pub fn contains(&self, file: &str) -> bool {
+        file.strip_prefix(&self.path)
+            .is_some_and(|rest| rest.starts_with('/'))
+    }
+}
+
+/// A workspace member pattern such as `crates/*` as a regex over directories
+fn member_regex(pattern: &str) -> Option<Regex> {
+    let pattern = pattern
+        .trim()
+        .trim_start_matches("./")
+        .trim_end_matches('/');
+    if pattern.is_empty() {
+        return None;
+    }
+    let mut regex = String::from("^");
+    let mut chars = pattern.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                regex.push_str(".*");
+            }
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    regex.push('$');
+    Regex::new(&regex).ok()
+}
+
+/// Quoted strings of the TOML array `key = [...]` in `section`, which may span lines
+fn toml_array(manifest: &str, section: &str, key: &str) -> Vec<String> {
+    let mut in_section = false;
+    let mut collecting = false;
+    let mut values = Vec::new();
+    for line in manifest.lines() {
+        let line = line.split(" #").next().unwrap_or(line).trim();
+        if !collecting && line.starts_with('[') {
+            in_section = line == format!("[{section}]");
+            continue;
+        }
+        if !in_section {
+            continue;
+        }
+        let rest = if collecting {
+            line
+        } else if let Some((name, value)) = line.split_once('=')
+            && name.trim() == key
+        {
+            collecting = true;
+            value
+        } else {
+            continue;
+        };
+        let (items, closed) = match rest.split_once(']') {
+            Some((items, _)) => (items, true),
+            None => (rest, false),
+        };
+        values.extend(QUOTED.captures_iter(items).filter_map(|captures| {
+            captures
+                .get(1)
+                .or_else(|| captures.get(2))
+                .map(|value| value.as_str().to_string())
+        }));
+        if closed {
+            break;
+        }
+    }
+    values
+}
+
+/// The `name` of a Cargo manifest's `[package]`
+fn cargo_name(manifest: &str) -> Option<String> {
+    let mut in_package = false;
+    for line in manifest.lines() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            in_package = line == "[package]";
+        } else if in_package
+            && let Some((key, value)) = line.split_once('=')
+            && key.trim() == "name"
+        {
+            return QUOTED
+                .captures(value)
+                .and_then(|captures| captures.get(1).or_else(|| captures.get(2)))
+                .map(|name| name.as_str().to_string());
+        }
+    }
+    None
+}
+
+/// The `workspaces` of a `package.json`, as an array or as `{ "packages": [...] }`
+fn npm_workspaces(manifest: &str) -> Vec<String> {
+    let Ok(json) = serde_json::from_str::<serde_json::Value>(manifest) else {
+        return Vec::new();
+    };
+    let workspaces = &json["workspaces"];
+    workspaces
+        .as_array()
+        .or_else(|| workspaces["packages"].as_array())
+        .map(|patterns| {
+            patterns
+                .iter()
+                .filter_map(|pattern| pattern.as_str().map(str::to_string))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn npm_name(manifest: &str) -> Option<String> {
+    let json = serde_json::from_str::<serde_json::Value>(manifest).ok()?;
+    json["name"].as_str().map(str::to_string)
+}
+
+/// The `packages` list of a `pnpm-workspace.yaml`
+fn pnpm_packages(manifest: &str) -> Vec<String> {
+    let mut in_packages = false;
+    let mut packages = Vec::new();
+    for line in manifest.lines() {
+        if line.trim().is_empty() || line.trim_start().starts_with('#') {
+            continue;
+        }
+        if !line.starts_with([' ', '\t', '-']) {
+            in_packages = line.trim_end() == "packages:";
+            continue;
+        }
+        if in_packages && let Some(item) = line.trim().strip_prefix('-') {
+            packages.push(item.trim().trim_matches(['"', '\'']).to_string());
+        }
+    }
+    packages
+}
+
+/// Directories of the packages whose manifest is in `manifests`, selected by
+/// member patterns; `include` patterns starting with `!` exclude
+fn members<'a>(
+    manifests: &'a [String],
+    include: &[String],
+    exclude: &[String],
+) -> impl Iterator<Item = &'a str> {
+    let (negated, positive): (Vec<&String>, Vec<&String>) =
+        include.iter().partition(|pattern| pattern.starts_with('!'));
+    let included: Vec<Regex> = positive
+        .iter()
+        .filter_map(|pattern| member_regex(pattern))
+        .collect();
+    let excluded: Vec<Regex> = negated
+        .iter()
+        .map(|pattern| &pattern[1..])
+        .chain(exclude.iter().map(String::as_str))
+        .filter_map(member_regex)
+        .collect();
+    manifests.iter().filter_map(move |manifest| {
+        let dir = manifest.rsplit_once('/').map(|(dir, _)| dir)?;
+        (included.iter().any(|regex| regex.is_match(dir))
+            && !excluded.iter().any(|regex| regex.is_match(dir)))
+        .then_some(dir)
+    })
+}
+
+fn blob_text(repo: &Repository, tree: &Tree<'_>, path: &str) -> Option<String> {
+    let entry = tree.get_path(std::path::Path::new(path)).ok()?;
+    let blob = repo.find_blob(entry.id()).ok()?;
+    String::from_utf8(blob.content().to_vec()).ok()
+}
+
+/// Paths of the files named `name` in `tree`, outside `node_modules`
+fn manifests_named(tree: &Tree<'_>, name: &str) -> Vec<String> {
+    let mut paths = Vec::new();
+    let walked = tree.walk(TreeWalkMode::PreOrder, |dir, entry| {
+        if entry.name() == Some("node_modules") {
+            return TreeWalkResult::Skip;
+        }
+        if entry.kind() == Some(ObjectType::Blob) && entry.name() == Some(name) {
+            paths.push(format!("{dir}{name}"));
+        }
+        TreeWalkResult::Ok
+    });
+    if let Err(e) = walked {
+        log::debug!("Failed to list {name} files: {e}");
+    }
+    paths
+}
+
+/// The workspace members declared at `rev`, by path. Cargo members come from
+/// `[workspace] members`, npm ones from `workspaces` in `package.json`, and
+/// pnpm ones from `pnpm-workspace.yaml`.
+pub fn workspace_packages(repo: &Repository, rev: &str) -> Result<Vec<Package>> {
+    let tree = repo.revparse_single(rev)?.peel_to_tree()?;
+    let mut packages: Vec<Package> = Vec::new();
+    let mut add = |manifests: &[String], include: &[String], exclude: &[String], manager| {
+        for dir in members(manifests, include, exclude) {
+            if packages.iter().any(|package| package.path == dir) {
+                continue;
+            }
+            let manifest = manifests
+                .iter()
+                .find(|path| path.rsplit_once('/').is_some_and(|(d, _)| d == dir))
+                .and_then(|path| blob_text(repo, &tree, path))
+                .unwrap_or_default();
+            let name = if manager == "cargo" {
+                cargo_name(&manifest)
+            } else {
+                npm_name(&manifest)
+            };
+            packages.push(Package {
+                name: name.unwrap_or_else(|| dir.rsplit('/').next().unwrap_or(dir).to_string()),
+                path: dir.to_string(),
+                manager,
+            });
+        }
+    };
+
+    if let Some(root) = blob_text(repo, &tree, "Cargo.toml") {
+        let include = toml_array(&root, "workspace", "members");
+        if !include.is_empty() {
+            let exclude = toml_array(&root, "workspace", "exclude");
+            add(
+                &manifests_named(&tree, "Cargo.toml"),
+                &include,
+                &exclude,
+                "cargo",
+            );
+        }
+    }
+    let pnpm = blob_text(repo, &tree, "pnpm-workspace.yaml")
+        .map(|manifest| pnpm_packages(&manifest))
+        .unwrap_or_default();
+    let npm = blob_text(repo, &tree, "package.json")
+        .map(|manifest| npm_workspaces(&manifest))
+        .unwrap_or_default();
+    if !pnpm.is_empty() || !npm.is_empty() {
+        let manifests = manifests_named(&tree, "package.json");
+        add(&manifests, &pnpm, &[], "pnpm");
+        add(&manifests, &npm, &[], "npm");
+    }
+    packages.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(packages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    #[test]
+    fn test_workspace_packages() {
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let repo = Repository::init(dir.path()).expect("Failed to init repo");
+        let files = [
+            (
+                "Cargo.toml",
+                "[workspace]\nmembers = [\n    \"crates/*\", # all crates\n    \"tools/gen\",\n]\nexclude = [\"crates/scratch\"]\n",
+            ),
+            (
+                "crates/core/Cargo.toml",
+                "[package]\nname = \"acme-core\"\n",
+            ),
+            (
+                "crates/scratch/Cargo.toml",
+                "[package]\nname = \"scratch\"\n",
+            ),
+            ("tools/gen/Cargo.toml", "[package]\nname = 'gen'\n"),
+            ("examples/demo/Cargo.toml", "[package]\nname = \"demo\"\n"),
+            (
+                "package.json",
+                r#"{"name": "root", "workspaces": ["web/*"]}"#,
+            ),
+            ("web/app/package.json", r#"{"name": "@acme/app"}"#),
+            (
+                "web/app/node_modules/dep/package.json",
+                r#"{"name": "dep"}"#,
+            ),
+            (
+                "pnpm-workspace.yaml",
+                "packages:\n  - 'web/*'\n  - \"!web/legacy\"\n",
+            ),
+            ("web/legacy/package.json", r#"{"name": "legacy"}"#),
+        ];
+        let mut index = repo.index().expect("Failed to open index");
+        for (path, content) in files {
+            let file = dir.path().join(path);
+            std::fs::create_dir_all(file.parent().expect("Parent dir"))
+                .expect("Failed to create dir");
+            std::fs::write(&file, content).expect("Failed to write file");
+            index.add_path(Path::new(path)).expect("Failed to stage");
+        }
+        let tree = repo
+            .find_tree(index.write_tree().expect("Failed to write tree"))
+            .expect("Failed to find tree");
+        let signature = git2::Signature::now("Test", "test@example.com").expect("Signature");
+        repo.commit(Some("HEAD"), &signature, &signature, "init", &tree, &[])
+            .expect("Failed to commit");
+
+        let packages = workspace_packages(&repo, "HEAD").expect("Failed to read packages");
+        let found: Vec<(&str, &str, &str)> = packages
+            .iter()
+            .map(|p| (p.name.as_str(), p.path.as_str(), p.manager))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("acme-core", "crates/core", "cargo"),
+                ("gen", "tools/gen", "cargo"),
+                ("@acme/app", "web/app", "pnpm"),
+                ("legacy", "web/legacy", "npm"),
+            ]
+        );
+        assert!(packages[0].contains("crates/core/src/lib.rs"));
+        assert!(!packages[0].contains("crates/core-extra/lib.rs"));
+    }
+}
@@ -0,0 +1,251 @@
+//! Proxy and TLS settings for outbound HTTP.
+//!
+//! gitai's own clients (Gemini, Bedrock and the model APIs) are built from
+//! [`client_builder`], which applies `gitai.proxy`, `gitai.no-proxy`,
+//! `gitai.ca-bundle` and `gitai.tls-backend` (or their `GITAI_*` variables).
+//! Without an explicit proxy they follow `HTTPS_PROXY`, `HTTP_PROXY` and
+//! `NO_PROXY` like any other tool. The llm backends (`OpenRouter`, Azure and
+//! `OpenAI`-compatible servers) build their own clients and only see those
+//! standard variables and `SSL_CERT_FILE`, so requests through them are
+//! refused by [`ensure_reaches`] when a setting would be lost.
+
+use crate::config::load_http_settings;
+use crate::llm::provider::ProviderKind;
+use anyhow::{Context, Result, anyhow};
+use reqwest::{Certificate, Client, ClientBuilder, NoProxy, Proxy};
+use std::path::PathBuf;
+use std::sync::LazyLock;
+use std::time::Duration;
+
+/// TLS implementation used when `gitai.tls-backend` is unset
+pub const DEFAULT_TLS_BACKEND: &str = "rustls";
+
+/// Proxy and TLS settings, read once per process
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HttpSettings {
+    /// Proxy URL all requests go through, such as `http://proxy.corp:3128`
+    pub proxy: Option<String>,
+    /// Comma-separated hosts reached without the proxy
+    pub no_proxy: Option<String>,
+    /// PEM file of root certificates trusted in addition to the built-in ones
+    pub ca_bundle: Option<PathBuf>,
+    /// `rustls`, or `native` for the platform's TLS library
+    pub tls_backend: Option<String>,
+}
+
+impl HttpSettings {
+    /// Whether any setting differs from a plain client
+    pub fn is_configured(&self) -> bool {
+        *self != Self::default()
+    }
+
+    /// Applies the settings to a client builder
+    pub fn apply(&self, mut builder: ClientBuilder) -> Result<ClientBuilder> {
+        if let Some(proxy) = &self.proxy {
+            let proxy = Proxy::all(proxy)
+                .with_context(|| format!("Invalid gitai.proxy: {proxy}"))?
+                .no_proxy(self.no_proxy.as_deref().and_then(NoProxy::from_string));
+            builder = builder.proxy(proxy);
+        }
+        if let Some(path) = &self.ca_bundle {
+            let pem = std::fs::read(path)
+                .with_context(|| format!("Failed to read gitai.ca-bundle {}", path.display()))?;
+            let certificates = Certificate::from_pem_bundle(&pem).with_context(|| {
+                format!("gitai.ca-bundle {} is not a PEM bundle", path.display())
+            })?;
+            if certificates.is_empty() {
+                return Err(anyhow!(
+                    "gitai.ca-bundle {} holds no certificates",
+                    path.display()
+                ));
+            }
+            for certificate in certificates {
+                builder = builder.add_root_certificate(certificate);
+            }
+        }
+        match self
+            .tls_backend
+            .as_deref()
+            .map(str::to_lowercase)
+            .as_deref()
+        {
+            None | Some(DEFAULT_TLS_BACKEND) => {}
+            Some("native") => builder = builder.tls_backend_native(),
+            Some(other) => {
+                return Err(anyhow!(
+                    "Unknown gitai.tls-backend {other:?}; use rustls or native"
+                ));
+            }
+        }
+        Ok(builder)
+    }
+
+    /// The settings an llm backend's client would miss, given which standard
+    /// variables `env_is_set`, each with the variable that carries it there
+    pub fn missed_by_backends(
+        &self,
+        env_is_set: impl Fn(&str) -> bool,
+    ) -> Vec<(&'static str, Option<&'static str>)> {
+        let native_tls = self
+            .tls_backend
+            .as_deref()
+            .is_some_and(|backend| backend.eq_ignore_ascii_case("native"));
+        [
+            (self.proxy.is_some(), "gitai.proxy", Some("HTTPS_PROXY")),
+            (self.no_proxy.is_some(), "gitai.no-proxy", Some("NO_PROXY")),
+            (
+                self.ca_bundle.is_some(),
+                "gitai.ca-bundle",
+                Some("SSL_CERT_FILE"),
+            ),
+            (native_tls, "gitai.tls-backend", None),
+        ]
+        .into_iter()
+        .filter(|(configured, _, variable)| *configured && !variable.is_some_and(&env_is_set))
+        .map(|(_, setting, variable)| (setting, variable))
+        .collect()
+    }
+
+    /// Checks the settings by building a client from them
+    pub fn validate(&self) -> Result<()> {
+        self.apply(Client::builder())?
+            .build()
+            .context("Failed to create HTTP client")?;
+        Ok(())
+    }
+}
+
+static SETTINGS: LazyLock<HttpSettings> = LazyLock::new(load_http_settings);
+
+/// The settings from the environment and git config
+pub fn settings() -> &'static HttpSettings {
+    &SETTINGS
+}
+
+/// A client builder with the configured proxy and TLS settings
+pub fn client_builder() -> Result<ClientBuilder> {
+    settings().apply(Client::builder())
+}
+
+/// Fails when `provider` is reached through an llm backend whose client would
+/// not apply the configured proxy and TLS settings
+pub fn ensure_reaches(provider: ProviderKind) -> Result<()> {
+    if provider.uses_own_client() {
+        return Ok(());
+    }
+    let env_is_set = |name: &str| {
+        [name.to_string(), name.to_lowercase()]
+            .iter()
+            .any(|name| std::env::var_os(name).is_some_and(|value| !value.is_empty()))
+    };
+    let missed = settings().missed_by_backends(env_is_set);
+    if missed.is_empty() {
+        return Ok(());
+    }
+    Err(anyhow!(
+        "{provider} requests go through the LLM client library, which cannot apply {}. {}",
+        missed
+            .iter()
+            .map(|(setting, _)| *setting)
+            .collect::<Vec<_>>()
+            .join(", "),
+        backend_fix(&missed)
+    ))
+}
+
+/// How to carry the settings an llm backend misses to its client
+pub fn backend_fix(missed: &[(&str, Option<&str>)]) -> String {
+    let variables: Vec<&str> = missed
+        .iter()
+        .filter_map(|(_, variable)| *variable)
+        .collect();
+    if variables.is_empty() {
+        "Unset them, or use Google or Bedrock".to_string()
+    } else {
+        format!(
+            "Export {} as well, or unset the rest",
+            variables.join(" and ")
+        )
+    }
+}
+
+/// A client with the configured proxy and TLS settings and a request timeout
+pub fn client(timeout: Option<Duration>) -> Result<Client> {
+    let mut builder = client_builder()?;
+    if let Some(timeout) = timeout {
+        builder = builder.timeout(timeout);
+    }
+    builder.build().context("Failed to create HTTP client")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_settings_validation() {
+        assert!(HttpSettings::default().validate().is_ok());
+        assert!(!HttpSettings::default().is_configured());
+
+        let proxied = HttpSettings {
+            proxy: Some("http://proxy.example.com:3128".to_string()),
+            no_proxy: Some("localhost,.internal".to_string()),
+            tls_backend: Some("native".to_string()),
+            ..HttpSettings::default()
+        };
+        assert!(proxied.validate().is_ok());
+
+        let unknown = HttpSettings {
+            tls_backend: Some("schannel".to_string()),
+            ..HttpSettings::default()
+        };
+        assert!(unknown.validate().is_err());
+
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let path = dir.path().join("ca.pem");
+        std::fs::write(&path, "not a certificate").expect("Failed to write bundle");
+        let bad_bundle = HttpSettings {
+            ca_bundle: Some(path),
+            ..HttpSettings::default()
+        };
+        let error = bad_bundle
+            .validate()
+            .expect_err("Bundle has no certificates");
+        assert!(error.to_string().contains("gitai.ca-bundle"), "{error}");
+    }
+
+    #[test]
+    fn test_settings_missed_by_backends() {
+        assert!(
+            HttpSettings::default()
+                .missed_by_backends(|_| false)
+                .is_empty()
+        );
+
+        let settings = HttpSettings {
+            proxy: Some("http://proxy.example.com:3128".to_string()),
+            ca_bundle: Some(PathBuf::from("/etc/ssl/corp.pem")),
+            tls_backend: Some("rustls".to_string()),
+            ..HttpSettings::default()
+        };
+        assert_eq!(
+            settings.missed_by_backends(|_| false),
+            [
+                ("gitai.proxy", Some("HTTPS_PROXY")),
+                ("gitai.ca-bundle", Some("SSL_CERT_FILE"))
+            ]
+        );
+        assert_eq!(
+            settings.missed_by_backends(|name| name == "HTTPS_PROXY"),
+            [("gitai.ca-bundle", Some("SSL_CERT_FILE"))]
+        );
+
+        let native = HttpSettings {
+            tls_backend: Some("native".to_string()),
+            ..HttpSettings::default()
+        };
+        let missed = native.missed_by_backends(|_| true);
+        assert_eq!(missed, [("gitai.tls-backend", None)]);
+        assert_eq!(backend_fix(&missed), "Unset them, or use Google or Bedrock");
+    }
+}
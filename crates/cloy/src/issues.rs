@@ -0,0 +1,333 @@
+//! Issue tracker context for commit and PR prompts.
+//!
+//! With `gitai.issue-tracker` set to `jira` or `github`, the ticket a branch
+//! names (`PROJ-123-login`, `fix/42-crash`) is fetched and a summary of it is
+//! added to the prompt, so messages describe the requirement being
+//! implemented. Lookups are best effort: a missing token, an unknown ticket
+//! or an unreachable tracker only drops the section.
+
+use crate::config::load_issue_settings;
+use crate::git::GitRepo;
+use crate::http;
+use crate::llm::context::CommitContext;
+use anyhow::{Context, Result, anyhow};
+use log::debug;
+use parking_lot::Mutex;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::LazyLock;
+use std::time::Duration;
+
+/// GitHub API used when `gitai.github-api` is unset
+pub const DEFAULT_GITHUB_API: &str = "https://api.github.com";
+
+/// Characters of the issue description kept in the prompt
+const MAX_SUMMARY_CHARS: usize = 1_200;
+
+/// How long a tracker may take to answer before the issue is skipped
+const LOOKUP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Tracker the branch's ticket is looked up in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IssueTracker {
+    Jira,
+    GitHub,
+}
+
+impl IssueTracker {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "jira" => Some(Self::Jira),
+            "github" => Some(Self::GitHub),
+            _ => None,
+        }
+    }
+}
+
+/// Tracker settings from git config and the environment
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IssueSettings {
+    /// Unset turns lookups off
+    pub tracker: Option<IssueTracker>,
+    /// Base URL of the Jira site, such as `https://example.atlassian.net`
+    pub jira_url: Option<String>,
+    /// Account email for Jira Cloud basic auth; without it the token is sent as a bearer token
+    pub jira_user: Option<String>,
+    pub jira_token: Option<String>,
+    /// Project keys whose tickets are recognized in lowercase branch names
+    pub jira_projects: Vec<String>,
+    pub github_token: Option<String>,
+    /// API root for GitHub Enterprise
+    pub github_api: Option<String>,
+}
+
+/// A ticket referenced by the branch
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Issue {
+    /// `PROJ-123` or `#42`
+    pub reference: String,
+    pub title: String,
+    /// Shortened description
+    pub summary: String,
+    pub url: String,
+}
+
+static JIRA_KEY: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?:^|[^A-Za-z0-9])([A-Za-z][A-Za-z0-9]{1,9})-(\d+)").expect("Valid regex")
+});
+
+static GITHUB_NUMBER: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(?:issues?[-_]?|gh-|#)?(\d+)(?:[-_].*)?$").expect("Valid regex")
+});
+
+/// Issues already fetched in this process, by reference; `None` when the lookup failed
+static FETCHED: LazyLock<Mutex<HashMap<String, Option<Issue>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// The Jira key in a branch name; lowercase keys count only for configured projects
+pub fn jira_key(branch: &str, projects: &[String]) -> Option<String> {
+    JIRA_KEY.captures_iter(branch).find_map(|captures| {
+        let project = &captures[1];
+        let known = projects.iter().any(|p| p.eq_ignore_ascii_case(project));
+        (known || project.chars().all(|c| !c.is_ascii_lowercase()))
+            .then(|| format!("{}-{}", project.to_uppercase(), &captures[2]))
+    })
+}
+
+/// The GitHub issue number in a branch name, from a segment such as `42-crash` or `issue-42`
+pub fn github_number(branch: &str) -> Option<u64> {
+    branch
+        .split(['/', ' '])
+        .find_map(|segment| GITHUB_NUMBER.captures(segment)?[1].parse().ok())
+}
+
+/// `owner/repo` of a GitHub remote URL, over HTTPS or SSH
+pub fn github_repository(remote_url: &str) -> Option<(String, String)> {
+    let path = remote_url
+        .trim()
+        .trim_end_matches('/')
+        .trim_end_matches(".git");
+    let path = path.split_once("://").map_or(path, |(_, rest)| rest);
+    // `git@host:owner/repo` and `host/owner/repo` both end in owner/repo
+    let path = path.rsplit_once(':').map_or(path, |(_, rest)| rest);
+    let mut segments = path.rsplit('/');
+    let repo = segments.next()?;
+    let owner = segments.next()?;
+    (!owner.is_empty() && !repo.is_empty()).then(|| (owner.to_string(), repo.to_string()))
+}
+
+/// `owner/repo` of the repository's URL or its `origin` remote
+pub fn github_remote(repo: &GitRepo) -> Option<(String, String)> {
+    let remote = repo.get_remote_url().map(ToString::to_string).or_else(|| {
+        let repo = repo.open_repo().ok()?;
+        let origin = repo.find_remote("origin").ok()?;
+        origin.url().map(ToString::to_string)
+    })?;
+    github_repository(&remote)
+}
+
+/// Tracker settings and GitHub credentials from git config and the environment
+pub fn settings() -> IssueSettings {
+    load_issue_settings()
+}
+
+/// The first paragraphs of a description, without HTML comments and runs of blank lines
+pub fn summarize_description(description: &str) -> String {
+    let mut text = description.replace("\r\n", "\n");
+    while let Some(start) = text.find("<!--") {
+        let end = text[start..]
+            .find("-->")
+            .map_or(text.len(), |end| start + end + 3);
+        text.replace_range(start..end, "");
+    }
+    let paragraphs: Vec<&str> = text
+        .split("\n\n")
+        .map(str::trim)
+        .filter(|paragraph| !paragraph.is_empty())
+        .collect();
+    let text = paragraphs.join("\n\n");
+    if text.chars().count() <= MAX_SUMMARY_CHARS {
+        return text;
+    }
+    let cut: String = text.chars().take(MAX_SUMMARY_CHARS).collect();
+    format!("{}…", cut.trim_end())
+}
+
+async fn fetch_jira(settings: &IssueSettings, key: &str) -> Result<Issue> {
+    let base = settings
+        .jira_url
+        .as_deref()
+        .ok_or_else(|| anyhow!("gitai.jira-url is not set"))?
+        .trim_end_matches('/');
+    let mut request = http::client(Some(LOOKUP_TIMEOUT))?
+        .get(format!(
+            "{base}/rest/api/2/issue/{key}?fields=summary,description"
+        ))
+        .header("Accept", "application/json");
+    request = match (&settings.jira_user, &settings.jira_token) {
+        (Some(user), Some(token)) => request.basic_auth(user, Some(token)),
+        (None, Some(token)) => request.bearer_auth(token),
+        _ => request,
+    };
+    let body: Value = request
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await
+        .context("Invalid Jira response")?;
+    let fields = &body["fields"];
+    Ok(Issue {
+        reference: key.to_string(),
+        title: fields["summary"].as_str().unwrap_or_default().to_string(),
+        summary: summarize_description(fields["description"].as_str().unwrap_or_default()),
+        url: format!("{base}/browse/{key}"),
+    })
+}
+
+async fn fetch_github(
+    settings: &IssueSettings,
+    (owner, repo): (&str, &str),
+    number: u64,
+) -> Result<Issue> {
+    let api = settings
+        .github_api
+        .as_deref()
+        .unwrap_or(DEFAULT_GITHUB_API)
+        .trim_end_matches('/');
+    let mut request = http::client(Some(LOOKUP_TIMEOUT))?
+        .get(format!("{api}/repos/{owner}/{repo}/issues/{number}"))
+        .header("Accept", "application/vnd.github+json")
+        .header("User-Agent", "gitai");
+    if let Some(token) = &settings.github_token {
+        request = request.bearer_auth(token);
+    }
+    let body: Value = request
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await
+        .context("Invalid GitHub response")?;
+    Ok(Issue {
+        reference: format!("#{number}"),
+        title: body["title"].as_str().unwrap_or_default().to_string(),
+        summary: summarize_description(body["body"].as_str().unwrap_or_default()),
+        url: body["html_url"].as_str().unwrap_or_default().to_string(),
+    })
+}
+
+/// What to fetch for a reference
+enum IssueLookup {
+    Jira(String),
+    GitHub(String, String, u64),
+}
+
+/// Looks up the ticket `branch` names, once per process
+async fn lookup(settings: &IssueSettings, repo: &GitRepo, branch: &str) -> Option<Issue> {
+    let (reference, fetch) = match settings.tracker? {
+        IssueTracker::Jira => {
+            let key = jira_key(branch, &settings.jira_projects)?;
+            (key.clone(), IssueLookup::Jira(key))
+        }
+        IssueTracker::GitHub => {
+            let number = github_number(branch)?;
+            let (owner, name) = github_remote(repo)?;
+            (
+                format!("{owner}/{name}#{number}"),
+                IssueLookup::GitHub(owner, name, number),
+            )
+        }
+    };
+    if let Some(fetched) = FETCHED.lock().get(&reference) {
+        return fetched.clone();
+    }
+    let result = match &fetch {
+        IssueLookup::Jira(key) => fetch_jira(settings, key).await,
+        IssueLookup::GitHub(owner, name, number) => {
+            fetch_github(settings, (owner, name), *number).await
+        }
+    };
+    let issue = result
+        .inspect_err(|e| debug!("Failed to fetch issue {reference}: {e:#}"))
+        .ok();
+    FETCHED.lock().insert(reference, issue.clone());
+    issue
+}
+
+/// Adds the ticket named by the context's branch, or the checked-out branch, to the context
+#[tracing::instrument(skip_all)]
+pub async fn attach_issue(repo: &GitRepo, context: &mut CommitContext) {
+    if context.issue.is_some() {
+        return;
+    }
+    let settings = settings();
+    if settings.tracker.is_none() {
+        return;
+    }
+    let current = repo.get_current_branch().unwrap_or_default();
+    for branch in [context.branch.clone(), current] {
+        if let Some(issue) = lookup(&settings, repo, &branch).await {
+            debug!("Adding issue {} to the prompt", issue.reference);
+            context.issue = Some(issue);
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_references_in_branch_names() {
+        assert_eq!(
+            jira_key("PROJ-123-login", &[]),
+            Some("PROJ-123".to_string())
+        );
+        assert_eq!(
+            jira_key("feature/ABC2-7_fix", &[]),
+            Some("ABC2-7".to_string())
+        );
+        assert_eq!(jira_key("feature/proj-123-login", &[]), None);
+        assert_eq!(
+            jira_key("feature/proj-123-login", &["PROJ".to_string()]),
+            Some("PROJ-123".to_string())
+        );
+        assert_eq!(jira_key("main", &[]), None);
+
+        assert_eq!(github_number("42-fix-crash"), Some(42));
+        assert_eq!(github_number("fix/issue-7"), Some(7));
+        assert_eq!(github_number("main -> gh-9-docs"), Some(9));
+        assert_eq!(github_number("release/v2"), None);
+        assert_eq!(github_number("main"), None);
+    }
+
+    #[test]
+    fn test_github_repository_from_remote() {
+        let expected = Some(("octo".to_string(), "gitai".to_string()));
+        assert_eq!(github_repository("git@github.com:octo/gitai.git"), expected);
+        assert_eq!(github_repository("https://github.com/octo/gitai"), expected);
+        assert_eq!(
+            github_repository("ssh://git@github.com/octo/gitai.git"),
+            expected
+        );
+        assert_eq!(github_repository("gitai"), None);
+    }
+
+    #[test]
+    fn test_description_summary() {
+        let description = "<!-- Describe the bug -->\r\nLogin fails.\r\n\r\n\r\n\r\nSteps below.";
+        assert_eq!(
+            summarize_description(description),
+            "Login fails.\n\nSteps below."
+        );
+
+        let long = "word ".repeat(400);
+        let summary = summarize_description(&long);
+        assert!(summary.ends_with('…'));
+        assert_eq!(summary.chars().count(), MAX_SUMMARY_CHARS);
+    }
+}
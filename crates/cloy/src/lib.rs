@@ -1,13 +1,33 @@
+pub mod analyzers;
 pub mod app;
+pub mod cache_crypt;
+pub mod client;
 pub mod commands;
+pub mod commit_cache;
 pub mod common;
 pub mod config;
+pub mod config_migration;
+pub mod conventions;
+#[cfg(unix)]
+pub mod daemon;
+pub mod error;
 pub mod git;
+pub mod http;
+pub mod issues;
 pub mod llm;
 pub mod output;
+pub mod plugins;
+pub mod progress;
+#[cfg(feature = "test-utils")]
+pub mod testing;
+pub mod trace;
+pub mod trailers;
 pub mod tui;
+pub mod usage;
 
 pub use ::llm::LLMProvider;
+pub use client::{Client, ClientBuilder};
+pub use common::DetailLevel;
 pub use config::Config;
 pub use config::ProviderConfig;
 pub use llm::context::FixedSizeBuffer;
@@ -0,0 +1,273 @@
+//! Public API changes that may break callers, read off the diff.
+//!
+//! Models often miss that a removed `pub fn` or a new parameter breaks
+//! callers, so the diffs of Rust, TypeScript and Java files are scanned for
+//! public declarations that disappear or change their signature. API schemas
+//! add the endpoints, types and fields they remove and the types they narrow,
+//! as read by their analyzers. Declarations are matched line by line, so a
+//! signature spread over several lines is compared by its first line only,
+//! and a crate's visibility to other crates is not known. The findings are
+//! therefore a hint in the prompt; the model decides whether the message
+//! needs a `BREAKING CHANGE:` footer.
+
+use crate::analyzers;
+use crate::llm::context::{CommitContext, StagedFile};
+use crate::llm::test_impact::is_test_path;
+use log::debug;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::LazyLock;
+
+static RUST_ITEM: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r#"^\s*pub\s+(?:(?:const|async|unsafe|extern\s+"[^"]*")\s+)*(fn|struct|enum|trait|type|const|static|mod|union)\s+([A-Za-z_][A-Za-z0-9_]*)"#,
+    )
+    .expect("Valid regex")
+});
+
+static TS_ITEM: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"^\s*export\s+(?:default\s+)?(?:declare\s+)?(?:abstract\s+)?(?:async\s+)?(function|class|interface|type|const|let|var|enum)\s+([A-Za-z_$][\w$]*)",
+    )
+    .expect("Valid regex")
+});
+
+static JAVA_ITEM: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"^\s*public\s+(?:(?:static|final|abstract|synchronized|default|sealed)\s+)*(?:(class|interface|enum|record)\s+(\w+)|[\w<>\[\],.? ]+?\s+(\w+)\s*\()",
+    )
+    .expect("Valid regex")
+});
+
+/// What happened to a public item
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApiChangeKind {
+    /// Gone from the file, or renamed
+    Removed,
+    /// Still there with a different signature
+    SignatureChanged,
+    /// Accepts or returns less than before, such as a newly required parameter
+    Narrowed,
+}
+
+/// A change to a public item that can break callers
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ApiChange {
+    pub path: String,
+    pub kind: ApiChangeKind,
+    /// Kind and name of the item, such as `fn parse`
+    pub item: String,
+    /// Declaration before the change
+    pub before: String,
+    /// Declaration after the change, for signature changes
+    pub after: Option<String>,
+}
+
+impl fmt::Display for ApiChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.kind, &self.after) {
+            (ApiChangeKind::SignatureChanged, Some(after)) => write!(
+                f,
+                "{}: signature of `{}` changed from `{}` to `{after}`",
+                self.path, self.item, self.before
+            ),
+            (ApiChangeKind::Narrowed, Some(after)) => write!(
+                f,
+                "{}: `{}` narrowed from `{}` to `{after}`",
+                self.path, self.item, self.before
+            ),
+            _ => write!(f, "{}: removed or renamed `{}`", self.path, self.before),
+        }
+    }
+}
+
+/// A public declaration on one diff line
+struct Declaration {
+    item: String,
+    signature: String,
+}
+
+/// The public declaration on `line` of a file with extension `ext`
+fn declaration(ext: &str, line: &str) -> Option<Declaration> {
+    let regex: &Regex = match ext {
+        "rs" => &RUST_ITEM,
+        "ts" | "tsx" | "mts" | "cts" => &TS_ITEM,
+        "java" => &JAVA_ITEM,
+        _ => return None,
+    };
+    let captures = regex.captures(line)?;
+    let item = match (captures.get(1), captures.get(2), captures.get(3)) {
+        (Some(kind), Some(name), _) => format!("{} {}", kind.as_str(), name.as_str()),
+        (_, _, Some(method)) => format!("method {}", method.as_str()),
+        _ => return None,
+    };
+    // The signature ends where a body or initializer starts
+    let end = line.find(['{', ';', '=']).unwrap_or(line.len());
+    let signature = line[..end].split_whitespace().collect::<Vec<_>>().join(" ");
+    Some(Declaration { item, signature })
+}
+
+/// Whether a Rust file is a binary's entry point, whose items no other crate sees
+fn is_binary_target(path: &str) -> bool {
+    path == "build.rs"
+        || path.ends_with("/build.rs")
+        || path.ends_with("main.rs")
+        || path.starts_with("src/bin/")
+        || path.contains("/src/bin/")
+}
+
+/// The public declarations a file's diff removes and adds, in that order
+fn file_declarations(file: &StagedFile) -> (Vec<Declaration>, Vec<Declaration>) {
+    let ext = file.path.rsplit('.').next().unwrap_or_default();
+    if is_test_path(&file.path) || (ext == "rs" && is_binary_target(&file.path)) {
+        return (Vec::new(), Vec::new());
+    }
+    let (mut removed, mut added) = (Vec::new(), Vec::new());
+    for line in file.diff.lines() {
+        if line.starts_with("---") || line.starts_with("+++") {
+            continue;
+        }
+        if let Some(rest) = line.strip_prefix('-') {
+            removed.extend(declaration(ext, rest));
+        } else if let Some(rest) = line.strip_prefix('+') {
+            added.extend(declaration(ext, rest));
+        }
+    }
+    (removed, added)
+}
+
+/// Public items the changed files remove or change the signature of, and
+/// breaking changes to the API contracts of changed schemas
+///
+/// Declarations are matched across files, so an item moved to another file
+/// unchanged is not reported.
+pub fn detect(files: &[StagedFile]) -> Vec<ApiChange> {
+    let declarations: Vec<_> = files.iter().map(file_declarations).collect();
+    let mut added: HashMap<&str, Vec<&str>> = HashMap::new();
+    for decl in declarations.iter().flat_map(|(_, added)| added) {
+        added
+            .entry(decl.item.as_str())
+            .or_default()
+            .push(decl.signature.as_str());
+    }
+
+    let mut changes = Vec::new();
+    for (file, (removed, _)) in files.iter().zip(&declarations) {
+        for decl in removed {
+            let signatures = added.get(decl.item.as_str());
+            if signatures.is_some_and(|signatures| signatures.contains(&decl.signature.as_str())) {
+                continue;
+            }
+            let after = signatures.and_then(|signatures| signatures.first());
+            changes.push(ApiChange {
+                path: file.path.clone(),
+                kind: if after.is_some() {
+                    ApiChangeKind::SignatureChanged
+                } else {
+                    ApiChangeKind::Removed
+                },
+                item: decl.item.clone(),
+                before: decl.signature.clone(),
+                after: after.map(ToString::to_string),
+            });
+        }
+        changes.extend(analyzers::contract_breaks(file));
+    }
+    changes
+}
+
+/// Runs the detection over the context's files
+#[tracing::instrument(skip_all)]
+pub fn attach_api_changes(context: &mut CommitContext) {
+    context.api_changes = detect(&context.staged_files);
+    if !context.api_changes.is_empty() {
+        debug!("Found {} breaking API changes", context.api_changes.len());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::context::ChangeType;
+
+    fn modified(path: &str, diff: &str) -> StagedFile {
+        StagedFile {
+            path: path.to_string(),
+            change_type: ChangeType::Modified,
+            diff: diff.to_string(),
+            content: None,
+            content_excluded: false,
+        }
+    }
+
+    #[test]
+    fn test_removed_and_changed_public_items() {
+        let diff = "\
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -1,6 +1,5 @@
+-pub fn parse(input: &str) -> Result<Ast> {
++pub fn parse(input: &str, strict: bool) -> Result<Ast> {
+-pub struct Token {
+-pub(crate) fn helper() {}
+-pub async fn load(path: &Path) {
++pub async fn load(path: &Path) {
++pub fn added() {}
+";
+        let changes = detect(&[modified("src/lib.rs", diff)]);
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0].kind, ApiChangeKind::SignatureChanged);
+        assert_eq!(changes[0].item, "fn parse");
+        assert_eq!(
+            changes[0].after.as_deref(),
+            Some("pub fn parse(input: &str, strict: bool) -> Result<Ast>")
+        );
+        assert_eq!(changes[1].kind, ApiChangeKind::Removed);
+        assert_eq!(changes[1].item, "struct Token");
+
+        // Tests are not public API
+        assert!(detect(&[modified("tests/parse.rs", diff)]).is_empty());
+    }
+
+    #[test]
+    fn test_typescript_and_java() {
+        let ts = "-export function render(node: Node): string {\n+export function render(node: Node, opts: Options): string {\n";
+        assert_eq!(
+            detect(&[modified("src/render.ts", ts)])[0].kind,
+            ApiChangeKind::SignatureChanged
+        );
+        let java = "-    public static Client connect(String host) {\n-public class Client {\n+public final class Client {\n";
+        let changes = detect(&[modified("src/Client.java", java)]);
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0].item, "method connect");
+        assert_eq!(changes[1].kind, ApiChangeKind::SignatureChanged);
+    }
+
+    #[test]
+    fn test_moved_items_and_binaries_are_skipped() {
+        let moved = [
+            modified("src/lib.rs", "-pub fn parse(input: &str) -> Ast {\n"),
+            StagedFile {
+                change_type: ChangeType::Added,
+                ..modified("src/parse.rs", "+pub fn parse(input: &str) -> Ast {\n")
+            },
+        ];
+        assert!(detect(&moved).is_empty());
+        assert!(detect(&[modified("src/main.rs", "-pub fn run() {\n")]).is_empty());
+        assert!(detect(&[modified("src/bin/tool.rs", "-pub fn run() {\n")]).is_empty());
+    }
+
+    #[test]
+    fn test_schema_contract_breaks() {
+        let diff = "@@ -1,4 +1,3 @@\n type Query {\n-  user(id: ID!): User\n+  user(id: ID!, org: ID!): User\n-  users: [User]\n }\n";
+        let changes = detect(&[modified("schema.graphql", diff)]);
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0].kind, ApiChangeKind::Narrowed);
+        assert_eq!(
+            changes[0].to_string(),
+            "schema.graphql: `field Query.user` narrowed from `user(id: ID!): User` to `user(id: ID!, org: ID!): User`"
+        );
+    }
+}
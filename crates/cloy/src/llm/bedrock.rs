@@ -0,0 +1,557 @@
+//! AWS Bedrock through the Converse API.
+//!
+//! The `llm` crate has no Bedrock backend, so requests are made here and
+//! wrapped in its provider traits to go through the same retry and decoding
+//! path as every other provider. Converse takes one request shape for all
+//! the chat models Bedrock hosts, Claude and Llama included.
+//!
+//! Requests are signed with `SigV4` using the standard AWS credentials: the
+//! `AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY` and `AWS_SESSION_TOKEN`
+//! variables, or the `AWS_PROFILE` (default `default`) section of
+//! `~/.aws/credentials`. A Bedrock API key, when configured, is sent as a
+//! bearer token instead.
+
+use crate::config::ProviderConfig;
+use crate::http;
+use crate::llm::endpoint::BASE_URL_PARAM;
+use crate::llm::rate_limit;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use llm::ToolCall;
+use llm::chat::{
+    ChatMessage, ChatProvider, ChatResponse, ChatRole, PromptTokensDetails, Tool, Usage,
+};
+use llm::error::LLMError;
+use reqwest::Client;
+use serde::Deserialize;
+use serde_json::{Value, json};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fmt;
+use std::fmt::Write;
+use std::path::PathBuf;
+
+/// Additional param with the AWS region, when not given by `AWS_REGION`
+pub const REGION_PARAM: &str = "region";
+
+const SERVICE: &str = "bedrock";
+
+/// Characters `SigV4` leaves unescaped in URI components
+fn is_unreserved(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'_' | b'.' | b'~')
+}
+
+/// Percent-encodes everything but the unreserved characters
+fn uri_encode(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        if is_unreserved(byte) {
+            out.push(char::from(byte));
+        } else {
+            let _ = write!(out, "%{byte:02X}");
+        }
+    }
+    out
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::new(), |mut out, byte| {
+        let _ = write!(out, "{byte:02x}");
+        out
+    })
+}
+
+fn hmac(key: &[u8], data: &str) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// AWS access key credentials
+#[derive(Clone, PartialEq, Eq)]
+pub struct AwsCredentials {
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    pub session_token: Option<String>,
+}
+
+impl fmt::Debug for AwsCredentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AwsCredentials")
+            .field("access_key_id", &self.access_key_id)
+            .finish_non_exhaustive()
+    }
+}
+
+impl AwsCredentials {
+    /// Credentials from the environment, or from the shared credentials file
+    pub fn load() -> Option<Self> {
+        Self::from_env().or_else(|| {
+            let profile = std::env::var("AWS_PROFILE").unwrap_or_else(|_| "default".to_string());
+            let path = std::env::var_os("AWS_SHARED_CREDENTIALS_FILE")
+                .map(PathBuf::from)
+                .or_else(|| dirs::home_dir().map(|home| home.join(".aws").join("credentials")))?;
+            Self::from_file(&std::fs::read_to_string(path).ok()?, &profile)
+        })
+    }
+
+    fn from_env() -> Option<Self> {
+        let var = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+        Some(Self {
+            access_key_id: var("AWS_ACCESS_KEY_ID")?,
+            secret_access_key: var("AWS_SECRET_ACCESS_KEY")?,
+            session_token: var("AWS_SESSION_TOKEN"),
+        })
+    }
+
+    /// Reads one profile of an INI-style credentials file
+    fn from_file(contents: &str, profile: &str) -> Option<Self> {
+        let mut in_profile = false;
+        let mut values = HashMap::new();
+        for line in contents.lines().map(str::trim) {
+            if let Some(section) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                in_profile = section.trim() == profile;
+            } else if in_profile && let Some((key, value)) = line.split_once('=') {
+                values.insert(key.trim().to_string(), value.trim().to_string());
+            }
+        }
+        Some(Self {
+            access_key_id: values.remove("aws_access_key_id")?,
+            secret_access_key: values.remove("aws_secret_access_key")?,
+            session_token: values.remove("aws_session_token"),
+        })
+    }
+}
+
+/// The region from the `region` param, `AWS_REGION` or `AWS_DEFAULT_REGION`
+pub fn region<S: std::hash::BuildHasher>(params: &HashMap<String, String, S>) -> Option<String> {
+    params
+        .get(REGION_PARAM)
+        .cloned()
+        .or_else(|| std::env::var("AWS_REGION").ok())
+        .or_else(|| std::env::var("AWS_DEFAULT_REGION").ok())
+        .map(|r| r.trim().to_string())
+        .filter(|r| !r.is_empty())
+}
+
+/// The canonical request of `SigV4`; `headers` are lowercase and sorted by name
+fn canonical_request(
+    method: &str,
+    path: &str,
+    query: &str,
+    headers: &[(&str, &str)],
+    payload_hash: &str,
+) -> String {
+    let canonical_headers = headers
+        .iter()
+        .fold(String::new(), |mut out, (name, value)| {
+            let _ = writeln!(out, "{name}:{}", value.trim());
+            out
+        });
+    let signed_headers = headers
+        .iter()
+        .map(|(name, _)| *name)
+        .collect::<Vec<_>>()
+        .join(";");
+    format!("{method}\n{path}\n{query}\n{canonical_headers}\n{signed_headers}\n{payload_hash}")
+}
+
+/// Signs a string to sign with the key derived for the date, region and service
+fn signature(
+    secret: &str,
+    date: &str,
+    region: &str,
+    service: &str,
+    string_to_sign: &str,
+) -> String {
+    let key = hmac(format!("AWS4{secret}").as_bytes(), date);
+    let key = hmac(&key, region);
+    let key = hmac(&key, service);
+    let key = hmac(&key, "aws4_request");
+    hex(&hmac(&key, string_to_sign))
+}
+
+/// The headers that sign a POST of `body` to `host` and `path` at `now`
+fn sign_request(
+    credentials: &AwsCredentials,
+    region: &str,
+    host: &str,
+    path: &str,
+    body: &[u8],
+    now: DateTime<Utc>,
+) -> Vec<(&'static str, String)> {
+    let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+    let date = now.format("%Y%m%d").to_string();
+    let payload_hash = hex(&Sha256::digest(body));
+
+    let mut headers = vec![
+        ("content-type", "application/json"),
+        ("host", host),
+        ("x-amz-date", amz_date.as_str()),
+    ];
+    if let Some(token) = &credentials.session_token {
+        headers.push(("x-amz-security-token", token.as_str()));
+    }
+    // Every path segment is encoded once more for the canonical form
+    let canonical_path = path
+        .split('/')
+        .map(uri_encode)
+        .collect::<Vec<_>>()
+        .join("/");
+    let request = canonical_request("POST", &canonical_path, "", &headers, &payload_hash);
+    let scope = format!("{date}/{region}/{SERVICE}/aws4_request");
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{amz_date}\n{scope}\n{}",
+        hex(&Sha256::digest(request.as_bytes()))
+    );
+    let signed_headers = headers
+        .iter()
+        .map(|(name, _)| *name)
+        .collect::<Vec<_>>()
+        .join(";");
+    let authorization = format!(
+        "AWS4-HMAC-SHA256 Credential={}/{scope}, SignedHeaders={signed_headers}, Signature={}",
+        credentials.access_key_id,
+        signature(
+            &credentials.secret_access_key,
+            &date,
+            region,
+            SERVICE,
+            &string_to_sign
+        )
+    );
+
+    let mut signed = vec![
+        ("authorization", authorization),
+        ("x-amz-date", amz_date.clone()),
+    ];
+    if let Some(token) = &credentials.session_token {
+        signed.push(("x-amz-security-token", token.clone()));
+    }
+    signed
+}
+
+/// How requests are authorized
+#[derive(Debug, Clone)]
+enum Auth {
+    SigV4(AwsCredentials),
+    /// A Bedrock API key
+    Bearer(String),
+}
+
+/// A Bedrock model reached through the Converse API
+#[derive(Debug)]
+pub struct BedrockProvider {
+    client: Client,
+    auth: Auth,
+    region: String,
+    endpoint: String,
+    model: String,
+    system: String,
+    inference_config: Value,
+}
+
+impl BedrockProvider {
+    /// Builds the provider from its configuration; fails when no region or credentials are found
+    pub fn new(config: &ProviderConfig, model: String, system: &str) -> Result<Self, LLMError> {
+        let params = &config.additional_params;
+        let region = region(params).ok_or_else(|| {
+            LLMError::InvalidRequest(
+                "No AWS region; set AWS_REGION or gitai.bedrock-additionalregion".to_string(),
+            )
+        })?;
+        let auth = if config.api_key.is_empty() {
+            Auth::SigV4(AwsCredentials::load().ok_or_else(|| {
+                LLMError::AuthError(
+                    "No AWS credentials in the environment or ~/.aws/credentials".to_string(),
+                )
+            })?)
+        } else {
+            Auth::Bearer(config.api_key.clone())
+        };
+        let endpoint = params.get(BASE_URL_PARAM).map_or_else(
+            || format!("https://bedrock-runtime.{region}.amazonaws.com"),
+            |url| url.trim().trim_end_matches('/').to_string(),
+        );
+
+        let mut inference_config = json!({
+            "maxTokens": params.get("max_tokens").and_then(|v| v.parse::<u32>().ok()).unwrap_or(4096),
+        });
+        if let Some(temperature) = config.temperature() {
+            inference_config["temperature"] = json!(temperature);
+        }
+        if let Some(top_p) = config.top_p() {
+            inference_config["topP"] = json!(top_p);
+        }
+        if config.seed().is_some() {
+            log::debug!("Bedrock's Converse API takes no seed; ignoring it");
+        }
+
+        Ok(Self {
+            client: http::client(None).map_err(|e| LLMError::InvalidRequest(format!("{e:#}")))?,
+            auth,
+            region,
+            endpoint,
+            model,
+            system: system.to_string(),
+            inference_config,
+        })
+    }
+
+    fn request_body(&self, messages: &[ChatMessage]) -> Value {
+        let messages: Vec<Value> = messages
+            .iter()
+            .map(|message| {
+                let role = match message.role {
+                    ChatRole::User => "user",
+                    ChatRole::Assistant => "assistant",
+                };
+                json!({ "role": role, "content": [{ "text": message.content }] })
+            })
+            .collect();
+        let mut body = json!({ "messages": messages, "inferenceConfig": self.inference_config });
+        if !self.system.is_empty() {
+            body["system"] = json!([{ "text": self.system }]);
+        }
+        body
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ConverseResponse {
+    output: ConverseOutput,
+    usage: Option<ConverseUsage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ConverseOutput {
+    message: ConverseMessage,
+}
+
+#[derive(Debug, Deserialize)]
+struct ConverseMessage {
+    content: Vec<ContentBlock>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ContentBlock {
+    text: Option<String>,
+}
+
+/// Token counts of a Converse call
+#[derive(Debug, Clone, Copy, Deserialize)]
+struct ConverseUsage {
+    #[serde(rename = "inputTokens")]
+    input: u32,
+    #[serde(rename = "outputTokens")]
+    output: u32,
+    #[serde(rename = "totalTokens")]
+    total: u32,
+    #[serde(rename = "cacheReadInputTokens")]
+    cache_read: Option<u32>,
+}
+
+/// The text blocks and usage of a Converse response
+#[derive(Debug)]
+struct BedrockResponse {
+    text: String,
+    usage: Option<ConverseUsage>,
+}
+
+impl fmt::Display for BedrockResponse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+impl ChatResponse for BedrockResponse {
+    fn text(&self) -> Option<String> {
+        Some(self.text.clone())
+    }
+
+    fn tool_calls(&self) -> Option<Vec<ToolCall>> {
+        None
+    }
+
+    fn usage(&self) -> Option<Usage> {
+        self.usage.map(|usage| Usage {
+            prompt_tokens: usage.input,
+            completion_tokens: usage.output,
+            total_tokens: usage.total,
+            completion_tokens_details: None,
+            prompt_tokens_details: usage.cache_read.map(|cached| PromptTokensDetails {
+                cached_tokens: Some(cached),
+                audio_tokens: None,
+            }),
+        })
+    }
+}
+
+#[async_trait]
+impl ChatProvider for BedrockProvider {
+    async fn chat_with_tools(
+        &self,
+        messages: &[ChatMessage],
+        _tools: Option<&[Tool]>,
+    ) -> Result<Box<dyn ChatResponse>, LLMError> {
+        let path = format!("/model/{}/converse", uri_encode(&self.model));
+        let url = format!("{}{path}", self.endpoint);
+        let body = serde_json::to_vec(&self.request_body(messages))
+            .map_err(|e| LLMError::JsonError(e.to_string()))?;
+
+        let mut request = self
+            .client
+            .post(&url)
+            .header("content-type", "application/json");
+        match &self.auth {
+            Auth::Bearer(key) => request = request.bearer_auth(key),
+            Auth::SigV4(credentials) => {
+                let host = reqwest::Url::parse(&url)
+                    .ok()
+                    .and_then(|u| u.host_str().map(ToString::to_string))
+                    .ok_or_else(|| LLMError::InvalidRequest(format!("Invalid endpoint {url}")))?;
+                for (name, value) in
+                    sign_request(credentials, &self.region, &host, &path, &body, Utc::now())
+                {
+                    request = request.header(name, value);
+                }
+            }
+        }
+
+        let response = request
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| LLMError::HttpError(e.to_string()))?;
+        let status = response.status();
+        let retry_after = response
+            .headers()
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(rate_limit::parse_retry_after);
+        let raw = response
+            .text()
+            .await
+            .map_err(|e| LLMError::HttpError(e.to_string()))?;
+        if !status.is_success() {
+            return Err(match status.as_u16() {
+                429 => rate_limit::rate_limited_error("Bedrock", retry_after, &raw),
+                401 | 403 => LLMError::AuthError(format!("Bedrock returned {status}: {raw}")),
+                _ => LLMError::ProviderError(format!("Bedrock returned {status}: {raw}")),
+            });
+        }
+
+        let parsed: ConverseResponse =
+            serde_json::from_str(&raw).map_err(|e| LLMError::ResponseFormatError {
+                message: e.to_string(),
+                raw_response: raw.clone(),
+            })?;
+        let text = parsed
+            .output
+            .message
+            .content
+            .into_iter()
+            .filter_map(|block| block.text)
+            .collect();
+        Ok(Box::new(BedrockResponse {
+            text,
+            usage: parsed.usage,
+        }))
+    }
+}
+
+chat_only_provider!(BedrockProvider, "Bedrock");
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_signature_matches_aws_example() {
+        // The GET ListUsers example from the AWS Signature Version 4 documentation
+        let request = canonical_request(
+            "GET",
+            "/",
+            "Action=ListUsers&Version=2010-05-08",
+            &[
+                (
+                    "content-type",
+                    "application/x-www-form-urlencoded; charset=utf-8",
+                ),
+                ("host", "iam.amazonaws.com"),
+                ("x-amz-date", "20150830T123600Z"),
+            ],
+            &hex(&Sha256::digest(b"")),
+        );
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n20150830T123600Z\n20150830/us-east-1/iam/aws4_request\n{}",
+            hex(&Sha256::digest(request.as_bytes()))
+        );
+        assert_eq!(
+            signature(
+                "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY",
+                "20150830",
+                "us-east-1",
+                "iam",
+                &string_to_sign
+            ),
+            "5d672d79c15b13162d9279b0855cfba6789a8edb4c82c400e06b5924a6f2b5d7"
+        );
+    }
+
+    #[test]
+    fn test_sign_request_headers() {
+        let credentials = AwsCredentials {
+            access_key_id: "AKIDEXAMPLE".to_string(),
+            secret_access_key: "secret".to_string(),
+            session_token: Some("token".to_string()),
+        };
+        let now = Utc
+            .with_ymd_and_hms(2024, 5, 1, 12, 0, 0)
+            .single()
+            .expect("Valid date");
+        let path = format!(
+            "/model/{}/converse",
+            uri_encode("anthropic.claude-3-haiku-20240307-v1:0")
+        );
+        assert_eq!(
+            path,
+            "/model/anthropic.claude-3-haiku-20240307-v1%3A0/converse"
+        );
+        let headers = sign_request(
+            &credentials,
+            "us-west-2",
+            "bedrock-runtime.us-west-2.amazonaws.com",
+            &path,
+            b"{}",
+            now,
+        );
+        let authorization = &headers[0].1;
+        assert!(
+            authorization.starts_with(
+                "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20240501/us-west-2/bedrock/aws4_request, \
+                 SignedHeaders=content-type;host;x-amz-date;x-amz-security-token, Signature="
+            ),
+            "{authorization}"
+        );
+        assert_eq!(headers[1], ("x-amz-date", "20240501T120000Z".to_string()));
+        assert_eq!(headers[2], ("x-amz-security-token", "token".to_string()));
+    }
+
+    #[test]
+    fn test_credentials_file_profile() {
+        let contents = "[default]\naws_access_key_id = A\naws_secret_access_key = B\n\n\
+                        [work]\naws_access_key_id=C\naws_secret_access_key=D\naws_session_token=E\n";
+        let work = AwsCredentials::from_file(contents, "work").expect("Profile present");
+        assert_eq!(work.access_key_id, "C");
+        assert_eq!(work.session_token.as_deref(), Some("E"));
+        assert_eq!(
+            AwsCredentials::from_file(contents, "default").map(|c| c.secret_access_key),
+            Some("B".to_string())
+        );
+        assert!(AwsCredentials::from_file(contents, "missing").is_none());
+    }
+}
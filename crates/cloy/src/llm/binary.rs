@@ -0,0 +1,501 @@
+//! What changed in binary files, for the prompt.
+//!
+//! A binary diff only says that the file changed. For changed images, fonts,
+//! archives and other binaries the placeholder is replaced with the file's
+//! format, its size before and after, and for images the dimensions read from
+//! the file header, so a message can say "compress hero.png (1.2 MB → 300 KB)".
+//! Files tracked by Git LFS are staged as small text pointers; their pointer
+//! diff is replaced the same way, with the size and object id from the
+//! pointers and the format and dimensions from the local LFS store when the
+//! objects are there.
+
+use crate::git;
+use crate::llm::context::{ChangeType, CommitContext, DiffSource, StagedFile};
+use git2::Repository;
+use log::debug;
+use std::fs::File;
+use std::io::Read;
+
+/// The diff libgit2 leaves for a binary file
+const PLACEHOLDER: &str = "[Binary file changed]";
+
+/// First line of a Git LFS pointer
+const LFS_VERSION: &str = "version https://git-lfs.github.com/spec/";
+
+/// Pointers are small; larger blobs are never read as one
+const MAX_POINTER_BYTES: usize = 1024;
+
+/// Bytes of an LFS object read for its format and dimensions
+const MAX_HEADER_BYTES: u64 = 256 * 1024;
+
+/// Formats recognized by their leading bytes
+const SIGNATURES: &[(&[u8], &str)] = &[
+    (b"\x89PNG\r\n\x1a\n", "PNG image"),
+    (b"\xff\xd8\xff", "JPEG image"),
+    (b"GIF87a", "GIF image"),
+    (b"GIF89a", "GIF image"),
+    (b"II*\0", "TIFF image"),
+    (b"MM\0*", "TIFF image"),
+    (b"\0\0\x01\0", "ICO image"),
+    (b"%PDF", "PDF document"),
+    (b"PK\x03\x04", "ZIP archive"),
+    (b"\x1f\x8b", "gzip archive"),
+    (b"7z\xbc\xaf\x27\x1c", "7z archive"),
+    (b"\xfd7zXZ\0", "xz archive"),
+    (b"BZh", "bzip2 archive"),
+    (b"wOFF", "WOFF font"),
+    (b"wOF2", "WOFF2 font"),
+    (b"OTTO", "OpenType font"),
+    (b"\0\x01\0\0", "TrueType font"),
+    (b"\0asm", "WebAssembly module"),
+    (b"\x7fELF", "ELF binary"),
+];
+
+/// The file's format, from its header
+fn format(bytes: &[u8]) -> &'static str {
+    if let Some((_, name)) = SIGNATURES
+        .iter()
+        .find(|(signature, _)| bytes.starts_with(signature))
+    {
+        return name;
+    }
+    match (bytes.get(..4), bytes.get(8..12), bytes.get(4..8)) {
+        (Some(b"RIFF"), Some(b"WEBP"), _) => "WebP image",
+        (_, _, Some(b"ftyp")) => "MP4 media",
+        _ if bytes.starts_with(b"BM") && bytes.len() > 26 => "BMP image",
+        _ if bytes.get(257..262) == Some(b"ustar") => "tar archive",
+        _ => "binary",
+    }
+}
+
+fn u16_be(bytes: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from(u16::from_be_bytes(
+        bytes.get(at..at + 2)?.try_into().ok()?,
+    )))
+}
+
+fn u16_le(bytes: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from(u16::from_le_bytes(
+        bytes.get(at..at + 2)?.try_into().ok()?,
+    )))
+}
+
+fn u24_le(bytes: &[u8], at: usize) -> Option<u32> {
+    let [a, b, c] = bytes.get(at..at + 3)?.try_into().ok()?;
+    Some(u32::from_le_bytes([a, b, c, 0]))
+}
+
+fn u32_be(bytes: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(bytes.get(at..at + 4)?.try_into().ok()?))
+}
+
+fn u32_le(bytes: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(bytes.get(at..at + 4)?.try_into().ok()?))
+}
+
+/// Width and height of a JPEG, from its first start-of-frame segment
+fn jpeg_dimensions(bytes: &[u8]) -> Option<(u32, u32)> {
+    let mut at = 2;
+    while *bytes.get(at)? == 0xff {
+        let marker = *bytes.get(at + 1)?;
+        if (0xc0..=0xcf).contains(&marker) && !matches!(marker, 0xc4 | 0xc8 | 0xcc) {
+            return Some((u16_be(bytes, at + 7)?, u16_be(bytes, at + 5)?));
+        }
+        at += 2 + usize::from(u16::from_be_bytes(
+            bytes.get(at + 2..at + 4)?.try_into().ok()?,
+        ));
+    }
+    None
+}
+
+/// Width and height of an image, from its header
+fn dimensions(format: &str, bytes: &[u8]) -> Option<(u32, u32)> {
+    match format {
+        "PNG image" => Some((u32_be(bytes, 16)?, u32_be(bytes, 20)?)),
+        "GIF image" => Some((u16_le(bytes, 6)?, u16_le(bytes, 8)?)),
+        "BMP image" => Some((
+            u32_le(bytes, 18)?,
+            u32_le(bytes, 22)?.cast_signed().unsigned_abs(),
+        )),
+        "JPEG image" => jpeg_dimensions(bytes),
+        "WebP image" => match bytes.get(12..16)? {
+            b"VP8 " => Some((u16_le(bytes, 26)? & 0x3fff, u16_le(bytes, 28)? & 0x3fff)),
+            b"VP8L" => {
+                let bits = u32_le(bytes, 21)?;
+                Some(((bits & 0x3fff) + 1, ((bits >> 14) & 0x3fff) + 1))
+            }
+            b"VP8X" => Some((u24_le(bytes, 24)? + 1, u24_le(bytes, 27)? + 1)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// A size such as `940 B`, `12.5 KB` or `300 KB`
+pub(crate) fn human_size(bytes: usize) -> String {
+    let mut unit = 0;
+    let mut scale: usize = 1;
+    while unit < 3 && bytes >= scale.saturating_mul(1024) {
+        scale *= 1024;
+        unit += 1;
+    }
+    let name = ["B", "KB", "MB", "GB"][unit];
+    let tenths = (bytes.saturating_mul(10) + scale / 2) / scale;
+    if unit == 0 || tenths >= 1000 {
+        format!("{} {name}", (bytes + scale / 2) / scale)
+    } else {
+        format!("{}.{} {name}", tenths / 10, tenths % 10)
+    }
+}
+
+/// Format, dimensions and size of a file's content
+#[derive(Debug, Clone, Copy)]
+struct Metadata {
+    /// `None` when only the size is known, as for LFS objects not fetched
+    format: Option<&'static str>,
+    dimensions: Option<(u32, u32)>,
+    size: usize,
+}
+
+impl Metadata {
+    /// Metadata of a file of `size` bytes starting with `header`
+    fn read(header: &[u8], size: usize) -> Self {
+        let format = format(header);
+        Self {
+            format: Some(format),
+            dimensions: dimensions(format, header),
+            size,
+        }
+    }
+
+    fn of(bytes: &[u8]) -> Self {
+        Self::read(bytes, bytes.len())
+    }
+}
+
+/// `old → new`, or the one value when both are the same
+fn change<T: PartialEq + Copy>(old: T, new: T, show: impl Fn(T) -> String) -> String {
+    if old == new {
+        show(new)
+    } else {
+        format!("{} → {}", show(old), show(new))
+    }
+}
+
+/// What changed in a file, from its metadata before and after, such as
+/// `changed: PNG image, 1920×1080 → 1280×720, 1.2 MB → 300 KB (-75%)`
+fn describe(old: Option<Metadata>, new: Option<Metadata>) -> Option<String> {
+    let show_format = |format: Option<&str>| format.unwrap_or("?").to_string();
+    let show_dimensions = |dimensions: Option<(u32, u32)>| {
+        dimensions.map_or_else(|| "?".to_string(), |(w, h)| format!("{w}×{h}"))
+    };
+    let (verb, parts) = match (old, new) {
+        (Some(old), Some(new)) => {
+            let mut parts = Vec::new();
+            if old.format.is_some() || new.format.is_some() {
+                parts.push(change(old.format, new.format, show_format));
+            }
+            if old.dimensions.is_some() || new.dimensions.is_some() {
+                parts.push(change(old.dimensions, new.dimensions, show_dimensions));
+            }
+            let mut size = change(old.size, new.size, human_size);
+            if let (Ok(before), Ok(after)) = (i64::try_from(old.size), i64::try_from(new.size))
+                && before > 0
+                && before != after
+            {
+                size = format!("{size} ({:+}%)", (after - before) * 100 / before);
+            }
+            parts.push(size);
+            ("changed", parts)
+        }
+        (None, Some(file)) | (Some(file), None) => {
+            let verb = if old.is_none() { "added" } else { "deleted" };
+            let mut parts: Vec<String> = file.format.map(ToString::to_string).into_iter().collect();
+            parts.extend(file.dimensions.map(|(w, h)| format!("{w}×{h}")));
+            parts.push(human_size(file.size));
+            (verb, parts)
+        }
+        (None, None) => return None,
+    };
+    Some(format!("{verb}: {}", parts.join(", ")))
+}
+
+/// The blobs of a changed file on the old and new side of the diff
+fn blobs(
+    repo: &Repository,
+    source: &DiffSource,
+    file: &StagedFile,
+) -> (Option<Vec<u8>>, Option<Vec<u8>>) {
+    let old_path = match &file.change_type {
+        ChangeType::Renamed { from, .. } | ChangeType::Copied { from, .. } => from.as_str(),
+        _ => file.path.as_str(),
+    };
+    let old = match file.change_type {
+        ChangeType::Added => None,
+        _ => git::blob_content(repo, source, old_path, false),
+    };
+    let new = match file.change_type {
+        ChangeType::Deleted => None,
+        _ => git::blob_content(repo, source, &file.path, true),
+    };
+    (old, new)
+}
+
+/// A Git LFS pointer, which is committed in place of the file it tracks
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Pointer {
+    /// SHA-256 of the tracked file
+    oid: String,
+    size: usize,
+}
+
+/// The pointer `bytes` hold, if they are one
+fn parse_pointer(bytes: &[u8]) -> Option<Pointer> {
+    if bytes.len() > MAX_POINTER_BYTES {
+        return None;
+    }
+    let text = std::str::from_utf8(bytes).ok()?;
+    let mut lines = text.lines();
+    lines.next()?.strip_prefix(LFS_VERSION)?;
+    let (mut oid, mut size) = (None, None);
+    for line in lines {
+        if let Some(hash) = line.strip_prefix("oid sha256:") {
+            oid = Some(hash.trim().to_string());
+        } else if let Some(bytes) = line.strip_prefix("size ") {
+            size = bytes.trim().parse().ok();
+        }
+    }
+    Some(Pointer {
+        oid: oid?,
+        size: size?,
+    })
+}
+
+/// Metadata of the file a pointer tracks, read from the local LFS store when
+/// the object was fetched, and from the pointer's size otherwise
+fn lfs_metadata(repo: &Repository, pointer: &Pointer) -> Metadata {
+    let object = pointer
+        .oid
+        .get(..2)
+        .zip(pointer.oid.get(2..4))
+        .map(|(a, b)| {
+            repo.commondir()
+                .join("lfs/objects")
+                .join(a)
+                .join(b)
+                .join(&pointer.oid)
+        });
+    let header = object
+        .and_then(|object| File::open(object).ok())
+        .and_then(|file| {
+            let mut header = Vec::new();
+            file.take(MAX_HEADER_BYTES).read_to_end(&mut header).ok()?;
+            Some(header)
+        });
+    match header {
+        Some(header) => Metadata::read(&header, pointer.size),
+        None => Metadata {
+            format: None,
+            dimensions: None,
+            size: pointer.size,
+        },
+    }
+}
+
+/// The first characters of an object id
+fn short_oid(oid: &str) -> &str {
+    oid.get(..8).unwrap_or(oid)
+}
+
+/// Describes an LFS pointer change as a change to the tracked file
+fn describe_pointers(repo: &Repository, source: &DiffSource, file: &StagedFile) -> Option<String> {
+    let (old, new) = blobs(repo, source, file);
+    let old = old.as_deref().and_then(parse_pointer);
+    let new = new.as_deref().and_then(parse_pointer);
+    if old.is_none() && new.is_none() {
+        return None;
+    }
+    let description = describe(
+        old.as_ref().map(|pointer| lfs_metadata(repo, pointer)),
+        new.as_ref().map(|pointer| lfs_metadata(repo, pointer)),
+    )?;
+    let oid = match (&old, &new) {
+        (Some(old), Some(new)) if old.oid != new.oid => {
+            format!("; oid {} → {}", short_oid(&old.oid), short_oid(&new.oid))
+        }
+        (Some(pointer), _) | (_, Some(pointer)) => format!("; oid {}", short_oid(&pointer.oid)),
+        (None, None) => String::new(),
+    };
+    Some(format!("[Git LFS file {description}{oid}]"))
+}
+
+/// Replaces the diff of each changed binary file and LFS pointer with what
+/// changed in the file: its format, size and, for images, dimensions
+///
+/// Both sides are read from where the context's diff came from; a diff from
+/// outside the repository is left as it is.
+pub fn describe_binaries(repo: &Repository, context: &mut CommitContext) {
+    let source = &context.diff_source;
+    for file in &mut context.staged_files {
+        if file.content_excluded {
+            continue;
+        }
+        if file.diff.contains(LFS_VERSION) {
+            match describe_pointers(repo, source, file) {
+                Some(description) => {
+                    file.diff = description;
+                    file.content = None;
+                }
+                None => debug!("Keeping the pointer diff of LFS file {}", file.path),
+            }
+        } else if file.diff == PLACEHOLDER {
+            let (old, new) = blobs(repo, source, file);
+            match describe(
+                old.as_deref().map(Metadata::of),
+                new.as_deref().map(Metadata::of),
+            ) {
+                Some(description) => file.diff = format!("[Binary file {description}]"),
+                None => debug!("No content to describe binary file {}", file.path),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn png(width: u32, height: u32, size: usize) -> Vec<u8> {
+        let mut bytes = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+        bytes.extend(width.to_be_bytes());
+        bytes.extend(height.to_be_bytes());
+        bytes.resize(size, 0);
+        bytes
+    }
+
+    #[test]
+    fn test_binary_descriptions() {
+        let old = png(1920, 1080, 1_258_291);
+        let new = png(1280, 720, 307_200);
+        assert_eq!(
+            describe(Some(Metadata::of(&old)), Some(Metadata::of(&new))).as_deref(),
+            Some("changed: PNG image, 1920×1080 → 1280×720, 1.2 MB → 300 KB (-75%)")
+        );
+
+        let font = b"wOF2\0\x01\0\0".repeat(512);
+        assert_eq!(
+            describe(None, Some(Metadata::of(&font))).as_deref(),
+            Some("added: WOFF2 font, 4.0 KB")
+        );
+        assert_eq!(describe(None, None), None);
+    }
+
+    #[test]
+    fn test_lfs_pointers() {
+        let pointer = |oid: &str, size: usize| {
+            format!("version https://git-lfs.github.com/spec/v1\noid sha256:{oid}\nsize {size}\n")
+        };
+        let old = parse_pointer(pointer("4d7a2146", 2_097_152).as_bytes()).expect("A pointer");
+        assert_eq!(
+            old,
+            Pointer {
+                oid: "4d7a2146".to_string(),
+                size: 2_097_152
+            }
+        );
+        assert_eq!(parse_pointer(b"size 12\n"), None);
+
+        // Objects that were not fetched are known by size only
+        let unfetched = |size| Metadata {
+            format: None,
+            dimensions: None,
+            size,
+        };
+        assert_eq!(
+            describe(Some(unfetched(old.size)), Some(unfetched(524_288))).as_deref(),
+            Some("changed: 2.0 MB → 512 KB (-75%)")
+        );
+    }
+
+    #[test]
+    fn test_image_dimensions() {
+        let gif = b"GIF89a\x40\x01\xf0\0".to_vec();
+        assert_eq!(dimensions(format(&gif), &gif), Some((320, 240)));
+
+        // SOI, an APP0 segment of 4 bytes, then SOF0 with height 480 and width 640
+        let jpeg = b"\xff\xd8\xff\xe0\0\x04\0\0\xff\xc0\0\x11\x08\x01\xe0\x02\x80".to_vec();
+        assert_eq!(format(&jpeg), "JPEG image");
+        assert_eq!(dimensions(format(&jpeg), &jpeg), Some((640, 480)));
+
+        let mut webp = b"RIFF\0\0\0\0WEBPVP8X\x0a\0\0\0\0\0\0\0".to_vec();
+        webp.extend([0x7f, 0x07, 0x00, 0x37, 0x04, 0x00]);
+        assert_eq!(dimensions(format(&webp), &webp), Some((1920, 1080)));
+        assert_eq!(human_size(940), "940 B");
+        assert_eq!(human_size(12_800), "12.5 KB");
+    }
+
+    /// Writes `bytes` to `path`, stages it and commits; returns the commit id
+    fn commit_png(repo: &Repository, path: &str, bytes: &[u8]) -> git2::Oid {
+        let workdir = repo.workdir().expect("Repo has a workdir");
+        std::fs::write(workdir.join(path), bytes).expect("Failed to write file");
+        let mut index = repo.index().expect("Failed to open index");
+        index
+            .add_path(std::path::Path::new(path))
+            .expect("Failed to stage file");
+        index.write().expect("Failed to write index");
+        let tree = repo
+            .find_tree(index.write_tree().expect("Failed to write tree"))
+            .expect("Failed to find tree");
+        let signature = git2::Signature::now("Ada", "ada@example.com").expect("Valid signature");
+        let parent = repo.head().ok().and_then(|head| head.peel_to_commit().ok());
+        let parents: Vec<&git2::Commit> = parent.iter().collect();
+        repo.commit(
+            Some("HEAD"),
+            &signature,
+            &signature,
+            "Logo",
+            &tree,
+            &parents,
+        )
+        .expect("Failed to commit")
+    }
+
+    #[test]
+    fn test_binaries_are_read_from_the_diff_source() {
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let repo = Repository::init(dir.path()).expect("Failed to init repo");
+        let first = commit_png(&repo, "logo.png", &png(64, 64, 2048));
+        let second = commit_png(&repo, "logo.png", &png(32, 32, 1024));
+        commit_png(&repo, "logo.png", &png(16, 16, 512));
+
+        let file = StagedFile {
+            path: "logo.png".to_string(),
+            change_type: ChangeType::Modified,
+            diff: PLACEHOLDER.to_string(),
+            content: None,
+            content_excluded: false,
+        };
+        let mut context = CommitContext::new(
+            "main".to_string(),
+            Vec::new(),
+            vec![file],
+            String::new(),
+            String::new(),
+            Vec::new(),
+        );
+        // A diff from stdin has nothing to read back
+        describe_binaries(&repo, &mut context);
+        assert_eq!(context.staged_files[0].diff, PLACEHOLDER);
+
+        // A past commit is described by its own trees, not HEAD and the index
+        context.diff_source = DiffSource::Commits {
+            from: Some(first.to_string()),
+            to: second.to_string(),
+        };
+        describe_binaries(&repo, &mut context);
+        assert_eq!(
+            context.staged_files[0].diff,
+            "[Binary file changed: PNG image, 64×64 → 32×32, 2.0 KB → 1.0 KB (-50%)]"
+        );
+    }
+}
@@ -0,0 +1,192 @@
+//! Error excerpts from a failed CI run.
+//!
+//! A fix commit reads better when the model knows what was failing, but a CI
+//! log is mostly setup noise and far too long for a prompt. The log is cleaned
+//! of color codes and runner timestamps, and only the lines that report an
+//! error, a failed test or a panic are kept, with a few lines around each. A
+//! log without any such line is represented by its tail, where runners print
+//! the step that failed.
+
+use crate::config::Config;
+use crate::http;
+use anyhow::{Context, Result, anyhow};
+use regex::Regex;
+use std::sync::LazyLock;
+use std::time::Duration;
+
+/// Seconds allowed for fetching a log
+const FETCH_TIMEOUT_SECS: u64 = 30;
+/// Lines kept before and after a failure line
+const LINES_BEFORE: usize = 2;
+const LINES_AFTER: usize = 4;
+/// Lines an excerpt is capped at
+const MAX_EXCERPT_LINES: usize = 80;
+/// Characters a kept line is cut to
+const MAX_LINE_CHARS: usize = 300;
+/// Lines kept from the end of a log without failure lines
+const TAIL_LINES: usize = 30;
+
+static ANSI: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\x1b\[[0-9;?]*[A-Za-z]").expect("Valid regex"));
+
+/// The timestamp GitHub Actions and GitLab put in front of each line
+static TIMESTAMP: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^\d{4}-\d{2}-\d{2}T\d{2}:\d{2}:\d{2}(?:\.\d+)?Z\s?").expect("Valid regex")
+});
+
+static FAILURE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?i)(?:^\s*(?:error|fatal|fail(?:ed)?)\b|error\[E\d+\]|##\[error\]|\bpanicked at\b|\bassertion\b.*\bfailed\b|(?-i:\bFAIL(?:ED)?\b|\bERROR\b)|\b\d*[1-9]\d* (?:failed|errors?)\b|^Traceback \(most recent call last\)|\b\w+(?:Error|Exception):\s)",
+    )
+    .expect("Valid regex")
+});
+
+/// A log line without color codes and runner timestamp
+fn clean_line(line: &str) -> String {
+    let line = ANSI.replace_all(line, "");
+    let line = TIMESTAMP.replace(&line, "");
+    let line = line.trim_end();
+    match line.char_indices().nth(MAX_LINE_CHARS) {
+        Some((end, _)) => format!("{}…", &line[..end]),
+        None => line.to_string(),
+    }
+}
+
+/// The lines of `log` that explain a failure, or `None` for an empty log
+pub fn extract_failures(log: &str) -> Option<String> {
+    let lines: Vec<String> = log.lines().map(clean_line).collect();
+    let failures: Vec<usize> = lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| FAILURE.is_match(line))
+        .map(|(index, _)| index)
+        .collect();
+
+    if failures.is_empty() {
+        let tail: Vec<&str> = lines
+            .iter()
+            .map(String::as_str)
+            .filter(|line| !line.trim().is_empty())
+            .collect();
+        let tail = &tail[tail.len().saturating_sub(TAIL_LINES)..];
+        return (!tail.is_empty()).then(|| tail.join("\n"));
+    }
+
+    // Overlapping windows around failure lines merge into one
+    let mut windows: Vec<(usize, usize)> = Vec::new();
+    for index in failures {
+        let start = index.saturating_sub(LINES_BEFORE);
+        let end = (index + LINES_AFTER + 1).min(lines.len());
+        match windows.last_mut() {
+            Some(last) if start <= last.1 => last.1 = last.1.max(end),
+            _ => windows.push((start, end)),
+        }
+    }
+
+    let mut excerpt: Vec<&str> = Vec::new();
+    let mut kept = 0;
+    for (start, end) in &windows {
+        if kept >= MAX_EXCERPT_LINES {
+            excerpt.push("[more failures omitted]");
+            break;
+        }
+        if !excerpt.is_empty() {
+            excerpt.push("...");
+        }
+        let end = (*end).min(start + MAX_EXCERPT_LINES - kept);
+        excerpt.extend(lines[*start..end].iter().map(String::as_str));
+        kept += end - start;
+    }
+    Some(excerpt.join("\n"))
+}
+
+/// The log at `source`, a file path or an http(s) URL
+pub async fn read_log(source: &str) -> Result<String> {
+    if source.starts_with("http://") || source.starts_with("https://") {
+        let client = http::client(Some(Duration::from_secs(FETCH_TIMEOUT_SECS)))?;
+        let response = client
+            .get(source)
+            .send()
+            .await
+            .with_context(|| format!("Failed to fetch CI log {source}"))?
+            .error_for_status()
+            .with_context(|| format!("Failed to fetch CI log {source}"))?;
+        return response
+            .text()
+            .await
+            .with_context(|| format!("Failed to read CI log {source}"));
+    }
+    let bytes = tokio::fs::read(source)
+        .await
+        .with_context(|| format!("Failed to read CI log {source}"))?;
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// The failure excerpt of the log at `source`
+pub async fn load_excerpt(source: &str) -> Result<String> {
+    let log = read_log(source).await?;
+    extract_failures(&log).ok_or_else(|| anyhow!("CI log {source} is empty"))
+}
+
+/// Loads the excerpt of the log at `source`, if given, into the config prompts are built from
+pub async fn apply_to_config(config: &mut Config, source: Option<&str>) -> Result<()> {
+    if let Some(source) = source {
+        config.ci_failure = Some(load_excerpt(source).await?);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_failure_lines_with_context() {
+        let log = "\
+2024-05-01T10:00:00.1234567Z ##[group]Run cargo test
+2024-05-01T10:00:01.0000000Z    Compiling gitai v0.1.0
+2024-05-01T10:00:02.0000000Z running 12 tests
+2024-05-01T10:00:03.0000000Z test parser::tests::test_parse ... \x1b[31mFAILED\x1b[0m
+2024-05-01T10:00:03.0000000Z
+2024-05-01T10:00:03.0000000Z thread 'parser::tests::test_parse' panicked at src/parser.rs:42:9:
+2024-05-01T10:00:03.0000000Z assertion `left == right` failed
+2024-05-01T10:00:04.0000000Z test result: FAILED. 11 passed; 1 failed; 0 ignored
+2024-05-01T10:00:05.0000000Z ##[group]Post job cleanup
+2024-05-01T10:00:05.0000000Z Cleaning up orphan processes
+2024-05-01T10:00:05.0000000Z Removing credentials
+2024-05-01T10:00:05.0000000Z Job finished
+";
+        let excerpt = extract_failures(log).expect("An excerpt");
+        assert!(excerpt.starts_with("   Compiling gitai v0.1.0\nrunning 12 tests\n"));
+        assert!(excerpt.contains("test parser::tests::test_parse ... FAILED\n"));
+        assert!(excerpt.contains("panicked at src/parser.rs:42:9"));
+        assert!(!excerpt.contains("\x1b["));
+        assert!(!excerpt.contains("2024-05-01T"));
+        assert!(!excerpt.contains("Run cargo test"));
+    }
+
+    #[test]
+    fn test_passing_summaries_are_not_failures() {
+        let log = "test result: ok. 10 passed; 0 failed; 0 ignored\nDone in 3s\n";
+        // Without failure lines the tail stands in
+        assert_eq!(extract_failures(log).as_deref(), Some(log.trim_end()));
+        assert_eq!(extract_failures("\n\n"), None);
+
+        let log = "setup\nerror[E0308]: mismatched types\n --> src/lib.rs:3:5\n";
+        assert_eq!(
+            extract_failures(log).as_deref(),
+            Some("setup\nerror[E0308]: mismatched types\n --> src/lib.rs:3:5")
+        );
+    }
+
+    #[test]
+    fn test_excerpt_is_capped() {
+        let log = (0..500)
+            .map(|i| format!("error: problem {i}"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let excerpt = extract_failures(&log).expect("An excerpt");
+        assert_eq!(excerpt.lines().count(), MAX_EXCERPT_LINES);
+        assert!(excerpt.ends_with("error: problem 79"));
+    }
+}
@@ -0,0 +1,400 @@
+//! What kind of change the staged files make, guessed from their paths and diffs.
+//!
+//! The kind goes to the model as a hint: docs-only and test-only changes,
+//! dependency bumps, refactors, features and fixes each call for a different
+//! type and wording. A bump of a single dependency is common and has one
+//! right message, so it is written directly, in the form Dependabot uses, and
+//! the model is never called.
+
+use crate::analyzers::dependency_changes;
+use crate::config::Config;
+use crate::llm::context::{ChangeType, CommitContext, StagedFile};
+use crate::llm::test_impact::is_test_path;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fmt;
+use std::path::Path;
+
+/// Changed lines up to which a change to existing code reads as a fix
+const MAX_FIX_LINES: usize = 20;
+
+/// Manifests that declare dependencies with their versions
+const MANIFESTS: &[&str] = &[
+    "Cargo.toml",
+    "package.json",
+    "pyproject.toml",
+    "requirements.txt",
+    "go.mod",
+    "Gemfile",
+    "composer.json",
+];
+
+/// Lock files written by package managers
+const LOCK_FILES: &[&str] = &[
+    "Cargo.lock",
+    "package-lock.json",
+    "yarn.lock",
+    "pnpm-lock.yaml",
+    "poetry.lock",
+    "uv.lock",
+    "go.sum",
+    "Gemfile.lock",
+    "composer.lock",
+];
+
+/// Extensions of documentation files
+const DOC_EXTENSIONS: &[&str] = &["md", "markdown", "rst", "adoc", "txt"];
+
+/// The kind of a changeset
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    Docs,
+    Tests,
+    Dependencies,
+    Refactor,
+    Feature,
+    Fix,
+}
+
+impl fmt::Display for ChangeKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Docs => "documentation only",
+            Self::Tests => "tests only",
+            Self::Dependencies => "dependency update",
+            Self::Refactor => "refactor (moves or removes code without adding behavior)",
+            Self::Feature => "new feature (adds new source files)",
+            Self::Fix => "small fix to existing code",
+        })
+    }
+}
+
+/// One dependency moving from one version to another
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Bump {
+    pub name: String,
+    pub from: String,
+    pub to: String,
+}
+
+fn file_name(path: &str) -> &str {
+    Path::new(path)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or(path)
+}
+
+fn is_dependency_file(path: &str) -> bool {
+    let name = file_name(path);
+    MANIFESTS.contains(&name) || LOCK_FILES.contains(&name)
+}
+
+fn is_doc_path(path: &str) -> bool {
+    if path.starts_with("docs/")
+        || path.starts_with("doc/")
+        || file_name(path).starts_with("LICENSE")
+    {
+        return true;
+    }
+    let extension = Path::new(path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or_default()
+        .to_lowercase();
+    // `requirements.txt` is a manifest
+    DOC_EXTENSIONS.contains(&extension.as_str()) && !is_dependency_file(path)
+}
+
+/// Added and removed lines of a diff
+fn line_counts(diff: &str) -> (usize, usize) {
+    diff.lines().fold((0, 0), |(added, removed), line| {
+        if line.starts_with('+') && !line.starts_with("+++") {
+            (added + 1, removed)
+        } else if line.starts_with('-') && !line.starts_with("---") {
+            (added, removed + 1)
+        } else {
+            (added, removed)
+        }
+    })
+}
+
+/// Guesses the kind of change the files make; `None` when nothing stands out
+pub fn classify(files: &[StagedFile]) -> Option<ChangeKind> {
+    if files.is_empty() {
+        return None;
+    }
+    let all = |test: fn(&StagedFile) -> bool| files.iter().all(test);
+    if all(|file| is_doc_path(&file.path)) {
+        return Some(ChangeKind::Docs);
+    }
+    if all(|file| is_test_path(&file.path)) {
+        return Some(ChangeKind::Tests);
+    }
+    if all(|file| is_dependency_file(&file.path)) {
+        return Some(ChangeKind::Dependencies);
+    }
+
+    let code: Vec<&StagedFile> = files
+        .iter()
+        .filter(|file| !is_doc_path(&file.path) && !is_test_path(&file.path))
+        .collect();
+    if code
+        .iter()
+        .any(|file| file.change_type == ChangeType::Added)
+    {
+        return Some(ChangeKind::Feature);
+    }
+    let (added, removed) = code
+        .iter()
+        .map(|file| line_counts(&file.diff))
+        .fold((0, 0), |(a, r), (added, removed)| (a + added, r + removed));
+    let moved = code.iter().all(|file| {
+        matches!(
+            file.change_type,
+            ChangeType::Renamed { .. } | ChangeType::Deleted
+        )
+    });
+    if moved || (removed > added && added > 0) {
+        return Some(ChangeKind::Refactor);
+    }
+    if code
+        .iter()
+        .all(|file| file.change_type == ChangeType::Modified)
+        && added + removed <= MAX_FIX_LINES
+    {
+        return Some(ChangeKind::Fix);
+    }
+    None
+}
+
+/// Versions a manifest's diff moves dependencies between
+///
+/// `None` when it also adds or removes one, or bumps a version it does not pin.
+fn manifest_bumps(file: &StagedFile) -> Option<Vec<Bump>> {
+    // `^1.2`, `>=1.2` and `v1.2` read as `1.2`, as Dependabot writes them
+    let version = |version: Option<String>| {
+        let version = version?
+            .trim_start_matches(['~', '^', '=', '<', '>', '!', 'v', ' '])
+            .to_string();
+        version
+            .starts_with(|c: char| c.is_ascii_digit())
+            .then_some(version)
+    };
+    dependency_changes(file)
+        .into_iter()
+        .map(|change| {
+            Some(Bump {
+                name: change.name,
+                from: version(change.from)?,
+                to: version(change.to)?,
+            })
+        })
+        .collect()
+}
+
+/// Versions `Cargo.lock` moves packages between, from `-version`/`+version` pairs under a name
+fn cargo_lock_bumps(diff: &str) -> Vec<Bump> {
+    let version = |line: &str| {
+        line.trim()
+            .strip_prefix("version = \"")
+            .and_then(|rest| rest.strip_suffix('"'))
+            .map(ToString::to_string)
+    };
+    let mut bumps = Vec::new();
+    let mut name = None;
+    let mut from = None;
+    for line in diff.lines() {
+        if let Some(rest) = line.get(1..)
+            && let Some(package) = rest
+                .trim()
+                .strip_prefix("name = \"")
+                .and_then(|rest| rest.strip_suffix('"'))
+        {
+            name = Some(package.to_string());
+            from = None;
+        } else if line.starts_with('-') && !line.starts_with("---") {
+            from = version(&line[1..]);
+        } else if line.starts_with('+')
+            && !line.starts_with("+++")
+            && let (Some(name), Some(old), Some(new)) = (&name, from.take(), version(&line[1..]))
+        {
+            bumps.push(Bump {
+                name: name.clone(),
+                from: old,
+                to: new,
+            });
+        }
+    }
+    bumps
+}
+
+/// The single dependency a dependency-only change bumps, if that is all it does
+pub fn single_bump(files: &[StagedFile]) -> Option<Bump> {
+    if files.is_empty() || !files.iter().all(|file| is_dependency_file(&file.path)) {
+        return None;
+    }
+    let manifests: Vec<&StagedFile> = files
+        .iter()
+        .filter(|file| MANIFESTS.contains(&file_name(&file.path)))
+        .collect();
+    let bumps: BTreeSet<Bump> = if manifests.is_empty() {
+        // A lock-only update, as `cargo update -p <name>` makes
+        files
+            .iter()
+            .filter(|file| file_name(&file.path) == "Cargo.lock")
+            .flat_map(|file| cargo_lock_bumps(&file.diff))
+            .collect()
+    } else {
+        manifests
+            .into_iter()
+            .map(manifest_bumps)
+            .collect::<Option<Vec<_>>>()?
+            .into_iter()
+            .flatten()
+            .collect()
+    };
+    let mut bumps = bumps.into_iter();
+    match (bumps.next(), bumps.next()) {
+        (Some(bump), None) => Some(bump),
+        _ => None,
+    }
+}
+
+/// Whether messages in this repository follow Conventional Commits
+fn uses_conventional(config: &Config, context: &CommitContext) -> bool {
+    if let Some(conventions) = &config.conventions {
+        return conventions.conventional;
+    }
+    if let Some(profile) = &context.style_profile {
+        let typed: usize = profile.preferred_types.iter().map(|(_, count)| count).sum();
+        return typed * 2 >= profile.sample_size && typed > 0;
+    }
+    config
+        .get_effective_instructions()
+        .contains("Conventional Commits")
+}
+
+/// The subject for a change that only bumps one dependency; the model is not needed
+pub fn trivial_subject(config: &Config, context: &CommitContext) -> Option<String> {
+    let Bump { name, from, to } = single_bump(&context.staged_files)?;
+    Some(if uses_conventional(config, context) {
+        format!("chore(deps): bump {name} from {from} to {to}")
+    } else {
+        format!("Bump {name} from {from} to {to}")
+    })
+}
+
+/// Labels the context's changes for the prompt
+pub fn attach_change_kind(context: &mut CommitContext) {
+    context.change_kind = classify(&context.staged_files);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(path: &str, change_type: ChangeType, diff: &str) -> StagedFile {
+        StagedFile {
+            path: path.to_string(),
+            change_type,
+            diff: diff.to_string(),
+            content: None,
+            content_excluded: false,
+        }
+    }
+
+    #[test]
+    fn test_classify() {
+        let modified = |path: &str| file(path, ChangeType::Modified, "-a\n+b\n");
+        assert_eq!(
+            classify(&[modified("README.md"), modified("docs/guide.html")]),
+            Some(ChangeKind::Docs)
+        );
+        assert_eq!(
+            classify(&[modified("tests/parser.rs")]),
+            Some(ChangeKind::Tests)
+        );
+        assert_eq!(
+            classify(&[modified("Cargo.toml"), modified("Cargo.lock")]),
+            Some(ChangeKind::Dependencies)
+        );
+        assert_eq!(
+            classify(&[
+                file("src/parser.rs", ChangeType::Added, "+fn parse() {}\n"),
+                modified("tests/parser.rs"),
+            ]),
+            Some(ChangeKind::Feature)
+        );
+        assert_eq!(classify(&[modified("src/lib.rs")]), Some(ChangeKind::Fix));
+        assert_eq!(
+            classify(&[file("src/lib.rs", ChangeType::Modified, "-a\n-b\n-c\n+d\n")]),
+            Some(ChangeKind::Refactor)
+        );
+        assert_eq!(
+            classify(&[file("src/lib.rs", ChangeType::Modified, &"+a\n".repeat(40))]),
+            None
+        );
+    }
+
+    #[test]
+    fn test_single_bump() {
+        let manifest = file(
+            "Cargo.toml",
+            ChangeType::Modified,
+            "--- a/Cargo.toml\n+++ b/Cargo.toml\n@@ -1,3 +1,3 @@\n [dependencies]\n-serde = { version = \"1.0.1\", features = [\"derive\"] }\n+serde = { version = \"1.0.2\", features = [\"derive\"] }\n",
+        );
+        let lock = file(
+            "Cargo.lock",
+            ChangeType::Modified,
+            "@@ -10,4 +10,4 @@\n [[package]]\n name = \"serde\"\n-version = \"1.0.1\"\n+version = \"1.0.2\"\n",
+        );
+        let expected = Bump {
+            name: "serde".to_string(),
+            from: "1.0.1".to_string(),
+            to: "1.0.2".to_string(),
+        };
+        assert_eq!(
+            single_bump(&[manifest.clone(), lock.clone()]),
+            Some(expected.clone())
+        );
+        assert_eq!(single_bump(std::slice::from_ref(&lock)), Some(expected));
+
+        let package_json = file(
+            "web/package.json",
+            ChangeType::Modified,
+            "-    \"react\": \"^18.2.0\",\n+    \"react\": \"^18.3.1\",\n",
+        );
+        assert_eq!(
+            single_bump(&[package_json]).map(|bump| bump.to),
+            Some("18.3.1".to_string())
+        );
+        let go_mod = file(
+            "go.mod",
+            ChangeType::Modified,
+            "-\tgithub.com/gin-gonic/gin v1.9.0\n+\tgithub.com/gin-gonic/gin v1.9.1\n",
+        );
+        assert_eq!(
+            single_bump(&[go_mod]).map(|bump| bump.name),
+            Some("github.com/gin-gonic/gin".to_string())
+        );
+
+        // Two bumps, or code changes alongside, need the model
+        let two = file(
+            "requirements.txt",
+            ChangeType::Modified,
+            "-django==4.2\n-flask==2.0\n+django==4.3\n+flask==2.1\n",
+        );
+        assert_eq!(single_bump(&[two]), None);
+        // Settings that take versions are not dependencies
+        let edition = file(
+            "Cargo.toml",
+            ChangeType::Modified,
+            "@@ -1,4 +1,4 @@\n [package]\n-edition = \"2021\"\n+edition = \"2024\"\n-rust-version = \"1.80\"\n+rust-version = \"1.85\"\n",
+        );
+        assert_eq!(single_bump(&[edition]), None);
+        assert_eq!(
+            single_bump(&[manifest, file("src/lib.rs", ChangeType::Modified, "+x\n")]),
+            None
+        );
+    }
+}
@@ -1,8 +1,16 @@
-use serde::Serialize;
+use super::api_changes::ApiChange;
+use super::classify::ChangeKind;
+use super::style::StyleProfile;
+use super::test_impact::TestGap;
+use crate::analyzers::FileAnalysis;
+use crate::git::MAX_RELATED_FILES;
+use crate::git::ProjectMetadata;
+use crate::issues::Issue;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fmt;
 
-#[derive(Serialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct CommitContext {
     pub branch: String,
     pub recent_commits: Vec<RecentCommit>,
@@ -10,16 +18,53 @@ pub struct CommitContext {
     pub user_name: String,
     pub user_email: String,
     pub author_history: Vec<String>,
+    /// Unchanged files that frame the staged changes (module parents, headers)
+    pub related_files: Vec<RelatedFile>,
+    /// Condensed description of the author's commit style
+    pub style_profile: Option<StyleProfile>,
+    /// Ticket the branch refers to, when an issue tracker is configured
+    pub issue: Option<Issue>,
+    /// CODEOWNERS owners of the changed files that have any
+    pub owners: Vec<FileOwners>,
+    /// Public items the change removes or changes the signature of
+    pub api_changes: Vec<ApiChange>,
+    /// Changed source files whose tests did not change
+    pub test_gaps: Vec<TestGap>,
+    /// Error excerpts of the failed CI run the change fixes
+    pub ci_failure: Option<String>,
+    /// Languages, frameworks and purpose of the project
+    pub project: Option<ProjectMetadata>,
+    /// What kind of change the staged files make, when it stands out
+    pub change_kind: Option<ChangeKind>,
+    /// Format-specific notes on changed files, such as dependency bumps
+    pub analyses: Vec<FileAnalysis>,
+    /// Files given with `--context-file`, such as design docs or ticket exports
+    pub context_files: Vec<ContextFile>,
+    /// Where the files' old and new contents can be read back from
+    #[serde(default)]
+    pub diff_source: DiffSource,
 }
 
-#[derive(Serialize, Debug, Clone)]
+/// The two sides a context's diffs compare, for reading whole files back
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub enum DiffSource {
+    /// Not in the repository, as for a diff read from stdin
+    #[default]
+    Unknown,
+    /// `HEAD` against the index
+    Staged,
+    /// Two commits by id; `from` is `None` for a root commit
+    Commits { from: Option<String>, to: String },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct RecentCommit {
     pub hash: String,
     pub message: String,
     pub timestamp: String,
 }
 
-#[derive(Serialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct StagedFile {
     pub path: String,
     pub change_type: ChangeType,
@@ -28,7 +73,29 @@ pub struct StagedFile {
     pub content_excluded: bool,
 }
 
-#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+/// A file the user added to the prompt for requirements the diff does not show
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ContextFile {
+    pub path: String,
+    pub content: String,
+}
+
+/// An unchanged file included as low-priority context for the staged changes
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct RelatedFile {
+    pub path: String,
+    pub reason: String,
+    pub excerpt: String,
+}
+
+/// Owners of a changed file, from CODEOWNERS
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct FileOwners {
+    pub path: String,
+    pub owners: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub enum ChangeType {
     Added,
     Modified,
@@ -150,6 +217,18 @@ impl CommitContext {
             user_name,
             user_email,
             author_history,
+            related_files: Vec::new(),
+            style_profile: None,
+            issue: None,
+            owners: Vec::new(),
+            api_changes: Vec::new(),
+            test_gaps: Vec::new(),
+            ci_failure: None,
+            project: None,
+            change_kind: None,
+            analyses: Vec::new(),
+            context_files: Vec::new(),
+            diff_source: DiffSource::Unknown,
         }
     }
 
@@ -184,8 +263,199 @@ impl CommitContext {
     }
 }
 
+/// Marker left where a diff was cut to fit the context window
+const DIFF_TRUNCATED_MARKER: &str = "\n[... diff truncated to fit the model's context window ...]";
+
+/// Marker left where a `--context-file` file was cut to fit the context window
+const CONTEXT_TRUNCATED_MARKER: &str =
+    "\n[... file truncated to fit the model's context window ...]";
+
+/// Rough token count of a text, at about four bytes per token
+pub fn estimate_tokens(text: &str) -> usize {
+    text.len().div_ceil(4)
+}
+
+impl CommitContext {
+    /// Estimated tokens of the parts of the context that end up in a prompt
+    pub fn estimated_tokens(&self) -> usize {
+        let files: usize = self
+            .staged_files
+            .iter()
+            .map(|f| f.path.len() + f.diff.len() + f.content.as_ref().map_or(0, String::len))
+            .sum();
+        let related: usize = self
+            .related_files
+            .iter()
+            .map(|f| f.path.len() + f.excerpt.len())
+            .sum();
+        let history: usize = self
+            .recent_commits
+            .iter()
+            .map(|c| c.message.len())
+            .chain(self.author_history.iter().map(String::len))
+            .sum();
+        let extra: usize = self
+            .context_files
+            .iter()
+            .map(|f| f.path.len() + f.content.len())
+            .sum();
+        (files + related + history + extra).div_ceil(4)
+    }
+
+    /// Shrinks the context until its estimate fits in `max_tokens`.
+    ///
+    /// The parts are placed in a [`PromptBudget`] most important first: the
+    /// history and file names, then the diffs, the `--context-file` files,
+    /// the full contents of new files and last the related files. Diffs and
+    /// context files that don't fit are cut down to a common length, so small
+    /// ones survive intact; contents and related files that don't fit are left
+    /// out one by one. Returns whether anything was removed.
+    #[tracing::instrument(skip(self), fields(estimated_tokens = self.estimated_tokens()))]
+    pub fn fit_to_budget(&mut self, max_tokens: usize) -> bool {
+        if self.estimated_tokens() <= max_tokens {
+            return false;
+        }
+        let mut budget = PromptBudget::new(max_tokens);
+        budget.reserve(
+            self.recent_commits
+                .iter()
+                .map(|c| c.message.len())
+                .chain(self.author_history.iter().map(String::len))
+                .chain(self.staged_files.iter().map(|f| f.path.len()))
+                .chain(self.context_files.iter().map(|f| f.path.len()))
+                .sum(),
+        );
+
+        budget.fit(
+            self.staged_files.iter_mut().map(|f| &mut f.diff),
+            DIFF_TRUNCATED_MARKER,
+        );
+        budget.fit(
+            self.context_files.iter_mut().map(|f| &mut f.content),
+            CONTEXT_TRUNCATED_MARKER,
+        );
+        // A file cut down to the marker tells the model nothing
+        self.context_files
+            .retain(|f| f.content != CONTEXT_TRUNCATED_MARKER);
+
+        for file in &mut self.staged_files {
+            if !budget.take(file.content.as_ref().map_or(0, String::len)) {
+                file.content = None;
+            }
+        }
+
+        let related: FixedSizeBuffer<RelatedFile, MAX_RELATED_FILES> = budget
+            .fill(std::mem::take(&mut self.related_files), |f| {
+                f.path.len() + f.excerpt.len()
+            });
+        self.related_files = related.iter().cloned().collect();
+        true
+    }
+}
+
+/// Bytes of a prompt still free, handed out to its parts in priority order
+///
+/// Each part takes what it needs while that fits; what doesn't fit is cut
+/// down or turned away, so the parts placed last are the ones that degrade.
+/// Bytes stand in for tokens at four to a token, as in [`estimate_tokens`].
+struct PromptBudget {
+    remaining: usize,
+}
+
+impl PromptBudget {
+    fn new(max_tokens: usize) -> Self {
+        Self {
+            remaining: max_tokens.saturating_mul(4),
+        }
+    }
+
+    /// Counts `len` bytes that are sent whatever the budget
+    fn reserve(&mut self, len: usize) {
+        self.remaining = self.remaining.saturating_sub(len);
+    }
+
+    /// Takes `len` bytes if they fit, returning whether they did
+    fn take(&mut self, len: usize) -> bool {
+        if len > self.remaining {
+            return false;
+        }
+        self.remaining -= len;
+        true
+    }
+
+    /// Cuts the longest of `texts` to a common length until they fit, and takes them
+    fn fit<'a>(&mut self, texts: impl Iterator<Item = &'a mut String>, marker: &str) {
+        let mut texts: Vec<&mut String> = texts.collect();
+        let total: usize = texts.iter().map(|text| text.len()).sum();
+        cut_to_fit(
+            texts.iter_mut().map(|text| &mut **text),
+            total.saturating_sub(self.remaining),
+            marker,
+        );
+        self.reserve(texts.iter().map(|text| text.len()).sum());
+    }
+
+    /// The leading `items` that fit, up to `N` of them; the rest are turned away
+    fn fill<T: Clone + Default, const N: usize>(
+        &mut self,
+        items: Vec<T>,
+        len: impl Fn(&T) -> usize,
+    ) -> FixedSizeBuffer<T, N> {
+        let mut buffer = FixedSizeBuffer::new();
+        for item in items {
+            if buffer.is_full() || !self.take(len(&item)) {
+                break;
+            }
+            buffer.push(item);
+        }
+        buffer
+    }
+}
+
+/// Cuts the longest of `texts` to a common length, at line boundaries, until
+/// they are `excess` bytes shorter
+fn cut_to_fit<'a>(texts: impl Iterator<Item = &'a mut String>, excess: usize, marker: &str) {
+    if excess == 0 {
+        return;
+    }
+    let mut texts: Vec<&mut String> = texts.collect();
+    let lengths: Vec<usize> = texts.iter().map(|text| text.len()).collect();
+    let cap = diff_length_cap(
+        &lengths,
+        lengths.iter().sum::<usize>().saturating_sub(excess),
+    );
+    for text in &mut texts {
+        if text.len() <= cap {
+            continue;
+        }
+        let mut end = cap.saturating_sub(marker.len());
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+        // Cut at a line boundary so the model never sees half a line
+        let end = text[..end].rfind('\n').unwrap_or(end);
+        text.truncate(end);
+        text.push_str(marker);
+    }
+}
+
+/// Largest per-diff length at which the diffs together stay within `budget` bytes
+fn diff_length_cap(lengths: &[usize], budget: usize) -> usize {
+    let fits = |cap: usize| lengths.iter().map(|len| (*len).min(cap)).sum::<usize>() <= budget;
+    let (mut low, mut high) = (0, lengths.iter().copied().max().unwrap_or(0));
+    while low < high {
+        let mid = low + (high - low).div_ceil(2);
+        if fits(mid) {
+            low = mid;
+        } else {
+            high = mid - 1;
+        }
+    }
+    low
+}
+
 /// Check if a word is an imperative verb commonly used in commit messages
-fn is_imperative_verb(word: &str) -> bool {
+pub(crate) fn is_imperative_verb(word: &str) -> bool {
     let imperative_verbs = [
         "add",
         "update",
@@ -218,6 +488,122 @@ fn is_imperative_verb(word: &str) -> bool {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_fit_to_budget() {
+        let file = |path: &str, diff_len: usize| StagedFile {
+            path: path.to_string(),
+            change_type: ChangeType::Modified,
+            diff: "+line of code\n".repeat(diff_len / 14),
+            content: Some("x".repeat(400)),
+            content_excluded: false,
+        };
+        let mut context = CommitContext::new(
+            "main".to_string(),
+            Vec::new(),
+            vec![file("small.rs", 140), file("big.rs", 14_000)],
+            String::new(),
+            String::new(),
+            Vec::new(),
+        );
+
+        assert!(!context.fit_to_budget(100_000));
+        assert!(context.fit_to_budget(1_000));
+        assert!(context.estimated_tokens() <= 1_000);
+        assert!(context.staged_files.iter().all(|f| f.content.is_none()));
+        assert_eq!(context.staged_files[0].diff.len(), 140);
+        assert!(
+            context.staged_files[1]
+                .diff
+                .ends_with(DIFF_TRUNCATED_MARKER)
+        );
+    }
+
+    #[test]
+    fn test_context_files_are_cut_before_diffs() {
+        let mut context = CommitContext::new(
+            "main".to_string(),
+            Vec::new(),
+            vec![StagedFile {
+                path: "src/lib.rs".to_string(),
+                change_type: ChangeType::Modified,
+                diff: "+line of code\n".repeat(100),
+                content: None,
+                content_excluded: false,
+            }],
+            String::new(),
+            String::new(),
+            Vec::new(),
+        );
+        context.context_files = vec![
+            ContextFile {
+                path: "docs/design.md".to_string(),
+                content: "Requirement line\n".repeat(500),
+            },
+            ContextFile {
+                path: "TICKET.txt".to_string(),
+                content: "Short ticket".to_string(),
+            },
+        ];
+
+        assert!(context.fit_to_budget(1_000));
+        assert!(context.estimated_tokens() <= 1_000);
+        assert_eq!(context.staged_files[0].diff.len(), 1_400);
+        assert!(
+            context.context_files[0]
+                .content
+                .ends_with(CONTEXT_TRUNCATED_MARKER)
+        );
+        assert_eq!(context.context_files[1].content, "Short ticket");
+
+        assert!(context.fit_to_budget(300));
+        assert!(context.context_files.is_empty());
+        assert!(
+            context.staged_files[0]
+                .diff
+                .ends_with(DIFF_TRUNCATED_MARKER)
+        );
+    }
+
+    #[test]
+    fn test_lower_priority_parts_are_dropped_one_by_one() {
+        let file = |path: &str, content_len: usize| StagedFile {
+            path: path.to_string(),
+            change_type: ChangeType::Added,
+            diff: "+line\n".repeat(20),
+            content: Some("x".repeat(content_len)),
+            content_excluded: false,
+        };
+        let mut context = CommitContext::new(
+            "main".to_string(),
+            Vec::new(),
+            vec![file("a.rs", 1_000), file("b.rs", 4_000), file("c.rs", 500)],
+            String::new(),
+            String::new(),
+            Vec::new(),
+        );
+        context.related_files = (0..10)
+            .map(|i| RelatedFile {
+                path: format!("related_{i}.rs"),
+                reason: "sibling".to_string(),
+                excerpt: "y".repeat(100),
+            })
+            .collect();
+
+        // Room for the diffs, two of the contents and a few related files
+        assert!(context.fit_to_budget(500));
+        assert!(context.estimated_tokens() <= 500);
+        assert!(context.staged_files.iter().all(|f| f.diff.len() == 120));
+        let kept: Vec<bool> = context
+            .staged_files
+            .iter()
+            .map(|f| f.content.is_some())
+            .collect();
+        assert_eq!(kept, [true, false, true]);
+        assert!(!context.related_files.is_empty());
+        assert!(context.related_files.len() < 10);
+        assert_eq!(context.related_files[0].path, "related_0.rs");
+    }
+
     #[test]
     fn test_fixed_size_buffer() {
         // Create a buffer of size 3
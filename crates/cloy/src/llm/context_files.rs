@@ -0,0 +1,71 @@
+//! Files given with `--context-file`.
+//!
+//! Design docs and ticket exports carry the requirements a change implements,
+//! which the diff alone does not show. Their text goes into the prompt as is,
+//! after the diffs in priority: when the prompt is too long they are cut down
+//! before any diff is.
+
+use crate::config::Config;
+use crate::llm::context::ContextFile;
+use anyhow::{Context, Result, anyhow};
+use std::path::PathBuf;
+
+/// Bytes read of each file; longer files are cut at a line boundary
+const MAX_FILE_BYTES: usize = 64 * 1024;
+
+/// Reads `path` as text, cut to `MAX_FILE_BYTES`
+fn read(path: &PathBuf) -> Result<ContextFile> {
+    let bytes =
+        std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let mut content =
+        String::from_utf8(bytes).map_err(|_| anyhow!("{} is not a text file", path.display()))?;
+    if content.len() > MAX_FILE_BYTES {
+        let mut end = MAX_FILE_BYTES;
+        while !content.is_char_boundary(end) {
+            end -= 1;
+        }
+        let end = content[..end].rfind('\n').unwrap_or(end);
+        content.truncate(end);
+        content.push_str("\n[...]");
+    }
+    Ok(ContextFile {
+        path: path.display().to_string(),
+        content,
+    })
+}
+
+/// Reads the files in `paths` into the config for this run
+pub fn apply_to_config(config: &mut Config, paths: &[PathBuf]) -> Result<()> {
+    config.context_files = paths.iter().map(read).collect::<Result<_>>()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_context_files() {
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let doc = dir.path().join("design.md");
+        std::fs::write(&doc, "# Design\nRetries back off exponentially.\n")
+            .expect("Failed to write file");
+        let long = dir.path().join("ticket.txt");
+        std::fs::write(&long, "line\n".repeat(MAX_FILE_BYTES)).expect("Failed to write file");
+        let binary = dir.path().join("logo.png");
+        std::fs::write(&binary, [0x89, b'P', b'N', b'G', 0xff, 0xfe]).expect("Failed to write");
+
+        let mut config = Config::default();
+        apply_to_config(&mut config, &[doc.clone(), long]).expect("Failed to read files");
+        assert_eq!(config.context_files[0].path, doc.display().to_string());
+        assert_eq!(
+            config.context_files[0].content,
+            "# Design\nRetries back off exponentially.\n"
+        );
+        assert!(config.context_files[1].content.len() <= MAX_FILE_BYTES + 6);
+        assert!(config.context_files[1].content.ends_with("line\n[...]"));
+
+        assert!(apply_to_config(&mut config, &[binary]).is_err());
+        assert!(apply_to_config(&mut config, &[dir.path().join("missing.md")]).is_err());
+    }
+}
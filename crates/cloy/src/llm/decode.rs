@@ -0,0 +1,253 @@
+//! Decoding of structured responses.
+//!
+//! Models do not always return the bare JSON object they were asked for:
+//! answers come wrapped in code fences or prose, cut off at the token limit,
+//! or with trailing commas. Gemini's JSON mode adds its own variations: the
+//! object wrapped in a one-element array, or encoded again as a JSON string.
+//! Decoding tries progressively more invasive fixes
+//! and reports which one was needed, so the engine can log how often each
+//! failure mode occurs and re-prompt only when nothing worked.
+
+use anyhow::{Result, anyhow};
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::fmt;
+
+/// What it took to decode a response
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Recovery {
+    /// The response was valid JSON as returned
+    None,
+    /// The object was valid JSON inside an array or string, as JSON modes return it
+    Unwrapped,
+    /// The object had to be cut out of code fences or surrounding text
+    Extracted,
+    /// The object had syntax errors that were repaired
+    Repaired,
+    /// The model had to be asked again with the validation error
+    Reprompted,
+}
+
+impl fmt::Display for Recovery {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::None => "none",
+            Self::Unwrapped => "unwrapped",
+            Self::Extracted => "extracted",
+            Self::Repaired => "repaired",
+            Self::Reprompted => "reprompted",
+        })
+    }
+}
+
+/// Decodes a response into `T`, repairing it if needed
+pub fn decode<T: DeserializeOwned>(text: &str) -> Result<(T, Recovery)> {
+    if let Ok(value) = serde_json::from_str(text) {
+        return Ok((value, Recovery::None));
+    }
+    if let Some(value) = unwrap_json_mode(text).and_then(|inner| serde_json::from_str(&inner).ok())
+    {
+        return Ok((value, Recovery::Unwrapped));
+    }
+
+    let extracted = extract_object(text);
+    let error = match serde_json::from_str(extracted) {
+        Ok(value) => return Ok((value, Recovery::Extracted)),
+        Err(e) => e,
+    };
+
+    serde_json::from_str(&repair_json(extracted))
+        .map(|value| (value, Recovery::Repaired))
+        .map_err(|_| anyhow!("JSON parse error: {error}"))
+}
+
+/// Follow-up prompt asking the model to correct a response that failed to decode
+pub fn repair_prompt<T: JsonSchema>(error: &anyhow::Error) -> String {
+    let schema = schemars::schema_for!(T);
+    format!(
+        "Your previous response could not be used: {error}\n\n\
+         Respond again with only a valid JSON object matching this schema, \
+         with no code fences or text around it:\n{}",
+        serde_json::to_string_pretty(&schema).unwrap_or_default()
+    )
+}
+
+/// The JSON inside a one-element array or a string-encoded response
+fn unwrap_json_mode(text: &str) -> Option<String> {
+    match serde_json::from_str(text).ok()? {
+        Value::String(inner) => Some(inner),
+        Value::Array(mut items) if items.len() == 1 => items.pop().map(|item| item.to_string()),
+        _ => None,
+    }
+}
+
+/// Cuts the outermost JSON object out of fences or surrounding prose
+fn extract_object(text: &str) -> &str {
+    let trimmed = text.trim();
+    let without_fences = trimmed
+        .strip_prefix("```")
+        .map_or(trimmed, |rest| {
+            // Drop the language tag on the opening fence
+            let rest = rest.split_once('\n').map_or(rest, |(_, body)| body);
+            rest.trim_end().strip_suffix("```").unwrap_or(rest)
+        })
+        .trim();
+
+    let Some(start) = without_fences.find('{') else {
+        return without_fences;
+    };
+    // A truncated response has no closing brace; keep everything for repair
+    let end = without_fences
+        .rfind('}')
+        .filter(|end| *end > start)
+        .map_or(without_fences.len(), |i| i + 1);
+    &without_fences[start..end]
+}
+
+/// Fixes the syntax errors models commonly make.
+///
+/// Removes trailing commas, escapes raw control characters inside strings,
+/// and closes strings, arrays and objects left open by a truncated response.
+fn repair_json(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut closers = Vec::new();
+    let mut in_string = false;
+    let mut escaped = false;
+
+    for c in text.chars() {
+        if in_string {
+            match c {
+                _ if escaped => {
+                    escaped = false;
+                    out.push(c);
+                }
+                '\\' => {
+                    escaped = true;
+                    out.push(c);
+                }
+                '"' => {
+                    in_string = false;
+                    out.push(c);
+                }
+                '\n' => out.push_str("\\n"),
+                '\r' => out.push_str("\\r"),
+                '\t' => out.push_str("\\t"),
+                _ => out.push(c),
+            }
+            continue;
+        }
+
+        match c {
+            '"' => {
+                in_string = true;
+                out.push(c);
+            }
+            '{' => {
+                closers.push('}');
+                out.push(c);
+            }
+            '[' => {
+                closers.push(']');
+                out.push(c);
+            }
+            '}' | ']' => {
+                // Mismatched closers are dropped rather than guessed at
+                if closers.last() == Some(&c) {
+                    closers.pop();
+                    strip_trailing_comma(&mut out);
+                    out.push(c);
+                }
+            }
+            _ => out.push(c),
+        }
+    }
+
+    if in_string {
+        if escaped {
+            out.pop();
+        }
+        out.push('"');
+    }
+    while let Some(closer) = closers.pop() {
+        strip_trailing_comma(&mut out);
+        // A key cut off before its value cannot be completed
+        if out.trim_end().ends_with(':') {
+            out.push_str("null");
+        }
+        out.push(closer);
+    }
+    out
+}
+
+fn strip_trailing_comma(out: &mut String) {
+    let trimmed = out.trim_end().len();
+    out.truncate(trimmed);
+    if out.ends_with(',') {
+        out.pop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Message {
+        title: String,
+        tags: Vec<String>,
+    }
+
+    fn message(title: &str, tags: &[&str]) -> Message {
+        Message {
+            title: title.to_string(),
+            tags: tags.iter().map(ToString::to_string).collect(),
+        }
+    }
+
+    #[test]
+    fn test_decode_recovery() {
+        let (value, recovery) =
+            decode::<Message>(r#"{"title": "a", "tags": []}"#).expect("valid JSON");
+        assert_eq!((value, recovery), (message("a", &[]), Recovery::None));
+
+        let fenced = "```json\n{\"title\": \"a\", \"tags\": [\"x\"]}\n```";
+        let (value, recovery) = decode::<Message>(fenced).expect("fenced JSON");
+        assert_eq!(
+            (value, recovery),
+            (message("a", &["x"]), Recovery::Extracted)
+        );
+
+        let trailing = "Here you go: {\"title\": \"a\",\n\"tags\": [\"x\",],}";
+        let (value, recovery) = decode::<Message>(trailing).expect("trailing commas");
+        assert_eq!(
+            (value, recovery),
+            (message("a", &["x"]), Recovery::Repaired)
+        );
+
+        let truncated = "{\"tags\": [\"x\", \"y\"], \"title\": \"line one\nline tw";
+        let (value, recovery) = decode::<Message>(truncated).expect("truncated JSON");
+        assert_eq!(
+            (value, recovery),
+            (
+                message("line one\nline tw", &["x", "y"]),
+                Recovery::Repaired
+            )
+        );
+
+        let wrapped = r#"[{"title": "a", "tags": []}]"#;
+        let (value, recovery) = decode::<Message>(wrapped).expect("array-wrapped JSON");
+        assert_eq!((value, recovery), (message("a", &[]), Recovery::Unwrapped));
+
+        let encoded = r#""{\"title\": \"a\", \"tags\": [\"x\"]}""#;
+        let (value, recovery) = decode::<Message>(encoded).expect("string-encoded JSON");
+        assert_eq!(
+            (value, recovery),
+            (message("a", &["x"]), Recovery::Unwrapped)
+        );
+
+        let error = decode::<Message>(r#"{"title": 1, "tags": []}"#).expect_err("wrong field type");
+        assert!(error.to_string().contains("invalid type"));
+    }
+}
@@ -0,0 +1,246 @@
+//! Embedding-based relevance ranking for prompt context.
+//!
+//! Related files are otherwise found and ordered by path conventions. When a
+//! provider has an embedding model configured, they are ranked by cosine
+//! similarity to the staged diff instead. The prompt budget keeps related
+//! files in order until it runs out, so the ones that survive truncation are
+//! the ones most related to the change.
+
+use crate::config::Config;
+use crate::http;
+use crate::llm::context::CommitContext;
+use crate::llm::endpoint;
+use crate::llm::provider::ProviderKind;
+use anyhow::{Result, anyhow};
+use llm::builder::LLMBuilder;
+use log::debug;
+
+/// Additional param holding the embedding model (`gitai.<provider>-additionalembedding-model`)
+pub const EMBEDDING_MODEL_PARAM: &str = "embedding-model";
+
+/// Characters of each text sent to the embedding endpoint
+const MAX_EMBEDDING_INPUT: usize = 2000;
+
+/// Returns the configured embedding model for a provider, if ranking is enabled.
+pub fn embedding_model(config: &Config, provider_name: &str) -> Option<String> {
+    config
+        .get_provider_config(provider_name)
+        .and_then(|p| p.additional_params.get(EMBEDDING_MODEL_PARAM))
+        .filter(|m| !m.trim().is_empty())
+        .cloned()
+}
+
+/// Embeds a batch of texts with the provider's embedding model.
+pub async fn embed_texts(
+    config: &Config,
+    provider_name: &str,
+    texts: Vec<String>,
+) -> Result<Vec<Vec<f32>>> {
+    let provider = ProviderKind::from_name(provider_name)
+        .ok_or_else(|| anyhow!("Provider '{provider_name}' is not supported"))?;
+    let provider_config = config
+        .get_provider_config(provider_name)
+        .ok_or_else(|| anyhow!("Provider '{provider_name}' not found in configuration"))?;
+    let model = embedding_model(config, provider_name)
+        .ok_or_else(|| anyhow!("No embedding model configured for '{provider_name}'"))?;
+
+    let backend = provider
+        .backend()
+        .ok_or_else(|| anyhow!("{provider} does not support embeddings"))?;
+    http::ensure_reaches(provider)?;
+    endpoint::validate_endpoint(provider, &provider_config.additional_params)?;
+    let mut builder = LLMBuilder::new().backend(backend).model(model);
+    if !provider_config.api_key.is_empty() {
+        builder = builder.api_key(provider_config.api_key.clone());
+    }
+    builder = endpoint::apply_endpoint(
+        builder,
+        provider,
+        &provider_config.additional_params,
+        &provider_config.api_key,
+    );
+    let embedder = builder
+        .build()
+        .map_err(|e| anyhow!("Failed to build embedding provider: {e}"))?;
+
+    let inputs = texts
+        .into_iter()
+        .map(|t| t.chars().take(MAX_EMBEDDING_INPUT).collect())
+        .collect();
+    embedder
+        .embed(inputs)
+        .await
+        .map_err(|e| anyhow!("Embedding request failed: {e}"))
+}
+
+/// Cosine similarity of two vectors; zero when either has no magnitude.
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        0.0
+    } else {
+        dot / (norm_a * norm_b)
+    }
+}
+
+/// Returns candidate indices ordered from most to least similar to `query`.
+pub fn rank_by_similarity(query: &[f32], candidates: &[Vec<f32>]) -> Vec<usize> {
+    let mut scored: Vec<(usize, f32)> = candidates
+        .iter()
+        .enumerate()
+        .map(|(i, c)| (i, cosine_similarity(query, c)))
+        .collect();
+    scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+    scored.into_iter().map(|(i, _)| i).collect()
+}
+
+/// Reorders related files by relevance to the staged diff.
+///
+/// Ranking is best effort: without an embedding model, or when the embedding
+/// call fails, the files are left in their heuristic order.
+#[tracing::instrument(skip_all)]
+pub async fn rank_context(config: &Config, provider_name: &str, context: &mut CommitContext) {
+    if embedding_model(config, provider_name).is_none()
+        || context.staged_files.is_empty()
+        || context.related_files.len() < 2
+    {
+        return;
+    }
+
+    let query = context
+        .staged_files
+        .iter()
+        .map(|f| format!("{}\n{}", f.path, f.diff))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let mut texts = vec![query];
+    texts.extend(
+        context
+            .related_files
+            .iter()
+            .map(|f| format!("{}\n{}", f.path, f.excerpt)),
+    );
+
+    let embeddings = match embed_texts(config, provider_name, texts).await {
+        Ok(embeddings) => embeddings,
+        Err(e) => {
+            debug!("Embedding ranking skipped: {e}");
+            return;
+        }
+    };
+
+    if embeddings.len() != 1 + context.related_files.len() {
+        debug!("Embedding ranking skipped: provider returned a mismatched batch");
+        return;
+    }
+
+    let (query, related_vecs) = embeddings.split_at(1);
+    context.related_files = rank_by_similarity(&query[0], related_vecs)
+        .into_iter()
+        .map(|i| context.related_files[i].clone())
+        .collect();
+    debug!(
+        "Ranked {} related files by embedding similarity",
+        context.related_files.len()
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ProviderConfig;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// Answers one embeddings request and returns the request it got
+    async fn serve_embedding(listener: TcpListener) -> String {
+        let (mut socket, _) = listener.accept().await.expect("No request");
+        let mut request = Vec::new();
+        let mut buf = [0; 4096];
+        loop {
+            let n = socket.read(&mut buf).await.expect("Failed to read request");
+            if n == 0 {
+                break;
+            }
+            request.extend_from_slice(&buf[..n]);
+            let text = String::from_utf8_lossy(&request);
+            if let Some((head, body)) = text.split_once("\r\n\r\n") {
+                let length = head
+                    .lines()
+                    .find_map(|l| {
+                        l.to_lowercase()
+                            .strip_prefix("content-length:")
+                            .map(|v| v.trim().to_string())
+                    })
+                    .and_then(|v| v.parse::<usize>().ok())
+                    .unwrap_or(0);
+                if body.len() >= length {
+                    break;
+                }
+            }
+        }
+        let body = r#"{"data":[{"embedding":[0.5,0.25]}]}"#;
+        let response = format!(
+            "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+            body.len()
+        );
+        socket
+            .write_all(response.as_bytes())
+            .await
+            .expect("Failed to respond");
+        String::from_utf8_lossy(&request).into_owned()
+    }
+
+    #[tokio::test]
+    async fn test_embed_texts_uses_configured_endpoint() {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("Failed to bind");
+        let address = listener.local_addr().expect("No address");
+        let server = tokio::spawn(serve_embedding(listener));
+
+        let mut config = Config::default();
+        config.providers.insert(
+            ProviderKind::OpenAICompatible.as_str().to_string(),
+            ProviderConfig {
+                api_key: String::new(),
+                model_name: "local".to_string(),
+                additional_params: [
+                    (
+                        endpoint::BASE_URL_PARAM.to_string(),
+                        format!("http://{address}/v1"),
+                    ),
+                    (EMBEDDING_MODEL_PARAM.to_string(), "nomic-embed".to_string()),
+                ]
+                .into_iter()
+                .collect(),
+            },
+        );
+
+        let embeddings = embed_texts(&config, "openai-compatible", vec!["diff".to_string()])
+            .await
+            .expect("Embedding failed");
+        assert_eq!(embeddings, vec![vec![0.5, 0.25]]);
+
+        let request = server.await.expect("Server failed");
+        assert!(request.starts_with("POST /v1/embeddings "), "{request}");
+        assert!(request.contains("nomic-embed"), "{request}");
+    }
+
+    #[test]
+    fn test_cosine_similarity() {
+        assert!((cosine_similarity(&[1.0, 0.0], &[1.0, 0.0]) - 1.0).abs() < f32::EPSILON);
+        assert!(cosine_similarity(&[1.0, 0.0], &[0.0, 1.0]).abs() < f32::EPSILON);
+        assert!(cosine_similarity(&[0.0, 0.0], &[1.0, 1.0]).abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn test_rank_by_similarity() {
+        let query = [1.0, 0.0];
+        let candidates = vec![vec![0.0, 1.0], vec![1.0, 0.1], vec![1.0, 1.0]];
+        assert_eq!(rank_by_similarity(&query, &candidates), vec![1, 2, 0]);
+    }
+}
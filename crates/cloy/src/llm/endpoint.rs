@@ -0,0 +1,189 @@
+//! Endpoint settings for Azure `OpenAI` and `OpenAI`-compatible servers.
+//!
+//! Both are configured through additional params: `base-url` names the
+//! endpoint (the resource URL for Azure, the `/v1` root for everything
+//! else), and Azure also needs the `deployment` and `api-version` it routes
+//! requests by. `OpenRouter` accepts a `base-url` too, for proxies in front
+//! of it, and Bedrock for VPC endpoints; Bedrock also needs a `region`.
+
+use crate::llm::bedrock;
+use crate::llm::provider::ProviderKind;
+use anyhow::{Result, anyhow};
+use llm::builder::LLMBuilder;
+use std::collections::HashMap;
+use url::Url;
+
+/// Additional param overriding the provider's endpoint
+pub const BASE_URL_PARAM: &str = "base-url";
+
+/// Additional param naming the Azure deployment requests are sent to
+pub const DEPLOYMENT_PARAM: &str = "deployment";
+
+/// Additional param with the Azure `OpenAI` REST API version
+pub const API_VERSION_PARAM: &str = "api-version";
+
+/// Key sent to `OpenAI`-compatible servers that do not check one
+const PLACEHOLDER_API_KEY: &str = "none";
+
+/// Checks that the params the provider needs to find its endpoint are present and valid
+pub fn validate_endpoint<S: std::hash::BuildHasher>(
+    provider: ProviderKind,
+    params: &HashMap<String, String, S>,
+) -> Result<()> {
+    let param = |key: &str| params.get(key).map(|v| v.trim()).filter(|v| !v.is_empty());
+    let setting = |key: &str| format!("gitai.{provider}-additional{key}");
+
+    match param(BASE_URL_PARAM) {
+        Some(_) if !provider.accepts_base_url() => {
+            return Err(anyhow!(
+                "{provider} does not accept a custom endpoint; unset {}",
+                setting(BASE_URL_PARAM)
+            ));
+        }
+        Some(base_url) => {
+            let url = Url::parse(base_url)
+                .map_err(|e| anyhow!("Invalid {}: {base_url}: {e}", setting(BASE_URL_PARAM)))?;
+            if !matches!(url.scheme(), "http" | "https") {
+                return Err(anyhow!(
+                    "{} must be an http or https URL, got {base_url}",
+                    setting(BASE_URL_PARAM)
+                ));
+            }
+        }
+        None if provider.requires_base_url() => {
+            return Err(anyhow!(
+                "{provider} needs an endpoint; set {}",
+                setting(BASE_URL_PARAM)
+            ));
+        }
+        None => {}
+    }
+
+    if provider == ProviderKind::AzureOpenAI {
+        for key in [DEPLOYMENT_PARAM, API_VERSION_PARAM] {
+            if param(key).is_none() {
+                return Err(anyhow!("{provider} needs {} to be set", setting(key)));
+            }
+        }
+    }
+    if provider == ProviderKind::Bedrock && bedrock::region(params).is_none() {
+        return Err(anyhow!(
+            "{provider} needs a region; set AWS_REGION or {}",
+            setting(bedrock::REGION_PARAM)
+        ));
+    }
+    Ok(())
+}
+
+/// Points the builder at the configured endpoint
+pub fn apply_endpoint<S: std::hash::BuildHasher>(
+    mut builder: LLMBuilder,
+    provider: ProviderKind,
+    params: &HashMap<String, String, S>,
+    api_key: &str,
+) -> LLMBuilder {
+    let param = |key: &str| params.get(key).map(|v| v.trim()).filter(|v| !v.is_empty());
+
+    if let Some(base_url) = param(BASE_URL_PARAM).filter(|_| provider.accepts_base_url()) {
+        builder = builder.base_url(normalize_base_url(provider, base_url));
+    }
+    if provider == ProviderKind::AzureOpenAI {
+        if let Some(deployment) = param(DEPLOYMENT_PARAM) {
+            builder = builder.deployment_id(deployment);
+        }
+        if let Some(api_version) = param(API_VERSION_PARAM) {
+            builder = builder.api_version(api_version);
+        }
+    }
+    if api_key.is_empty() && !provider.requires_api_key() {
+        builder = builder.api_key(PLACEHOLDER_API_KEY);
+    }
+    builder
+}
+
+/// The form each backend joins request paths onto.
+///
+/// Azure appends `/openai/deployments/...` to the resource URL itself, while
+/// the `OpenAI` client resolves `chat/completions` relative to the base URL,
+/// which drops its last segment unless it ends with a slash.
+fn normalize_base_url(provider: ProviderKind, base_url: &str) -> String {
+    let trimmed = base_url.trim_end_matches('/');
+    if provider == ProviderKind::AzureOpenAI {
+        trimmed.to_string()
+    } else {
+        format!("{trimmed}/")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| ((*k).to_string(), (*v).to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_validate_endpoint() {
+        let azure = params(&[
+            (BASE_URL_PARAM, "https://example.openai.azure.com"),
+            (DEPLOYMENT_PARAM, "gpt-4o"),
+            (API_VERSION_PARAM, "2024-10-21"),
+        ]);
+        assert!(validate_endpoint(ProviderKind::AzureOpenAI, &azure).is_ok());
+
+        let mut missing = azure.clone();
+        missing.remove(DEPLOYMENT_PARAM);
+        let error = validate_endpoint(ProviderKind::AzureOpenAI, &missing)
+            .expect_err("Deployment is required");
+        assert!(
+            error
+                .to_string()
+                .contains("gitai.azure-openai-additionaldeployment"),
+            "{error}"
+        );
+
+        assert!(validate_endpoint(ProviderKind::OpenAICompatible, &params(&[])).is_err());
+        assert!(
+            validate_endpoint(
+                ProviderKind::OpenAICompatible,
+                &params(&[(BASE_URL_PARAM, "localhost:1234/v1")])
+            )
+            .is_err()
+        );
+        assert!(
+            validate_endpoint(
+                ProviderKind::OpenAICompatible,
+                &params(&[(BASE_URL_PARAM, "http://localhost:1234/v1")])
+            )
+            .is_ok()
+        );
+
+        assert!(validate_endpoint(ProviderKind::Google, &params(&[])).is_ok());
+        assert!(
+            validate_endpoint(
+                ProviderKind::Google,
+                &params(&[(BASE_URL_PARAM, "https://proxy.example.com")])
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_normalize_base_url() {
+        assert_eq!(
+            normalize_base_url(ProviderKind::OpenAICompatible, "http://localhost:8000/v1"),
+            "http://localhost:8000/v1/"
+        );
+        assert_eq!(
+            normalize_base_url(
+                ProviderKind::AzureOpenAI,
+                "https://example.openai.azure.com/"
+            ),
+            "https://example.openai.azure.com"
+        );
+    }
+}
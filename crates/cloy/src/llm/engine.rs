@@ -1,19 +1,40 @@
-use crate::config::Config;
+use crate::config::{Config, ProviderConfig};
+use crate::error::{ErrorCode, coded};
+use crate::http;
+use crate::llm::bedrock::BedrockProvider;
+use crate::llm::context::estimate_tokens;
+use crate::llm::decode::{self, Recovery};
+use crate::llm::endpoint;
+use crate::llm::gemini::GeminiProvider;
 use crate::llm::provider::ProviderKind;
+use crate::llm::rate_limit::{self, MAX_RATE_LIMIT_WAIT, REQUESTS_PER_MINUTE_PARAM, Throttle};
+use crate::progress::{self, Event};
 use anyhow::{Result, anyhow};
-use llm::{LLMProvider, builder::LLMBuilder, chat::ChatMessage};
+use llm::{
+    LLMProvider,
+    builder::{FunctionBuilder, LLMBuilder},
+    chat::{ChatMessage, ChatResponse, ToolChoice, Usage},
+    error::LLMError,
+};
 use log::debug;
 use schemars::JsonSchema;
 use serde::de::DeserializeOwned;
 use std::collections::HashMap;
+use std::ops::Add;
+use std::time::Duration;
+use tokio::time::error::Elapsed;
 use tokio_retry::Retry;
 use tokio_retry::strategy::ExponentialBackoff;
 
-/// Initialize tracing to a rolling file in temp directory
+/// Initialize tracing to a rolling file in temp directory.
+///
+/// The subscriber also has an empty slot that `--trace` fills with a Chrome
+/// trace writer; see [`crate::trace`].
 pub fn init_tracing_to_file() {
-    use tracing::Level;
     use tracing_appender::rolling::{RollingFileAppender, Rotation};
+    use tracing_subscriber::filter::LevelFilter;
     use tracing_subscriber::fmt::format::FmtSpan;
+    use tracing_subscriber::prelude::*;
 
     let log_dir = std::env::temp_dir().join("gitai-logs").join("debug");
     if let Err(e) = std::fs::create_dir_all(&log_dir) {
@@ -21,11 +42,14 @@ pub fn init_tracing_to_file() {
         return;
     }
     let file_appender = RollingFileAppender::new(Rotation::DAILY, log_dir, "llm-debug.log");
-    if tracing_subscriber::fmt()
+    let file_layer = tracing_subscriber::fmt::layer()
         .with_writer(file_appender)
-        .with_max_level(Level::INFO)
         .with_span_events(FmtSpan::CLOSE)
         .json()
+        .with_filter(LevelFilter::INFO);
+    if tracing_subscriber::registry()
+        .with(crate::trace::trace_slot())
+        .with(file_layer)
         .try_init()
         .is_err()
     {
@@ -33,6 +57,49 @@ pub fn init_tracing_to_file() {
     }
 }
 
+/// Tokens billed for one generation, as reported by the provider
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TokenUsage {
+    pub prompt_tokens: u32,
+    pub completion_tokens: u32,
+    /// Prompt tokens the provider served from its prompt cache
+    pub cached_tokens: u32,
+}
+
+impl From<Usage> for TokenUsage {
+    fn from(usage: Usage) -> Self {
+        Self {
+            prompt_tokens: usage.prompt_tokens,
+            completion_tokens: usage.completion_tokens,
+            cached_tokens: usage
+                .prompt_tokens_details
+                .and_then(|details| details.cached_tokens)
+                .unwrap_or(0),
+        }
+    }
+}
+
+impl Add for TokenUsage {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            prompt_tokens: self.prompt_tokens + other.prompt_tokens,
+            completion_tokens: self.completion_tokens + other.completion_tokens,
+            cached_tokens: self.cached_tokens + other.cached_tokens,
+        }
+    }
+}
+
+/// Additional param selecting how structured output is requested: `prompt` (default) or `tools`
+pub const STRUCTURED_OUTPUT_PARAM: &str = "structured-output";
+
+/// Name of the function models call to return structured output
+const STRUCTURED_OUTPUT_TOOL: &str = "submit_output";
+
+/// How long a single provider call may take
+const RESPONSE_TIMEOUT: Duration = Duration::from_mins(1);
+
 /// Generates a message using the given configuration
 pub async fn get_message<T>(
     config: &Config,
@@ -40,6 +107,67 @@ pub async fn get_message<T>(
     system_prompt: &str,
     user_prompt: &str,
 ) -> Result<T>
+where
+    T: DeserializeOwned + JsonSchema,
+{
+    get_message_with_usage(config, provider_name, system_prompt, user_prompt)
+        .await
+        .map(|(message, _)| message)
+}
+
+/// Generates a message and returns the token usage of the successful attempt, if reported.
+///
+/// With `gitai.race` naming a second provider, both are asked at once and the
+/// first valid response wins; the other request is dropped, which cancels it.
+pub async fn get_message_with_usage<T>(
+    config: &Config,
+    provider_name: &str,
+    system_prompt: &str,
+    user_prompt: &str,
+) -> Result<(T, Option<TokenUsage>)>
+where
+    T: DeserializeOwned + JsonSchema,
+{
+    let Some(rival) = config.race_provider(provider_name) else {
+        return get_message_from(config, provider_name, system_prompt, user_prompt).await;
+    };
+
+    let attempts = [provider_name, rival.as_str()].map(|name| {
+        Box::pin(async move {
+            let result = get_message_from(config, name, system_prompt, user_prompt).await;
+            match &result {
+                Ok(_) => debug!("{name} answered first"),
+                Err(e) => debug!("{name} failed in the race: {e}"),
+            }
+            result
+        })
+    });
+    futures::future::select_ok(attempts)
+        .await
+        .map(|(message, _slower)| message)
+        .map_err(|e| {
+            let code = ErrorCode::of(&e).unwrap_or(ErrorCode::ProviderFailed);
+            coded(
+                code,
+                format!("Both {provider_name} and {rival} failed; last error: {e}"),
+            )
+        })
+}
+
+/// Generates a message with one provider
+#[tracing::instrument(
+    skip_all,
+    fields(
+        provider = provider_name,
+        prompt_tokens = estimate_tokens(system_prompt) + estimate_tokens(user_prompt),
+    )
+)]
+async fn get_message_from<T>(
+    config: &Config,
+    provider_name: &str,
+    system_prompt: &str,
+    user_prompt: &str,
+) -> Result<(T, Option<TokenUsage>)>
 where
     T: DeserializeOwned + JsonSchema,
 {
@@ -47,16 +175,31 @@ where
     debug!("System prompt: {system_prompt}");
     debug!("User prompt: {user_prompt}");
 
-    let provider = ProviderKind::from_name(provider_name)
-        .ok_or_else(|| anyhow!("Provider '{provider_name}' is not supported"))?;
+    #[cfg(feature = "test-utils")]
+    if let Some(mock) = crate::testing::installed() {
+        return chat_with_retry(
+            Box::new(mock.with_system(system_prompt)),
+            user_prompt,
+            false,
+            None,
+        )
+        .await;
+    }
 
-    // Get provider configuration
-    let provider_config = config
-        .get_provider_config(provider_name)
-        .ok_or_else(|| anyhow!("Provider '{provider_name}' not found in configuration"))?;
+    let provider = ProviderKind::from_name(provider_name).ok_or_else(|| {
+        coded(
+            ErrorCode::UnknownProvider,
+            format!("Provider '{provider_name}' is not supported"),
+        )
+    })?;
 
-    // Build the provider
-    let mut builder = LLMBuilder::new().backend(provider.backend());
+    // Get provider configuration
+    let provider_config = config.get_provider_config(provider_name).ok_or_else(|| {
+        coded(
+            ErrorCode::UnknownProvider,
+            format!("Provider '{provider_name}' not found in configuration"),
+        )
+    })?;
 
     // Set model (use config if set, otherwise default)
     let model = if provider_config.model_name.is_empty() {
@@ -64,7 +207,37 @@ where
     } else {
         provider_config.model_name.clone()
     };
-    builder = builder.model(model);
+    progress::emit(&Event::LlmRequestStarted {
+        provider: provider.as_str(),
+        model: &model,
+    });
+
+    let throttle = Throttle {
+        provider,
+        requests_per_minute: provider_config
+            .additional_params
+            .get(REQUESTS_PER_MINUTE_PARAM)
+            .and_then(|v| v.trim().parse().ok()),
+    };
+
+    // Providers gitai calls itself rather than through an llm backend
+    let json_output = std::any::type_name::<T>() != std::any::type_name::<String>();
+    if let Some(native) = native_provider(
+        provider,
+        provider_config,
+        &model,
+        system_prompt,
+        json_output,
+    )? {
+        return chat_with_retry(native, user_prompt, false, Some(throttle)).await;
+    }
+    let backend = provider
+        .backend()
+        .ok_or_else(|| anyhow!("Provider '{provider_name}' has no backend"))?;
+    http::ensure_reaches(provider)?;
+
+    // Build the provider
+    let mut builder = LLMBuilder::new().backend(backend).model(model);
 
     // Set system prompt
     builder = builder.system(system_prompt.to_string());
@@ -73,12 +246,16 @@ where
     if !provider_config.api_key.is_empty() {
         builder = builder.api_key(provider_config.api_key.clone());
     }
+    builder = endpoint::apply_endpoint(
+        builder,
+        provider,
+        &provider_config.additional_params,
+        &provider_config.api_key,
+    );
 
     // Set temperature if specified in additional params
-    if let Some(temp) = provider_config.additional_params.get("temperature")
-        && let Ok(temp_val) = temp.parse::<f32>()
-    {
-        builder = builder.temperature(temp_val);
+    if let Some(temperature) = provider_config.temperature() {
+        builder = builder.temperature(temperature);
     }
 
     // Set max tokens if specified in additional params, otherwise use a safe default
@@ -92,10 +269,19 @@ where
     }
 
     // Set top_p if specified in additional params
-    if let Some(top_p) = provider_config.additional_params.get("top_p")
-        && let Ok(tp_val) = top_p.parse::<f32>()
-    {
-        builder = builder.top_p(tp_val);
+    if let Some(top_p) = provider_config.top_p() {
+        builder = builder.top_p(top_p);
+    }
+    if provider_config.seed().is_some() {
+        debug!("The {provider} backend cannot send a seed; ignoring it");
+    }
+
+    // Request the output through a forced tool call where the provider supports it
+    let use_tools = wants_tool_output::<T>(provider, &provider_config.additional_params);
+    if use_tools {
+        builder = builder
+            .function(structured_output_function::<T>())
+            .tool_choice(ToolChoice::Tool(STRUCTURED_OUTPUT_TOOL.to_string()));
     }
 
     // Build the provider
@@ -104,22 +290,92 @@ where
         .map_err(|e| anyhow!("Failed to build provider: {e}"))?;
 
     // Generate the message
-    get_message_with_provider(provider, user_prompt, provider_name, system_prompt).await
+    chat_with_retry(provider, user_prompt, use_tools, Some(throttle)).await
+}
+
+/// The provider's own client, for providers the `llm` crate lacks or covers only partly
+fn native_provider(
+    provider: ProviderKind,
+    config: &ProviderConfig,
+    model: &str,
+    system_prompt: &str,
+    json_output: bool,
+) -> Result<Option<Box<dyn LLMProvider + Send + Sync>>> {
+    let native: Box<dyn LLMProvider + Send + Sync> = match provider {
+        ProviderKind::Google => Box::new(
+            GeminiProvider::new(config, model.to_string(), system_prompt, json_output)
+                .map_err(|e| anyhow!("Failed to build provider: {e}"))?,
+        ),
+        ProviderKind::Bedrock => Box::new(
+            BedrockProvider::new(config, model.to_string(), system_prompt)
+                .map_err(|e| anyhow!("Failed to build provider: {e}"))?,
+        ),
+        ProviderKind::OpenRouter | ProviderKind::AzureOpenAI | ProviderKind::OpenAICompatible => {
+            return Ok(None);
+        }
+    };
+    Ok(Some(native))
+}
+
+/// Whether to request `T` through native tool use rather than a schema in the prompt
+fn wants_tool_output<T>(provider: ProviderKind, params: &HashMap<String, String>) -> bool {
+    if std::any::type_name::<T>() == std::any::type_name::<String>()
+        || params.get(STRUCTURED_OUTPUT_PARAM).map(String::as_str) != Some("tools")
+    {
+        return false;
+    }
+    if !provider.supports_tool_use() {
+        debug!("{provider} does not support forced tool calls; embedding the schema in the prompt");
+        return false;
+    }
+    true
+}
+
+/// The function the model is forced to call with the structured output as its arguments
+fn structured_output_function<T: JsonSchema>() -> FunctionBuilder {
+    let mut schema = serde_json::to_value(schemars::schema_for!(T)).unwrap_or_default();
+    if let Some(object) = schema.as_object_mut() {
+        object.remove("$schema");
+        object.remove("title");
+    }
+    FunctionBuilder::new(STRUCTURED_OUTPUT_TOOL)
+        .description("Submit the requested output")
+        .json_schema(schema)
 }
 
 /// Generates a message using the given provider (mainly for testing purposes)
 pub async fn get_message_with_provider<T>(
     provider: Box<dyn LLMProvider + Send + Sync>,
     user_prompt: &str,
-    _provider_type: &str,
+    provider_type: &str,
     #[allow(clippy::used_underscore_binding)] _system_prompt: &str,
 ) -> Result<T>
 where
     T: DeserializeOwned + JsonSchema,
 {
     debug!("Entering get_message_with_provider");
+    let throttle = ProviderKind::from_name(provider_type).map(|provider| Throttle {
+        provider,
+        requests_per_minute: None,
+    });
+    chat_with_retry(provider, user_prompt, false, throttle)
+        .await
+        .map(|(message, _)| message)
+}
 
-    let retry_strategy = ExponentialBackoff::from_millis(50).factor(2).take(3); // 3 attempts total: initial + 2 retries
+/// Sends the prompt with retries and parses the response
+async fn chat_with_retry<T>(
+    provider: Box<dyn LLMProvider + Send + Sync>,
+    user_prompt: &str,
+    use_tools: bool,
+    throttle: Option<Throttle>,
+) -> Result<(T, Option<TokenUsage>)>
+where
+    T: DeserializeOwned + JsonSchema,
+{
+    // 3 attempts total: initial + 2 retries after 100ms and 200ms. The backoff
+    // raises its base to the attempt number, so 50 * 2^n is base 2, factor 50
+    let retry_strategy = ExponentialBackoff::from_millis(2).factor(50).take(2);
 
     let result = Retry::spawn(retry_strategy, async || {
         debug!("Attempting to generate message");
@@ -134,32 +390,56 @@ where
         // Create chat message with user prompt
         let messages = vec![ChatMessage::user().content(enhanced_prompt.clone()).build()];
 
-        match tokio::time::timeout(std::time::Duration::from_mins(1), provider.chat(&messages)).await {
+        match send_throttled(provider.as_ref(), &messages, use_tools, throttle).await {
             Ok(Ok(response)) => {
-                let response_text = response.text().unwrap_or_else(|| {
+                // Tool arguments carry the output; a model that answered in text falls back to it
+                let tool_arguments = response.tool_calls().and_then(|calls| {
+                    calls
+                        .into_iter()
+                        .find(|call| call.function.name == STRUCTURED_OUTPUT_TOOL)
+                        .map(|call| call.function.arguments)
+                });
+                let response_text = tool_arguments.or_else(|| response.text()).unwrap_or_else(|| {
                     debug!("Provider returned empty response text");
                     String::new()
                 });
                 debug!("Received response from provider");
+                let usage = response.usage().map(TokenUsage::from);
+                let count = |tokens: u32| usize::try_from(tokens).unwrap_or(usize::MAX);
+                progress::emit(&usage.map_or_else(
+                    || Event::TokensStreamed {
+                        prompt_tokens: estimate_tokens(user_prompt),
+                        completion_tokens: estimate_tokens(&response_text),
+                    },
+                    |usage| Event::TokensStreamed {
+                        prompt_tokens: count(usage.prompt_tokens),
+                        completion_tokens: count(usage.completion_tokens),
+                    },
+                ));
+                if let Some(usage) = usage.filter(|u| u.cached_tokens > 0) {
+                    debug!(
+                        "{} of {} prompt tokens were served from the provider's cache",
+                        usage.cached_tokens, usage.prompt_tokens
+                    );
+                }
 
                 if std::any::type_name::<T>() == std::any::type_name::<String>() {
                     // For String type, we need to handle differently
                     #[allow(clippy::unnecessary_to_owned)]
                     let string_result: T = serde_json::from_value(serde_json::Value::String(response_text.clone()))
                         .map_err(|e| anyhow!("String conversion error: {e}"))?;
-                    Ok(string_result)
+                    Ok((string_result, usage))
                 } else {
-                    // First try direct parsing, then fall back to extraction
-                    parse_json_response::<T>(&response_text)
+                    decode_or_reprompt(provider.as_ref(), messages, response_text, usage, throttle).await
                 }
             }
             Ok(Err(e)) => {
                 debug!("Provider error: {e}");
-                Err(anyhow!("Provider error: {e}"))
+                Err(provider_error(&e))
             }
             Err(_) => {
                 debug!("Provider timed out");
-                Err(anyhow!("Provider timed out"))
+                Err(coded(ErrorCode::Timeout, "Provider timed out"))
             }
         }
     })
@@ -172,27 +452,114 @@ where
         }
         Err(e) => {
             debug!("Failed to generate message after retries: {e}");
-            Err(anyhow!("Failed to generate message: {e}"))
+            let code = ErrorCode::of(&e).unwrap_or(ErrorCode::ProviderFailed);
+            Err(coded(code, format!("Failed to generate message: {e}")))
+        }
+    }
+}
+
+/// Sends the messages, waiting out the provider's rate limits rather than failing.
+///
+/// Rate-limited attempts do not count against the retries of `chat_with_retry`;
+/// a request gives up once it has waited `MAX_RATE_LIMIT_WAIT` in total.
+async fn send_throttled(
+    provider: &(dyn LLMProvider + Send + Sync),
+    messages: &[ChatMessage],
+    use_tools: bool,
+    throttle: Option<Throttle>,
+) -> Result<Result<Box<dyn ChatResponse>, LLMError>, Elapsed> {
+    let mut waited = Duration::ZERO;
+    let mut rate_limits = 0;
+    loop {
+        if let Some(throttle) = throttle {
+            throttle.acquire().await;
         }
+        let request = if use_tools {
+            provider.chat_with_tools(messages, provider.tools())
+        } else {
+            provider.chat(messages)
+        };
+        let result = tokio::time::timeout(RESPONSE_TIMEOUT, request).await;
+        if let (Ok(Err(error)), Some(throttle)) = (&result, throttle)
+            && let Some(delay) = rate_limit::retry_delay(error, rate_limits)
+            && waited + delay <= MAX_RATE_LIMIT_WAIT
+        {
+            throttle.pause(delay);
+            waited += delay;
+            rate_limits += 1;
+            continue;
+        }
+        return result;
     }
 }
 
-/// Parse a provider's response that should be pure JSON
-fn parse_json_response<T: DeserializeOwned>(text: &str) -> Result<T> {
-    match serde_json::from_str::<T>(text) {
-        Ok(message) => Ok(message),
+/// Decodes a structured response, asking the model once to fix it if it cannot be repaired
+async fn decode_or_reprompt<T>(
+    provider: &(dyn LLMProvider + Send + Sync),
+    mut messages: Vec<ChatMessage>,
+    response_text: String,
+    usage: Option<TokenUsage>,
+    throttle: Option<Throttle>,
+) -> Result<(T, Option<TokenUsage>)>
+where
+    T: DeserializeOwned + JsonSchema,
+{
+    let error = match decode::decode::<T>(&response_text) {
+        Ok((message, recovery)) => {
+            record_recovery(recovery, None);
+            return Ok((message, usage));
+        }
+        Err(e) => e,
+    };
+
+    debug!("Response failed to decode: {error}. Re-prompting with the error.");
+    messages.push(ChatMessage::assistant().content(response_text).build());
+    messages.push(
+        ChatMessage::user()
+            .content(decode::repair_prompt::<T>(&error))
+            .build(),
+    );
+    let response = send_throttled(provider, &messages, false, throttle)
+        .await
+        .map_err(|_| coded(ErrorCode::Timeout, "Provider timed out"))?
+        .map_err(|e| provider_error(&e))?;
+    let retry_usage = response.usage().map(TokenUsage::from);
+    let usage = match (usage, retry_usage) {
+        (Some(first), Some(retry)) => Some(first + retry),
+        (first, retry) => first.or(retry),
+    };
+
+    match decode::decode::<T>(&response.text().unwrap_or_default()) {
+        Ok((message, _)) => {
+            record_recovery(Recovery::Reprompted, Some(&error));
+            Ok((message, usage))
+        }
         Err(e) => {
-            // Fallback to a more robust extraction if direct parsing fails
-            debug!("Direct JSON parse failed: {e}. Attempting fallback extraction.");
-            extract_and_parse_json(text)
+            tracing::warn!(failure_mode = "invalid", error = %e, "Structured response could not be decoded");
+            Err(coded(ErrorCode::InvalidResponse, e))
         }
     }
 }
 
-/// Extracts and parses JSON from a potentially non-JSON response
-fn extract_and_parse_json<T: DeserializeOwned>(text: &str) -> Result<T> {
-    let cleaned_json = clean_json_from_llm(text);
-    serde_json::from_str(&cleaned_json).map_err(|e| anyhow!("JSON parse error: {e}"))
+/// A provider failure, with the code its kind implies
+fn provider_error(e: &LLMError) -> anyhow::Error {
+    let code = match e {
+        LLMError::HttpError(_) => ErrorCode::ProviderUnreachable,
+        LLMError::AuthError(_) | LLMError::InvalidRequest(_) => ErrorCode::ProviderRejected,
+        LLMError::ResponseFormatError { .. } | LLMError::JsonError(_) => ErrorCode::InvalidResponse,
+        _ => ErrorCode::ProviderFailed,
+    };
+    coded(code, format!("Provider error: {e}"))
+}
+
+/// Records which failure mode a decoded response had, if any
+fn record_recovery(recovery: Recovery, error: Option<&anyhow::Error>) {
+    if recovery == Recovery::None {
+        return;
+    }
+    debug!("Recovered malformed response ({recovery})");
+    let error = error.map(ToString::to_string).unwrap_or_default();
+    tracing::warn!(failure_mode = %recovery, error, "Recovered malformed structured response");
 }
 
 pub fn get_available_provider_names() -> Vec<String> {
@@ -214,12 +581,23 @@ pub fn provider_requires_api_key(provider_type: &str) -> bool {
 
 /// Validates the provider configuration
 pub fn validate_provider_config(config: &Config, provider_name: &str) -> Result<()> {
-    let provider_config = config
-        .get_provider_config(provider_name)
-        .ok_or_else(|| anyhow!("Provider '{provider_name}' not found in configuration"))?;
-
-    if provider_config.api_key.is_empty() {
-        return Err(anyhow!("API key required for provider: {provider_name}"));
+    let provider_config = config.get_provider_config(provider_name).ok_or_else(|| {
+        coded(
+            ErrorCode::UnknownProvider,
+            format!("Provider '{provider_name}' not found in configuration"),
+        )
+    })?;
+
+    let provider = ProviderKind::from_name(provider_name);
+    if provider_config.api_key.is_empty() && provider.is_none_or(ProviderKind::requires_api_key) {
+        return Err(coded(
+            ErrorCode::MissingApiKey,
+            format!("API key required for provider: {provider_name}"),
+        ));
+    }
+    if let Some(provider) = provider {
+        endpoint::validate_endpoint(provider, &provider_config.additional_params)
+            .map_err(|e| coded(ErrorCode::InvalidConfig, e))?;
     }
 
     Ok(())
@@ -264,26 +642,33 @@ pub fn get_combined_config<S: ::std::hash::BuildHasher>(
     combined_params
 }
 
-fn clean_json_from_llm(json_str: &str) -> String {
-    // Remove potential leading/trailing whitespace and invisible characters
-    let trimmed = json_str
-        .trim_start_matches(|c: char| c.is_whitespace() || !c.is_ascii())
-        .trim_end_matches(|c: char| c.is_whitespace() || !c.is_ascii());
-
-    // If wrapped in code block, remove the markers
-    let without_codeblock = if trimmed.starts_with("```") && trimmed.ends_with("```") {
-        let start = trimmed.find('{').unwrap_or(0);
-        let end = trimmed.rfind('}').map_or(trimmed.len(), |i| i + 1);
-        &trimmed[start..end]
-    } else {
-        trimmed
-    };
-
-    // Find the first '{' and last '}' to extract the JSON object
-    let start = without_codeblock.find('{').unwrap_or(0);
-    let end = without_codeblock
-        .rfind('}')
-        .map_or(without_codeblock.len(), |i| i + 1);
-
-    without_codeblock[start..end].trim().to_string()
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wants_tool_output() {
+        let mut params = HashMap::new();
+        // The schema stays in the prompt unless tool use is asked for
+        assert!(!wants_tool_output::<serde_json::Value>(
+            ProviderKind::OpenRouter,
+            &params
+        ));
+
+        params.insert(STRUCTURED_OUTPUT_PARAM.to_string(), "tools".to_string());
+        assert!(wants_tool_output::<serde_json::Value>(
+            ProviderKind::OpenRouter,
+            &params
+        ));
+        // Plain text has no schema to call a tool with
+        assert!(!wants_tool_output::<String>(
+            ProviderKind::OpenRouter,
+            &params
+        ));
+        // Providers without forced tool calls fall back to the prompt
+        assert!(!wants_tool_output::<serde_json::Value>(
+            ProviderKind::Google,
+            &params
+        ));
+    }
 }
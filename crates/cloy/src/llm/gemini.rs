@@ -0,0 +1,471 @@
+//! Google Gemini through the `generateContent` API.
+//!
+//! The `llm` crate's Google backend sends the system prompt as a user turn
+//! and has no safety settings, so chat requests are made here. The backend is
+//! still used for embeddings.
+//!
+//! Additional params:
+//! - `safety` sets the block threshold of every harm category, and
+//!   `safety-<category>` (`harassment`, `hate-speech`, `sexually-explicit`,
+//!   `dangerous-content`, `civic-integrity`) of one. Thresholds are the API's
+//!   names, such as `BLOCK_ONLY_HIGH` or `block-none`. Diffs of security
+//!   tooling or content filters are otherwise sometimes refused.
+//! - `system-instruction` is `native` to send the system prompt as the
+//!   request's system instruction, or `inline` to send it as the first user
+//!   turn. Gemma models only accept `inline`, their default.
+//! - `json-mode` requests a JSON response for structured output; on by
+//!   default except for Gemma models.
+
+use crate::config::{ProviderConfig, parse_bool};
+use crate::http;
+use crate::llm::rate_limit;
+use async_trait::async_trait;
+use llm::ToolCall;
+use llm::chat::{
+    ChatMessage, ChatProvider, ChatResponse, ChatRole, PromptTokensDetails, Tool, Usage,
+};
+use llm::error::LLMError;
+use reqwest::Client;
+use serde::Deserialize;
+use serde_json::{Value, json};
+use std::fmt;
+
+/// Additional param with the block threshold of every harm category
+pub const SAFETY_PARAM: &str = "safety";
+
+/// Additional param choosing how the system prompt is sent: `native` or `inline`
+pub const SYSTEM_INSTRUCTION_PARAM: &str = "system-instruction";
+
+/// Additional param turning the JSON response mode on or off
+pub const JSON_MODE_PARAM: &str = "json-mode";
+
+const API_BASE: &str = "https://generativelanguage.googleapis.com/v1beta";
+
+/// Harm categories by the suffix of their `safety-<category>` param
+const HARM_CATEGORIES: &[(&str, &str)] = &[
+    ("harassment", "HARM_CATEGORY_HARASSMENT"),
+    ("hate-speech", "HARM_CATEGORY_HATE_SPEECH"),
+    ("sexually-explicit", "HARM_CATEGORY_SEXUALLY_EXPLICIT"),
+    ("dangerous-content", "HARM_CATEGORY_DANGEROUS_CONTENT"),
+    ("civic-integrity", "HARM_CATEGORY_CIVIC_INTEGRITY"),
+];
+
+const THRESHOLDS: &[&str] = &[
+    "BLOCK_NONE",
+    "BLOCK_ONLY_HIGH",
+    "BLOCK_MEDIUM_AND_ABOVE",
+    "BLOCK_LOW_AND_ABOVE",
+    "OFF",
+];
+
+/// The API name of a threshold written in either case, with dashes or underscores
+fn parse_threshold(value: &str) -> Option<&'static str> {
+    let normalized = value.trim().to_uppercase().replace('-', "_");
+    THRESHOLDS.iter().copied().find(|t| *t == normalized)
+}
+
+/// The `safetySettings` of a request; fails on unknown categories or thresholds
+fn safety_settings(config: &ProviderConfig) -> Result<Vec<Value>, LLMError> {
+    let params = &config.additional_params;
+    let threshold = |key: &str, value: &str| {
+        parse_threshold(value).ok_or_else(|| {
+            LLMError::InvalidRequest(format!(
+                "Unknown threshold {value:?} in gitai.google-additional{key}; use one of {}",
+                THRESHOLDS.join(", ")
+            ))
+        })
+    };
+
+    let default = params
+        .get(SAFETY_PARAM)
+        .map(|value| threshold(SAFETY_PARAM, value))
+        .transpose()?;
+    for key in params.keys() {
+        if let Some(category) = key.strip_prefix("safety-")
+            && !HARM_CATEGORIES.iter().any(|(name, _)| *name == category)
+        {
+            return Err(LLMError::InvalidRequest(format!(
+                "Unknown harm category in gitai.google-additional{key}"
+            )));
+        }
+    }
+
+    let mut settings = Vec::new();
+    for (name, category) in HARM_CATEGORIES {
+        let key = format!("{SAFETY_PARAM}-{name}");
+        let value = match params.get(&key) {
+            Some(value) => Some(threshold(&key, value)?),
+            None => default,
+        };
+        if let Some(value) = value {
+            settings.push(json!({ "category": category, "threshold": value }));
+        }
+    }
+    Ok(settings)
+}
+
+/// A Gemini model
+#[derive(Debug)]
+pub struct GeminiProvider {
+    client: Client,
+    api_key: String,
+    model: String,
+    system: String,
+    native_system: bool,
+    generation_config: Value,
+    safety_settings: Vec<Value>,
+}
+
+impl GeminiProvider {
+    /// Builds the provider; `json_output` asks for JSON mode unless the params turn it off
+    pub fn new(
+        config: &ProviderConfig,
+        model: String,
+        system: &str,
+        json_output: bool,
+    ) -> Result<Self, LLMError> {
+        let params = &config.additional_params;
+        // Gemma models reject both system instructions and JSON mode
+        let gemma = model.starts_with("gemma");
+        let native_system = match params.get(SYSTEM_INSTRUCTION_PARAM).map(|v| v.trim()) {
+            None => !gemma,
+            Some("native") => true,
+            Some("inline") => false,
+            Some(other) => {
+                return Err(LLMError::InvalidRequest(format!(
+                    "gitai.google-additional{SYSTEM_INSTRUCTION_PARAM} must be native or inline, got {other:?}"
+                )));
+            }
+        };
+        let json_mode = params
+            .get(JSON_MODE_PARAM)
+            .and_then(|v| parse_bool(v))
+            .unwrap_or(!gemma);
+
+        let mut generation_config = json!({
+            "maxOutputTokens": params.get("max_tokens").and_then(|v| v.parse::<u32>().ok()).unwrap_or(4096),
+        });
+        if let Some(temperature) = config.temperature() {
+            generation_config["temperature"] = json!(temperature);
+        }
+        if let Some(top_p) = config.top_p() {
+            generation_config["topP"] = json!(top_p);
+        }
+        if let Some(seed) = config.seed() {
+            generation_config["seed"] = json!(seed);
+        }
+        if json_output && json_mode {
+            generation_config["responseMimeType"] = json!("application/json");
+        }
+
+        Ok(Self {
+            client: http::client(None).map_err(|e| LLMError::InvalidRequest(format!("{e:#}")))?,
+            api_key: config.api_key.clone(),
+            model,
+            system: system.to_string(),
+            native_system,
+            generation_config,
+            safety_settings: safety_settings(config)?,
+        })
+    }
+
+    fn request_body(&self, messages: &[ChatMessage]) -> Value {
+        let mut contents = Vec::new();
+        if !self.native_system && !self.system.is_empty() {
+            contents.push(json!({ "role": "user", "parts": [{ "text": self.system }] }));
+        }
+        contents.extend(messages.iter().map(|message| {
+            let role = match message.role {
+                ChatRole::User => "user",
+                ChatRole::Assistant => "model",
+            };
+            json!({ "role": role, "parts": [{ "text": message.content }] })
+        }));
+
+        let mut body = json!({ "contents": contents, "generationConfig": self.generation_config });
+        if self.native_system && !self.system.is_empty() {
+            body["systemInstruction"] = json!({ "parts": [{ "text": self.system }] });
+        }
+        if !self.safety_settings.is_empty() {
+            body["safetySettings"] = json!(self.safety_settings);
+        }
+        body
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GenerateResponse {
+    #[serde(default)]
+    candidates: Vec<Candidate>,
+    prompt_feedback: Option<PromptFeedback>,
+    usage_metadata: Option<UsageMetadata>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Candidate {
+    content: Option<Content>,
+    finish_reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Content {
+    #[serde(default)]
+    parts: Vec<Part>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Part {
+    text: Option<String>,
+    /// Set on the reasoning parts of thinking models
+    #[serde(default)]
+    thought: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PromptFeedback {
+    block_reason: Option<String>,
+}
+
+/// Token counts of a call
+#[derive(Debug, Clone, Copy, Deserialize)]
+struct UsageMetadata {
+    #[serde(rename = "promptTokenCount", default)]
+    prompt: u32,
+    #[serde(rename = "candidatesTokenCount", default)]
+    candidates: u32,
+    #[serde(rename = "totalTokenCount", default)]
+    total: u32,
+    #[serde(rename = "cachedContentTokenCount")]
+    cached: Option<u32>,
+}
+
+/// Finish reasons that mean the answer was withheld rather than completed
+const BLOCKED_FINISH_REASONS: &[&str] = &[
+    "SAFETY",
+    "RECITATION",
+    "BLOCKLIST",
+    "PROHIBITED_CONTENT",
+    "SPII",
+];
+
+/// The answer text of a response, or why Gemini withheld it
+fn response_text(response: &GenerateResponse) -> Result<String, LLMError> {
+    let blocked = |reason: &str| {
+        LLMError::ProviderError(format!(
+            "Gemini blocked the response ({reason}); relax gitai.google-additional{SAFETY_PARAM} if the diff is safe to send"
+        ))
+    };
+    if let Some(reason) = response
+        .prompt_feedback
+        .as_ref()
+        .and_then(|f| f.block_reason.as_deref())
+    {
+        return Err(blocked(reason));
+    }
+    let candidate = response
+        .candidates
+        .first()
+        .ok_or_else(|| LLMError::ProviderError("Gemini returned no candidates".to_string()))?;
+    if let Some(reason) = candidate
+        .finish_reason
+        .as_deref()
+        .filter(|r| BLOCKED_FINISH_REASONS.contains(r))
+    {
+        return Err(blocked(reason));
+    }
+    Ok(candidate
+        .content
+        .iter()
+        .flat_map(|content| &content.parts)
+        .filter(|part| !part.thought)
+        .filter_map(|part| part.text.as_deref())
+        .collect())
+}
+
+/// The answer text and usage of a response
+#[derive(Debug)]
+struct GeminiResponse {
+    text: String,
+    usage: Option<UsageMetadata>,
+}
+
+impl fmt::Display for GeminiResponse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+impl ChatResponse for GeminiResponse {
+    fn text(&self) -> Option<String> {
+        Some(self.text.clone())
+    }
+
+    fn tool_calls(&self) -> Option<Vec<ToolCall>> {
+        None
+    }
+
+    fn usage(&self) -> Option<Usage> {
+        self.usage.map(|usage| Usage {
+            prompt_tokens: usage.prompt,
+            completion_tokens: usage.candidates,
+            total_tokens: usage.total,
+            completion_tokens_details: None,
+            prompt_tokens_details: usage.cached.map(|cached| PromptTokensDetails {
+                cached_tokens: Some(cached),
+                audio_tokens: None,
+            }),
+        })
+    }
+}
+
+#[async_trait]
+impl ChatProvider for GeminiProvider {
+    async fn chat_with_tools(
+        &self,
+        messages: &[ChatMessage],
+        _tools: Option<&[Tool]>,
+    ) -> Result<Box<dyn ChatResponse>, LLMError> {
+        if self.api_key.is_empty() {
+            return Err(LLMError::AuthError("Missing Google API key".to_string()));
+        }
+        let url = format!("{API_BASE}/models/{}:generateContent", self.model);
+        let response = self
+            .client
+            .post(&url)
+            .header("x-goog-api-key", &self.api_key)
+            .json(&self.request_body(messages))
+            .send()
+            .await
+            .map_err(|e| LLMError::HttpError(e.to_string()))?;
+        let status = response.status();
+        let retry_after = response
+            .headers()
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(rate_limit::parse_retry_after);
+        let raw = response
+            .text()
+            .await
+            .map_err(|e| LLMError::HttpError(e.to_string()))?;
+        if !status.is_success() {
+            return Err(match status.as_u16() {
+                429 => rate_limit::rate_limited_error(
+                    "Gemini",
+                    retry_after.or_else(|| rate_limit::gemini_retry_delay(&raw)),
+                    &raw,
+                ),
+                401 | 403 => LLMError::AuthError(format!("Gemini returned {status}: {raw}")),
+                _ => LLMError::ProviderError(format!("Gemini returned {status}: {raw}")),
+            });
+        }
+
+        let parsed: GenerateResponse =
+            serde_json::from_str(&raw).map_err(|e| LLMError::ResponseFormatError {
+                message: e.to_string(),
+                raw_response: raw.clone(),
+            })?;
+        Ok(Box::new(GeminiResponse {
+            text: response_text(&parsed)?,
+            usage: parsed.usage_metadata,
+        }))
+    }
+}
+
+chat_only_provider!(GeminiProvider, "Gemini");
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(params: &[(&str, &str)]) -> ProviderConfig {
+        ProviderConfig {
+            api_key: "key".to_string(),
+            model_name: String::new(),
+            additional_params: params
+                .iter()
+                .map(|(k, v)| ((*k).to_string(), (*v).to_string()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_safety_settings() {
+        let settings = safety_settings(&config(&[
+            ("safety", "block-only-high"),
+            ("safety-dangerous-content", "BLOCK_NONE"),
+        ]))
+        .expect("Valid settings");
+        assert_eq!(settings.len(), HARM_CATEGORIES.len());
+        assert!(settings.contains(&json!({
+            "category": "HARM_CATEGORY_DANGEROUS_CONTENT",
+            "threshold": "BLOCK_NONE"
+        })));
+        assert!(settings.contains(&json!({
+            "category": "HARM_CATEGORY_HARASSMENT",
+            "threshold": "BLOCK_ONLY_HIGH"
+        })));
+
+        assert!(
+            safety_settings(&config(&[]))
+                .expect("No settings")
+                .is_empty()
+        );
+        assert!(safety_settings(&config(&[("safety", "lenient")])).is_err());
+        assert!(safety_settings(&config(&[("safety-violence", "BLOCK_NONE")])).is_err());
+    }
+
+    #[test]
+    fn test_system_instruction_and_json_mode() {
+        let messages = [ChatMessage::user().content("diff").build()];
+
+        let gemini = GeminiProvider::new(&config(&[]), "gemini-2.0-flash".to_string(), "sys", true)
+            .expect("Valid config");
+        let body = gemini.request_body(&messages);
+        assert_eq!(body["systemInstruction"]["parts"][0]["text"], "sys");
+        assert_eq!(body["contents"].as_array().map(Vec::len), Some(1));
+        assert_eq!(
+            body["generationConfig"]["responseMimeType"],
+            "application/json"
+        );
+
+        let gemma = GeminiProvider::new(&config(&[]), "gemma-3-27b-it".to_string(), "sys", true)
+            .expect("Valid config");
+        let body = gemma.request_body(&messages);
+        assert!(body.get("systemInstruction").is_none());
+        assert_eq!(body["contents"][0]["parts"][0]["text"], "sys");
+        assert!(body["generationConfig"].get("responseMimeType").is_none());
+
+        assert!(
+            GeminiProvider::new(
+                &config(&[(SYSTEM_INSTRUCTION_PARAM, "first")]),
+                "gemini-2.0-flash".to_string(),
+                "sys",
+                true
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_blocked_response() {
+        let parse = |raw: &str| -> GenerateResponse {
+            serde_json::from_str(raw).expect("Valid response JSON")
+        };
+        let answered = parse(
+            r#"{"candidates": [{"content": {"parts": [
+                {"text": "thinking", "thought": true}, {"text": "{\"a\": 1}"}
+            ]}, "finishReason": "STOP"}]}"#,
+        );
+        assert_eq!(response_text(&answered).expect("Answered"), r#"{"a": 1}"#);
+
+        let blocked = parse(r#"{"promptFeedback": {"blockReason": "SAFETY"}}"#);
+        let error = response_text(&blocked).expect_err("Blocked prompt");
+        assert!(error.to_string().contains("SAFETY"), "{error}");
+
+        let withheld = parse(r#"{"candidates": [{"finishReason": "RECITATION"}]}"#);
+        assert!(response_text(&withheld).is_err());
+    }
+}
@@ -1,5 +1,72 @@
+/// Implements the `llm` provider traits besides chat for a provider gitai calls itself.
+///
+/// The engine only chats, but `LLMProvider` also covers completion,
+/// embeddings and speech; those report that the provider does not offer them.
+macro_rules! chat_only_provider {
+    ($provider:ty, $name:literal) => {
+        #[async_trait::async_trait]
+        impl llm::completion::CompletionProvider for $provider {
+            async fn complete(
+                &self,
+                _req: &llm::completion::CompletionRequest,
+            ) -> Result<llm::completion::CompletionResponse, llm::error::LLMError> {
+                Err(llm::error::LLMError::ProviderError(
+                    concat!($name, " is only used through chat").to_string(),
+                ))
+            }
+        }
+
+        #[async_trait::async_trait]
+        impl llm::embedding::EmbeddingProvider for $provider {
+            async fn embed(
+                &self,
+                _input: Vec<String>,
+            ) -> Result<Vec<Vec<f32>>, llm::error::LLMError> {
+                Err(llm::error::LLMError::ProviderError(
+                    concat!($name, " embeddings are not supported").to_string(),
+                ))
+            }
+        }
+
+        #[async_trait::async_trait]
+        impl llm::stt::SpeechToTextProvider for $provider {
+            async fn transcribe(&self, _audio: Vec<u8>) -> Result<String, llm::error::LLMError> {
+                Err(llm::error::LLMError::ProviderError(
+                    concat!($name, " speech to text is not supported").to_string(),
+                ))
+            }
+        }
+
+        #[async_trait::async_trait]
+        impl llm::tts::TextToSpeechProvider for $provider {}
+
+        #[async_trait::async_trait]
+        impl llm::models::ModelsProvider for $provider {}
+
+        impl llm::LLMProvider for $provider {}
+    };
+}
+#[cfg(feature = "test-utils")]
+pub(crate) use chat_only_provider;
+
+pub mod api_changes;
+pub mod bedrock;
+pub mod binary;
+pub mod ci_log;
+pub mod classify;
 pub mod context;
+pub mod context_files;
+pub mod decode;
+pub mod embeddings;
+pub mod endpoint;
 pub mod engine;
+pub mod gemini;
 pub mod messages;
 pub mod model_info;
+pub mod noise;
+pub mod notebook;
 pub mod provider;
+pub mod rate_limit;
+pub mod style;
+pub mod summarize;
+pub mod test_impact;
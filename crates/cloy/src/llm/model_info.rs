@@ -3,8 +3,10 @@
 //! This module provides dynamic token limit resolution by querying provider APIs
 //! to get the actual context window size for a given model, with caching and fallbacks.
 
+use crate::config::Config;
+use crate::http;
 use crate::llm::provider::ProviderKind;
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, anyhow};
 use log::{debug, warn};
 use reqwest::Client;
 use serde::Deserialize;
@@ -19,6 +21,13 @@ const CACHE_TTL_SECS: u64 = 3600;
 /// HTTP request timeout
 const REQUEST_TIMEOUT_SECS: u64 = 5;
 
+/// Additional param reserving part of the context window for the response
+pub const RESPONSE_RESERVE_PARAM: &str = "response-reserve";
+
+/// Response reserve when neither `response-reserve` nor `max_tokens` is set,
+/// matching the engine's default `max_tokens`
+const DEFAULT_RESPONSE_RESERVE: usize = 4096;
+
 /// Cached model information
 #[derive(Debug, Clone)]
 pub struct ModelInfo {
@@ -47,6 +56,9 @@ async fn fetch_info(
     match provider {
         ProviderKind::Google => fetch_google(client, model, api_key).await,
         ProviderKind::OpenRouter => fetch_openrouter(client, model, api_key).await,
+        ProviderKind::AzureOpenAI | ProviderKind::OpenAICompatible | ProviderKind::Bedrock => {
+            Err(anyhow!("{provider} does not publish model metadata"))
+        }
     }
 }
 
@@ -119,10 +131,15 @@ impl ModelInfoService {
     pub fn new() -> Self {
         Self {
             cache: RwLock::new(HashMap::new()),
-            http_client: Client::builder()
-                .timeout(Duration::from_secs(REQUEST_TIMEOUT_SECS))
-                .build()
-                .expect("Failed to create HTTP client"),
+            http_client: http::client(Some(Duration::from_secs(REQUEST_TIMEOUT_SECS)))
+                .unwrap_or_else(|e| {
+                    // The settings are reported by check_environment; fall back to a plain client
+                    debug!("Ignoring the HTTP settings for model info: {e:#}");
+                    Client::builder()
+                        .timeout(Duration::from_secs(REQUEST_TIMEOUT_SECS))
+                        .build()
+                        .expect("Failed to create HTTP client")
+                }),
         }
     }
 
@@ -153,7 +170,12 @@ impl ModelInfoService {
         }
 
         // Try to fetch from provider
-        if let Some(provider) = ProviderKind::from_name(&provider_key) {
+        #[cfg(feature = "test-utils")]
+        if crate::testing::installed().is_some() {
+            return Self::get_fallback_limit(&provider_key, model);
+        }
+        if let Some(provider) = ProviderKind::from_name(&provider_key).filter(|p| p.has_model_api())
+        {
             match fetch_info(provider, &self.http_client, model, api_key).await {
                 Ok(info) => {
                     let context_length = info.context_length;
@@ -243,6 +265,131 @@ impl ModelInfoService {
     }
 }
 
+/// Fetches a model's information from the provider, bypassing the cache and fallbacks.
+///
+/// Used to diagnose the setup: the error says whether the provider could not
+/// be reached, rejected the key or does not know the model.
+pub async fn fetch_model_info(
+    provider: ProviderKind,
+    model: &str,
+    api_key: &str,
+) -> Result<ModelInfo> {
+    let client = http::client(Some(Duration::from_secs(REQUEST_TIMEOUT_SECS)))?;
+    fetch_info(provider, &client, model, api_key).await
+}
+
+/// A model offered by a provider
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModelListing {
+    pub id: String,
+    pub context_length: Option<usize>,
+    /// Why the model is going away, when the provider says it is
+    pub deprecation: Option<String>,
+}
+
+/// Lists the models a provider offers for text generation, sorted by name
+pub async fn list_models(provider: ProviderKind, api_key: &str) -> Result<Vec<ModelListing>> {
+    let client = http::client(Some(Duration::from_secs(REQUEST_TIMEOUT_SECS)))?;
+    let mut models = match provider {
+        ProviderKind::Google => {
+            let url =
+                format!("https://generativelanguage.googleapis.com/v1beta/models?key={api_key}");
+            let response: GoogleModelsResponse = client
+                .get(&url)
+                .send()
+                .await
+                .context("Failed to send request to Google API")?
+                .error_for_status()
+                .context("Google API returned error status")?
+                .json()
+                .await
+                .context("Failed to parse Google API response")?;
+            response
+                .models
+                .into_iter()
+                .filter(|m| {
+                    m.supported_generation_methods
+                        .iter()
+                        .any(|method| method == "generateContent")
+                })
+                .map(|m| ModelListing {
+                    id: m.name.trim_start_matches("models/").to_string(),
+                    context_length: m.input_token_limit,
+                    // The API has no deprecation field; retiring models say so in their description
+                    deprecation: m
+                        .description
+                        .filter(|d| d.to_lowercase().contains("deprecated"))
+                        .map(|_| "deprecated".to_string()),
+                })
+                .collect()
+        }
+        ProviderKind::OpenRouter => {
+            let response: OpenRouterModelsResponse = client
+                .get("https://openrouter.ai/api/v1/models")
+                .header("Authorization", format!("Bearer {api_key}"))
+                .send()
+                .await
+                .context("Failed to send request to OpenRouter API")?
+                .error_for_status()
+                .context("OpenRouter API returned error status")?
+                .json()
+                .await
+                .context("Failed to parse OpenRouter API response")?;
+            response
+                .data
+                .into_iter()
+                .map(|m| ModelListing {
+                    id: m.id,
+                    context_length: Some(m.context_length),
+                    deprecation: m.expiration_date.map(|date| format!("expires {date}")),
+                })
+                .collect::<Vec<_>>()
+        }
+        ProviderKind::AzureOpenAI | ProviderKind::OpenAICompatible | ProviderKind::Bedrock => {
+            return Err(anyhow!("{provider} does not list its models"));
+        }
+    };
+    models.sort_by(|a, b| a.id.cmp(&b.id));
+    Ok(models)
+}
+
+/// Tokens left for the prompt with the configured model of a provider.
+///
+/// The context window comes from the provider's model API, or the fallbacks
+/// above when it cannot be reached, minus the tokens reserved for the response.
+pub async fn input_token_budget(config: &Config, provider_name: &str) -> usize {
+    let provider_config = config.get_provider_config(provider_name);
+    let model = provider_config
+        .map(|p| p.model_name.clone())
+        .filter(|m| !m.is_empty())
+        .or_else(|| ProviderKind::from_name(provider_name).map(|p| p.default_model().to_string()))
+        .unwrap_or_default();
+    let api_key = provider_config.map_or("", |p| p.api_key.as_str());
+
+    let context_length = ModelInfoService::global()
+        .get_context_length(provider_name, &model, api_key)
+        .await;
+    let reserve = response_reserve(provider_config.map(|p| &p.additional_params));
+    debug!(
+        "Context window for {provider_name}/{model}: {context_length} tokens, {reserve} reserved"
+    );
+    context_length.saturating_sub(reserve)
+}
+
+/// Tokens to keep free for the response
+pub fn response_reserve<S: ::std::hash::BuildHasher>(
+    params: Option<&HashMap<String, String, S>>,
+) -> usize {
+    let param = |key: &str| {
+        params
+            .and_then(|p| p.get(key))
+            .and_then(|v| v.trim().parse::<usize>().ok())
+    };
+    param(RESPONSE_RESERVE_PARAM)
+        .or_else(|| param("max_tokens"))
+        .unwrap_or(DEFAULT_RESPONSE_RESERVE)
+}
+
 impl Default for ModelInfoService {
     fn default() -> Self {
         Self::new()
@@ -262,6 +409,24 @@ struct GoogleModelResponse {
     output_token_limit: usize,
 }
 
+/// Google Gemini API response for listing models
+#[derive(Debug, Deserialize)]
+struct GoogleModelsResponse {
+    #[serde(default)]
+    models: Vec<GoogleModel>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GoogleModel {
+    name: String,
+    #[serde(default)]
+    description: Option<String>,
+    #[serde(rename = "inputTokenLimit", default)]
+    input_token_limit: Option<usize>,
+    #[serde(rename = "supportedGenerationMethods", default)]
+    supported_generation_methods: Vec<String>,
+}
+
 /// `OpenRouter` API response for listing models
 #[derive(Debug, Deserialize)]
 struct OpenRouterModelsResponse {
@@ -273,6 +438,8 @@ struct OpenRouterModel {
     id: String,
     context_length: usize,
     top_provider: Option<OpenRouterTopProvider>,
+    #[serde(default)]
+    expiration_date: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -323,6 +490,19 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_response_reserve() {
+        let mut params = HashMap::new();
+        assert_eq!(
+            response_reserve(None::<&HashMap<String, String>>),
+            DEFAULT_RESPONSE_RESERVE
+        );
+        params.insert("max_tokens".to_string(), "2048".to_string());
+        assert_eq!(response_reserve(Some(&params)), 2048);
+        params.insert(RESPONSE_RESERVE_PARAM.to_string(), "8000".to_string());
+        assert_eq!(response_reserve(Some(&params)), 8000);
+    }
+
     #[test]
     fn test_cache_key_format() {
         let provider = "google";
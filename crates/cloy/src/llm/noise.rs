@@ -0,0 +1,230 @@
+//! Removal of diff noise before prompting.
+//!
+//! Build output, minified bundles, protobuf stubs and vendored dependencies
+//! say little about why a change was made but can fill most of the prompt.
+//! Their diffs are replaced with a placeholder while the files stay listed,
+//! and hunks that only change whitespace are dropped from the remaining diffs.
+
+use crate::config::Config;
+use crate::llm::context::{CommitContext, StagedFile};
+use crate::llm::summarize::split_hunks;
+use git2::{Pathspec, PathspecFlags};
+use log::debug;
+use std::path::Path;
+
+/// Pathspecs of generated and vendored files, extended by `gitai.noise-patterns`
+pub const DEFAULT_NOISE_PATTERNS: &[&str] = &[
+    "*.min.js",
+    "*.min.css",
+    "*.map",
+    "*.pb.go",
+    "*_pb2.py",
+    "*_pb2_grpc.py",
+    "*.pb.h",
+    "*.pb.cc",
+    "*_pb.js",
+    "*_pb.d.ts",
+    "dist/*",
+    "*/dist/*",
+    "build/*",
+    "*/build/*",
+    "vendor/*",
+    "*/vendor/*",
+    "third_party/*",
+    "*/third_party/*",
+    "node_modules/*",
+    "*/node_modules/*",
+];
+
+/// Markers that generators put near the top of their output
+const GENERATED_MARKERS: &[&str] = &["@generated", "DO NOT EDIT", "Code generated by"];
+
+/// Lines of a diff searched for a generated-file marker
+const MARKER_SEARCH_LINES: usize = 20;
+
+/// An added line longer than this marks a file as minified
+const MINIFIED_LINE_LENGTH: usize = 1_000;
+
+const NOISE_PLACEHOLDER: &str = "[Generated or vendored file, diff omitted]";
+const WHITESPACE_PLACEHOLDER: &str = "[Whitespace-only changes omitted]";
+
+/// Replaces noisy diffs in the context; does nothing when `gitai.noise-filter` is off
+#[tracing::instrument(skip_all)]
+pub fn filter_noise(config: &Config, context: &mut CommitContext) {
+    if config.noise_filter == Some(false) {
+        return;
+    }
+    let patterns = DEFAULT_NOISE_PATTERNS
+        .iter()
+        .map(ToString::to_string)
+        .chain(config.noise_patterns.iter().cloned());
+    let pathspec = match Pathspec::new(patterns) {
+        Ok(pathspec) => pathspec,
+        Err(e) => {
+            debug!("Ignoring invalid noise patterns: {e}");
+            return;
+        }
+    };
+
+    for file in &mut context.staged_files {
+        if file.content_excluded {
+            continue;
+        }
+        if pathspec.matches_path(Path::new(&file.path), PathspecFlags::DEFAULT)
+            || is_generated(&file.diff)
+        {
+            debug!("Omitting the diff of generated or vendored {}", file.path);
+            file.diff = NOISE_PLACEHOLDER.to_string();
+            file.content = None;
+            continue;
+        }
+        drop_whitespace_hunks(file);
+    }
+}
+
+fn is_generated(diff: &str) -> bool {
+    let changed_lines = || diff.lines().filter(|l| is_change(l));
+    changed_lines()
+        .take(MARKER_SEARCH_LINES)
+        .any(|l| GENERATED_MARKERS.iter().any(|marker| l.contains(marker)))
+        || changed_lines().any(|l| l.starts_with('+') && l.len() > MINIFIED_LINE_LENGTH)
+}
+
+fn drop_whitespace_hunks(file: &mut StagedFile) {
+    let Some(header_end) = first_hunk_start(&file.diff) else {
+        return;
+    };
+    let (header, body) = file.diff.split_at(header_end);
+    let hunks = split_hunks(body);
+    let kept: Vec<&str> = hunks
+        .iter()
+        .copied()
+        .filter(|hunk| !is_whitespace_only(hunk))
+        .collect();
+    if kept.len() == hunks.len() {
+        return;
+    }
+
+    debug!(
+        "Dropped {} whitespace-only hunks of {}",
+        hunks.len() - kept.len(),
+        file.path
+    );
+    file.diff = if kept.is_empty() {
+        format!("{header}{WHITESPACE_PLACEHOLDER}")
+    } else {
+        format!("{header}{}", kept.concat())
+    };
+}
+
+fn first_hunk_start(diff: &str) -> Option<usize> {
+    if diff.starts_with("@@") {
+        return Some(0);
+    }
+    diff.find("\n@@").map(|i| i + 1)
+}
+
+fn is_change(line: &str) -> bool {
+    (line.starts_with('+') || line.starts_with('-'))
+        && !line.starts_with("+++")
+        && !line.starts_with("---")
+}
+
+/// Whether a hunk's removed and added lines differ only in whitespace
+fn is_whitespace_only(hunk: &str) -> bool {
+    let strip = |prefix: char| -> String {
+        hunk.lines()
+            .filter(|l| is_change(l) && l.starts_with(prefix))
+            .flat_map(|l| l[1..].chars().filter(|c| !c.is_whitespace()))
+            .collect()
+    };
+    hunk.lines().any(is_change) && strip('-') == strip('+')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::context::ChangeType;
+
+    fn staged(path: &str, diff: &str) -> StagedFile {
+        StagedFile {
+            path: path.to_string(),
+            change_type: ChangeType::Modified,
+            diff: diff.to_string(),
+            content: None,
+            content_excluded: false,
+        }
+    }
+
+    fn filtered(config: &Config, files: Vec<StagedFile>) -> Vec<StagedFile> {
+        let mut context = CommitContext::new(
+            "main".to_string(),
+            Vec::new(),
+            files,
+            String::new(),
+            String::new(),
+            Vec::new(),
+        );
+        filter_noise(config, &mut context);
+        context.staged_files
+    }
+
+    #[test]
+    fn test_generated_and_vendored_files() {
+        let mut config = Config {
+            noise_patterns: vec!["*.snap".to_string()],
+            ..Config::default()
+        };
+        let files = filtered(
+            &config,
+            vec![
+                staged("web/dist/app.js", "@@ -1 +1 @@\n-a\n+b\n"),
+                staged("api/user.pb.go", "@@ -1 +1 @@\n-a\n+b\n"),
+                staged("tests/output.snap", "@@ -1 +1 @@\n-a\n+b\n"),
+                staged(
+                    "src/schema.rs",
+                    "@@ -0,0 +1,2 @@\n+// @generated by build.rs\n+pub struct A;\n",
+                ),
+                staged("src/lib.rs", "@@ -1 +1 @@\n-a\n+b\n"),
+            ],
+        );
+        let omitted: Vec<&str> = files
+            .iter()
+            .filter(|f| f.diff == NOISE_PLACEHOLDER)
+            .map(|f| f.path.as_str())
+            .collect();
+        assert_eq!(
+            omitted,
+            vec![
+                "web/dist/app.js",
+                "api/user.pb.go",
+                "tests/output.snap",
+                "src/schema.rs"
+            ]
+        );
+
+        config.noise_filter = Some(false);
+        let files = filtered(
+            &config,
+            vec![staged("dist/app.js", "@@ -1 +1 @@\n-a\n+b\n")],
+        );
+        assert_ne!(files[0].diff, NOISE_PLACEHOLDER);
+    }
+
+    #[test]
+    fn test_whitespace_only_hunks() {
+        let header = "diff --git a/a.rs b/a.rs\n--- a/a.rs\n+++ b/a.rs\n";
+        let reindent = "@@ -1,2 +1,2 @@\n-fn a() {\n-  x\n+fn a()  {\n+    x\n";
+        let real = "@@ -9 +9 @@\n-let a = 1;\n+let a = 2;\n";
+
+        let files = filtered(
+            &Config::default(),
+            vec![
+                staged("a.rs", &format!("{header}{reindent}{real}")),
+                staged("b.rs", &format!("{header}{reindent}")),
+            ],
+        );
+        assert_eq!(files[0].diff, format!("{header}{real}"));
+        assert_eq!(files[1].diff, format!("{header}{WHITESPACE_PLACEHOLDER}"));
+    }
+}
@@ -0,0 +1,366 @@
+//! Jupyter notebooks as cell-level summaries.
+//!
+//! A notebook is stored as JSON, so its diff is mostly brackets, escaped
+//! source lines, execution counts and base64 images. The diff of a changed
+//! `.ipynb` is replaced with a summary of the cells added, removed and
+//! changed, each with the source lines it changes. Outputs are left out
+//! unless `gitai.notebook-outputs` is on, in which case cells whose text
+//! output changed list it too.
+
+use crate::config::Config;
+use crate::git;
+use crate::llm::context::{ChangeType, CommitContext, DiffSource, StagedFile};
+use git2::Repository;
+use log::debug;
+use serde_json::Value;
+use std::fmt::Write as _;
+use std::path::Path;
+
+/// Lines of a cell's output kept when outputs are shown
+const MAX_OUTPUT_LINES: usize = 5;
+
+/// A notebook cell, as far as the summary needs it
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Cell {
+    id: Option<String>,
+    kind: String,
+    source: String,
+    /// Text of the outputs, with rich outputs named by MIME type
+    output: String,
+}
+
+impl Cell {
+    /// The cell id from nbformat 4.5, or else the source
+    fn key(&self) -> &str {
+        self.id.as_deref().unwrap_or(&self.source)
+    }
+}
+
+/// A notebook field that is a string or a list of lines
+fn text(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        Value::Array(lines) => lines.iter().filter_map(Value::as_str).collect(),
+        _ => String::new(),
+    }
+}
+
+fn output_text(output: &Value) -> String {
+    if let Some(stream) = output.get("text") {
+        return text(stream);
+    }
+    if let Some(error) = output.get("ename").and_then(Value::as_str) {
+        let message = output.get("evalue").and_then(Value::as_str).unwrap_or("");
+        return format!("{error}: {message}\n");
+    }
+    let Some(data) = output.get("data").and_then(Value::as_object) else {
+        return String::new();
+    };
+    match data.get("text/plain") {
+        Some(plain) => text(plain),
+        None => data.keys().fold(String::new(), |mut names, mime| {
+            let _ = writeln!(names, "[{mime} output]");
+            names
+        }),
+    }
+}
+
+/// The cells of a notebook; `None` when the JSON is not a notebook
+fn parse_cells(json: &str) -> Option<Vec<Cell>> {
+    let notebook: Value = serde_json::from_str(json).ok()?;
+    let cells = notebook.get("cells")?.as_array()?;
+    Some(
+        cells
+            .iter()
+            .map(|cell| Cell {
+                id: cell
+                    .get("id")
+                    .and_then(Value::as_str)
+                    .map(ToString::to_string),
+                kind: cell
+                    .get("cell_type")
+                    .and_then(Value::as_str)
+                    .unwrap_or("code")
+                    .to_string(),
+                source: cell.get("source").map(text).unwrap_or_default(),
+                output: cell
+                    .get("outputs")
+                    .and_then(Value::as_array)
+                    .map(|outputs| outputs.iter().map(output_text).collect())
+                    .unwrap_or_default(),
+            })
+            .collect(),
+    )
+}
+
+/// How a cell changed between two versions of a notebook
+#[derive(Debug, PartialEq, Eq)]
+enum CellChange<'a> {
+    Added(usize, &'a Cell),
+    Removed(usize, &'a Cell),
+    Modified(usize, &'a Cell, &'a Cell),
+}
+
+/// Pairs the cells of two versions, by id or source, in notebook order
+fn align<'a>(old: &'a [Cell], new: &'a [Cell]) -> Vec<CellChange<'a>> {
+    // Longest common subsequence of cell keys
+    let mut lengths = vec![vec![0_usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lengths[i][j] = if old[i].key() == new[j].key() {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+
+    let mut changes = Vec::new();
+    let (mut removed, mut added) = (Vec::new(), Vec::new());
+    let flush = |removed: &mut Vec<usize>, added: &mut Vec<usize>, changes: &mut Vec<_>| {
+        // Cells replaced in the same place read as edits of each other
+        let paired = removed.len().min(added.len());
+        for (&i, &j) in removed.iter().zip(added.iter()) {
+            changes.push(CellChange::Modified(j, &old[i], &new[j]));
+        }
+        changes.extend(
+            removed[paired..]
+                .iter()
+                .map(|&i| CellChange::Removed(i, &old[i])),
+        );
+        changes.extend(
+            added[paired..]
+                .iter()
+                .map(|&j| CellChange::Added(j, &new[j])),
+        );
+        removed.clear();
+        added.clear();
+    };
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i].key() == new[j].key() {
+            flush(&mut removed, &mut added, &mut changes);
+            if old[i] != new[j] {
+                changes.push(CellChange::Modified(j, &old[i], &new[j]));
+            }
+            i += 1;
+            j += 1;
+        } else if j < new.len() && (i == old.len() || lengths[i][j + 1] >= lengths[i + 1][j]) {
+            added.push(j);
+            j += 1;
+        } else {
+            removed.push(i);
+            i += 1;
+        }
+    }
+    flush(&mut removed, &mut added, &mut changes);
+    changes
+}
+
+/// `old`'s lines that `new` lacks as `-` lines, then `new`'s that `old` lacks as `+` lines
+fn line_changes(summary: &mut String, old: &str, new: &str) {
+    let (old_lines, new_lines): (Vec<&str>, Vec<&str>) =
+        (old.lines().collect(), new.lines().collect());
+    for line in old_lines.iter().filter(|line| !new_lines.contains(line)) {
+        let _ = writeln!(summary, "-{line}");
+    }
+    for line in new_lines.iter().filter(|line| !old_lines.contains(line)) {
+        let _ = writeln!(summary, "+{line}");
+    }
+}
+
+fn lines_with(summary: &mut String, prefix: char, text: &str) {
+    for line in text.lines() {
+        let _ = writeln!(summary, "{prefix}{line}");
+    }
+}
+
+fn output_lines(summary: &mut String, output: &str) {
+    let lines: Vec<&str> = output.lines().collect();
+    for line in lines.iter().take(MAX_OUTPUT_LINES) {
+        let _ = writeln!(summary, "> {line}");
+    }
+    if lines.len() > MAX_OUTPUT_LINES {
+        let _ = writeln!(
+            summary,
+            "> ... {} more lines",
+            lines.len() - MAX_OUTPUT_LINES
+        );
+    }
+}
+
+/// A cell-level summary of the change from `old` to `new`
+fn summarize(old: &[Cell], new: &[Cell], outputs: bool) -> String {
+    let changes = align(old, new);
+    let count =
+        |test: fn(&CellChange<'_>) -> bool| changes.iter().filter(|change| test(change)).count();
+    let modified: Vec<&CellChange<'_>> = changes
+        .iter()
+        .filter(|change| match change {
+            CellChange::Modified(_, before, after) => {
+                before.source != after.source
+                    || before.kind != after.kind
+                    || (outputs && before.output != after.output)
+            }
+            _ => true,
+        })
+        .collect();
+
+    let mut summary = format!(
+        "[Notebook: {} cells added, {} removed, {} changed; outputs {}]\n",
+        count(|change| matches!(change, CellChange::Added(..))),
+        count(|change| matches!(change, CellChange::Removed(..))),
+        modified
+            .iter()
+            .filter(|change| matches!(change, CellChange::Modified(..)))
+            .count(),
+        if outputs { "shown" } else { "stripped" }
+    );
+    for change in modified {
+        match change {
+            CellChange::Added(at, cell) => {
+                let _ = writeln!(summary, "Cell {} ({}) added:", at + 1, cell.kind);
+                lines_with(&mut summary, '+', &cell.source);
+                if outputs {
+                    output_lines(&mut summary, &cell.output);
+                }
+            }
+            CellChange::Removed(at, cell) => {
+                let _ = writeln!(summary, "Cell {} ({}) removed:", at + 1, cell.kind);
+                lines_with(&mut summary, '-', &cell.source);
+            }
+            CellChange::Modified(at, before, after) => {
+                let output_changed = outputs && before.output != after.output;
+                let _ = writeln!(summary, "Cell {} ({}) changed:", at + 1, after.kind);
+                line_changes(&mut summary, &before.source, &after.source);
+                if output_changed {
+                    let _ = writeln!(summary, "Output now:");
+                    output_lines(&mut summary, &after.output);
+                }
+            }
+        }
+    }
+    summary
+}
+
+fn is_notebook(path: &str) -> bool {
+    Path::new(path)
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("ipynb"))
+}
+
+/// A blob's text on the old or new side of the diff
+fn read_blob(repo: &Repository, source: &DiffSource, path: &str, new: bool) -> Option<String> {
+    let content = git::blob_content(repo, source, path, new)?;
+    Some(String::from_utf8_lossy(&content).into_owned())
+}
+
+fn normalize(
+    repo: &Repository,
+    source: &DiffSource,
+    file: &mut StagedFile,
+    outputs: bool,
+) -> Option<()> {
+    let old_path = match &file.change_type {
+        ChangeType::Renamed { from, .. } => from.clone(),
+        _ => file.path.clone(),
+    };
+    let old = match file.change_type {
+        ChangeType::Added => Vec::new(),
+        _ => parse_cells(&read_blob(repo, source, &old_path, false)?)?,
+    };
+    let new = match file.change_type {
+        ChangeType::Deleted => Vec::new(),
+        _ => parse_cells(&read_blob(repo, source, &file.path, true)?)?,
+    };
+    file.diff = summarize(&old, &new, outputs);
+    file.content = None;
+    Some(())
+}
+
+/// Replaces the JSON diffs of changed notebooks with cell-level summaries
+///
+/// Both sides are read from where the context's diff came from; a diff from
+/// outside the repository keeps its raw JSON.
+#[tracing::instrument(skip_all)]
+pub fn normalize_notebooks(repo: &Repository, config: &Config, context: &mut CommitContext) {
+    let outputs = config.notebook_outputs.unwrap_or(false);
+    let source = &context.diff_source;
+    for file in &mut context.staged_files {
+        if !is_notebook(&file.path) || file.content_excluded {
+            continue;
+        }
+        if normalize(repo, source, file, outputs).is_none() {
+            debug!("Keeping the raw diff of notebook {}", file.path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OLD: &str = r##"{"cells": [
+        {"id": "a", "cell_type": "markdown", "source": ["# Churn\n"]},
+        {"id": "b", "cell_type": "code", "execution_count": 3,
+         "source": ["df = load()\n", "df.head()"],
+         "outputs": [{"output_type": "execute_result", "data": {"text/plain": ["   id\n", "0  1"]}}]},
+        {"id": "c", "cell_type": "code", "source": "plot(df)",
+         "outputs": [{"output_type": "display_data", "data": {"image/png": "iVBOR"}}]}
+    ], "nbformat": 4}"##;
+
+    const NEW: &str = r##"{"cells": [
+        {"id": "a", "cell_type": "markdown", "source": ["# Churn\n"]},
+        {"id": "b", "cell_type": "code", "execution_count": 7,
+         "source": ["df = load(cache=True)\n", "df.head()"],
+         "outputs": [{"output_type": "execute_result", "data": {"text/plain": ["   id\n", "0  2"]}}]},
+        {"id": "d", "cell_type": "code", "source": "model = fit(df)", "outputs": []}
+    ], "nbformat": 4}"##;
+
+    #[test]
+    fn test_notebook_summary() {
+        let old = parse_cells(OLD).expect("A notebook");
+        let new = parse_cells(NEW).expect("A notebook");
+        assert_eq!(
+            summarize(&old, &new, false),
+            "[Notebook: 0 cells added, 0 removed, 2 changed; outputs stripped]\n\
+             Cell 2 (code) changed:\n\
+             -df = load()\n\
+             +df = load(cache=True)\n\
+             Cell 3 (code) changed:\n\
+             -plot(df)\n\
+             +model = fit(df)\n"
+        );
+
+        let shown = summarize(&old, &new, true);
+        assert!(shown.contains("outputs shown"));
+        assert!(shown.contains("Output now:\n>    id\n> 0  2\n"));
+        assert!(!shown.contains("iVBOR"));
+
+        // Only the execution count changed
+        assert_eq!(
+            summarize(&old, &old, false),
+            "[Notebook: 0 cells added, 0 removed, 0 changed; outputs stripped]\n"
+        );
+        assert!(parse_cells("[]").is_none());
+    }
+
+    #[test]
+    fn test_align_without_ids() {
+        let cell = |source: &str| Cell {
+            id: None,
+            kind: "code".to_string(),
+            source: source.to_string(),
+            output: String::new(),
+        };
+        let old = [cell("a = 1"), cell("b = 2"), cell("c = 3")];
+        let new = [cell("a = 1"), cell("x = 0"), cell("c = 3"), cell("d = 4")];
+        assert_eq!(
+            align(&old, &new),
+            [
+                CellChange::Modified(1, &old[1], &new[1]),
+                CellChange::Added(3, &new[3]),
+            ]
+        );
+    }
+}
@@ -11,6 +11,12 @@ use std::str::FromStr;
 pub enum ProviderKind {
     Google,
     OpenRouter,
+    /// A deployment on an Azure `OpenAI` resource
+    AzureOpenAI,
+    /// Any server speaking the `OpenAI` chat completions API, such as vLLM or LM Studio
+    OpenAICompatible,
+    /// Models hosted on AWS Bedrock, called through the Converse API
+    Bedrock,
 }
 
 impl ProviderKind {
@@ -19,6 +25,9 @@ impl ProviderKind {
         match name.to_lowercase().as_str() {
             "google" => Some(Self::Google),
             "openrouter" => Some(Self::OpenRouter),
+            "azure-openai" | "azure" => Some(Self::AzureOpenAI),
+            "openai-compatible" | "openai" => Some(Self::OpenAICompatible),
+            "bedrock" | "aws-bedrock" => Some(Self::Bedrock),
             _ => None,
         }
     }
@@ -28,14 +37,22 @@ impl ProviderKind {
         match self {
             Self::Google => "google",
             Self::OpenRouter => "openrouter",
+            Self::AzureOpenAI => "azure-openai",
+            Self::OpenAICompatible => "openai-compatible",
+            Self::Bedrock => "bedrock",
         }
     }
 
     /// The backend type from the `llm` crate used to construct providers.
-    pub fn backend(self) -> LLMBackend {
+    ///
+    /// `None` for Bedrock, which the `llm` crate has no backend for; see `llm::bedrock`.
+    pub fn backend(self) -> Option<LLMBackend> {
         match self {
-            Self::Google => LLMBackend::Google,
-            Self::OpenRouter => LLMBackend::OpenRouter,
+            Self::Google => Some(LLMBackend::Google),
+            Self::OpenRouter => Some(LLMBackend::OpenRouter),
+            Self::AzureOpenAI => Some(LLMBackend::AzureOpenAI),
+            Self::OpenAICompatible => Some(LLMBackend::OpenAI),
+            Self::Bedrock => None,
         }
     }
 
@@ -44,25 +61,74 @@ impl ProviderKind {
         match self {
             Self::Google => "gemini-2.0-flash",
             Self::OpenRouter => "google/gemini-2.0-flash-001",
+            // Azure routes by deployment; the model name is only used for labels and limits
+            Self::AzureOpenAI | Self::OpenAICompatible => "gpt-4o-mini",
+            Self::Bedrock => "anthropic.claude-3-haiku-20240307-v1:0",
         }
     }
 
     /// Whether this provider requires an API key.
+    ///
+    /// Local `OpenAI`-compatible servers usually accept any key or none, and
+    /// Bedrock signs requests with the AWS credentials unless given an API key.
     pub const fn requires_api_key(self) -> bool {
-        true
+        !matches!(self, Self::OpenAICompatible | Self::Bedrock)
+    }
+
+    /// Whether the endpoint comes from the `base-url` parameter rather than a built-in URL.
+    pub const fn requires_base_url(self) -> bool {
+        matches!(self, Self::AzureOpenAI | Self::OpenAICompatible)
+    }
+
+    /// Whether the `base-url` parameter can point the provider at another endpoint.
+    pub const fn accepts_base_url(self) -> bool {
+        !matches!(self, Self::Google)
+    }
+
+    /// Whether the provider can be forced to answer through a specific tool call.
+    ///
+    /// `OpenRouter` speaks the `OpenAI` tools API for `OpenAI`, Anthropic and most
+    /// other hosted models, as do Azure deployments. The Gemini backend ignores
+    /// `tool_choice`, and self-hosted servers differ in how much of the tools API
+    /// they implement, so a call could not be relied on.
+    pub const fn supports_tool_use(self) -> bool {
+        matches!(self, Self::OpenRouter | Self::AzureOpenAI)
+    }
+
+    /// Whether the provider has an API describing its models and their context windows.
+    pub const fn has_model_api(self) -> bool {
+        matches!(self, Self::Google | Self::OpenRouter)
+    }
+
+    /// Whether gitai sends the requests itself rather than through an llm backend.
+    ///
+    /// Only these clients pick up `gitai.proxy`, `gitai.ca-bundle` and
+    /// `gitai.tls-backend`; the others refuse to run when one would be lost,
+    /// see [`crate::http::ensure_reaches`].
+    pub const fn uses_own_client(self) -> bool {
+        matches!(self, Self::Google | Self::Bedrock)
     }
 
     /// Fallback context window for model info when the provider doesn't expose an API.
     pub fn model_info_fallback_limit(self) -> usize {
         match self {
             Self::Google => 1_000_000,
-            Self::OpenRouter => 128_000,
+            Self::OpenRouter | Self::AzureOpenAI => 128_000,
+            // Self-hosted models are often served with a reduced context
+            Self::OpenAICompatible => 32_768,
+            Self::Bedrock => 200_000,
         }
     }
 
     /// All known providers.
     pub fn all() -> &'static [Self] {
-        &[Self::Google, Self::OpenRouter]
+        &[
+            Self::Google,
+            Self::OpenRouter,
+            Self::AzureOpenAI,
+            Self::OpenAICompatible,
+            Self::Bedrock,
+        ]
     }
 }
 
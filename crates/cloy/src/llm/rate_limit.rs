@@ -0,0 +1,266 @@
+//! Rate limiting of provider requests.
+//!
+//! Each provider has a token bucket filled at `requests-per-minute` (an
+//! additional param; unlimited when unset) and a pause set when it answers
+//! HTTP 429. The pause lasts as long as its `Retry-After` says, or backs off
+//! exponentially when it gives no delay. Requests wait out both instead of
+//! failing the command, and the spinner shows the wait.
+
+use crate::llm::provider::ProviderKind;
+use crate::output;
+use chrono::{DateTime, Utc};
+use llm::error::LLMError;
+use log::debug;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::sync::LazyLock;
+use std::time::{Duration, Instant};
+
+/// Additional param capping the requests sent to a provider per minute
+pub const REQUESTS_PER_MINUTE_PARAM: &str = "requests-per-minute";
+
+/// Longest a single request waits on rate limits before giving up
+pub const MAX_RATE_LIMIT_WAIT: Duration = Duration::from_mins(3);
+
+/// First delay when a provider rate limits without saying how long to wait
+const BASE_BACKOFF: Duration = Duration::from_secs(5);
+
+/// Longest delay taken from a provider's response or the backoff
+const MAX_BACKOFF: Duration = Duration::from_mins(1);
+
+/// Marker in errors whose retry delay the provider gave
+const RETRY_AFTER_MARKER: &str = "retry after ";
+
+/// One provider's request budget
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+    paused_until: Option<Instant>,
+}
+
+impl Bucket {
+    fn new(now: Instant, capacity: f64) -> Self {
+        Self {
+            tokens: capacity,
+            updated: now,
+            paused_until: None,
+        }
+    }
+
+    /// Takes a request token, or returns how long until one is available
+    fn take(&mut self, now: Instant, requests_per_minute: Option<u32>) -> Result<(), Duration> {
+        if let Some(until) = self.paused_until {
+            if until > now {
+                return Err(until - now);
+            }
+            self.paused_until = None;
+        }
+        let Some(rpm) = requests_per_minute.filter(|rpm| *rpm > 0) else {
+            return Ok(());
+        };
+        let capacity = f64::from(rpm);
+        let per_second = capacity / 60.0;
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * per_second).min(capacity);
+        self.updated = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - self.tokens) / per_second))
+        }
+    }
+}
+
+static BUCKETS: LazyLock<Mutex<HashMap<ProviderKind, Bucket>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// What a request is currently waiting for, shown by the spinners
+static STATUS: LazyLock<Mutex<Option<(ProviderKind, Instant)>>> =
+    LazyLock::new(|| Mutex::new(None));
+
+/// The requests of one provider
+#[derive(Debug, Clone, Copy)]
+pub struct Throttle {
+    pub provider: ProviderKind,
+    pub requests_per_minute: Option<u32>,
+}
+
+impl Throttle {
+    /// Waits until the provider may be sent another request
+    pub async fn acquire(self) {
+        loop {
+            let wait = {
+                let now = Instant::now();
+                let mut buckets = BUCKETS.lock();
+                let bucket = buckets.entry(self.provider).or_insert_with(|| {
+                    Bucket::new(now, f64::from(self.requests_per_minute.unwrap_or(0)))
+                });
+                bucket.take(now, self.requests_per_minute)
+            };
+            match wait {
+                Ok(()) => break,
+                Err(wait) => {
+                    set_status(self.provider, wait);
+                    tokio::time::sleep(wait).await;
+                }
+            }
+        }
+        let mut status = STATUS.lock();
+        if status.is_some_and(|(provider, _)| provider == self.provider) {
+            *status = None;
+        }
+    }
+
+    /// Holds back the provider's requests for `delay` after it rate limited one
+    pub fn pause(self, delay: Duration) {
+        let until = Instant::now() + delay;
+        {
+            let mut buckets = BUCKETS.lock();
+            let bucket = buckets
+                .entry(self.provider)
+                .or_insert_with(|| Bucket::new(Instant::now(), 0.0));
+            bucket.paused_until = Some(bucket.paused_until.map_or(until, |u| u.max(until)));
+        }
+        debug!(
+            "{} rate limited the request; pausing {delay:?}",
+            self.provider
+        );
+        set_status(self.provider, delay);
+        if output::is_plain_mode()
+            && let Some(status) = status()
+        {
+            output::print_info(&status);
+        }
+    }
+}
+
+fn set_status(provider: ProviderKind, wait: Duration) {
+    *STATUS.lock() = Some((provider, Instant::now() + wait));
+}
+
+/// A line such as "rate limited by google, retrying in 12s", while a request waits
+pub fn status() -> Option<String> {
+    let (provider, until) = (*STATUS.lock())?;
+    let remaining = until.checked_duration_since(Instant::now())?;
+    Some(format!(
+        "rate limited by {provider}, retrying in {}s",
+        remaining.as_secs() + 1
+    ))
+}
+
+/// Parses a `Retry-After` header: delay seconds or an HTTP date
+pub fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<f64>() {
+        return (seconds >= 0.0).then(|| Duration::from_secs_f64(seconds));
+    }
+    let date = DateTime::parse_from_rfc2822(value).ok()?;
+    (date.with_timezone(&Utc) - Utc::now()).to_std().ok()
+}
+
+/// The error for a rate-limited response, carrying the delay the provider asked for
+pub fn rate_limited_error(provider: &str, retry_after: Option<Duration>, body: &str) -> LLMError {
+    let delay = retry_after
+        .map(|d| format!("; {RETRY_AFTER_MARKER}{}s", d.as_secs_f64().ceil()))
+        .unwrap_or_default();
+    LLMError::ProviderError(format!(
+        "{provider} rate limited the request (HTTP 429){delay}: {body}"
+    ))
+}
+
+/// How long to wait before retrying after `error`, or `None` when it is not a rate limit.
+///
+/// `attempt` counts the rate limits this request has already hit and sets the
+/// backoff when the provider gave no delay.
+pub fn retry_delay(error: &LLMError, attempt: u32) -> Option<Duration> {
+    let message = error.to_string();
+    if let Some(seconds) = message
+        .split_once(RETRY_AFTER_MARKER)
+        .and_then(|(_, rest)| rest.split_once('s'))
+        .and_then(|(seconds, _)| seconds.parse::<f64>().ok())
+    {
+        return Some(Duration::from_secs_f64(seconds).min(MAX_BACKOFF));
+    }
+    let rate_limited = [
+        "429",
+        "Too Many Requests",
+        "RESOURCE_EXHAUSTED",
+        "ThrottlingException",
+    ]
+    .iter()
+    .any(|marker| message.contains(marker));
+    rate_limited.then(|| {
+        BASE_BACKOFF
+            .saturating_mul(2_u32.saturating_pow(attempt))
+            .min(MAX_BACKOFF)
+    })
+}
+
+/// The delay in a Gemini error body's `RetryInfo`, such as `"retryDelay": "12s"`
+pub fn gemini_retry_delay(body: &str) -> Option<Duration> {
+    let value: serde_json::Value = serde_json::from_str(body).ok()?;
+    value["error"]["details"]
+        .as_array()?
+        .iter()
+        .find_map(|detail| detail["retryDelay"].as_str())
+        .and_then(|delay| delay.strip_suffix('s'))
+        .and_then(|seconds| seconds.parse::<f64>().ok())
+        .map(Duration::from_secs_f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bucket_refills_at_the_configured_rate() {
+        let start = Instant::now();
+        let mut bucket = Bucket::new(start, 2.0);
+        assert_eq!(bucket.take(start, Some(2)), Ok(()));
+        assert_eq!(bucket.take(start, Some(2)), Ok(()));
+        let wait = bucket.take(start, Some(2)).expect_err("Bucket is empty");
+        assert_eq!(wait.as_secs(), 30);
+        assert_eq!(
+            bucket.take(start + Duration::from_secs(30), Some(2)),
+            Ok(())
+        );
+
+        assert_eq!(bucket.take(start, None), Ok(()));
+        bucket.paused_until = Some(start + Duration::from_secs(10));
+        assert_eq!(
+            bucket.take(start + Duration::from_secs(4), None),
+            Err(Duration::from_secs(6))
+        );
+        assert_eq!(bucket.take(start + Duration::from_secs(10), None), Ok(()));
+    }
+
+    #[test]
+    fn test_retry_delay() {
+        let given = rate_limited_error("google", Some(Duration::from_secs(12)), "{}");
+        assert_eq!(retry_delay(&given, 0), Some(Duration::from_secs(12)));
+
+        let backend = LLMError::HttpError(
+            "HTTP status client error (429 Too Many Requests) for url".to_string(),
+        );
+        assert_eq!(retry_delay(&backend, 0), Some(BASE_BACKOFF));
+        assert_eq!(retry_delay(&backend, 1), Some(BASE_BACKOFF * 2));
+        assert_eq!(retry_delay(&backend, 10), Some(MAX_BACKOFF));
+
+        let other = LLMError::AuthError("Missing Google API key".to_string());
+        assert_eq!(retry_delay(&other, 0), None);
+    }
+
+    #[test]
+    fn test_retry_after_values() {
+        assert_eq!(parse_retry_after("7"), Some(Duration::from_secs(7)));
+        assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"), None);
+        assert_eq!(parse_retry_after("soon"), None);
+
+        let body = r#"{"error": {"code": 429, "details": [
+            {"@type": "type.googleapis.com/google.rpc.RetryInfo", "retryDelay": "12s"}
+        ]}}"#;
+        assert_eq!(gemini_retry_delay(body), Some(Duration::from_secs(12)));
+    }
+}
@@ -0,0 +1,336 @@
+//! Per-author commit style profiles.
+//!
+//! Raw history lines are an expensive way to tell the model how an author
+//! writes. A profile condenses a large sample of the author's messages into a
+//! few numbers that fit on one prompt line, and is cached on disk per repository
+//! and author so it is only recomputed when the author commits again. A sealed
+//! profile is named by a hash of the address rather than the address itself.
+
+use crate::cache_crypt;
+use anyhow::Result;
+use log::debug;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::PathBuf;
+
+/// Number of commits scanned when building a profile
+pub const PROFILE_SAMPLE_SIZE: usize = 200;
+/// Number of conventional types reported in the summary
+const MAX_PREFERRED_TYPES: usize = 3;
+
+/// Condensed description of how an author writes commit messages
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct StyleProfile {
+    /// Number of author messages the profile was computed from
+    pub sample_size: usize,
+    /// Most used conventional commit types, most frequent first
+    pub preferred_types: Vec<(String, usize)>,
+    /// Share of subjects starting with an imperative verb
+    pub imperative_ratio: f32,
+    /// Average subject length in characters
+    pub avg_subject_length: usize,
+    /// Share of subjects containing an emoji or gitmoji shortcode
+    pub emoji_ratio: f32,
+    /// Share of conventional subjects that carry a `(scope)`
+    pub scope_ratio: f32,
+    /// Author's latest commit when the profile was computed, used to
+    /// invalidate the cache
+    #[serde(default)]
+    pub last_commit: String,
+}
+
+impl StyleProfile {
+    /// Computes a profile from commit messages.
+    #[allow(clippy::cast_precision_loss, clippy::as_conversions)]
+    pub fn from_messages(messages: &[String]) -> Self {
+        let subjects: Vec<&str> = messages
+            .iter()
+            .filter_map(|m| m.lines().next())
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .collect();
+        if subjects.is_empty() {
+            return Self::default();
+        }
+
+        let mut types: HashMap<String, usize> = HashMap::new();
+        let mut scoped = 0;
+        let mut imperative = 0;
+        let mut emoji = 0;
+        let mut total_len = 0;
+
+        for subject in &subjects {
+            total_len += subject.chars().count();
+            if let Some((kind, has_scope)) = parse_conventional_prefix(subject) {
+                *types.entry(kind).or_insert(0) += 1;
+                if has_scope {
+                    scoped += 1;
+                }
+            }
+            if first_description_word(subject).is_some_and(super::context::is_imperative_verb) {
+                imperative += 1;
+            }
+            if has_emoji(subject) {
+                emoji += 1;
+            }
+        }
+
+        let conventional: usize = types.values().sum();
+        let mut preferred_types: Vec<(String, usize)> = types.into_iter().collect();
+        preferred_types.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        preferred_types.truncate(MAX_PREFERRED_TYPES);
+
+        let count = subjects.len();
+        Self {
+            sample_size: count,
+            preferred_types,
+            imperative_ratio: imperative as f32 / count as f32,
+            avg_subject_length: total_len / count,
+            emoji_ratio: emoji as f32 / count as f32,
+            scope_ratio: if conventional == 0 {
+                0.0
+            } else {
+                scoped as f32 / conventional as f32
+            },
+            last_commit: String::new(),
+        }
+    }
+
+    /// Renders the profile as a single compact prompt line.
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        clippy::as_conversions
+    )]
+    pub fn summary(&self) -> String {
+        let pct = |ratio: f32| (ratio * 100.0).round() as u32;
+        let types = if self.preferred_types.is_empty() {
+            "none".to_string()
+        } else {
+            self.preferred_types
+                .iter()
+                .map(|(t, n)| format!("{t} ({n})"))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+
+        format!(
+            "Author style over {} commits: conventional types {types}; scoped {}%; \
+             imperative {}%; emoji {}%; average subject {} chars.",
+            self.sample_size,
+            pct(self.scope_ratio),
+            pct(self.imperative_ratio),
+            pct(self.emoji_ratio),
+            self.avg_subject_length,
+        )
+    }
+
+    /// Loads the cached profile for an author or rebuilds it from history.
+    ///
+    /// `repository` identifies the repository the profile belongs to,
+    /// `last_commit` is the oid of the author's latest commit and
+    /// `load_history` fetches the author's messages; it is only called on a
+    /// cache miss. With `encrypt` the profile is sealed. Caching is best
+    /// effort: a missing or unwritable cache directory, or a lost key, only
+    /// costs a recomputation.
+    pub fn load_or_build<F>(
+        repository: &str,
+        last_commit: &str,
+        author_email: &str,
+        encrypt: bool,
+        load_history: F,
+    ) -> Result<Option<Self>>
+    where
+        F: FnOnce(usize) -> Result<Vec<String>>,
+    {
+        if author_email.is_empty() {
+            return Ok(None);
+        }
+
+        let path = profile_path(repository, author_email, encrypt);
+        if let Some(path) = &path
+            && let Ok(raw) = cache_crypt::read(path)
+            && let Ok(profile) = serde_json::from_slice::<Self>(&raw)
+            && profile.last_commit == last_commit
+        {
+            debug!("Using cached style profile for {author_email}");
+            return Ok(Some(profile));
+        }
+
+        let messages = load_history(PROFILE_SAMPLE_SIZE)?;
+        if messages.is_empty() {
+            return Ok(None);
+        }
+
+        let mut profile = Self::from_messages(&messages);
+        profile.last_commit = last_commit.to_string();
+
+        if let Some(path) = path {
+            let written = path
+                .parent()
+                .map_or(Ok(()), fs::create_dir_all)
+                .map_err(anyhow::Error::from)
+                .and_then(|()| cache_crypt::write(&path, &serde_json::to_vec(&profile)?, encrypt));
+            if let Err(e) = written {
+                debug!("Failed to cache style profile: {e}");
+            }
+            // Only one form of the profile is kept
+            if let Some(other) = profile_path(repository, author_email, !encrypt) {
+                let _ = fs::remove_file(other);
+            }
+        }
+
+        Ok(Some(profile))
+    }
+
+    /// Deletes the cached profiles of an author in every repository; returns
+    /// whether there was one
+    pub fn purge(author_email: &str) -> Result<bool> {
+        let Some(style_dir) = style_dir() else {
+            return Ok(false);
+        };
+        let repositories = match fs::read_dir(&style_dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut removed = false;
+        for entry in repositories {
+            let dir = entry?.path();
+            if !dir.is_dir() {
+                continue;
+            }
+            for sealed in [false, true] {
+                match fs::remove_file(dir.join(profile_file_name(author_email, sealed))) {
+                    Ok(()) => removed = true,
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                    Err(e) => return Err(e.into()),
+                }
+            }
+        }
+        Ok(removed)
+    }
+}
+
+fn style_dir() -> Option<PathBuf> {
+    dirs::cache_dir().map(|dir| dir.join("gitai").join("style"))
+}
+
+fn hex_digest(input: &str) -> String {
+    Sha256::digest(input.as_bytes())
+        .iter()
+        .fold(String::new(), |mut out, byte| {
+            let _ = write!(out, "{byte:02x}");
+            out
+        })
+}
+
+/// Location of the cached profile for an author in a repository
+fn profile_path(repository: &str, author_email: &str, sealed: bool) -> Option<PathBuf> {
+    let repository = &hex_digest(repository)[..16];
+    style_dir().map(|dir| {
+        dir.join(repository)
+            .join(profile_file_name(author_email, sealed))
+    })
+}
+
+/// File name of an author's profile, sealed or in plain JSON
+fn profile_file_name(author_email: &str, sealed: bool) -> String {
+    let author_email = author_email.to_lowercase();
+    if sealed {
+        format!("{}.sealed", hex_digest(&author_email))
+    } else {
+        let name: String = author_email
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+        format!("{name}.json")
+    }
+}
+
+/// Parses `type(scope)!:` and returns the lowercase type and whether a scope is present.
+pub fn parse_conventional_prefix(subject: &str) -> Option<(String, bool)> {
+    let subject = strip_leading_emoji(subject);
+    let colon = subject.find(':')?;
+    let head = subject[..colon].trim_end_matches('!');
+    let (kind, has_scope) = match head.find('(') {
+        Some(open) if head.ends_with(')') => (&head[..open], true),
+        Some(_) => return None,
+        None => (head, false),
+    };
+    (!kind.is_empty() && kind.chars().all(|c| c.is_ascii_alphabetic()))
+        .then(|| (kind.to_lowercase(), has_scope))
+}
+
+/// Returns the first word of the description, skipping any conventional prefix
+pub(crate) fn first_description_word(subject: &str) -> Option<&str> {
+    let subject = strip_leading_emoji(subject);
+    let description = match parse_conventional_prefix(subject) {
+        Some(_) => subject.split_once(':').map_or(subject, |(_, rest)| rest),
+        None => subject,
+    };
+    description.split_whitespace().next()
+}
+
+pub(crate) fn strip_leading_emoji(subject: &str) -> &str {
+    let trimmed = subject.trim_start_matches(|c: char| is_emoji_char(c) || c.is_whitespace());
+    if let Some(rest) = trimmed.strip_prefix(':')
+        && let Some(end) = rest.find(':')
+        && !rest[..end].contains(' ')
+    {
+        return rest[end + 1..].trim_start();
+    }
+    trimmed
+}
+
+/// Pictographs plus the arrow/technical blocks and variation selector gitmoji use
+pub(crate) fn is_emoji_char(c: char) -> bool {
+    matches!(
+        u32::from(c),
+        0x1F300..=0x1FAFF | 0x2600..=0x27BF | 0x2300..=0x23FF | 0x2B00..=0x2BFF | 0xFE0F
+    )
+}
+
+pub(crate) fn has_emoji(subject: &str) -> bool {
+    subject.chars().any(is_emoji_char) || strip_leading_emoji(subject).len() != subject.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile_from_messages() {
+        let messages = vec![
+            "feat(tui): add history browser".to_string(),
+            "fix: remove crash on empty repos\n\nbody".to_string(),
+            "feat(git): add rename detection".to_string(),
+            "✨ Update docs".to_string(),
+        ];
+        let profile = StyleProfile::from_messages(&messages);
+
+        assert_eq!(profile.sample_size, 4);
+        assert_eq!(profile.preferred_types[0], ("feat".to_string(), 2));
+        assert!((profile.scope_ratio - 2.0 / 3.0).abs() < 0.01);
+        assert!((profile.imperative_ratio - 1.0).abs() < 0.01);
+        assert!((profile.emoji_ratio - 0.25).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_parse_conventional_prefix() {
+        assert_eq!(
+            parse_conventional_prefix("feat(api)!: drop v1"),
+            Some(("feat".to_string(), true))
+        );
+        assert_eq!(
+            parse_conventional_prefix(":bug: fix: crash"),
+            Some(("fix".to_string(), false))
+        );
+        assert_eq!(parse_conventional_prefix("Update README"), None);
+        assert_eq!(parse_conventional_prefix("see http://x"), None);
+    }
+}
@@ -0,0 +1,366 @@
+//! Two-stage summarization of oversized diffs.
+//!
+//! A single generated file or sweeping refactor can take most of the prompt
+//! and still get cut mid-hunk. Diffs above a threshold are split at hunk
+//! boundaries, each chunk is summarized separately (map), and the chunk
+//! summaries are merged into one (reduce). The prompt then carries the summary
+//! and a few representative hunks instead of the raw diff.
+//!
+//! Summaries are kept in `.git/gitai/summaries.json` keyed by a hash of the
+//! diff, so regenerating a message for the same staged changes costs no
+//! further requests. With `gitai.encrypt-cache` on, the file is sealed; see
+//! [`crate::cache_crypt`].
+
+use crate::cache_crypt;
+use crate::config::Config;
+use crate::llm::context::{CommitContext, StagedFile};
+use crate::llm::engine;
+use anyhow::Result;
+use chrono::Utc;
+use futures::{StreamExt, TryStreamExt, stream};
+use git2::Repository;
+use log::debug;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::PathBuf;
+
+/// Diffs longer than this many bytes are summarized
+const LARGE_DIFF_THRESHOLD: usize = 16_000;
+
+/// Bytes of hunks summarized in one request
+const CHUNK_SIZE: usize = 24_000;
+
+/// Hunks kept verbatim next to the summary
+const REPRESENTATIVE_HUNKS: usize = 3;
+
+/// Bytes kept of each representative hunk
+const MAX_HUNK_LENGTH: usize = 1_500;
+
+/// Chunk summaries requested at once
+const MAX_CONCURRENT_CHUNKS: usize = 4;
+
+/// Bumped when the prompts change, so older summaries are recomputed
+const CACHE_VERSION: u32 = 1;
+
+/// Summaries kept per repository; the longest unused are dropped first
+const MAX_CACHED_SUMMARIES: usize = 500;
+
+const CHUNK_SYSTEM_PROMPT: &str = "You summarize part of a diff of one file for someone writing \
+    the commit message. List the changes in behavior, API and structure as short bullet points. \
+    Skip formatting-only changes. Do not speculate about intent.";
+
+const REDUCE_SYSTEM_PROMPT: &str = "You merge summaries of consecutive parts of one file's diff \
+    into a single list of short bullet points. Remove duplicates and keep the most significant \
+    changes first.";
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct CachedSummary {
+    summary: String,
+    /// When a run last read or wrote the entry
+    used_at: i64,
+}
+
+/// Summaries of large diffs already sent to the model, by diff hash
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct SummaryCache {
+    version: u32,
+    entries: HashMap<String, CachedSummary>,
+    #[serde(skip)]
+    path: Option<PathBuf>,
+    #[serde(skip)]
+    encrypt: bool,
+}
+
+impl SummaryCache {
+    /// The repository's cache, or an empty one when it is missing, unreadable
+    /// or from another version
+    pub fn load(repo: &Repository) -> Self {
+        let path = repo.path().join("gitai").join("summaries.json");
+        let cache = cache_crypt::read(&path)
+            .ok()
+            .and_then(|raw| serde_json::from_slice::<Self>(&raw).ok())
+            .filter(|cache| cache.version == CACHE_VERSION)
+            .unwrap_or_default();
+        Self {
+            path: Some(path),
+            encrypt: cache_crypt::enabled(Some(repo)),
+            ..cache
+        }
+    }
+
+    fn get(&mut self, key: &str) -> Option<String> {
+        let entry = self.entries.get_mut(key)?;
+        entry.used_at = Utc::now().timestamp();
+        Some(entry.summary.clone())
+    }
+
+    fn insert(&mut self, key: String, summary: &str) {
+        self.entries.insert(
+            key,
+            CachedSummary {
+                summary: summary.to_string(),
+                used_at: Utc::now().timestamp(),
+            },
+        );
+    }
+
+    /// Writes the cache, keeping the `MAX_CACHED_SUMMARIES` most recently used;
+    /// a cache not loaded from a repository is dropped
+    pub fn save(mut self) -> Result<()> {
+        let Some(path) = self.path.take() else {
+            return Ok(());
+        };
+        if self.entries.len() > MAX_CACHED_SUMMARIES {
+            let mut used: Vec<i64> = self.entries.values().map(|entry| entry.used_at).collect();
+            used.sort_unstable_by(|a, b| b.cmp(a));
+            let cutoff = used[MAX_CACHED_SUMMARIES - 1];
+            self.entries.retain(|_, entry| entry.used_at >= cutoff);
+        }
+        self.version = CACHE_VERSION;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        cache_crypt::write(&path, &serde_json::to_vec(&self)?, self.encrypt)
+    }
+}
+
+/// Cache key of a file's diff
+fn diff_key(path: &str, diff: &str) -> String {
+    let digest = Sha256::new()
+        .chain_update(path.as_bytes())
+        .chain_update([0])
+        .chain_update(diff.as_bytes())
+        .finalize();
+    digest.iter().fold(String::new(), |mut out, byte| {
+        let _ = write!(out, "{byte:02x}");
+        out
+    })
+}
+
+/// Replaces every oversized diff in the context with a summary and representative hunks.
+///
+/// Summaries are looked up in `cache` before any request is sent, and new
+/// ones are added to it. A failed summary leaves that diff as it was; later
+/// trimming still applies. `gitai.summarize-diffs false` turns this off.
+#[tracing::instrument(skip_all)]
+pub async fn summarize_large_diffs(
+    config: &Config,
+    provider_name: &str,
+    cache: &mut SummaryCache,
+    context: &mut CommitContext,
+) {
+    if config.summarize_diffs == Some(false) {
+        return;
+    }
+    let large: Vec<&mut StagedFile> = context
+        .staged_files
+        .iter_mut()
+        .filter(|f| f.diff.len() > LARGE_DIFF_THRESHOLD)
+        .collect();
+    if large.is_empty() {
+        return;
+    }
+
+    for file in large {
+        let key = diff_key(&file.path, &file.diff);
+        if let Some(summary) = cache.get(&key) {
+            debug!("Using the cached summary of {}", file.path);
+            file.diff = condensed_diff(&summary, &file.diff);
+            continue;
+        }
+        match summarize_diff(config, provider_name, &file.path, &file.diff).await {
+            Ok(summary) => {
+                cache.insert(key, &summary);
+                debug!(
+                    "Summarized {} ({} bytes of diff)",
+                    file.path,
+                    file.diff.len()
+                );
+                file.diff = condensed_diff(&summary, &file.diff);
+            }
+            Err(e) => debug!("Failed to summarize the diff of {}: {e}", file.path),
+        }
+    }
+}
+
+async fn summarize_diff(
+    config: &Config,
+    provider_name: &str,
+    path: &str,
+    diff: &str,
+) -> Result<String> {
+    let chunks = chunk_hunks(&split_hunks(diff), CHUNK_SIZE);
+    let count = chunks.len();
+    // Collected first so the stream holds futures, not a borrowing closure
+    let requests: Vec<_> = chunks
+        .iter()
+        .enumerate()
+        .map(|(i, chunk)| {
+            let user_prompt = format!(
+                "File: {path}\nPart {} of {count} of the diff:\n\n{chunk}",
+                i + 1
+            );
+            async move {
+                engine::get_message::<String>(
+                    config,
+                    provider_name,
+                    CHUNK_SYSTEM_PROMPT,
+                    &user_prompt,
+                )
+                .await
+                .map(|summary| (i, summary))
+            }
+        })
+        .collect();
+    let mut summaries: Vec<(usize, String)> = stream::iter(requests)
+        .buffer_unordered(MAX_CONCURRENT_CHUNKS)
+        .try_collect()
+        .await?;
+    summaries.sort_by_key(|(i, _)| *i);
+    let summaries: Vec<String> = summaries.into_iter().map(|(_, s)| s).collect();
+
+    if let [summary] = summaries.as_slice() {
+        return Ok(summary.trim().to_string());
+    }
+    let user_prompt = format!(
+        "File: {path}\n\n{}",
+        summaries
+            .iter()
+            .enumerate()
+            .map(|(i, s)| format!("Part {}:\n{}", i + 1, s.trim()))
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    );
+    let merged =
+        engine::get_message::<String>(config, provider_name, REDUCE_SYSTEM_PROMPT, &user_prompt)
+            .await?;
+    Ok(merged.trim().to_string())
+}
+
+/// Splits a diff at hunk headers; anything before the first hunk stays with it
+pub(crate) fn split_hunks(diff: &str) -> Vec<&str> {
+    let mut starts: Vec<usize> = diff.match_indices("\n@@").map(|(i, _)| i + 1).collect();
+    match starts.first_mut() {
+        Some(_) if diff.starts_with("@@") => starts.insert(0, 0),
+        Some(first) => *first = 0,
+        None => starts.push(0),
+    }
+    starts
+        .iter()
+        .zip(starts.iter().skip(1).chain(std::iter::once(&diff.len())))
+        .map(|(start, end)| &diff[*start..*end])
+        .filter(|hunk| !hunk.trim().is_empty())
+        .collect()
+}
+
+/// Groups consecutive hunks into chunks of about `size` bytes
+fn chunk_hunks(hunks: &[&str], size: usize) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+    for hunk in hunks {
+        if !current.is_empty() && current.len() + hunk.len() > size {
+            chunks.push(std::mem::take(&mut current));
+        }
+        current.push_str(hunk);
+    }
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+    chunks
+}
+
+/// The hunks with the most changed lines, in their original order
+fn representative_hunks<'a>(hunks: &[&'a str], count: usize) -> Vec<&'a str> {
+    let changed = |hunk: &str| {
+        hunk.lines()
+            .filter(|l| {
+                (l.starts_with('+') || l.starts_with('-'))
+                    && !l.starts_with("+++")
+                    && !l.starts_with("---")
+            })
+            .count()
+    };
+    let mut ranked: Vec<usize> = (0..hunks.len()).collect();
+    ranked.sort_by_key(|i| std::cmp::Reverse(changed(hunks[*i])));
+    ranked.truncate(count);
+    ranked.sort_unstable();
+    ranked.into_iter().map(|i| hunks[i]).collect()
+}
+
+fn condensed_diff(summary: &str, diff: &str) -> String {
+    let hunks = split_hunks(diff);
+    let examples = representative_hunks(&hunks, REPRESENTATIVE_HUNKS)
+        .into_iter()
+        .map(|hunk| {
+            if hunk.len() <= MAX_HUNK_LENGTH {
+                return hunk.trim_end().to_string();
+            }
+            let mut end = MAX_HUNK_LENGTH;
+            while !hunk.is_char_boundary(end) {
+                end -= 1;
+            }
+            let end = hunk[..end].rfind('\n').unwrap_or(end);
+            format!("{}\n[... hunk continues ...]", &hunk[..end])
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    format!(
+        "[Large diff of {} hunks summarized]\n{summary}\n\nRepresentative hunks:\n{examples}",
+        hunks.len()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DIFF: &str = "diff --git a/lib.rs b/lib.rs\n\
+        @@ -1,2 +1,2 @@\n-a\n+b\n\
+        @@ -10,3 +10,5 @@\n+c\n+d\n+e\n-f\n\
+        @@ -40 +42 @@\n ctx\n+g\n";
+
+    #[test]
+    fn test_split_and_chunk_hunks() {
+        let hunks = split_hunks(DIFF);
+        assert_eq!(hunks.len(), 3);
+        assert!(hunks[0].starts_with("diff --git"));
+        assert!(hunks[1].starts_with("@@ -10,3"));
+        assert_eq!(hunks.concat(), DIFF);
+        let body = &DIFF[DIFF.find("@@").unwrap_or(0)..];
+        assert_eq!(split_hunks(body).len(), 3);
+
+        let chunks = chunk_hunks(&hunks, 60);
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks.concat(), DIFF);
+    }
+
+    #[test]
+    fn test_condensed_diff_keeps_busiest_hunks_in_order() {
+        let hunks = split_hunks(DIFF);
+        let picked = representative_hunks(&hunks, 2);
+        assert_eq!(picked, vec![hunks[0], hunks[1]]);
+
+        let condensed = condensed_diff("- renamed a to b", DIFF);
+        assert!(condensed.starts_with("[Large diff of 3 hunks summarized]\n- renamed a to b"));
+        assert!(condensed.contains("@@ -10,3 +10,5 @@"));
+    }
+
+    #[test]
+    fn test_summary_cache_round_trip() {
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let repo = Repository::init(dir.path()).expect("Failed to init repo");
+        let key = diff_key("lib.rs", DIFF);
+        assert_ne!(key, diff_key("main.rs", DIFF));
+
+        let mut cache = SummaryCache::load(&repo);
+        assert!(cache.get(&key).is_none());
+        cache.insert(key.clone(), "- renamed a to b");
+        cache.save().expect("Failed to save");
+
+        let mut cache = SummaryCache::load(&repo);
+        assert_eq!(cache.get(&key).as_deref(), Some("- renamed a to b"));
+    }
+}
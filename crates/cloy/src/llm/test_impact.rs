@@ -0,0 +1,267 @@
+//! Source changes that come without test changes.
+//!
+//! Each changed source file is matched to test files by name: `parser.rs`,
+//! `parser_test.go`, `test_parser.py`, `parser.spec.ts` and `ParserTest.java`
+//! all belong to `parser`. A source file counts as tested when one of its
+//! test files changed too, or, for languages with tests next to the code,
+//! when its own diff touches test code. The others are testing gaps; the
+//! test files they have but did not change are listed so the model can say
+//! which ones to look at.
+
+use crate::llm::context::{ChangeType, CommitContext, StagedFile};
+use git2::Repository;
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt;
+use std::path::Path;
+
+/// Extensions of source files expected to have tests
+const SOURCE_EXTENSIONS: &[&str] = &[
+    "rs", "go", "py", "ts", "tsx", "js", "jsx", "mjs", "java", "kt", "rb", "c", "cc", "cpp", "cs",
+    "swift", "php",
+];
+
+/// Directories that hold tests, examples and benchmarks rather than code under test
+const TEST_DIRECTORIES: &[&str] = &["tests", "test", "spec", "__tests__", "examples", "benches"];
+
+/// Test files kept per gap
+const MAX_TESTS_PER_GAP: usize = 3;
+
+/// A changed source file whose tests did not change
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct TestGap {
+    pub path: String,
+    /// Existing test files for it, none of which changed
+    pub tests: Vec<String>,
+}
+
+impl fmt::Display for TestGap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.tests.is_empty() {
+            write!(f, "{}: changed, no test file found", self.path)
+        } else {
+            write!(
+                f,
+                "{}: changed, tests not updated ({})",
+                self.path,
+                self.tests.join(", ")
+            )
+        }
+    }
+}
+
+fn extension(path: &str) -> &str {
+    Path::new(path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or_default()
+}
+
+/// File names that stand for their directory
+const DIRECTORY_STEMS: &[&str] = &["mod", "index", "__init__", "tests", "test"];
+
+/// The file name without its extension and test markers, lowercased.
+///
+/// `mod.rs`, `index.ts` and a `tests.rs` module go by their directory.
+fn subject(path: &str) -> String {
+    let path = Path::new(path);
+    let name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or_default();
+    let stem = name.split('.').next().unwrap_or_default();
+    if DIRECTORY_STEMS.contains(&stem) {
+        return path
+            .parent()
+            .and_then(|dir| dir.file_name())
+            .and_then(|dir| dir.to_str())
+            .unwrap_or_default()
+            .to_lowercase();
+    }
+    // `ParserTest` but not `contest`
+    let stem = stem
+        .strip_suffix("Tests")
+        .or_else(|| stem.strip_suffix("Test"))
+        .unwrap_or(stem)
+        .to_lowercase();
+    let stem = stem
+        .strip_prefix("test_")
+        .or_else(|| stem.strip_suffix("_test"))
+        .or_else(|| stem.strip_suffix("_spec"))
+        .unwrap_or(&stem);
+    stem.to_string()
+}
+
+/// Whether `path` is a test file by directory or by name
+pub fn is_test_path(path: &str) -> bool {
+    let lower = path.to_lowercase();
+    let in_test_dir = lower
+        .split('/')
+        .rev()
+        .skip(1)
+        .any(|dir| TEST_DIRECTORIES.contains(&dir));
+    let name = path.rsplit('/').next().unwrap_or_default();
+    let stem = name.split('.').next().unwrap_or_default();
+    let lower_name = name.to_lowercase();
+    in_test_dir
+        || lower_name.contains(".test.")
+        || lower_name.contains(".spec.")
+        || stem.starts_with("test_")
+        || stem.ends_with("_test")
+        || stem.ends_with("_spec")
+        || stem.ends_with("Test")
+        || stem.ends_with("Tests")
+        || stem == "tests"
+}
+
+/// Whether a diff changes test code inside the file itself, as Rust modules do
+fn touches_inline_tests(file: &StagedFile) -> bool {
+    extension(&file.path) == "rs"
+        && file.diff.lines().any(|line| {
+            (line.starts_with('+') || line.starts_with('-'))
+                && ["#[test]", "#[tokio::test]", "#[cfg(test)]", "assert"]
+                    .iter()
+                    .any(|marker| line.contains(marker))
+        })
+}
+
+/// Changed source files without test changes; `known_tests` are the repository's test files
+pub fn find_gaps(files: &[StagedFile], known_tests: &[String]) -> Vec<TestGap> {
+    let changed_tests: HashSet<String> = files
+        .iter()
+        .filter(|file| is_test_path(&file.path))
+        .map(|file| subject(&file.path))
+        .collect();
+
+    files
+        .iter()
+        .filter(|file| {
+            !matches!(file.change_type, ChangeType::Deleted)
+                && SOURCE_EXTENSIONS.contains(&extension(&file.path))
+                && !is_test_path(&file.path)
+                && !touches_inline_tests(file)
+        })
+        .filter_map(|file| {
+            let subject = subject(&file.path);
+            if subject.is_empty() || changed_tests.contains(&subject) {
+                return None;
+            }
+            let tests = known_tests
+                .iter()
+                .filter(|test| self::subject(test) == subject)
+                .take(MAX_TESTS_PER_GAP)
+                .cloned()
+                .collect();
+            Some(TestGap {
+                path: file.path.clone(),
+                tests,
+            })
+        })
+        .collect()
+}
+
+/// Test files in the repository's index
+fn indexed_tests(repo: &Repository) -> Vec<String> {
+    let Ok(index) = repo.index() else {
+        return Vec::new();
+    };
+    index
+        .iter()
+        .filter_map(|entry| String::from_utf8(entry.path).ok())
+        .filter(|path| is_test_path(path))
+        .collect()
+}
+
+/// Records the context's testing gaps
+#[tracing::instrument(skip_all)]
+pub fn attach_test_gaps(repo: &Repository, context: &mut CommitContext) {
+    context.test_gaps = find_gaps(&context.staged_files, &indexed_tests(repo));
+    if !context.test_gaps.is_empty() {
+        debug!("Found {} testing gaps", context.test_gaps.len());
+    }
+}
+
+/// Testing notes naming the gaps, for a PR description the model left without any
+pub fn testing_notes(gaps: &[TestGap]) -> Option<String> {
+    if gaps.is_empty() {
+        return None;
+    }
+    let lines: Vec<String> = gaps.iter().map(|gap| format!("- {gap}")).collect();
+    Some(format!("Testing gaps:\n{}", lines.join("\n")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn changed(path: &str, diff: &str) -> StagedFile {
+        StagedFile {
+            path: path.to_string(),
+            change_type: ChangeType::Modified,
+            diff: diff.to_string(),
+            content: None,
+            content_excluded: false,
+        }
+    }
+
+    #[test]
+    fn test_test_files_are_matched_by_name() {
+        assert!(is_test_path("tests/parser.rs"));
+        assert!(is_test_path("pkg/parser_test.go"));
+        assert!(is_test_path("app/test_views.py"));
+        assert!(is_test_path("src/render.spec.ts"));
+        assert!(is_test_path("src/test/java/a/ParserTest.java"));
+        assert!(!is_test_path("src/parser.rs"));
+        assert!(!is_test_path("src/contest.rs"));
+        assert!(is_test_path("src/parser/tests.rs"));
+
+        for test in [
+            "tests/parser.rs",
+            "pkg/parser_test.go",
+            "test_parser.py",
+            "src/parser.spec.ts",
+            "ParserTest.java",
+            "src/parser/tests.rs",
+            "src/parser/mod.rs",
+        ] {
+            assert_eq!(subject(test), "parser", "{test}");
+        }
+    }
+
+    #[test]
+    fn test_gaps() {
+        let files = [
+            changed("src/parser.rs", "+let x = 1;"),
+            changed("src/lexer.rs", "+    #[test]\n+    fn test_lex() {}"),
+            changed("src/render.ts", "+render();"),
+            changed("src/render.test.ts", "+it('renders');"),
+            changed("pkg/server.go", "+serve()"),
+            changed("README.md", "+docs"),
+        ];
+        let known = [
+            "tests/parser.rs".to_string(),
+            "pkg/client_test.go".to_string(),
+        ];
+        let gaps = find_gaps(&files, &known);
+        assert_eq!(
+            gaps,
+            vec![
+                TestGap {
+                    path: "src/parser.rs".to_string(),
+                    tests: vec!["tests/parser.rs".to_string()],
+                },
+                TestGap {
+                    path: "pkg/server.go".to_string(),
+                    tests: vec![],
+                },
+            ]
+        );
+        assert_eq!(
+            testing_notes(&gaps).as_deref(),
+            Some(
+                "Testing gaps:\n- src/parser.rs: changed, tests not updated (tests/parser.rs)\n- pkg/server.go: changed, no test file found"
+            )
+        );
+    }
+}
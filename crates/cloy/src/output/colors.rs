@@ -1,4 +1,4 @@
-//! Color constants and quiet mode management for CLI output.
+//! Color constants plus quiet, no-color and plain mode management for CLI output.
 
 use parking_lot::Mutex;
 use ratatui::style::Color;
@@ -29,3 +29,42 @@ pub fn set_quiet_mode(enabled: bool) {
 pub fn is_quiet_mode() -> bool {
     *QUIET_MODE.lock()
 }
+
+/// Track whether colored output is allowed
+static COLOR_ENABLED: std::sync::LazyLock<Mutex<bool>> =
+    std::sync::LazyLock::new(|| Mutex::new(true));
+
+/// Track plain (screen-reader friendly) mode state
+static PLAIN_MODE: std::sync::LazyLock<Mutex<bool>> =
+    std::sync::LazyLock::new(|| Mutex::new(false));
+
+/// Whether `NO_COLOR` is set to a non-empty value (<https://no-color.org>)
+pub fn no_color_requested() -> bool {
+    std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty())
+}
+
+/// Enable or disable colored output for the CLI and the TUI
+pub fn set_color_enabled(enabled: bool) {
+    *COLOR_ENABLED.lock() = enabled;
+    if enabled {
+        colored::control::unset_override();
+    } else {
+        colored::control::set_override(false);
+    }
+}
+
+/// Check if colored output is allowed, honoring `NO_COLOR`
+pub fn is_color_enabled() -> bool {
+    *COLOR_ENABLED.lock() && !no_color_requested()
+}
+
+/// Enable or disable plain mode: no animations, no full-screen TUI
+#[inline]
+pub fn set_plain_mode(enabled: bool) {
+    *PLAIN_MODE.lock() = enabled;
+}
+
+/// Check if plain mode is enabled, or the terminal cannot do more (`TERM=dumb`)
+pub fn is_plain_mode() -> bool {
+    *PLAIN_MODE.lock() || std::env::var("TERM").is_ok_and(|term| term == "dumb")
+}
@@ -1,6 +1,6 @@
 //! CLI output utilities.
 //!
-//! - Color constants and quiet mode (`colors`)
+//! - Color constants, quiet, no-color and plain modes (`colors`)
 //! - Text formatting, gradients, terminal output (`formatting`)
 
 mod colors;
@@ -11,7 +11,8 @@ pub use crate::tui::spinner::SpinnerState;
 
 pub use colors::{
     AURORA_GREEN, BLACK_HOLE, CELESTIAL_BLUE, COMET_ORANGE, GALAXY_PINK, METEOR_RED, NEBULA_PURPLE,
-    PLASMA_CYAN, SOLAR_YELLOW, STARLIGHT, is_quiet_mode, set_quiet_mode,
+    PLASMA_CYAN, SOLAR_YELLOW, STARLIGHT, is_color_enabled, is_plain_mode, is_quiet_mode,
+    no_color_requested, set_color_enabled, set_plain_mode, set_quiet_mode,
 };
 
 pub use formatting::{
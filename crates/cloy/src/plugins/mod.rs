@@ -0,0 +1,229 @@
+//! External commands run at fixed points of message generation.
+//!
+//! `gitai.hook-context-collected`, `gitai.hook-message-generated` and
+//! `gitai.hook-pre-commit` each name shell commands, one per value, run in
+//! order. A command reads a JSON document on stdin and prints it back,
+//! changed or not; printing nothing leaves it as it was. A command that exits
+//! non-zero stops the run with what it wrote to stderr, which is how a policy
+//! rejects a message or a commit.
+//!
+//! With the `wasm-plugins` feature, WebAssembly modules declared in
+//! `[gitai-plugin "<name>"]` sections also run in-process as file analyzers
+//! and message post-processors, in a sandbox without access to the system;
+//! see `wasm`.
+//!
+//! Both come from git config only, never from a project's `.gitai`, so
+//! cloning a repository cannot make gitai run its code.
+
+#[cfg(feature = "wasm-plugins")]
+pub mod wasm;
+
+use anyhow::{Context, Result, anyhow};
+use log::debug;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::Once;
+
+static WASM_PLUGINS: Once = Once::new();
+
+/// A point of a run where plugin commands are called
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookPoint {
+    /// The commit context is complete and about to become the prompt
+    ContextCollected,
+    /// A message came back from the model
+    MessageGenerated,
+    /// A commit is about to be created with the final message
+    PreCommit,
+}
+
+impl HookPoint {
+    /// Name of the point in config keys and in `GITAI_HOOK`
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::ContextCollected => "context-collected",
+            Self::MessageGenerated => "message-generated",
+            Self::PreCommit => "pre-commit",
+        }
+    }
+}
+
+/// What `gitai.hook-pre-commit` commands receive
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct PreCommit {
+    /// Complete commit message, trailers included
+    pub message: String,
+    pub amend: bool,
+}
+
+/// Plugin commands by hook point, and the declared WASM plugins
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PluginSettings {
+    pub context_collected: Vec<String>,
+    pub message_generated: Vec<String>,
+    pub pre_commit: Vec<String>,
+    pub wasm: Vec<WasmPluginSpec>,
+}
+
+impl PluginSettings {
+    fn commands(&self, point: HookPoint) -> &[String] {
+        match point {
+            HookPoint::ContextCollected => &self.context_collected,
+            HookPoint::MessageGenerated => &self.message_generated,
+            HookPoint::PreCommit => &self.pre_commit,
+        }
+    }
+
+    /// Passes `value` through the commands of `point`, in `dir`
+    pub fn run<T>(&self, point: HookPoint, mut value: T, dir: &Path) -> Result<T>
+    where
+        T: Serialize + DeserializeOwned,
+    {
+        for command in self.commands(point) {
+            if let Some(changed) = run_command(command, point, &value, dir)? {
+                value = changed;
+            }
+        }
+        Ok(value)
+    }
+}
+
+/// A WebAssembly plugin declared with `[gitai-plugin "<name>"]`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WasmPluginSpec {
+    pub name: String,
+    /// Module file; `<name>.wasm` in [`plugin_dir`] unless `path` is set
+    pub path: PathBuf,
+    /// Gitignore-style patterns of the files it analyzes, from `analyze`
+    pub analyze: Vec<String>,
+    /// Whether it post-processes generated messages, from `post-process`
+    pub post_process: bool,
+}
+
+/// Directory WASM plugins are looked up in: `<config dir>/gitai/plugins`
+pub fn plugin_dir() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("gitai").join("plugins"))
+}
+
+/// Loads the declared WASM plugins and registers them as analyzers and
+/// post-processors, once per process. A plugin that fails to load is
+/// skipped with a warning.
+pub fn register_wasm_plugins(specs: &[WasmPluginSpec]) {
+    if specs.is_empty() {
+        return;
+    }
+    WASM_PLUGINS.call_once(|| {
+        #[cfg(feature = "wasm-plugins")]
+        for spec in specs {
+            if let Err(e) = wasm::register(spec) {
+                log::warn!("Skipping WASM plugin {}: {e:#}", spec.name);
+            }
+        }
+        #[cfg(not(feature = "wasm-plugins"))]
+        log::warn!(
+            "Ignoring {} WASM plugins; this build of gitai has no `wasm-plugins` feature",
+            specs.len()
+        );
+    });
+}
+
+/// Runs one plugin command with `value` as JSON on stdin and reads the
+/// document it prints back; `None` when it printed nothing
+fn run_command<T>(command: &str, point: HookPoint, value: &T, dir: &Path) -> Result<Option<T>>
+where
+    T: Serialize + DeserializeOwned,
+{
+    debug!("Running {} plugin: {command}", point.name());
+    let input = serde_json::to_vec(value)?;
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .current_dir(dir)
+        .env("GITAI_HOOK", point.name())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to run {} plugin `{command}`", point.name()))?;
+
+    // Written from a thread so a command that prints before reading all of
+    // its input cannot block on a full pipe
+    let mut stdin = child.stdin.take().context("Could not open plugin stdin")?;
+    let writer = std::thread::spawn(move || stdin.write_all(&input));
+    let output = child.wait_with_output()?;
+    if let Ok(Err(e)) = writer.join() {
+        debug!("Plugin stopped reading its input: {e}");
+    }
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let reason = stderr.trim();
+        return Err(anyhow!(
+            "{} plugin `{command}` failed ({}){}",
+            point.name(),
+            output.status,
+            if reason.is_empty() {
+                String::new()
+            } else {
+                format!(": {reason}")
+            }
+        ));
+    }
+    if output.stdout.iter().all(u8::is_ascii_whitespace) {
+        return Ok(None);
+    }
+    serde_json::from_slice(&output.stdout)
+        .map(Some)
+        .with_context(|| {
+            format!(
+                "{} plugin `{command}` did not print the JSON it was given",
+                point.name()
+            )
+        })
+}
+
+// The commands run through `sh`
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plugins_modify_and_reject() {
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let settings = PluginSettings {
+            pre_commit: vec![
+                "sed 's/WIP: //'".to_string(),
+                "cat > /dev/null".to_string(),
+                r#"[ "$GITAI_HOOK" = pre-commit ] && grep -q '"amend":false' || { echo "amends are reviewed" >&2; exit 1; }"#.to_string(),
+            ],
+            ..PluginSettings::default()
+        };
+        let commit = PreCommit {
+            message: "WIP: fix parser".to_string(),
+            amend: false,
+        };
+        let result = settings
+            .run(HookPoint::PreCommit, commit.clone(), dir.path())
+            .expect("Plugins failed");
+        assert_eq!(result.message, "fix parser");
+        // Commands of other points are not run
+        assert_eq!(
+            settings
+                .run(HookPoint::MessageGenerated, commit.clone(), dir.path())
+                .expect("No plugins"),
+            commit
+        );
+
+        let amend = PreCommit {
+            amend: true,
+            ..commit
+        };
+        let error = settings
+            .run(HookPoint::PreCommit, amend, dir.path())
+            .expect_err("Plugin should reject amends");
+        assert!(error.to_string().ends_with(": amends are reviewed"));
+    }
+}
@@ -0,0 +1,227 @@
+//! WebAssembly analyzers and post-processors, run with wasmtime.
+//!
+//! A plugin is a core module that imports nothing, so it cannot reach files,
+//! the network or the environment, and exports:
+//!
+//! - `memory`
+//! - `gitai_alloc(len: i32) -> i32`, returning space for `len` input bytes
+//! - `gitai_analyze(ptr: i32, len: i32) -> i64` for analyzers: the input is
+//!   the changed file as JSON (`path`, `change_type`, `diff`, `content`) and
+//!   the output a JSON array of notes
+//! - `gitai_post_process(ptr: i32, len: i32) -> i64` for post-processors: the
+//!   input and output are `{"title": ..., "message": ...}`
+//!
+//! Outputs are returned as `ptr << 32 | len`; `0` means no notes, or the
+//! message unchanged. Every call gets a fresh instance with bounded fuel and
+//! memory, so a plugin keeps no state between files and cannot hang a run.
+
+use super::WasmPluginSpec;
+use crate::analyzers::{FileAnalyzer, register_analyzer};
+use crate::commands::commit::post_process::{MessagePostProcessor, register_post_processor};
+use crate::commands::commit::types::GeneratedMessage;
+use crate::llm::context::StagedFile;
+use anyhow::{Context, Result, anyhow};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::sync::{Arc, LazyLock};
+use wasmtime::{Engine, Instance, Module, Store, StoreLimits, StoreLimitsBuilder};
+
+/// Instructions a plugin may run per call
+const FUEL_PER_CALL: u64 = 2_000_000_000;
+
+/// Linear memory a plugin may grow to
+const MAX_MEMORY_BYTES: usize = 64 << 20;
+
+const ANALYZE: &str = "gitai_analyze";
+const POST_PROCESS: &str = "gitai_post_process";
+
+static ENGINE: LazyLock<Result<Engine>> = LazyLock::new(|| {
+    let mut config = wasmtime::Config::new();
+    config.consume_fuel(true);
+    Engine::new(&config).map_err(|e| anyhow!("Failed to start the WASM runtime: {e}"))
+});
+
+/// A compiled plugin module
+pub struct WasmPlugin {
+    /// Leaked once per plugin, as analyzer names are static
+    name: &'static str,
+    module: Module,
+}
+
+impl WasmPlugin {
+    /// Compiles the module of `spec`
+    pub fn load(spec: &WasmPluginSpec) -> Result<Self> {
+        let engine = ENGINE.as_ref().map_err(|e| anyhow!("{e}"))?;
+        let module = Module::from_file(engine, &spec.path)
+            .map_err(|e| anyhow!("Failed to load {}: {e}", spec.path.display()))?;
+        if let Some(import) = module.imports().next() {
+            return Err(anyhow!(
+                "{} imports {}::{}; plugins must not import anything",
+                spec.path.display(),
+                import.module(),
+                import.name()
+            ));
+        }
+        Ok(Self {
+            name: Box::leak(spec.name.clone().into_boxed_str()),
+            module,
+        })
+    }
+
+    fn exports(&self, export: &str) -> bool {
+        self.module.get_export(export).is_some()
+    }
+
+    /// Calls `export` with `input` as JSON in a fresh instance; `None` when
+    /// the plugin returned nothing
+    fn call<I, O>(&self, export: &str, input: &I) -> Result<Option<O>>
+    where
+        I: Serialize,
+        O: DeserializeOwned,
+    {
+        let input = serde_json::to_vec(input)?;
+        let limits = StoreLimitsBuilder::new()
+            .memory_size(MAX_MEMORY_BYTES)
+            .build();
+        let mut store = Store::new(self.module.engine(), limits);
+        store.limiter(|limits: &mut StoreLimits| limits);
+        let fault = |e: wasmtime::Error| anyhow!("WASM plugin {} failed: {e}", self.name);
+        store.set_fuel(FUEL_PER_CALL).map_err(fault)?;
+        let instance = Instance::new(&mut store, &self.module, &[]).map_err(fault)?;
+        let memory = instance
+            .get_memory(&mut store, "memory")
+            .with_context(|| format!("WASM plugin {} exports no memory", self.name))?;
+        let alloc = instance
+            .get_typed_func::<i32, i32>(&mut store, "gitai_alloc")
+            .map_err(fault)?;
+        let function = instance
+            .get_typed_func::<(i32, i32), i64>(&mut store, export)
+            .map_err(fault)?;
+
+        let len = i32::try_from(input.len()).context("Input too large for a WASM plugin")?;
+        let ptr = alloc.call(&mut store, len).map_err(fault)?;
+        memory
+            .write(&mut store, usize::try_from(ptr)?, &input)
+            .map_err(|e| anyhow!("WASM plugin {} gave no room for input: {e}", self.name))?;
+        let packed = function
+            .call(&mut store, (ptr, len))
+            .map_err(fault)?
+            .cast_unsigned();
+        if packed == 0 {
+            return Ok(None);
+        }
+        let mut output = vec![0; usize::try_from(packed & 0xffff_ffff)?];
+        memory
+            .read(&store, usize::try_from(packed >> 32)?, &mut output)
+            .map_err(|e| anyhow!("WASM plugin {} returned a bad range: {e}", self.name))?;
+        serde_json::from_slice(&output)
+            .map(Some)
+            .with_context(|| format!("WASM plugin {} returned invalid JSON", self.name))
+    }
+}
+
+impl FileAnalyzer for WasmPlugin {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn analyze(&self, file: &StagedFile) -> Vec<String> {
+        self.call(ANALYZE, file)
+            .unwrap_or_else(|e| {
+                log::warn!("{e:#}");
+                None
+            })
+            .unwrap_or_default()
+    }
+}
+
+impl MessagePostProcessor for WasmPlugin {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn process(&self, message: GeneratedMessage) -> Result<GeneratedMessage> {
+        Ok(self.call(POST_PROCESS, &message)?.unwrap_or(message))
+    }
+}
+
+/// Loads the plugin of `spec` and registers it for what it declares and exports
+pub fn register(spec: &WasmPluginSpec) -> Result<()> {
+    let plugin = Arc::new(WasmPlugin::load(spec)?);
+    if !spec.analyze.is_empty() {
+        if !plugin.exports(ANALYZE) {
+            return Err(anyhow!("it declares `analyze` but exports no {ANALYZE}"));
+        }
+        for pattern in &spec.analyze {
+            register_analyzer(pattern, plugin.clone())?;
+        }
+    }
+    if spec.post_process {
+        if !plugin.exports(POST_PROCESS) {
+            return Err(anyhow!(
+                "it declares `post-process` but exports no {POST_PROCESS}"
+            ));
+        }
+        register_post_processor(plugin);
+    }
+    log::debug!("Registered WASM plugin {}", spec.name);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::context::ChangeType;
+
+    /// Notes `["adds a message"]` for any file, rewrites every message to a
+    /// fixed one, and loops forever when asked to process anything else
+    const PLUGIN: &str = r#"(module
+      (memory (export "memory") 1)
+      (data (i32.const 16) "[\"adds a message\"]")
+      (data (i32.const 64) "{\"title\":\"feat: add proto\",\"message\":\"\"}")
+      (func (export "gitai_alloc") (param i32) (result i32) (i32.const 1024))
+      (func (export "gitai_analyze") (param i32 i32) (result i64)
+        (i64.or (i64.shl (i64.const 16) (i64.const 32)) (i64.const 18)))
+      (func (export "gitai_post_process") (param i32 i32) (result i64)
+        (if (i32.gt_u (local.get 1) (i32.const 100)) (then (loop (br 0))))
+        (i64.or (i64.shl (i64.const 64) (i64.const 32)) (i64.const 40))))"#;
+
+    #[test]
+    fn test_wasm_plugin_calls() {
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let path = dir.path().join("proto.wat");
+        std::fs::write(&path, PLUGIN).expect("Failed to write plugin");
+        let plugin = WasmPlugin::load(&WasmPluginSpec {
+            name: "proto".to_string(),
+            path,
+            analyze: vec!["*.proto".to_string()],
+            post_process: true,
+        })
+        .expect("Failed to load plugin");
+
+        let file = StagedFile {
+            path: "api/user.proto".to_string(),
+            change_type: ChangeType::Modified,
+            diff: "+message User {}".to_string(),
+            content: None,
+            content_excluded: false,
+        };
+        assert_eq!(plugin.analyze(&file), ["adds a message"]);
+
+        let message = GeneratedMessage {
+            title: "add proto".to_string(),
+            message: String::new(),
+        };
+        assert_eq!(
+            plugin.process(message).expect("Failed to process").title,
+            "feat: add proto"
+        );
+
+        // Running out of fuel ends the call instead of hanging
+        let long = GeneratedMessage {
+            title: "add proto".to_string(),
+            message: "x".repeat(200),
+        };
+        assert!(plugin.process(long).is_err());
+    }
+}
@@ -0,0 +1,124 @@
+//! Machine-readable progress events, for `--progress json`.
+//!
+//! Tools that wrap gitai draw their own progress rather than scraping the
+//! spinner. In JSON mode the spinner stays off, and each step of a
+//! generation writes one JSON object per line to stderr, with its `event`
+//! name and the milliseconds since the mode was turned on. stdout is left
+//! for the output itself.
+
+use clap::ValueEnum;
+use serde::Serialize;
+use std::io::Write;
+use std::sync::OnceLock;
+use std::time::Instant;
+
+/// How progress is shown
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum ProgressFormat {
+    /// Spinners and status lines
+    #[default]
+    Human,
+    /// Newline-delimited JSON events on stderr
+    Json,
+}
+
+/// When JSON events were turned on; unset in the default mode
+static JSON_SINCE: OnceLock<Instant> = OnceLock::new();
+
+/// Sets the process-wide progress format
+pub fn set_format(format: ProgressFormat) {
+    if format == ProgressFormat::Json {
+        JSON_SINCE.get_or_init(Instant::now);
+    }
+}
+
+/// Whether progress is written as JSON events
+pub fn is_json() -> bool {
+    JSON_SINCE.get().is_some()
+}
+
+/// A step of a generation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event<'a> {
+    /// The changes and their context are gathered
+    ContextCollected { files: usize, recent_commits: usize },
+    /// The prompt fits the model's budget and is ready to send
+    PromptBuilt { prompt_tokens: usize },
+    /// A request to the provider is on its way
+    LlmRequestStarted { provider: &'a str, model: &'a str },
+    /// The provider's response arrived; counts are estimated when the
+    /// provider reports none
+    TokensStreamed {
+        prompt_tokens: usize,
+        completion_tokens: usize,
+    },
+    /// The finished message
+    MessageReady { title: &'a str },
+}
+
+/// An event with the time it happened at
+#[derive(Serialize)]
+struct Line<'e, 'a> {
+    #[serde(flatten)]
+    event: &'e Event<'a>,
+    elapsed_ms: u128,
+}
+
+/// The line written for `event`, `elapsed_ms` after the mode was turned on
+fn line(event: &Event<'_>, elapsed_ms: u128) -> String {
+    serde_json::to_string(&Line { event, elapsed_ms }).unwrap_or_default()
+}
+
+/// Writes `event` to stderr in JSON mode
+pub fn emit(event: &Event<'_>) {
+    let Some(since) = JSON_SINCE.get() else {
+        return;
+    };
+    let line = line(event, since.elapsed().as_millis());
+    // One write per line, so events from parallel requests do not interleave
+    let mut stderr = std::io::stderr().lock();
+    if let Err(e) = writeln!(stderr, "{line}") {
+        log::debug!("Progress event write failed: {e}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{Value, json};
+
+    #[test]
+    fn test_events_are_single_json_lines() {
+        let line = line(
+            &Event::LlmRequestStarted {
+                provider: "anthropic",
+                model: "claude\nsonnet",
+            },
+            42,
+        );
+        assert!(!line.contains('\n'));
+        assert!(line.starts_with(r#"{"event":"llm_request_started","#));
+        let value: Value = serde_json::from_str(&line).expect("Valid JSON");
+        assert_eq!(
+            value,
+            json!({
+                "event": "llm_request_started",
+                "provider": "anthropic",
+                "model": "claude\nsonnet",
+                "elapsed_ms": 42,
+            })
+        );
+
+        let value: Value = serde_json::from_str(&super::line(
+            &Event::TokensStreamed {
+                prompt_tokens: 1200,
+                completion_tokens: 80,
+            },
+            7,
+        ))
+        .expect("Valid JSON");
+        assert_eq!(value["event"], "tokens_streamed");
+        assert_eq!(value["completion_tokens"], 80);
+    }
+}
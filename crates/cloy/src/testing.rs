@@ -0,0 +1,425 @@
+//! Fakes for testing gitai without a network or a hand-made repository.
+//!
+//! Enabled by the `test-utils` feature. [`MockProvider`] answers every
+//! generation with canned or scripted text once installed, and
+//! [`RepoFixture`] sets up a throwaway repository with commits and staged
+//! changes:
+//!
+//! ```no_run
+//! # async fn run() -> anyhow::Result<()> {
+//! use cloy::testing::{MockProvider, RepoFixture};
+//!
+//! let fixture = RepoFixture::builder()
+//!     .commit("feat: add greeting", &[("src/lib.rs", "pub fn hi() {}\n")])
+//!     .stage("src/lib.rs", "pub fn hi() {}\npub fn bye() {}\n")
+//!     .build()?;
+//! let mock = MockProvider::always(r#"{"title": "feat: add bye", "message": ""}"#);
+//! let _installed = mock.install();
+//!
+//! let client = cloy::Client::builder()
+//!     .repository(fixture.path())
+//!     .config(cloy::Config::default())
+//!     .provider("openrouter")
+//!     .api_key("unused")
+//!     .build()?;
+//! let message = client.generate_commit_message().await?;
+//! assert_eq!(message.title, "feat: add bye");
+//! assert!(mock.requests()[0].user.contains("bye"));
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The provider is installed for the whole process, so tests that install
+//! different ones must not run at the same time.
+//!
+//! The fixture is not held in memory. [`GitRepo`] and the pipeline open
+//! repositories by path and read staged changes from a real index and work
+//! tree, which git2's in-memory object database does not provide. The
+//! fixture therefore lives in a temporary directory, but it is built with
+//! git2 directly, so it needs no `git` binary and no network.
+
+use crate::git::GitRepo;
+use crate::llm::chat_only_provider;
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use git2::{Repository, Signature};
+use llm::ToolCall;
+use llm::chat::{ChatMessage, ChatProvider, ChatResponse, Tool, Usage};
+use llm::error::LLMError;
+use parking_lot::{Mutex, RwLock};
+use std::collections::VecDeque;
+use std::fmt;
+use std::path::Path;
+use std::sync::Arc;
+use tempfile::TempDir;
+
+static INSTALLED: RwLock<Option<MockProvider>> = RwLock::new(None);
+
+/// The provider generations go to instead of the configured one, if any
+pub(crate) fn installed() -> Option<MockProvider> {
+    INSTALLED.read().clone()
+}
+
+/// A prompt the mock was sent
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MockRequest {
+    pub system: String,
+    pub user: String,
+}
+
+#[derive(Debug, Default)]
+struct MockState {
+    script: VecDeque<Result<String, String>>,
+    fallback: Option<String>,
+    requests: Vec<MockRequest>,
+}
+
+/// A provider that replies without a network.
+///
+/// Scripted replies are used once each, in order; after them comes the
+/// [`Self::otherwise`] reply, or an error when there is none. Clones share the
+/// script and the recorded requests.
+#[derive(Debug, Clone, Default)]
+pub struct MockProvider {
+    state: Arc<Mutex<MockState>>,
+    /// System prompt of the generation this copy serves
+    system: String,
+}
+
+impl MockProvider {
+    /// A provider with nothing to reply yet
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A provider that replies `text` to everything
+    pub fn always(text: impl Into<String>) -> Self {
+        Self::new().otherwise(text)
+    }
+
+    /// Replies `text` once, after the replies scripted before it
+    #[must_use]
+    pub fn then_reply(self, text: impl Into<String>) -> Self {
+        self.state.lock().script.push_back(Ok(text.into()));
+        self
+    }
+
+    /// Fails once with `message`, after the replies scripted before it.
+    ///
+    /// Generations retry a failed request twice, so a failure has to be
+    /// scripted three times to fail a generation.
+    #[must_use]
+    pub fn then_fail(self, message: impl Into<String>) -> Self {
+        self.state.lock().script.push_back(Err(message.into()));
+        self
+    }
+
+    /// Replies `text` once the script has run out
+    #[must_use]
+    pub fn otherwise(self, text: impl Into<String>) -> Self {
+        self.state.lock().fallback = Some(text.into());
+        self
+    }
+
+    /// Every prompt sent so far, oldest first
+    pub fn requests(&self) -> Vec<MockRequest> {
+        self.state.lock().requests.clone()
+    }
+
+    /// Sends every generation in this process to the mock until the guard is dropped
+    #[must_use = "the mock is uninstalled when the guard is dropped"]
+    pub fn install(&self) -> MockGuard {
+        let previous = INSTALLED.write().replace(self.clone());
+        MockGuard { previous }
+    }
+
+    /// A copy that records `system` as the system prompt of its requests
+    pub(crate) fn with_system(mut self, system: &str) -> Self {
+        system.clone_into(&mut self.system);
+        self
+    }
+}
+
+/// Keeps a [`MockProvider`] installed; dropping it restores the previous one
+#[derive(Debug)]
+pub struct MockGuard {
+    previous: Option<MockProvider>,
+}
+
+impl Drop for MockGuard {
+    fn drop(&mut self) {
+        *INSTALLED.write() = self.previous.take();
+    }
+}
+
+#[derive(Debug)]
+struct MockResponse(String);
+
+impl fmt::Display for MockResponse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl ChatResponse for MockResponse {
+    fn text(&self) -> Option<String> {
+        Some(self.0.clone())
+    }
+
+    fn tool_calls(&self) -> Option<Vec<ToolCall>> {
+        None
+    }
+
+    fn usage(&self) -> Option<Usage> {
+        None
+    }
+}
+
+#[async_trait]
+impl ChatProvider for MockProvider {
+    async fn chat_with_tools(
+        &self,
+        messages: &[ChatMessage],
+        _tools: Option<&[Tool]>,
+    ) -> Result<Box<dyn ChatResponse>, LLMError> {
+        let mut state = self.state.lock();
+        state.requests.push(MockRequest {
+            system: self.system.clone(),
+            user: messages
+                .iter()
+                .map(|message| message.content.as_str())
+                .collect::<Vec<_>>()
+                .join("\n"),
+        });
+        let reply = match state.script.pop_front() {
+            Some(reply) => reply,
+            None => state
+                .fallback
+                .clone()
+                .ok_or_else(|| "MockProvider has no replies left".to_string()),
+        };
+        let text = reply.map_err(LLMError::ProviderError)?;
+        Ok(Box::new(MockResponse(text)))
+    }
+}
+
+chat_only_provider!(MockProvider, "MockProvider");
+
+/// A file to write, or `None` to delete it
+type Change = (String, Option<String>);
+
+#[derive(Debug)]
+enum Step {
+    Commit(String, Vec<Change>),
+    Stage(Change),
+    Write(Change),
+    Config(String, String),
+}
+
+/// Builds a [`RepoFixture`] step by step
+#[derive(Debug, Default)]
+pub struct RepoFixtureBuilder {
+    steps: Vec<Step>,
+}
+
+impl RepoFixtureBuilder {
+    /// Writes `files` and commits them with `message`
+    #[must_use]
+    pub fn commit(mut self, message: &str, files: &[(&str, &str)]) -> Self {
+        let files = files
+            .iter()
+            .map(|(path, content)| ((*path).to_string(), Some((*content).to_string())))
+            .collect();
+        self.steps.push(Step::Commit(message.to_string(), files));
+        self
+    }
+
+    /// Writes `path` and stages it
+    #[must_use]
+    pub fn stage(mut self, path: &str, content: &str) -> Self {
+        self.steps
+            .push(Step::Stage((path.to_string(), Some(content.to_string()))));
+        self
+    }
+
+    /// Deletes `path` and stages the deletion
+    #[must_use]
+    pub fn stage_removal(mut self, path: &str) -> Self {
+        self.steps.push(Step::Stage((path.to_string(), None)));
+        self
+    }
+
+    /// Writes `path` without staging it
+    #[must_use]
+    pub fn write(mut self, path: &str, content: &str) -> Self {
+        self.steps
+            .push(Step::Write((path.to_string(), Some(content.to_string()))));
+        self
+    }
+
+    /// Sets `key` in the repository's git config, such as `gitai.gitmoji`
+    #[must_use]
+    pub fn config(mut self, key: &str, value: &str) -> Self {
+        self.steps
+            .push(Step::Config(key.to_string(), value.to_string()));
+        self
+    }
+
+    /// The repository, after running the steps in order
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the repository cannot be created or a step fails.
+    pub fn build(self) -> Result<RepoFixture> {
+        let dir = TempDir::new()?;
+        let repo = Repository::init(dir.path())?;
+        let mut config = repo.config()?;
+        config.set_str("user.name", "Test User")?;
+        config.set_str("user.email", "test@example.com")?;
+        for step in self.steps {
+            match step {
+                Step::Commit(message, files) => {
+                    for change in &files {
+                        apply(&repo, change)?;
+                    }
+                    commit(&repo, &message)?;
+                }
+                Step::Stage(change) => apply(&repo, &change)?,
+                Step::Write((path, content)) => {
+                    write(dir.path(), &path, content.as_deref())?;
+                }
+                Step::Config(key, value) => config.set_str(&key, &value)?,
+            }
+        }
+        Ok(RepoFixture { dir })
+    }
+}
+
+/// Writes or deletes a file and stages the change
+fn apply(repo: &Repository, (path, content): &Change) -> Result<()> {
+    let workdir = repo
+        .workdir()
+        .context("Fixture repository has no workdir")?;
+    write(workdir, path, content.as_deref())?;
+    let mut index = repo.index()?;
+    if content.is_some() {
+        index.add_path(Path::new(path))?;
+    } else {
+        index.remove_path(Path::new(path))?;
+    }
+    index.write()?;
+    Ok(())
+}
+
+fn write(workdir: &Path, path: &str, content: Option<&str>) -> Result<()> {
+    let full = workdir.join(path);
+    match content {
+        Some(content) => {
+            if let Some(parent) = full.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::write(&full, content)?;
+        }
+        None => std::fs::remove_file(&full)?,
+    }
+    Ok(())
+}
+
+fn commit(repo: &Repository, message: &str) -> Result<()> {
+    let tree = repo.find_tree(repo.index()?.write_tree()?)?;
+    let signature = Signature::now("Test User", "test@example.com")?;
+    let parent = repo.head().ok().and_then(|head| head.peel_to_commit().ok());
+    let parents = parent.iter().collect::<Vec<_>>();
+    repo.commit(
+        Some("HEAD"),
+        &signature,
+        &signature,
+        message,
+        &tree,
+        &parents,
+    )?;
+    Ok(())
+}
+
+/// A repository in a temporary directory, removed when the fixture is dropped
+///
+/// A directory is needed because [`GitRepo`] works on a repository path,
+/// with an index and work tree, rather than on an object database alone.
+#[derive(Debug)]
+pub struct RepoFixture {
+    dir: TempDir,
+}
+
+impl RepoFixture {
+    pub fn builder() -> RepoFixtureBuilder {
+        RepoFixtureBuilder::default()
+    }
+
+    pub fn path(&self) -> &Path {
+        self.dir.path()
+    }
+
+    /// A `GitRepo` for the fixture; it must not outlive the fixture
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the repository cannot be opened.
+    pub fn git_repo(&self) -> Result<GitRepo> {
+        GitRepo::new(self.path())
+    }
+
+    /// Writes `path` and stages it
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be written or staged.
+    pub fn stage(&self, path: &str, content: &str) -> Result<()> {
+        let repo = Repository::open(self.path())?;
+        apply(&repo, &(path.to_string(), Some(content.to_string())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Client;
+    use crate::config::Config;
+
+    #[tokio::test]
+    async fn test_full_pipeline_with_mock_and_fixture() {
+        let fixture = RepoFixture::builder()
+            .commit("feat: add greeting", &[("src/lib.rs", "pub fn hi() {}\n")])
+            .stage("src/lib.rs", "pub fn hi() {}\npub fn bye() {}\n")
+            .write("notes.txt", "not staged\n")
+            .build()
+            .expect("Failed to build fixture");
+        let mock = MockProvider::new()
+            .then_reply(r#"{"title": "feat: add bye", "message": "Adds bye."}"#)
+            .otherwise(r#"{"title": "feat: later", "message": ""}"#);
+        let _installed = mock.install();
+
+        let client = Client::builder()
+            .repository(fixture.path())
+            .config(Config::default())
+            .provider("openrouter")
+            .api_key("unused")
+            .build()
+            .expect("Failed to build client");
+        let message = client
+            .generate_commit_message()
+            .await
+            .expect("Failed to generate");
+        assert_eq!(message.title, "feat: add bye");
+        let again = client
+            .generate_commit_message()
+            .await
+            .expect("Failed to generate");
+        assert_eq!(again.title, "feat: later");
+
+        let requests = mock.requests();
+        assert_eq!(requests.len(), 2);
+        assert!(requests[0].user.contains("pub fn bye"));
+        assert!(!requests[0].user.contains("not staged"));
+        assert!(!requests[0].system.is_empty());
+    }
+}
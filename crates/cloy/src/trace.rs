@@ -0,0 +1,189 @@
+//! Chrome trace export of the pipeline's spans, for `--trace`.
+//!
+//! Each closed span becomes a complete (`"ph": "X"`) event with its start,
+//! duration and fields, written as soon as the span closes. The trace format
+//! allows the event array to stay unterminated, so the file is readable by
+//! `chrome://tracing` and Perfetto even when the process exits early.
+
+use anyhow::{Context, Result};
+use serde_json::{Map, Value, json};
+use std::fmt;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::Instant;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Subscriber, span};
+use tracing_subscriber::layer::Context as LayerContext;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::{Layer, Registry, reload};
+
+/// Slot for the trace layer, filled by `--trace` once arguments are parsed
+type TraceSlot = reload::Layer<Option<ChromeTraceLayer>, Registry>;
+
+static TRACE_HANDLE: OnceLock<reload::Handle<Option<ChromeTraceLayer>, Registry>> = OnceLock::new();
+
+static NEXT_THREAD: AtomicU64 = AtomicU64::new(1);
+
+thread_local! {
+    /// Small per-thread number for the trace's `tid`
+    static THREAD: u64 = NEXT_THREAD.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Start, thread and fields of an open span
+struct Timing {
+    start: Instant,
+    thread: u64,
+    fields: Map<String, Value>,
+}
+
+/// Collects span fields as JSON values
+struct FieldVisitor<'a>(&'a mut Map<String, Value>);
+
+impl Visit for FieldVisitor<'_> {
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0
+            .insert(field.name().to_string(), format!("{value:?}").into());
+    }
+}
+
+/// A layer writing closed spans to a Chrome trace file
+pub struct ChromeTraceLayer {
+    file: Mutex<File>,
+    epoch: Instant,
+}
+
+impl ChromeTraceLayer {
+    /// Creates the trace file at `path`, replacing any existing one
+    pub fn create(path: &Path) -> Result<Self> {
+        let mut file = File::create(path)
+            .with_context(|| format!("Failed to create trace file {}", path.display()))?;
+        file.write_all(b"[\n")?;
+        Ok(Self {
+            file: Mutex::new(file),
+            epoch: Instant::now(),
+        })
+    }
+
+    fn micros_since_epoch(&self, instant: Instant) -> u64 {
+        u64::try_from(instant.duration_since(self.epoch).as_micros()).unwrap_or(u64::MAX)
+    }
+}
+
+impl<S> Layer<S> for ChromeTraceLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: LayerContext<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut fields = Map::new();
+        attrs.record(&mut FieldVisitor(&mut fields));
+        span.extensions_mut().insert(Timing {
+            start: Instant::now(),
+            thread: THREAD.with(|thread| *thread),
+            fields,
+        });
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: LayerContext<'_, S>) {
+        if let Some(span) = ctx.span(id)
+            && let Some(timing) = span.extensions_mut().get_mut::<Timing>()
+        {
+            values.record(&mut FieldVisitor(&mut timing.fields));
+        }
+    }
+
+    fn on_close(&self, id: span::Id, ctx: LayerContext<'_, S>) {
+        let Some(span) = ctx.span(&id) else {
+            return;
+        };
+        let extensions = span.extensions();
+        let Some(timing) = extensions.get::<Timing>() else {
+            return;
+        };
+        let event = json!({
+            "name": span.name(),
+            "cat": span.metadata().target(),
+            "ph": "X",
+            "ts": self.micros_since_epoch(timing.start),
+            "dur": u64::try_from(timing.start.elapsed().as_micros()).unwrap_or(u64::MAX),
+            "pid": std::process::id(),
+            "tid": timing.thread,
+            "args": timing.fields,
+        });
+        if let Ok(mut file) = self.file.lock() {
+            // Tracing must never fail the command; a lost event only thins the trace
+            let _ = writeln!(file, "{event},");
+        }
+    }
+}
+
+/// An empty slot for the trace layer, to install with the process's subscriber
+pub fn trace_slot() -> TraceSlot {
+    let (layer, handle) = reload::Layer::new(None);
+    // Only the installed subscriber's slot can be filled
+    let _ = TRACE_HANDLE.set(handle);
+    layer
+}
+
+/// Starts writing a Chrome trace of every span to `path`
+pub fn enable_chrome_trace(path: &Path) -> Result<()> {
+    let handle = TRACE_HANDLE.get().context("Tracing is not initialized")?;
+    let layer = ChromeTraceLayer::create(path)?;
+    handle
+        .modify(|slot| *slot = Some(layer))
+        .context("Failed to enable the trace")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    #[test]
+    fn test_closed_spans_become_complete_events() {
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let path = dir.path().join("trace.json");
+        let layer = ChromeTraceLayer::create(&path).expect("Failed to create layer");
+        let subscriber = tracing_subscriber::registry().with(layer);
+
+        tracing::subscriber::with_default(subscriber, || {
+            let span = tracing::info_span!("fit_to_budget", max_tokens = 100_u64, trimmed = false);
+            let _outer = span.enter();
+            span.record("trimmed", true);
+            tracing::info_span!("inner").in_scope(|| {});
+        });
+
+        let text = std::fs::read_to_string(&path).expect("Failed to read trace");
+        let events: Vec<Value> =
+            serde_json::from_str(&format!("{}]", text.trim_end().trim_end_matches(',')))
+                .expect("Valid trace");
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0]["name"], "inner");
+        assert_eq!(events[1]["name"], "fit_to_budget");
+        assert_eq!(events[1]["ph"], "X");
+        assert_eq!(events[1]["args"]["max_tokens"], 100);
+        assert_eq!(events[1]["args"]["trimmed"], true);
+    }
+}
@@ -0,0 +1,187 @@
+//! `Co-authored-by:`, `Signed-off-by:` and other trailers on created commits.
+//!
+//! Trailers come from `gitai.co-authors`, `gitai.trailers` and
+//! `gitai.signoff`, and from `--co-author`, `--trailer` and `--signoff`. A
+//! `[gitai-pair]` entry names a group of co-authors, so `--co-author mob`
+//! adds everyone in `gitai-pair.mob`. They are added when gitai commits, after
+//! the message is final, and join the message's own trailer block if it ends
+//! with one. A trailer the message already has is not repeated.
+
+use anyhow::{Result, anyhow};
+use regex::Regex;
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+/// `Key: value`, the form of one trailer line
+static TRAILER_LINE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^[A-Za-z0-9][A-Za-z0-9-]*: \S").expect("Valid regex"));
+
+/// `Name <email>`
+static IDENTITY: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^[^<>]+ <[^<>\s]+@[^<>\s]+>$").expect("Valid regex"));
+
+/// Trailers added to the commits gitai creates
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TrailerSettings {
+    /// `Name <email>` of each co-author
+    pub co_authors: Vec<String>,
+    /// Other trailers as `Key: value`
+    pub trailers: Vec<String>,
+    /// Whether to sign off as the configured user for the DCO
+    pub signoff: bool,
+    /// Co-author groups by name, from `gitai-pair.<name>`
+    pub pairs: HashMap<String, Vec<String>>,
+}
+
+impl TrailerSettings {
+    /// Adds a co-author given as `Name <email>` or as the name of a pair preset
+    pub fn add_co_author(&mut self, spec: &str) -> Result<()> {
+        let spec = spec.trim();
+        if spec.contains('<') {
+            if !IDENTITY.is_match(spec) {
+                return Err(anyhow!(
+                    "Co-author '{spec}' is not in the form Name <email>"
+                ));
+            }
+            self.co_authors.push(spec.to_string());
+            return Ok(());
+        }
+        let members = self.pairs.get(&spec.to_lowercase()).ok_or_else(|| {
+            anyhow!("No pair preset '{spec}'; define it with `git config gitai-pair.{spec} \"Name <email>, ...\"`")
+        })?;
+        self.co_authors.extend(members.iter().cloned());
+        Ok(())
+    }
+
+    /// Adds a trailer given as `Key: value` or `Key=value`
+    pub fn add_trailer(&mut self, spec: &str) -> Result<()> {
+        let (key, value) = spec
+            .split_once(':')
+            .or_else(|| spec.split_once('='))
+            .ok_or_else(|| anyhow!("Trailer '{spec}' is not in the form Key: value"))?;
+        let line = format!("{}: {}", key.trim(), value.trim());
+        if !TRAILER_LINE.is_match(&line) {
+            return Err(anyhow!("Trailer '{spec}' is not in the form Key: value"));
+        }
+        self.trailers.push(line);
+        Ok(())
+    }
+
+    /// The trailer lines to add, with `signer` as `Name <email>` for the sign-off
+    pub(crate) fn lines(&self, signer: Option<&str>) -> Vec<String> {
+        let mut lines: Vec<String> = self
+            .co_authors
+            .iter()
+            .map(|co_author| format!("Co-authored-by: {co_author}"))
+            .collect();
+        lines.extend(self.trailers.iter().cloned());
+        if self.signoff
+            && let Some(signer) = signer
+        {
+            lines.push(format!("Signed-off-by: {signer}"));
+        }
+        lines
+    }
+
+    /// `message` with the configured trailers added
+    pub fn apply(&self, message: &str, signer: Option<&str>) -> String {
+        append_trailers(message, &self.lines(signer))
+    }
+}
+
+/// Whether a paragraph is a block of trailers
+fn is_trailer_block(paragraph: &str) -> bool {
+    paragraph.lines().all(|line| TRAILER_LINE.is_match(line))
+}
+
+/// `message` with `lines` added to its trailer block, skipping lines it already has.
+///
+/// The last paragraph counts as a trailer block only after a subject, so a
+/// subject such as `fix: typo` is never mistaken for one.
+pub fn append_trailers(message: &str, lines: &[String]) -> String {
+    let body = message.trim_end();
+    let mut new_lines: Vec<&str> = Vec::new();
+    for line in lines {
+        if !body.lines().any(|existing| existing == line) && !new_lines.contains(&line.as_str()) {
+            new_lines.push(line);
+        }
+    }
+    if new_lines.is_empty() {
+        return message.to_string();
+    }
+
+    let separator = match body.rsplit_once("\n\n") {
+        Some((_, last)) if is_trailer_block(last) => "\n",
+        _ => "\n\n",
+    };
+    format!("{body}{separator}{}", new_lines.join("\n"))
+}
+
+/// Splits a comma-separated config list
+pub fn parse_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(ToString::to_string)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trailers_join_the_existing_block() {
+        let lines = vec!["Co-authored-by: Ada <ada@example.com>".to_string()];
+        assert_eq!(
+            append_trailers("fix: typo", &lines),
+            "fix: typo\n\nCo-authored-by: Ada <ada@example.com>"
+        );
+        assert_eq!(
+            append_trailers("Add parser\n\nBody text.\n\nRefs: #12\n", &lines),
+            "Add parser\n\nBody text.\n\nRefs: #12\nCo-authored-by: Ada <ada@example.com>"
+        );
+        assert_eq!(
+            append_trailers(
+                "Add parser\n\nBody: with a colon, but prose\nmore prose",
+                &lines
+            ),
+            "Add parser\n\nBody: with a colon, but prose\nmore prose\n\nCo-authored-by: Ada <ada@example.com>"
+        );
+        // Already there
+        let message = "Add parser\n\nCo-authored-by: Ada <ada@example.com>";
+        assert_eq!(append_trailers(message, &lines), message);
+    }
+
+    #[test]
+    fn test_settings() {
+        let mut settings = TrailerSettings {
+            signoff: true,
+            pairs: HashMap::from([(
+                "mob".to_string(),
+                parse_list("Ada <ada@example.com>, Grace <grace@example.com>"),
+            )]),
+            ..TrailerSettings::default()
+        };
+        settings.add_co_author("Mob").expect("Known preset");
+        settings
+            .add_trailer("Reviewed-by=Linus <linus@example.com>")
+            .expect("Valid trailer");
+        assert!(settings.add_co_author("nobody").is_err());
+        assert!(settings.add_co_author("Ada <not an email>").is_err());
+        assert!(settings.add_trailer("no separator").is_err());
+        assert!(settings.add_trailer("two words: value").is_err());
+
+        assert_eq!(
+            settings.apply("Add parser", Some("Me <me@example.com>")),
+            "Add parser\n\n\
+             Co-authored-by: Ada <ada@example.com>\n\
+             Co-authored-by: Grace <grace@example.com>\n\
+             Reviewed-by: Linus <linus@example.com>\n\
+             Signed-off-by: Me <me@example.com>"
+        );
+        // Without a configured user there is nobody to sign off as
+        assert!(!settings.apply("Add parser", None).contains("Signed-off-by"));
+    }
+}
@@ -0,0 +1,173 @@
+//! Terminal capability detection for glyphs and the alternate screen
+//!
+//! Legacy Windows consoles and `LANG=C` terminals show Nerd Font icons,
+//! braille spinners and emoji as mojibake. When Unicode is not available the
+//! TUI keeps its layout but those glyphs are rewritten to ASCII fallbacks after
+//! drawing, and spinners switch to ASCII frames. Other text, such as accented
+//! or CJK letters in a message, is left as it is.
+//!
+//! `GITAI_ASCII=1` forces the ASCII fallback, `GITAI_ASCII=0` disables it and
+//! `GITAI_ALT_SCREEN=0` keeps the TUI on the main screen.
+
+use crate::llm::style::is_emoji_char;
+use ratatui::buffer::Buffer;
+use std::sync::OnceLock;
+
+/// ASCII spinner frames used when Unicode glyphs are unavailable
+pub const ASCII_SPINNER_FRAMES: &[&str] = &["|", "/", "-", "\\"];
+
+/// What the current terminal can render
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TerminalCapabilities {
+    /// Unicode symbols, icons and emoji render correctly
+    pub unicode: bool,
+    /// The alternate screen can be entered and left cleanly
+    pub alternate_screen: bool,
+}
+
+impl TerminalCapabilities {
+    /// Detects capabilities from the environment
+    pub fn detect() -> Self {
+        Self::from_env(|key| std::env::var(key).ok(), cfg!(windows))
+    }
+
+    fn from_env(var: impl Fn(&str) -> Option<String>, windows: bool) -> Self {
+        let dumb = var("TERM").is_some_and(|term| term == "dumb");
+        let forced = var("GITAI_ASCII").map(|value| value != "0");
+
+        let unicode = match forced {
+            Some(ascii) => !ascii,
+            None if dumb => false,
+            None if windows => windows_supports_unicode(&var),
+            None => locale_is_utf8(&var),
+        };
+        let alternate_screen = !dumb && var("GITAI_ALT_SCREEN").is_none_or(|value| value != "0");
+
+        Self {
+            unicode,
+            alternate_screen,
+        }
+    }
+}
+
+/// Windows Terminal, VS Code, `ConEmu` and mintty handle Unicode; conhost does not
+fn windows_supports_unicode(var: &impl Fn(&str) -> Option<String>) -> bool {
+    var("WT_SESSION").is_some()
+        || var("TERM_PROGRAM").is_some_and(|program| program == "vscode")
+        || var("ConEmuANSI").is_some_and(|value| value == "ON")
+        || var("TERM").is_some()
+}
+
+/// The first non-empty of `LC_ALL`, `LC_CTYPE` and `LANG` decides; no locale means UTF-8
+fn locale_is_utf8(var: &impl Fn(&str) -> Option<String>) -> bool {
+    ["LC_ALL", "LC_CTYPE", "LANG"]
+        .iter()
+        .find_map(|key| var(key).filter(|value| !value.is_empty()))
+        .is_none_or(|locale| {
+            let locale = locale.to_lowercase();
+            locale.contains("utf-8") || locale.contains("utf8")
+        })
+}
+
+static CAPABILITIES: OnceLock<TerminalCapabilities> = OnceLock::new();
+
+/// Capabilities of the current terminal, detected once
+pub fn capabilities() -> TerminalCapabilities {
+    *CAPABILITIES.get_or_init(TerminalCapabilities::detect)
+}
+
+/// ASCII replacement for a single rendered symbol
+pub fn ascii_fallback(symbol: &str) -> &str {
+    if symbol.is_ascii() {
+        return symbol;
+    }
+    match symbol {
+        "━" | "─" | "═" => "-",
+        "│" | "┃" | "║" => "|",
+        "┌" | "┐" | "└" | "┘" | "╭" | "╮" | "╰" | "╯" => "+",
+        "←" => "<",
+        "→" | "󰁕" | "▶" | "›" => ">",
+        "↑" => "^",
+        "↓" => "v",
+        "󰄲" | "󰄬" | "✓" | "✔" => "x",
+        "•" | "·" => "*",
+        "…" => ".",
+        // Empty checkboxes, icons, emoji and other symbols: keep the cell, drop the glyph
+        _ if symbol.chars().any(is_glyph) => " ",
+        _ => symbol,
+    }
+}
+
+/// Arrows, box drawing, shapes, braille, Nerd Font icons and emoji
+fn is_glyph(c: char) -> bool {
+    is_emoji_char(c)
+        || matches!(
+            u32::from(c),
+            0x2190..=0x21FF | 0x2500..=0x25FF | 0x2800..=0x28FF | 0xE000..=0xF8FF | 0xF0000..
+        )
+}
+
+/// Rewrites the glyphs of a drawn frame to their ASCII fallbacks
+pub fn asciify_buffer(buffer: &mut Buffer) {
+    for cell in &mut buffer.content {
+        let symbol = cell.symbol();
+        if !symbol.is_ascii() {
+            let replacement = ascii_fallback(symbol).to_string();
+            cell.set_symbol(&replacement);
+        }
+    }
+}
+
+/// Replaces the glyphs of a string with ASCII fallbacks
+pub fn asciify(text: &str) -> String {
+    let mut buffer = [0; 4];
+    text.chars()
+        .map(|c| ascii_fallback(c.encode_utf8(&mut buffer)).to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn detect(vars: &[(&str, &str)], windows: bool) -> TerminalCapabilities {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(k, v)| ((*k).to_string(), (*v).to_string()))
+            .collect();
+        TerminalCapabilities::from_env(|key| vars.get(key).cloned(), windows)
+    }
+
+    #[test]
+    fn test_detection() {
+        assert!(detect(&[("LANG", "en_US.UTF-8")], false).unicode);
+        assert!(!detect(&[("LANG", "C")], false).unicode);
+        assert!(detect(&[], false).unicode);
+
+        // Legacy conhost vs Windows Terminal
+        assert!(!detect(&[], true).unicode);
+        assert!(detect(&[("WT_SESSION", "1")], true).unicode);
+
+        let dumb = detect(&[("TERM", "dumb")], false);
+        assert!(!dumb.unicode && !dumb.alternate_screen);
+
+        assert!(!detect(&[("GITAI_ASCII", "1"), ("LANG", "en_US.UTF-8")], false).unicode);
+        assert!(detect(&[("GITAI_ASCII", "0")], true).unicode);
+        assert!(!detect(&[("GITAI_ALT_SCREEN", "0")], false).alternate_screen);
+    }
+
+    #[test]
+    fn test_ascii_fallback() {
+        assert_eq!(asciify("󰁕 󰄲 file ━━ ← ✨"), "> x file -- <  ");
+        let mut buffer = Buffer::with_lines(["󰄱 a→b"]);
+        asciify_buffer(&mut buffer);
+        assert_eq!(buffer, Buffer::with_lines(["  a>b"]));
+
+        // Letters outside ASCII are text, not glyphs
+        assert_eq!(asciify("café → 日本"), "café > 日本");
+        let mut buffer = Buffer::with_lines(["ü ⠋"]);
+        asciify_buffer(&mut buffer);
+        assert_eq!(buffer, Buffer::with_lines(["ü  "]));
+    }
+}
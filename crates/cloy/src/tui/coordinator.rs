@@ -7,21 +7,27 @@ use super::renderer::draw_ui;
 use super::runtime::{ExitStatus, TerminalGuard, TuiRuntime};
 use super::spinner::SpinnerState;
 use super::state::{Mode, TuiState};
-use super::task_runner::TuiTaskRunner;
+use super::task_runner::{HistoryActionResult, TuiTaskRunner};
 use crate::commands::commit::{
     CommitService, completion::CompletionService, format_commit_result, types::GeneratedMessage,
 };
-use anyhow::{Error, Result};
+use crate::usage::Outcome;
+use anyhow::{Error, Result, anyhow};
 use crossterm::event::{EventStream, KeyEventKind};
 use futures::StreamExt;
 use std::io;
 use std::sync::Arc;
 use std::time::Duration;
 
+/// Number of commits listed in the history browser
+const HISTORY_SIZE: usize = 100;
+
 pub struct TuiCommit {
     pub state: TuiState,
     service: Arc<CommitService>,
     completion_service: Arc<CompletionService>,
+    /// Accept the message instead of committing, and leave the index and history alone
+    message_only: bool,
 }
 
 impl TuiCommit {
@@ -31,11 +37,14 @@ impl TuiCommit {
         service: Arc<CommitService>,
         completion_service: Arc<CompletionService>,
     ) -> Self {
-        let state = TuiState::new(initial_messages, custom_instructions);
+        let mut state = TuiState::new(initial_messages, custom_instructions);
+        state.set_wrap_column(service.wrap_column());
+        state.set_spell_check(service.spell_check());
         Self {
             state,
             service,
             completion_service,
+            message_only: false,
         }
     }
 
@@ -43,15 +52,27 @@ impl TuiCommit {
     pub async fn initialize_context(&mut self) -> Result<(), anyhow::Error> {
         let context = self.service.get_git_info().await?;
         self.state.initialize_context(context);
+        self.state
+            .set_unstaged_files(self.service.get_unstaged_files().unwrap_or_default());
         Ok(())
     }
 
+    /// Re-read context and unstaged files after the index or HEAD changed
+    async fn refresh_context(&mut self) -> Result<(), anyhow::Error> {
+        let context = self.service.refresh_git_info().await?;
+        let unstaged = self.service.get_unstaged_files()?;
+        self.state.refresh_context(context, unstaged);
+        Ok(())
+    }
+
+    /// Runs the TUI showing `initial_messages[initial_index]` first
     pub async fn run(
         initial_messages: Vec<GeneratedMessage>,
+        initial_index: usize,
         custom_instructions: String,
         service: Arc<CommitService>,
         completion_service: Arc<CompletionService>,
-        theme_mode: crate::common::ThemeMode,
+        theme: super::theme::Theme,
     ) -> Result<()> {
         let mut app = Self::new(
             initial_messages,
@@ -59,14 +80,45 @@ impl TuiCommit {
             service,
             completion_service,
         );
+        app.state.set_current_index(initial_index);
+        if let Err(e) = app.initialize_context().await {
+            log::warn!("Context initialization failed: {e}");
+        }
+        app.run_app(theme).await.map_err(Error::from)
+    }
+
+    /// Runs the TUI for the message alone, for `git commit` to use.
+    ///
+    /// Returns the accepted message, or `None` when the user cancelled.
+    pub async fn run_for_message(
+        initial_messages: Vec<GeneratedMessage>,
+        custom_instructions: String,
+        service: Arc<CommitService>,
+        completion_service: Arc<CompletionService>,
+        theme: super::theme::Theme,
+    ) -> Result<Option<String>> {
+        let mut app = Self::new(
+            initial_messages,
+            custom_instructions,
+            service,
+            completion_service,
+        );
+        app.message_only = true;
         if let Err(e) = app.initialize_context().await {
             log::warn!("Context initialization failed: {e}");
         }
-        app.run_app(theme_mode).await.map_err(Error::from)
+        let mut guard = TuiRuntime::setup_with_theme(theme)?;
+        let result = app.main_loop(&mut guard).await;
+        drop(guard);
+        match result? {
+            ExitStatus::Accepted(message) => Ok(Some(message)),
+            ExitStatus::Error(e) => Err(anyhow!(e)),
+            _ => Ok(None),
+        }
     }
 
-    pub async fn run_app(&mut self, theme_mode: crate::common::ThemeMode) -> io::Result<()> {
-        let mut guard = TuiRuntime::setup_with_theme(theme_mode)?;
+    pub async fn run_app(&mut self, theme: super::theme::Theme) -> io::Result<()> {
+        let mut guard = TuiRuntime::setup_with_theme(theme)?;
         let result = self.main_loop(&mut guard).await;
         drop(guard);
         Self::handle_exit_result(result)
@@ -77,12 +129,14 @@ impl TuiCommit {
             tokio::sync::mpsc::channel::<Result<GeneratedMessage, anyhow::Error>>(1);
         let (completion_tx, mut completion_rx) =
             tokio::sync::mpsc::channel::<Result<Vec<String>, anyhow::Error>>(1);
+        let (history_tx, mut history_rx) = tokio::sync::mpsc::channel::<HistoryActionResult>(1);
 
         let mut task_runner = TuiTaskRunner::new(
             self.service.clone(),
             self.completion_service.clone(),
             generation_tx,
             completion_tx,
+            history_tx,
         );
 
         let mut events = EventStream::new();
@@ -97,12 +151,35 @@ impl TuiCommit {
             if self.state.mode() == Mode::Generating && !task_runner.is_generation_spawned() {
                 let instructions = self.state.custom_instructions().to_string();
                 let filtered_context = self.state.get_filtered_context();
-                task_runner.spawn_generation_if_needed(true, instructions, filtered_context);
+                if let Some(feedback) = self.state.take_pending_refinement() {
+                    let previous = self.state.current_message().clone();
+                    task_runner.spawn_refinement_if_needed(
+                        instructions,
+                        previous,
+                        feedback,
+                        filtered_context,
+                    );
+                } else {
+                    task_runner.spawn_generation_if_needed(true, instructions, filtered_context);
+                }
             }
             if self.state.mode() != Mode::Generating && task_runner.is_generation_spawned() {
                 task_runner.reset_generation_flag();
             }
 
+            if self.state.mode() == Mode::History && !self.state.is_history_loaded() {
+                self.load_history();
+            }
+            if let Some(action) = self.state.take_pending_history_action()
+                && let Some(commit) = self.state.selected_history_commit()
+            {
+                task_runner.spawn_history_action(
+                    action,
+                    commit.hash.clone(),
+                    self.state.custom_instructions().to_string(),
+                );
+            }
+
             if let Some(prefix) = self.state.pending_completion_prefix().cloned()
                 && !task_runner.is_completion_spawned()
             {
@@ -114,6 +191,7 @@ impl TuiCommit {
                 .wait_for_events(
                     &mut generation_rx,
                     &mut completion_rx,
+                    &mut history_rx,
                     &mut events,
                     &mut ticker,
                 )
@@ -129,6 +207,7 @@ impl TuiCommit {
         &mut self,
         generation_rx: &mut tokio::sync::mpsc::Receiver<Result<GeneratedMessage, anyhow::Error>>,
         completion_rx: &mut tokio::sync::mpsc::Receiver<Result<Vec<String>, anyhow::Error>>,
+        history_rx: &mut tokio::sync::mpsc::Receiver<HistoryActionResult>,
         events: &mut EventStream,
         ticker: &mut tokio::time::Interval,
     ) -> Result<LoopResult> {
@@ -136,7 +215,7 @@ impl TuiCommit {
             biased;
 
             _ = ticker.tick() => {
-                if self.state.mode() == Mode::Generating
+                if (self.state.mode() == Mode::Generating || self.state.is_history_busy())
                     && let Some(spinner) = self.state.spinner_mut() {
                         spinner.tick();
                         self.state.set_dirty(true);
@@ -154,16 +233,57 @@ impl TuiCommit {
                 Ok(LoopResult::Continue)
             }
 
+            Some((action, result)) = history_rx.recv() => {
+                self.handle_history_result(action, result);
+                Ok(LoopResult::Continue)
+            }
+
             maybe_event = events.next() => {
                 if let Some(Ok(crossterm::event::Event::Key(key))) = maybe_event
                     && key.kind == KeyEventKind::Press {
                         let input_result = handle_input(&mut self.state, key);
+                        if self.message_only && changes_repository(&input_result) {
+                            self.state
+                                .set_status("Only the message can be changed while git waits for it");
+                            self.state.set_dirty(true);
+                            return Ok(LoopResult::Continue);
+                        }
                         match input_result {
-                            InputResult::Exit => Ok(LoopResult::Exit(ExitStatus::Cancelled)),
+                            InputResult::Exit => {
+                                self.record_usage(false);
+                                self.service
+                                    .save_draft(self.state.messages(), self.state.current_index());
+                                Ok(LoopResult::Exit(ExitStatus::Cancelled))
+                            }
                             InputResult::Commit(message) => {
                                 let status = self.perform_commit(&message);
                                 Ok(LoopResult::Exit(status))
                             },
+                            InputResult::Reword(message) => {
+                                let status = self.perform_reword(&message);
+                                Ok(LoopResult::Exit(status))
+                            },
+                            InputResult::Print(text) => Ok(LoopResult::Exit(ExitStatus::Printed(text))),
+                            InputResult::Stage(path) => {
+                                let result = self.service.stage_file(&path);
+                                self.finish_index_update(result, format!("Staged {path}")).await;
+                                Ok(LoopResult::Continue)
+                            }
+                            InputResult::StageHunks(path, hunks) => {
+                                let result = self.service.stage_hunks(&path, &hunks);
+                                let done = format!("Staged {} hunk(s) of {path}", hunks.len());
+                                self.finish_index_update(result, done).await;
+                                Ok(LoopResult::Continue)
+                            }
+                            InputResult::Unstage(path) => {
+                                let result = self.service.unstage_file(&path);
+                                self.finish_index_update(result, format!("Unstaged {path}")).await;
+                                Ok(LoopResult::Continue)
+                            }
+                            InputResult::Undo => {
+                                self.perform_undo().await;
+                                Ok(LoopResult::Continue)
+                            }
                             InputResult::Continue => {
                                 self.state.set_dirty(true);
                                 Ok(LoopResult::Continue)
@@ -183,9 +303,10 @@ impl TuiCommit {
                 self.state.set_mode(Mode::Normal);
                 self.state.set_spinner(None);
                 self.state.set_status(format!(
-                    "New message generated! Viewing {}/{}",
+                    "New message generated! Viewing {}/{}{}",
                     self.state.current_index() + 1,
-                    self.state.messages().len()
+                    self.state.messages().len(),
+                    self.state.typo_hint()
                 ));
             }
             Err(e) => {
@@ -198,6 +319,45 @@ impl TuiCommit {
         }
     }
 
+    fn load_history(&mut self) {
+        match self.service.get_recent_commits(HISTORY_SIZE) {
+            Ok(commits) => {
+                let count = commits.len();
+                self.state.set_history(commits);
+                if count == 0 {
+                    self.state
+                        .set_status("No commits yet. Press 'Esc' to go back.");
+                }
+            }
+            Err(e) => {
+                // Mark as loaded so a failing repository is not retried every frame
+                self.state.set_history(Vec::new());
+                self.state
+                    .set_status(format!("Failed to load history: {e}"));
+            }
+        }
+    }
+
+    fn handle_history_result(
+        &mut self,
+        action: super::state::HistoryAction,
+        result: Result<GeneratedMessage, anyhow::Error>,
+    ) {
+        match result {
+            Ok(message) => {
+                self.state.finish_history_action(Some((action, message)));
+                self.state.set_status(format!(
+                    "{} ready. 'Enter' to apply, 'Esc' to dismiss.",
+                    action.label()
+                ));
+            }
+            Err(e) => {
+                self.state.finish_history_action(None);
+                self.state.set_status(format!("Failed: {e}"));
+            }
+        }
+    }
+
     fn handle_completion_result(&mut self, result: Result<Vec<String>, anyhow::Error>) {
         match result {
             Ok(suggestions) => {
@@ -222,6 +382,7 @@ impl TuiCommit {
                 ExitStatus::Error(error_message) => {
                     eprintln!("An error occurred: {error_message}");
                 }
+                ExitStatus::Printed(text) | ExitStatus::Accepted(text) => println!("{text}"),
             },
             Err(e) => {
                 eprintln!("An unexpected error occurred: {e}");
@@ -232,8 +393,15 @@ impl TuiCommit {
     }
 
     fn perform_commit(&self, message: &str) -> ExitStatus {
+        if self.message_only {
+            self.record_usage(true);
+            self.service.discard_draft();
+            return ExitStatus::Accepted(message.to_string());
+        }
         match self.service.perform_commit(message, false, None) {
             Ok(result) => {
+                self.record_usage(true);
+                self.service.discard_draft();
                 let output = format_commit_result(&result, message);
                 ExitStatus::Committed(output)
             }
@@ -241,6 +409,54 @@ impl TuiCommit {
         }
     }
 
+    fn record_usage(&self, committed: bool) {
+        let regenerations = self.state.regenerations();
+        let outcome = Outcome::classify(committed, self.state.is_edited(), regenerations);
+        self.service.record_usage(outcome, regenerations);
+    }
+
+    fn perform_reword(&self, message: &str) -> ExitStatus {
+        match self.service.reword_head(message) {
+            Ok(result) => ExitStatus::Committed(format_commit_result(&result, message)),
+            Err(e) => ExitStatus::Error(e.to_string()),
+        }
+    }
+
+    /// Refresh the context selection after staging, or report why it failed
+    async fn finish_index_update(&mut self, result: Result<()>, done: String) {
+        let result = match result {
+            Ok(()) => self.refresh_context().await,
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(()) => self.state.set_status(format!(
+                "{done}. Press 'R' to regenerate with the new changes."
+            )),
+            Err(e) => self
+                .state
+                .set_status(format!("Failed to update index: {e}")),
+        }
+        self.state.set_dirty(true);
+    }
+
+    /// Soft-reset the last gitai commit and refresh history and context
+    async fn perform_undo(&mut self) {
+        match self.service.undo_last_commit() {
+            Ok(result) => {
+                self.load_history();
+                if let Err(e) = self.refresh_context().await {
+                    log::warn!("Context refresh after undo failed: {e}");
+                }
+                self.state.set_status(format!(
+                    "Undid {} \"{}\". Its changes are staged again.",
+                    result.undone, result.subject
+                ));
+            }
+            Err(e) => self.state.set_status(format!("Undo failed: {e}")),
+        }
+        self.state.set_dirty(true);
+    }
+
     pub fn handle_regenerate(&mut self) {
         self.state.set_mode(Mode::Generating);
         self.state.set_spinner(Some(SpinnerState::new()));
@@ -250,24 +466,57 @@ impl TuiCommit {
     }
 }
 
+/// Whether an input would touch the index or history rather than the message
+fn changes_repository(input: &InputResult) -> bool {
+    matches!(
+        input,
+        InputResult::Reword(_)
+            | InputResult::Stage(_)
+            | InputResult::StageHunks(..)
+            | InputResult::Unstage(_)
+            | InputResult::Undo
+    )
+}
+
 enum LoopResult {
     Continue,
     Exit(ExitStatus),
 }
 
+/// Runs the commit TUI; see [`TuiCommit::run`]
 pub async fn run_tui_commit(
     initial_messages: Vec<GeneratedMessage>,
+    initial_index: usize,
     custom_instructions: String,
     service: Arc<CommitService>,
     completion_service: Arc<CompletionService>,
-    theme_mode: crate::common::ThemeMode,
+    theme: super::theme::Theme,
 ) -> Result<()> {
     TuiCommit::run(
         initial_messages,
+        initial_index,
         custom_instructions,
         service,
         completion_service,
-        theme_mode,
+        theme,
+    )
+    .await
+}
+
+/// Runs the TUI for the message alone; see [`TuiCommit::run_for_message`]
+pub async fn run_tui_message(
+    initial_messages: Vec<GeneratedMessage>,
+    custom_instructions: String,
+    service: Arc<CommitService>,
+    completion_service: Arc<CompletionService>,
+    theme: super::theme::Theme,
+) -> Result<Option<String>> {
+    TuiCommit::run_for_message(
+        initial_messages,
+        custom_instructions,
+        service,
+        completion_service,
+        theme,
     )
     .await
 }
@@ -305,6 +554,31 @@ mod tests {
         assert_eq!(state.messages()[1].title, "Second commit");
     }
 
+    #[test]
+    fn test_usage_tracks_regenerations_and_edits() {
+        let mut state = TuiState::new(
+            vec![GeneratedMessage {
+                title: "First commit".to_string(),
+                message: "First message".to_string(),
+            }],
+            "test instructions".to_string(),
+        );
+        assert_eq!(state.regenerations(), 0);
+        assert!(!state.is_edited());
+
+        state.add_message(GeneratedMessage {
+            title: "New commit".to_string(),
+            message: "New message".to_string(),
+        });
+        assert_eq!(state.regenerations(), 1);
+        assert!(!state.is_edited());
+
+        state.messages_mut()[1].title = "Edited commit".to_string();
+        assert!(state.is_edited());
+        state.set_current_index(0);
+        assert!(!state.is_edited());
+    }
+
     #[test]
     fn test_regeneration_with_empty_messages() {
         let mut state = TuiState::new(vec![], "test instructions".to_string());
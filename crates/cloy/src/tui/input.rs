@@ -4,7 +4,7 @@
 //! They have no dependency on services, terminals, or async runtimes.
 
 use super::spinner::SpinnerState;
-use super::state::{Mode, TuiState};
+use super::state::{EditorField, HistoryAction, Mode, TuiState};
 
 /// Result of processing a single input event.
 #[derive(Debug, PartialEq, Eq)]
@@ -12,6 +12,18 @@ pub enum InputResult {
     Continue,
     Exit,
     Commit(String),
+    /// Replace the message of HEAD
+    Reword(String),
+    /// Leave the TUI and print the text
+    Print(String),
+    /// Add a working tree file to the index
+    Stage(String),
+    /// Add only the listed hunks of a file to the index
+    StageHunks(String, Vec<usize>),
+    /// Reset a file's index entry to HEAD
+    Unstage(String),
+    /// Soft-reset the last commit made by gitai
+    Undo,
 }
 
 /// Dispatch a key event to the appropriate handler for the current mode.
@@ -24,6 +36,8 @@ pub fn handle_input(state: &mut TuiState, key: crossterm::event::KeyEvent) -> In
         Mode::Help => handle_help_mode(state, key),
         Mode::Completing => handle_completing_mode(state, key),
         Mode::ContextSelection => handle_context_selection_mode(state, key),
+        Mode::EditingFeedback => handle_editing_feedback_mode(state, key),
+        Mode::History => handle_history_mode(state, key),
     }
 }
 
@@ -61,6 +75,24 @@ fn handle_normal_mode(state: &mut TuiState, key: crossterm::event::KeyEvent) ->
             state.set_dirty(true);
             InputResult::Continue
         }
+        KeyCode::Char('H') => {
+            state.set_mode(Mode::History);
+            state.set_status(
+                "History: 'r' reword, 'x' explain, 'p' PR draft, 'Enter' apply, 'Esc' back",
+            );
+            InputResult::Continue
+        }
+        KeyCode::Char('F') => {
+            state.set_mode(Mode::EditingFeedback);
+            state.set_status(
+                "Describe how to refine the message... 'Enter' to refine, 'Esc' to cancel",
+            );
+            InputResult::Continue
+        }
+        KeyCode::Char('S') => {
+            fix_typos(state);
+            InputResult::Continue
+        }
         KeyCode::Char('?') => {
             state.set_mode(Mode::Help);
             InputResult::Continue
@@ -75,28 +107,30 @@ fn handle_normal_mode(state: &mut TuiState, key: crossterm::event::KeyEvent) ->
         KeyCode::Left => {
             state.previous_message();
             state.set_status(format!(
-                " Message {}/{}",
+                " Message {}/{}{}",
                 state.current_index() + 1,
-                state.messages().len()
+                state.messages().len(),
+                state.typo_hint()
             ));
             InputResult::Continue
         }
         KeyCode::Right => {
             state.next_message();
             state.set_status(format!(
-                " Message {}/{}",
+                " Message {}/{}{}",
                 state.current_index() + 1,
-                state.messages().len()
+                state.messages().len(),
+                state.typo_hint()
             ));
             InputResult::Continue
         }
         KeyCode::Up => {
-            state.message_textarea_mut().scroll((-1, 0));
+            state.body_textarea_mut().scroll((-1, 0));
             state.set_dirty(true);
             InputResult::Continue
         }
         KeyCode::Down => {
-            state.message_textarea_mut().scroll((1, 0));
+            state.body_textarea_mut().scroll((1, 0));
             state.set_dirty(true);
             InputResult::Continue
         }
@@ -104,6 +138,19 @@ fn handle_normal_mode(state: &mut TuiState, key: crossterm::event::KeyEvent) ->
     }
 }
 
+fn fix_typos(state: &mut TuiState) {
+    let typos: Vec<String> = state
+        .typos()
+        .iter()
+        .map(|typo| format!("{} → {}", typo.word, typo.suggestion))
+        .collect();
+    if state.fix_typos() == 0 {
+        state.set_status(" No typos flagged in this message.");
+    } else {
+        state.set_status(format!(" Fixed {}", typos.join(", ")));
+    }
+}
+
 fn handle_editing_message_mode(
     state: &mut TuiState,
     key: crossterm::event::KeyEvent,
@@ -113,12 +160,27 @@ fn handle_editing_message_mode(
         KeyCode::Esc => {
             state.set_mode(Mode::Normal);
             state.update_current_message_from_textarea();
-            state.set_status(" Edited message saved. Press 'Enter' to commit.");
+            state.set_status(format!(
+                " Edited message saved. Press 'Enter' to commit.{}",
+                state.typo_hint()
+            ));
+            InputResult::Continue
+        }
+        // The subject is a single line; Enter moves on to the body
+        KeyCode::Enter | KeyCode::Down if state.editor_field() == EditorField::Subject => {
+            state.focus_field(EditorField::Body);
+            InputResult::Continue
+        }
+        KeyCode::Up
+            if state.editor_field() == EditorField::Body
+                && state.body_textarea().cursor().0 == 0 =>
+        {
+            state.focus_field(EditorField::Subject);
             InputResult::Continue
         }
         KeyCode::Tab => {
-            let (row, col) = state.message_textarea().cursor();
-            let lines = state.message_textarea().lines();
+            let (row, col) = state.focused_textarea().cursor();
+            let lines = state.focused_textarea().lines();
             if row < lines.len() {
                 let line = &lines[row];
                 if col <= line.len() {
@@ -134,8 +196,10 @@ fn handle_editing_message_mode(
             InputResult::Continue
         }
         _ => {
-            state.message_textarea_mut().input(key);
-            state.set_dirty(true);
+            state.focused_textarea_mut().input(key);
+            if state.editor_field() == EditorField::Body {
+                state.wrap_body();
+            }
             InputResult::Continue
         }
     }
@@ -158,6 +222,97 @@ fn handle_editing_instructions_mode(
     }
 }
 
+fn handle_editing_feedback_mode(
+    state: &mut TuiState,
+    key: crossterm::event::KeyEvent,
+) -> InputResult {
+    use crossterm::event::KeyCode;
+    match key.code {
+        KeyCode::Esc => {
+            state.cancel_feedback();
+            state.set_mode(Mode::Normal);
+            state.set_status("Refinement cancelled.");
+            InputResult::Continue
+        }
+        KeyCode::Enter => {
+            if state.submit_feedback() {
+                state.set_mode(Mode::Generating);
+                state.set_spinner(Some(SpinnerState::new()));
+                state.set_status("Refining commit message...");
+            } else {
+                state.set_mode(Mode::Normal);
+                state.set_status("No feedback given. Press 'F' to try again.");
+            }
+            InputResult::Continue
+        }
+        _ => {
+            state.feedback_textarea_mut().input(key);
+            state.set_dirty(true);
+            InputResult::Continue
+        }
+    }
+}
+
+fn handle_history_mode(state: &mut TuiState, key: crossterm::event::KeyEvent) -> InputResult {
+    use crossterm::event::KeyCode;
+    if state.is_history_busy() {
+        return InputResult::Continue;
+    }
+
+    let action = match key.code {
+        KeyCode::Esc | KeyCode::Char('q') => {
+            if !state.clear_history_result() {
+                state.set_mode(Mode::Normal);
+                state.set_status("Press '?': help | 'Esc': exit");
+            }
+            return InputResult::Continue;
+        }
+        KeyCode::Up | KeyCode::Char('k') => {
+            state.move_history_up();
+            return InputResult::Continue;
+        }
+        KeyCode::Down | KeyCode::Char('j') => {
+            state.move_history_down();
+            return InputResult::Continue;
+        }
+        KeyCode::Enter => return apply_history_result(state),
+        KeyCode::Char('u') => return InputResult::Undo,
+        KeyCode::Char('r') => HistoryAction::Reword,
+        KeyCode::Char('x') => HistoryAction::Explain,
+        KeyCode::Char('p') => HistoryAction::PullRequest,
+        _ => return InputResult::Continue,
+    };
+
+    if state.request_history_action(action) {
+        state.set_status(match action {
+            HistoryAction::Reword => "Generating a new message for the commit...",
+            HistoryAction::Explain => "Explaining the commit...",
+            HistoryAction::PullRequest => "Drafting a pull request description...",
+        });
+        // set_status clears the spinner; restore it for the running action
+        state.set_spinner(Some(SpinnerState::new()));
+    }
+    InputResult::Continue
+}
+
+fn apply_history_result(state: &mut TuiState) -> InputResult {
+    let Some((action, message)) = state.history_result().cloned() else {
+        return InputResult::Continue;
+    };
+    let text = format!("{}\n\n{}", message.title, message.message);
+    match action {
+        HistoryAction::Reword if state.history_index() == 0 => InputResult::Reword(text),
+        HistoryAction::Reword => {
+            state.set_status(
+                "Warning: only HEAD can be reworded here; use an interactive rebase for older commits.",
+            );
+            InputResult::Continue
+        }
+        HistoryAction::PullRequest => InputResult::Print(text),
+        HistoryAction::Explain => InputResult::Continue,
+    }
+}
+
 fn handle_help_mode(state: &mut TuiState, _key: crossterm::event::KeyEvent) -> InputResult {
     state.set_mode(Mode::Normal);
     state.set_status("Press '?': help | 'Esc': exit");
@@ -176,7 +331,7 @@ fn handle_completing_mode(state: &mut TuiState, key: crossterm::event::KeyEvent)
         KeyCode::Enter => {
             if !state.completion_suggestions().is_empty() {
                 let suggestion = state.completion_suggestions()[state.completion_index()].clone();
-                state.message_textarea_mut().insert_str(&suggestion);
+                state.focused_textarea_mut().insert_str(&suggestion);
                 state.set_completion_suggestions(Vec::new());
                 state.set_mode(Mode::EditingMessage);
                 state.set_status(" Completion applied.");
@@ -195,11 +350,17 @@ fn handle_completing_mode(state: &mut TuiState, key: crossterm::event::KeyEvent)
     }
 }
 
+/// Lines moved by `PgUp`/`PgDn` in the preview pane
+const PREVIEW_PAGE: isize = 20;
+
 fn handle_context_selection_mode(
     state: &mut TuiState,
     key: crossterm::event::KeyEvent,
 ) -> InputResult {
     use crossterm::event::KeyCode;
+    if state.preview_search_input().is_some() {
+        return handle_preview_search_input(state, key);
+    }
     match key.code {
         KeyCode::Esc => {
             state.set_mode(Mode::Normal);
@@ -227,6 +388,101 @@ fn handle_context_selection_mode(
             state.toggle_current_selection();
             InputResult::Continue
         }
+        KeyCode::Char(']') => {
+            state.jump_to_hunk(true);
+            InputResult::Continue
+        }
+        KeyCode::Char('[') => {
+            state.jump_to_hunk(false);
+            InputResult::Continue
+        }
+        KeyCode::Char('s') => {
+            if let Some(file) = state.selected_unstaged_file() {
+                let path = file.path.clone();
+                return match state.partial_hunk_selection() {
+                    None => InputResult::Stage(path),
+                    Some(hunks) if hunks.is_empty() => {
+                        state.set_status("No hunks selected. Press 'Space' to include a hunk.");
+                        InputResult::Continue
+                    }
+                    Some(hunks) => InputResult::StageHunks(path, hunks),
+                };
+            }
+            state.set_status("Select an unstaged file to stage it.");
+            InputResult::Continue
+        }
+        KeyCode::Char('u') => {
+            if let Some(file) = state.selected_staged_file() {
+                return InputResult::Unstage(file.path.clone());
+            }
+            state.set_status("Select a staged file to unstage it.");
+            InputResult::Continue
+        }
+        KeyCode::PageDown => {
+            state.scroll_preview(PREVIEW_PAGE);
+            InputResult::Continue
+        }
+        KeyCode::PageUp => {
+            state.scroll_preview(-PREVIEW_PAGE);
+            InputResult::Continue
+        }
+        KeyCode::Char('j') => {
+            state.scroll_preview(1);
+            InputResult::Continue
+        }
+        KeyCode::Char('k') => {
+            state.scroll_preview(-1);
+            InputResult::Continue
+        }
+        KeyCode::Char('/') => {
+            state.start_preview_search();
+            state.set_status("Search preview: type a query, 'Enter' to search, 'Esc' to cancel");
+            InputResult::Continue
+        }
+        KeyCode::Char('n') => {
+            state.jump_to_match(true);
+            InputResult::Continue
+        }
+        KeyCode::Char('N') => {
+            state.jump_to_match(false);
+            InputResult::Continue
+        }
         _ => InputResult::Continue,
     }
 }
+
+fn handle_preview_search_input(
+    state: &mut TuiState,
+    key: crossterm::event::KeyEvent,
+) -> InputResult {
+    use crossterm::event::KeyCode;
+    match key.code {
+        KeyCode::Esc => {
+            state.cancel_preview_search();
+            state.set_status("Search cancelled.");
+        }
+        KeyCode::Enter => {
+            state.submit_preview_search();
+            let matches = state.preview_matches().len();
+            if state.preview_search().is_some() {
+                state.set_status(format!(
+                    " {matches} matching line(s). 'n'/'N' next/previous match"
+                ));
+            } else {
+                state.set_status("Search cleared.");
+            }
+        }
+        KeyCode::Backspace => {
+            if let Some(input) = state.preview_search_input_mut() {
+                input.pop();
+            }
+        }
+        KeyCode::Char(c) => {
+            if let Some(input) = state.preview_search_input_mut() {
+                input.push(c);
+            }
+        }
+        _ => {}
+    }
+    InputResult::Continue
+}
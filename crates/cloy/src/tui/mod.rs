@@ -12,9 +12,13 @@
 //! - `renderer` - Pure UI rendering
 //! - [`TuiState`] - Pure UI model (state management)
 //! - `input` - Key event dispatch and mode-specific handlers
+//! - `capabilities` - Unicode and alternate screen detection with ASCII fallbacks
+//! - `plain` - Sequential prompt flow used instead of the TUI in plain mode
 
+pub mod capabilities;
 mod coordinator;
 mod input;
+mod plain;
 mod renderer;
 mod runtime;
 pub mod spinner;
@@ -23,7 +27,8 @@ mod task_runner;
 pub mod theme;
 
 pub use coordinator::TuiCommit;
-pub use coordinator::run_tui_commit;
+pub use coordinator::{run_tui_commit, run_tui_message};
+pub use plain::run_plain_commit;
 pub use runtime::{ExitStatus, TuiRuntime};
 pub use state::TuiState;
 pub use theme::Theme;
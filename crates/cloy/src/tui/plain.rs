@@ -0,0 +1,193 @@
+//! Plain commit flow for screen readers and dumb terminals.
+//!
+//! Instead of a full-screen interface, the message is printed once and the
+//! user answers numbered prompts on stdin. Output is strictly sequential, so
+//! nothing is redrawn or cleared.
+
+use crate::commands::commit::types::{GeneratedMessage, format_commit_message};
+use crate::commands::commit::{CommitService, format_commit_result};
+use crate::usage::Outcome;
+use anyhow::Result;
+use std::io::{self, BufRead, Write};
+use std::sync::Arc;
+
+/// Choices offered after each message, in menu order
+const MENU: &[&str] = &[
+    "Commit with this message",
+    "Write a new message",
+    "Regenerate",
+    "Refine with feedback",
+    "Cancel",
+];
+
+#[derive(Debug, PartialEq, Eq)]
+enum Choice {
+    Commit,
+    Write,
+    Regenerate,
+    Refine,
+    Cancel,
+}
+
+fn parse_choice(input: &str) -> Option<Choice> {
+    match input.trim().to_lowercase().as_str() {
+        "1" => Some(Choice::Commit),
+        "2" => Some(Choice::Write),
+        "3" => Some(Choice::Regenerate),
+        "4" => Some(Choice::Refine),
+        "5" | "q" => Some(Choice::Cancel),
+        _ => None,
+    }
+}
+
+/// Reads one line; `None` at end of input
+fn read_line(input: &mut impl BufRead) -> Result<Option<String>> {
+    let mut line = String::new();
+    if input.read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    Ok(Some(line.trim_end_matches(['\r', '\n']).to_string()))
+}
+
+/// Reads a message up to a line containing only `.`; the first line is the title
+fn read_message(input: &mut impl BufRead) -> Result<Option<GeneratedMessage>> {
+    let mut lines = Vec::new();
+    while let Some(line) = read_line(input)? {
+        if line == "." {
+            break;
+        }
+        lines.push(line);
+    }
+    let Some((title, body)) = lines.split_first() else {
+        return Ok(None);
+    };
+    if title.trim().is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(GeneratedMessage {
+        title: title.trim().to_string(),
+        message: body.join("\n").trim().to_string(),
+    }))
+}
+
+fn prompt(text: &str) -> Result<()> {
+    print!("{text}");
+    io::stdout().flush()?;
+    Ok(())
+}
+
+/// Runs the commit flow with numbered prompts.
+pub async fn run_plain_commit(
+    initial_message: GeneratedMessage,
+    instructions: String,
+    service: Arc<CommitService>,
+) -> Result<()> {
+    let mut current = initial_message;
+    // For the usage stats
+    let mut regenerations = 0;
+    let mut edited = false;
+
+    loop {
+        println!("\nCommit message:\n");
+        println!("{}\n", format_commit_message(&current));
+        for (i, item) in MENU.iter().enumerate() {
+            println!("{}. {item}", i + 1);
+        }
+        prompt(&format!("Choose 1-{}: ", MENU.len()))?;
+
+        let Some(line) = read_line(&mut io::stdin().lock())? else {
+            service.record_usage(Outcome::Cancelled, regenerations);
+            service.save_draft(std::slice::from_ref(&current), 0);
+            println!("\nCommit operation cancelled. Your changes remain staged.");
+            return Ok(());
+        };
+
+        match parse_choice(&line) {
+            Some(Choice::Commit) => {
+                let message = format_commit_message(&current);
+                let result = service.perform_commit(&message, false, None)?;
+                service.record_usage(
+                    Outcome::classify(true, edited, regenerations),
+                    regenerations,
+                );
+                service.discard_draft();
+                println!("{}", format_commit_result(&result, &message));
+                return Ok(());
+            }
+            Some(Choice::Write) => {
+                println!(
+                    "Type the new message. The first line is the title. End with a line containing only a period."
+                );
+                match read_message(&mut io::stdin().lock())? {
+                    Some(message) => {
+                        current = message;
+                        edited = true;
+                    }
+                    None => println!("Empty message, keeping the previous one."),
+                }
+            }
+            Some(Choice::Regenerate) => {
+                println!("Generating a new message...");
+                match service.generate_message(&instructions).await {
+                    Ok(message) => {
+                        current = message;
+                        regenerations += 1;
+                    }
+                    Err(e) => println!("Generation failed: {e}"),
+                }
+            }
+            Some(Choice::Refine) => {
+                prompt("Feedback: ")?;
+                let feedback = read_line(&mut io::stdin().lock())?.unwrap_or_default();
+                if feedback.trim().is_empty() {
+                    println!("No feedback given, keeping the message.");
+                    continue;
+                }
+                println!("Refining the message...");
+                match service
+                    .refine_message_with_context(&instructions, &current, feedback.trim(), None)
+                    .await
+                {
+                    Ok(message) => {
+                        current = message;
+                        regenerations += 1;
+                    }
+                    Err(e) => println!("Refinement failed: {e}"),
+                }
+            }
+            Some(Choice::Cancel) => {
+                service.record_usage(Outcome::Cancelled, regenerations);
+                service.save_draft(std::slice::from_ref(&current), 0);
+                println!("Commit operation cancelled. Your changes remain staged.");
+                return Ok(());
+            }
+            None => println!("Please enter a number from 1 to {}.", MENU.len()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_choice_and_read_message() {
+        assert_eq!(parse_choice(" 1 "), Some(Choice::Commit));
+        assert_eq!(parse_choice("q"), Some(Choice::Cancel));
+        assert_eq!(parse_choice("9"), None);
+
+        let mut input: &[u8] = b"feat: add plain mode\n\nFor screen readers.\n.\nignored\n";
+        let message = read_message(&mut input)
+            .expect("Reading should succeed")
+            .expect("Message expected");
+        assert_eq!(message.title, "feat: add plain mode");
+        assert_eq!(message.message, "For screen readers.");
+
+        let mut empty: &[u8] = b".\n";
+        assert!(
+            read_message(&mut empty)
+                .expect("Reading should succeed")
+                .is_none()
+        );
+    }
+}
@@ -7,8 +7,10 @@
 
 #![allow(clippy::as_conversions)]
 
-use super::state::{Mode, TuiState};
+use super::capabilities::{asciify_buffer, capabilities};
+use super::state::{EditorField, Mode, SUBJECT_SOFT_LIMIT, SubjectLength, TuiState};
 use super::theme::get_theme;
+use crate::commands::commit::spelling::Typo;
 use ratatui::prelude::Stylize;
 use ratatui::{
     Frame,
@@ -57,6 +59,7 @@ fn selection_style() -> Style {
     Style::default()
         .bg(get_theme().selection_bg)
         .fg(get_theme().selection_fg)
+        .add_modifier(get_theme().selection_modifier)
 }
 fn success_color() -> Color {
     get_theme().state_success
@@ -84,6 +87,10 @@ pub fn draw_ui(f: &mut Frame, state: &mut TuiState) {
 
     let chunks = create_layout(f, state);
     render_sections(f, state, &chunks);
+
+    if !capabilities().unicode {
+        asciify_buffer(f.buffer_mut());
+    }
 }
 
 /// Creates dynamic layout based on visible sections
@@ -202,18 +209,33 @@ fn draw_header(f: &mut Frame, state: &TuiState, area: Rect) {
         ],
         Mode::ContextSelection => vec![
             ("SPACE", "Toggle"),
+            ("S/U", "Stage"),
+            ("[/]", "Hunk"),
             ("TAB", "Category"),
+            ("J/K", "Scroll"),
+            ("/", "Search"),
             ("ENTER", "Confirm"),
             ("ESC", "Cancel"),
         ],
         Mode::EditingMessage => vec![("TAB", "Complete"), ("ESC", "Save")],
         Mode::EditingInstructions => vec![("ESC", "Save")],
+        Mode::EditingFeedback => vec![("ENTER", "Refine"), ("ESC", "Cancel")],
+        Mode::History => vec![
+            ("r", "Reword"),
+            ("x", "Explain"),
+            ("p", "PR"),
+            ("u", "Undo"),
+            ("ENTER", "Apply"),
+            ("ESC", "Back"),
+        ],
         Mode::Help => vec![("ANY", "Close")],
         _ => vec![
             ("E", "Edit"),
             ("I", "Instructions"),
             ("C", "Context"),
             ("R", "Regenerate"),
+            ("F", "Refine"),
+            ("H", "History"),
             ("ENTER", "Commit"),
             ("?", "Help"),
         ],
@@ -221,11 +243,9 @@ fn draw_header(f: &mut Frame, state: &TuiState, area: Rect) {
 
     let mut nav_spans = Vec::new();
     for (key, desc) in nav_items {
-        let desc_lower = desc.to_lowercase();
-        let key_lower = key.to_lowercase();
-
-        // Expressive: if the key is a single char in the word, highlight it inline
-        if let Some(idx) = desc_lower.find(&key_lower).filter(|_| key.len() == 1) {
+        // Expressive: if the key is a single char in the word, highlight it inline.
+        // Match case-sensitively so the highlighted letter is the one to press.
+        if let Some(idx) = desc.find(key).filter(|_| key.len() == 1) {
             let (prefix, rest) = desc.split_at(idx);
             let (k, suffix) = rest.split_at(1);
 
@@ -303,10 +323,42 @@ fn draw_main_content(f: &mut Frame, state: &mut TuiState, area: Rect) {
         Mode::Help => draw_help(f, state, area),
         Mode::Completing => draw_completion(f, state, area),
         Mode::ContextSelection => draw_context_selection(f, state, area),
+        Mode::EditingFeedback => {
+            draw_commit_editor(f, state, area);
+            draw_feedback(f, state, area);
+        }
+        Mode::History => draw_history(f, state, area),
         _ => draw_commit_editor(f, state, area),
     }
 }
 
+fn draw_feedback(f: &mut Frame, state: &mut TuiState, area: Rect) {
+    let popup_area = centered_rect(area, 60, 25);
+    draw_shadow(f, popup_area);
+    f.render_widget(Clear, popup_area);
+
+    let block = Block::default()
+        .bg(background_surface())
+        .border_style(Style::default().fg(secondary_accent_color()))
+        .borders(ratatui::widgets::Borders::ALL)
+        .padding(ratatui::widgets::Padding::new(1, 1, 0, 0))
+        .title(Span::styled(
+            " REFINE MESSAGE ",
+            Style::default()
+                .fg(secondary_accent_color())
+                .add_modifier(font_weight_bold()),
+        ));
+
+    state.feedback_textarea_mut().set_block(block);
+    state
+        .feedback_textarea_mut()
+        .set_cursor_style(Style::default().bg(component_focus()).fg(text_on_accent()));
+    state
+        .feedback_textarea_mut()
+        .set_style(Style::default().fg(text_color()));
+    f.render_widget(state.feedback_textarea(), popup_area);
+}
+
 fn draw_commit_editor(f: &mut Frame, state: &mut TuiState, area: Rect) {
     let is_editing = state.mode() == Mode::EditingMessage;
 
@@ -322,21 +374,103 @@ fn draw_commit_editor(f: &mut Frame, state: &mut TuiState, area: Rect) {
         .padding(ratatui::widgets::Padding::new(2, 2, 1, 1));
 
     if is_editing {
-        state.message_textarea_mut().set_block(block);
-        state
-            .message_textarea_mut()
-            .set_cursor_style(Style::default().bg(component_focus()).fg(text_on_accent()));
-        f.render_widget(state.message_textarea(), area);
+        draw_message_fields(f, state, card_bg, area);
     } else {
         render_commit_message_content(f, state, block, area);
     }
 }
 
+/// Subject and body as separate fields, with a live subject length counter
+fn draw_message_fields(f: &mut Frame, state: &mut TuiState, card_bg: Color, area: Rect) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(3), Constraint::Min(1)])
+        .split(area);
+
+    let subject_length = state.edited_subject().chars().count();
+    let counter_color = match SubjectLength::of(&state.edited_subject()) {
+        SubjectLength::Fits => subtle_color(),
+        SubjectLength::PastSoftLimit => warning_color(),
+        SubjectLength::PastHardLimit => error_color(),
+    };
+    let field_title = |label: &'static str, field: EditorField| {
+        let color = if state.editor_field() == field {
+            accent_color()
+        } else {
+            subtle_color()
+        };
+        Span::styled(
+            label,
+            Style::default().fg(color).add_modifier(font_weight_bold()),
+        )
+    };
+    let subject_title = Line::from(vec![
+        field_title("SUBJECT ", EditorField::Subject),
+        Span::styled(
+            format!("{subject_length}/{SUBJECT_SOFT_LIMIT}"),
+            Style::default().fg(counter_color),
+        ),
+    ]);
+    let wrap = match state.wrap_column() {
+        0 => String::new(),
+        column => format!("wraps at {column}"),
+    };
+    let body_title = Line::from(vec![
+        field_title("BODY ", EditorField::Body),
+        Span::styled(wrap, Style::default().fg(subtle_color())),
+    ]);
+
+    let focused = state.editor_field();
+    let cursor_style = |field: EditorField| {
+        if field == focused {
+            Style::default().bg(component_focus()).fg(text_on_accent())
+        } else {
+            Style::default()
+        }
+    };
+    let subject = state.subject_textarea_mut();
+    subject.set_block(
+        Block::default()
+            .bg(card_bg)
+            .title(subject_title)
+            .padding(ratatui::widgets::Padding::new(2, 2, 1, 0)),
+    );
+    subject.set_cursor_style(cursor_style(EditorField::Subject));
+    let body = state.body_textarea_mut();
+    body.set_block(
+        Block::default()
+            .bg(card_bg)
+            .title(body_title)
+            .padding(ratatui::widgets::Padding::new(2, 2, 1, 1)),
+    );
+    body.set_cursor_style(cursor_style(EditorField::Body));
+
+    f.render_widget(state.subject_textarea(), chunks[0]);
+    f.render_widget(state.body_textarea(), chunks[1]);
+}
+
+/// `text` as spans in `style`, with the misspelled words on `line` underlined
+fn spans_with_typos<'a>(text: &'a str, line: usize, typos: &[Typo], style: Style) -> Vec<Span<'a>> {
+    let mut spans = Vec::new();
+    let mut end = 0;
+    for typo in typos.iter().filter(|typo| typo.line == line) {
+        spans.push(Span::styled(&text[end..typo.range.start], style));
+        spans.push(Span::styled(
+            &text[typo.range.clone()],
+            style.fg(warning_color()).add_modifier(Modifier::UNDERLINED),
+        ));
+        end = typo.range.end;
+    }
+    spans.push(Span::styled(&text[end..], style));
+    spans
+}
+
 fn render_commit_message_content(f: &mut Frame, state: &TuiState, block: Block, area: Rect) {
     let current_message = state.current_message();
+    let typos = state.typos();
 
     // Expressive Title
-    let title_text = Line::from(vec![
+    let mut title_spans = vec![
         Span::styled(" 󰜘 ", Style::default().fg(accent_color())),
         Span::styled(
             "COMMIT MESSAGE ",
@@ -345,13 +479,16 @@ fn render_commit_message_content(f: &mut Frame, state: &TuiState, block: Block,
                 .add_modifier(font_weight_bold()),
         ),
         Span::raw(" "),
-        Span::styled(
-            &current_message.title,
-            Style::default()
-                .fg(brand_color())
-                .add_modifier(font_weight_bold()),
-        ),
-    ]);
+    ];
+    title_spans.extend(spans_with_typos(
+        &current_message.title,
+        0,
+        &typos.title,
+        Style::default()
+            .fg(brand_color())
+            .add_modifier(font_weight_bold()),
+    ));
+    let title_text = Line::from(title_spans);
 
     let mut content = vec![
         title_text,
@@ -362,11 +499,18 @@ fn render_commit_message_content(f: &mut Frame, state: &TuiState, block: Block,
         Line::from(""), // Spacing
     ];
 
-    for line in current_message.message.lines() {
-        content.push(Line::from(vec![
-            Span::styled("  │ ", Style::default().fg(background_overlay())),
-            Span::styled(line, Style::default().fg(text_color())),
-        ]));
+    for (index, line) in current_message.message.lines().enumerate() {
+        let mut spans = vec![Span::styled(
+            "  │ ",
+            Style::default().fg(background_overlay()),
+        )];
+        spans.extend(spans_with_typos(
+            line,
+            index,
+            &typos.body,
+            Style::default().fg(text_color()),
+        ));
+        content.push(Line::from(spans));
     }
 
     let message = Paragraph::new(content)
@@ -545,58 +689,63 @@ fn draw_help(f: &mut Frame, _state: &mut TuiState, area: Rect) {
             ),
             Span::styled("━".repeat(10), Style::default().fg(background_overlay())),
         ]),
-        Line::from(vec![
-            Span::styled("  ← / →     ", Style::default().fg(component_focus())),
-            Span::styled(
-                "Cycle through generated messages",
-                Style::default().fg(text_color()),
-            ),
-        ]),
-        Line::from(vec![
-            Span::styled("  ↑ / ↓     ", Style::default().fg(component_focus())),
-            Span::styled("Scroll content", Style::default().fg(text_color())),
-        ]),
+        help_entry(
+            "  ← / →     ",
+            "Cycle through generated messages",
+            component_focus(),
+        ),
+        help_entry("  ↑ / ↓     ", "Scroll content", component_focus()),
+        help_entry(
+            "  j / k     ",
+            "Scroll context preview (PgUp/PgDn by page)",
+            component_focus(),
+        ),
+        help_entry(
+            "  / n N     ",
+            "Search preview, next/previous match",
+            component_focus(),
+        ),
         Line::from(""),
         // Editing Section
         Line::from(vec![
             Span::styled("󰏫 Editing ", Style::default().fg(secondary_accent_color())),
             Span::styled("━".repeat(10), Style::default().fg(background_overlay())),
         ]),
-        Line::from(vec![
-            Span::styled("  e         ", Style::default().fg(component_focus())),
-            Span::styled("Edit current message", Style::default().fg(text_color())),
-        ]),
-        Line::from(vec![
-            Span::styled("  i         ", Style::default().fg(component_focus())),
-            Span::styled("Edit instructions", Style::default().fg(text_color())),
-        ]),
-        Line::from(vec![
-            Span::styled("  TAB       ", Style::default().fg(component_focus())),
-            Span::styled("AI Completion", Style::default().fg(text_color())),
-        ]),
+        help_entry("  e         ", "Edit current message", component_focus()),
+        help_entry("  i         ", "Edit instructions", component_focus()),
+        help_entry("  S         ", "Fix flagged typos", component_focus()),
+        help_entry("  TAB       ", "AI Completion", component_focus()),
         Line::from(""),
         // Actions Section
         Line::from(vec![
             Span::styled("󰀘 Actions ", Style::default().fg(secondary_accent_color())),
             Span::styled("━".repeat(10), Style::default().fg(background_overlay())),
         ]),
-        Line::from(vec![
-            Span::styled("  c         ", Style::default().fg(component_focus())),
-            Span::styled("Manage context", Style::default().fg(text_color())),
-        ]),
-        Line::from(vec![
-            Span::styled("  r         ", Style::default().fg(component_focus())),
-            Span::styled("Regenerate", Style::default().fg(text_color())),
-        ]),
-        Line::from(vec![
-            Span::styled("  ENTER     ", Style::default().fg(success_color())),
-            Span::styled("Commit changes", Style::default().fg(text_color())),
-        ]),
+        help_entry("  c         ", "Manage context", component_focus()),
+        help_entry(
+            "  s / u     ",
+            "Stage / unstage the selected file (in context)",
+            component_focus(),
+        ),
+        help_entry(
+            "  [ / ]     ",
+            "Previous / next hunk; Space picks hunks to stage",
+            component_focus(),
+        ),
+        help_entry("  r         ", "Regenerate", component_focus()),
+        help_entry(
+            "  F         ",
+            "Refine with feedback (\"shorter\", \"more detail\", ...)",
+            component_focus(),
+        ),
+        help_entry(
+            "  H         ",
+            "Browse history: reword, explain, PR draft, undo (u)",
+            component_focus(),
+        ),
+        help_entry("  ENTER     ", "Commit changes", success_color()),
         Line::from(""),
-        Line::from(vec![
-            Span::styled("  ESC / q   ", Style::default().fg(error_color())),
-            Span::styled("Close / Exit", Style::default().fg(text_color())),
-        ]),
+        help_entry("  ESC / q   ", "Close / Exit", error_color()),
     ];
 
     let p = Paragraph::new(help_content)
@@ -606,6 +755,13 @@ fn draw_help(f: &mut Frame, _state: &mut TuiState, area: Rect) {
     f.render_widget(p, popup_area);
 }
 
+fn help_entry(key: &'static str, desc: &'static str, key_color: Color) -> Line<'static> {
+    Line::from(vec![
+        Span::styled(key, Style::default().fg(key_color)),
+        Span::styled(desc, Style::default().fg(text_color())),
+    ])
+}
+
 fn draw_completion(f: &mut Frame, state: &mut TuiState, area: Rect) {
     let popup_area = centered_rect(area, 40, 30);
     draw_shadow(f, popup_area);
@@ -660,6 +816,127 @@ fn draw_context_selection(f: &mut Frame, state: &mut TuiState, area: Rect) {
     draw_preview(f, state, chunks[1]);
 }
 
+fn draw_history(f: &mut Frame, state: &mut TuiState, area: Rect) {
+    let chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(40), Constraint::Percentage(60)])
+        .split(area);
+
+    draw_history_list(f, state, chunks[0]);
+    draw_history_detail(f, state, chunks[1]);
+}
+
+fn draw_history_list(f: &mut Frame, state: &TuiState, area: Rect) {
+    let block = Block::default()
+        .bg(background_base())
+        .padding(ratatui::widgets::Padding::new(1, 1, 1, 1));
+
+    let mut lines = vec![Line::from(vec![
+        Span::styled(
+            " 󰜘 HISTORY ",
+            Style::default()
+                .fg(secondary_accent_color())
+                .add_modifier(font_weight_bold()),
+        ),
+        Span::styled("━".repeat(10), Style::default().fg(background_overlay())),
+    ])];
+
+    // Keep the selection visible by starting the window above it
+    let visible = area.height.saturating_sub(3) as usize;
+    let start = state
+        .history_index()
+        .saturating_sub(visible.saturating_sub(1));
+
+    for (i, commit) in state.history().iter().enumerate().skip(start).take(visible) {
+        let is_current = i == state.history_index();
+        let style = if is_current {
+            selection_style()
+        } else {
+            Style::default().fg(text_color())
+        };
+        lines.push(Line::from(vec![
+            Span::styled(if is_current { " 󰁕 " } else { "   " }, style),
+            Span::styled(
+                commit.hash.get(..7).unwrap_or(&commit.hash),
+                Style::default().fg(subtle_color()),
+            ),
+            Span::raw(" "),
+            Span::styled(commit.message.lines().next().unwrap_or(""), style),
+        ]));
+    }
+
+    f.render_widget(Paragraph::new(lines).block(block), area);
+}
+
+fn draw_history_detail(f: &mut Frame, state: &TuiState, area: Rect) {
+    let block = Block::default()
+        .bg(background_surface())
+        .padding(ratatui::widgets::Padding::new(2, 2, 1, 1));
+
+    let Some(commit) = state.selected_history_commit() else {
+        f.render_widget(Paragraph::new(" No commits ").block(block), area);
+        return;
+    };
+
+    let date = commit
+        .timestamp
+        .parse::<i64>()
+        .ok()
+        .and_then(|secs| chrono::DateTime::from_timestamp(secs, 0))
+        .map_or_else(
+            || commit.timestamp.clone(),
+            |d| d.format("%Y-%m-%d %H:%M").to_string(),
+        );
+
+    let mut lines = vec![
+        Line::from(vec![
+            Span::styled("󰜘 ", Style::default().fg(secondary_accent_color())),
+            Span::styled(
+                &commit.hash,
+                Style::default().add_modifier(font_weight_bold()),
+            ),
+        ]),
+        Line::from(vec![
+            Span::styled("Date: ", Style::default().fg(subtle_color())),
+            Span::raw(date),
+        ]),
+        Line::from(Span::styled(
+            "━".repeat(area.width.saturating_sub(4) as usize),
+            Style::default().fg(background_overlay()),
+        )),
+    ];
+
+    let (heading, body) = match state.history_result() {
+        Some((action, message)) => (
+            Some(action.label()),
+            format!("{}\n\n{}", message.title, message.message),
+        ),
+        None => (None, commit.message.clone()),
+    };
+    if let Some(heading) = heading {
+        lines.push(Line::from(Span::styled(
+            format!("{} ", heading.to_uppercase()),
+            Style::default()
+                .fg(accent_color())
+                .add_modifier(font_weight_bold()),
+        )));
+        lines.push(Line::from(""));
+    }
+    lines.extend(body.lines().map(|l| {
+        Line::from(Span::styled(
+            l.to_string(),
+            Style::default().fg(text_color()),
+        ))
+    }));
+
+    f.render_widget(
+        Paragraph::new(lines)
+            .block(block)
+            .wrap(Wrap { trim: false }),
+        area,
+    );
+}
+
 fn draw_selection_list(f: &mut Frame, state: &mut TuiState, area: Rect) {
     let block = Block::default()
         .bg(background_base())
@@ -744,6 +1021,11 @@ fn draw_selection_list(f: &mut Frame, state: &mut TuiState, area: Rect) {
                 Span::styled(commit.message.lines().next().unwrap_or(""), style),
             ]));
         }
+
+        if !state.unstaged_files().is_empty() {
+            list_items.push(Line::from(""));
+            list_items.extend(unstaged_list_items(state));
+        }
     }
 
     let p = Paragraph::new(list_items)
@@ -753,58 +1035,50 @@ fn draw_selection_list(f: &mut Frame, state: &mut TuiState, area: Rect) {
     f.render_widget(p, area);
 }
 
+/// Unstaged section of the context list; `s` stages the file under the cursor
+fn unstaged_list_items(state: &TuiState) -> Vec<Line<'_>> {
+    let mut items = vec![Line::from(vec![
+        Span::styled(
+            " 󰏫 UNSTAGED ",
+            Style::default()
+                .fg(warning_color())
+                .add_modifier(font_weight_bold()),
+        ),
+        Span::styled("━".repeat(10), Style::default().fg(background_overlay())),
+    ])];
+
+    let current = state.selected_unstaged_file().map(|f| f.path.as_str());
+    for file in state.unstaged_files() {
+        let is_current = current == Some(file.path.as_str());
+        let style = if is_current {
+            selection_style()
+        } else {
+            Style::default().fg(subtle_color())
+        };
+        items.push(Line::from(vec![
+            Span::styled(if is_current { " 󰁕 " } else { "   " }, style),
+            Span::styled("󰐕 ", Style::default().fg(warning_color())),
+            Span::styled(&file.path, style),
+        ]));
+    }
+    items
+}
+
 fn draw_preview(f: &mut Frame, state: &mut TuiState, area: Rect) {
     let block = Block::default()
         .bg(background_surface())
         .padding(ratatui::widgets::Padding::new(2, 2, 1, 1));
 
-    if let Some(context) = state.context() {
-        if state.context_selection_category() == super::state::ContextSelectionCategory::Files {
-            if let Some(file) = context.staged_files.get(state.context_selection_index()) {
-                let mut lines = Vec::new();
-                lines.push(Line::from(vec![
-                    Span::styled("󰈔 ", Style::default().fg(accent_color())),
-                    Span::styled(
-                        &file.path,
-                        Style::default().add_modifier(font_weight_bold()),
-                    ),
-                ]));
-                lines.push(Line::from(vec![
-                    Span::styled("Type: ", Style::default().fg(subtle_color())),
-                    Span::raw(format!("{}", file.change_type)),
-                ]));
-                lines.push(Line::from(vec![Span::styled(
-                    "━".repeat(area.width.saturating_sub(4) as usize),
-                    Style::default().fg(background_overlay()),
-                )]));
-
-                for line in file.diff.lines().take(100) {
-                    let style = if line.starts_with('+') {
-                        Style::default().fg(success_color())
-                    } else if line.starts_with('-') {
-                        Style::default().fg(error_color())
-                    } else if line.starts_with('@') {
-                        Style::default().fg(info_color())
-                    } else {
-                        Style::default().fg(text_color())
-                    };
-                    lines.push(Line::from(Span::styled(line, style)));
-                }
+    // Padding (2) plus the three header lines
+    let viewport = area.height.saturating_sub(5) as usize;
 
-                f.render_widget(
-                    Paragraph::new(lines)
-                        .block(block)
-                        .wrap(Wrap { trim: false }),
-                    area,
-                );
-                return;
-            }
-        } else {
+    if let Some(context) = state.context() {
+        if state.context_selection_category() == super::state::ContextSelectionCategory::Commits {
             let commit_index = state
                 .context_selection_index()
                 .saturating_sub(context.staged_files.len());
             if let Some(commit) = context.recent_commits.get(commit_index) {
-                let lines = vec![
+                let mut lines = vec![
                     Line::from(vec![
                         Span::styled("󰜘 ", Style::default().fg(secondary_accent_color())),
                         Span::styled(
@@ -812,31 +1086,208 @@ fn draw_preview(f: &mut Frame, state: &mut TuiState, area: Rect) {
                             Style::default().add_modifier(font_weight_bold()),
                         ),
                     ]),
-                    Line::from(vec![
-                        Span::styled("Date: ", Style::default().fg(subtle_color())),
-                        Span::raw(&commit.timestamp),
-                    ]),
+                    preview_info_line(
+                        state,
+                        vec![
+                            Span::styled("Date: ", Style::default().fg(subtle_color())),
+                            Span::raw(&commit.timestamp),
+                        ],
+                        viewport,
+                    ),
                     Line::from(vec![Span::styled(
                         "━".repeat(area.width.saturating_sub(4) as usize),
                         Style::default().fg(background_overlay()),
                     )]),
-                    Line::from(Span::styled(
-                        &commit.message,
-                        Style::default().fg(text_color()),
-                    )),
                 ];
+                lines.extend(
+                    commit
+                        .message
+                        .lines()
+                        .skip(state.preview_scroll())
+                        .take(viewport)
+                        .map(|line| {
+                            highlight_matches(
+                                line,
+                                state.preview_search(),
+                                Style::default().fg(text_color()),
+                            )
+                        }),
+                );
                 f.render_widget(
                     Paragraph::new(lines).block(block).wrap(Wrap { trim: true }),
                     area,
                 );
                 return;
             }
+        } else if let Some(lines) = file_preview_lines(state, area, viewport) {
+            f.render_widget(
+                Paragraph::new(lines)
+                    .block(block)
+                    .wrap(Wrap { trim: false }),
+                area,
+            );
+            return;
         }
     }
 
     f.render_widget(Paragraph::new(" No selection ").block(block), area);
 }
 
+/// Header and visible diff lines for the staged or unstaged file under the cursor
+fn file_preview_lines(state: &TuiState, area: Rect, viewport: usize) -> Option<Vec<Line<'_>>> {
+    let staged = state.selected_staged_file();
+    let file = staged.or_else(|| state.selected_unstaged_file())?;
+
+    let mut lines = vec![
+        Line::from(vec![
+            Span::styled("󰈔 ", Style::default().fg(accent_color())),
+            Span::styled(
+                &file.path,
+                Style::default().add_modifier(font_weight_bold()),
+            ),
+        ]),
+        preview_info_line(
+            state,
+            vec![
+                Span::styled("Type: ", Style::default().fg(subtle_color())),
+                Span::raw(format!(
+                    "{}{}",
+                    file.change_type,
+                    unstaged_note(state, staged.is_some())
+                )),
+            ],
+            viewport,
+        ),
+        Line::from(vec![Span::styled(
+            "━".repeat(area.width.saturating_sub(4) as usize),
+            Style::default().fg(background_overlay()),
+        )]),
+    ];
+
+    // Unstaged hunk headers get a checkbox showing whether `s` will stage them
+    let hunks = if staged.is_some() {
+        Vec::new()
+    } else {
+        state.hunk_offsets()
+    };
+    lines.extend(
+        file.diff
+            .lines()
+            .enumerate()
+            .skip(state.preview_scroll())
+            .take(viewport)
+            .map(|(i, line)| {
+                let mut rendered =
+                    highlight_matches(line, state.preview_search(), diff_line_style(line));
+                if let Some(hunk) = hunks.iter().position(|&offset| offset == i) {
+                    let (checkbox, color) = if state.is_hunk_selected(hunk) {
+                        ("󰄲 ", success_color())
+                    } else {
+                        ("󰄱 ", subtle_color())
+                    };
+                    rendered
+                        .spans
+                        .insert(0, Span::styled(checkbox, Style::default().fg(color)));
+                }
+                rendered
+            }),
+    );
+    Some(lines)
+}
+
+/// Unstaged marker with the hunk selection, e.g. ` (unstaged, 2/3 hunks)`
+fn unstaged_note(state: &TuiState, staged: bool) -> String {
+    if staged {
+        return String::new();
+    }
+    let total = state.hunk_offsets().len();
+    if total == 0 {
+        return " (unstaged)".to_string();
+    }
+    let selected = (0..total).filter(|&h| state.is_hunk_selected(h)).count();
+    format!(" (unstaged, {selected}/{total} hunks)")
+}
+
+fn diff_line_style(line: &str) -> Style {
+    if line.starts_with('+') {
+        Style::default().fg(success_color())
+    } else if line.starts_with('-') {
+        Style::default().fg(error_color())
+    } else if line.starts_with('@') {
+        Style::default().fg(info_color())
+    } else {
+        Style::default().fg(text_color())
+    }
+}
+
+/// Appends the visible line range, match count and search prompt to a preview header
+fn preview_info_line<'a>(state: &TuiState, mut spans: Vec<Span<'a>>, viewport: usize) -> Line<'a> {
+    let total = state.preview_line_count();
+    let first = state.preview_scroll().min(total);
+    let last = (first + viewport).min(total);
+    spans.push(Span::styled(
+        format!("   Lines {}-{last} of {total}", (first + 1).min(total)),
+        Style::default().fg(subtle_color()),
+    ));
+
+    if let Some(input) = state.preview_search_input() {
+        spans.push(Span::styled(
+            format!("   /{input}▏"),
+            Style::default()
+                .fg(component_focus())
+                .add_modifier(font_weight_bold()),
+        ));
+    } else if let Some(query) = state.preview_search() {
+        let matches = state.preview_matches();
+        let current = matches
+            .iter()
+            .position(|&line| line == state.preview_scroll())
+            .map_or_else(|| "-".to_string(), |i| (i + 1).to_string());
+        spans.push(Span::styled(
+            format!("   /{query} [{current}/{}]", matches.len()),
+            Style::default().fg(warning_color()),
+        ));
+    }
+
+    Line::from(spans)
+}
+
+/// Splits a line into spans, highlighting case-insensitive occurrences of `query`
+fn highlight_matches<'a>(line: &'a str, query: Option<&str>, style: Style) -> Line<'a> {
+    let Some(query) = query.filter(|q| !q.is_empty()) else {
+        return Line::from(Span::styled(line, style));
+    };
+
+    let lower = line.to_lowercase();
+    let needle = query.to_lowercase();
+    // Lowercasing can change byte lengths for some scripts; fall back to no highlight
+    if lower.len() != line.len() {
+        return Line::from(Span::styled(line, style));
+    }
+
+    let highlight = Style::default()
+        .bg(warning_color())
+        .fg(text_on_accent())
+        .add_modifier(font_weight_bold());
+    let mut spans = Vec::new();
+    let mut cursor = 0;
+    for (start, _) in lower.match_indices(&needle) {
+        let end = start + needle.len();
+        if !line.is_char_boundary(start) || !line.is_char_boundary(end) {
+            continue;
+        }
+        if start > cursor {
+            spans.push(Span::styled(&line[cursor..start], style));
+        }
+        spans.push(Span::styled(&line[start..end], highlight));
+        cursor = end;
+    }
+    if cursor < line.len() {
+        spans.push(Span::styled(&line[cursor..], style));
+    }
+    Line::from(spans)
+}
+
 /// Helper to center a rect
 fn centered_rect(r: Rect, percent_x: u16, percent_y: u16) -> Rect {
     let popup_layout = Layout::default()
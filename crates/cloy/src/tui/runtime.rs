@@ -1,37 +1,52 @@
 //! Terminal lifecycle management for the TUI
 //!
 //! This module handles:
-//! - Terminal setup (raw mode, alternate screen, panic hook)
+//! - Terminal setup (raw mode, alternate screen when supported, panic hook)
 //! - Terminal cleanup (RAII-style guard for automatic restoration)
 //! - Theme initialization
 
-use crate::common::ThemeMode;
 use ratatui::{
     Terminal,
     backend::CrosstermBackend,
     crossterm::{
         execute,
-        terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
+        terminal::{
+            Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode,
+            enable_raw_mode,
+        },
     },
 };
 use std::{io, panic};
 
-use super::theme::init_theme;
+use super::capabilities::capabilities;
+use super::theme::{Theme, set_theme};
 
 /// RAII guard for terminal state
 ///
 /// Automatically restores terminal state when dropped:
 /// - Disables raw mode
-/// - Leaves alternate screen
+/// - Leaves alternate screen, or clears the main screen if it was not entered
 /// - Shows cursor
 pub struct TerminalGuard {
     terminal: Terminal<CrosstermBackend<io::Stdout>>,
+    alternate_screen: bool,
 }
 
 impl TerminalGuard {
     /// Create a new terminal guard wrapping an existing terminal
     pub fn new(terminal: Terminal<CrosstermBackend<io::Stdout>>) -> Self {
-        Self { terminal }
+        Self {
+            terminal,
+            alternate_screen: true,
+        }
+    }
+
+    /// Create a guard for a terminal drawn on the main screen
+    pub fn without_alternate_screen(terminal: Terminal<CrosstermBackend<io::Stdout>>) -> Self {
+        Self {
+            terminal,
+            alternate_screen: false,
+        }
     }
 
     /// Get mutable access to the underlying terminal
@@ -49,7 +64,11 @@ impl Drop for TerminalGuard {
     fn drop(&mut self) {
         // Restore terminal state on drop
         let _ = disable_raw_mode();
-        let _ = execute!(self.terminal.backend_mut(), LeaveAlternateScreen);
+        if self.alternate_screen {
+            let _ = execute!(self.terminal.backend_mut(), LeaveAlternateScreen);
+        } else {
+            let _ = execute!(self.terminal.backend_mut(), Clear(ClearType::All));
+        }
         let _ = self.terminal.show_cursor();
     }
 }
@@ -65,7 +84,7 @@ impl TuiRuntime {
     /// This performs:
     /// 1. Installs panic hook to restore terminal on panic
     /// 2. Enables raw mode
-    /// 3. Enters alternate screen
+    /// 3. Enters alternate screen if the terminal supports it, otherwise clears it
     /// 4. Creates Terminal with `CrosstermBackend`
     ///
     /// Returns a `TerminalGuard` that will automatically clean up on drop.
@@ -81,12 +100,21 @@ impl TuiRuntime {
         enable_raw_mode()?;
 
         // Enter alternate screen and create terminal
+        let alternate_screen = capabilities().alternate_screen;
         let mut stdout = io::stdout();
-        execute!(stdout, EnterAlternateScreen)?;
+        if alternate_screen {
+            execute!(stdout, EnterAlternateScreen)?;
+        } else {
+            execute!(stdout, Clear(ClearType::All))?;
+        }
         let backend = CrosstermBackend::new(stdout);
         let terminal = Terminal::new(backend)?;
 
-        Ok(TerminalGuard::new(terminal))
+        if alternate_screen {
+            Ok(TerminalGuard::new(terminal))
+        } else {
+            Ok(TerminalGuard::without_alternate_screen(terminal))
+        }
     }
 
     /// Initialize theme and prepare terminal for TUI operation
@@ -94,9 +122,8 @@ impl TuiRuntime {
     /// This performs setup only; caller is responsible for:
     /// - Running main loop
     /// - Dropping `TerminalGuard` for cleanup
-    pub fn setup_with_theme(theme_mode: ThemeMode) -> io::Result<TerminalGuard> {
-        // Initialize adaptive theme
-        init_theme(theme_mode);
+    pub fn setup_with_theme(theme: Theme) -> io::Result<TerminalGuard> {
+        set_theme(theme);
 
         // Setup terminal
         Self::setup()
@@ -111,6 +138,10 @@ pub enum ExitStatus {
     Cancelled,
     /// An error occurred during commit
     Error(String),
+    /// Output to print after leaving the TUI
+    Printed(String),
+    /// Message accepted in message-only mode, for the caller to write
+    Accepted(String),
 }
 
 #[cfg(test)]
@@ -1,4 +1,6 @@
+use super::capabilities::{ASCII_SPINNER_FRAMES, asciify, capabilities};
 use crate::llm::messages::{ColoredMessage, get_waiting_message};
+use crate::llm::rate_limit;
 use ratatui::style::Color;
 use unicode_width::UnicodeWidthStr;
 
@@ -38,17 +40,30 @@ impl SpinnerState {
     }
 
     pub fn tick(&mut self) -> (String, String, Color, usize) {
-        let frame = self.frames[self.current_frame];
-        self.current_frame = (self.current_frame + 1) % self.frames.len();
+        let unicode = capabilities().unicode;
+        let frames = if unicode {
+            self.frames.as_slice()
+        } else {
+            ASCII_SPINNER_FRAMES
+        };
+        let frame = frames[self.current_frame % frames.len()];
+        self.current_frame = (self.current_frame + 1) % frames.len();
 
+        // A rate-limit wait replaces the message until the request goes out
+        let status = rate_limit::status();
+        let (message, color) = status
+            .as_deref()
+            .map_or((self.message.text.as_str(), self.message.color), |s| {
+                (s, Color::Yellow)
+            });
+        let text = if unicode {
+            message.to_string()
+        } else {
+            asciify(message)
+        };
         let spinner_with_space = format!("{frame} ");
-        let width = spinner_with_space.width() + self.message.text.width();
+        let width = spinner_with_space.width() + text.width();
 
-        (
-            spinner_with_space,
-            self.message.text.clone(),
-            self.message.color,
-            width,
-        )
+        (spinner_with_space, text, color, width)
     }
 }
@@ -1,8 +1,17 @@
 use super::spinner::SpinnerState;
-use crate::commands::commit::types::{GeneratedMessage, format_commit_message};
-use crate::llm::context::CommitContext;
+use crate::commands::commit::spelling::{self, MessageTypos};
+use crate::commands::commit::types::GeneratedMessage;
+use crate::config::DEFAULT_WRAP_COLUMN;
+use crate::llm::context::{CommitContext, RecentCommit, StagedFile};
 
-use tui_textarea::TextArea;
+use tui_textarea::{CursorMove, TextArea};
+use unicode_width::UnicodeWidthStr;
+
+/// Subject length past which the editor warns
+pub const SUBJECT_SOFT_LIMIT: usize = 50;
+
+/// Subject length git tools truncate at; the editor shows it as an error
+pub const SUBJECT_HARD_LIMIT: usize = 72;
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum Mode {
@@ -13,12 +22,59 @@ pub enum Mode {
     Help,
     Completing,
     ContextSelection,
+    EditingFeedback,
+    History,
+}
+
+/// Part of the message the editor's cursor is in
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum EditorField {
+    Subject,
+    Body,
+}
+
+/// How the subject's length compares to the conventional limits
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum SubjectLength {
+    Fits,
+    PastSoftLimit,
+    PastHardLimit,
+}
+
+impl SubjectLength {
+    pub fn of(subject: &str) -> Self {
+        match subject.chars().count() {
+            n if n > SUBJECT_HARD_LIMIT => Self::PastHardLimit,
+            n if n > SUBJECT_SOFT_LIMIT => Self::PastSoftLimit,
+            _ => Self::Fits,
+        }
+    }
+}
+
+/// AI action run on a commit selected in the history browser
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum HistoryAction {
+    Reword,
+    Explain,
+    PullRequest,
+}
+
+impl HistoryAction {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Reword => "Reworded message",
+            Self::Explain => "Explanation",
+            Self::PullRequest => "Pull request draft",
+        }
+    }
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum ContextSelectionCategory {
     Files,
     Commits,
+    /// Working tree changes that can be staged from the TUI
+    Unstaged,
 }
 
 pub struct TuiState {
@@ -27,7 +83,12 @@ pub struct TuiState {
     custom_instructions: String,
     status: String,
     mode: Mode,
-    message_textarea: TextArea<'static>,
+    subject_textarea: TextArea<'static>,
+    body_textarea: TextArea<'static>,
+    editor_field: EditorField,
+    wrap_column: usize,
+    /// Misspellings in the current message; `None` when spell checking is off
+    typos: Option<MessageTypos>,
     instructions_textarea: TextArea<'static>,
     spinner: Option<SpinnerState>,
     dirty: bool,
@@ -37,17 +98,33 @@ pub struct TuiState {
     completion_suggestions: Vec<String>,
     completion_index: usize,
     pending_completion_prefix: Option<String>,
+    feedback_textarea: TextArea<'static>,
+    pending_refinement: Option<String>,
+    // Usage stats: messages generated after the first, and each message as generated
+    regenerations: usize,
+    generated: Vec<GeneratedMessage>,
     // Context selection fields
     context: Option<CommitContext>,
     selected_files: Vec<bool>,      // Which staged files are selected
     selected_commits: Vec<bool>,    // Which recent commits are selected
     context_selection_index: usize, // Current selection index in context selection UI
-    context_selection_category: ContextSelectionCategory, // Files or commits
+    context_selection_category: ContextSelectionCategory, // Files, commits or unstaged
+    unstaged_files: Vec<StagedFile>, // Listed after the commits
+    selected_hunks: Vec<bool>,      // Hunks of the unstaged file to stage; empty = all
+    // Preview pane fields
+    preview_scroll: usize,                // First visible preview line
+    preview_search: Option<String>,       // Active search query
+    preview_search_input: Option<String>, // Query being typed after '/'
+    // History browser fields
+    history: Option<Vec<RecentCommit>>, // None until first loaded
+    history_index: usize,
+    history_result: Option<(HistoryAction, GeneratedMessage)>,
+    pending_history_action: Option<HistoryAction>,
+    running_history_action: Option<HistoryAction>,
 }
 
 impl TuiState {
     pub fn new(initial_messages: Vec<GeneratedMessage>, custom_instructions: String) -> Self {
-        let mut message_textarea = TextArea::default();
         let messages = if initial_messages.is_empty() {
             vec![GeneratedMessage {
                 title: String::new(),
@@ -56,12 +133,14 @@ impl TuiState {
         } else {
             initial_messages
         };
-        if let Some(first_message) = messages.first() {
-            message_textarea.insert_str(format_commit_message(first_message));
-        }
+        let (subject_textarea, body_textarea) = messages
+            .first()
+            .map(Self::editor_textareas)
+            .unwrap_or_default();
 
         let mut instructions_textarea = TextArea::default();
         instructions_textarea.insert_str(&custom_instructions);
+        let generated = messages.clone();
 
         Self {
             messages,
@@ -69,7 +148,11 @@ impl TuiState {
             custom_instructions,
             status: "Press '?': help | 'Esc': exit".to_string(),
             mode: Mode::Normal,
-            message_textarea,
+            subject_textarea,
+            body_textarea,
+            editor_field: EditorField::Subject,
+            wrap_column: DEFAULT_WRAP_COLUMN,
+            typos: None,
             instructions_textarea,
             spinner: None,
             dirty: true,
@@ -79,15 +162,36 @@ impl TuiState {
             completion_suggestions: Vec::new(),
             completion_index: 0,
             pending_completion_prefix: None,
+            feedback_textarea: Self::new_feedback_textarea(),
+            pending_refinement: None,
+            regenerations: 0,
+            generated,
             // Context selection fields
             context: None,
             selected_files: Vec::new(),
             selected_commits: Vec::new(),
             context_selection_index: 0,
             context_selection_category: ContextSelectionCategory::Files,
+            unstaged_files: Vec::new(),
+            selected_hunks: Vec::new(),
+            preview_scroll: 0,
+            preview_search: None,
+            preview_search_input: None,
+            history: None,
+            history_index: 0,
+            history_result: None,
+            pending_history_action: None,
+            running_history_action: None,
         }
     }
 
+    fn new_feedback_textarea() -> TextArea<'static> {
+        let mut textarea = TextArea::default();
+        textarea
+            .set_placeholder_text("e.g. \"shorter\", \"more detail\", \"mention the migration\"");
+        textarea
+    }
+
     // -- Getters --
 
     pub fn messages(&self) -> &[GeneratedMessage] {
@@ -106,6 +210,15 @@ impl TuiState {
         &self.messages[self.current_index]
     }
 
+    pub fn regenerations(&self) -> usize {
+        self.regenerations
+    }
+
+    /// Whether the current message differs from what was generated
+    pub fn is_edited(&self) -> bool {
+        self.generated.get(self.current_index) != Some(self.current_message())
+    }
+
     pub fn custom_instructions(&self) -> &str {
         &self.custom_instructions
     }
@@ -118,12 +231,112 @@ impl TuiState {
         self.mode
     }
 
-    pub fn message_textarea(&self) -> &TextArea<'static> {
-        &self.message_textarea
+    pub fn subject_textarea(&self) -> &TextArea<'static> {
+        &self.subject_textarea
+    }
+
+    pub fn subject_textarea_mut(&mut self) -> &mut TextArea<'static> {
+        &mut self.subject_textarea
+    }
+
+    pub fn body_textarea(&self) -> &TextArea<'static> {
+        &self.body_textarea
     }
 
-    pub fn message_textarea_mut(&mut self) -> &mut TextArea<'static> {
-        &mut self.message_textarea
+    pub fn body_textarea_mut(&mut self) -> &mut TextArea<'static> {
+        &mut self.body_textarea
+    }
+
+    pub fn editor_field(&self) -> EditorField {
+        self.editor_field
+    }
+
+    /// The textarea of the field the cursor is in
+    pub fn focused_textarea(&self) -> &TextArea<'static> {
+        match self.editor_field {
+            EditorField::Subject => &self.subject_textarea,
+            EditorField::Body => &self.body_textarea,
+        }
+    }
+
+    pub fn focused_textarea_mut(&mut self) -> &mut TextArea<'static> {
+        self.dirty = true;
+        match self.editor_field {
+            EditorField::Subject => &mut self.subject_textarea,
+            EditorField::Body => &mut self.body_textarea,
+        }
+    }
+
+    /// Moves the cursor to the subject or the body
+    pub fn focus_field(&mut self, field: EditorField) {
+        self.editor_field = field;
+        self.dirty = true;
+    }
+
+    /// The subject as typed so far
+    pub fn edited_subject(&self) -> String {
+        self.subject_textarea.lines().join(" ").trim().to_string()
+    }
+
+    pub fn wrap_column(&self) -> usize {
+        self.wrap_column
+    }
+
+    /// Column the body is hard-wrapped at; 0 turns wrapping off
+    pub fn set_wrap_column(&mut self, column: usize) {
+        self.wrap_column = column;
+    }
+
+    /// Turns flagging of misspellings in the current message on or off
+    pub fn set_spell_check(&mut self, enabled: bool) {
+        self.typos = enabled.then(MessageTypos::default);
+        self.refresh_typos();
+    }
+
+    /// Misspellings in the current message
+    pub fn typos(&self) -> &MessageTypos {
+        static NONE: MessageTypos = MessageTypos {
+            title: Vec::new(),
+            body: Vec::new(),
+        };
+        self.typos.as_ref().unwrap_or(&NONE)
+    }
+
+    /// Status suffix pointing out the current message's misspellings, if any
+    pub fn typo_hint(&self) -> String {
+        match self.typos().len() {
+            0 => String::new(),
+            1 => " · 1 possible typo, 'S' to fix".to_string(),
+            n => format!(" · {n} possible typos, 'S' to fix"),
+        }
+    }
+
+    fn refresh_typos(&mut self) {
+        if let Some(typos) = &mut self.typos {
+            *typos = self
+                .messages
+                .get(self.current_index)
+                .map(spelling::check_message)
+                .unwrap_or_default();
+        }
+    }
+
+    /// Replaces the flagged words of the current message with their corrections.
+    ///
+    /// Returns the number of words corrected.
+    pub fn fix_typos(&mut self) -> usize {
+        let count = self.typos.as_ref().map_or(0, MessageTypos::len);
+        if count > 0 {
+            let fixed = spelling::fix_message(&self.messages[self.current_index]);
+            self.messages[self.current_index] = fixed;
+            self.update_message_textarea();
+        }
+        count
+    }
+
+    /// Re-wraps the body line being typed once it passes the wrap column
+    pub fn wrap_body(&mut self) {
+        hard_wrap_cursor_line(&mut self.body_textarea, self.wrap_column);
     }
 
     pub fn instructions_textarea(&self) -> &TextArea<'static> {
@@ -183,6 +396,18 @@ impl TuiState {
         self.pending_completion_prefix.as_ref()
     }
 
+    pub fn feedback_textarea(&self) -> &TextArea<'static> {
+        &self.feedback_textarea
+    }
+
+    pub fn feedback_textarea_mut(&mut self) -> &mut TextArea<'static> {
+        &mut self.feedback_textarea
+    }
+
+    pub fn pending_refinement(&self) -> Option<&String> {
+        self.pending_refinement.as_ref()
+    }
+
     pub fn context(&self) -> Option<&CommitContext> {
         self.context.as_ref()
     }
@@ -203,6 +428,33 @@ impl TuiState {
         self.context_selection_category
     }
 
+    pub fn unstaged_files(&self) -> &[StagedFile] {
+        &self.unstaged_files
+    }
+
+    /// Staged file under the cursor, if any
+    pub fn selected_staged_file(&self) -> Option<&StagedFile> {
+        if self.context_selection_category != ContextSelectionCategory::Files {
+            return None;
+        }
+        self.context
+            .as_ref()?
+            .staged_files
+            .get(self.context_selection_index)
+    }
+
+    /// Unstaged file under the cursor, if any
+    pub fn selected_unstaged_file(&self) -> Option<&StagedFile> {
+        if self.context_selection_category != ContextSelectionCategory::Unstaged {
+            return None;
+        }
+        let ctx = self.context.as_ref()?;
+        self.unstaged_files.get(
+            self.context_selection_index
+                .checked_sub(ctx.staged_files.len() + ctx.recent_commits.len())?,
+        )
+    }
+
     // -- Setters and Modifiers --
 
     pub fn set_mode(&mut self, mode: Mode) {
@@ -236,6 +488,7 @@ impl TuiState {
 
     pub fn set_messages(&mut self, messages: Vec<GeneratedMessage>) {
         self.messages = messages;
+        self.refresh_typos();
         self.dirty = true;
     }
 
@@ -248,7 +501,9 @@ impl TuiState {
     }
 
     pub fn add_message(&mut self, message: GeneratedMessage) {
+        self.generated.push(message.clone());
         self.messages.push(message);
+        self.regenerations += 1;
         self.current_index = self.messages.len() - 1;
         self.update_message_textarea();
         self.dirty = true;
@@ -264,17 +519,45 @@ impl TuiState {
         self.pending_completion_prefix = prefix;
     }
 
-    pub fn update_message_textarea(&mut self) {
-        let current_message = &self.messages[self.current_index];
-        let message_content = format!(
-            "{}\n\n{}",
-            current_message.title,
-            current_message.message.trim()
-        );
+    /// Queues the typed feedback for a refinement and clears the input.
+    ///
+    /// Returns false when the feedback is empty.
+    pub fn submit_feedback(&mut self) -> bool {
+        let feedback = self.feedback_textarea.lines().join(" ").trim().to_string();
+        self.feedback_textarea = Self::new_feedback_textarea();
+        if feedback.is_empty() {
+            return false;
+        }
+        self.pending_refinement = Some(feedback);
+        self.dirty = true;
+        true
+    }
+
+    pub fn take_pending_refinement(&mut self) -> Option<String> {
+        self.pending_refinement.take()
+    }
+
+    pub fn cancel_feedback(&mut self) {
+        self.feedback_textarea = Self::new_feedback_textarea();
+        self.dirty = true;
+    }
 
-        let mut new_textarea = TextArea::default();
-        new_textarea.insert_str(&message_content);
-        self.message_textarea = new_textarea;
+    /// The subject and body editors for a message
+    fn editor_textareas(message: &GeneratedMessage) -> (TextArea<'static>, TextArea<'static>) {
+        let mut subject = TextArea::default();
+        subject.insert_str(&message.title);
+        let mut body = TextArea::default();
+        body.insert_str(message.message.trim());
+        body.move_cursor(CursorMove::Top);
+        (subject, body)
+    }
+
+    pub fn update_message_textarea(&mut self) {
+        let (subject, body) = Self::editor_textareas(&self.messages[self.current_index]);
+        self.subject_textarea = subject;
+        self.body_textarea = body;
+        self.editor_field = EditorField::Subject;
+        self.refresh_typos();
         self.dirty = true;
     }
 
@@ -314,15 +597,12 @@ impl TuiState {
     }
 
     pub fn update_current_message_from_textarea(&mut self) {
-        let content = self.message_textarea.lines().join("\n");
-        let parts: Vec<&str> = content.splitn(2, "\n\n").collect();
-        if parts.len() == 2 {
-            self.messages[self.current_index].title = parts[0].trim().to_string();
-            self.messages[self.current_index].message = parts[1].trim().to_string();
-        } else {
-            self.messages[self.current_index].title = content.trim().to_string();
-            self.messages[self.current_index].message = String::new();
-        }
+        let title = self.edited_subject();
+        let message = self.body_textarea.lines().join("\n").trim().to_string();
+        let current = &mut self.messages[self.current_index];
+        current.title = title;
+        current.message = message;
+        self.refresh_typos();
         self.dirty = true;
     }
 
@@ -344,6 +624,142 @@ impl TuiState {
         self.dirty = true;
     }
 
+    pub fn set_unstaged_files(&mut self, files: Vec<StagedFile>) {
+        self.unstaged_files = files;
+        self.selected_hunks.clear();
+        self.dirty = true;
+    }
+
+    /// Preview line offsets of the hunk headers of the unstaged file under the cursor
+    pub fn hunk_offsets(&self) -> Vec<usize> {
+        self.selected_unstaged_file()
+            .map(|file| {
+                file.diff
+                    .lines()
+                    .enumerate()
+                    .filter(|(_, line)| is_hunk_header(line))
+                    .map(|(i, _)| i)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Hunk containing the first visible preview line
+    pub fn current_hunk(&self) -> Option<usize> {
+        let offsets = self.hunk_offsets();
+        if offsets.is_empty() {
+            return None;
+        }
+        Some(
+            offsets
+                .iter()
+                .rposition(|&offset| offset <= self.preview_scroll)
+                .unwrap_or(0),
+        )
+    }
+
+    /// Whether a hunk will be staged; every hunk is until one is toggled
+    pub fn is_hunk_selected(&self, hunk: usize) -> bool {
+        self.selected_hunks.get(hunk).copied().unwrap_or(true)
+    }
+
+    /// Include or exclude the current hunk of the unstaged file
+    pub fn toggle_current_hunk(&mut self) {
+        let Some(hunk) = self.current_hunk() else {
+            return;
+        };
+        if self.selected_hunks.is_empty() {
+            self.selected_hunks = vec![true; self.hunk_offsets().len()];
+        }
+        self.selected_hunks[hunk] = !self.selected_hunks[hunk];
+        self.dirty = true;
+    }
+
+    /// Scroll the preview to the next or previous hunk header
+    pub fn jump_to_hunk(&mut self, forward: bool) {
+        let offsets = self.hunk_offsets();
+        let target = if forward {
+            offsets.into_iter().find(|&o| o > self.preview_scroll)
+        } else {
+            offsets.into_iter().rev().find(|&o| o < self.preview_scroll)
+        };
+        if let Some(offset) = target {
+            self.preview_scroll = offset;
+            self.dirty = true;
+        }
+    }
+
+    /// Selected hunk positions, or `None` when the whole file is selected
+    pub fn partial_hunk_selection(&self) -> Option<Vec<usize>> {
+        if self.selected_hunks.iter().all(|&selected| selected) {
+            return None;
+        }
+        Some(
+            self.selected_hunks
+                .iter()
+                .enumerate()
+                .filter(|(_, selected)| **selected)
+                .map(|(i, _)| i)
+                .collect(),
+        )
+    }
+
+    /// Replace the context after the index changed, keeping the cursor position
+    /// and any files or commits the user excluded.
+    pub fn refresh_context(&mut self, context: CommitContext, unstaged: Vec<StagedFile>) {
+        let (excluded_files, excluded_commits): (Vec<String>, Vec<String>) =
+            self.context.as_ref().map_or_else(Default::default, |ctx| {
+                (
+                    ctx.staged_files
+                        .iter()
+                        .zip(&self.selected_files)
+                        .filter(|(_, selected)| !**selected)
+                        .map(|(f, _)| f.path.clone())
+                        .collect(),
+                    ctx.recent_commits
+                        .iter()
+                        .zip(&self.selected_commits)
+                        .filter(|(_, selected)| !**selected)
+                        .map(|(c, _)| c.hash.clone())
+                        .collect(),
+                )
+            });
+        let index = self.context_selection_index;
+
+        self.selected_files = context
+            .staged_files
+            .iter()
+            .map(|f| !excluded_files.contains(&f.path))
+            .collect();
+        self.selected_commits = context
+            .recent_commits
+            .iter()
+            .map(|c| !excluded_commits.contains(&c.hash))
+            .collect();
+        let total = context.staged_files.len() + context.recent_commits.len() + unstaged.len();
+        self.context = Some(context);
+        self.unstaged_files = unstaged;
+        self.context_selection_index = index.min(total.saturating_sub(1));
+        self.context_selection_category = self.category_at(self.context_selection_index);
+        self.preview_scroll = 0;
+        self.selected_hunks.clear();
+        self.dirty = true;
+    }
+
+    /// Category of the item at a position in the selection list
+    fn category_at(&self, index: usize) -> ContextSelectionCategory {
+        let Some(ctx) = &self.context else {
+            return ContextSelectionCategory::Files;
+        };
+        if index < ctx.staged_files.len() {
+            ContextSelectionCategory::Files
+        } else if index < ctx.staged_files.len() + ctx.recent_commits.len() {
+            ContextSelectionCategory::Commits
+        } else {
+            ContextSelectionCategory::Unstaged
+        }
+    }
+
     /// Toggle selection of current item
     pub fn toggle_current_selection(&mut self) {
         if let Some(ctx) = &self.context {
@@ -362,6 +778,11 @@ impl TuiState {
                         self.selected_commits[commit_index] = !self.selected_commits[commit_index];
                     }
                 }
+                // Unstaged files are not part of the context; pick hunks to stage instead
+                ContextSelectionCategory::Unstaged => {
+                    self.toggle_current_hunk();
+                    return;
+                }
             }
         }
         self.dirty = true;
@@ -370,55 +791,240 @@ impl TuiState {
     /// Switch to next category or wrap around
     pub fn next_category(&mut self) {
         if let Some(ctx) = &self.context {
-            match self.context_selection_category {
-                ContextSelectionCategory::Files => {
-                    if !ctx.recent_commits.is_empty() {
-                        self.context_selection_category = ContextSelectionCategory::Commits;
-                        self.context_selection_index = ctx.staged_files.len();
-                    }
-                }
-                ContextSelectionCategory::Commits => {
-                    if !ctx.staged_files.is_empty() {
-                        self.context_selection_category = ContextSelectionCategory::Files;
-                        self.context_selection_index = 0;
-                    }
-                }
+            let files = ctx.staged_files.len();
+            let commits = ctx.recent_commits.len();
+            // Category start offsets and sizes, in display order
+            let sections = [
+                (ContextSelectionCategory::Files, 0, files),
+                (ContextSelectionCategory::Commits, files, commits),
+                (
+                    ContextSelectionCategory::Unstaged,
+                    files + commits,
+                    self.unstaged_files.len(),
+                ),
+            ];
+            let current = sections
+                .iter()
+                .position(|(category, _, _)| *category == self.context_selection_category)
+                .unwrap_or(0);
+            if let Some((category, start, _)) = (1..sections.len())
+                .map(|step| sections[(current + step) % sections.len()])
+                .find(|(_, _, len)| *len > 0)
+            {
+                self.context_selection_category = category;
+                self.context_selection_index = start;
             }
         }
+        self.preview_scroll = 0;
+        self.selected_hunks.clear();
         self.dirty = true;
     }
 
     /// Move selection up
     pub fn move_selection_up(&mut self) {
-        if let Some(ctx) = &self.context
-            && self.context_selection_index > 0
-        {
+        if self.context.is_some() && self.context_selection_index > 0 {
             self.context_selection_index -= 1;
-            // Check if we need to switch categories
-            if self.context_selection_category == ContextSelectionCategory::Commits
-                && self.context_selection_index < ctx.staged_files.len()
-            {
-                self.context_selection_category = ContextSelectionCategory::Files;
-            }
+            self.context_selection_category = self.category_at(self.context_selection_index);
         }
+        self.preview_scroll = 0;
+        self.selected_hunks.clear();
         self.dirty = true;
     }
 
     /// Move selection down
     pub fn move_selection_down(&mut self) {
         if let Some(ctx) = &self.context {
-            let total_items = ctx.staged_files.len() + ctx.recent_commits.len();
+            let total_items =
+                ctx.staged_files.len() + ctx.recent_commits.len() + self.unstaged_files.len();
             if self.context_selection_index < total_items.saturating_sub(1) {
                 self.context_selection_index += 1;
-                // Check if we need to switch categories
-                if self.context_selection_category == ContextSelectionCategory::Files
-                    && self.context_selection_index >= ctx.staged_files.len()
-                {
-                    self.context_selection_category = ContextSelectionCategory::Commits;
-                }
+                self.context_selection_category = self.category_at(self.context_selection_index);
+            }
+        }
+        self.preview_scroll = 0;
+        self.selected_hunks.clear();
+        self.dirty = true;
+    }
+
+    pub fn preview_scroll(&self) -> usize {
+        self.preview_scroll
+    }
+
+    pub fn preview_search(&self) -> Option<&str> {
+        self.preview_search.as_deref()
+    }
+
+    pub fn preview_search_input(&self) -> Option<&str> {
+        self.preview_search_input.as_deref()
+    }
+
+    /// Text shown in the preview pane for the current selection
+    pub fn preview_text(&self) -> Option<&str> {
+        let ctx = self.context.as_ref()?;
+        match self.context_selection_category {
+            ContextSelectionCategory::Files => ctx
+                .staged_files
+                .get(self.context_selection_index)
+                .map(|f| f.diff.as_str()),
+            ContextSelectionCategory::Commits => ctx
+                .recent_commits
+                .get(
+                    self.context_selection_index
+                        .saturating_sub(ctx.staged_files.len()),
+                )
+                .map(|c| c.message.as_str()),
+            ContextSelectionCategory::Unstaged => {
+                self.selected_unstaged_file().map(|f| f.diff.as_str())
             }
         }
+    }
+
+    pub fn preview_line_count(&self) -> usize {
+        self.preview_text().map_or(0, |t| t.lines().count())
+    }
+
+    /// Scroll the preview by `delta` lines, clamped to the content
+    pub fn scroll_preview(&mut self, delta: isize) {
+        let max = self.preview_line_count().saturating_sub(1);
+        self.preview_scroll = self.preview_scroll.saturating_add_signed(delta).min(max);
+        self.dirty = true;
+    }
+
+    /// Line indices of the preview that match the active search
+    pub fn preview_matches(&self) -> Vec<usize> {
+        let (Some(query), Some(text)) = (self.preview_search.as_deref(), self.preview_text())
+        else {
+            return Vec::new();
+        };
+        let query = query.to_lowercase();
+        text.lines()
+            .enumerate()
+            .filter(|(_, line)| line.to_lowercase().contains(&query))
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    pub fn start_preview_search(&mut self) {
+        self.preview_search_input = Some(String::new());
+        self.dirty = true;
+    }
+
+    pub fn preview_search_input_mut(&mut self) -> Option<&mut String> {
         self.dirty = true;
+        self.preview_search_input.as_mut()
+    }
+
+    pub fn cancel_preview_search(&mut self) {
+        self.preview_search_input = None;
+        self.dirty = true;
+    }
+
+    /// Activates the typed query and jumps to its first match
+    pub fn submit_preview_search(&mut self) {
+        let query = self.preview_search_input.take().unwrap_or_default();
+        self.preview_search = (!query.is_empty()).then_some(query);
+        self.preview_scroll = self.preview_matches().first().copied().unwrap_or(0);
+        self.dirty = true;
+    }
+
+    /// Moves the preview to the next (or previous) match, wrapping around
+    pub fn jump_to_match(&mut self, forward: bool) {
+        let matches = self.preview_matches();
+        let target = if forward {
+            matches
+                .iter()
+                .find(|&&line| line > self.preview_scroll)
+                .or_else(|| matches.first())
+        } else {
+            matches
+                .iter()
+                .rev()
+                .find(|&&line| line < self.preview_scroll)
+                .or_else(|| matches.last())
+        };
+        if let Some(&line) = target {
+            self.preview_scroll = line;
+        }
+        self.dirty = true;
+    }
+
+    pub fn history(&self) -> &[RecentCommit] {
+        self.history.as_deref().unwrap_or_default()
+    }
+
+    pub fn is_history_loaded(&self) -> bool {
+        self.history.is_some()
+    }
+
+    pub fn history_index(&self) -> usize {
+        self.history_index
+    }
+
+    pub fn selected_history_commit(&self) -> Option<&RecentCommit> {
+        self.history().get(self.history_index)
+    }
+
+    pub fn history_result(&self) -> Option<&(HistoryAction, GeneratedMessage)> {
+        self.history_result.as_ref()
+    }
+
+    pub fn is_history_busy(&self) -> bool {
+        self.pending_history_action.is_some() || self.running_history_action.is_some()
+    }
+
+    pub fn set_history(&mut self, commits: Vec<RecentCommit>) {
+        self.history = Some(commits);
+        self.history_index = 0;
+        self.history_result = None;
+        self.dirty = true;
+    }
+
+    pub fn move_history_up(&mut self) {
+        if self.history_index > 0 {
+            self.history_index -= 1;
+            self.history_result = None;
+        }
+        self.dirty = true;
+    }
+
+    pub fn move_history_down(&mut self) {
+        if self.history_index + 1 < self.history().len() {
+            self.history_index += 1;
+            self.history_result = None;
+        }
+        self.dirty = true;
+    }
+
+    /// Queues an action for the selected commit; ignored while one is running
+    pub fn request_history_action(&mut self, action: HistoryAction) -> bool {
+        if self.is_history_busy() || self.selected_history_commit().is_none() {
+            return false;
+        }
+        self.pending_history_action = Some(action);
+        self.history_result = None;
+        self.dirty = true;
+        true
+    }
+
+    /// Marks the queued action as running and returns it
+    pub fn take_pending_history_action(&mut self) -> Option<HistoryAction> {
+        let action = self.pending_history_action.take();
+        if action.is_some() {
+            self.running_history_action = action;
+        }
+        action
+    }
+
+    pub fn finish_history_action(&mut self, result: Option<(HistoryAction, GeneratedMessage)>) {
+        self.history_result = result;
+        self.running_history_action = None;
+        self.spinner = None;
+        self.dirty = true;
+    }
+
+    pub fn clear_history_result(&mut self) -> bool {
+        self.dirty = true;
+        self.history_result.take().is_some()
     }
 
     /// Get filtered context based on selections
@@ -446,10 +1052,50 @@ impl TuiState {
     }
 }
 
+/// Hunk headers are rendered with a leading space in context diffs
+fn is_hunk_header(line: &str) -> bool {
+    line.strip_prefix(' ').unwrap_or(line).starts_with("@@ -")
+}
+
+/// Breaks the cursor's line at the last space before `column`, when the cursor is at its end.
+///
+/// Only the line being typed is wrapped, so pasted or deliberately long lines
+/// elsewhere, such as URLs, stay as they are. List items keep their indent.
+pub(crate) fn hard_wrap_cursor_line(textarea: &mut TextArea<'static>, column: usize) {
+    let (row, col) = textarea.cursor();
+    let Some(line) = textarea.lines().get(row) else {
+        return;
+    };
+    if column == 0 || line.width() <= column || col < line.chars().count() {
+        return;
+    }
+    let indent = match line.trim_start().get(..2) {
+        Some("- " | "* ") => " ".repeat(line.len() - line.trim_start().len() + 2),
+        _ => String::new(),
+    };
+    let options = textwrap::Options::new(column)
+        .break_words(false)
+        .subsequent_indent(&indent);
+    let mut wrapped = textwrap::wrap(line, options).join("\n");
+    if wrapped.lines().count() < 2 {
+        return;
+    }
+    // The space just typed separates the next word
+    if line.ends_with(' ') && !wrapped.ends_with(' ') {
+        wrapped.push(' ');
+    }
+
+    let yank = textarea.yank_text();
+    textarea.move_cursor(CursorMove::Head);
+    textarea.delete_line_by_end();
+    textarea.insert_str(wrapped);
+    textarea.set_yank_text(yank);
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::llm::context::{ChangeType, RecentCommit, StagedFile};
+    use crate::llm::context::{ChangeType, DiffSource, RecentCommit, StagedFile};
 
     #[test]
     fn test_get_filtered_context_filters_files_and_commits() {
@@ -487,6 +1133,18 @@ mod tests {
             user_name: "Test User".to_string(),
             user_email: "test@example.com".to_string(),
             author_history: vec![],
+            related_files: vec![],
+            style_profile: None,
+            issue: None,
+            owners: vec![],
+            api_changes: vec![],
+            test_gaps: vec![],
+            ci_failure: None,
+            project: None,
+            change_kind: None,
+            analyses: Vec::new(),
+            context_files: Vec::new(),
+            diff_source: DiffSource::Unknown,
         };
 
         let mut state = TuiState::new(vec![], "test".to_string());
@@ -513,6 +1171,122 @@ mod tests {
         assert_eq!(filtered.recent_commits[0].hash, "def456");
     }
 
+    #[test]
+    fn test_submit_feedback_queues_refinement() {
+        let mut state = TuiState::new(vec![], "test".to_string());
+        assert!(!state.submit_feedback());
+        assert!(state.pending_refinement().is_none());
+
+        state.feedback_textarea_mut().insert_str("  shorter  ");
+        assert!(state.submit_feedback());
+        assert_eq!(state.take_pending_refinement().as_deref(), Some("shorter"));
+        assert!(state.feedback_textarea().is_empty());
+        assert!(state.pending_refinement().is_none());
+    }
+
+    #[test]
+    fn test_preview_scroll_and_search() {
+        let diff = (0..50)
+            .map(|i| {
+                if i % 20 == 5 {
+                    format!("+ Needle {i}")
+                } else {
+                    format!(" line {i}")
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        let context = CommitContext {
+            branch: "main".to_string(),
+            recent_commits: vec![],
+            staged_files: vec![StagedFile {
+                path: "big.rs".to_string(),
+                change_type: ChangeType::Modified,
+                diff,
+                content: None,
+                content_excluded: false,
+            }],
+            user_name: "Test User".to_string(),
+            user_email: "test@example.com".to_string(),
+            author_history: vec![],
+            related_files: vec![],
+            style_profile: None,
+            issue: None,
+            owners: vec![],
+            api_changes: vec![],
+            test_gaps: vec![],
+            ci_failure: None,
+            project: None,
+            change_kind: None,
+            analyses: Vec::new(),
+            context_files: Vec::new(),
+            diff_source: DiffSource::Unknown,
+        };
+
+        let mut state = TuiState::new(vec![], "test".to_string());
+        state.initialize_context(context);
+        assert_eq!(state.preview_line_count(), 50);
+
+        state.scroll_preview(-3);
+        assert_eq!(state.preview_scroll(), 0);
+        state.scroll_preview(100);
+        assert_eq!(state.preview_scroll(), 49);
+
+        state.start_preview_search();
+        state
+            .preview_search_input_mut()
+            .expect("search input should be active")
+            .push_str("needle");
+        state.submit_preview_search();
+        assert_eq!(state.preview_matches(), vec![5, 25, 45]);
+        assert_eq!(state.preview_scroll(), 5);
+
+        state.jump_to_match(true);
+        assert_eq!(state.preview_scroll(), 25);
+        state.jump_to_match(false);
+        assert_eq!(state.preview_scroll(), 5);
+        state.jump_to_match(false);
+        assert_eq!(state.preview_scroll(), 45);
+    }
+
+    #[test]
+    fn test_history_navigation_and_actions() {
+        let commit = |hash: &str| RecentCommit {
+            hash: hash.to_string(),
+            message: format!("commit {hash}"),
+            timestamp: "1700000000".to_string(),
+        };
+        let mut state = TuiState::new(vec![], "test".to_string());
+        assert!(!state.request_history_action(HistoryAction::Explain));
+
+        state.set_history(vec![commit("aaa"), commit("bbb")]);
+        state.move_history_up();
+        assert_eq!(state.history_index(), 0);
+        state.move_history_down();
+        state.move_history_down();
+        assert_eq!(state.history_index(), 1);
+
+        assert!(state.request_history_action(HistoryAction::Explain));
+        assert!(!state.request_history_action(HistoryAction::Reword));
+        assert_eq!(
+            state.take_pending_history_action(),
+            Some(HistoryAction::Explain)
+        );
+
+        state.finish_history_action(Some((
+            HistoryAction::Explain,
+            GeneratedMessage {
+                title: "Summary".to_string(),
+                message: "Details".to_string(),
+            },
+        )));
+        assert!(!state.is_history_busy());
+        assert!(state.history_result().is_some());
+
+        state.move_history_up();
+        assert!(state.history_result().is_none());
+    }
+
     #[test]
     fn test_get_filtered_context_returns_none_when_no_context() {
         let state = TuiState::new(vec![], "test".to_string());
@@ -534,6 +1308,18 @@ mod tests {
             user_name: "Test User".to_string(),
             user_email: "test@example.com".to_string(),
             author_history: vec![],
+            related_files: vec![],
+            style_profile: None,
+            issue: None,
+            owners: vec![],
+            api_changes: vec![],
+            test_gaps: vec![],
+            ci_failure: None,
+            project: None,
+            change_kind: None,
+            analyses: Vec::new(),
+            context_files: Vec::new(),
+            diff_source: DiffSource::Unknown,
         };
 
         let mut state = TuiState::new(vec![], "test".to_string());
@@ -566,6 +1352,18 @@ mod tests {
             user_name: "Test User".to_string(),
             user_email: "test@example.com".to_string(),
             author_history: vec![],
+            related_files: vec![],
+            style_profile: None,
+            issue: None,
+            owners: vec![],
+            api_changes: vec![],
+            test_gaps: vec![],
+            ci_failure: None,
+            project: None,
+            change_kind: None,
+            analyses: Vec::new(),
+            context_files: Vec::new(),
+            diff_source: DiffSource::Unknown,
         };
 
         let mut state = TuiState::new(vec![], "test".to_string());
@@ -580,4 +1378,194 @@ mod tests {
         state.toggle_current_selection();
         assert!(!state.selected_commits[0]);
     }
+
+    #[test]
+    fn test_unstaged_section_and_refresh_keep_selection() {
+        let file = |path: &str| StagedFile {
+            path: path.to_string(),
+            change_type: ChangeType::Modified,
+            diff: "+ change".to_string(),
+            content: None,
+            content_excluded: false,
+        };
+        let context = |paths: &[&str]| CommitContext {
+            branch: "main".to_string(),
+            recent_commits: vec![RecentCommit {
+                hash: "abc1234".to_string(),
+                message: "First commit".to_string(),
+                timestamp: "1234567890".to_string(),
+            }],
+            staged_files: paths.iter().map(|p| file(p)).collect(),
+            user_name: "Test User".to_string(),
+            user_email: "test@example.com".to_string(),
+            author_history: vec![],
+            related_files: vec![],
+            style_profile: None,
+            issue: None,
+            owners: vec![],
+            api_changes: vec![],
+            test_gaps: vec![],
+            ci_failure: None,
+            project: None,
+            change_kind: None,
+            analyses: Vec::new(),
+            context_files: Vec::new(),
+            diff_source: DiffSource::Unknown,
+        };
+
+        let mut state = TuiState::new(vec![], "test".to_string());
+        state.initialize_context(context(&["a.rs", "b.rs"]));
+        state.set_unstaged_files(vec![file("c.rs")]);
+        state.toggle_current_selection(); // exclude a.rs
+
+        state.next_category();
+        state.next_category();
+        assert_eq!(
+            state.context_selection_category(),
+            ContextSelectionCategory::Unstaged
+        );
+        assert_eq!(
+            state.selected_unstaged_file().map(|f| f.path.as_str()),
+            Some("c.rs")
+        );
+        assert!(state.selected_staged_file().is_none());
+
+        // Staging c.rs moves it into the staged files; a.rs stays excluded
+        state.refresh_context(context(&["a.rs", "b.rs", "c.rs"]), vec![]);
+        assert_eq!(state.selected_files(), &[false, true, true]);
+        assert_eq!(
+            state.context_selection_category(),
+            ContextSelectionCategory::Commits
+        );
+    }
+
+    #[test]
+    fn test_hunk_selection_on_unstaged_file() {
+        let mut state = TuiState::new(vec![], "test".to_string());
+        state.initialize_context(CommitContext {
+            branch: "main".to_string(),
+            recent_commits: vec![],
+            staged_files: vec![],
+            user_name: "Test User".to_string(),
+            user_email: "test@example.com".to_string(),
+            author_history: vec![],
+            related_files: vec![],
+            style_profile: None,
+            issue: None,
+            owners: vec![],
+            api_changes: vec![],
+            test_gaps: vec![],
+            ci_failure: None,
+            project: None,
+            change_kind: None,
+            analyses: Vec::new(),
+            context_files: Vec::new(),
+            diff_source: DiffSource::Unknown,
+        });
+        state.set_unstaged_files(vec![StagedFile {
+            path: "a.rs".to_string(),
+            change_type: ChangeType::Modified,
+            diff: " @@ -1 +1 @@\n-a\n+b\n @@ -9 +9 @@\n-c\n+d".to_string(),
+            content: None,
+            content_excluded: false,
+        }]);
+        state.next_category();
+
+        assert_eq!(state.hunk_offsets(), vec![0, 3]);
+        assert_eq!(state.partial_hunk_selection(), None);
+
+        state.jump_to_hunk(true);
+        assert_eq!(state.current_hunk(), Some(1));
+        state.toggle_current_selection();
+        assert!(!state.is_hunk_selected(1));
+        assert_eq!(state.partial_hunk_selection(), Some(vec![0]));
+
+        // Moving the cursor forgets the selection
+        state.move_selection_up();
+        assert_eq!(state.partial_hunk_selection(), None);
+    }
+
+    #[test]
+    fn test_subject_and_body_are_edited_separately() {
+        let mut state = TuiState::new(
+            vec![GeneratedMessage {
+                title: "Fix parser".to_string(),
+                message: "Handles empty input.".to_string(),
+            }],
+            "test".to_string(),
+        );
+        assert_eq!(state.subject_textarea().lines(), ["Fix parser"]);
+        assert_eq!(state.body_textarea().lines(), ["Handles empty input."]);
+        assert_eq!(state.editor_field(), EditorField::Subject);
+
+        state.subject_textarea_mut().insert_str(" crash");
+        state.focus_field(EditorField::Body);
+        state.focused_textarea_mut().move_cursor(CursorMove::Bottom);
+        state.focused_textarea_mut().move_cursor(CursorMove::End);
+        state.focused_textarea_mut().insert_str("\n\nSee #12.");
+        state.update_current_message_from_textarea();
+        assert_eq!(state.current_message().title, "Fix parser crash");
+        assert_eq!(
+            state.current_message().message,
+            "Handles empty input.\n\nSee #12."
+        );
+
+        assert_eq!(SubjectLength::of("Fix parser crash"), SubjectLength::Fits);
+        assert_eq!(
+            SubjectLength::of(&"a".repeat(51)),
+            SubjectLength::PastSoftLimit
+        );
+        assert_eq!(
+            SubjectLength::of(&"a".repeat(73)),
+            SubjectLength::PastHardLimit
+        );
+    }
+
+    #[test]
+    fn test_body_wraps_the_line_being_typed() {
+        let mut textarea = TextArea::default();
+        textarea.insert_str("- one two three four");
+        hard_wrap_cursor_line(&mut textarea, 12);
+        assert_eq!(textarea.lines(), ["- one two", "  three four"]);
+        assert_eq!(textarea.cursor(), (1, 12));
+
+        // Lines the cursor is not at the end of are left alone
+        let mut textarea = TextArea::default();
+        textarea.insert_str("https://example.com/a/very/long/link");
+        textarea.move_cursor(CursorMove::Head);
+        hard_wrap_cursor_line(&mut textarea, 12);
+        assert_eq!(textarea.lines().len(), 1);
+
+        // A column of 0 turns wrapping off
+        let mut textarea = TextArea::default();
+        textarea.insert_str("one two three four");
+        hard_wrap_cursor_line(&mut textarea, 0);
+        assert_eq!(textarea.lines().len(), 1);
+    }
+
+    #[test]
+    fn test_typos_are_flagged_and_fixed() {
+        let mut state = TuiState::new(
+            vec![GeneratedMessage {
+                title: "Seperate config loading".to_string(),
+                message: "Teh loader is now lazy.".to_string(),
+            }],
+            "test".to_string(),
+        );
+        assert!(state.typos().is_empty());
+
+        state.set_spell_check(true);
+        assert_eq!(state.typos().len(), 2);
+        assert_eq!(state.typo_hint(), " · 2 possible typos, 'S' to fix");
+
+        assert_eq!(state.fix_typos(), 2);
+        assert_eq!(state.current_message().title, "Separate config loading");
+        assert_eq!(state.current_message().message, "The loader is now lazy.");
+        assert_eq!(
+            state.subject_textarea().lines(),
+            ["Separate config loading"]
+        );
+        assert!(state.typos().is_empty());
+        assert_eq!(state.fix_typos(), 0);
+    }
 }
@@ -6,6 +6,7 @@
 //! - Channel management for task results
 //! - Preventing duplicate task spawns
 
+use super::state::HistoryAction;
 use crate::commands::commit::{
     CommitService, completion::CompletionService, types::GeneratedMessage,
 };
@@ -13,6 +14,9 @@ use crate::llm::context::CommitContext;
 use std::sync::Arc;
 use tokio::sync::mpsc;
 
+/// Outcome of a history browser action
+pub type HistoryActionResult = (HistoryAction, Result<GeneratedMessage, anyhow::Error>);
+
 /// Task runner for async operations
 ///
 /// Manages spawning and coordinating results for:
@@ -27,6 +31,8 @@ pub struct TuiTaskRunner {
     generation_tx: mpsc::Sender<Result<GeneratedMessage, anyhow::Error>>,
     /// Channel sender for completion results
     completion_tx: mpsc::Sender<Result<Vec<String>, anyhow::Error>>,
+    /// Channel sender for history action results
+    history_tx: mpsc::Sender<HistoryActionResult>,
     /// Flag to prevent duplicate generation spawns
     generation_task_spawned: bool,
     /// Flag to prevent duplicate completion spawns
@@ -40,12 +46,14 @@ impl TuiTaskRunner {
         completion_service: Arc<CompletionService>,
         generation_tx: mpsc::Sender<Result<GeneratedMessage, anyhow::Error>>,
         completion_tx: mpsc::Sender<Result<Vec<String>, anyhow::Error>>,
+        history_tx: mpsc::Sender<HistoryActionResult>,
     ) -> Self {
         Self {
             commit_service,
             completion_service,
             generation_tx,
             completion_tx,
+            history_tx,
             generation_task_spawned: false,
             completion_task_spawned: false,
         }
@@ -83,6 +91,58 @@ impl TuiTaskRunner {
         }
     }
 
+    /// Spawn a refinement of `previous` guided by `feedback`
+    ///
+    /// Shares the generation channel and flag, so the result is handled like
+    /// any regenerated message.
+    pub fn spawn_refinement_if_needed(
+        &mut self,
+        instructions: String,
+        previous: GeneratedMessage,
+        feedback: String,
+        context: Option<CommitContext>,
+    ) {
+        if !self.generation_task_spawned {
+            let service = self.commit_service.clone();
+            let tx = self.generation_tx.clone();
+
+            tokio::spawn(async move {
+                let result = service
+                    .refine_message_with_context(&instructions, &previous, &feedback, context)
+                    .await;
+                if tx.send(result).await.is_err() {
+                    log::debug!("Refinement result channel closed, result dropped");
+                }
+            });
+
+            self.generation_task_spawned = true;
+        }
+    }
+
+    /// Spawn an AI action on a commit from the history browser
+    pub fn spawn_history_action(
+        &self,
+        action: HistoryAction,
+        commit_id: String,
+        instructions: String,
+    ) {
+        let service = self.commit_service.clone();
+        let tx = self.history_tx.clone();
+
+        tokio::spawn(async move {
+            let result = match action {
+                HistoryAction::Reword => service.reword_commit(&instructions, &commit_id).await,
+                HistoryAction::Explain => service.explain_commit(&instructions, &commit_id).await,
+                HistoryAction::PullRequest => {
+                    service.draft_pull_request(&instructions, &commit_id).await
+                }
+            };
+            if tx.send((action, result)).await.is_err() {
+                log::debug!("History result channel closed, result dropped");
+            }
+        });
+    }
+
     /// Spawn completion task if needed
     ///
     /// Spawns a task when:
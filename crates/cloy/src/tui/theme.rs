@@ -4,8 +4,36 @@
 //! terminal capabilities and user preferences.
 
 use crate::common::ThemeMode;
+use anyhow::{Result, anyhow};
 use ratatui::style::{Color, Modifier};
+use std::collections::HashMap;
 use std::env;
+use std::str::FromStr;
+
+/// Built-in truecolor palettes selectable with `--theme <name>`
+pub const BUILTIN_PALETTES: &[&str] = &["solarized", "gruvbox", "catppuccin"];
+
+/// Color slots a user-defined theme can override
+pub const THEME_SLOTS: &[&str] = &[
+    "brand",
+    "text",
+    "text-dimmed",
+    "text-on-accent",
+    "background",
+    "surface",
+    "overlay",
+    "focus",
+    "selection-bg",
+    "selection-fg",
+    "success",
+    "error",
+    "warning",
+    "info",
+    "accent",
+    "secondary-accent",
+    "border",
+    "border-active",
+];
 
 /// Terminal color capability levels
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -48,6 +76,8 @@ pub struct Theme {
     /// Selection/Highlight
     pub selection_bg: Color,
     pub selection_fg: Color,
+    /// Added to the selection so it stays visible without colors
+    pub selection_modifier: Modifier,
 
     /// State colors
     pub state_success: Color,
@@ -154,6 +184,7 @@ impl Theme {
                 border: Color::Rgb(226, 232, 240), // Slate-200
                 border_active: Color::Rgb(99, 102, 241), // Indigo-500
 
+                selection_modifier: Modifier::empty(),
                 font_weight_regular: Modifier::empty(),
                 font_weight_bold: Modifier::BOLD,
                 font_weight_italic: Modifier::ITALIC,
@@ -188,6 +219,7 @@ impl Theme {
                 border: Color::Rgb(69, 71, 90),              // Surface1
                 border_active: Color::Rgb(203, 166, 247),    // Mauve
 
+                selection_modifier: Modifier::empty(),
                 font_weight_regular: Modifier::empty(),
                 font_weight_bold: Modifier::BOLD,
                 font_weight_italic: Modifier::ITALIC,
@@ -240,6 +272,199 @@ impl Theme {
             border: Color::DarkGray,
             border_active: Color::Cyan,
 
+            selection_modifier: Modifier::empty(),
+            font_weight_regular: Modifier::empty(),
+            font_weight_bold: Modifier::BOLD,
+            font_weight_italic: Modifier::ITALIC,
+        }
+    }
+
+    /// Resolve a `--theme` value: a mode, a built-in palette or a user theme.
+    ///
+    /// User themes come from `[gitai-theme "<name>"]` config sections. Each
+    /// maps slots from [`THEME_SLOTS`] to a color name or `#rrggbb` value and
+    /// may set `base` to the mode or palette it starts from.
+    pub fn from_name(
+        name: &str,
+        user_themes: &HashMap<String, HashMap<String, String>>,
+    ) -> Result<Self> {
+        if let Some(overrides) = user_themes.get(name) {
+            let base = overrides.get("base").map_or("system", String::as_str);
+            let mut theme = Self::builtin(base)
+                .ok_or_else(|| anyhow!("Theme '{name}' has unknown base '{base}'"))?;
+            for (slot, value) in overrides {
+                if slot != "base" {
+                    theme
+                        .set_slot(slot, value)
+                        .map_err(|e| anyhow!("Theme '{name}': {e}"))?;
+                }
+            }
+            return Ok(theme);
+        }
+
+        Self::builtin(name).ok_or_else(|| {
+            let mut known: Vec<&str> = vec!["dark", "light", "system"];
+            known.extend(BUILTIN_PALETTES);
+            known.extend(user_themes.keys().map(String::as_str));
+            anyhow!("Unknown theme '{name}'. Available: {}", known.join(", "))
+        })
+    }
+
+    /// Whether `name` is a mode, a built-in palette or a user theme
+    pub fn exists(name: &str, user_themes: &HashMap<String, HashMap<String, String>>) -> bool {
+        user_themes.contains_key(name)
+            || ["dark", "light", "system"]
+                .iter()
+                .chain(BUILTIN_PALETTES)
+                .any(|builtin| builtin.eq_ignore_ascii_case(name))
+    }
+
+    /// Modes and built-in palettes
+    fn builtin(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "dark" => Some(Self::new(ThemeMode::Dark)),
+            "light" => Some(Self::new(ThemeMode::Light)),
+            "system" => Some(Self::new(ThemeMode::System)),
+            // The default dark theme is Catppuccin Mocha
+            "catppuccin" => Some(Self::modern_theme(
+                ColorCapability::TrueColor,
+                ThemeMode::Dark,
+            )),
+            "solarized" => Some(Self::solarized()),
+            "gruvbox" => Some(Self::gruvbox()),
+            _ => None,
+        }
+    }
+
+    fn solarized() -> Self {
+        Self {
+            brand_primary: Color::Rgb(38, 139, 210), // blue
+
+            text_default: Color::Rgb(147, 161, 161), // base1
+            text_dimmed: Color::Rgb(88, 110, 117),   // base01
+            text_on_accent: Color::Rgb(0, 43, 54),   // base03
+
+            background_base: Color::Rgb(0, 43, 54), // base03
+            background_surface: Color::Rgb(7, 54, 66), // base02
+            background_overlay: Color::Rgb(88, 110, 117), // base01
+
+            component_active: Color::Rgb(38, 139, 210), // blue
+            component_inactive: Color::Rgb(88, 110, 117), // base01
+            component_focus: Color::Rgb(42, 161, 152),  // cyan
+
+            selection_bg: Color::Rgb(7, 54, 66),   // base02
+            selection_fg: Color::Rgb(181, 137, 0), // yellow
+
+            state_success: Color::Rgb(133, 153, 0), // green
+            state_error: Color::Rgb(220, 50, 47),   // red
+            state_warning: Color::Rgb(203, 75, 22), // orange
+            state_info: Color::Rgb(42, 161, 152),   // cyan
+
+            accent: Color::Rgb(211, 54, 130),            // magenta
+            secondary_accent: Color::Rgb(108, 113, 196), // violet
+            border: Color::Rgb(7, 54, 66),               // base02
+            border_active: Color::Rgb(38, 139, 210),     // blue
+
+            ..Self::modern_theme(ColorCapability::TrueColor, ThemeMode::Dark)
+        }
+    }
+
+    fn gruvbox() -> Self {
+        Self {
+            brand_primary: Color::Rgb(254, 128, 25), // orange
+
+            text_default: Color::Rgb(235, 219, 178), // fg
+            text_dimmed: Color::Rgb(146, 131, 116),  // gray
+            text_on_accent: Color::Rgb(29, 32, 33),  // bg0_h
+
+            background_base: Color::Rgb(29, 32, 33), // bg0_h
+            background_surface: Color::Rgb(40, 40, 40), // bg0
+            background_overlay: Color::Rgb(60, 56, 54), // bg1
+
+            component_active: Color::Rgb(254, 128, 25), // orange
+            component_inactive: Color::Rgb(102, 92, 84), // bg3
+            component_focus: Color::Rgb(131, 165, 152), // blue
+
+            selection_bg: Color::Rgb(80, 73, 69),   // bg2
+            selection_fg: Color::Rgb(250, 189, 47), // yellow
+
+            state_success: Color::Rgb(184, 187, 38), // green
+            state_error: Color::Rgb(251, 73, 52),    // red
+            state_warning: Color::Rgb(250, 189, 47), // yellow
+            state_info: Color::Rgb(131, 165, 152),   // blue
+
+            accent: Color::Rgb(211, 134, 155),           // purple
+            secondary_accent: Color::Rgb(142, 192, 124), // aqua
+            border: Color::Rgb(80, 73, 69),              // bg2
+            border_active: Color::Rgb(254, 128, 25),     // orange
+
+            ..Self::modern_theme(ColorCapability::TrueColor, ThemeMode::Dark)
+        }
+    }
+
+    /// Override one color slot with a color name, index or `#rrggbb` value
+    fn set_slot(&mut self, slot: &str, value: &str) -> Result<()> {
+        let color = Color::from_str(value.trim())
+            .map_err(|_| anyhow!("invalid color '{value}' for '{slot}'"))?;
+        let target = match slot {
+            "brand" => &mut self.brand_primary,
+            "text" => &mut self.text_default,
+            "text-dimmed" => &mut self.text_dimmed,
+            "text-on-accent" => &mut self.text_on_accent,
+            "background" => &mut self.background_base,
+            "surface" => &mut self.background_surface,
+            "overlay" => &mut self.background_overlay,
+            "focus" => &mut self.component_focus,
+            "selection-bg" => &mut self.selection_bg,
+            "selection-fg" => &mut self.selection_fg,
+            "success" => &mut self.state_success,
+            "error" => &mut self.state_error,
+            "warning" => &mut self.state_warning,
+            "info" => &mut self.state_info,
+            "accent" => &mut self.accent,
+            "secondary-accent" => &mut self.secondary_accent,
+            "border" => &mut self.border,
+            "border-active" => &mut self.border_active,
+            _ => {
+                return Err(anyhow!(
+                    "unknown color slot '{slot}' (expected one of: {})",
+                    THEME_SLOTS.join(", ")
+                ));
+            }
+        };
+        *target = color;
+        Ok(())
+    }
+
+    /// Theme without colors for `--no-color` and `NO_COLOR`.
+    ///
+    /// Every slot uses the terminal's own colors; selections are shown in
+    /// reverse video and headings keep their weight.
+    pub fn monochrome() -> Self {
+        Self {
+            capability: ColorCapability::Basic16,
+            mode: ThemeMode::Dark,
+            brand_primary: Color::Reset,
+            text_default: Color::Reset,
+            text_dimmed: Color::Reset,
+            text_on_accent: Color::Reset,
+            background_base: Color::Reset,
+            background_surface: Color::Reset,
+            background_overlay: Color::Reset,
+            component_active: Color::Reset,
+            component_inactive: Color::Reset,
+            component_focus: Color::Reset,
+            selection_bg: Color::Reset,
+            selection_fg: Color::Reset,
+            selection_modifier: Modifier::REVERSED,
+            state_success: Color::Reset,
+            state_error: Color::Reset,
+            state_warning: Color::Reset,
+            state_info: Color::Reset,
+            accent: Color::Reset,
+            secondary_accent: Color::Reset,
+            border: Color::Reset,
+            border_active: Color::Reset,
             font_weight_regular: Modifier::empty(),
             font_weight_bold: Modifier::BOLD,
             font_weight_italic: Modifier::ITALIC,
@@ -343,6 +568,34 @@ mod tests {
         assert_eq!(color, Color::Rgb(255, 0, 0)); // Modern theme should use RGB color
     }
 
+    #[test]
+    fn test_builtin_palettes_and_user_themes() {
+        let no_user_themes = HashMap::new();
+        for name in BUILTIN_PALETTES {
+            let theme = Theme::from_name(name, &no_user_themes).expect("Palette should resolve");
+            assert_eq!(theme.capability, ColorCapability::TrueColor);
+        }
+        assert!(Theme::from_name("nope", &no_user_themes).is_err());
+
+        let mut ocean = HashMap::new();
+        ocean.insert("base".to_string(), "gruvbox".to_string());
+        ocean.insert("accent".to_string(), "#0ea5e9".to_string());
+        ocean.insert("border".to_string(), "blue".to_string());
+        let user_themes = HashMap::from([("ocean".to_string(), ocean)]);
+
+        let theme = Theme::from_name("ocean", &user_themes).expect("User theme should resolve");
+        assert_eq!(theme.accent, Color::Rgb(14, 165, 233));
+        assert_eq!(theme.border, Color::Blue);
+        assert_eq!(theme.state_error, Theme::gruvbox().state_error);
+
+        let mut broken = user_themes.clone();
+        broken
+            .get_mut("ocean")
+            .expect("Theme exists")
+            .insert("accent".to_string(), "not-a-color".to_string());
+        assert!(Theme::from_name("ocean", &broken).is_err());
+    }
+
     #[test]
     fn test_capability_detection() {
         // Test basic detection - should not panic
@@ -0,0 +1,202 @@
+//! Local, opt-in counters of how generated commit messages are used.
+//!
+//! With `gitai.usage-stats` on, every interactive commit session adds one
+//! outcome to `<data dir>/gitai/usage.json`, keyed by provider and message
+//! preset. Only counts are kept, never messages or repository names, and
+//! nothing leaves the machine. `gitai usage` reports them.
+
+use crate::config::{CONVENTIONAL_INSTRUCTIONS, Config};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// How a commit session ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    /// Committed the first generated message unchanged
+    Accepted,
+    /// Committed after editing the message
+    Edited,
+    /// Committed an unchanged message after regenerating or refining
+    Regenerated,
+    /// Left without committing
+    Cancelled,
+}
+
+impl Outcome {
+    /// Classifies a session from what happened in it; an edit outweighs regenerations
+    pub fn classify(committed: bool, edited: bool, regenerations: usize) -> Self {
+        match (committed, edited, regenerations) {
+            (false, _, _) => Self::Cancelled,
+            (true, true, _) => Self::Edited,
+            (true, false, 0) => Self::Accepted,
+            (true, false, _) => Self::Regenerated,
+        }
+    }
+}
+
+/// Session counts of one provider and preset
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Counters {
+    pub accepted: u32,
+    pub edited: u32,
+    pub regenerated: u32,
+    pub cancelled: u32,
+    /// Messages generated after the first, across all sessions
+    pub regenerations: u32,
+}
+
+impl Counters {
+    fn record(&mut self, outcome: Outcome, regenerations: usize) {
+        let count = match outcome {
+            Outcome::Accepted => &mut self.accepted,
+            Outcome::Edited => &mut self.edited,
+            Outcome::Regenerated => &mut self.regenerated,
+            Outcome::Cancelled => &mut self.cancelled,
+        };
+        *count = count.saturating_add(1);
+        self.regenerations = self
+            .regenerations
+            .saturating_add(u32::try_from(regenerations).unwrap_or(u32::MAX));
+    }
+
+    pub fn committed(&self) -> u32 {
+        self.accepted + self.edited + self.regenerated
+    }
+
+    pub fn sessions(&self) -> u32 {
+        self.committed() + self.cancelled
+    }
+
+    /// Share of commits that used the first message unchanged
+    pub fn acceptance_rate(&self) -> Option<f64> {
+        let committed = self.committed();
+        (committed > 0).then(|| f64::from(self.accepted) / f64::from(committed))
+    }
+}
+
+/// Counters by provider, then by preset
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UsageStats {
+    /// When the first session was recorded
+    pub since: Option<DateTime<Utc>>,
+    pub providers: BTreeMap<String, BTreeMap<String, Counters>>,
+}
+
+impl UsageStats {
+    /// Reads the stats file; a missing file has no sessions yet
+    pub fn load(path: &Path) -> Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(content) => serde_json::from_str(&content)
+                .with_context(|| format!("Failed to parse {}", path.display())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    pub fn record(&mut self, provider: &str, preset: &str, outcome: Outcome, regenerations: usize) {
+        self.since.get_or_insert_with(Utc::now);
+        self.providers
+            .entry(provider.to_string())
+            .or_default()
+            .entry(preset.to_string())
+            .or_default()
+            .record(outcome, regenerations);
+    }
+}
+
+/// Location of the stats file
+pub fn stats_path() -> Option<PathBuf> {
+    dirs::data_local_dir().map(|dir| dir.join("gitai").join("usage.json"))
+}
+
+/// The message style the config amounts to, as set by `gitai init`
+pub fn preset(config: &Config) -> &'static str {
+    let instructions = config.instructions.trim();
+    if config.gitmoji == Some(true) {
+        "gitmoji"
+    } else if instructions == CONVENTIONAL_INSTRUCTIONS {
+        "conventional"
+    } else if instructions.is_empty() {
+        "plain"
+    } else {
+        "custom"
+    }
+}
+
+/// Adds a session's outcome to the stats when `gitai.usage-stats` is on
+pub fn record(config: &Config, provider: &str, outcome: Outcome, regenerations: usize) {
+    if config.usage_stats != Some(true) {
+        return;
+    }
+    let Some(path) = stats_path() else {
+        return;
+    };
+    let recorded = UsageStats::load(&path).and_then(|mut stats| {
+        stats.record(provider, preset(config), outcome, regenerations);
+        stats.save(&path)
+    });
+    if let Err(e) = recorded {
+        debug!("Failed to record usage stats: {e:#}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_outcomes_are_counted_per_provider_and_preset() {
+        assert_eq!(Outcome::classify(true, false, 0), Outcome::Accepted);
+        assert_eq!(Outcome::classify(true, false, 2), Outcome::Regenerated);
+        assert_eq!(Outcome::classify(true, true, 2), Outcome::Edited);
+        assert_eq!(Outcome::classify(false, true, 0), Outcome::Cancelled);
+
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let path = dir.path().join("usage.json");
+        let mut stats = UsageStats::load(&path).expect("Missing file is empty");
+        stats.record("google", "conventional", Outcome::Accepted, 0);
+        stats.record("google", "conventional", Outcome::Accepted, 0);
+        stats.record("google", "conventional", Outcome::Edited, 1);
+        stats.record("google", "conventional", Outcome::Regenerated, 2);
+        stats.record("openrouter", "plain", Outcome::Cancelled, 0);
+        stats.save(&path).expect("Failed to save stats");
+
+        let stats = UsageStats::load(&path).expect("Failed to load stats");
+        let google = stats.providers["google"]["conventional"];
+        assert_eq!(google.sessions(), 4);
+        assert_eq!(google.regenerations, 3);
+        assert_eq!(google.acceptance_rate(), Some(0.5));
+        assert_eq!(
+            stats.providers["openrouter"]["plain"].acceptance_rate(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_preset() {
+        let mut config = Config::default();
+        assert_eq!(preset(&config), "plain");
+        config.instructions = CONVENTIONAL_INSTRUCTIONS.to_string();
+        assert_eq!(preset(&config), "conventional");
+        config.gitmoji = Some(true);
+        assert_eq!(preset(&config), "gitmoji");
+        config.gitmoji = Some(false);
+        config.instructions = "Mention the ticket".to_string();
+        assert_eq!(preset(&config), "custom");
+    }
+}
@@ -0,0 +1,79 @@
+use anyhow::{Context, Result, anyhow};
+use clap::{Args, Subcommand};
+use cloy::conventions::ProjectConventions;
+use cloy::git::GitRepo;
+use cloy::output;
+use log::debug;
+
+/// Default number of commits scanned by `analyze`
+const DEFAULT_ANALYZE_COUNT: usize = 200;
+
+#[derive(Args, Clone, Debug)]
+pub struct ConventionsArgs {
+    #[command(subcommand)]
+    pub command: ConventionsCommand,
+}
+
+#[derive(Subcommand, Clone, Debug)]
+pub enum ConventionsCommand {
+    /// Detect commit conventions from history and write them to .gitai
+    Analyze {
+        #[arg(
+            long,
+            short = 'n',
+            default_value_t = DEFAULT_ANALYZE_COUNT,
+            help = "Number of recent commits to scan"
+        )]
+        count: usize,
+
+        #[arg(long, help = "Print the detected conventions without writing the file")]
+        dry_run: bool,
+    },
+}
+
+/// Handles the conventions command.
+///
+/// `analyze` scans the latest commits of all authors, infers the conventions
+/// the project follows and stores them in `.gitai` at the repository
+/// root, where the commit message generator picks them up automatically.
+pub fn handle_conventions(args: &ConventionsArgs) -> Result<()> {
+    match &args.command {
+        ConventionsCommand::Analyze { count, dry_run } => analyze(*count, *dry_run),
+    }
+}
+
+fn analyze(count: usize, dry_run: bool) -> Result<()> {
+    let repo = GitRepo::open_local().context("Not in a Git repository")?;
+    let root = repo
+        .workdir()
+        .ok_or_else(|| anyhow!("Cannot analyze conventions in a bare repository"))?
+        .to_path_buf();
+
+    let messages: Vec<String> = GitRepo::new(&root)?
+        .get_recent_commits(count)?
+        .into_iter()
+        .map(|c| c.message)
+        .collect();
+    debug!("Analyzing conventions over {} commits", messages.len());
+
+    if messages.is_empty() {
+        return Err(anyhow!("No commits found to analyze"));
+    }
+
+    let conventions = ProjectConventions::detect(&messages);
+    let content = conventions.to_file_content();
+
+    if dry_run {
+        output::print_bordered_content(&content);
+        return Ok(());
+    }
+
+    let path = conventions.save(&root)?;
+    output::print_bordered_content(&content);
+    output::print_success(&format!(
+        "Saved conventions from {} commits to {}",
+        conventions.sample_size,
+        path.display()
+    ));
+    Ok(())
+}
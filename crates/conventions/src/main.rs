@@ -0,0 +1,37 @@
+use clap::Parser;
+use cloy::{error, init_app};
+use conventions::{ConventionsArgs, handle_conventions};
+
+#[derive(Parser)]
+#[command(
+    name = "git-conventions",
+    version,
+    author,
+    about = "Detect and store the repository's commit conventions",
+    styles = cloy::app::args::get_styles(),
+)]
+struct ConventionsCli {
+    #[command(flatten)]
+    args: ConventionsArgs,
+}
+
+fn main() {
+    init_app();
+
+    let cli = ConventionsCli::parse();
+
+    if let Err(e) = handle_conventions(&cli.args) {
+        std::process::exit(error::report(&e));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn verify_cli() {
+        ConventionsCli::command().debug_assert();
+    }
+}
@@ -0,0 +1,579 @@
+//! The individual diagnostics, in the order they are reported
+
+use crate::{Check, Status};
+use cloy::config::{Config, get_api_key_env_var};
+use cloy::config_migration;
+use cloy::git::message_hook_installed;
+use cloy::http::{self, HttpSettings};
+use cloy::llm::bedrock::AwsCredentials;
+use cloy::llm::endpoint::{BASE_URL_PARAM, validate_endpoint};
+use cloy::llm::model_info::{self, ModelInfo, RESPONSE_RESERVE_PARAM};
+use cloy::llm::provider::ProviderKind;
+use git2::Repository;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+/// Hooks gitai runs itself when it creates a commit
+const HOOKS: &[&str] = &["pre-commit", "post-commit"];
+
+/// Prompt budget below which most diffs have to be cut
+const MIN_INPUT_BUDGET: usize = 8_192;
+
+/// Runs every check; later checks are skipped when what they need is missing
+pub async fn run_all(config: &Config, offline: bool) -> Vec<Check> {
+    let active = config.active_provider();
+    let provider = active.as_str();
+    let api_key = config
+        .get_provider_config(provider)
+        .map(|p| p.api_key.clone())
+        .unwrap_or_default();
+    let model = config
+        .get_provider_config(provider)
+        .map(|p| p.model_name.clone())
+        .filter(|m| !m.is_empty())
+        .unwrap_or_else(|| active.default_model().to_string());
+
+    let mut checks = vec![check_git_binary()];
+    let repo = Repository::discover(".");
+    checks.push(check_repository(repo.as_ref()));
+    checks.push(check_config_layout(repo.as_ref().ok()));
+    checks.push(check_api_key(active, &api_key));
+    let endpoint = check_endpoint(config, active);
+    let endpoint_ok = endpoint.status == Status::Pass;
+    checks.push(endpoint);
+    checks.push(check_network(http::settings(), active, |name| {
+        std::env::var_os(name).is_some()
+    }));
+
+    let info = if offline {
+        checks.push(Check::skip("Provider", "--offline"));
+        checks.push(Check::skip("Model", "--offline"));
+        None
+    } else if api_key.is_empty() && active.requires_api_key() {
+        checks.push(Check::skip("Provider", "no API key"));
+        checks.push(Check::skip("Model", "no API key"));
+        None
+    } else if !endpoint_ok {
+        checks.push(Check::skip("Provider", "endpoint not configured"));
+        checks.push(Check::skip("Model", "endpoint not configured"));
+        None
+    } else if !active.has_model_api() {
+        let detail = format!("{active} has no model API to probe");
+        checks.push(Check::skip("Provider", detail.clone()));
+        checks.push(Check::skip("Model", detail));
+        None
+    } else {
+        let (provider_check, model_check, info) = check_provider(active, &model, &api_key).await;
+        checks.push(provider_check);
+        checks.push(model_check);
+        info
+    };
+
+    checks.push(check_token_limits(config, active, info.as_ref()));
+    checks.push(match &repo {
+        Ok(repo) => check_hooks(repo),
+        Err(_) => Check::skip("Hooks", "not in a repository"),
+    });
+    checks.push(check_cache());
+    checks
+}
+
+fn check_git_binary() -> Check {
+    const NAME: &str = "Git";
+    match Command::new("git").arg("--version").output() {
+        Ok(output) if output.status.success() => {
+            Check::pass(NAME, String::from_utf8_lossy(&output.stdout).trim())
+        }
+        Ok(output) => Check::fail(
+            NAME,
+            format!(
+                "git --version failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ),
+            "Reinstall git",
+        ),
+        Err(e) => Check::fail(
+            NAME,
+            format!("git is not on PATH: {e}"),
+            "Install git and make sure it is on PATH",
+        ),
+    }
+}
+
+fn check_repository(repo: Result<&Repository, &git2::Error>) -> Check {
+    const NAME: &str = "Repository";
+    match repo {
+        Ok(repo) => match repo.workdir() {
+            Some(workdir) => Check::pass(NAME, workdir.display().to_string()),
+            None => Check::warn(
+                NAME,
+                format!("{} is a bare repository", repo.path().display()),
+                "Run gitai from a checkout with a working tree",
+            ),
+        },
+        Err(e) => Check::fail(
+            NAME,
+            format!("No repository found: {}", e.message()),
+            "Run from inside a git repository, or create one with `git init`",
+        ),
+    }
+}
+
+fn check_config_layout(repo: Option<&Repository>) -> Check {
+    const NAME: &str = "Config layout";
+    let outdated: Vec<&str> = config_migration::config_files(repo)
+        .into_iter()
+        .filter(|(_, config)| {
+            config_migration::entries(config)
+                .is_ok_and(|entries| config_migration::plan(&entries).changes_settings())
+        })
+        .map(|(name, _)| name)
+        .collect();
+    if outdated.is_empty() {
+        Check::pass(NAME, "current")
+    } else {
+        Check::warn(
+            NAME,
+            format!(
+                "The {} has settings gitai no longer reads",
+                outdated.join(" and ")
+            ),
+            "Run `gitai config migrate --dry-run` to list the changes, then `gitai config migrate`",
+        )
+    }
+}
+
+fn check_api_key(active: ProviderKind, api_key: &str) -> Check {
+    const NAME: &str = "API key";
+    let provider = active.as_str();
+    if !api_key.is_empty() {
+        return Check::pass(NAME, format!("set for {provider}"));
+    }
+    if active == ProviderKind::Bedrock {
+        return match AwsCredentials::load() {
+            Some(credentials) => Check::pass(
+                NAME,
+                format!("AWS credentials for {}", credentials.access_key_id),
+            ),
+            None => Check::fail(
+                NAME,
+                "No AWS credentials or Bedrock API key",
+                "export AWS_ACCESS_KEY_ID and AWS_SECRET_ACCESS_KEY, set AWS_PROFILE, or export AWS_BEARER_TOKEN_BEDROCK=<key>",
+            ),
+        };
+    }
+    if !active.requires_api_key() {
+        return Check::pass(NAME, format!("not required for {provider}"));
+    }
+    let env = get_api_key_env_var(provider).unwrap_or("the provider's API key variable");
+    Check::fail(
+        NAME,
+        format!("No API key for {provider}"),
+        format!("export {env}=<key>, or `git config --global gitai.{provider}-apikey <key>`"),
+    )
+}
+
+/// The endpoint settings of providers that are not reached at a fixed URL
+fn check_endpoint(config: &Config, active: ProviderKind) -> Check {
+    const NAME: &str = "Endpoint";
+    let params = config
+        .get_provider_config(active.as_str())
+        .map(|p| p.additional_params.clone())
+        .unwrap_or_default();
+    match validate_endpoint(active, &params) {
+        Ok(()) => match params.get(BASE_URL_PARAM) {
+            Some(base_url) => Check::pass(NAME, base_url.clone()),
+            None => Check::pass(NAME, format!("{active} default")),
+        },
+        Err(e) => Check::fail(
+            NAME,
+            e.to_string(),
+            "See \"Choose a provider\" in the README for the settings each provider needs",
+        ),
+    }
+}
+
+/// The proxy and CA settings, and whether they reach the active provider's client
+fn check_network(
+    settings: &HttpSettings,
+    active: ProviderKind,
+    env_is_set: impl Fn(&str) -> bool,
+) -> Check {
+    const NAME: &str = "Network";
+    if let Err(e) = settings.validate() {
+        return Check::fail(
+            NAME,
+            format!("{e:#}"),
+            "Fix gitai.proxy, gitai.ca-bundle or gitai.tls-backend",
+        );
+    }
+    if !settings.is_configured() {
+        return Check::pass(NAME, "no proxy or CA bundle configured");
+    }
+    // The llm backends build their own clients, which only read the standard variables
+    if !active.uses_own_client() {
+        let missed = settings.missed_by_backends(env_is_set);
+        if !missed.is_empty() {
+            let names: Vec<&str> = missed.iter().map(|(setting, _)| *setting).collect();
+            return Check::fail(
+                NAME,
+                format!("{} cannot reach the {active} client", names.join(", ")),
+                http::backend_fix(&missed),
+            );
+        }
+    }
+    let mut detail = Vec::new();
+    if let Some(proxy) = &settings.proxy {
+        detail.push(format!("proxy {proxy}"));
+    }
+    if let Some(path) = &settings.ca_bundle {
+        detail.push(format!("CA bundle {}", path.display()));
+    }
+    if let Some(backend) = &settings.tls_backend {
+        detail.push(format!("{backend} TLS"));
+    }
+    Check::pass(NAME, detail.join(", "))
+}
+
+/// Why probing the model failed
+#[derive(Debug, PartialEq, Eq)]
+enum ProbeFailure {
+    Unreachable(String),
+    KeyRejected(u16),
+    ModelNotFound,
+    Other(String),
+}
+
+fn classify_probe_error(error: &anyhow::Error) -> ProbeFailure {
+    let request_error = error
+        .chain()
+        .find_map(|e| e.downcast_ref::<reqwest::Error>());
+    match request_error.and_then(reqwest::Error::status) {
+        // Google answers an invalid key with 400 rather than 401
+        Some(status) if matches!(status.as_u16(), 400 | 401 | 403) => {
+            ProbeFailure::KeyRejected(status.as_u16())
+        }
+        Some(status) if status.as_u16() == 404 => ProbeFailure::ModelNotFound,
+        None if request_error.is_some() => ProbeFailure::Unreachable(format!("{error:#}")),
+        None if error.to_string().contains("not found") => ProbeFailure::ModelNotFound,
+        _ => ProbeFailure::Other(format!("{error:#}")),
+    }
+}
+
+/// Provider reachability (including the key) and the model, from one probe
+async fn check_provider(
+    active: ProviderKind,
+    model: &str,
+    api_key: &str,
+) -> (Check, Check, Option<ModelInfo>) {
+    const PROVIDER: &str = "Provider";
+    const MODEL: &str = "Model";
+    let provider = active.as_str();
+
+    let error = match model_info::fetch_model_info(active, model, api_key).await {
+        Ok(info) => {
+            let model_check = Check::pass(
+                MODEL,
+                format!("{model} ({} token context window)", info.context_length),
+            );
+            return (
+                Check::pass(PROVIDER, format!("{provider} reachable")),
+                model_check,
+                Some(info),
+            );
+        }
+        Err(e) => e,
+    };
+
+    let skipped_model = || Check::skip(MODEL, format!("{provider} not reachable"));
+    let (provider_check, model_check) = match classify_probe_error(&error) {
+        ProbeFailure::Unreachable(detail) => (
+            Check::fail(
+                PROVIDER,
+                detail,
+                "Check the network connection and any proxy settings",
+            ),
+            skipped_model(),
+        ),
+        ProbeFailure::KeyRejected(status) => (
+            Check::fail(
+                PROVIDER,
+                format!("{provider} rejected the API key (HTTP {status})"),
+                format!("Replace the key with `git config --global gitai.{provider}-apikey <key>`"),
+            ),
+            skipped_model(),
+        ),
+        ProbeFailure::ModelNotFound => (
+            Check::pass(PROVIDER, format!("{provider} reachable")),
+            Check::fail(
+                MODEL,
+                format!("{provider} does not know the model {model}"),
+                format!(
+                    "Pick an available model with `git config --global gitai.{provider}-model <model>` (default: {})",
+                    active.default_model()
+                ),
+            ),
+        ),
+        ProbeFailure::Other(detail) => (
+            Check::fail(
+                PROVIDER,
+                detail,
+                "Retry later; the provider may be having an outage",
+            ),
+            skipped_model(),
+        ),
+    };
+    (provider_check, model_check, None)
+}
+
+/// Whether the reserved response leaves a usable prompt budget
+fn check_token_limits(config: &Config, active: ProviderKind, info: Option<&ModelInfo>) -> Check {
+    const NAME: &str = "Token limits";
+    let provider = active.as_str();
+    let params = config
+        .get_provider_config(provider)
+        .map(|p| &p.additional_params);
+
+    for key in [RESPONSE_RESERVE_PARAM, "max_tokens"] {
+        if let Some(value) = params.and_then(|p| p.get(key))
+            && value.trim().parse::<usize>().is_err()
+        {
+            return Check::fail(
+                NAME,
+                format!("{key} is not a number: {value}"),
+                format!("git config --global gitai.{provider}-additional{key} <tokens>"),
+            );
+        }
+    }
+
+    let Some(info) = info else {
+        return Check::skip(NAME, "context window unknown");
+    };
+    let reserve = model_info::response_reserve(params);
+    let budget = info.context_length.saturating_sub(reserve);
+    let fix = format!(
+        "Lower gitai.{provider}-additional{RESPONSE_RESERVE_PARAM} (or max_tokens), or use a model with a larger context window"
+    );
+    if budget == 0 {
+        return Check::fail(
+            NAME,
+            format!(
+                "{reserve} tokens reserved for the response leave nothing of the {} token window",
+                info.context_length
+            ),
+            fix,
+        );
+    }
+    if let Some(max_output) = info.max_output_tokens
+        && reserve > max_output
+    {
+        return Check::warn(
+            NAME,
+            format!(
+                "{reserve} tokens reserved for the response, the model writes at most {max_output}"
+            ),
+            format!("git config --global gitai.{provider}-additionalmax_tokens {max_output}"),
+        );
+    }
+    if budget < MIN_INPUT_BUDGET {
+        return Check::warn(
+            NAME,
+            format!("Only {budget} tokens left for the prompt; large diffs will be cut"),
+            fix,
+        );
+    }
+    Check::pass(
+        NAME,
+        format!("{budget} prompt tokens, {reserve} reserved for the response"),
+    )
+}
+
+/// Hooks gitai runs must be executable, and must live where gitai looks for them
+fn check_hooks(repo: &Repository) -> Check {
+    const NAME: &str = "Hooks";
+    let hooks_dir = repo.path().join("hooks");
+
+    let not_executable: Vec<&str> = HOOKS
+        .iter()
+        .copied()
+        .filter(|hook| {
+            let path = hooks_dir.join(hook);
+            path.exists() && !is_executable(&path)
+        })
+        .collect();
+    if !not_executable.is_empty() {
+        return Check::fail(
+            NAME,
+            format!("Not executable: {}", not_executable.join(", ")),
+            format!(
+                "chmod +x {}",
+                not_executable
+                    .iter()
+                    .map(|hook| hooks_dir.join(hook).display().to_string())
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            ),
+        );
+    }
+
+    if let Ok(hooks_path) = repo.config().and_then(|c| c.get_string("core.hooksPath")) {
+        return Check::warn(
+            NAME,
+            format!("core.hooksPath is {hooks_path}, but gitai runs hooks from .git/hooks"),
+            format!(
+                "Link the hooks gitai should run into {}",
+                hooks_dir.display()
+            ),
+        );
+    }
+
+    let installed: Vec<&str> = HOOKS
+        .iter()
+        .copied()
+        .filter(|hook| hooks_dir.join(hook).exists())
+        .collect();
+    let mut detail = if installed.is_empty() {
+        "none installed".to_string()
+    } else {
+        format!("{} will run", installed.join(", "))
+    };
+    if message_hook_installed(repo) {
+        detail.push_str("; prepare-commit-msg drafts messages");
+    }
+    Check::pass(NAME, detail)
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    fs::metadata(path).is_ok_and(|m| m.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+/// The style profile cache is optional, but a broken one costs a history scan per run
+fn check_cache() -> Check {
+    const NAME: &str = "Cache";
+    let Some(dir) = dirs::cache_dir().map(|dir| dir.join("gitai")) else {
+        return Check::warn(
+            NAME,
+            "No cache directory on this platform",
+            "Set XDG_CACHE_HOME to a writable directory",
+        );
+    };
+    let probe = dir.join(".doctor-probe");
+    let result = fs::create_dir_all(&dir)
+        .and_then(|()| fs::write(&probe, b"ok"))
+        .and_then(|()| fs::remove_file(&probe));
+    match result {
+        Ok(()) => Check::pass(NAME, format!("{} is writable", dir.display())),
+        Err(e) => Check::warn(
+            NAME,
+            format!("{} is not writable: {e}", dir.display()),
+            format!(
+                "Fix the permissions of {}, or point XDG_CACHE_HOME elsewhere",
+                dir.display()
+            ),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Status;
+
+    fn set_param(config: &mut Config, key: &str, value: &str) {
+        config
+            .providers
+            .get_mut(ProviderKind::Google.as_str())
+            .expect("Google is always configured")
+            .additional_params
+            .insert(key.to_string(), value.to_string());
+    }
+
+    #[test]
+    fn test_token_limits() {
+        let mut config = Config::default();
+        let info = ModelInfo {
+            model_id: "m".to_string(),
+            context_length: 32_000,
+            max_output_tokens: Some(8_000),
+            cached_at: std::time::Instant::now(),
+        };
+        assert_eq!(
+            check_token_limits(&config, ProviderKind::Google, Some(&info)).status,
+            Status::Pass
+        );
+
+        set_param(&mut config, RESPONSE_RESERVE_PARAM, "30000");
+        assert_eq!(
+            check_token_limits(&config, ProviderKind::Google, Some(&info)).status,
+            Status::Warn
+        );
+        set_param(&mut config, RESPONSE_RESERVE_PARAM, "40000");
+        assert_eq!(
+            check_token_limits(&config, ProviderKind::Google, Some(&info)).status,
+            Status::Fail
+        );
+        set_param(&mut config, "max_tokens", "lots");
+        let check = check_token_limits(&config, ProviderKind::Google, None);
+        assert_eq!(check.status, Status::Fail);
+        assert!(check.detail.contains("max_tokens"));
+    }
+
+    #[test]
+    fn test_network_settings_reach_the_client() {
+        let settings = HttpSettings {
+            proxy: Some("http://proxy.example.com:3128".to_string()),
+            ..HttpSettings::default()
+        };
+        let check = check_network(&settings, ProviderKind::Google, |_| false);
+        assert_eq!(check.status, Status::Pass);
+        assert_eq!(check.detail, "proxy http://proxy.example.com:3128");
+
+        let check = check_network(&settings, ProviderKind::OpenRouter, |_| false);
+        assert_eq!(check.status, Status::Fail);
+        assert_eq!(
+            check.fix.as_deref(),
+            Some("Export HTTPS_PROXY as well, or unset the rest")
+        );
+        let check = check_network(&settings, ProviderKind::OpenRouter, |name| {
+            name == "HTTPS_PROXY"
+        });
+        assert_eq!(check.status, Status::Pass);
+
+        let invalid = HttpSettings {
+            tls_backend: Some("schannel".to_string()),
+            ..HttpSettings::default()
+        };
+        let check = check_network(&invalid, ProviderKind::Google, |_| false);
+        assert_eq!(check.status, Status::Fail);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_hooks_must_be_executable() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let repo = Repository::init(dir.path()).expect("Failed to init repo");
+        let hook = repo.path().join("hooks").join("pre-commit");
+        fs::create_dir_all(hook.parent().expect("hooks dir")).expect("Failed to create hooks");
+        fs::write(&hook, "#!/bin/sh\n").expect("Failed to write hook");
+        fs::set_permissions(&hook, fs::Permissions::from_mode(0o644))
+            .expect("Failed to set permissions");
+
+        let check = check_hooks(&repo);
+        assert_eq!(check.status, Status::Fail);
+        assert!(check.fix.is_some_and(|fix| fix.starts_with("chmod +x")));
+
+        fs::set_permissions(&hook, fs::Permissions::from_mode(0o755))
+            .expect("Failed to set permissions");
+        assert_eq!(check_hooks(&repo).detail, "pre-commit will run");
+    }
+}
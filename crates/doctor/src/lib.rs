@@ -0,0 +1,148 @@
+//! Environment diagnostics.
+//!
+//! Each check reports whether one thing gitai depends on is in place and,
+//! when it is not, the command or setting that fixes it. Network checks probe
+//! the provider's model API, which also confirms the key and the model name
+//! without spending tokens on a generation.
+
+pub mod checks;
+
+use anyhow::{Result, anyhow};
+use clap::Args;
+use cloy::common::CommonParams;
+use cloy::config::Config;
+use std::fmt::{self, Write};
+
+#[derive(Args, Clone, Debug)]
+pub struct DoctorParams {
+    #[arg(long, help = "Skip the checks that contact the provider")]
+    pub offline: bool,
+}
+
+/// Outcome of a single check
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Pass,
+    Warn,
+    Fail,
+    /// Not run because an earlier check failed or `--offline` was given
+    Skip,
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Pass => "ok",
+            Self::Warn => "warn",
+            Self::Fail => "fail",
+            Self::Skip => "skip",
+        })
+    }
+}
+
+/// A check's result and, unless it passed, how to fix it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Check {
+    pub name: &'static str,
+    pub status: Status,
+    pub detail: String,
+    pub fix: Option<String>,
+}
+
+impl Check {
+    fn pass(name: &'static str, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: Status::Pass,
+            detail: detail.into(),
+            fix: None,
+        }
+    }
+
+    fn skip(name: &'static str, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: Status::Skip,
+            detail: detail.into(),
+            fix: None,
+        }
+    }
+
+    fn warn(name: &'static str, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: Status::Warn,
+            detail: detail.into(),
+            fix: Some(fix.into()),
+        }
+    }
+
+    fn fail(name: &'static str, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: Status::Fail,
+            detail: detail.into(),
+            fix: Some(fix.into()),
+        }
+    }
+}
+
+/// Handles the doctor command.
+pub async fn handle_doctor(common: &CommonParams, params: &DoctorParams) -> Result<()> {
+    let mut config = Config::load()?;
+    common.apply_to_config(&mut config)?;
+
+    let checks = checks::run_all(&config, params.offline).await;
+    print!("{}", format_report(&checks));
+
+    let failed = checks.iter().filter(|c| c.status == Status::Fail).count();
+    if failed > 0 {
+        return Err(anyhow!(
+            "{failed} check{} failed",
+            if failed == 1 { "" } else { "s" }
+        ));
+    }
+    Ok(())
+}
+
+/// Renders one line per check, with the fix indented below failures and warnings
+pub fn format_report(checks: &[Check]) -> String {
+    let width = checks.iter().map(|c| c.name.len()).max().unwrap_or(0);
+    let mut out = String::new();
+    for check in checks {
+        let _ = writeln!(
+            out,
+            "{:<6} {:<width$}  {}",
+            format!("[{}]", check.status),
+            check.name,
+            check.detail
+        );
+        if let Some(fix) = &check.fix {
+            let _ = writeln!(out, "{:<6} {:<width$}  fix: {fix}", "", "");
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_report() {
+        let checks = vec![
+            Check::pass("Git", "git version 2.45.0"),
+            Check::fail(
+                "API key",
+                "No key for google",
+                "export GOOGLE_API_KEY=<key>",
+            ),
+        ];
+        assert_eq!(
+            format_report(&checks),
+            "[ok]   Git      git version 2.45.0\n\
+             [fail] API key  No key for google\n\
+             \x20               fix: export GOOGLE_API_KEY=<key>\n"
+        );
+    }
+}
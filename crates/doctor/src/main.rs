@@ -0,0 +1,43 @@
+use clap::Parser;
+use cloy::app::defaults;
+use cloy::common::CommonParams;
+use cloy::{error, init_app};
+use doctor::{DoctorParams, handle_doctor};
+
+#[derive(Parser)]
+#[command(
+    name = "git-doctor",
+    version,
+    author,
+    about = "Check the environment gitai needs and suggest fixes",
+    styles = cloy::app::args::get_styles(),
+)]
+struct DoctorCli {
+    #[command(flatten)]
+    common: CommonParams,
+
+    #[command(flatten)]
+    params: DoctorParams,
+}
+
+#[tokio::main]
+async fn main() {
+    init_app();
+
+    let DoctorCli { common, params } = defaults::parse::<DoctorCli>("doctor");
+
+    if let Err(e) = handle_doctor(&common, &params).await {
+        std::process::exit(error::report(&e));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn verify_cli() {
+        DoctorCli::command().debug_assert();
+    }
+}
@@ -0,0 +1,147 @@
+//! Offline evaluation of generated commit messages against repository history.
+//!
+//! Each of the last N commits is replayed as if its changes were staged on its
+//! parent, a message is generated, and the result is scored against the
+//! message the author actually wrote. Running it with different `--model`
+//! values gives comparable numbers for picking a default.
+
+pub mod metrics;
+
+use anyhow::{Result, anyhow};
+use clap::Args;
+use cloy::commands::commit::types::format_commit_message;
+use cloy::commands::common::service::create_commit_service;
+use cloy::common::CommonParams;
+use cloy::config::Config;
+use cloy::llm::embeddings::{cosine_similarity, embed_texts, embedding_model};
+use cloy::llm::provider::ProviderKind;
+use cloy::output;
+use log::debug;
+use metrics::{EvalSummary, Sample, bleu, type_matches};
+
+/// Default number of commits replayed
+const DEFAULT_EVAL_COUNT: usize = 200;
+
+#[derive(Args, Clone, Debug)]
+pub struct EvalParams {
+    #[arg(
+        long,
+        default_value_t = DEFAULT_EVAL_COUNT,
+        help = "Number of recent non-merge commits to replay"
+    )]
+    pub last: usize,
+
+    #[arg(long, help = "Print the metrics as JSON")]
+    pub json: bool,
+}
+
+/// A generated message next to the original it is scored against
+struct Replay {
+    generated: String,
+    original: String,
+}
+
+/// Handles the eval command.
+///
+/// Progress and failures go to stderr; the aggregate metrics go to stdout.
+pub async fn handle_eval(
+    common: CommonParams,
+    repository_url: Option<String>,
+    params: &EvalParams,
+) -> Result<()> {
+    let mut config = Config::load()?;
+    common.apply_to_config(&mut config)?;
+    let service = create_commit_service(&common, repository_url, &config)?;
+    let instructions = common
+        .instructions
+        .clone()
+        .unwrap_or_else(|| config.instructions.clone());
+
+    let commits: Vec<_> = service
+        .get_recent_commits(params.last * 2)?
+        .into_iter()
+        .filter(|commit| !commit.message.starts_with("Merge "))
+        .take(params.last)
+        .collect();
+    if commits.is_empty() {
+        return Err(anyhow!("No commits found to evaluate"));
+    }
+
+    let mut replays = Vec::with_capacity(commits.len());
+    let mut failures = 0;
+    for (i, commit) in commits.iter().enumerate() {
+        let short = &commit.hash[..commit.hash.len().min(7)];
+        eprintln!("[{}/{}] {short}", i + 1, commits.len());
+        match service.replay_commit(&instructions, &commit.hash).await {
+            Ok(message) => replays.push(Replay {
+                generated: format_commit_message(&message),
+                original: commit.message.trim().to_string(),
+            }),
+            Err(e) => {
+                output::print_error(&format!("Skipping {short}: {e}"));
+                failures += 1;
+            }
+        }
+    }
+
+    let similarities = embedding_similarities(&config, &replays).await;
+    let samples: Vec<Sample> = replays
+        .iter()
+        .enumerate()
+        .map(|(i, replay)| Sample {
+            bleu: bleu(&replay.generated, &replay.original),
+            embedding_similarity: similarities.as_ref().map(|s| s[i]),
+            type_match: type_matches(&replay.generated, &replay.original),
+        })
+        .collect();
+
+    let provider = config.active_provider().as_str();
+    let model = config
+        .get_provider_config(provider)
+        .map(|p| p.model_name.clone())
+        .unwrap_or_default();
+    let summary = EvalSummary::from_samples(&format!("{provider}/{model}"), &samples, failures);
+
+    if params.json {
+        println!("{}", serde_json::to_string_pretty(&summary)?);
+    } else {
+        println!("{}", summary.to_text());
+    }
+    Ok(())
+}
+
+/// Cosine similarity of each generated message to its original.
+///
+/// `None` without an embedding model or when embedding fails; the metric is
+/// then reported as unavailable rather than failing the run.
+async fn embedding_similarities(config: &Config, replays: &[Replay]) -> Option<Vec<f32>> {
+    let provider = ProviderKind::Google.as_str();
+    if replays.is_empty() || embedding_model(config, provider).is_none() {
+        return None;
+    }
+
+    let texts = replays
+        .iter()
+        .flat_map(|r| [r.generated.clone(), r.original.clone()])
+        .collect();
+    match embed_texts(config, provider, texts).await {
+        Ok(vectors) if vectors.len() == replays.len() * 2 => Some(
+            vectors
+                .chunks(2)
+                .map(|pair| cosine_similarity(&pair[0], &pair[1]))
+                .collect(),
+        ),
+        Ok(vectors) => {
+            debug!(
+                "Expected {} embeddings, got {}",
+                replays.len() * 2,
+                vectors.len()
+            );
+            None
+        }
+        Err(e) => {
+            output::print_warning(&format!("Embedding similarity unavailable: {e}"));
+            None
+        }
+    }
+}
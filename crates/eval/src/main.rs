@@ -0,0 +1,44 @@
+use clap::Parser;
+use cloy::app::defaults;
+use cloy::common::CommonParams;
+use cloy::{error, init_app};
+use eval::{EvalParams, handle_eval};
+
+#[derive(Parser)]
+#[command(
+    name = "git-eval",
+    version,
+    author,
+    about = "Score generated commit messages against repository history",
+    styles = cloy::app::args::get_styles(),
+)]
+struct EvalCli {
+    #[command(flatten)]
+    common: CommonParams,
+
+    #[command(flatten)]
+    params: EvalParams,
+}
+
+#[tokio::main]
+async fn main() {
+    init_app();
+
+    let EvalCli { mut common, params } = defaults::parse::<EvalCli>("eval");
+    let repository_url = std::mem::take(&mut common.repository_url);
+
+    if let Err(e) = handle_eval(common, repository_url, &params).await {
+        std::process::exit(error::report(&e));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn verify_cli() {
+        EvalCli::command().debug_assert();
+    }
+}
@@ -0,0 +1,173 @@
+//! Scores comparing generated messages with the messages authors wrote
+
+use cloy::llm::style::parse_conventional_prefix;
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Longest n-gram BLEU considers
+const BLEU_MAX_N: usize = 4;
+
+fn tokens(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+fn ngram_counts(tokens: &[String], n: usize) -> HashMap<&[String], usize> {
+    let mut counts = HashMap::new();
+    for gram in tokens.windows(n) {
+        *counts.entry(gram).or_insert(0) += 1;
+    }
+    counts
+}
+
+/// Sentence-level BLEU-4 with add-one smoothing, from 0 to 1
+#[allow(clippy::cast_precision_loss, clippy::as_conversions)]
+pub fn bleu(candidate: &str, reference: &str) -> f32 {
+    let candidate = tokens(candidate);
+    let reference = tokens(reference);
+    if candidate.is_empty() || reference.is_empty() {
+        return 0.0;
+    }
+
+    let mut log_precision = 0.0;
+    for n in 1..=BLEU_MAX_N {
+        let reference_counts = ngram_counts(&reference, n);
+        let candidate_counts = ngram_counts(&candidate, n);
+        let matches: usize = candidate_counts
+            .iter()
+            .map(|(gram, count)| (*count).min(reference_counts.get(gram).copied().unwrap_or(0)))
+            .sum();
+        let total = candidate.len().saturating_sub(n - 1);
+        // Smoothing keeps short messages from scoring zero on missing 4-grams
+        log_precision += ((matches + 1) as f32 / (total + 1) as f32).ln();
+    }
+
+    let brevity = if candidate.len() >= reference.len() {
+        1.0
+    } else {
+        (1.0 - reference.len() as f32 / candidate.len() as f32).exp()
+    };
+    brevity * (log_precision / BLEU_MAX_N as f32).exp()
+}
+
+/// Whether both subjects carry the same Conventional Commit type.
+///
+/// `None` when the original has no type, so untyped history does not count.
+pub fn type_matches(generated: &str, original: &str) -> Option<bool> {
+    let subject = |text: &str| text.lines().next().unwrap_or("").trim().to_string();
+    let (original_type, _) = parse_conventional_prefix(&subject(original))?;
+    let generated_type = parse_conventional_prefix(&subject(generated)).map(|(kind, _)| kind);
+    Some(generated_type.as_deref() == Some(original_type.as_str()))
+}
+
+/// Scores of one replayed commit
+#[derive(Debug, Clone, Default)]
+pub struct Sample {
+    pub bleu: f32,
+    pub embedding_similarity: Option<f32>,
+    pub type_match: Option<bool>,
+}
+
+/// Aggregate metrics of an evaluation run
+#[derive(Debug, Clone, Serialize)]
+pub struct EvalSummary {
+    pub model: String,
+    pub commits: usize,
+    pub failures: usize,
+    pub bleu: f32,
+    pub embedding_similarity: Option<f32>,
+    pub type_accuracy: Option<f32>,
+    pub typed_commits: usize,
+}
+
+impl EvalSummary {
+    #[allow(clippy::cast_precision_loss, clippy::as_conversions)]
+    pub fn from_samples(model: &str, samples: &[Sample], failures: usize) -> Self {
+        let mean = |values: &[f32]| -> Option<f32> {
+            (!values.is_empty()).then(|| values.iter().sum::<f32>() / values.len() as f32)
+        };
+        let bleu: Vec<f32> = samples.iter().map(|s| s.bleu).collect();
+        let embeddings: Vec<f32> = samples
+            .iter()
+            .filter_map(|s| s.embedding_similarity)
+            .collect();
+        let typed: Vec<bool> = samples.iter().filter_map(|s| s.type_match).collect();
+        let correct = typed.iter().filter(|m| **m).count();
+
+        Self {
+            model: model.to_string(),
+            commits: samples.len() + failures,
+            failures,
+            bleu: mean(&bleu).unwrap_or(0.0),
+            embedding_similarity: mean(&embeddings),
+            type_accuracy: (!typed.is_empty()).then(|| correct as f32 / typed.len() as f32),
+            typed_commits: typed.len(),
+        }
+    }
+
+    /// Renders the summary for the terminal
+    pub fn to_text(&self) -> String {
+        let optional =
+            |value: Option<f32>| value.map_or_else(|| "n/a".to_string(), |v| format!("{v:.3}"));
+        [
+            format!("Model:                {}", self.model),
+            format!(
+                "Commits:              {} ({} failed)",
+                self.commits, self.failures
+            ),
+            format!("BLEU:                 {:.3}", self.bleu),
+            format!(
+                "Embedding similarity: {}",
+                optional(self.embedding_similarity)
+            ),
+            format!(
+                "Type accuracy:        {} over {} typed commits",
+                optional(self.type_accuracy),
+                self.typed_commits
+            ),
+        ]
+        .join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bleu() {
+        let reference = "fix(git): handle empty repositories when reading history";
+        assert!((bleu(reference, reference) - 1.0).abs() < 1e-6);
+        assert!(bleu("update docs", reference) < 0.2);
+        assert!(bleu("fix(git): handle empty repositories", reference) > 0.4);
+        assert!(bleu("", reference).abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn test_type_accuracy_and_summary() {
+        assert_eq!(type_matches("fix: a", "fix(git): b"), Some(true));
+        assert_eq!(type_matches("feat: a", "fix: b"), Some(false));
+        assert_eq!(type_matches("feat: a", "Update readme"), None);
+
+        let samples = vec![
+            Sample {
+                bleu: 0.5,
+                embedding_similarity: None,
+                type_match: Some(true),
+            },
+            Sample {
+                bleu: 0.3,
+                embedding_similarity: None,
+                type_match: Some(false),
+            },
+        ];
+        let summary = EvalSummary::from_samples("gemini", &samples, 1);
+        assert_eq!(summary.commits, 3);
+        assert!((summary.bleu - 0.4).abs() < 1e-6);
+        assert_eq!(summary.embedding_similarity, None);
+        assert_eq!(summary.type_accuracy, Some(0.5));
+        assert!(summary.to_text().contains("Embedding similarity: n/a"));
+    }
+}
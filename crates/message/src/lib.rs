@@ -1,15 +1,30 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
+use chrono::Utc;
 use clap::{Parser, crate_authors, crate_version};
-use cloy::app::args::{self, MessageParams};
+use cloy::app::args::{self, MessageParams, TrailerArgs};
+use cloy::commands::commit::batch::{BatchEntry, BatchFormat, format_batch};
+use cloy::commands::commit::experiment::{
+    DEFAULT_VARIANT, VariantReport, config_for_variant, format_report,
+};
 use cloy::commands::commit::service::CommitService;
 use cloy::commands::commit::types::{GeneratedMessage, format_commit_message};
-use cloy::commands::common::service::{create_commit_service, create_completion_service};
+use cloy::commands::common::service::{
+    create_commit_service, create_completion_service, create_patch_service,
+};
 use cloy::commands::common::{run_with_spinner, validate_staged_files};
+use cloy::commit_cache::{self, Draft};
 use cloy::common::CommonParams;
 use cloy::config::Config;
-use cloy::llm::messages;
+#[cfg(unix)]
+use cloy::daemon;
+use cloy::error::{self, ErrorCode, coded};
+use cloy::git::{GitRepo, files_from_patch};
+use cloy::llm::context::CommitContext;
+use cloy::llm::{ci_log, context_files, messages};
 use cloy::output;
-use cloy::tui::run_tui_commit;
+use cloy::tui::{Theme, run_plain_commit, run_tui_commit};
+use std::io::{IsTerminal, Read, Write};
+use std::path::PathBuf;
 
 #[derive(Parser)]
 #[command(
@@ -40,8 +55,126 @@ async fn generate_initial_message(
     .await
 }
 
+/// Asks whether to continue from a draft; only asked on a terminal
+fn offer_draft(draft: &Draft) -> bool {
+    if !std::io::stdin().is_terminal() {
+        return false;
+    }
+    let title = draft
+        .current_message()
+        .map(|m| m.title.as_str())
+        .unwrap_or_default();
+    let age = format_age(Utc::now().timestamp() - draft.saved_at);
+    eprint!("A draft from {age} matches the staged changes:\n  {title}\nRestore it? [Y/n] ");
+    let _ = std::io::stderr().flush();
+    let mut answer = String::new();
+    if std::io::stdin().read_line(&mut answer).is_err() {
+        return false;
+    }
+    !matches!(answer.trim().to_lowercase().as_str(), "n" | "no")
+}
+
+/// "3 minutes ago" and the like, for listing drafts
+fn format_age(seconds: i64) -> String {
+    let (count, unit) = match seconds.max(0) {
+        s if s < 60 => return "just now".to_string(),
+        s if s < 3_600 => (s / 60, "minute"),
+        s if s < 86_400 => (s / 3_600, "hour"),
+        s => (s / 86_400, "day"),
+    };
+    format!("{count} {unit}{} ago", if count == 1 { "" } else { "s" })
+}
+
+/// Lists the drafts of the repository, or deletes one of them
+pub fn handle_drafts_command(repository_url: Option<&str>, delete: Option<&str>) -> Result<()> {
+    if repository_url.is_some() {
+        return Err(anyhow::anyhow!(
+            "Drafts are only kept for local repositories"
+        ));
+    }
+    let repo = GitRepo::open_local()?;
+    if let Some(id) = delete {
+        let deleted = commit_cache::delete(&repo, id)?;
+        output::print_success(&format!("Deleted {deleted} draft(s)"));
+        return Ok(());
+    }
+
+    let drafts = commit_cache::list(&repo);
+    if drafts.is_empty() {
+        output::print_info("No drafts saved in this repository.");
+        return Ok(());
+    }
+    let now = Utc::now().timestamp();
+    for draft in &drafts {
+        let title = draft
+            .current_message()
+            .map(|m| m.title.as_str())
+            .unwrap_or_default();
+        println!(
+            "{}  {:<14} {:<16} {title}",
+            draft.id(),
+            format_age(now - draft.saved_at),
+            draft.branch.as_deref().unwrap_or("(detached)"),
+        );
+    }
+    Ok(())
+}
+
+/// Prints a message for the diff on stdin, built without the repository.
+///
+/// Anything that reads or writes the repository, like drafts, history and
+/// committing, is skipped, so other version control tools can pipe in their diffs.
+pub async fn handle_stdin_command(
+    common: CommonParams,
+    ci_log: Option<&str>,
+    context_files: &[PathBuf],
+) -> Result<()> {
+    let mut patch = String::new();
+    std::io::stdin()
+        .read_to_string(&mut patch)
+        .context("Failed to read the diff from stdin")?;
+    let staged_files = files_from_patch(&patch)?;
+    if staged_files.is_empty() {
+        return Err(anyhow::anyhow!("The diff on stdin changes no files"));
+    }
+
+    let mut config = Config::load()?;
+    common.apply_to_config(&mut config)?;
+    ci_log::apply_to_config(&mut config, ci_log).await?;
+    context_files::apply_to_config(&mut config, context_files)?;
+    let service = create_patch_service(&common, &config)?;
+
+    let context = CommitContext::new(
+        String::new(),
+        Vec::new(),
+        staged_files,
+        String::new(),
+        String::new(),
+        Vec::new(),
+    );
+    let instructions = common
+        .instructions
+        .unwrap_or_else(|| config.instructions.clone());
+    // Stdout carries only the message, so no spinner
+    let message = service
+        .generate_message_with_context(&instructions, context)
+        .await?;
+    println!("{}", format_commit_message(&message));
+    Ok(())
+}
+
 pub struct MessageConfig {
     pub print: bool,
+    /// Generate only a subject, from the file list without diffs
+    pub subject_only: bool,
+    /// Commit to write a `fixup!` message for
+    pub fixup: Option<String>,
+    /// Log of the failed CI run the change fixes, a file or URL
+    pub ci_log: Option<String>,
+    /// Files whose text goes into the prompt as extra context
+    pub context_files: Vec<PathBuf>,
+    /// Trailers given on the command line
+    pub trailers: TrailerArgs,
 }
 
 pub async fn handle_message_command(
@@ -49,20 +182,29 @@ pub async fn handle_message_command(
     config: MessageConfig,
     repository_url: Option<String>,
 ) -> Result<()> {
-    let print = config.print;
+    let MessageConfig {
+        print,
+        subject_only,
+        fixup,
+        ci_log,
+        context_files,
+        trailers,
+    } = config;
     let mut config = Config::load()?;
     common.apply_to_config(&mut config)?;
+    trailers.apply_to_config(&mut config)?;
+    ci_log::apply_to_config(&mut config, ci_log.as_deref()).await?;
+    context_files::apply_to_config(&mut config, &context_files)?;
 
-    let service = create_commit_service(&common, repository_url.clone(), &config).map_err(|e| {
-        output::print_error(&format!("Error: {e}"));
-        e
-    })?;
+    let service = create_commit_service(&common, repository_url.clone(), &config)?;
+    let completion_service = create_completion_service(&common, repository_url, &config)?;
 
-    let completion_service =
-        create_completion_service(&common, repository_url, &config).map_err(|e| {
-            output::print_error(&format!("Error: {e}"));
-            e
-        })?;
+    // Resolve the theme before generating so a typo fails fast
+    let theme = if output::is_color_enabled() {
+        Theme::from_name(&common.theme, &config.themes)?
+    } else {
+        Theme::monochrome()
+    };
 
     let git_info = service.get_git_info().await?;
 
@@ -75,7 +217,37 @@ pub async fn handle_message_command(
         .instructions
         .unwrap_or_else(|| config.instructions.clone());
 
-    let initial_message = generate_initial_message(&service, &effective_instructions).await?;
+    // Reverts and fixups have a fixed form and skip the model
+    let special = service.special_message(fixup.as_deref())?;
+
+    // A draft of these staged changes replaces the first generation
+    let restored = if print || special.is_some() {
+        None
+    } else {
+        service.find_draft().filter(offer_draft).and_then(|draft| {
+            let message = draft.current_message()?.clone();
+            // The TUI reopens on the message that was showing
+            let index = if draft.current < draft.messages.len() {
+                draft.current
+            } else {
+                0
+            };
+            Some((message, draft.messages, index))
+        })
+    };
+    let (initial_message, initial_messages, initial_index) = if let Some(message) = special {
+        (message.clone(), vec![message], 0)
+    } else if let Some(restored) = restored {
+        restored
+    } else {
+        let message = if subject_only {
+            // Fast enough that a spinner would only flicker
+            service.generate_subject(&effective_instructions).await?
+        } else {
+            generate_initial_message(&service, &effective_instructions).await?
+        };
+        (message.clone(), vec![message], 0)
+    };
 
     if print {
         println!("{}", format_commit_message(&initial_message));
@@ -90,12 +262,17 @@ pub async fn handle_message_command(
         return Ok(());
     }
 
+    if output::is_plain_mode() {
+        return run_plain_commit(initial_message, effective_instructions, service).await;
+    }
+
     run_tui_commit(
-        vec![initial_message],
+        initial_messages,
+        initial_index,
         effective_instructions,
         service,
         completion_service,
-        common.theme,
+        theme,
     )
     .await?;
 
@@ -110,9 +287,11 @@ pub async fn handle_completion_command(
     repository_url: Option<String>,
 ) -> Result<()> {
     let print = config.print;
+    let trailers = config.trailers;
 
     let mut config = Config::load()?;
     common.apply_to_config(&mut config)?;
+    trailers.apply_to_config(&mut config)?;
 
     let context_ratio = context_ratio.unwrap_or(0.5);
 
@@ -127,15 +306,7 @@ pub async fn handle_completion_command(
         context_ratio * 100.0
     ));
 
-    let service = create_completion_service(&common, repository_url, &config).map_err(|e| {
-        output::print_error(&format!("Error: {e}"));
-        output::print_info("\nPlease ensure the following:");
-        output::print_info("1. Git is installed and accessible from the command line.");
-        output::print_info(
-            "2. You are running this command from within a Git repository or provide a repository URL with --repo.",
-        );
-        e
-    })?;
+    let service = create_completion_service(&common, repository_url, &config)?;
 
     let git_info = service.get_git_info().await?;
 
@@ -189,9 +360,297 @@ pub struct MessageArgs {
     pub context_ratio: Option<f32>,
 }
 
+/// Soft-resets the last commit made by gitai so it can be fixed and retried.
+///
+/// HEAD must still be the commit gitai recorded in its journal; anything else
+/// is refused so unrelated commits are never rewound.
+pub fn handle_undo_command(repository_url: Option<&str>) -> Result<()> {
+    if repository_url.is_some() {
+        return Err(anyhow::anyhow!(
+            "Cannot undo commits in a remote repository"
+        ));
+    }
+    let repo = GitRepo::open_local()?;
+    let root = repo
+        .workdir()
+        .ok_or_else(|| anyhow::anyhow!("Cannot undo commits in a bare repository"))?;
+
+    let result = GitRepo::new(root)?.undo_last_commit()?;
+    output::print_success(&format!(
+        "Undid {} \"{}\"; HEAD is now {} with its changes staged",
+        result.undone, result.subject, result.restored
+    ));
+    Ok(())
+}
+
+/// Exit codes reported by `--ci`
+pub use cloy::error::exit_code;
+
+/// Seconds `--ci` waits for a message when `--timeout` is not given
+pub const DEFAULT_CI_TIMEOUT_SECS: u64 = 120;
+
+enum CiError {
+    NoStagedChanges,
+    Provider(anyhow::Error),
+    Other(anyhow::Error),
+}
+
+/// The request for a running `gitai daemon`, unless the run has settings the
+/// daemon cannot apply, such as a `--config` file or a remote repository
+#[cfg(unix)]
+fn daemon_request(
+    common: &CommonParams,
+    repository_url: Option<&str>,
+    subject_only: bool,
+    fixup: Option<&str>,
+    ci_log: Option<&str>,
+    context_files: &[PathBuf],
+) -> Option<daemon::Request> {
+    let selects_config = common.config_file.is_some()
+        || common.profile.is_some()
+        || ["GITAI_CONFIG", "GITAI_PROFILE"]
+            .iter()
+            .any(|var| std::env::var_os(var).is_some_and(|value| !value.is_empty()));
+    let per_run = repository_url.is_some()
+        || common.repository_url.is_some()
+        || fixup.is_some()
+        || ci_log.is_some()
+        || !context_files.is_empty()
+        || common.trace.is_some()
+        || common.progress == cloy::progress::ProgressFormat::Json
+        || common.unified.is_some()
+        || common.word_diff
+        || common.deterministic
+        || common.max_bullets.is_some()
+        || common.max_subject_length.is_some()
+        || common.max_length.is_some();
+    if selects_config || per_run {
+        return None;
+    }
+    Some(daemon::Request {
+        directory: std::env::current_dir().ok()?,
+        instructions: common.instructions.clone(),
+        model: common.model.clone(),
+        language: common.language.clone(),
+        detail_level: Some(common.detail_level.as_str().to_string()),
+        subject_only,
+    })
+}
+
+async fn generate_for_ci(
+    common: CommonParams,
+    repository_url: Option<String>,
+    subject_only: bool,
+    fixup: Option<&str>,
+    ci_log: Option<&str>,
+    context_files: &[PathBuf],
+) -> Result<GeneratedMessage, CiError> {
+    #[cfg(unix)]
+    if let Some(request) = daemon_request(
+        &common,
+        repository_url.as_deref(),
+        subject_only,
+        fixup,
+        ci_log,
+        context_files,
+    ) && let Some(response) = daemon::send(&daemon::socket_path(), &request).await
+    {
+        return match response {
+            daemon::Response::Message(message) => Ok(message),
+            daemon::Response::NoStagedChanges => Err(CiError::NoStagedChanges),
+            daemon::Response::Error { error } => Err(CiError::Provider(anyhow::anyhow!(error))),
+        };
+    }
+
+    let mut config = Config::load().map_err(CiError::Other)?;
+    common
+        .apply_to_config(&mut config)
+        .map_err(CiError::Other)?;
+    ci_log::apply_to_config(&mut config, ci_log)
+        .await
+        .map_err(CiError::Other)?;
+    context_files::apply_to_config(&mut config, context_files).map_err(CiError::Other)?;
+    let service =
+        create_commit_service(&common, repository_url, &config).map_err(CiError::Other)?;
+
+    let git_info = service.get_git_info().await.map_err(CiError::Other)?;
+    if git_info.staged_files.is_empty() {
+        return Err(CiError::NoStagedChanges);
+    }
+    if let Some(message) = service.special_message(fixup).map_err(CiError::Other)? {
+        return Ok(message);
+    }
+
+    let instructions = common.instructions.unwrap_or(config.instructions);
+    if subject_only {
+        return service
+            .generate_subject(&instructions)
+            .await
+            .map_err(CiError::Provider);
+    }
+    service
+        .generate_message(&instructions)
+        .await
+        .map_err(CiError::Provider)
+}
+
+/// Generates a message without prompts, spinners or the TUI.
+///
+/// The message goes to stdout and diagnostics to stderr; the returned value is
+/// the process exit code (see [`exit_code`]).
+pub async fn handle_ci_command(
+    common: CommonParams,
+    repository_url: Option<String>,
+    timeout_secs: u64,
+    subject_only: bool,
+    fixup: Option<&str>,
+    ci_log: Option<&str>,
+    context_files: &[PathBuf],
+) -> i32 {
+    output::set_quiet_mode(true);
+    output::set_plain_mode(true);
+
+    let timeout = std::time::Duration::from_secs(timeout_secs);
+    match tokio::time::timeout(
+        timeout,
+        generate_for_ci(
+            common,
+            repository_url,
+            subject_only,
+            fixup,
+            ci_log,
+            context_files,
+        ),
+    )
+    .await
+    {
+        Ok(Ok(message)) => {
+            println!("{}", format_commit_message(&message));
+            0
+        }
+        Ok(Err(CiError::NoStagedChanges)) => {
+            error::report(&coded(ErrorCode::NoStagedChanges, "No staged changes"))
+        }
+        Ok(Err(CiError::Provider(e))) => error::report_as(&e, ErrorCode::ProviderFailed),
+        Ok(Err(CiError::Other(e))) => error::report(&e),
+        Err(_) => error::report(&coded(
+            ErrorCode::Timeout,
+            format!("Timed out after {timeout_secs}s"),
+        )),
+    }
+}
+
+/// Regenerates the message of every commit in `from..to` and prints the mapping.
+///
+/// Progress and per-commit failures go to stderr so stdout stays parseable;
+/// failed commits are left out of the mapping and reported at the end.
+pub async fn handle_batch_command(
+    common: CommonParams,
+    repository_url: Option<String>,
+    from: &str,
+    to: &str,
+    format: BatchFormat,
+) -> Result<()> {
+    let mut config = Config::load()?;
+    common.apply_to_config(&mut config)?;
+    let service = create_commit_service(&common, repository_url, &config)?;
+    let instructions = common.instructions.unwrap_or(config.instructions);
+
+    let commits = service.get_commits_between(from, to)?;
+    let total = commits.len();
+    let mut entries = Vec::with_capacity(total);
+    let mut failed = 0;
+
+    for (i, commit) in commits.iter().enumerate() {
+        let short = &commit.hash[..commit.hash.len().min(7)];
+        eprintln!("[{}/{total}] {short}", i + 1);
+        match service.reword_commit(&instructions, &commit.hash).await {
+            Ok(message) => entries.push(BatchEntry {
+                commit: commit.hash.clone(),
+                message: format_commit_message(&message),
+            }),
+            Err(e) => {
+                output::print_error(&format!("Skipping {short}: {e}"));
+                failed += 1;
+            }
+        }
+    }
+
+    println!("{}", format_batch(&entries, format)?.trim_end());
+
+    if failed > 0 {
+        return Err(anyhow::anyhow!(
+            "{failed} of {total} commits could not be regenerated"
+        ));
+    }
+    Ok(())
+}
+
+/// Regenerates recent commit messages with two prompt variants and prints a report.
+///
+/// Each variant is a template file for `prompt`, or `default` for the built-in
+/// prompt. Merge commits are skipped since their messages are not written by
+/// hand.
+pub async fn handle_experiment_command(
+    common: CommonParams,
+    repository_url: Option<String>,
+    variants: &[String],
+    prompt: &str,
+    samples: usize,
+) -> Result<()> {
+    let mut config = Config::load()?;
+    common.apply_to_config(&mut config)?;
+    let instructions = common
+        .instructions
+        .clone()
+        .unwrap_or_else(|| config.instructions.clone());
+    let conventions = config.conventions.clone().unwrap_or_default();
+
+    let mut runs = Vec::new();
+    for variant in variants {
+        if variant != DEFAULT_VARIANT && !std::path::Path::new(variant).is_file() {
+            return Err(anyhow::anyhow!("Template not found: {variant}"));
+        }
+        let variant_config = config_for_variant(&config, prompt, variant);
+        let service = create_commit_service(&common, repository_url.clone(), &variant_config)?;
+        runs.push((service, VariantReport::new(variant)));
+    }
+    let Some((first, _)) = runs.first() else {
+        return Err(anyhow::anyhow!("No variants to compare"));
+    };
+
+    let commits: Vec<_> = first
+        .get_recent_commits(samples * 2)?
+        .into_iter()
+        .filter(|commit| !commit.message.starts_with("Merge "))
+        .take(samples)
+        .collect();
+
+    for (i, commit) in commits.iter().enumerate() {
+        let short = &commit.hash[..commit.hash.len().min(7)];
+        eprintln!("[{}/{}] {short}", i + 1, commits.len());
+        for (service, report) in &mut runs {
+            let generated = service
+                .replay_commit(&instructions, &commit.hash)
+                .await
+                .map(|message| format_commit_message(&message));
+            report.record(generated, &commit.message, &conventions);
+        }
+    }
+
+    let reports: Vec<VariantReport> = runs.into_iter().map(|(_, report)| report).collect();
+    println!("{}", format_report(prompt, commits.len(), &reports));
+    Ok(())
+}
+
 #[derive(Clone, Debug)]
 pub struct CmsgConfig {
     pub print_only: bool,
+    pub subject_only: bool,
+    pub fixup: Option<String>,
+    pub ci_log: Option<String>,
+    pub context_files: Vec<PathBuf>,
+    pub trailers: TrailerArgs,
 }
 
 pub async fn handle_message(
@@ -219,6 +678,11 @@ pub async fn handle_message(
             args.context_ratio,
             MessageConfig {
                 print: config.print_only,
+                subject_only: config.subject_only,
+                fixup: config.fixup.clone(),
+                ci_log: config.ci_log.clone(),
+                context_files: config.context_files.clone(),
+                trailers: config.trailers.clone(),
             },
             repository_url,
         )
@@ -228,6 +692,11 @@ pub async fn handle_message(
             common,
             MessageConfig {
                 print: config.print_only,
+                subject_only: config.subject_only,
+                fixup: config.fixup.clone(),
+                ci_log: config.ci_log.clone(),
+                context_files: config.context_files.clone(),
+                trailers: config.trailers.clone(),
             },
             repository_url,
         )
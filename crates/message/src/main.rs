@@ -1,20 +1,100 @@
 use anyhow::Result;
-use clap::Parser;
-use cloy::{init_app, output::print_error};
-use message::{CmsgConfig, CommonArgs, MessageArgs, handle_message};
+use cloy::app::defaults;
+use cloy::{error, init_app, output::set_plain_mode};
+use message::{
+    CmsgConfig, CommonArgs, DEFAULT_CI_TIMEOUT_SECS, MessageArgs, handle_batch_command,
+    handle_ci_command, handle_drafts_command, handle_experiment_command, handle_message,
+    handle_stdin_command, handle_undo_command,
+};
 
 #[tokio::main]
 async fn main() -> Result<()> {
     init_app();
 
-    let cli_args = CommonArgs::parse();
+    let cli_args = defaults::parse::<CommonArgs>("message");
     let CommonArgs { mut common, params } = cli_args;
     let repository_url = std::mem::take(&mut common.repository_url);
 
+    if params.plain {
+        set_plain_mode(true);
+    }
+
+    if params.stdin {
+        if let Err(e) =
+            handle_stdin_command(common, params.ci_log.as_deref(), &params.context_files).await
+        {
+            std::process::exit(error::report(&e));
+        }
+        return Ok(());
+    }
+
+    if params.ci {
+        let timeout = params.timeout.unwrap_or(DEFAULT_CI_TIMEOUT_SECS);
+        std::process::exit(
+            handle_ci_command(
+                common,
+                repository_url,
+                timeout,
+                params.subject_only,
+                params.fixup.as_deref(),
+                params.ci_log.as_deref(),
+                &params.context_files,
+            )
+            .await,
+        );
+    }
+
+    if params.batch {
+        let from = params.from.unwrap_or_default();
+        let to = params.to.as_deref().unwrap_or("HEAD");
+        if let Err(e) =
+            handle_batch_command(common, repository_url, &from, to, params.batch_format).await
+        {
+            std::process::exit(error::report(&e));
+        }
+        return Ok(());
+    }
+
+    if let Some(variants) = &params.experiment {
+        if let Err(e) = handle_experiment_command(
+            common,
+            repository_url,
+            variants,
+            &params.experiment_prompt,
+            params.samples,
+        )
+        .await
+        {
+            std::process::exit(error::report(&e));
+        }
+        return Ok(());
+    }
+
+    if params.undo {
+        if let Err(e) = handle_undo_command(repository_url.as_deref()) {
+            std::process::exit(error::report(&e));
+        }
+        return Ok(());
+    }
+
+    if params.drafts || params.delete_draft.is_some() {
+        if let Err(e) =
+            handle_drafts_command(repository_url.as_deref(), params.delete_draft.as_deref())
+        {
+            std::process::exit(error::report(&e));
+        }
+        return Ok(());
+    }
+
     if let Err(e) = handle_message(
         common,
         CmsgConfig {
             print_only: params.print,
+            subject_only: params.subject_only,
+            fixup: params.fixup,
+            ci_log: params.ci_log,
+            context_files: params.context_files,
+            trailers: params.trailers,
         },
         repository_url,
         MessageArgs {
@@ -25,8 +105,7 @@ async fn main() -> Result<()> {
     )
     .await
     {
-        print_error(&format!("Error: {e}"));
-        std::process::exit(1);
+        std::process::exit(error::report(&e));
     }
 
     Ok(())
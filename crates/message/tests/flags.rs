@@ -1,4 +1,5 @@
 use clap::{CommandFactory, Parser};
+use cloy::app::args::TrailerArgs;
 use message::{CmsgConfig, CommonArgs, MessageArgs};
 
 #[test]
@@ -52,6 +53,111 @@ mod constraints {
         );
     }
 
+    #[test]
+    fn plain_conflicts_with_print() {
+        let res = CommonArgs::try_parse_from(["git-message", "--plain", "--print"]);
+        assert!(res.is_err(), "--plain --print should conflict");
+    }
+
+    #[test]
+    fn subject_only_conflicts_with_complete() {
+        let res = CommonArgs::try_parse_from(["git-message", "--subject-only", "--complete"]);
+        assert!(res.is_err(), "--subject-only --complete should conflict");
+        let res = CommonArgs::try_parse_from(["git-message", "--subject-only", "--print"]);
+        assert!(res.is_ok(), "--subject-only --print should succeed");
+    }
+
+    #[test]
+    fn plain_with_no_color_succeeds() {
+        let res = CommonArgs::try_parse_from(["git-message", "--plain", "--no-color"]);
+        let args = res.expect("--plain --no-color should parse");
+        assert!(args.params.plain);
+        assert!(args.common.no_color);
+    }
+
+    #[test]
+    fn ci_conflicts_with_plain() {
+        let res = CommonArgs::try_parse_from(["git-message", "--ci", "--plain"]);
+        assert!(res.is_err(), "--ci --plain should conflict");
+    }
+
+    #[test]
+    fn stdin_conflicts_with_interactive_modes() {
+        for flag in ["--complete", "--plain", "--undo", "--ci"] {
+            let res = CommonArgs::try_parse_from(["git-message", "--stdin", flag]);
+            assert!(res.is_err(), "--stdin {flag} should conflict");
+        }
+        let args = CommonArgs::try_parse_from(["git-message", "--stdin", "--ci-log", "build.log"])
+            .expect("--stdin --ci-log should parse");
+        assert!(args.params.stdin);
+    }
+
+    #[test]
+    fn timeout_requires_ci() {
+        let res = CommonArgs::try_parse_from(["git-message", "--timeout", "30"]);
+        assert!(res.is_err(), "--timeout without --ci should fail");
+
+        let args = CommonArgs::try_parse_from(["git-message", "--ci", "--timeout", "30"])
+            .expect("--ci --timeout should parse");
+        assert_eq!(args.params.timeout, Some(30));
+    }
+
+    #[test]
+    fn batch_requires_from() {
+        let res = CommonArgs::try_parse_from(["git-message", "--batch"]);
+        assert!(res.is_err(), "--batch without --from should fail");
+
+        let res = CommonArgs::try_parse_from(["git-message", "--from", "v1.0"]);
+        assert!(res.is_err(), "--from without --batch should fail");
+    }
+
+    #[test]
+    fn batch_with_range_and_format_succeeds() {
+        let args = CommonArgs::try_parse_from([
+            "git-message",
+            "--batch",
+            "--from",
+            "v1.0",
+            "--to",
+            "main",
+            "--batch-format",
+            "filter-repo",
+        ])
+        .expect("--batch with a range should parse");
+        assert_eq!(args.params.from.as_deref(), Some("v1.0"));
+        assert_eq!(args.params.to.as_deref(), Some("main"));
+    }
+
+    #[test]
+    fn experiment_takes_two_variants() {
+        let res = CommonArgs::try_parse_from(["git-message", "--experiment", "a.hbs"]);
+        assert!(res.is_err(), "--experiment needs two variants");
+
+        let args = CommonArgs::try_parse_from([
+            "git-message",
+            "--experiment",
+            "default",
+            "b.hbs",
+            "--experiment-prompt",
+            "commit-system",
+        ])
+        .expect("--experiment with two variants should parse");
+        assert_eq!(
+            args.params.experiment,
+            Some(vec!["default".to_string(), "b.hbs".to_string()])
+        );
+
+        let res = CommonArgs::try_parse_from([
+            "git-message",
+            "--experiment",
+            "a",
+            "b",
+            "--experiment-prompt",
+            "pr-user",
+        ]);
+        assert!(res.is_err(), "only commit prompts can be compared");
+    }
+
     #[test]
     fn context_ratio_rejects_out_of_range_low() {
         let res =
@@ -329,6 +435,11 @@ mod data_flow {
         let args = res.expect("--print should unwrap");
         let config = CmsgConfig {
             print_only: args.params.print,
+            subject_only: false,
+            fixup: None,
+            ci_log: None,
+            context_files: Vec::new(),
+            trailers: TrailerArgs::default(),
         };
         assert!(
             config.print_only,
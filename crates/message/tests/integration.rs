@@ -2,8 +2,10 @@ use std::path::{Path, PathBuf};
 use std::time::Duration;
 
 use clap::Parser;
+use cloy::app::args::TrailerArgs;
+use cloy::testing::MockProvider;
 use git2::Repository;
-use message::{CmsgConfig, CommonArgs, MessageArgs, handle_message};
+use message::{CmsgConfig, CommonArgs, MessageArgs, exit_code, handle_ci_command, handle_message};
 use tempfile::TempDir;
 use tokio::sync::Mutex;
 use tokio::time::timeout;
@@ -103,6 +105,11 @@ mod with_repo {
             cli.common,
             CmsgConfig {
                 print_only: cli.params.print,
+                subject_only: false,
+                fixup: None,
+                ci_log: None,
+                context_files: Vec::new(),
+                trailers: TrailerArgs::default(),
             },
             repo_url,
             MessageArgs {
@@ -119,6 +126,56 @@ mod with_repo {
         );
     }
 
+    #[tokio::test]
+    async fn ci_mode_no_staged_files_exits_with_code_2() {
+        let _lock = CWD_LOCK.lock().await;
+        let repo = TestRepo::new();
+        let _cwd = CwdGuard::new(&repo.path);
+
+        let (cli, repo_url) = make_handler_args(&["git-message", "--ci"]);
+        let code = handle_ci_command(cli.common, repo_url, 10, false, None, None, &[]).await;
+
+        assert_eq!(code, exit_code::NO_STAGED_CHANGES);
+    }
+
+    #[tokio::test]
+    async fn ci_mode_provider_failure_exits_with_code_3() {
+        let _lock = CWD_LOCK.lock().await;
+        let repo = TestRepo::new();
+        repo.stage_file("main.rs", "fn main() {}");
+        let _cwd = CwdGuard::new(&repo.path);
+        // One failure for the request and each of its retries
+        let mock = MockProvider::new()
+            .then_fail("model overloaded")
+            .then_fail("model overloaded")
+            .then_fail("model overloaded");
+        let _installed = mock.install();
+
+        let (cli, repo_url) = make_handler_args(&["git-message", "--ci"]);
+        let code = handle_ci_command(cli.common, repo_url, 10, false, None, None, &[]).await;
+
+        assert_eq!(code, exit_code::PROVIDER_FAILURE);
+        assert_eq!(mock.requests().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn ci_mode_with_mock_provider_prints_message() {
+        let _lock = CWD_LOCK.lock().await;
+        let repo = TestRepo::new();
+        repo.stage_file("main.rs", "fn main() {}");
+        let _cwd = CwdGuard::new(&repo.path);
+        let mock = MockProvider::always(r#"{"title": "feat: add main", "message": ""}"#);
+        let _installed = mock.install();
+
+        let (cli, repo_url) = make_handler_args(&["git-message", "--ci"]);
+        let code = handle_ci_command(cli.common, repo_url, 10, false, None, None, &[]).await;
+
+        assert_eq!(code, 0);
+        let requests = mock.requests();
+        assert_eq!(requests.len(), 1);
+        assert!(requests[0].user.contains("main.rs"));
+    }
+
     #[tokio::test]
     async fn complete_mode_no_staged_files_returns_ok() {
         // Risk #2: completion path should also handle no-staged-files gracefully
@@ -133,6 +190,11 @@ mod with_repo {
             cli.common,
             CmsgConfig {
                 print_only: cli.params.print,
+                subject_only: false,
+                fixup: None,
+                ci_log: None,
+                context_files: Vec::new(),
+                trailers: TrailerArgs::default(),
             },
             repo_url,
             MessageArgs {
@@ -162,6 +224,11 @@ mod with_repo {
             cli.common,
             CmsgConfig {
                 print_only: cli.params.print,
+                subject_only: false,
+                fixup: None,
+                ci_log: None,
+                context_files: Vec::new(),
+                trailers: TrailerArgs::default(),
             },
             repo_url,
             MessageArgs {
@@ -202,6 +269,11 @@ mod with_repo {
                 cli.common,
                 CmsgConfig {
                     print_only: cli.params.print,
+                    subject_only: false,
+                    fixup: None,
+                    ci_log: None,
+                    context_files: Vec::new(),
+                    trailers: TrailerArgs::default(),
                 },
                 repo_url,
                 MessageArgs {
@@ -238,6 +310,11 @@ mod with_repo {
                 cli.common,
                 CmsgConfig {
                     print_only: cli.params.print,
+                    subject_only: false,
+                    fixup: None,
+                    ci_log: None,
+                    context_files: Vec::new(),
+                    trailers: TrailerArgs::default(),
                 },
                 repo_url,
                 MessageArgs {
@@ -276,6 +353,11 @@ mod dispatch {
                 cli_gen.common,
                 CmsgConfig {
                     print_only: cli_gen.params.print,
+                    subject_only: false,
+                    fixup: None,
+                    ci_log: None,
+                    context_files: Vec::new(),
+                    trailers: TrailerArgs::default(),
                 },
                 repo_url_gen,
                 MessageArgs {
@@ -296,6 +378,11 @@ mod dispatch {
                 cli_comp.common,
                 CmsgConfig {
                     print_only: cli_comp.params.print,
+                    subject_only: false,
+                    fixup: None,
+                    ci_log: None,
+                    context_files: Vec::new(),
+                    trailers: TrailerArgs::default(),
                 },
                 repo_url_comp,
                 MessageArgs {
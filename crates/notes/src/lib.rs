@@ -42,16 +42,7 @@ pub async fn handle_release_notes_command(
     let mut spinner = output::create_tui_spinner("Generating release notes...");
 
     // Check environment prerequisites
-    if let Err(e) = config.check_environment() {
-        output::print_error(&format!("Error: {e}"));
-        output::print_info("\nPlease ensure the following:");
-        output::print_info("1. Git is installed and accessible from the command line.");
-        output::print_info(
-            "2. You are running this command from within a Git repository or provide a repository URL with --repo.",
-        );
-        output::print_info("3. You have set up your configuration using 'git config'.");
-        return Err(e);
-    }
+    config.check_environment()?;
 
     // Use the repository URL from command line or common params
     let repo_url = repository_url.or(common.repository_url);
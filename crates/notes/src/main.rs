@@ -1,10 +1,10 @@
 use anyhow::Result;
 use clap::{Args, Parser, crate_authors, crate_version};
+use cloy::app::defaults;
 use cloy::{
     app::args::{get_dynamic_help, get_styles},
     common::CommonParams,
-    init_app,
-    output::print_error,
+    error, init_app,
 };
 use notes::handle_release_notes_command;
 
@@ -41,7 +41,7 @@ struct NotesArgs {
 async fn main() -> Result<()> {
     init_app();
 
-    let args = NotesArgs::parse();
+    let args = defaults::parse::<NotesArgs>("notes");
     let NotesArgs { mut common, params } = args;
     let repository_url = std::mem::take(&mut common.repository_url);
 
@@ -54,8 +54,7 @@ async fn main() -> Result<()> {
     )
     .await
     {
-        print_error(&format!("Error: {e}"));
-        std::process::exit(1);
+        std::process::exit(error::report(&e));
     }
 
     Ok(())
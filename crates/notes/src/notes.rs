@@ -1,7 +1,7 @@
 use crate::models::{Highlight, ReleaseNotesResponse, Section, SectionItem};
 use anyhow::Result;
 use cloy::commands::changelog::change_analyzer::AnalyzedChange;
-use cloy::commands::changelog::common::generate_changes_content;
+use cloy::commands::changelog::common::{ChangePrompts, generate_changes_content};
 use cloy::commands::changelog::models::{BreakingChange, ChangeMetrics};
 use cloy::common::DetailLevel;
 use cloy::config::Config;
@@ -30,8 +30,11 @@ impl ReleaseNotesGenerator {
             to,
             config,
             detail_level,
-            system_prompt_adapter,
-            user_prompt_adapter,
+            ChangePrompts {
+                name: "notes",
+                system: system_prompt_adapter,
+                user: user_prompt_adapter,
+            },
         )
         .await?;
 
@@ -102,9 +105,13 @@ fn user_prompt_adapter(
         }
         writeln!(changes_buf, "Impact score: {:.2}", change.impact_score).ok();
 
-        match detail_level {
-            DetailLevel::Minimal => {}
-            DetailLevel::Standard | DetailLevel::Detailed => {
+        match (detail_level, &change.summary) {
+            (DetailLevel::Minimal, _) => {}
+            // Commits of long ranges are summarized to keep the prompt small
+            (_, Some(summary)) => {
+                writeln!(changes_buf, "Summary: {summary}").ok();
+            }
+            (DetailLevel::Standard | DetailLevel::Detailed, None) => {
                 changes_buf.push_str("File changes:\n");
                 for file_change in &change.file_changes {
                     writeln!(
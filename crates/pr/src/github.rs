@@ -0,0 +1,102 @@
+//! The GitHub API calls behind `git-pr --update`.
+//!
+//! The repository comes from `--repo` or the `origin` remote, and the token
+//! and API root from the issue tracker settings (`gitai.github-token` or
+//! `GITHUB_TOKEN`, and `gitai.github-api`).
+
+use anyhow::{Context, Result, anyhow};
+use cloy::git::GitRepo;
+use cloy::http;
+use cloy::issues::{self, DEFAULT_GITHUB_API};
+use serde::Deserialize;
+use serde_json::json;
+use std::time::Duration;
+
+/// How long a GitHub request may take
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The parts of a pull request an update needs
+#[derive(Deserialize, Debug, Clone)]
+pub struct PullRequest {
+    pub number: u64,
+    #[serde(default)]
+    pub body: Option<String>,
+    pub html_url: String,
+    pub base: Branch,
+    pub head: Branch,
+}
+
+/// One side of a pull request
+#[derive(Deserialize, Debug, Clone)]
+pub struct Branch {
+    #[serde(rename = "ref")]
+    pub name: String,
+    pub sha: String,
+}
+
+/// Pull requests of one repository
+pub struct GitHubPulls {
+    api: String,
+    owner: String,
+    repo: String,
+    token: String,
+}
+
+impl GitHubPulls {
+    /// Client for the GitHub repository `git_repo` was cloned from
+    pub fn for_repository(git_repo: &GitRepo) -> Result<Self> {
+        let (owner, repo) = issues::github_remote(git_repo)
+            .ok_or_else(|| anyhow!("No GitHub remote found; --update needs an origin on GitHub"))?;
+        let settings = issues::settings();
+        let token = settings.github_token.ok_or_else(|| {
+            anyhow!("--update needs a token; set gitai.github-token or GITHUB_TOKEN")
+        })?;
+        let api = settings
+            .github_api
+            .unwrap_or_else(|| DEFAULT_GITHUB_API.to_string())
+            .trim_end_matches('/')
+            .to_string();
+        Ok(Self {
+            api,
+            owner,
+            repo,
+            token,
+        })
+    }
+
+    fn url(&self, number: u64) -> String {
+        format!(
+            "{}/repos/{}/{}/pulls/{number}",
+            self.api, self.owner, self.repo
+        )
+    }
+
+    fn request(&self, method: reqwest::Method, number: u64) -> Result<reqwest::RequestBuilder> {
+        Ok(http::client(Some(REQUEST_TIMEOUT))?
+            .request(method, self.url(number))
+            .bearer_auth(&self.token)
+            .header("Accept", "application/vnd.github+json")
+            .header("User-Agent", "gitai"))
+    }
+
+    pub async fn get(&self, number: u64) -> Result<PullRequest> {
+        self.request(reqwest::Method::GET, number)?
+            .send()
+            .await?
+            .error_for_status()
+            .with_context(|| format!("Failed to fetch PR #{number}"))?
+            .json()
+            .await
+            .context("Invalid GitHub response")
+    }
+
+    pub async fn update_body(&self, number: u64, body: &str) -> Result<()> {
+        self.request(reqwest::Method::PATCH, number)?
+            .json(&json!({ "body": body }))
+            .send()
+            .await?
+            .error_for_status()
+            .with_context(|| format!("Failed to update PR #{number}"))?;
+        Ok(())
+    }
+}
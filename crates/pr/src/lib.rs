@@ -1,13 +1,22 @@
+pub mod github;
 #[allow(clippy::uninlined_format_args)]
 pub mod models;
 pub mod pr;
+pub mod update;
 
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, anyhow};
 use cloy::common::CommonParams;
 use cloy::config::Config;
-use cloy::git::GitRepo;
-use cloy::llm::provider::ProviderKind;
+use cloy::git::{
+    GitRepo, ReviewerSuggestion, owners_for_range, owners_for_staged, reviewers_for_range,
+    reviewers_for_staged,
+};
+use cloy::llm::ci_log;
+use cloy::llm::context::FileOwners;
 use cloy::output;
+use log::debug;
+use pr::Comparison;
+use serde::Serialize;
 use std::env;
 use std::sync::Arc;
 
@@ -16,38 +25,37 @@ pub async fn handle_pr_command(
     from: Option<String>,
     to: Option<String>,
     repository_url: Option<String>,
+    update: Option<u64>,
+    ci_log: Option<&str>,
 ) -> Result<()> {
     let mut config = Config::load()?;
     common.apply_to_config(&mut config)?;
+    ci_log::apply_to_config(&mut config, ci_log).await?;
 
-    if let Err(e) = config.check_environment() {
-        output::print_error(&format!("Error: {e}"));
-        output::print_info("\nPlease ensure the following:");
-        output::print_info("1. Git is installed and accessible from the command line.");
-        output::print_info(
-            "2. You are running this command from within a Git repository or provide a repository URL with --repo.",
-        );
-        output::print_info("3. You have set up your configuration using 'git config'.");
-        return Err(e);
-    }
+    config.check_environment()?;
 
-    let repo_url = repository_url.or(common.repository_url.clone());
-
-    let git_repo = if let Some(url) = repo_url {
-        Arc::new(GitRepo::clone_remote_repository(&url).context("Failed to clone repository")?)
-    } else {
-        let repo_path = env::current_dir()?;
-        Arc::new(GitRepo::new(&repo_path).context("Failed to create GitRepo")?)
-    };
+    let git_repo = open_repository(repository_url.or(common.repository_url.clone()))?;
 
     let effective_instructions = common
         .instructions
         .unwrap_or_else(|| config.instructions.clone());
 
-    let provider_name = ProviderKind::Google.as_str();
+    let provider_name = config.active_provider().as_str();
+
+    if let Some(number) = update {
+        return update_pull_request(
+            git_repo,
+            &effective_instructions,
+            &config,
+            number,
+            (from, to),
+        )
+        .await;
+    }
 
+    let (base, target) = Comparison::from_args(from.as_deref(), to.as_deref()).range();
     let pr_description = pr::generate_pr_based_on_parameters(
-        git_repo,
+        Arc::clone(&git_repo),
         &effective_instructions,
         &config,
         provider_name,
@@ -58,5 +66,128 @@ pub async fn handle_pr_command(
 
     println!("{}", models::format_pull_request(&pr_description));
 
+    let repo = git_repo.open_repo()?;
+    match owners_for_range(&repo, &base, &target) {
+        Ok(owners) => print!("{}", models::format_owners(&owners)),
+        Err(e) => debug!("Failed to look up code owners: {e:#}"),
+    }
+    match reviewers_for_range(&repo, &base, &target) {
+        Ok(reviewers) => print!("{}", models::format_reviewers(&reviewers)),
+        Err(e) => debug!("Failed to suggest reviewers: {e:#}"),
+    }
+
+    Ok(())
+}
+
+/// Suggested reviewers and CODEOWNERS matches, as `--reviewers` prints them
+#[derive(Serialize)]
+struct ReviewersReport {
+    reviewers: Vec<ReviewerSuggestion>,
+    owners: Vec<FileOwners>,
+}
+
+/// Prints the reviewers suggested for the staged changes or the compared range,
+/// and the owners of the changed files, as JSON
+pub fn handle_reviewers_command(
+    from: Option<&str>,
+    to: Option<&str>,
+    repository_url: Option<String>,
+    staged: bool,
+) -> Result<()> {
+    let git_repo = open_repository(repository_url)?;
+    let repo = git_repo.open_repo()?;
+    let report = if staged {
+        ReviewersReport {
+            reviewers: reviewers_for_staged(&repo)?,
+            owners: owners_for_staged(&repo)?,
+        }
+    } else {
+        let (base, target) = Comparison::from_args(from, to).range();
+        ReviewersReport {
+            reviewers: reviewers_for_range(&repo, &base, &target)?,
+            owners: owners_for_range(&repo, &base, &target)?,
+        }
+    };
+    println!("{}", serde_json::to_string_pretty(&report)?);
+    Ok(())
+}
+
+/// The repository at `repository_url`, cloned, or the one in the current directory
+fn open_repository(repository_url: Option<String>) -> Result<Arc<GitRepo>> {
+    Ok(if let Some(url) = repository_url {
+        Arc::new(GitRepo::clone_remote_repository(&url).context("Failed to clone repository")?)
+    } else {
+        let repo_path = env::current_dir()?;
+        Arc::new(GitRepo::new(&repo_path).context("Failed to create GitRepo")?)
+    })
+}
+
+/// The first of `candidates` the repository can resolve
+fn first_resolvable(git_repo: &GitRepo, candidates: &[String]) -> Option<String> {
+    let repo = git_repo.open_repo().ok()?;
+    candidates
+        .iter()
+        .find(|candidate| repo.revparse_single(candidate).is_ok())
+        .cloned()
+}
+
+/// Regenerates the description of PR `number` and merges it into the PR's body
+async fn update_pull_request(
+    git_repo: Arc<GitRepo>,
+    effective_instructions: &str,
+    config: &Config,
+    number: u64,
+    (from, to): (Option<String>, Option<String>),
+) -> Result<()> {
+    let pulls = github::GitHubPulls::for_repository(&git_repo)?;
+    let pull = pulls.get(number).await?;
+
+    // Without --from/--to, the PR's own range: the remote base and the local head
+    let base = &pull.base;
+    let head = &pull.head;
+    let from = from.or_else(|| {
+        first_resolvable(
+            &git_repo,
+            &[
+                format!("origin/{}", base.name),
+                base.name.clone(),
+                base.sha.clone(),
+            ],
+        )
+    });
+    let to = to.or_else(|| {
+        first_resolvable(
+            &git_repo,
+            &[
+                head.name.clone(),
+                format!("origin/{}", head.name),
+                head.sha.clone(),
+            ],
+        )
+    });
+    if from.is_none() || to.is_none() {
+        return Err(anyhow!(
+            "The branches of PR #{number} are not in this clone; fetch them or pass --from and --to"
+        ));
+    }
+
+    let pr_description = pr::generate_pr_based_on_parameters(
+        git_repo,
+        effective_instructions,
+        config,
+        config.active_provider().as_str(),
+        from,
+        to,
+    )
+    .await?;
+    let body = update::merge_description(
+        pull.body.as_deref().unwrap_or_default(),
+        &models::format_pull_request(&pr_description),
+    );
+    pulls.update_body(number, &body).await?;
+    output::print_success(&format!(
+        "Updated the description of PR #{number}: {}",
+        pull.html_url
+    ));
     Ok(())
 }
@@ -1,12 +1,12 @@
 use anyhow::Result;
 use clap::{Args, Parser, crate_authors, crate_version};
+use cloy::app::defaults;
 use cloy::{
     app::args::{get_dynamic_help, get_styles},
     common::CommonParams,
-    init_app,
-    output::print_error,
+    error, init_app,
 };
-use cloy_pr::handle_pr_command;
+use cloy_pr::{handle_pr_command, handle_reviewers_command};
 
 #[derive(Args, Clone, Debug)]
 struct PrParams {
@@ -15,6 +15,35 @@ struct PrParams {
 
     #[arg(long, help = "Target branch, commit, or commitish for comparison")]
     to: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "NUMBER",
+        help = "Regenerate the description of this GitHub PR and update it, keeping hand-written sections"
+    )]
+    update: Option<u64>,
+
+    #[arg(
+        long,
+        value_name = "FILE|URL",
+        conflicts_with = "reviewers",
+        help = "Log of the failed CI run the branch fixes; its error excerpts go into the prompt"
+    )]
+    ci_log: Option<String>,
+
+    #[arg(
+        long,
+        conflicts_with = "update",
+        help = "Print reviewers suggested by blame of the changed lines as JSON, without generating a description"
+    )]
+    reviewers: bool,
+
+    #[arg(
+        long,
+        requires = "reviewers",
+        help = "Suggest reviewers for the staged changes instead of a range"
+    )]
+    staged: bool,
 }
 
 #[derive(Parser)]
@@ -38,13 +67,33 @@ struct PrArgs {
 async fn main() -> Result<()> {
     init_app();
 
-    let args = PrArgs::parse();
+    let args = defaults::parse::<PrArgs>("pr");
     let PrArgs { mut common, params } = args;
     let repository_url = std::mem::take(&mut common.repository_url);
 
-    if let Err(e) = handle_pr_command(common, params.from, params.to, repository_url).await {
-        print_error(&format!("Error: {e}"));
-        std::process::exit(1);
+    if params.reviewers {
+        if let Err(e) = handle_reviewers_command(
+            params.from.as_deref(),
+            params.to.as_deref(),
+            repository_url,
+            params.staged,
+        ) {
+            std::process::exit(error::report(&e));
+        }
+        return Ok(());
+    }
+
+    if let Err(e) = handle_pr_command(
+        common,
+        params.from,
+        params.to,
+        repository_url,
+        params.update,
+        params.ci_log.as_deref(),
+    )
+    .await
+    {
+        std::process::exit(error::report(&e));
     }
 
     Ok(())
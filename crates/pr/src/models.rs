@@ -1,3 +1,5 @@
+use cloy::git::{ReviewerSuggestion, areas_by_owner};
+use cloy::llm::context::FileOwners;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::fmt::Write as _;
@@ -15,21 +17,66 @@ pub struct GeneratedPullRequest {
     pub testing_notes: Option<String>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub notes: Option<String>,
+    /// Section headings in the requested language; English when absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub headings: Option<SectionHeadings>,
+}
+
+/// Translated headings of the description's sections
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Default)]
+pub struct SectionHeadings {
+    pub summary: Option<String>,
+    pub description: Option<String>,
+    pub commits: Option<String>,
+    pub breaking_changes: Option<String>,
+    pub testing: Option<String>,
+    pub notes: Option<String>,
+}
+
+impl GeneratedPullRequest {
+    /// The translated heading picked by `field`, or `default`
+    fn heading<'a>(
+        &'a self,
+        field: fn(&SectionHeadings) -> &Option<String>,
+        default: &'a str,
+    ) -> &'a str {
+        self.headings
+            .as_ref()
+            .and_then(|headings| field(headings).as_deref())
+            .map(str::trim)
+            .filter(|heading| !heading.is_empty())
+            .unwrap_or(default)
+    }
 }
 
 pub fn format_pull_request(response: &GeneratedPullRequest) -> String {
     let mut message = String::new();
 
-    writeln!(&mut message, "## Summary").expect("String write is infallible");
+    writeln!(
+        &mut message,
+        "## {}",
+        response.heading(|h| &h.summary, "Summary")
+    )
+    .expect("String write is infallible");
     writeln!(&mut message, "{}", response.summary).expect("String write is infallible");
     message.push('\n');
 
-    writeln!(&mut message, "## Description").expect("String write is infallible");
+    writeln!(
+        &mut message,
+        "## {}",
+        response.heading(|h| &h.description, "Description")
+    )
+    .expect("String write is infallible");
     writeln!(&mut message, "{}", response.description).expect("String write is infallible");
     message.push('\n');
 
     if !response.commits.is_empty() {
-        writeln!(&mut message, "## Commits").expect("String write is infallible");
+        writeln!(
+            &mut message,
+            "## {}",
+            response.heading(|h| &h.commits, "Commits")
+        )
+        .expect("String write is infallible");
         for commit in &response.commits {
             writeln!(&mut message, "- {commit}").expect("String write is infallible");
         }
@@ -37,7 +84,12 @@ pub fn format_pull_request(response: &GeneratedPullRequest) -> String {
     }
 
     if !response.breaking_changes.is_empty() {
-        writeln!(&mut message, "## Breaking Changes").expect("String write is infallible");
+        writeln!(
+            &mut message,
+            "## {}",
+            response.heading(|h| &h.breaking_changes, "Breaking Changes")
+        )
+        .expect("String write is infallible");
         for change in &response.breaking_changes {
             writeln!(&mut message, "- {change}").expect("String write is infallible");
         }
@@ -45,19 +97,61 @@ pub fn format_pull_request(response: &GeneratedPullRequest) -> String {
     }
 
     if let Some(testing) = &response.testing_notes {
-        writeln!(&mut message, "## Testing").expect("String write is infallible");
+        writeln!(
+            &mut message,
+            "## {}",
+            response.heading(|h| &h.testing, "Testing")
+        )
+        .expect("String write is infallible");
         writeln!(&mut message, "{testing}").expect("String write is infallible");
         message.push('\n');
     }
 
     if let Some(notes) = &response.notes {
-        writeln!(&mut message, "## Notes").expect("String write is infallible");
+        writeln!(
+            &mut message,
+            "## {}",
+            response.heading(|h| &h.notes, "Notes")
+        )
+        .expect("String write is infallible");
         writeln!(&mut message, "{notes}").expect("String write is infallible");
     }
 
     message
 }
 
+/// A "Suggested reviewers" section, or nothing without suggestions
+pub fn format_reviewers(reviewers: &[ReviewerSuggestion]) -> String {
+    if reviewers.is_empty() {
+        return String::new();
+    }
+    let lines: Vec<String> = reviewers
+        .iter()
+        .map(|r| {
+            format!(
+                "- {} <{}>: last touched {} changed line(s) in {}",
+                r.name,
+                r.email,
+                r.lines,
+                r.files.join(", ")
+            )
+        })
+        .collect();
+    format!("## Suggested reviewers\n{}\n", lines.join("\n"))
+}
+
+/// An "Areas touched / owners" section from CODEOWNERS, or nothing when no file is owned
+pub fn format_owners(owners: &[FileOwners]) -> String {
+    if owners.is_empty() {
+        return String::new();
+    }
+    let lines: Vec<String> = areas_by_owner(owners)
+        .into_iter()
+        .map(|(owner, paths)| format!("- {owner}: {}", paths.join(", ")))
+        .collect();
+    format!("## Areas touched / owners\n{}\n", lines.join("\n"))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -75,6 +169,7 @@ mod tests {
             breaking_changes: vec!["Auth header format changed".to_string()],
             testing_notes: Some("Verify with test OAuth server.".to_string()),
             notes: Some("See RFC 6749.".to_string()),
+            headings: None,
         };
 
         let formatted = format_pull_request(&pr);
@@ -97,6 +192,7 @@ mod tests {
             breaking_changes: Vec::new(),
             testing_notes: None,
             notes: None,
+            headings: None,
         };
 
         let formatted = format_pull_request(&pr);
@@ -119,6 +215,7 @@ mod tests {
             breaking_changes: Vec::new(),
             testing_notes: None,
             notes: None,
+            headings: None,
         };
 
         let formatted = format_pull_request(&pr);
@@ -139,6 +236,7 @@ mod tests {
             breaking_changes: vec!["All v1 endpoints removed".to_string()],
             testing_notes: Some("Run integration suite.".to_string()),
             notes: None,
+            headings: None,
         };
 
         let formatted = format_pull_request(&pr);
@@ -159,6 +257,7 @@ mod tests {
             breaking_changes: Vec::new(),
             testing_notes: Some("Check serde.".to_string()),
             notes: None,
+            headings: None,
         };
 
         let json = serde_json::to_string(&pr).expect("serialization should succeed");
@@ -178,4 +277,64 @@ mod tests {
         assert_eq!(deserialized.testing_notes, pr.testing_notes);
         assert!(deserialized.notes.is_none());
     }
+
+    #[test]
+    fn test_format_pull_request_uses_translated_headings() {
+        let pr = GeneratedPullRequest {
+            title: "feat: ログイン".to_string(),
+            summary: "ログインを追加します。".to_string(),
+            description: "OAuth2 のフロー。".to_string(),
+            commits: Vec::new(),
+            breaking_changes: Vec::new(),
+            testing_notes: Some("テスト済み。".to_string()),
+            notes: None,
+            headings: Some(SectionHeadings {
+                summary: Some("概要".to_string()),
+                description: Some("説明".to_string()),
+                testing: Some(" ".to_string()),
+                ..SectionHeadings::default()
+            }),
+        };
+
+        let formatted = format_pull_request(&pr);
+
+        assert!(formatted.contains("## 概要\nログインを追加します。"));
+        assert!(formatted.contains("## 説明\n"));
+        // Blank or missing translations fall back to English
+        assert!(formatted.contains("## Testing\nテスト済み。"));
+    }
+
+    #[test]
+    fn test_format_reviewers() {
+        assert_eq!(format_reviewers(&[]), "");
+        let reviewers = [ReviewerSuggestion {
+            name: "Grace".to_string(),
+            email: "grace@example.com".to_string(),
+            lines: 3,
+            files: vec!["src/a.rs".to_string(), "src/b.rs".to_string()],
+        }];
+        assert_eq!(
+            format_reviewers(&reviewers),
+            "## Suggested reviewers\n- Grace <grace@example.com>: last touched 3 changed line(s) in src/a.rs, src/b.rs\n"
+        );
+    }
+
+    #[test]
+    fn test_format_owners() {
+        assert_eq!(format_owners(&[]), "");
+        let owners = [
+            FileOwners {
+                path: "src/a.rs".to_string(),
+                owners: vec!["@org/rust".to_string()],
+            },
+            FileOwners {
+                path: "docs/a.md".to_string(),
+                owners: vec!["@org/docs".to_string(), "@org/rust".to_string()],
+            },
+        ];
+        assert_eq!(
+            format_owners(&owners),
+            "## Areas touched / owners\n- @org/docs: docs/a.md\n- @org/rust: src/a.rs, docs/a.md\n"
+        );
+    }
 }
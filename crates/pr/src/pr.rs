@@ -1,13 +1,18 @@
 use crate::models::GeneratedPullRequest;
 use anyhow::Result;
+use cloy::analyzers;
+use cloy::commands::commit::prompt_helpers::{
+    apply_template, context_template_vars, with_context_sections,
+};
 use cloy::common::get_combined_instructions;
 use cloy::config::Config;
-use cloy::git::GitRepo;
+use cloy::git::{self, GitRepo};
+use cloy::issues;
 use cloy::llm::context::CommitContext;
 use cloy::llm::engine;
 use cloy::llm::messages;
+use cloy::llm::{api_changes, test_impact};
 use cloy::output;
-use cloy::tui::spinner::SpinnerState;
 use prompts::pr as pr_prompts;
 use std::sync::Arc;
 
@@ -24,13 +29,15 @@ impl PullRequestStrategy {
         let schema = schemars::schema_for!(GeneratedPullRequest);
         let schema_str = serde_json::to_string_pretty(&schema)?;
         let instructions = get_combined_instructions(config);
-        Ok(pr_prompts::create_pr_system_prompt(
-            &instructions,
-            &schema_str,
-        ))
+        apply_template(
+            config,
+            "pr-system",
+            serde_json::json!({ "instructions": instructions, "schema": schema_str }),
+            pr_prompts::create_pr_system_prompt(&instructions, &schema_str),
+        )
     }
 
-    pub fn create_user_prompt(&self, context: &CommitContext) -> String {
+    pub fn create_user_prompt(&self, config: &Config, context: &CommitContext) -> Result<String> {
         let commits_section = if self.commit_messages.is_empty() {
             "No commits in current range.".to_string()
         } else {
@@ -40,17 +47,27 @@ impl PullRequestStrategy {
         let detailed_changes = format_detailed_changes(&context.staged_files);
         let recent_commits = format_recent_commits(&context.recent_commits);
 
-        pr_prompts::create_pr_user_prompt(
-            &context.branch,
-            &commits_section,
-            &detailed_changes,
-            &recent_commits,
-        )
+        let default = with_context_sections(
+            pr_prompts::create_pr_user_prompt(
+                &context.branch,
+                &commits_section,
+                &detailed_changes,
+                &recent_commits,
+            ),
+            context,
+        );
+
+        let mut vars = context_template_vars(context);
+        if let Some(vars) = vars.as_object_mut() {
+            vars.insert("commits".to_string(), commits_section.into());
+        }
+        apply_template(config, "pr-user", vars, default)
     }
 }
 
 async fn generate_pr(
     strategy: PullRequestStrategy,
+    git_repo: &GitRepo,
     instructions: &str,
     context: Option<cloy::llm::context::CommitContext>,
     config: &Config,
@@ -59,22 +76,34 @@ async fn generate_pr(
     let mut config_clone = config.clone();
     config_clone.instructions = instructions.to_string();
 
-    let Some(context) = context else {
+    let Some(mut context) = context else {
         return Err(anyhow::anyhow!(
             "Commit context is required for PR generation"
         ));
     };
+    issues::attach_issue(git_repo, &mut context).await;
+    api_changes::attach_api_changes(&mut context);
+    analyzers::attach_analyses(&mut context);
+    context.ci_failure.clone_from(&config.ci_failure);
+    if let Ok(repo) = git_repo.open_repo() {
+        test_impact::attach_test_gaps(&repo, &mut context);
+    }
+    git::render_word_diffs(&mut context.staged_files, config.diff);
 
     let system_prompt = strategy.create_system_prompt(&config_clone)?;
-    let user_prompt = strategy.create_user_prompt(&context);
+    let user_prompt = strategy.create_user_prompt(&config_clone, &context)?;
 
-    engine::get_message::<GeneratedPullRequest>(
+    let mut pr = engine::get_message::<GeneratedPullRequest>(
         &config_clone,
         provider_name,
         &system_prompt,
         &user_prompt,
     )
-    .await
+    .await?;
+    if pr.testing_notes.is_none() {
+        pr.testing_notes = test_impact::testing_notes(&context.test_gaps);
+    }
+    Ok(pr)
 }
 
 pub async fn generate_pr_based_on_parameters(
@@ -90,273 +119,26 @@ pub async fn generate_pr_based_on_parameters(
         format!("{} - Generating PR description", random_message.text).as_str(),
     );
 
-    match (from, to) {
-        (Some(from_ref), Some(to_ref)) => {
-            handle_from_and_to_parameters(
-                git_repo,
-                effective_instructions,
-                config,
-                provider_name,
-                from_ref,
-                to_ref,
-                random_message,
-            )
-            .await
-        }
-        (None, Some(to_ref)) => {
-            handle_to_only_parameter(
-                git_repo,
-                effective_instructions,
-                config,
-                provider_name,
-                to_ref,
-                random_message,
-            )
-            .await
-        }
-        (Some(from_ref), None) => {
-            handle_from_only_parameter(
-                git_repo,
-                effective_instructions,
-                config,
-                provider_name,
-                from_ref,
-                random_message,
-            )
-            .await
-        }
-        (None, None) => {
-            handle_no_parameters(
-                git_repo,
-                effective_instructions,
-                config,
-                provider_name,
-                random_message,
-            )
-            .await
-        }
-    }
-}
-
-async fn handle_from_and_to_parameters(
-    git_repo: Arc<GitRepo>,
-    effective_instructions: &str,
-    config: &Config,
-    provider_name: &str,
-    from_ref: String,
-    to_ref: String,
-    random_message: &messages::ColoredMessage,
-) -> Result<GeneratedPullRequest> {
-    if from_ref == to_ref {
-        output::create_tui_spinner(
-            format!(
-                "{} - Analyzing single commit: {}",
-                random_message.text, from_ref
-            )
-            .as_str(),
-        )
-        .tick();
-
-        let context =
-            git_repo.get_git_info_for_commit_range(config, &format!("{from_ref}^"), &from_ref)?;
-        let commit_messages = git_repo.get_commits_for_pr(&format!("{from_ref}^"), &from_ref)?;
-        let strategy = PullRequestStrategy::new(commit_messages);
-        generate_pr(
-            strategy,
-            effective_instructions,
-            Some(context),
-            config,
-            provider_name,
-        )
-        .await
-    } else if is_likely_commit_hash_or_commitish(&from_ref)
-        || is_likely_commit_hash_or_commitish(&to_ref)
-    {
-        output::create_tui_spinner(
-            format!(
-                "{} - Analyzing commit range: {}..{}",
-                random_message.text, from_ref, to_ref
-            )
-            .as_str(),
-        )
-        .tick();
-
-        let context = git_repo.get_git_info_for_commit_range(config, &from_ref, &to_ref)?;
-        let commit_messages = git_repo.get_commits_for_pr(&from_ref, &to_ref)?;
-        let strategy = PullRequestStrategy::new(commit_messages);
-        generate_pr(
-            strategy,
-            effective_instructions,
-            Some(context),
-            config,
-            provider_name,
-        )
-        .await
-    } else {
-        output::create_tui_spinner(
-            format!(
-                "{} - Comparing branches: {} -> {}",
-                random_message.text, from_ref, to_ref
-            )
-            .as_str(),
-        )
-        .tick();
-
-        let context = git_repo.get_git_info_for_branch_diff(config, &from_ref, &to_ref)?;
-        let commit_messages = git_repo.get_commits_for_pr(&from_ref, &to_ref)?;
-        let strategy = PullRequestStrategy::new(commit_messages);
-        generate_pr(
-            strategy,
-            effective_instructions,
-            Some(context),
-            config,
-            provider_name,
-        )
-        .await
-    }
-}
-
-async fn handle_to_only_parameter(
-    git_repo: Arc<GitRepo>,
-    effective_instructions: &str,
-    config: &Config,
-    provider_name: &str,
-    to_ref: String,
-    random_message: &messages::ColoredMessage,
-) -> Result<GeneratedPullRequest> {
-    if is_likely_commit_hash(&to_ref) {
-        output::create_tui_spinner(
-            format!(
-                "{} - Analyzing single commit: {}",
-                random_message.text, to_ref
-            )
-            .as_str(),
-        )
-        .tick();
-
-        let context =
-            git_repo.get_git_info_for_commit_range(config, &format!("{to_ref}^"), &to_ref)?;
-        let commit_messages = git_repo.get_commits_for_pr(&format!("{to_ref}^"), &to_ref)?;
-        let strategy = PullRequestStrategy::new(commit_messages);
-        generate_pr(
-            strategy,
-            effective_instructions,
-            Some(context),
-            config,
-            provider_name,
-        )
-        .await
-    } else if is_commitish_syntax(&to_ref) {
-        SpinnerState::with_message(
-            format!(
-                "{} - Analyzing single commit: {}",
-                random_message.text, to_ref
-            )
-            .as_str(),
-        );
-
-        let context =
-            git_repo.get_git_info_for_commit_range(config, &format!("{to_ref}^"), &to_ref)?;
-        let commit_messages = git_repo.get_commits_for_pr(&format!("{to_ref}^"), &to_ref)?;
-        let strategy = PullRequestStrategy::new(commit_messages);
-        generate_pr(
-            strategy,
-            effective_instructions,
-            Some(context),
-            config,
-            provider_name,
-        )
-        .await
-    } else {
-        SpinnerState::with_message(
-            format!("{} - Comparing main -> {}", random_message.text, to_ref).as_str(),
-        );
-
-        let context = git_repo.get_git_info_for_branch_diff(config, "main", &to_ref)?;
-        let commit_messages = git_repo.get_commits_for_pr("main", &to_ref)?;
-        let strategy = PullRequestStrategy::new(commit_messages);
-        generate_pr(
-            strategy,
-            effective_instructions,
-            Some(context),
-            config,
-            provider_name,
-        )
-        .await
-    }
-}
-
-async fn handle_from_only_parameter(
-    git_repo: Arc<GitRepo>,
-    effective_instructions: &str,
-    config: &Config,
-    provider_name: &str,
-    from_ref: String,
-    random_message: &messages::ColoredMessage,
-) -> Result<GeneratedPullRequest> {
-    if is_likely_commit_hash(&from_ref) {
-        output::create_tui_spinner(
-            format!(
-                "{} - Analyzing single commit: {}",
-                random_message.text, from_ref
-            )
-            .as_str(),
-        )
-        .tick();
-
-        let context =
-            git_repo.get_git_info_for_commit_range(config, &format!("{from_ref}^"), &from_ref)?;
-        let commit_messages = git_repo.get_commits_for_pr(&format!("{from_ref}^"), &from_ref)?;
-        let strategy = PullRequestStrategy::new(commit_messages);
-        generate_pr(
-            strategy,
-            effective_instructions,
-            Some(context),
-            config,
-            provider_name,
-        )
-        .await
-    } else {
-        output::create_tui_spinner(
-            format!(
-                "{} - Analyzing range: {}..HEAD",
-                random_message.text, from_ref
-            )
-            .as_str(),
-        )
-        .tick();
-
-        let context = git_repo.get_git_info_for_commit_range(config, &from_ref, "HEAD")?;
-        let commit_messages = git_repo.get_commits_for_pr(&from_ref, "HEAD")?;
-        let strategy = PullRequestStrategy::new(commit_messages);
-        generate_pr(
-            strategy,
-            effective_instructions,
-            Some(context),
-            config,
-            provider_name,
-        )
-        .await
-    }
-}
-
-async fn handle_no_parameters(
-    git_repo: Arc<GitRepo>,
-    effective_instructions: &str,
-    config: &Config,
-    provider_name: &str,
-    random_message: &messages::ColoredMessage,
-) -> Result<GeneratedPullRequest> {
+    let comparison = Comparison::from_args(from.as_deref(), to.as_deref());
     output::create_tui_spinner(
-        format!("{} - Comparing main -> HEAD", random_message.text).as_str(),
+        format!("{} - {}", random_message.text, comparison.describe()).as_str(),
     )
     .tick();
 
-    let context = git_repo.get_git_info_for_branch_diff(config, "main", "HEAD")?;
-    let commit_messages = git_repo.get_commits_for_pr("main", "HEAD")?;
+    let (base, target) = comparison.range();
+    let context = match comparison {
+        Comparison::Branches { .. } => {
+            git_repo.get_git_info_for_branch_diff(config, &base, &target)?
+        }
+        Comparison::Commit(_) | Comparison::Commits { .. } => {
+            git_repo.get_git_info_for_commit_range(config, &base, &target)?
+        }
+    };
+    let commit_messages = git_repo.get_commits_for_pr(&base, &target)?;
     let strategy = PullRequestStrategy::new(commit_messages);
     generate_pr(
         strategy,
+        &git_repo,
         effective_instructions,
         Some(context),
         config,
@@ -365,6 +147,64 @@ async fn handle_no_parameters(
     .await
 }
 
+/// What a PR description covers, chosen from the `--from` and `--to` arguments
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Comparison {
+    /// One commit, against its parent
+    Commit(String),
+    /// The commits between two revisions
+    Commits { from: String, to: String },
+    /// A branch against the branch it forked from
+    Branches { from: String, to: String },
+}
+
+impl Comparison {
+    /// Single commits are named by a hash or commit-ish syntax; other names are branches
+    pub fn from_args(from: Option<&str>, to: Option<&str>) -> Self {
+        let commits = |from: &str, to: &str| Self::Commits {
+            from: from.to_string(),
+            to: to.to_string(),
+        };
+        let branches = |from: &str, to: &str| Self::Branches {
+            from: from.to_string(),
+            to: to.to_string(),
+        };
+        match (from, to) {
+            (Some(from), Some(to)) if from == to => Self::Commit(from.to_string()),
+            (Some(from), Some(to))
+                if is_likely_commit_hash_or_commitish(from)
+                    || is_likely_commit_hash_or_commitish(to) =>
+            {
+                commits(from, to)
+            }
+            (Some(from), Some(to)) => branches(from, to),
+            (None, Some(to)) if is_likely_commit_hash_or_commitish(to) => {
+                Self::Commit(to.to_string())
+            }
+            (None, Some(to)) => branches("main", to),
+            (Some(from), None) if is_likely_commit_hash(from) => Self::Commit(from.to_string()),
+            (Some(from), None) => commits(from, "HEAD"),
+            (None, None) => branches("main", "HEAD"),
+        }
+    }
+
+    /// The `(from, to)` revisions compared
+    pub fn range(&self) -> (String, String) {
+        match self {
+            Self::Commit(commit) => (format!("{commit}^"), commit.clone()),
+            Self::Commits { from, to } | Self::Branches { from, to } => (from.clone(), to.clone()),
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            Self::Commit(commit) => format!("Analyzing single commit: {commit}"),
+            Self::Commits { from, to } => format!("Analyzing commit range: {from}..{to}"),
+            Self::Branches { from, to } => format!("Comparing branches: {from} -> {to}"),
+        }
+    }
+}
+
 use cloy::llm::context::{ChangeType, RecentCommit, StagedFile};
 
 const MAX_DIFF_LENGTH: usize = 2000;
@@ -564,6 +404,30 @@ mod tests {
         assert!(!is_commitish_syntax("release-v2"));
     }
 
+    #[test]
+    fn test_comparison_range() {
+        let range = |from, to| {
+            let (from, to) = Comparison::from_args(from, to).range();
+            format!("{from}..{to}")
+        };
+        assert_eq!(range(None, None), "main..HEAD");
+        assert_eq!(range(Some("develop"), None), "develop..HEAD");
+        assert_eq!(range(Some("abc1234"), None), "abc1234^..abc1234");
+        assert_eq!(range(None, Some("feature")), "main..feature");
+        assert_eq!(range(None, Some("HEAD~1")), "HEAD~1^..HEAD~1");
+        assert_eq!(range(Some("v1"), Some("v1")), "v1^..v1");
+        assert_eq!(range(Some("v1"), Some("v2")), "v1..v2");
+
+        assert!(matches!(
+            Comparison::from_args(Some("main"), Some("feature")),
+            Comparison::Branches { .. }
+        ));
+        assert!(matches!(
+            Comparison::from_args(Some("main"), Some("HEAD~2")),
+            Comparison::Commits { .. }
+        ));
+    }
+
     #[test]
     fn test_is_likely_commit_hash_or_commitish_combined() {
         assert!(is_likely_commit_hash_or_commitish("abc1234"));
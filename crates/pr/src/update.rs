@@ -0,0 +1,109 @@
+//! Merging a regenerated description into the body of an existing PR.
+//!
+//! The generated description is written between `<!-- gitai:begin -->` and
+//! `<!-- gitai:end -->`. On update only that region is replaced: text around
+//! it stays, and so does any block wrapped in `<!-- gitai:keep -->` and
+//! `<!-- /gitai:keep -->`, wherever it is. A body without the markers was
+//! written by hand; it stays whole and the generated region is appended.
+
+/// Start of the generated region
+pub const BEGIN_MARKER: &str = "<!-- gitai:begin -->";
+
+/// End of the generated region
+pub const END_MARKER: &str = "<!-- gitai:end -->";
+
+/// Start of a hand-written block that updates keep
+pub const KEEP_MARKER: &str = "<!-- gitai:keep -->";
+
+/// End of a hand-written block
+pub const KEEP_END_MARKER: &str = "<!-- /gitai:keep -->";
+
+/// The keep blocks of `text`, markers included; an unterminated block runs to the end
+fn keep_blocks(text: &str) -> Vec<&str> {
+    let mut blocks = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find(KEEP_MARKER) {
+        let block = &rest[start..];
+        let end = block
+            .find(KEEP_END_MARKER)
+            .map_or(block.len(), |end| end + KEEP_END_MARKER.len());
+        blocks.push(block[..end].trim_end());
+        rest = &block[end..];
+    }
+    blocks
+}
+
+/// The generated description between the markers
+pub fn wrap_generated(description: &str) -> String {
+    format!("{BEGIN_MARKER}\n{}\n{END_MARKER}", description.trim())
+}
+
+/// The PR body with its generated region replaced by `description`
+pub fn merge_description(existing: &str, description: &str) -> String {
+    let generated = wrap_generated(description);
+    let region = existing.find(BEGIN_MARKER).and_then(|begin| {
+        let end = existing[begin..].find(END_MARKER)? + begin + END_MARKER.len();
+        Some((begin, end))
+    });
+    let Some((begin, end)) = region else {
+        let existing = existing.trim_end();
+        return if existing.is_empty() {
+            generated
+        } else {
+            format!("{existing}\n\n{generated}")
+        };
+    };
+
+    let mut body = existing[..begin].to_string();
+    body.push_str(&generated);
+    for block in keep_blocks(&existing[begin..end]) {
+        body.push_str("\n\n");
+        body.push_str(block);
+    }
+    body.push_str(&existing[end..]);
+    body
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_the_generated_region_is_replaced() {
+        let existing = format!(
+            "Closes #12\n\n{BEGIN_MARKER}\n## Summary\nOld.\n\n{KEEP_MARKER}\nDeploy after 6pm.\n{KEEP_END_MARKER}\n{END_MARKER}\n\nScreenshots below."
+        );
+        let merged = merge_description(&existing, "## Summary\nNew.\n");
+        assert_eq!(
+            merged,
+            format!(
+                "Closes #12\n\n{BEGIN_MARKER}\n## Summary\nNew.\n{END_MARKER}\n\n{KEEP_MARKER}\nDeploy after 6pm.\n{KEEP_END_MARKER}\n\nScreenshots below."
+            )
+        );
+        // Updating again leaves the hand-written parts as they are
+        let again = merge_description(&merged, "## Summary\nNewer.");
+        assert!(again.starts_with("Closes #12\n\n"));
+        assert!(again.contains("Newer."));
+        assert_eq!(again.matches("Deploy after 6pm.").count(), 1);
+        assert!(again.ends_with("Screenshots below."));
+    }
+
+    #[test]
+    fn test_unmarked_body_is_kept_and_generated_region_appended() {
+        let merged = merge_description("Written by hand.\n", "## Summary\nNew.");
+        assert_eq!(
+            merged,
+            format!("Written by hand.\n\n{}", wrap_generated("## Summary\nNew."))
+        );
+        // The next update only replaces the appended region
+        let again = merge_description(&merged, "## Summary\nNewer.");
+        assert_eq!(
+            again,
+            format!(
+                "Written by hand.\n\n{}",
+                wrap_generated("## Summary\nNewer.")
+            )
+        );
+        assert_eq!(merge_description("  \n", "New."), wrap_generated("New."));
+    }
+}
@@ -50,32 +50,32 @@ pub fn create_system_prompt(instructions: &str, schema_json: &str) -> String {
     )
 }
 
+/// History block of a user prompt, left out entirely before the first commit
+fn history_section(label: &str, recent_commits: &str, separator: &str) -> String {
+    if recent_commits.trim().is_empty() {
+        String::new()
+    } else {
+        format!("- **{label}:**\n{recent_commits}\n{separator}")
+    }
+}
+
+/// User prompt for a commit message.
+///
+/// Sections that rarely change between calls (requirements, rules, history and
+/// style) come before the diff, so providers that cache prompt prefixes can
+/// reuse them when messages are generated repeatedly.
 pub fn create_user_prompt(
     branch: &str,
     staged_changes: &str,
     detailed_changes: &str,
     recent_commits: &str,
     author_history: &str,
+    related_files: &str,
     detail_instruction: &str,
 ) -> String {
+    let history = history_section("Contextual History", recent_commits, "\n");
     format!(
         "### MAINTAINER TASK: GENERATE TECHNICAL COMMIT LOG\n\
-         \n\
-         #### DATA CONTEXT\n\
-         - **Branch:** `{branch}`\n\
-         - **Staged Change List:**\n\
-         ```\n\
-         {staged_changes}\n\
-         ```\n\
-         \n\
-         - **Detailed Diffs (Source of Truth):**\n\
-         {detailed_changes}\n\
-         \n\
-         - **Contextual History:**\n\
-         {recent_commits}\n\
-         \n\
-         - **Detected Style:**\n\
-         {author_history}\n\
          \n\
          #### ANALYSIS REQUIREMENTS\n\
          1. **Subsystem Subject:** Determine the most specific subsystem prefix (e.g. \"core\", \"tui/theme\").\n\
@@ -89,6 +89,24 @@ pub fn create_user_prompt(
          - Focus on the technical merit and the narrative of the change.\n\
          - {detail_instruction}\n\
          \n\
+         #### PROJECT CONTEXT\n\
+         {history}\
+         - **Detected Style:**\n\
+         {author_history}\n\
+         \n\
+         #### DATA CONTEXT\n\
+         - **Branch:** `{branch}`\n\
+         - **Staged Change List:**\n\
+         ```\n\
+         {staged_changes}\n\
+         ```\n\
+         \n\
+         - **Detailed Diffs (Source of Truth):**\n\
+         {detailed_changes}\n\
+         \n\
+         - **Related Files (unchanged, for orientation only):**\n\
+         {related_files}\n\
+         \n\
          Generate the JSON object now."
     )
 }
@@ -126,6 +144,7 @@ pub fn create_completion_system_prompt(instructions: &str, schema_json: &str) ->
     )
 }
 
+/// User prompt for completing a partial message; stable sections come first, as above
 pub fn create_completion_user_prompt(
     prefix: &str,
     context_ratio: f32,
@@ -137,13 +156,22 @@ pub fn create_completion_user_prompt(
 ) -> String {
     let mut detail = String::new();
     let pct = context_ratio * 100.0;
+    let history = history_section("Recent History", recent_commits, "");
     write!(
         detail,
         "### TASK: COMPLETE PARTIAL COMMIT MESSAGE\n\
          \n\
-         #### USER INPUT\n\
-         - **Current Prefix:** `{prefix}`\n\
-         - **Context Match Ratio:** {pct:.0}%\n\
+         #### COMPLETION INSTRUCTIONS\n\
+         1. **Syntactic Match:** If the prefix ends with a colon or a space, continue with the \
+         description. If it ends mid-word, finish the word.\n\
+         2. **Pattern Recognition:** Use the author's history to determine the likely completion.\n\
+         3. **Final synthesis:** The final message (Prefix + your Completion) must be a high-quality, \
+         professional commit message.\n\
+         \n\
+         #### PROJECT CONTEXT\n\
+         {history}\
+         - **Author Style:**\n\
+         {author_history}\n\
          \n\
          #### DATA CONTEXT\n\
          - **Branch:** `{branch}`\n\
@@ -153,21 +181,136 @@ pub fn create_completion_user_prompt(
          ```\n\
          - **Diff Detais:\n\
          {detailed_changes}\n\
-         - **Recent History:**\n\
-         {recent_commits}\n\
-         - **Author Style:**\n\
-         {author_history}\n\
          \n\
-         #### COMPLETION INSTRUCTIONS\n\
-         1. **Syntactic Match:** If the prefix ends with a colon or a space, continue with the \
-         description. If it ends mid-word, finish the word.\n\
-         2. **Pattern Recognition:** Use the author's history to determine the likely completion.\n\
-         3. **Final synthesis:** The final message (Prefix + your Completion) must be a high-quality, \
-         professional commit message.\n\
-          \n\
-          Generate the JSON completion now."
+         #### USER INPUT\n\
+         - **Current Prefix:** `{prefix}`\n\
+         - **Context Match Ratio:** {pct:.0}%\n\
+         \n\
+         Generate the JSON completion now."
     )
     .expect("writing to string should never fail");
 
     detail
 }
+
+pub fn create_refine_user_prompt(
+    base_prompt: &str,
+    previous_title: &str,
+    previous_body: &str,
+    feedback: &str,
+) -> String {
+    format!(
+        "{base_prompt}\n\
+         \n\
+         ### REVISION REQUEST\n\
+         The previous draft for these changes was:\n\
+         ```\n\
+         {previous_title}\n\
+         \n\
+         {previous_body}\n\
+         ```\n\
+         \n\
+         The developer asked for this revision:\n\
+         > {feedback}\n\
+         \n\
+         Revise the previous draft to satisfy the request. Keep everything the request does \
+         not ask to change, and stay faithful to the diffs above.\n\
+         \n\
+         Generate the revised JSON object now."
+    )
+}
+
+pub fn create_explain_system_prompt(instructions: &str, schema_json: &str) -> String {
+    format!(
+        "# PERSONA\n\
+         You are a senior engineer walking a colleague through an existing commit. You explain \
+         what changed, why it was likely needed, and what to watch out for, without speculation \
+         beyond what the diff supports.\n\
+         \n\
+         # OUTPUT\n\
+         Respond with a JSON object matching this schema:\n\
+         {schema_json}\n\
+         Use `title` for a one-line summary of the commit and `message` for the explanation.\n\
+         {instructions}"
+    )
+}
+
+pub fn create_explain_user_prompt(commit: &str, detailed_changes: &str) -> String {
+    format!(
+        "### TASK: EXPLAIN AN EXISTING COMMIT\n\
+         \n\
+         #### COMMIT\n\
+         {commit}\n\
+         \n\
+         #### CHANGES\n\
+         {detailed_changes}\n\
+         \n\
+         Explain the intent of the change, how the code achieves it, and any risks or \
+         follow-ups a reviewer should know about.\n\
+         \n\
+         Generate the JSON object now."
+    )
+}
+
+/// System prompt for `--subject-only`, kept short so the request stays fast
+pub fn create_subject_system_prompt(instructions: &str) -> String {
+    format!(
+        "You write the subject line of a git commit from a list of changed files. Use the \
+         imperative mood and at most 72 characters, and follow the conventions of the recent \
+         subjects. Reply with the subject only, without quotes or a body.\n\
+         {instructions}"
+    )
+}
+
+/// User prompt for `--subject-only`: the changed files and their line counts, no diffs
+pub fn create_subject_user_prompt(
+    branch: &str,
+    change_summary: &str,
+    recent_subjects: &str,
+) -> String {
+    format!(
+        "Branch: {branch}\n\
+         \n\
+         Recent subjects:\n\
+         {recent_subjects}\n\
+         \n\
+         Changed files:\n\
+         {change_summary}\n\
+         \n\
+         Write the subject now."
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_user_prompt_keeps_stable_sections_first() {
+        let prompt = |diff: &str| {
+            create_user_prompt(
+                "main",
+                "M src/lib.rs",
+                diff,
+                "abc123 core: add cache",
+                "Imperative subjects",
+                "",
+                "Be brief.",
+            )
+        };
+        let first = prompt("@@ -1 +1 @@\n-a\n+b");
+        let second = prompt("@@ -9 +9 @@\n-c\n+d");
+        let shared = first
+            .chars()
+            .zip(second.chars())
+            .take_while(|(a, b)| a == b)
+            .count();
+        let data = first.find("#### DATA CONTEXT").expect("data section");
+        assert!(
+            shared > data,
+            "prefix of {shared} chars ends before the data"
+        );
+        assert!(first[..data].contains("Imperative subjects"));
+        assert!(first[..data].contains("Be brief."));
+    }
+}
@@ -0,0 +1,92 @@
+//! Instructions for writing generated text in a language other than English.
+//!
+//! `--language` and `gitai.language` take a code such as `ja` or `pt-BR`, or
+//! a language name. Codes in [`LANGUAGES`] are spelled out for the model;
+//! anything else is passed through as given.
+
+/// Language codes and their English names
+pub const LANGUAGES: &[(&str, &str)] = &[
+    ("ar", "Arabic"),
+    ("bn", "Bengali"),
+    ("cs", "Czech"),
+    ("da", "Danish"),
+    ("de", "German"),
+    ("el", "Greek"),
+    ("en", "English"),
+    ("es", "Spanish"),
+    ("fa", "Persian"),
+    ("fi", "Finnish"),
+    ("fr", "French"),
+    ("he", "Hebrew"),
+    ("hi", "Hindi"),
+    ("hu", "Hungarian"),
+    ("id", "Indonesian"),
+    ("it", "Italian"),
+    ("ja", "Japanese"),
+    ("ko", "Korean"),
+    ("nl", "Dutch"),
+    ("no", "Norwegian"),
+    ("pl", "Polish"),
+    ("pt", "Portuguese"),
+    ("pt-br", "Brazilian Portuguese"),
+    ("ro", "Romanian"),
+    ("ru", "Russian"),
+    ("sv", "Swedish"),
+    ("th", "Thai"),
+    ("tr", "Turkish"),
+    ("uk", "Ukrainian"),
+    ("vi", "Vietnamese"),
+    ("zh", "Chinese"),
+    ("zh-cn", "Simplified Chinese"),
+    ("zh-tw", "Traditional Chinese"),
+];
+
+/// The English name of a language code, or the value itself when it is not a known code
+pub fn language_name(language: &str) -> String {
+    let language = language.trim();
+    let code = language.to_lowercase().replace('_', "-");
+    LANGUAGES
+        .iter()
+        .find(|(known, _)| *known == code)
+        .or_else(|| {
+            let primary = code.split('-').next()?;
+            LANGUAGES.iter().find(|(known, _)| *known == primary)
+        })
+        .map_or_else(|| language.to_string(), |(_, name)| (*name).to_string())
+}
+
+/// Whether the language is English, the language prompts are written in
+pub fn is_english(language: &str) -> bool {
+    language_name(language).eq_ignore_ascii_case("english")
+}
+
+/// Prompt instructions for answering in `language`; empty for English
+pub fn language_instructions(language: &str) -> String {
+    if language.trim().is_empty() || is_english(language) {
+        return String::new();
+    }
+    let name = language_name(language);
+    format!(
+        "Write every human-readable text field of the response (titles, summaries, \
+         descriptions and list items) in {name}. Keep code identifiers, file paths, \
+         commands, conventional commit types and scopes, and JSON keys unchanged. \
+         When the schema has a `headings` object, fill it with the section headings \
+         translated into {name}."
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_language_names() {
+        assert_eq!(language_name("ja"), "Japanese");
+        assert_eq!(language_name("pt_BR"), "Brazilian Portuguese");
+        assert_eq!(language_name("de-AT"), "German");
+        assert_eq!(language_name("Esperanto"), "Esperanto");
+        assert!(is_english("en-GB"));
+        assert!(language_instructions("en").is_empty());
+        assert!(language_instructions("ja").contains("in Japanese"));
+    }
+}
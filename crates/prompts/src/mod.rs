@@ -2,11 +2,15 @@
 //!
 //! The `builder` and `sections` modules provide a composable prompt-building API.
 //! The `commit`, `changelog`, `pr`, and `notes` modules provide ready-to-use
-//! prompt template functions for each domain.
+//! prompt template functions for each domain, `language` the instructions for
+//! answering in another language, and `templates` renders the user-supplied
+//! files that can replace them.
 
 pub mod builder;
 pub mod changelog;
 pub mod commit;
+pub mod language;
 pub mod notes;
 pub mod pr;
 pub mod sections;
+pub mod templates;
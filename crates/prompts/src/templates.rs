@@ -0,0 +1,61 @@
+//! User-supplied Handlebars templates that replace the built-in prompts.
+//!
+//! Templates are rendered in strict mode, so a misspelled variable is an error
+//! instead of an empty string, and without HTML escaping since prompts are
+//! plain text. Every template receives `default`, the built-in prompt it
+//! replaces, so a template can also wrap rather than rewrite it.
+
+use anyhow::{Context, Result};
+use handlebars::Handlebars;
+use serde_json::Value;
+
+/// Prompts that can be overridden with `gitai.template-<name>`
+pub const TEMPLATE_NAMES: &[&str] = &[
+    "commit-system",
+    "commit-user",
+    "completion-system",
+    "completion-user",
+    "explain-system",
+    "explain-user",
+    "pr-system",
+    "pr-user",
+    "subject-system",
+    "subject-user",
+    "changelog-system",
+    "changelog-user",
+    "notes-system",
+    "notes-user",
+];
+
+/// Renders a Handlebars template with the given variables
+pub fn render(template: &str, vars: &Value) -> Result<String> {
+    let mut registry = Handlebars::new();
+    registry.set_strict_mode(true);
+    registry.register_escape_fn(handlebars::no_escape);
+    registry
+        .render_template(template, vars)
+        .context("Failed to render prompt template")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_render() {
+        let vars = json!({
+            "branch": "main",
+            "diffs": "<diff>",
+            "commits": [{"hash": "abc"}, {"hash": "def"}],
+        });
+        let rendered = render(
+            "On {{branch}}: {{diffs}}{{#each commits}} {{hash}}{{/each}}",
+            &vars,
+        )
+        .expect("Template should render");
+        assert_eq!(rendered, "On main: <diff> abc def");
+
+        assert!(render("{{brnch}}", &vars).is_err());
+    }
+}
@@ -0,0 +1,46 @@
+//! Purging what gitai cached about an author
+
+use anyhow::{Result, anyhow};
+use cloy::commit_cache;
+use cloy::git::{canonical_email, load_mailmap};
+use cloy::llm::style::StyleProfile;
+use cloy::output;
+use git2::Repository;
+
+/// Deletes the style profile of `email` and the current repository's drafts generated for it
+pub fn handle_purge(email: &str) -> Result<()> {
+    let email = email.trim();
+    if !email.contains('@') {
+        return Err(anyhow!("'{email}' is not an email address"));
+    }
+    let repo = Repository::open_from_env().ok();
+
+    // The profile is kept under the address the mailmap resolves to
+    let mut emails = vec![email.to_lowercase()];
+    if let Some(repo) = &repo {
+        let canonical = canonical_email(load_mailmap(repo).as_ref(), "", email);
+        if !emails.contains(&canonical) {
+            emails.push(canonical);
+        }
+    }
+
+    let mut profiles = 0;
+    let mut drafts = 0;
+    for email in &emails {
+        if StyleProfile::purge(email)? {
+            profiles += 1;
+        }
+        if let Some(repo) = &repo {
+            drafts += commit_cache::purge_author(repo, email)?;
+        }
+    }
+
+    if profiles == 0 && drafts == 0 {
+        output::print_info(&format!("Nothing cached for {email}"));
+    } else {
+        output::print_success(&format!(
+            "Removed {profiles} style profile(s) and {drafts} draft(s) of {email}"
+        ));
+    }
+    Ok(())
+}
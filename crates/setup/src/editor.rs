@@ -0,0 +1,296 @@
+//! `gitai editor`, the commit message editor for `GIT_EDITOR` and `core.editor`.
+//!
+//! When git opens `COMMIT_EDITMSG`, a draft is written above git's comment
+//! lines and the file is handed to the editor the user normally runs, or
+//! edited in the TUI with `--tui`. A started subject is completed rather than
+//! replaced. A message that already has a body, as when amending, and the
+//! messages git writes for merges, reverts and cherry-picks are kept as they
+//! are. Any failure to draft is only a warning, so `git commit` always reaches
+//! an editor. Files other than `COMMIT_EDITMSG`, such as a rebase todo list,
+//! go straight to the editor.
+
+use anyhow::{Context, Result, anyhow};
+use cloy::commands::commit::{CommitService, GeneratedMessage, format_commit_message};
+use cloy::commands::common::service::{create_commit_service, create_completion_service};
+use cloy::common::CommonParams;
+use cloy::config::Config;
+use cloy::output;
+use cloy::tui::{Theme, run_tui_message};
+use git2::{Repository, RepositoryState};
+use std::ffi::OsStr;
+use std::fs;
+use std::io::IsTerminal;
+use std::path::Path;
+use std::process::Command;
+use std::time::Duration;
+
+/// Completion ratio used when the file already holds the start of a message
+const COMPLETION_CONTEXT_RATIO: f32 = 0.5;
+
+/// Seconds drafting may take before git gets the editor without a draft
+pub const DEFAULT_EDITOR_TIMEOUT_SECS: u64 = 60;
+
+/// The message and git's comment lines of a message file
+#[derive(Debug, PartialEq, Eq)]
+pub struct MessageFile {
+    /// Lines that are not comments, trimmed
+    pub message: String,
+    /// Comment lines, then everything from the scissors line on
+    pub comments: String,
+}
+
+impl MessageFile {
+    /// Splits `text` with `comment` as the comment character
+    pub fn parse(text: &str, comment: char) -> Self {
+        let scissors = format!("{comment} ------------------------ >8 ------------------------");
+        let (text, cut) = match text.find(&scissors) {
+            Some(at) => text.split_at(at),
+            None => (text, ""),
+        };
+        let (comments, message): (Vec<&str>, Vec<&str>) =
+            text.lines().partition(|line| line.starts_with(comment));
+        let mut comments = comments.join("\n");
+        if !cut.is_empty() {
+            if !comments.is_empty() {
+                comments.push('\n');
+            }
+            comments.push_str(cut.trim_end());
+        }
+        Self {
+            message: message.join("\n").trim().to_string(),
+            comments,
+        }
+    }
+
+    /// The file's text with `message` in place of the current one
+    pub fn render(&self, message: &str) -> String {
+        let message = message.trim();
+        match (message.is_empty(), self.comments.is_empty()) {
+            (_, true) => format!("{message}\n"),
+            (true, false) => format!("\n{}\n", self.comments),
+            (false, false) => format!("{message}\n\n{}\n", self.comments),
+        }
+    }
+}
+
+/// The comment character git uses for the repository's message files
+fn comment_char(repo: &Repository) -> char {
+    repo.config()
+        .and_then(|config| config.get_string("core.commentChar"))
+        .ok()
+        // `auto` picks a character the message does not use; git starts with `#`
+        .filter(|value| value != "auto")
+        .and_then(|value| value.chars().next())
+        .unwrap_or('#')
+}
+
+/// The editor to hand files to: `gitai.editor`, `$VISUAL`, `$EDITOR` or `vi`
+fn fallback_editor(repo: Option<&Repository>) -> String {
+    let configured = repo
+        .and_then(|repo| repo.config().ok())
+        .and_then(|config| config.get_string("gitai.editor").ok());
+    [
+        configured,
+        std::env::var("VISUAL").ok(),
+        std::env::var("EDITOR").ok(),
+    ]
+    .into_iter()
+    .flatten()
+    // Never hand the file back to ourselves
+    .find(|editor| !editor.trim().is_empty() && !editor.contains("gitai editor"))
+    .unwrap_or_else(|| "vi".to_string())
+}
+
+/// Opens `file` in `editor` the way git runs editors, through the shell
+fn run_editor(editor: &str, file: &Path) -> Result<()> {
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(format!("{editor} \"$@\""))
+        .arg(editor)
+        .arg(file)
+        .status()
+        .with_context(|| format!("Failed to run editor {editor}"))?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(anyhow!("Editor {editor} exited with {status}"))
+    }
+}
+
+/// `Name <email>` of the configured user, for `Signed-off-by`
+fn signer(repo: &Repository) -> Option<String> {
+    let signature = repo.signature().ok()?;
+    Some(format!("{} <{}>", signature.name()?, signature.email()?))
+}
+
+/// What drafting needs from the repository, read up front
+struct RepoInfo {
+    comment: char,
+    /// No merge, revert or cherry-pick in progress
+    clean: bool,
+    signer: Option<String>,
+}
+
+impl RepoInfo {
+    fn read(repo: &Repository) -> Self {
+        Self {
+            comment: comment_char(repo),
+            clean: repo.state() == RepositoryState::Clean,
+            signer: signer(repo),
+        }
+    }
+}
+
+/// A draft for the staged changes, completing `started` if the user began a subject
+async fn draft(
+    service: &CommitService,
+    config: &Config,
+    started: &str,
+) -> Result<GeneratedMessage> {
+    let instructions = config.instructions.clone();
+    let message = if started.is_empty() {
+        match service.special_message(None)? {
+            Some(message) => message,
+            None => service.generate_message(&instructions).await?,
+        }
+    } else {
+        service
+            .generate_completion(started, COMPLETION_CONTEXT_RATIO, &instructions)
+            .await?
+    };
+    Ok(message)
+}
+
+/// Writes a draft into the message file; returns whether the user already reviewed it
+async fn draft_into(repo: &RepoInfo, file: &Path, tui: bool, timeout: Duration) -> Result<bool> {
+    let text =
+        fs::read_to_string(file).with_context(|| format!("Failed to read {}", file.display()))?;
+    let parsed = MessageFile::parse(&text, repo.comment);
+    if parsed.message.lines().count() > 1 || !repo.clean {
+        return Ok(false);
+    }
+
+    let config = Config::load()?;
+    let common = CommonParams::default();
+    let service = create_commit_service(&common, None, &config)?;
+    if service.get_git_info().await?.staged_files.is_empty() {
+        return Ok(false);
+    }
+    output::print_info("Drafting the commit message...");
+    let drafted = tokio::time::timeout(timeout, draft(&service, &config, &parsed.message))
+        .await
+        .map_err(|_| anyhow!("timed out after {}s", timeout.as_secs()))??;
+    let signer = repo.signer.as_deref();
+
+    let message = if tui {
+        let theme = if output::is_color_enabled() {
+            Theme::from_name(&common.theme, &config.themes)?
+        } else {
+            Theme::monochrome()
+        };
+        let completion_service = create_completion_service(&common, None, &config)?;
+        // Cancelling leaves the message empty, so git aborts the commit
+        run_tui_message(
+            vec![drafted],
+            config.instructions.clone(),
+            service,
+            completion_service,
+            theme,
+        )
+        .await?
+        .map(|message| config.trailers.apply(&message, signer))
+        .unwrap_or_default()
+    } else {
+        config
+            .trailers
+            .apply(&format_commit_message(&drafted), signer)
+    };
+
+    fs::write(file, parsed.render(&message))
+        .with_context(|| format!("Failed to write {}", file.display()))?;
+    Ok(tui)
+}
+
+/// Drafts the message of a `git commit` into `file`, then opens an editor on it.
+///
+/// With `tui` the draft is edited in the TUI instead; with `no_edit` it is
+/// written without opening anything. Without a terminal nothing is opened.
+/// Drafting gives up after `timeout_secs`.
+pub async fn handle_editor(file: &Path, tui: bool, no_edit: bool, timeout_secs: u64) -> Result<()> {
+    let repo = Repository::open_from_env().ok();
+    let editor = fallback_editor(repo.as_ref());
+    let repo = repo.as_ref().map(RepoInfo::read);
+    let interactive = std::io::stdin().is_terminal();
+
+    let mut reviewed = false;
+    let mut drafted = false;
+    if file.file_name() == Some(OsStr::new("COMMIT_EDITMSG"))
+        && let Some(repo) = &repo
+    {
+        match draft_into(
+            repo,
+            file,
+            tui && interactive,
+            Duration::from_secs(timeout_secs),
+        )
+        .await
+        {
+            Ok(done) => {
+                reviewed = done;
+                drafted = true;
+            }
+            Err(e) => output::print_warning(&format!("No draft from gitai: {e:#}")),
+        }
+    }
+
+    if reviewed || !interactive || (no_edit && drafted) {
+        return Ok(());
+    }
+    run_editor(&editor, file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEMPLATE: &str = "\n\
+# Please enter the commit message for your changes. Lines starting\n\
+# with '#' will be ignored.\n\
+#\n\
+# On branch main\n";
+
+    #[test]
+    fn test_draft_goes_above_the_comments() {
+        let file = MessageFile::parse(TEMPLATE, '#');
+        assert!(file.message.is_empty());
+        assert_eq!(
+            file.render("Add parser\n\nWith tests.\n"),
+            format!("Add parser\n\nWith tests.\n{TEMPLATE}")
+        );
+        assert_eq!(file.render(""), TEMPLATE);
+
+        let started = MessageFile::parse(&format!("fix(parser): \n{TEMPLATE}"), '#');
+        assert_eq!(started.message, "fix(parser):");
+        assert_eq!(started.comments, TEMPLATE.trim());
+    }
+
+    #[test]
+    fn test_verbose_diff_is_kept_below_the_scissors() {
+        let text = "\n\
+; On branch main\n\
+; ------------------------ >8 ------------------------\n\
+; Do not modify or remove the line above.\n\
+diff --git a/src/lib.rs b/src/lib.rs\n\
++#[test]\n";
+        let file = MessageFile::parse(text, ';');
+        assert!(file.message.is_empty());
+        assert!(
+            file.comments
+                .ends_with("diff --git a/src/lib.rs b/src/lib.rs\n+#[test]")
+        );
+        assert!(
+            file.render("Add test")
+                .starts_with("Add test\n\n; On branch main\n; ---")
+        );
+    }
+}
@@ -0,0 +1,429 @@
+pub mod cache;
+pub mod editor;
+pub mod migrate;
+pub mod models;
+pub mod usage;
+
+use anyhow::{Result, anyhow};
+use clap::{Args, Subcommand};
+use cloy::config::{CONVENTIONAL_INSTRUCTIONS, Config};
+#[cfg(unix)]
+use cloy::daemon;
+use cloy::git::{install_message_hook, message_hook_installed};
+use cloy::llm::bedrock::REGION_PARAM;
+use cloy::llm::endpoint::{API_VERSION_PARAM, BASE_URL_PARAM, DEPLOYMENT_PARAM, validate_endpoint};
+use cloy::llm::model_info;
+use cloy::llm::provider::ProviderKind;
+use cloy::output;
+use git2::Config as GitConfig;
+use inquire::{Confirm, Password, PasswordDisplayMode, Select, Text};
+use std::collections::HashMap;
+use std::fmt;
+use std::io::IsTerminal;
+use std::path::PathBuf;
+#[cfg(unix)]
+use std::time::Duration;
+
+#[derive(Args, Clone, Debug)]
+pub struct SetupArgs {
+    #[command(subcommand)]
+    pub command: SetupCommand,
+}
+
+#[derive(Subcommand, Clone, Debug)]
+pub enum SetupCommand {
+    /// Choose a provider, API key, model and message style interactively
+    Init,
+    /// List the models of each configured provider and check the configured model
+    Models {
+        #[arg(long, help = "Only list this provider's models")]
+        provider: Option<ProviderKind>,
+    },
+    /// Report how often generated messages were committed as-is, edited or regenerated
+    Usage {
+        #[arg(long, help = "Delete the recorded counts")]
+        reset: bool,
+    },
+    /// Draft commit messages inside `git commit`; set as `GIT_EDITOR` or `core.editor`
+    Editor {
+        /// File git asks to edit
+        file: PathBuf,
+        #[arg(long, help = "Edit the draft in the gitai TUI instead of your editor")]
+        tui: bool,
+        #[arg(
+            long,
+            conflicts_with = "tui",
+            help = "Write the draft without opening an editor"
+        )]
+        no_edit: bool,
+        #[arg(
+            long,
+            value_name = "SECS",
+            default_value_t = editor::DEFAULT_EDITOR_TIMEOUT_SECS,
+            help = "Open the editor without a draft after this many seconds"
+        )]
+        timeout: u64,
+    },
+    /// Manage what gitai caches about authors
+    Cache {
+        #[command(subcommand)]
+        command: CacheCommand,
+    },
+    /// Manage the gitai settings in git config
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+    /// Keep gitai loaded and answer `git-message --ci` over a Unix socket
+    #[cfg(unix)]
+    Daemon {
+        #[arg(
+            long,
+            value_name = "PATH",
+            help = "Socket to listen on (default: $GITAI_DAEMON_SOCKET or gitai/daemon.sock in the runtime directory)"
+        )]
+        socket: Option<PathBuf>,
+        #[arg(
+            long,
+            value_name = "SECS",
+            help = "Stop after this many seconds without requests"
+        )]
+        idle_timeout: Option<u64>,
+    },
+}
+
+#[derive(Subcommand, Clone, Debug)]
+pub enum CacheCommand {
+    /// Delete an author's style profile and their drafts in the current repository
+    Purge {
+        #[arg(
+            long,
+            value_name = "EMAIL",
+            help = "Author whose cached data to delete"
+        )]
+        author: String,
+    },
+}
+
+#[derive(Subcommand, Clone, Debug)]
+pub enum ConfigCommand {
+    /// Upgrade settings written in an older layout to the current one
+    Migrate {
+        #[arg(long, help = "List the changes without writing them")]
+        dry_run: bool,
+        #[arg(
+            long,
+            value_name = "PATH",
+            help = "Config file to migrate (default: the global and repository config)"
+        )]
+        file: Option<PathBuf>,
+    },
+}
+
+/// Handles the setup command.
+///
+/// `init` walks through the settings every command needs and writes them to
+/// the global git config in one go. The API key is kept in a separate file
+/// only the current user can read; see `Config::store_api_key`. `models`
+/// lists what the providers currently offer, and `usage` the locally
+/// recorded acceptance rates. `editor` is run by git rather than by hand,
+/// `cache purge` deletes what was cached about an author, `config migrate`
+/// upgrades settings written in an older layout, and `daemon` serves
+/// messages to hooks until stopped.
+pub async fn handle_setup(args: &SetupArgs) -> Result<()> {
+    match &args.command {
+        SetupCommand::Init => init().await,
+        SetupCommand::Models { provider } => models::handle_models(*provider).await,
+        SetupCommand::Usage { reset } => usage::handle_usage(*reset),
+        SetupCommand::Editor {
+            file,
+            tui,
+            no_edit,
+            timeout,
+        } => editor::handle_editor(file, *tui, *no_edit, *timeout).await,
+        SetupCommand::Cache {
+            command: CacheCommand::Purge { author },
+        } => cache::handle_purge(author),
+        SetupCommand::Config {
+            command: ConfigCommand::Migrate { dry_run, file },
+        } => migrate::handle_migrate(file.as_deref(), *dry_run),
+        #[cfg(unix)]
+        SetupCommand::Daemon {
+            socket,
+            idle_timeout,
+        } => {
+            let socket = socket.clone().unwrap_or_else(daemon::socket_path);
+            output::print_info(&format!("Listening on {}", socket.display()));
+            daemon::serve(&socket, idle_timeout.map(Duration::from_secs)).await
+        }
+    }
+}
+
+/// Commit message style presets
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Preset {
+    Keep,
+    Conventional,
+    Gitmoji,
+    Plain,
+}
+
+impl Preset {
+    const ALL: [Self; 4] = [Self::Keep, Self::Conventional, Self::Gitmoji, Self::Plain];
+}
+
+impl fmt::Display for Preset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Keep => "Keep the current style",
+            Self::Conventional => "Conventional Commits (type(scope): subject)",
+            Self::Gitmoji => "Gitmoji (an emoji before the subject)",
+            Self::Plain => "Plain (no prefix)",
+        })
+    }
+}
+
+/// Choices made in the wizard
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Settings {
+    pub provider: ProviderKind,
+    pub model: String,
+    /// Endpoint params such as `base-url`, for providers without a fixed URL
+    pub endpoint: Vec<(&'static str, String)>,
+    pub preset: Preset,
+}
+
+async fn init() -> Result<()> {
+    if !std::io::stdin().is_terminal() {
+        return Err(anyhow!(
+            "gitai init needs an interactive terminal; set gitai.* with git config instead"
+        ));
+    }
+    let config = Config::load()?;
+
+    let providers = ProviderKind::all().to_vec();
+    let current = config.active_provider();
+    let provider = Select::new("Provider:", providers.clone())
+        .with_starting_cursor(providers.iter().position(|p| *p == current).unwrap_or(0))
+        .prompt()?;
+
+    let endpoint = prompt_endpoint(&config, provider)?;
+    let api_key = prompt_api_key(&config, provider)?;
+    let model = prompt_model(&config, provider, &api_key).await?;
+    let preset = Select::new("Message style:", Preset::ALL.to_vec()).prompt()?;
+
+    let settings = Settings {
+        provider,
+        model,
+        endpoint,
+        preset,
+    };
+    write_settings(&mut GitConfig::open_default()?, &settings)?;
+    output::print_success(&format!(
+        "Saved {}/{} to the global git config",
+        settings.provider, settings.model
+    ));
+
+    offer_hook()?;
+    output::print_info("Run git-doctor to check the setup.");
+    Ok(())
+}
+
+/// Asks for the endpoint settings of providers that are not reached at a fixed URL
+fn prompt_endpoint(config: &Config, provider: ProviderKind) -> Result<Vec<(&'static str, String)>> {
+    let keys: &[(&'static str, &str)] = match provider {
+        ProviderKind::AzureOpenAI => &[
+            (
+                BASE_URL_PARAM,
+                "Resource endpoint (https://<resource>.openai.azure.com):",
+            ),
+            (DEPLOYMENT_PARAM, "Deployment name:"),
+            (API_VERSION_PARAM, "API version:"),
+        ],
+        ProviderKind::OpenAICompatible => {
+            &[(BASE_URL_PARAM, "Base URL (e.g. http://localhost:1234/v1):")]
+        }
+        ProviderKind::Bedrock => &[(REGION_PARAM, "AWS region (e.g. us-east-1):")],
+        ProviderKind::Google | ProviderKind::OpenRouter => return Ok(Vec::new()),
+    };
+    let params = config
+        .get_provider_config(provider.as_str())
+        .map(|p| p.additional_params.clone())
+        .unwrap_or_default();
+
+    let mut endpoint = Vec::new();
+    for (key, message) in keys {
+        let mut prompt = Text::new(message);
+        if let Some(current) = params.get(*key) {
+            prompt = prompt.with_default(current);
+        }
+        endpoint.push((*key, prompt.prompt()?.trim().to_string()));
+    }
+    let entered: HashMap<String, String> = endpoint
+        .iter()
+        .map(|(key, value)| ((*key).to_string(), value.clone()))
+        .collect();
+    validate_endpoint(provider, &entered)?;
+    Ok(endpoint)
+}
+
+/// Keeps the configured key or asks for a new one and stores it
+fn prompt_api_key(config: &Config, provider: ProviderKind) -> Result<String> {
+    let existing = config
+        .get_provider_config(provider.as_str())
+        .map(|p| p.api_key.clone())
+        .unwrap_or_default();
+    if !existing.is_empty()
+        && Confirm::new(&format!("Keep the existing {provider} API key?"))
+            .with_default(true)
+            .prompt()?
+    {
+        return Ok(existing);
+    }
+
+    let api_key = Password::new(&format!("{provider} API key:"))
+        .with_display_mode(PasswordDisplayMode::Masked)
+        .without_confirmation()
+        .prompt()?
+        .trim()
+        .to_string();
+    if api_key.is_empty() {
+        if !provider.requires_api_key() {
+            return Ok(api_key);
+        }
+        return Err(anyhow!("An API key is required for {provider}"));
+    }
+    let path = Config::store_api_key(provider.as_str(), &api_key)?;
+    output::print_info(&format!("Stored the API key in {}", path.display()));
+    Ok(api_key)
+}
+
+/// Picks from the provider's live model list, or asks for a name when it cannot be fetched
+async fn prompt_model(config: &Config, provider: ProviderKind, api_key: &str) -> Result<String> {
+    let current = config
+        .get_provider_config(provider.as_str())
+        .map(|p| p.model_name.clone())
+        .filter(|m| !m.is_empty())
+        .unwrap_or_else(|| provider.default_model().to_string());
+    if !provider.has_model_api() {
+        return Ok(Text::new("Model:").with_default(&current).prompt()?);
+    }
+
+    let models = model_info::list_models(provider, api_key)
+        .await
+        .map(|models| models.into_iter().map(|m| m.id).collect::<Vec<_>>());
+    match models {
+        Ok(models) if !models.is_empty() => {
+            let cursor = models.iter().position(|m| *m == current).unwrap_or(0);
+            Ok(Select::new("Model:", models)
+                .with_starting_cursor(cursor)
+                .with_help_message("type to filter")
+                .prompt()?)
+        }
+        Ok(_) => Ok(Text::new("Model:").with_default(&current).prompt()?),
+        Err(e) => {
+            output::print_warning(&format!("Could not fetch the model list: {e:#}"));
+            Ok(Text::new("Model:").with_default(&current).prompt()?)
+        }
+    }
+}
+
+/// Offers the `prepare-commit-msg` hook when run inside a repository
+fn offer_hook() -> Result<()> {
+    let Ok(repo) = git2::Repository::discover(".") else {
+        return Ok(());
+    };
+    if message_hook_installed(&repo) {
+        output::print_info("The prepare-commit-msg hook is already installed.");
+        return Ok(());
+    }
+    let install =
+        Confirm::new("Install a prepare-commit-msg hook so `git commit` drafts the message?")
+            .with_default(false)
+            .prompt()?;
+    if install {
+        match install_message_hook(&repo) {
+            Ok(path) => output::print_success(&format!("Installed {}", path.display())),
+            Err(e) => output::print_warning(&format!("Skipped the hook: {e}")),
+        }
+    }
+    Ok(())
+}
+
+/// Writes the chosen provider, model, endpoint and style preset to a git config
+pub fn write_settings(config: &mut GitConfig, settings: &Settings) -> Result<()> {
+    let provider = settings.provider.as_str();
+    config.set_str("gitai.provider", provider)?;
+    config.set_str(&format!("gitai.{provider}-model"), &settings.model)?;
+    for (key, value) in &settings.endpoint {
+        config.set_str(&format!("gitai.{provider}-additional{key}"), value)?;
+    }
+
+    let conventional = config
+        .get_string("gitai.instructions")
+        .is_ok_and(|value| value == CONVENTIONAL_INSTRUCTIONS);
+    match settings.preset {
+        Preset::Keep => {}
+        Preset::Conventional => {
+            config.set_str("gitai.instructions", CONVENTIONAL_INSTRUCTIONS)?;
+            config.set_bool("gitai.gitmoji", false)?;
+        }
+        Preset::Gitmoji => config.set_bool("gitai.gitmoji", true)?,
+        Preset::Plain => {
+            // Instructions the user wrote themselves are left alone
+            if conventional {
+                config.remove("gitai.instructions")?;
+            }
+            config.set_bool("gitai.gitmoji", false)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_settings() {
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let path = dir.path().join("gitconfig");
+        std::fs::write(&path, "").expect("Failed to write config");
+        let mut config = GitConfig::open(&path).expect("Failed to open config");
+
+        let mut settings = Settings {
+            provider: ProviderKind::OpenRouter,
+            model: "anthropic/claude-3.5-sonnet".to_string(),
+            endpoint: vec![(
+                BASE_URL_PARAM,
+                "https://proxy.example.com/api/v1".to_string(),
+            )],
+            preset: Preset::Conventional,
+        };
+        write_settings(&mut config, &settings).expect("Failed to write settings");
+        let read = |key: &str| {
+            GitConfig::open(&path)
+                .expect("Failed to open config")
+                .get_string(key)
+                .ok()
+        };
+        assert_eq!(read("gitai.provider").as_deref(), Some("openrouter"));
+        assert_eq!(
+            read("gitai.openrouter-model").as_deref(),
+            Some("anthropic/claude-3.5-sonnet")
+        );
+        assert_eq!(
+            read("gitai.openrouter-additionalbase-url").as_deref(),
+            Some("https://proxy.example.com/api/v1")
+        );
+        assert_eq!(
+            read("gitai.instructions").as_deref(),
+            Some(CONVENTIONAL_INSTRUCTIONS)
+        );
+
+        settings.preset = Preset::Plain;
+        write_settings(&mut config, &settings).expect("Failed to write settings");
+        assert_eq!(read("gitai.instructions"), None);
+        assert_eq!(read("gitai.gitmoji").as_deref(), Some("false"));
+    }
+}
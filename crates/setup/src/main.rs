@@ -0,0 +1,41 @@
+use anyhow::Result;
+use clap::Parser;
+use cloy::{error, init_app};
+use setup::{SetupArgs, handle_setup};
+
+#[derive(Parser)]
+#[command(
+    name = "gitai",
+    version,
+    author,
+    about = "Set up gitai and inspect providers",
+    styles = cloy::app::args::get_styles(),
+)]
+struct SetupCli {
+    #[command(flatten)]
+    args: SetupArgs,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    init_app();
+
+    let cli = SetupCli::parse();
+
+    if let Err(e) = handle_setup(&cli.args).await {
+        std::process::exit(error::report(&e));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn verify_cli() {
+        SetupCli::command().debug_assert();
+    }
+}
@@ -0,0 +1,51 @@
+//! Upgrading gitai settings written in an older layout
+
+use anyhow::Result;
+use cloy::config_migration::{self, CURRENT_VERSION};
+use cloy::output;
+use git2::{Config as GitConfig, Repository};
+use std::path::Path;
+
+/// Migrates `file`, or the global and repository config files, to the current layout
+pub fn handle_migrate(file: Option<&Path>, dry_run: bool) -> Result<()> {
+    let files = if let Some(path) = file {
+        vec![(
+            format!("config file {}", path.display()),
+            GitConfig::open(path)?,
+        )]
+    } else {
+        let repo = Repository::open_from_env().ok();
+        config_migration::config_files(repo.as_ref())
+            .into_iter()
+            .map(|(name, config)| (name.to_string(), config))
+            .collect()
+    };
+
+    for (name, mut config) in files {
+        let entries = config_migration::entries(&config)?;
+        if entries.is_empty() {
+            continue;
+        }
+        let plan = config_migration::plan(&entries);
+        if plan.is_current() {
+            output::print_info(&format!("The {name} is at version {}", plan.from));
+            continue;
+        }
+        output::print_info(&format!(
+            "The {name} is at version {}, migrating to {CURRENT_VERSION}",
+            plan.from
+        ));
+        for (description, changes) in &plan.steps {
+            println!("  {description}");
+            for change in changes {
+                println!("    {change}");
+            }
+        }
+        if dry_run {
+            continue;
+        }
+        config_migration::apply(&mut config, &plan)?;
+        output::print_success(&format!("Migrated the {name}"));
+    }
+    Ok(())
+}
@@ -0,0 +1,141 @@
+//! Model listing from the providers' APIs
+
+use anyhow::{Result, anyhow};
+use cloy::config::Config;
+use cloy::llm::model_info::{self, ModelListing};
+use cloy::llm::provider::ProviderKind;
+use cloy::output;
+use std::fmt::Write;
+
+/// Lists the models of one provider, or of every provider with an API key
+/// and a model API, and warns when the configured model is missing from the list or retiring.
+pub async fn handle_models(provider: Option<ProviderKind>) -> Result<()> {
+    let config = Config::load()?;
+    let api_key = |provider: ProviderKind| {
+        config
+            .get_provider_config(provider.as_str())
+            .map(|p| p.api_key.clone())
+            .unwrap_or_default()
+    };
+    let providers: Vec<ProviderKind> = match provider {
+        Some(provider) => vec![provider],
+        None => ProviderKind::all()
+            .iter()
+            .copied()
+            .filter(|p| p.has_model_api() && !api_key(*p).is_empty())
+            .collect(),
+    };
+    if providers.is_empty() {
+        return Err(anyhow!(
+            "No provider has an API key configured; run `gitai init` or pass --provider"
+        ));
+    }
+
+    let mut failed = 0;
+    for provider in providers {
+        let configured = config
+            .get_provider_config(provider.as_str())
+            .map(|p| p.model_name.clone())
+            .filter(|m| !m.is_empty())
+            .unwrap_or_else(|| provider.default_model().to_string());
+        match model_info::list_models(provider, &api_key(provider)).await {
+            Ok(models) => {
+                print!("{}", format_models(provider, &models, &configured));
+                if let Some(warning) = configured_model_warning(provider, &models, &configured) {
+                    output::print_warning(&warning);
+                }
+            }
+            Err(e) => {
+                output::print_error(&format!("Failed to list {provider} models: {e:#}"));
+                failed += 1;
+            }
+        }
+    }
+    if failed > 0 {
+        return Err(anyhow!("Could not list the models of {failed} provider(s)"));
+    }
+    Ok(())
+}
+
+/// Renders one provider's models; the configured one is marked with `*`
+fn format_models(provider: ProviderKind, models: &[ModelListing], configured: &str) -> String {
+    let width = models.iter().map(|m| m.id.len()).max().unwrap_or(0);
+    let mut out = format!("== {provider} ({} models) ==\n", models.len());
+    for model in models {
+        let context = model
+            .context_length
+            .map_or_else(|| "n/a".to_string(), |c| format!("{c}"));
+        let line = format!(
+            "{} {:<width$}  {:>9}  {}",
+            if model.id == configured { '*' } else { ' ' },
+            model.id,
+            context,
+            model.deprecation.as_deref().unwrap_or_default()
+        );
+        let _ = writeln!(out, "{}", line.trim_end());
+    }
+    out
+}
+
+/// Warning about the configured model, if it is unknown to the provider or retiring
+fn configured_model_warning(
+    provider: ProviderKind,
+    models: &[ModelListing],
+    configured: &str,
+) -> Option<String> {
+    match models.iter().find(|m| m.id == configured) {
+        None => Some(format!(
+            "The configured model {configured} is not offered by {provider}; \
+             set another with `git config --global gitai.{provider}-model <model>`"
+        )),
+        Some(ModelListing {
+            deprecation: Some(deprecation),
+            ..
+        }) => Some(format!(
+            "The configured model {configured} is {deprecation}; consider switching models"
+        )),
+        Some(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn listing(id: &str, context_length: Option<usize>, deprecation: Option<&str>) -> ModelListing {
+        ModelListing {
+            id: id.to_string(),
+            context_length,
+            deprecation: deprecation.map(ToString::to_string),
+        }
+    }
+
+    #[test]
+    fn test_format_and_validate_models() {
+        let models = vec![
+            listing("gemini-1.5-pro", Some(2_000_000), Some("deprecated")),
+            listing("gemini-2.5-flash", Some(1_048_576), None),
+            listing("gemma", None, None),
+        ];
+        assert_eq!(
+            format_models(ProviderKind::Google, &models, "gemini-2.5-flash"),
+            "== google (3 models) ==\n  \
+             gemini-1.5-pro      2000000  deprecated\n\
+             * gemini-2.5-flash    1048576\n  \
+             gemma                   n/a\n"
+        );
+
+        assert_eq!(
+            configured_model_warning(ProviderKind::Google, &models, "gemini-2.5-flash"),
+            None
+        );
+        assert!(
+            configured_model_warning(ProviderKind::Google, &models, "gemini-1.5-pro")
+                .is_some_and(|w| w.contains("is deprecated"))
+        );
+        assert!(
+            configured_model_warning(ProviderKind::Google, &models, "gemini-9")
+                .is_some_and(|w| w.contains("not offered by google"))
+        );
+    }
+}
@@ -0,0 +1,93 @@
+//! Acceptance rates from the local usage stats
+
+use anyhow::{Result, anyhow};
+use cloy::config::Config;
+use cloy::output;
+use cloy::usage::{UsageStats, stats_path};
+use std::fmt::Write;
+
+/// Prints the counts recorded under `gitai.usage-stats`, or clears them
+pub fn handle_usage(reset: bool) -> Result<()> {
+    let path = stats_path().ok_or_else(|| anyhow!("No data directory on this platform"))?;
+    if reset {
+        if path.exists() {
+            std::fs::remove_file(&path)?;
+        }
+        output::print_success(&format!("Removed {}", path.display()));
+        return Ok(());
+    }
+
+    if Config::load()?.usage_stats != Some(true) {
+        output::print_info(
+            "Usage stats are off; turn them on with `git config --global gitai.usage-stats true`",
+        );
+    }
+    let stats = UsageStats::load(&path)?;
+    if stats.providers.is_empty() {
+        output::print_info("No commit sessions recorded yet.");
+        return Ok(());
+    }
+    print!("{}", format_stats(&stats));
+    Ok(())
+}
+
+/// One row per provider and preset: session outcomes and the acceptance rate
+fn format_stats(stats: &UsageStats) -> String {
+    let mut out = String::new();
+    if let Some(since) = stats.since {
+        let _ = writeln!(out, "Since {}", since.format("%Y-%m-%d"));
+    }
+    let _ = writeln!(
+        out,
+        "{:<20} {:<12} {:>8} {:>8} {:>8} {:>11} {:>9} {:>9}",
+        "provider",
+        "preset",
+        "sessions",
+        "accepted",
+        "edited",
+        "regenerated",
+        "cancelled",
+        "accept %"
+    );
+    for (provider, presets) in &stats.providers {
+        for (preset, counters) in presets {
+            let rate = counters
+                .acceptance_rate()
+                .map_or_else(|| "n/a".to_string(), |rate| format!("{:.0}%", rate * 100.0));
+            let _ = writeln!(
+                out,
+                "{provider:<20} {preset:<12} {:>8} {:>8} {:>8} {:>11} {:>9} {rate:>9}",
+                counters.sessions(),
+                counters.accepted,
+                counters.edited,
+                counters.regenerated,
+                counters.cancelled,
+            );
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cloy::usage::Outcome;
+
+    #[test]
+    fn test_format_stats() {
+        let mut stats = UsageStats::default();
+        stats.record("google", "conventional", Outcome::Accepted, 0);
+        stats.record("google", "conventional", Outcome::Edited, 1);
+        stats.record("google", "conventional", Outcome::Accepted, 0);
+        stats.record("google", "conventional", Outcome::Cancelled, 2);
+        let table = format_stats(&stats);
+        let row = table
+            .lines()
+            .find(|line| line.starts_with("google"))
+            .expect("Row for google");
+        assert_eq!(
+            row.split_whitespace().collect::<Vec<_>>(),
+            ["google", "conventional", "4", "2", "1", "0", "1", "67%"]
+        );
+    }
+}
@@ -1,6 +1,7 @@
 use anyhow::Result;
 use clap::Parser;
-use cloy::{init_app, output::print_error};
+use cloy::app::defaults;
+use cloy::{error, init_app};
 use wire::sync_wire::{WireArgs, handle_wire};
 
 #[derive(Parser)]
@@ -20,11 +21,10 @@ struct WireCli {
 async fn main() -> Result<()> {
     init_app();
 
-    let cli = WireCli::parse();
+    let cli = defaults::parse::<WireCli>("wire");
 
     if let Err(e) = handle_wire(cli.args).await {
-        print_error(&format!("Error: {e}"));
-        std::process::exit(1);
+        std::process::exit(error::report(&e));
     }
 
     Ok(())
@@ -4,7 +4,7 @@ use std::process::Command;
 
 use cause::Cause;
 use cause::cause;
-use git2::Repository;
+use git2::build::RepoBuilder;
 use regex::Regex;
 use temp_dir::TempDir;
 
@@ -41,7 +41,17 @@ fn git_clone(prefix: &str, path: &Path, parsed: &Parsed) -> Result<(), Cause<Err
 
     std::env::set_current_dir(path).map_err(|e| cause!(GitCloneCommand).src(e))?;
 
-    Repository::clone(&parsed.url, ".").map_err(|e| cause!(GitCloneCommand).src(e))?;
+    // Reach the remote as the git CLI would, then keep its URL as given so
+    // the `git` commands that follow resolve it the same way
+    let (url, fetch) = cloy::git::fetch_options(&parsed.url);
+    let repo = RepoBuilder::new()
+        .fetch_options(fetch)
+        .clone(&url, Path::new("."))
+        .map_err(|e| cause!(GitCloneCommand).src(e))?;
+    if url != parsed.url {
+        repo.remote_set_url("origin", &parsed.url)
+            .map_err(|e| cause!(GitCloneCommand).src(e))?;
+    }
 
     Ok(())
 }
@@ -3,6 +3,7 @@ pub mod parse;
 pub mod sequence;
 
 use clap::ValueEnum;
+use cloy::error::ErrorCode;
 use serde::{Deserialize, Serialize};
 use std::ffi::OsStr;
 use std::path::{Component, Path};
@@ -37,6 +38,41 @@ pub enum ErrorType {
     PromptError,
 }
 
+impl ErrorType {
+    /// The user-facing code of the failure
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            Self::RepositoryRootPathCommand | Self::RepositoryRootPathParse => {
+                ErrorCode::NotARepository
+            }
+            Self::DotGitWireFileOpen
+            | Self::DotGitWireFileParse
+            | Self::DotGitWireFileSoundness
+            | Self::DotGitWireFileNameNotUnique
+            | Self::NoItemToOperate => ErrorCode::WireConfig,
+            Self::GitCloneCommand
+            | Self::GitCloneCommandExitStatus
+            | Self::GitCheckoutCommand
+            | Self::GitCheckoutCommandExitStatus
+            | Self::GitFetchCommand
+            | Self::GitFetchCommandExitStatus
+            | Self::GitLsRemoteCommand
+            | Self::GitLsRemoteCommandExitStatus
+            | Self::GitLsRemoteCommandStdoutDecode
+            | Self::GitLsRemoteCommandStdoutRegex => ErrorCode::Remote,
+            Self::CurrentDirRetrieve
+            | Self::CurrentDirConvert
+            | Self::DotGitWireFileWrite
+            | Self::TempDirCreation => ErrorCode::Io,
+            Self::GitCheckoutChangeDirectory
+            | Self::MoveFromTempToDest
+            | Self::CheckDifferenceExecution
+            | Self::CheckDifferenceStringReplace
+            | Self::PromptError => ErrorCode::WireSync,
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ValueEnum)]
 pub enum Method {
     Shallow,
@@ -6,6 +6,7 @@ use crate::sync::{
 
 use anyhow::Result;
 use clap::{Args, Subcommand};
+use cloy::error::{ErrorCode, coded};
 use colored::Colorize;
 
 #[derive(Args, Clone, Debug)]
@@ -129,9 +130,9 @@ pub async fn handle_wire(args: WireArgs) -> Result<()> {
         }
         Ok(false) => {
             println!("{}", "Failure".red().bold());
-            Err(anyhow::anyhow!("Wire operation failed"))
+            Err(coded(ErrorCode::WireDiffers, "Wire operation failed"))
         }
-        Err(e) => Err(anyhow::anyhow!("{e}")),
+        Err(e) => Err(coded(e.cause().code(), e)),
     }
 }
 
@@ -147,7 +148,7 @@ fn build_target_config(
     if let Some(ref parsed) = cli_override {
         parsed
             .validate()
-            .map_err(|e| anyhow::anyhow!("Invalid arguments: {e}"))?;
+            .map_err(|e| coded(ErrorCode::WireConfig, format!("Invalid arguments: {e}")))?;
     }
 
     Ok(TargetConfig {